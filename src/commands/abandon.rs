@@ -0,0 +1,292 @@
+use crate::commands::{ExportOptions, export};
+use crate::gh::{
+    client::{self, Client},
+    mutations,
+};
+use crate::git::{backup, commit_ref, notes, refs, slots};
+use crate::model::{Config, PrState, PushHooks};
+use crate::stack::{discover_stack, ensure_online};
+use crate::workflows::autostash;
+use anyhow::{Context, Result, bail};
+use console::style;
+use git2::Repository;
+use std::path::Path;
+use std::process::Command;
+
+/// Drop a commit from the stack and clean up everything attached to it
+///
+/// The commit is rebased out, its note and slot are released, and the stack is
+/// re-exported. Only then is the PR closed with a comment and the slot branch
+/// deleted: GitHub closes PRs whose base branch disappears, so the PR above has
+/// to be retargeted first.
+pub async fn abandon(
+    commit_ref: &str,
+    autostash: bool,
+    skip_confirm: bool,
+    fetch: Option<bool>,
+) -> Result<()> {
+    let git_repo = Repository::open(".").context("Failed to open git repository")?;
+    let mut config = Config::load(&git_repo)?;
+    config.fetch = fetch.unwrap_or(config.fetch);
+    config.autostash |= autostash;
+    let gh_client = client::create_configured_client(&config)?;
+
+    let stash = autostash::stash_if_dirty(&git_repo, &config, "abandoning a commit")?;
+    let result = abandon_commit(&git_repo, &config, &gh_client, commit_ref, skip_confirm).await;
+    autostash::restore(&git_repo, stash, result.is_ok());
+    result
+}
+
+async fn abandon_commit(
+    git_repo: &Repository,
+    config: &Config,
+    gh_client: &Client,
+    commit_ref: &str,
+    skip_confirm: bool,
+) -> Result<()> {
+    let stack = discover_stack(git_repo, config, gh_client).await?;
+    ensure_online(&stack, config, "abandon")?;
+    let current_branch = stack.current_branch.clone();
+
+    let commit_oid = commit_ref::resolve_commit_ref(git_repo, &stack, commit_ref)?;
+    let entry = stack
+        .entries
+        .iter()
+        .find(|e| e.oid == commit_oid)
+        .with_context(|| format!("Commit {} is not part of the current stack", commit_ref))?
+        .clone();
+
+    let open_pr = entry.pr_number.filter(|_| {
+        matches!(
+            entry.pr_state,
+            Some(PrState::Open) | Some(PrState::Draft) | None
+        )
+    });
+    if entry.pr_state == Some(PrState::Merged) {
+        bail!(
+            "PR #{} is already merged. Use 'git stk landed' to clean up after it.",
+            entry.pr_number.unwrap_or_default()
+        );
+    }
+    let head_ref = entry
+        .slot
+        .as_deref()
+        .map(|slot| slots::generate_head_ref(&current_branch, slot));
+
+    println!(
+        "🗑️  Abandoning {}. {} {}",
+        entry.index,
+        style(&entry.short_sha).yellow(),
+        entry.subject
+    );
+    if let Some(pr_number) = open_pr {
+        println!("  {} Close PR #{}", style("→").dim(), pr_number);
+    }
+    if let Some(head_ref) = head_ref.as_ref().filter(|_| entry.remote_branch_exists) {
+        println!(
+            "  {} Delete {}/{}",
+            style("→").dim(),
+            config.remote,
+            head_ref
+        );
+    }
+    if entry.index < stack.len() {
+        let above = stack.len() - entry.index;
+        println!(
+            "  {} Rebase {} commit{} above it",
+            style("→").dim(),
+            above,
+            if above == 1 { "" } else { "s" }
+        );
+    }
+
+    let confirmed = if skip_confirm {
+        true
+    } else {
+        use dialoguer::Confirm;
+        Confirm::new()
+            .with_prompt(format!("Abandon {}?", entry.short_sha))
+            .default(false)
+            .interact()?
+    };
+
+    if !confirmed {
+        println!("\n{}", style("✗ Operation cancelled").red());
+        return Ok(());
+    }
+
+    println!();
+
+    // Snapshot the stack so the rebase can be rolled back with `git stk restore`
+    let snapshot = backup::create_snapshot(
+        git_repo,
+        &config.notes_ref,
+        "abandon",
+        config.keep_snapshots,
+    )?;
+    println!(
+        "💾 Saved snapshot {} ({})",
+        style(snapshot.id).cyan(),
+        style(format!("{:.7}", snapshot.head)).yellow()
+    );
+
+    let repo_path = git_repo
+        .workdir()
+        .context("Repository has no working directory")?;
+    println!("🔄 Removing {} from the stack...", entry.short_sha);
+    if config.jj {
+        jj_abandon(repo_path, commit_oid, snapshot.id)?;
+    } else {
+        git_drop(repo_path, config, commit_oid, snapshot.id)?;
+    }
+    println!("  ✓ Removed");
+
+    if let Err(e) = notes::remove_note(git_repo, commit_oid, &config.notes_ref)
+        && !e.to_string().contains("not found")
+    {
+        eprintln!("  Warning: Failed to remove note: {}", e);
+    }
+    if let Some(slot) = &entry.slot {
+        let mut slot_cache = slots::SlotCache::load(git_repo)?;
+        slot_cache.release_slot(&current_branch, slot);
+        slot_cache.save(git_repo)?;
+    }
+
+    if entry.remote_branch_exists || stack.entries.iter().any(|e| e.pr_number.is_some()) {
+        // Retarget the PRs above before their base branch goes away
+        println!("\n📤 Re-exporting stack...\n");
+        if let Err(e) = export::export(ExportOptions::default()).await {
+            eprintln!(
+                "\n{} Failed to re-export stack after abandoning:",
+                style("✗").red()
+            );
+            eprintln!("  {}", e);
+            if let Some(pr_number) = open_pr {
+                eprintln!(
+                    "\n{} PR #{} is still open: close it once 'git stk export' succeeds.",
+                    style("💡").yellow(),
+                    pr_number
+                );
+            }
+            if let Some(head_ref) = head_ref.filter(|_| entry.remote_branch_exists) {
+                eprintln!(
+                    "\n{} Once 'git stk export' succeeds, delete the old branch with: git push {} :{}",
+                    style("💡").yellow(),
+                    config.remote,
+                    head_ref
+                );
+            }
+            return Err(e);
+        }
+    }
+
+    // Only closed once the commit is gone and the stack re-exported, so a failed
+    // rebase or export leaves the PR as it was
+    if let Some(pr_number) = open_pr {
+        let owner = entry.repo_owner.clone().unwrap_or_default();
+        let repo_name = entry.repo_name.clone().unwrap_or_default();
+
+        println!("\n🔒 Closing PR #{}...", pr_number);
+        let comment = format!(
+            "This PR is being closed because its commit (`{}`) was abandoned and removed from the `{}` stack.",
+            entry.short_sha, current_branch
+        );
+        if let Err(e) =
+            mutations::add_pr_comment(gh_client, &owner, &repo_name, pr_number, &comment).await
+        {
+            eprintln!("  Warning: Failed to add comment to PR: {}", e);
+        }
+        match mutations::close_pull_request(gh_client, &owner, &repo_name, pr_number).await {
+            Ok(_) => println!("  ✓ PR #{} closed", pr_number),
+            Err(e) => {
+                eprintln!("  Warning: Failed to close PR: {}", e);
+                eprintln!("  You may need to close it manually.");
+            }
+        }
+    }
+
+    if let Some(head_ref) = head_ref.filter(|_| entry.remote_branch_exists) {
+        println!("\n🗑️  Deleting {}...", head_ref);
+        let mut capabilities = refs::RemoteCapabilities::load(git_repo, &config.remote);
+        let result = refs::push_refs(
+            git_repo,
+            &config.remote,
+            &[format!(":refs/heads/{}", head_ref)],
+            &mut capabilities,
+            config.push_hooks != PushHooks::Each,
+            config.max_refs_per_push,
+            &config.transport,
+            &mut |_| {},
+        )?;
+        match result.error {
+            None => println!("  ✓ Deleted"),
+            Some(e) => eprintln!("  Warning: Failed to delete branch:\n{}", e.trim_end()),
+        }
+    }
+
+    println!(
+        "\n{} Abandoned {}",
+        style("✨").green(),
+        style(&entry.short_sha).yellow()
+    );
+
+    Ok(())
+}
+
+/// Rebase the commits above `oid` onto its parent with git
+fn git_drop(repo_path: &Path, config: &Config, oid: git2::Oid, snapshot_id: i64) -> Result<()> {
+    let mut rebase = Command::new("git");
+    rebase.current_dir(repo_path).arg("rebase");
+    if config.gpg_sign {
+        rebase.arg("--gpg-sign");
+    }
+    if config.signoff {
+        rebase.arg("--signoff");
+    }
+
+    let output = rebase
+        .arg("--onto")
+        .arg(format!("{}^", oid))
+        .arg(oid.to_string())
+        .output()
+        .context("Failed to rebase")?;
+
+    if !output.status.success() {
+        eprintln!("{} Rebase failed!", style("✗").red());
+        eprintln!("\n{}", String::from_utf8_lossy(&output.stderr));
+        eprintln!(
+            "\n{} The commits above depend on the abandoned one.",
+            style("💡").yellow()
+        );
+        eprintln!("  Resolve conflicts and run 'git rebase --continue', then 'git stk export',");
+        eprintln!(
+            "  or roll back with: git rebase --abort && git stk restore {}",
+            snapshot_id
+        );
+        return Err(anyhow::anyhow!("Rebase failed - see guidance above"));
+    }
+
+    Ok(())
+}
+
+/// Abandon the change with jj, which rebases its descendants and moves the bookmark
+fn jj_abandon(repo_path: &Path, oid: git2::Oid, snapshot_id: i64) -> Result<()> {
+    let output = Command::new("jj")
+        .current_dir(repo_path)
+        .args(["abandon", &oid.to_string()])
+        .output()
+        .context("Failed to execute jj abandon. Is jj installed?")?;
+
+    if !output.status.success() {
+        eprintln!("{} jj abandon failed!", style("✗").red());
+        eprintln!("\n{}", String::from_utf8_lossy(&output.stderr));
+        eprintln!(
+            "\n{} Roll back with 'git stk restore {}'.",
+            style("💡").yellow(),
+            snapshot_id
+        );
+        return Err(anyhow::anyhow!("Abandon failed - see guidance above"));
+    }
+
+    Ok(())
+}
@@ -0,0 +1,215 @@
+use crate::commands::{ExportOptions, export};
+use crate::gh::{client, mutations, queries};
+use crate::git::{notes, repo, slots};
+use crate::model::{CommitMetadata, Config, PrState};
+use crate::stack::{discover_local_stack, discover_stack, ensure_online, is_slot_taken_remotely};
+use anyhow::{Context, Result, bail};
+use console::style;
+use git2::{Oid, Repository};
+use std::collections::HashMap;
+
+/// Convert a classic feature branch into a stack: one slot and PR per commit
+///
+/// Each commit gets a slot, then the stack is exported. An open PR from the
+/// branch itself is closed once the stack's PRs exist, with a comment listing
+/// them: export only pushes slot branches, so it couldn't keep that PR's head
+/// (the branch) up to date.
+pub async fn adopt_branch(branch: &str, skip_confirm: bool, fetch: Option<bool>) -> Result<()> {
+    let git_repo = Repository::open(".").context("Failed to open git repository")?;
+    let mut config = Config::load(&git_repo)?;
+    config.fetch = fetch.unwrap_or(config.fetch);
+    let gh_client = client::create_configured_client(&config)?;
+
+    if repo::is_detached(&git_repo)? || repo::current_branch(&git_repo)? != branch {
+        if repo::has_uncommitted_changes(&git_repo)? {
+            bail!(
+                "You have uncommitted changes. Commit or stash them before adopting '{}'.",
+                branch
+            );
+        }
+        repo::checkout(&git_repo, branch, false)?;
+        println!("Switched to {}", style(branch).cyan());
+    }
+
+    println!("🔍 Discovering stack...\n");
+    let stack = discover_stack(&git_repo, &config, &gh_client).await?;
+    if stack.is_empty() {
+        bail!("'{}' has no commits on top of {}", branch, config.base);
+    }
+    ensure_online(&stack, &config, "adopt the branch")?;
+    if stack.entries.iter().any(|e| e.slot.is_some()) {
+        bail!(
+            "'{}' is already a stack. Run 'git stk export' to update it.",
+            branch
+        );
+    }
+
+    let owner = stack.entries[0]
+        .repo_owner
+        .clone()
+        .context("Missing repo owner")?;
+    let repo_name = stack.entries[0]
+        .repo_name
+        .clone()
+        .context("Missing repo name")?;
+    let original = queries::find_pr_by_head(&gh_client, &owner, &repo_name, branch)
+        .await?
+        .filter(|pr| matches!(pr.state, PrState::Open | PrState::Draft));
+
+    // Allocate slots as export would, around slot branches pushed from elsewhere
+    let mut slot_cache = slots::SlotCache::load(&git_repo)?;
+    let oids: Vec<Oid> = stack.entries.iter().map(|e| e.oid).collect();
+    let slots = assign_slots(&stack.remote_refs, branch, &oids, &mut slot_cache);
+    let assignments: Vec<_> = stack.entries.iter().zip(slots).collect();
+
+    println!(
+        "  {} {} commit{} of {} become a stack:",
+        style("→").dim(),
+        stack.len(),
+        if stack.len() == 1 { "" } else { "s" },
+        style(branch).cyan()
+    );
+    for (entry, slot) in &assignments {
+        println!(
+            "  {} {}  {}  {}",
+            style(format!("{:>2}.", entry.index)).dim(),
+            style(&entry.short_sha).yellow(),
+            style(slots::generate_head_ref(branch, slot)).cyan(),
+            entry.subject
+        );
+    }
+    if let Some(pr) = &original {
+        println!(
+            "  {} PR #{} will be closed in favor of the new PRs",
+            style("→").dim(),
+            pr.number
+        );
+    }
+
+    let confirmed = if skip_confirm {
+        true
+    } else {
+        use dialoguer::Confirm;
+        Confirm::new()
+            .with_prompt(format!("Adopt {} as a stack and export it?", branch))
+            .default(false)
+            .interact()?
+    };
+    if !confirmed {
+        println!("\n{}", style("✗ Operation cancelled").red());
+        return Ok(());
+    }
+
+    for (entry, slot) in &assignments {
+        let metadata = CommitMetadata {
+            pr: None,
+            slot: slot.clone(),
+            annotation: entry.annotation.clone(),
+            last_export: None,
+            owner: entry.owner.clone(),
+            signature: None,
+        };
+        notes::write_note(
+            &git_repo,
+            entry.oid,
+            &metadata,
+            &config.notes_ref,
+            config.sign_notes,
+        )
+        .context("Failed to write note to commit")?;
+    }
+    slot_cache.save(&git_repo)?;
+
+    println!("\n📤 Exporting the stack...\n");
+    export::export(ExportOptions::default()).await?;
+
+    if let Some(pr) = original {
+        let numbers: Vec<u64> = discover_local_stack(&git_repo, &config)?
+            .entries
+            .iter()
+            .filter_map(|e| e.pr_number)
+            .collect();
+        let comment = replacement_comment(&numbers);
+        if let Err(e) =
+            mutations::add_pr_comment(&gh_client, &owner, &repo_name, pr.number, &comment).await
+        {
+            eprintln!(
+                "  Warning: Failed to add comment to PR #{}: {}",
+                pr.number, e
+            );
+        }
+        match mutations::close_pull_request(&gh_client, &owner, &repo_name, pr.number).await {
+            Ok(_) => println!("🔒 Closed PR #{}", pr.number),
+            Err(e) => eprintln!("  Warning: Failed to close PR #{}: {}", pr.number, e),
+        }
+    }
+
+    Ok(())
+}
+
+/// A slot for each of `oids` on `branch`, skipping slots whose branch on the
+/// remote holds another commit
+fn assign_slots(
+    remote_refs: &HashMap<String, Oid>,
+    branch: &str,
+    oids: &[Oid],
+    slot_cache: &mut slots::SlotCache,
+) -> Vec<String> {
+    oids.iter()
+        .map(|&oid| {
+            let (slot, _) = slot_cache.allocate_skipping(branch, |slot| {
+                is_slot_taken_remotely(remote_refs, branch, slot, oid)
+            });
+            slot
+        })
+        .collect()
+}
+
+/// Comment closing the branch's original PR, pointing at the stack's PRs
+fn replacement_comment(numbers: &[u64]) -> String {
+    let list: Vec<String> = numbers
+        .iter()
+        .map(|number| format!("#{}", number))
+        .collect();
+    format!(
+        "This branch was split into a stack of {} PRs, one per commit: {}. \
+         Review continues there.",
+        numbers.len(),
+        list.join(", ")
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn oid(hex: char) -> Oid {
+        Oid::from_str(&hex.to_string().repeat(40)).unwrap()
+    }
+
+    #[test]
+    fn test_assign_slots_skips_taken_remote_slots() {
+        // Slot 01 was pushed from another clone and holds a commit that isn't ours
+        let remote_refs = HashMap::from([("feat--01".to_string(), oid('3'))]);
+        let mut slot_cache = slots::SlotCache::default();
+        let slots = assign_slots(&remote_refs, "feat", &[oid('1'), oid('2')], &mut slot_cache);
+        assert_eq!(slots, vec!["02", "03"]);
+        assert!(!slot_cache.is_slot_available("feat", "03"));
+    }
+
+    #[test]
+    fn test_assign_slots_reuses_slot_holding_the_same_commit() {
+        let remote_refs = HashMap::from([("feat--01".to_string(), oid('1'))]);
+        let mut slot_cache = slots::SlotCache::default();
+        let slots = assign_slots(&remote_refs, "feat", &[oid('1')], &mut slot_cache);
+        assert_eq!(slots, vec!["01"]);
+    }
+
+    #[test]
+    fn test_replacement_comment() {
+        assert_eq!(
+            replacement_comment(&[12, 13]),
+            "This branch was split into a stack of 2 PRs, one per commit: #12, #13. Review continues there."
+        );
+    }
+}
@@ -0,0 +1,89 @@
+use crate::gh::client;
+use crate::git::{commit_ref, notes, slots};
+use crate::model::{CommitMetadata, Config};
+use crate::stack::discover_stack;
+use crate::workflows;
+use anyhow::{Context, Result, bail};
+use console::style;
+use git2::Repository;
+
+/// Attach (or clear) a free-form annotation on a commit
+pub async fn annotate(
+    commit_ref: &str,
+    text: Option<&str>,
+    clear: bool,
+    fetch: Option<bool>,
+) -> Result<()> {
+    let git_repo = Repository::open(".").context("Failed to open git repository")?;
+    let mut config = Config::load(&git_repo)?;
+    config.fetch = fetch.unwrap_or(config.fetch);
+    let gh_client = client::create_configured_client(&config)?;
+
+    let annotation = match (text.map(str::trim), clear) {
+        (_, true) => None,
+        (Some(t), false) if !t.is_empty() => Some(t.to_string()),
+        _ => bail!("Annotation text cannot be empty. Use --clear to remove an annotation."),
+    };
+
+    let stack = discover_stack(&git_repo, &config, &gh_client).await?;
+
+    let commit_oid = commit_ref::resolve_commit_ref(&git_repo, &stack, commit_ref)?;
+    let entry = stack
+        .entries
+        .iter()
+        .find(|e| e.oid == commit_oid)
+        .with_context(|| format!("Commit {} is not part of the current stack", commit_ref))?;
+
+    // Commits without metadata get their predicted slot, as export would assign it
+    let mut slot_cache = slots::SlotCache::load(&git_repo)?;
+    let slot = match (&entry.slot, &entry.predicted_slot) {
+        (Some(slot), _) => slot.clone(),
+        (None, Some(predicted))
+            if slot_cache.is_slot_available(&stack.current_branch, predicted) =>
+        {
+            predicted.clone()
+        }
+        (None, _) => slot_cache.allocate(&stack.current_branch),
+    };
+    slot_cache.mark_slot_used(&stack.current_branch, &slot);
+
+    let metadata = CommitMetadata {
+        pr: entry.pr_number,
+        slot,
+        annotation: annotation.clone(),
+        last_export: entry.last_export.clone(),
+        owner: entry.owner.clone(),
+        signature: None,
+    };
+
+    notes::write_note(
+        &git_repo,
+        commit_oid,
+        &metadata,
+        &config.notes_ref,
+        config.sign_notes,
+    )
+    .context("Failed to write note to commit")?;
+    slot_cache.save(&git_repo)?;
+    workflows::notes_sync::push_notes_if_always(&git_repo, &config, &gh_client).await?;
+
+    match annotation {
+        Some(text) => println!(
+            "{} Annotated {} {}",
+            style("📌").yellow(),
+            style(&entry.short_sha).yellow(),
+            style(&text).bold()
+        ),
+        None => println!(
+            "{} Cleared annotation on {}",
+            style("✓").green(),
+            style(&entry.short_sha).yellow()
+        ),
+    }
+    println!(
+        "\n{}",
+        style("Run 'git-stk export' to share the annotation with the remote.").dim()
+    );
+
+    Ok(())
+}
@@ -0,0 +1,86 @@
+use crate::git::worktree::TempWorktree;
+use crate::model::Config;
+use crate::stack::discover_local_stack;
+use crate::workflows::test_run::{self, TestCache};
+use anyhow::{Context, Result, bail};
+use console::style;
+use git2::Repository;
+
+/// Binary-search the stack for the first entry where a command fails
+pub async fn bisect(command: &[String], no_cache: bool) -> Result<()> {
+    let command = &test_run::shell_command(command)?;
+    let git_repo = Repository::open(".").context("Failed to open git repository")?;
+    let config = Config::load(&git_repo)?;
+
+    let stack = discover_local_stack(&git_repo, &config)?;
+    if stack.is_empty() {
+        bail!("No commits in stack to bisect.");
+    }
+
+    println!(
+        "\n🔎 Bisecting {} commit{} with {}...\n",
+        stack.len(),
+        if stack.len() == 1 { "" } else { "s" },
+        style(command).cyan()
+    );
+
+    let worktree = TempWorktree::create(&git_repo, "bisect", stack.entries[0].oid)?;
+    let mut cache = TestCache::load(&git_repo)?;
+
+    let first_bad = test_run::bisect_first_failure(stack.len(), |i| {
+        let entry = &stack.entries[i];
+        let outcome = test_run::run_command_at(
+            &git_repo, &worktree, &mut cache, entry.oid, command, !no_cache,
+        )?;
+
+        println!(
+            "  {} {}. {}  {}{}",
+            if outcome.passed {
+                style("✓").green()
+            } else {
+                style("✗").red()
+            },
+            entry.index,
+            style(&entry.short_sha).yellow(),
+            style(&entry.subject).dim(),
+            if outcome.cached {
+                style(" (cached)").dim().to_string()
+            } else {
+                String::new()
+            }
+        );
+
+        Ok(outcome.passed)
+    })?;
+
+    cache.save(&git_repo)?;
+    println!();
+
+    let Some(bad_index) = first_bad else {
+        println!(
+            "{} The top of the stack passes, nothing to bisect",
+            style("✓").green()
+        );
+        return Ok(());
+    };
+
+    let entry = &stack.entries[bad_index];
+    println!(
+        "{} First failing entry: {}. {} {}",
+        style("✗").red().bold(),
+        style(entry.index).bold(),
+        style(&entry.short_sha).yellow(),
+        style(&entry.subject).bold()
+    );
+    if let Some(pr_number) = entry.pr_number {
+        println!("  {} PR #{}", style("→").dim(), pr_number);
+    }
+    if bad_index == 0 {
+        println!(
+            "  {}",
+            style(format!("(assuming {} itself passes)", stack.base_branch)).dim()
+        );
+    }
+
+    Ok(())
+}
@@ -0,0 +1,221 @@
+use crate::gh::queries::PrInfo;
+use crate::gh::{client, queries};
+use crate::git::{refs, repo};
+use crate::model::Config;
+use crate::stack::discover_stack;
+use crate::ui::render_timeline;
+use anyhow::{Context, Result, bail};
+use console::style;
+use git2::{Repository, build::CheckoutBuilder};
+use std::collections::HashSet;
+
+/// Check out the stack a PR belongs to, for reviewing it with full context
+///
+/// The chain is rebuilt from GitHub: down through the PR's base branches and
+/// up through open PRs based on its slot branch, as long as they're slot
+/// branches of the same stack (`<branch>--<slot>`). A local `<branch>` is
+/// created at the top of the chain and the remote's notes are fetched, so
+/// `git stk view` shows the stack the way its author sees it.
+pub async fn checkout_pr(pr_number: u64, fetch: Option<bool>) -> Result<()> {
+    let git_repo = Repository::open(".").context("Failed to open git repository")?;
+    let mut config = Config::load(&git_repo)?;
+    config.fetch = fetch.unwrap_or(config.fetch);
+    let gh_client = client::create_configured_client(&config)?;
+
+    let remote = git_repo
+        .find_remote(&config.remote)
+        .context("Failed to find remote")?;
+    let (owner, repo_name) =
+        client::parse_repo_from_url(remote.url().context("Remote URL is not valid UTF-8")?)?;
+
+    if repo::has_uncommitted_changes(&git_repo)? {
+        bail!("You have uncommitted changes. Commit or stash them before checking out a stack.");
+    }
+
+    println!("🔍 Looking up the stack of PR #{}...", pr_number);
+    let pr = queries::get_pr(&gh_client, &owner, &repo_name, pr_number).await?;
+    let Some(branch) = stack_branch(&pr.head_ref).map(String::from) else {
+        bail!(
+            "PR #{} is on '{}', which isn't a git-stk slot branch (<branch>--<slot>)",
+            pr_number,
+            pr.head_ref
+        );
+    };
+    if git_repo
+        .find_branch(&branch, git2::BranchType::Local)
+        .is_ok()
+    {
+        bail!(
+            "Branch '{}' already exists locally. Delete or rename it to check out the stack of PR #{}.",
+            branch,
+            pr_number
+        );
+    }
+
+    // Walk down through the bases, then up through the open PRs based on the top
+    let mut chain = vec![pr];
+    let mut seen: HashSet<u64> = chain.iter().map(|pr| pr.number).collect();
+    loop {
+        let base_ref = chain[0].base_ref.clone();
+        if stack_branch(&base_ref) != Some(branch.as_str()) {
+            break;
+        }
+        match queries::find_pr_by_head(&gh_client, &owner, &repo_name, &base_ref).await? {
+            Some(below) if seen.insert(below.number) => chain.insert(0, below),
+            _ => break,
+        }
+    }
+    let open_prs = queries::list_open_prs(&gh_client, &owner, &repo_name).await?;
+    let top = &chain[chain.len() - 1].head_ref;
+    let above: Vec<PrInfo> = chain_above(top, &branch, &open_prs)
+        .into_iter()
+        .filter(|pr| seen.insert(pr.number))
+        .cloned()
+        .collect();
+    chain.extend(above);
+
+    for pr in &chain {
+        println!(
+            "  {} {} {} {}",
+            style("→").dim(),
+            style(format!("#{}", pr.number)).cyan(),
+            pr.title,
+            style(format!("({})", pr.head_ref)).dim()
+        );
+    }
+
+    // Slot branches, so the stack is hydrated like on the author's side
+    let mut refspecs: Vec<String> = chain
+        .iter()
+        .map(|pr| {
+            format!(
+                "+refs/heads/{}:refs/remotes/{}/{}",
+                pr.head_ref, config.remote, pr.head_ref
+            )
+        })
+        .collect();
+    let bottom_base = chain[0].base_ref.clone();
+    refspecs.push(format!(
+        "+refs/heads/{}:refs/remotes/{}/{}",
+        bottom_base, config.remote, bottom_base
+    ));
+    println!(
+        "\n📥 Fetching {} slot branch{}...",
+        chain.len(),
+        if chain.len() == 1 { "" } else { "es" }
+    );
+    refs::fetch_refspecs(&git_repo, &config.remote, &refspecs, &config.transport)?;
+
+    // Without a force, the fetch only fast-forwards: local notes are never overwritten
+    let notes_refspec = format!("{}:{}", config.notes_ref, config.notes_ref);
+    if let Err(e) = refs::fetch_refspecs(
+        &git_repo,
+        &config.remote,
+        &[notes_refspec],
+        &config.transport,
+    ) {
+        eprintln!(
+            "  {} Couldn't fetch the stack's notes, PR numbers and slots may be missing: {}",
+            style("⚠").yellow(),
+            e
+        );
+    }
+
+    let top = &chain[chain.len() - 1];
+    let top_oid = repo::resolve_sha(&git_repo, &top.head_sha).with_context(|| {
+        format!(
+            "Commit {} of PR #{} wasn't fetched",
+            top.head_sha, top.number
+        )
+    })?;
+    let commit = git_repo.find_commit(top_oid).with_context(|| {
+        format!(
+            "Commit {} of PR #{} wasn't fetched",
+            top.head_sha, top.number
+        )
+    })?;
+    git_repo
+        .branch(&branch, &commit, false)
+        .with_context(|| format!("Failed to create branch '{}'", branch))?;
+    git_repo
+        .checkout_tree(commit.as_object(), Some(CheckoutBuilder::new().safe()))
+        .context("Failed to check out the stack")?;
+    git_repo
+        .set_head(&format!("refs/heads/{}", branch))
+        .with_context(|| format!("Failed to check out branch '{}'", branch))?;
+
+    println!(
+        "\n{} Checked out {} ({} PR{})\n",
+        style("✓").green(),
+        style(&branch).bold(),
+        chain.len(),
+        if chain.len() == 1 { "" } else { "s" }
+    );
+
+    if config.base_branch.as_deref() != Some(bottom_base.as_str()) {
+        println!(
+            "{} The stack is based on {}, but git-stk.base is {}. Run 'git stk set base {}' to view it.",
+            style("⚠").yellow(),
+            style(&bottom_base).yellow(),
+            style(config.base_name()).yellow(),
+            bottom_base
+        );
+        return Ok(());
+    }
+
+    let stack = discover_stack(&git_repo, &config, &gh_client).await?;
+    render_timeline(&stack, false);
+    Ok(())
+}
+
+/// The stack branch of a slot branch: `feature` for `feature--01`
+fn stack_branch(head_ref: &str) -> Option<&str> {
+    let (branch, slot) = head_ref.rsplit_once("--")?;
+    (!branch.is_empty() && !slot.is_empty()).then_some(branch)
+}
+
+/// Open PRs stacked on `head_ref` within the same stack, bottom first. Where
+/// several PRs are based on the same branch, the lowest number wins.
+fn chain_above<'a>(head_ref: &str, branch: &str, open_prs: &'a [PrInfo]) -> Vec<&'a PrInfo> {
+    let mut chain: Vec<&PrInfo> = Vec::new();
+    let mut current = head_ref;
+    while let Some(next) = open_prs
+        .iter()
+        .filter(|pr| pr.base_ref == current && stack_branch(&pr.head_ref) == Some(branch))
+        .filter(|pr| !chain.iter().any(|c| c.number == pr.number))
+        .min_by_key(|pr| pr.number)
+    {
+        chain.push(next);
+        current = &next.head_ref;
+    }
+    chain
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_stack_branch() {
+        assert_eq!(stack_branch("feature--01"), Some("feature"));
+        assert_eq!(stack_branch("user/fix--a--03"), Some("user/fix--a"));
+        assert_eq!(stack_branch("feature"), None);
+        assert_eq!(stack_branch("feature--"), None);
+    }
+
+    #[test]
+    fn test_chain_above() {
+        let open_prs = vec![
+            PrInfo::for_test(13, "feat--02", "feat--03"),
+            PrInfo::for_test(12, "feat--01", "feat--02"),
+            PrInfo::for_test(20, "feat--02", "other--01"),
+            PrInfo::for_test(14, "feat--03", "feat--04"),
+        ];
+        let numbers: Vec<u64> = chain_above("feat--01", "feat", &open_prs)
+            .iter()
+            .map(|pr| pr.number)
+            .collect();
+        assert_eq!(numbers, vec![12, 13, 14]);
+        assert!(chain_above("feat--04", "feat", &open_prs).is_empty());
+    }
+}
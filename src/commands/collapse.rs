@@ -0,0 +1,259 @@
+use crate::commands::{ExportOptions, export};
+use crate::gh::{client, mutations};
+use crate::git::{backup, refs, repo, rewrite, slots};
+use crate::model::{Config, Entry, PrState, PushHooks};
+use crate::stack::{discover_stack, ensure_online};
+use anyhow::{Context, Result, bail};
+use console::style;
+use git2::Repository;
+
+/// Squash the whole stack into one commit and PR, for when reviewers would
+/// rather see it in one piece
+///
+/// The commit keeps the slot and PR of the lowest entry with an open PR (or
+/// of the bottom entry), so that PR ends up holding the whole change. Once the
+/// stack is exported, the other open PRs are closed with a link to it and
+/// their slot branches are deleted.
+pub async fn collapse(skip_confirm: bool, fetch: Option<bool>) -> Result<()> {
+    let git_repo = Repository::open(".").context("Failed to open git repository")?;
+    let mut config = Config::load(&git_repo)?;
+    config.fetch = fetch.unwrap_or(config.fetch);
+    let gh_client = client::create_configured_client(&config)?;
+
+    // Collapsing rewrites the branch; a detached HEAD has none
+    repo::current_branch(&git_repo)?;
+
+    println!("🔍 Discovering stack...\n");
+    let stack = discover_stack(&git_repo, &config, &gh_client).await?;
+    if stack.len() < 2 {
+        bail!(
+            "The stack has {} entr{}: nothing to collapse",
+            stack.len(),
+            if stack.len() == 1 { "y" } else { "ies" }
+        );
+    }
+    ensure_online(&stack, &config, "collapse")?;
+    if !stack.context.is_empty() {
+        bail!(
+            "The stack has context commits between its entries (git-stk.onlyMine, git-stk.onlySince), which collapsing would squash too"
+        );
+    }
+
+    let is_open = |entry: &Entry| {
+        entry.pr_number.is_some()
+            && matches!(entry.pr_state, Some(PrState::Open) | Some(PrState::Draft))
+    };
+    let keep = stack
+        .entries
+        .iter()
+        .find(|e| is_open(e))
+        .unwrap_or(&stack.entries[0]);
+    let to_close: Vec<&Entry> = stack
+        .entries
+        .iter()
+        .filter(|e| e.oid != keep.oid && is_open(e))
+        .collect();
+
+    println!(
+        "  {} Squash {} entries of {} into one commit",
+        style("→").dim(),
+        stack.len(),
+        style(&stack.current_branch).cyan()
+    );
+    match keep.pr_number.filter(|_| is_open(keep)) {
+        Some(pr) => println!("  {} PR #{} gets the whole change", style("→").dim(), pr),
+        None => println!("  {} Export creates one PR for it", style("→").dim()),
+    }
+    for entry in &to_close {
+        println!(
+            "  {} Close PR #{} {}",
+            style("→").dim(),
+            entry.pr_number.unwrap_or_default(),
+            entry.subject
+        );
+    }
+
+    let confirmed = if skip_confirm {
+        true
+    } else {
+        use dialoguer::Confirm;
+        Confirm::new()
+            .with_prompt(format!(
+                "Collapse {} into a single PR?",
+                stack.current_branch
+            ))
+            .default(false)
+            .interact()?
+    };
+    if !confirmed {
+        println!("\n{}", style("✗ Operation cancelled").red());
+        return Ok(());
+    }
+    println!();
+
+    let snapshot = backup::create_snapshot(
+        &git_repo,
+        &config.notes_ref,
+        "collapse",
+        config.keep_snapshots,
+    )?;
+    println!(
+        "💾 Saved snapshot {} ({})",
+        style(snapshot.id).cyan(),
+        style(format!("{:.7}", snapshot.head)).yellow()
+    );
+
+    let messages = stack
+        .entries
+        .iter()
+        .map(|e| {
+            Ok(git_repo
+                .find_commit(e.oid)?
+                .message()
+                .unwrap_or_default()
+                .to_string())
+        })
+        .collect::<Result<Vec<_>>>()?;
+    let keep_position = keep.index - 1;
+    let new_oid = rewrite::squash_stack(
+        &git_repo,
+        &config,
+        &stack,
+        keep.oid,
+        &squash_message(&messages, keep_position),
+        "git-stk: collapse stack",
+    )?;
+    println!("🗜️  Squashed {} entries into {:.7}", stack.len(), new_oid);
+
+    println!("\n📤 Exporting the collapsed stack...\n");
+    export::export(ExportOptions::default()).await?;
+
+    // Only closed once the squash is exported, so a failed export leaves them as they were
+    let owner = keep.repo_owner.clone().context("Missing repo owner")?;
+    let repo_name = keep.repo_name.clone().context("Missing repo name")?;
+    if let Some(kept_pr) = keep.pr_number.filter(|_| is_open(keep)) {
+        for entry in &to_close {
+            let pr_number = entry.pr_number.unwrap_or_default();
+            let comment = format!(
+                "The `{}` stack was collapsed into a single PR: this change continues in #{}.",
+                stack.current_branch, kept_pr
+            );
+            if let Err(e) =
+                mutations::add_pr_comment(&gh_client, &owner, &repo_name, pr_number, &comment).await
+            {
+                eprintln!(
+                    "  Warning: Failed to add comment to PR #{}: {}",
+                    pr_number, e
+                );
+            }
+            match mutations::close_pull_request(&gh_client, &owner, &repo_name, pr_number).await {
+                Ok(_) => println!("🔒 Closed PR #{}", pr_number),
+                Err(e) => eprintln!("  Warning: Failed to close PR #{}: {}", pr_number, e),
+            }
+        }
+        if !to_close.is_empty() {
+            let replaced = to_close
+                .iter()
+                .filter_map(|e| e.pr_number)
+                .map(|n| format!("#{}", n))
+                .collect::<Vec<_>>()
+                .join(", ");
+            let comment = format!(
+                "The `{}` stack was collapsed into this PR, which replaces {}.",
+                stack.current_branch, replaced
+            );
+            if let Err(e) =
+                mutations::add_pr_comment(&gh_client, &owner, &repo_name, kept_pr, &comment).await
+            {
+                eprintln!("  Warning: Failed to add comment to PR #{}: {}", kept_pr, e);
+            }
+        }
+    }
+
+    // Only now that the kept PR targets the base can the branches below it go
+    let dropped: Vec<&Entry> = stack
+        .entries
+        .iter()
+        .filter(|e| e.oid != keep.oid && e.slot.is_some())
+        .collect();
+    let refspecs: Vec<String> = dropped
+        .iter()
+        .filter(|e| e.remote_branch_exists)
+        .filter_map(|e| e.head_ref.as_ref())
+        .map(|head_ref| format!(":refs/heads/{}", head_ref))
+        .collect();
+    if !refspecs.is_empty() {
+        println!(
+            "\n🗑️  Deleting {} slot branch{}...",
+            refspecs.len(),
+            if refspecs.len() == 1 { "" } else { "es" }
+        );
+        let mut capabilities = refs::RemoteCapabilities::load(&git_repo, &config.remote);
+        let result = refs::push_refs(
+            &git_repo,
+            &config.remote,
+            &refspecs,
+            &mut capabilities,
+            config.push_hooks != PushHooks::Each,
+            config.max_refs_per_push,
+            &config.transport,
+            &mut |_| {},
+        )?;
+        match result.error {
+            None => println!("  ✓ Deleted"),
+            Some(e) => eprintln!(
+                "  Warning: Failed to delete some branches:\n{}",
+                e.trim_end()
+            ),
+        }
+    }
+    let mut slot_cache = slots::SlotCache::load(&git_repo)?;
+    for slot in dropped.iter().filter_map(|e| e.slot.as_ref()) {
+        slot_cache.release_slot(&stack.current_branch, slot);
+    }
+    slot_cache.save(&git_repo)?;
+
+    println!(
+        "\n{} Collapsed {} entries. Undo with 'git stk restore {}'",
+        style("✨").green(),
+        stack.len(),
+        snapshot.id
+    );
+    Ok(())
+}
+
+/// The squashed commit's message: the kept entry's message, then the others'
+/// in stack order. Only the kept entry's `Change-Id` is kept.
+fn squash_message(messages: &[String], keep: usize) -> String {
+    let mut parts = vec![messages[keep].trim_end().to_string()];
+    for (i, message) in messages.iter().enumerate() {
+        if i == keep {
+            continue;
+        }
+        let body: Vec<&str> = message
+            .trim_end()
+            .lines()
+            .filter(|line| !line.starts_with("Change-Id:"))
+            .collect();
+        parts.push(body.join("\n").trim_end().to_string());
+    }
+    format!("{}\n", parts.join("\n\n"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_squash_message() {
+        let messages = vec![
+            "Add parser\n\nChange-Id: I1\n".to_string(),
+            "Add lexer\n\nTokens first.\n\nChange-Id: I2\n".to_string(),
+            "Wire it up\n".to_string(),
+        ];
+        assert_eq!(
+            squash_message(&messages, 1),
+            "Add lexer\n\nTokens first.\n\nChange-Id: I2\n\nAdd parser\n\nWire it up\n"
+        );
+    }
+}
@@ -0,0 +1,61 @@
+use crate::gh::{client, mutations};
+use crate::git::commit_ref;
+use crate::model::Config;
+use crate::stack::discover_stack;
+use anyhow::{Context, Result, bail};
+use console::style;
+use git2::Repository;
+use std::path::Path;
+
+/// Post a comment on the PR of a stack entry
+///
+/// The body comes from `message`, or from `file` for longer comments.
+pub async fn comment(
+    commit_ref: &str,
+    message: Option<&str>,
+    file: Option<&Path>,
+    fetch: Option<bool>,
+) -> Result<()> {
+    let git_repo = Repository::open(".").context("Failed to open git repository")?;
+    let mut config = Config::load(&git_repo)?;
+    config.fetch = fetch.unwrap_or(config.fetch);
+    let gh_client = client::create_configured_client(&config)?;
+
+    let body = match (message, file) {
+        (_, Some(path)) => std::fs::read_to_string(path)
+            .with_context(|| format!("Failed to read comment body from {}", path.display()))?,
+        (Some(message), None) => message.to_string(),
+        (None, None) => bail!("Provide a comment message or --file"),
+    };
+    if body.trim().is_empty() {
+        bail!("Comment body cannot be empty");
+    }
+
+    let stack = discover_stack(&git_repo, &config, &gh_client).await?;
+
+    let commit_oid = commit_ref::resolve_commit_ref(&git_repo, &stack, commit_ref)?;
+    let entry = stack
+        .entries
+        .iter()
+        .find(|e| e.oid == commit_oid)
+        .with_context(|| format!("Commit {} is not part of the current stack", commit_ref))?;
+
+    let pr_number = entry.pr_number.with_context(|| {
+        format!(
+            "Commit {} has no PR yet. Run 'git stk export' first.",
+            entry.short_sha
+        )
+    })?;
+    let owner = entry.repo_owner.as_ref().context("Missing repo owner")?;
+    let repo_name = entry.repo_name.as_ref().context("Missing repo name")?;
+
+    mutations::add_pr_comment(&gh_client, owner, repo_name, pr_number, &body).await?;
+
+    println!(
+        "{} Commented on {}",
+        style("💬").cyan(),
+        style(entry.pr_url().unwrap_or_else(|| format!("#{}", pr_number))).cyan()
+    );
+
+    Ok(())
+}
@@ -0,0 +1,164 @@
+use crate::gh::client;
+use crate::git::{notes, refs, slots};
+use crate::model::{CommitMetadata, Config, Entry, PushHooks, PushNotes};
+use crate::stack::{discover_stack, recover_from_slot_heads};
+use crate::workflows;
+use anyhow::{Context, Result, bail};
+use console::style;
+use git2::Repository;
+
+/// Check the current stack's metadata and, with `fix`, repair it
+///
+/// Detects a notes ref missing on the remote (deleted, or never pushed) while
+/// the branch has slot branches there, and entries without a local note.
+/// Their slots and PRs are rebuilt from the PR bodies' metadata blocks and from
+/// the open PRs on the branch's slot branches; `fix` writes them to the notes
+/// and pushes the notes.
+pub async fn doctor(fix: bool, fetch: Option<bool>) -> Result<()> {
+    let git_repo = Repository::open(".").context("Failed to open git repository")?;
+    let mut config = Config::load(&git_repo)?;
+    config.fetch = fetch.unwrap_or(config.fetch);
+    let gh_client = client::create_configured_client(&config)?;
+
+    let mut stack = discover_stack(&git_repo, &config, &gh_client).await?;
+    println!(
+        "🩺 Checking the metadata of {}...\n",
+        style(&stack.current_branch).cyan()
+    );
+    if stack.offline {
+        bail!(
+            "Couldn't reach GitHub or {}, so the metadata can't be checked against them. Check your connection and try again.",
+            config.remote
+        );
+    }
+    recover_from_slot_heads(&git_repo, &config, &gh_client, &mut stack).await?;
+
+    let slot_prefix = format!("{}--", slots::sanitize_branch_name(&stack.current_branch));
+    let slot_branches = stack
+        .remote_refs
+        .keys()
+        .filter(|name| name.starts_with(&slot_prefix))
+        .count();
+    let remote_notes = refs::get_remote_refs_under(
+        &git_repo,
+        &config.remote,
+        &config.notes_ref,
+        &config.transport,
+    )?;
+    let notes_missing = remote_notes.is_empty() && slot_branches > 0;
+    if notes_missing {
+        println!(
+            "  {} {} is missing on {}, but {} slot branch{} of {} {} there",
+            style("✗").red(),
+            config.notes_ref,
+            config.remote,
+            slot_branches,
+            if slot_branches == 1 { "" } else { "es" },
+            stack.current_branch,
+            if slot_branches == 1 { "is" } else { "are" }
+        );
+    } else {
+        println!("  {} Notes on {}", style("✓").green(), config.remote);
+    }
+
+    // Slots known in memory (recovered by discovery or above) but not in the notes
+    let mut rebuild: Vec<&Entry> = Vec::new();
+    for entry in stack.entries.iter().filter(|e| e.slot.is_some()) {
+        if notes::read_note(&git_repo, entry.oid, &config.notes_ref)?.is_none() {
+            rebuild.push(entry);
+        }
+    }
+    let lost = stack.entries.iter().filter(|e| e.slot.is_none()).count();
+    if rebuild.is_empty() {
+        println!("  {} Every exported entry has a note", style("✓").green());
+    } else {
+        println!(
+            "  {} {} entr{} without a note can be rebuilt from {}'s PRs:",
+            style("✗").red(),
+            rebuild.len(),
+            if rebuild.len() == 1 { "y" } else { "ies" },
+            stack.current_branch
+        );
+        for entry in &rebuild {
+            println!(
+                "      {} {}  → slot {}{}",
+                style(&entry.short_sha).yellow(),
+                entry.subject,
+                entry.slot.as_deref().unwrap_or_default(),
+                entry
+                    .pr_number
+                    .map(|pr| format!(", PR #{}", pr))
+                    .unwrap_or_default()
+            );
+        }
+    }
+    if lost > 0 && slot_branches > 0 {
+        println!(
+            "  {} {} entr{} matched no PR and will get new slots on export",
+            style("ℹ").blue(),
+            lost,
+            if lost == 1 { "y" } else { "ies" }
+        );
+    }
+
+    if !notes_missing && rebuild.is_empty() {
+        println!("\n{} No problems found", style("✓").green());
+        return Ok(());
+    }
+    if !fix {
+        println!(
+            "\nRun {} to rebuild the notes and push them.",
+            style("git stk doctor --fix").bold()
+        );
+        return Ok(());
+    }
+
+    println!();
+    let mut slot_cache = slots::SlotCache::load(&git_repo)?;
+    for entry in &rebuild {
+        let slot = entry.slot.clone().unwrap_or_default();
+        let metadata = CommitMetadata {
+            pr: entry.pr_number,
+            slot: slot.clone(),
+            annotation: entry.annotation.clone(),
+            last_export: entry.last_export.clone(),
+            owner: entry.owner.clone(),
+            signature: None,
+        };
+        notes::write_note(
+            &git_repo,
+            entry.oid,
+            &metadata,
+            &config.notes_ref,
+            config.sign_notes,
+        )?;
+        slot_cache.ensure_slot(&stack.current_branch, &slot);
+    }
+    slot_cache.save(&git_repo)?;
+    if !rebuild.is_empty() {
+        println!(
+            "📝 Rebuilt {} note{}",
+            rebuild.len(),
+            if rebuild.len() == 1 { "" } else { "s" }
+        );
+    }
+
+    if config.push_notes == PushNotes::Never {
+        println!(
+            "{}",
+            style("git-stk.pushNotes is never: push the notes yourself to share them").dim()
+        );
+        return Ok(());
+    }
+    println!("☁️  Pushing metadata...");
+    workflows::notes_sync::push_notes(
+        &git_repo,
+        &config,
+        &gh_client,
+        config.push_hooks != PushHooks::Each,
+    )
+    .await?;
+    println!("   {} Done", style("✓").green());
+
+    Ok(())
+}
@@ -1,15 +1,28 @@
 //! Export command - pushes commits and creates/updates PRs for the stack.
 
-use crate::gh::{client, mutations, queries};
-use crate::git::{notes, refs, reorder_detect, slots};
-use crate::model::{CommitMetadata, Config, PrState, Stack};
-use crate::stack::discover_stack;
-use crate::ui::callout;
-use anyhow::{bail, Context, Result};
+use crate::gh::{
+    client::{self, Client},
+    mutations, queries,
+};
+use crate::git::{
+    change_id, commit_ref, conventional, format_patch, issue_keys, mirror, notes, overlap,
+    range_diff, refs, reorder_detect, repo, slots, trailers,
+};
+use crate::model::{
+    CommitMetadata, Config, Entry, LastExport, OrphanedPr, PrStackMetadata, PrState, PushHooks,
+    PushNotes, Stack, UpdateStatus,
+};
+use crate::profile;
+use crate::stack::{discover_local_stack, discover_stack, ensure_online, rediscover_stack};
+use crate::ui::{callout, render_overlaps, timeline};
+use crate::workflows::webhook::{self, Activity, PrSummary, WebhookEvent};
+use crate::workflows::{self, HookEvent};
+use anyhow::{Context, Result, bail};
 use console::style;
 use git2::Repository;
-use octocrab::Octocrab;
+use serde_json::json;
 use std::collections::HashMap;
+use std::path::PathBuf;
 
 // =============================================================================
 // Options
@@ -24,20 +37,77 @@ pub struct ExportOptions {
     pub dry_run: bool,
     pub json: bool,
     pub verbose: bool,
+    /// Post/update a stack summary comment on the top PR
+    pub summary_comment: bool,
+    /// Users to assign to created PRs ("me" is the authenticated user)
+    pub assignees: Vec<String>,
+    /// Milestone (number or title) for created PRs, overrides `git-stk.milestone`
+    pub milestone: Option<String>,
+    /// ProjectV2 (number or node ID) for created PRs, overrides `git-stk.project`
+    pub project: Option<String>,
+    /// PR title override, only allowed for single-commit exports
+    pub title: Option<String>,
+    /// PR body override, only allowed for single-commit exports
+    pub body: Option<String>,
+    /// Skip local pre-push hooks, overrides `git-stk.pushHooks`
+    pub no_verify: bool,
+    /// Print a timing summary of plan building, git operations, and GitHub calls
+    pub profile: bool,
+    /// When to push notes, overrides `git-stk.pushNotes`
+    pub push_notes: Option<PushNotes>,
+    /// All-or-nothing: push atomically and roll the remote back if a GitHub update fails
+    pub atomic: bool,
+    /// Where the stack goes: GitHub PRs, or a mailing-list patch series
+    pub format: ExportFormat,
+    /// Directory for the patch series (mbox format only)
+    pub output_dir: Option<PathBuf>,
+    /// Send the patch series with `git send-email` (mbox format only)
+    pub send_email: bool,
+    /// Add your Signed-off-by to stack commits lacking their author's, rewriting them
+    pub amend_signoff: bool,
+    /// Rebase the stack onto the remote base first if it (or a PR) is behind it
+    pub update_behind: bool,
+    /// Mirror slot branches as local `stk/<branch>/<slot>` branches, overrides `git-stk.keepBranches`
+    pub keep_branches: bool,
+    /// Skip the checks of the stack policy (`[policy]`)
+    pub no_verify_policy: bool,
+    /// Force-push slot branches other people own, and own them from now on
+    pub take_ownership: bool,
+    /// Close the PRs of entries this clone dropped from the stack
+    pub close_dropped: bool,
+    /// `--fetch`/`--no-fetch`, overriding `git-stk.fetch` when given
+    pub fetch: Option<bool>,
+    /// Only export these entries: a commit reference or a range (`resolve_commit_refs`)
+    pub commits: Option<String>,
+}
+
+/// Export target (`--format`)
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, clap::ValueEnum)]
+pub enum ExportFormat {
+    /// Push slot branches and create/update PRs (default)
+    #[default]
+    Github,
+    /// Write a `git format-patch` series with a cover letter
+    Mbox,
 }
 
 // =============================================================================
 // Export Plan - describes what actions will be taken
 // =============================================================================
 
-#[derive(Debug)]
+#[derive(Debug, Default)]
 struct ExportPlan {
     slot_assignments: Vec<SlotAssignment>,
+    slot_conflicts: Vec<SlotConflict>,
     refs_to_push: Vec<RefToPush>,
     prs_to_create: Vec<PrToCreate>,
     prs_to_update: Vec<PrToUpdate>,
     phase1_base_updates: Vec<(u64, String)>,
     phase3_base_updates: Vec<(u64, String)>,
+    /// Open PRs of entries dropped from the stack, closed once the PRs above are rebased
+    prs_to_close: Vec<OrphanedPr>,
+    /// Checks that found problems the export goes on with (e.g. unpublished submodule pointers)
+    warnings: Vec<String>,
 }
 
 #[derive(Debug)]
@@ -48,6 +118,14 @@ struct SlotAssignment {
     is_new: bool,
 }
 
+/// A slot skipped during allocation because its remote branch holds unrelated commits
+#[derive(Debug)]
+struct SlotConflict {
+    slot: String,
+    head_ref: String,
+    remote_oid: git2::Oid,
+}
+
 #[derive(Debug)]
 struct RefToPush {
     oid: git2::Oid,
@@ -70,6 +148,9 @@ struct PrToUpdate {
     head_ref: String,
     base_ref: String,
     title: String,
+    /// Title/body overrides to apply to the existing PR (single-commit exports only)
+    title_override: Option<String>,
+    body_override: Option<String>,
     needs_base_update: bool,
     is_reordered: bool,
 }
@@ -79,10 +160,250 @@ struct PrToUpdate {
 // =============================================================================
 
 pub async fn export(options: ExportOptions) -> Result<()> {
+    let started = std::time::Instant::now();
+    if options.profile {
+        profile::enable();
+    }
+
+    if options.format == ExportFormat::Mbox {
+        if options.commits.is_some() {
+            bail!("--format mbox writes the whole stack; it can't export a commit range");
+        }
+        return export_patch_series(&options);
+    }
+    if options.output_dir.is_some() || options.send_email {
+        bail!("--output-dir and --send-email require --format mbox");
+    }
+
+    let exported = export_inner(&options).await;
+
+    if options.profile {
+        profile::print_summary(started.elapsed());
+    }
+
+    let Some(report) = exported? else {
+        return Ok(());
+    };
+    if options.json {
+        let mut output = report.result;
+        output["warnings"] = json!(report.warnings);
+        output["mismatches"] = json!(report.mismatches);
+        println!("{}", serde_json::to_string_pretty(&output)?);
+        return Ok(());
+    }
+
+    // Show final state
+    if report.mismatches.is_empty() {
+        println!("\n✨ Export complete!\n");
+    } else {
+        eprintln!(
+            "\n{}",
+            style("✗ Export finished, but reconciliation needed:")
+                .red()
+                .bold()
+        );
+        for mismatch in &report.mismatches {
+            eprintln!("  {} {}", style("•").red(), mismatch);
+        }
+        eprintln!("  Run 'git stk export' again, or check the PRs on GitHub.\n");
+    }
+    let git_repo = Repository::open(".").context("Failed to open git repository")?;
+    let config = Config::load(&git_repo)?;
+    crate::commands::view::show_stack(
+        &git_repo,
+        &config,
+        &report.verified,
+        &crate::commands::ViewOptions::default(),
+    )?;
+
+    Ok(())
+}
+
+/// Entries whose slot branch or PR doesn't reflect what the export planned:
+/// each slot branch at its commit (unless `--pr-only`) and each open PR based
+/// on the entry below it (unless `--push-only`). Only entries in the plan are
+/// checked: merged entries and slots the export left alone aren't its doing,
+/// and context commits (`git-stk.onlyMine`, `git-stk.onlySince`) have no slot
+/// or PR of their own.
+fn export_mismatches(
+    stack: &Stack,
+    remote: &str,
+    plan: &ExportPlan,
+    options: &ExportOptions,
+) -> Vec<String> {
+    let mut mismatches = Vec::new();
+
+    for entry in stack.entries.iter().filter(|e| !e.merged_into_main) {
+        if !plan.refs_to_push.iter().any(|r| r.oid == entry.oid) {
+            continue;
+        }
+        let name = format!("{} {}", entry.short_sha, entry.subject);
+
+        if !options.pr_only && entry.status != UpdateStatus::UpToDate {
+            let mismatch = match (&entry.head_ref, entry.remote_oid) {
+                (Some(head_ref), Some(oid)) => {
+                    format!("{}: {}/{} is at {:.7}", name, remote, head_ref, oid)
+                }
+                (Some(head_ref), None) => {
+                    format!("{}: {}/{} wasn't pushed", name, remote, head_ref)
+                }
+                (None, _) => format!("{}: no slot branch recorded", name),
+            };
+            mismatches.push(mismatch);
+        }
+
+        if options.push_only {
+            continue;
+        }
+        let Some(pr) = entry.pr_number else {
+            mismatches.push(format!("{}: no PR", name));
+            continue;
+        };
+        if matches!(entry.pr_state, Some(PrState::Open | PrState::Draft))
+            && let Some(pr_base) = &entry.pr_base_ref
+            && *pr_base != entry.base_ref
+        {
+            mismatches.push(format!(
+                "#{} targets {}, expected {}",
+                pr, pr_base, entry.base_ref
+            ));
+        }
+    }
+
+    mismatches
+}
+
+/// Write the stack as a patch series for mailing-list review instead of pushing to GitHub
+fn export_patch_series(options: &ExportOptions) -> Result<()> {
     let git_repo = Repository::open(".").context("Failed to open git repository")?;
     let config = Config::load(&git_repo)?;
-    let gh_client = client::create_client()?;
+    config.ensure_shared_config()?;
+
+    workflows::ensure_on_branch(&git_repo, &config, false)?;
+    workflows::ensure_not_protected(&git_repo, &config)?;
+
+    let mut stack = discover_local_stack(&git_repo, &config)?;
+    if stack.is_empty() {
+        println!("No commits to export.");
+        return Ok(());
+    }
+    if options.amend_signoff
+        && !options.dry_run
+        && amend_signoffs(&git_repo, &config, &stack, false)?
+    {
+        stack = discover_local_stack(&git_repo, &config)?;
+    }
+    if !options.no_verify {
+        workflows::ensure_subjects_match(&stack, &config)?;
+        if !(options.amend_signoff && options.dry_run) {
+            workflows::ensure_signed_off(&git_repo, &stack, &config)?;
+        }
+        workflows::check_entry_sizes(&git_repo, &stack, &config, false)?;
+    }
+
+    let output_dir = match &options.output_dir {
+        Some(dir) => dir.clone(),
+        None => Config::git_stack_dir(&git_repo)?
+            .join("patches")
+            .join(slots::sanitize_branch_name(&stack.current_branch)),
+    };
+
+    if options.dry_run {
+        println!(
+            "Would write {} patch{} and a cover letter to {}",
+            stack.len(),
+            if stack.len() == 1 { "" } else { "es" },
+            style(output_dir.display()).cyan()
+        );
+        return Ok(());
+    }
+
+    // Our own directory: drop patches left over from a longer earlier series
+    if options.output_dir.is_none() && output_dir.exists() {
+        std::fs::remove_dir_all(&output_dir)
+            .with_context(|| format!("Failed to clean {}", output_dir.display()))?;
+    }
+
+    let files = format_patch::write_series(
+        &git_repo,
+        &stack,
+        &output_dir,
+        &stack.current_branch,
+        &callout::generate_cover_letter(&stack.entries),
+    )?;
+
+    println!(
+        "📧 Wrote {} files to {}",
+        files.len(),
+        style(output_dir.display()).cyan()
+    );
+    for file in &files {
+        let name = file
+            .file_name()
+            .map(|n| n.to_string_lossy())
+            .unwrap_or_default();
+        println!("   {} {}", style("✓").green(), name);
+    }
+
+    if options.send_email {
+        format_patch::send_email(&git_repo, &files)?;
+    } else {
+        println!(
+            "\n   Edit the cover letter if needed, then send with {}",
+            style(format!("git send-email {}", output_dir.display())).cyan()
+        );
+    }
+
+    Ok(())
+}
+
+/// Sign off the stack for `--amend-signoff`; returns whether commits were rewritten
+fn amend_signoffs(
+    git_repo: &Repository,
+    config: &Config,
+    stack: &Stack,
+    quiet: bool,
+) -> Result<bool> {
+    let added = workflows::signoff::amend_signoffs(git_repo, config, stack)?;
+    if added > 0 && !quiet {
+        println!(
+            "✍️  Signed off {} commit{}",
+            added,
+            if added == 1 { "" } else { "s" }
+        );
+    }
+    Ok(added > 0)
+}
+
+/// Outcome of an export that ran
+struct ExportReport {
+    /// The stack's PRs, as `export_result_json` describes them
+    result: serde_json::Value,
+    /// Steps that failed without stopping the export (e.g., assigning a PR)
+    warnings: Vec<String>,
+    /// The stack rediscovered after the export, to check and show the result
+    verified: Stack,
+    /// What the verified stack shows didn't go as planned (`export_mismatches`)
+    mismatches: Vec<String>,
+}
+
+/// Run the export; returns its report, or `None` if nothing was exported
+async fn export_inner(options: &ExportOptions) -> Result<Option<ExportReport>> {
+    let git_repo = Repository::open(".").context("Failed to open git repository")?;
+    let mut config = Config::load(&git_repo)?;
+    config.ensure_shared_config()?;
+    // Slots are allocated around the remote's branches: stale ones could hand
+    // out a slot another clone pushed since
+    if options.fetch == Some(false) {
+        bail!(
+            "Export needs the remote's current branches to allocate slots, so it can't run with --no-fetch."
+        );
+    }
+    config.fetch = true;
+    let gh_client = client::create_configured_client(&config)?;
 
+    workflows::ensure_on_branch(&git_repo, &config, false)?;
+    workflows::ensure_not_protected(&git_repo, &config)?;
     Config::ensure_notes_rewrite_config(&git_repo, &config.notes_ref)?;
 
     let mut stack = discover_stack(&git_repo, &config, &gh_client).await?;
@@ -91,40 +412,167 @@ pub async fn export(options: ExportOptions) -> Result<()> {
         if !options.json {
             println!("No commits to export.");
         }
-        return Ok(());
+        return Ok(None);
+    }
+
+    if options.update_behind
+        && !options.dry_run
+        && workflows::update_behind::restack_if_behind(&git_repo, &config, &stack, options.json)?
+    {
+        stack = rediscover_stack(&git_repo, &config, &gh_client, &stack).await?;
+    }
+
+    if options.amend_signoff
+        && !options.dry_run
+        && amend_signoffs(&git_repo, &config, &stack, options.json)?
+    {
+        stack = rediscover_stack(&git_repo, &config, &gh_client, &stack).await?;
+    }
+
+    let mut check_warnings = Vec::new();
+    if !options.no_verify {
+        workflows::ensure_subjects_match(&stack, &config)?;
+        if !(options.amend_signoff && options.dry_run) {
+            workflows::ensure_signed_off(&git_repo, &stack, &config)?;
+        }
+        workflows::check_entry_sizes(&git_repo, &stack, &config, options.json)?;
+        check_warnings = workflows::check_submodule_pointers(&git_repo, &stack, options.json)?;
+    }
+    if !options.no_verify_policy {
+        workflows::ensure_policy(&config, &stack, options.draft, options.push_only)?;
+    }
+
+    // Stamp Change-Ids before anything is pushed so slot branches carry them too.
+    // jj commits have their own change-id; rewriting them behind jj's back would diverge them.
+    if config.change_id && !config.jj && !options.dry_run {
+        let added = change_id::ensure_change_ids(&git_repo, &config, &stack)?;
+        if added > 0 {
+            if !options.json {
+                println!(
+                    "🔖 Added Change-Id to {} commit{}",
+                    added,
+                    if added == 1 { "" } else { "s" }
+                );
+            }
+            stack = rediscover_stack(&git_repo, &config, &gh_client, &stack).await?;
+        }
+    }
+
+    let selected = match &options.commits {
+        Some(spec) => Some(select_entries(&git_repo, &stack, spec, options.push_only)?),
+        None => None,
+    };
+    if (options.title.is_some() || options.body.is_some())
+        && selected.as_ref().map_or(stack.len(), Vec::len) != 1
+    {
+        bail!("--title/--body can only be used when exporting a single commit");
     }
 
-    let owner = stack.entries[0].repo_owner.clone().context("Missing repo owner")?;
-    let repo_name = stack.entries[0].repo_name.clone().context("Missing repo name")?;
+    let owner = stack.entries[0]
+        .repo_owner
+        .clone()
+        .context("Missing repo owner")?;
+    let repo_name = stack.entries[0]
+        .repo_name
+        .clone()
+        .context("Missing repo name")?;
+
+    // Planning starts from the PRs' state, which discovery couldn't get
+    ensure_online(&stack, &config, "export")?;
 
     // Build the plan
-    let plan = build_export_plan(&git_repo, &config, &gh_client, &stack, &owner, &repo_name, &options).await?;
+    let mut plan = build_export_plan(
+        &git_repo, &config, &gh_client, &stack, &owner, &repo_name, options,
+    )
+    .await?;
+    plan.warnings = check_warnings;
+    if let Some(selected) = &selected {
+        restrict_plan(&mut plan, selected);
+    }
+
+    if !options.pr_only && !options.take_ownership {
+        ensure_slots_owned(
+            &stack,
+            &plan.refs_to_push,
+            repo::user_email(&git_repo).as_deref(),
+        )?;
+    }
 
     // Display plan (always, but styled differently for dry-run)
     if options.dry_run {
-        display_dry_run_plan(&plan, &options);
-        return Ok(());
+        if options.json {
+            println!(
+                "{}",
+                serde_json::to_string_pretty(&dry_run_plan_json(&stack, &plan, options))?
+            );
+        } else {
+            display_dry_run_plan(&plan, options);
+            if config.overlap_warnings {
+                let overlaps = overlap::stack_overlaps(&git_repo, &stack)?;
+                render_overlaps(&stack, &overlaps, false);
+            }
+        }
+        return Ok(None);
+    }
+
+    if !options.no_verify {
+        workflows::run_hooks(
+            &git_repo,
+            &config,
+            HookEvent::PreExport,
+            dry_run_plan_json(&stack, &plan, options),
+        )?;
     }
 
     // Execute the plan
-    execute_export_plan(
-        &git_repo,
-        &config,
-        &gh_client,
-        &mut stack,
-        &owner,
-        &repo_name,
-        &plan,
-        &options,
-    ).await?;
+    let mut warnings = plan.warnings.clone();
+    warnings.extend(
+        execute_export_plan(
+            &git_repo, &config, &gh_client, &mut stack, &owner, &repo_name, &plan, options,
+        )
+        .await?,
+    );
 
-    // Show final state
+    let context_warnings = context_in_pr_warnings(&stack);
     if !options.json {
-        println!("\n✨ Export complete!\n");
-        crate::commands::view().await?;
+        for warning in &context_warnings {
+            eprintln!("   ⚠ {}", warning);
+        }
     }
+    warnings.extend(context_warnings);
 
-    Ok(())
+    if options.keep_branches || config.keep_branches {
+        warnings.extend(sync_local_mirrors(&git_repo, &stack, &plan, options)?);
+    }
+
+    if let Some(mirror_remote) = &config.mirror_remote
+        && !options.pr_only
+    {
+        warnings.extend(push_to_mirror_remote(
+            &git_repo,
+            &config,
+            mirror_remote,
+            &plan,
+            options,
+        ));
+    }
+
+    let result = export_result_json(&stack);
+    workflows::run_hooks(&git_repo, &config, HookEvent::PostExport, result.clone())?;
+
+    // Rediscover to check what GitHub and the remote ended up with
+    let verified = {
+        let _span = profile::span("export: verify");
+        discover_stack(&git_repo, &config, &gh_client).await?
+    };
+    let mismatches = export_mismatches(&verified, &config.remote, &plan, options);
+
+    Ok(Some(ExportReport {
+        result,
+        warnings,
+        verified,
+        mismatches,
+    }))
 }
 
 // =============================================================================
@@ -134,16 +582,17 @@ pub async fn export(options: ExportOptions) -> Result<()> {
 async fn build_export_plan(
     git_repo: &Repository,
     config: &Config,
-    gh_client: &Octocrab,
+    gh_client: &Client,
     stack: &Stack,
     owner: &str,
     repo_name: &str,
     options: &ExportOptions,
 ) -> Result<ExportPlan> {
+    let _span = profile::span("export: build plan");
     let current_branch = &stack.current_branch;
 
     // Build slot assignments
-    let slot_assignments = build_slot_assignments(git_repo, stack)?;
+    let (slot_assignments, slot_conflicts) = build_slot_assignments(git_repo, stack)?;
 
     // Build metadata map for reorder detection
     let metadata_map = build_metadata_map(stack);
@@ -161,11 +610,11 @@ async fn build_export_plan(
         &stack.entries,
         &reorder_info,
         &metadata_map,
-        &config.base,
+        config.pr_base()?,
     );
 
     // Build refs to push
-    let refs_to_push = build_refs_to_push(git_repo, config, stack, &slot_assignments)?;
+    let refs_to_push = build_refs_to_push(stack, &slot_assignments)?;
 
     // Build PR actions
     let (prs_to_create, prs_to_update) = build_pr_actions(
@@ -179,19 +628,91 @@ async fn build_export_plan(
         &phase1_base_updates,
         &phase3_base_updates,
         options,
-    ).await?;
+    )
+    .await?;
+
+    // Entries dropped from the stack leave their slot branch and PR behind.
+    // Only those this clone dropped, and only when asked: the rest is for prune.
+    let prs_to_close = if options.push_only || !options.close_dropped {
+        Vec::new()
+    } else {
+        stack
+            .orphaned_prs
+            .iter()
+            .filter(|pr| {
+                pr.dropped_here && !slot_assignments.iter().any(|a| a.head_ref == pr.head_ref)
+            })
+            .cloned()
+            .collect()
+    };
 
     Ok(ExportPlan {
         slot_assignments,
+        slot_conflicts,
         refs_to_push,
         prs_to_create,
         prs_to_update,
         phase1_base_updates,
         phase3_base_updates,
+        prs_to_close,
+        warnings: Vec::new(),
     })
 }
 
-fn build_slot_assignments(git_repo: &Repository, stack: &Stack) -> Result<Vec<SlotAssignment>> {
+/// The entries `export <commits>` exports. Their PRs are based on the slot
+/// branches of the entries below, so those must be exported as they are.
+fn select_entries(
+    git_repo: &Repository,
+    stack: &Stack,
+    spec: &str,
+    push_only: bool,
+) -> Result<Vec<git2::Oid>> {
+    let selected = commit_ref::resolve_commit_refs(git_repo, stack, spec)?;
+    let Some(first) = stack.entries.iter().position(|e| selected.contains(&e.oid)) else {
+        bail!("'{}' selects no commits in the stack", spec);
+    };
+    if let Some(entry) = stack.entries[..first]
+        .iter()
+        .find(|e| e.status != UpdateStatus::UpToDate || (!push_only && e.pr_number.is_none()))
+    {
+        bail!(
+            "{} ({}) below '{}' isn't exported at its current commit. Export it first, or include it: 'git stk export ..{}'",
+            entry.short_sha,
+            entry.subject,
+            spec,
+            spec.rsplit("..")
+                .next()
+                .filter(|top| !top.is_empty())
+                .unwrap_or("last")
+        );
+    }
+    Ok(selected)
+}
+
+/// Narrow the plan to the `selected` entries (`export <commits>`). The others
+/// keep their slots but aren't pushed, nor are their PRs touched.
+fn restrict_plan(plan: &mut ExportPlan, selected: &[git2::Oid]) {
+    let heads: Vec<&str> = plan
+        .slot_assignments
+        .iter()
+        .filter(|a| selected.contains(&a.oid))
+        .map(|a| a.head_ref.as_str())
+        .collect();
+    plan.refs_to_push.retain(|r| selected.contains(&r.oid));
+    plan.prs_to_create.retain(|pr| selected.contains(&pr.oid));
+    plan.prs_to_update
+        .retain(|pr| heads.contains(&pr.head_ref.as_str()));
+    let prs: Vec<u64> = plan.prs_to_update.iter().map(|pr| pr.pr_number).collect();
+    plan.phase1_base_updates.retain(|(pr, _)| prs.contains(pr));
+    plan.phase3_base_updates.retain(|(pr, _)| prs.contains(pr));
+    plan.prs_to_close.clear();
+}
+
+fn build_slot_assignments(
+    git_repo: &Repository,
+    stack: &Stack,
+) -> Result<(Vec<SlotAssignment>, Vec<SlotConflict>)> {
+    let _span = profile::span("export: slot assignments");
     let mut slot_cache = slots::SlotCache::load(git_repo)?;
     let current_branch = &stack.current_branch;
 
@@ -203,11 +724,30 @@ fn build_slot_assignments(git_repo: &Repository, stack: &Stack) -> Result<Vec<Sl
     }
 
     let mut assignments = Vec::new();
+    let mut conflicts = Vec::new();
     for entry in &stack.entries {
         let (slot, is_new) = if let Some(ref existing_slot) = entry.slot {
             (existing_slot.clone(), false)
         } else {
-            (slot_cache.allocate(current_branch), true)
+            // The slot cache is local: skip slots whose remote branch was created elsewhere
+            let (slot, skipped) = slot_cache.allocate_skipping(current_branch, |slot| {
+                crate::stack::is_slot_taken_remotely(
+                    &stack.remote_refs,
+                    current_branch,
+                    slot,
+                    entry.oid,
+                )
+            });
+            for skipped_slot in skipped {
+                let head_ref = slots::generate_head_ref(current_branch, &skipped_slot);
+                let remote_oid = stack.remote_refs[&head_ref];
+                conflicts.push(SlotConflict {
+                    slot: skipped_slot,
+                    head_ref,
+                    remote_oid,
+                });
+            }
+            (slot, true)
         };
 
         let head_ref = slots::generate_head_ref(current_branch, &slot);
@@ -219,35 +759,42 @@ fn build_slot_assignments(git_repo: &Repository, stack: &Stack) -> Result<Vec<Sl
         });
     }
 
-    Ok(assignments)
+    Ok((assignments, conflicts))
 }
 
 fn build_metadata_map(stack: &Stack) -> HashMap<git2::Oid, CommitMetadata> {
     let mut map = HashMap::new();
     for entry in &stack.entries {
         if let Some(ref slot) = entry.slot {
-            map.insert(entry.oid, CommitMetadata {
-                pr: entry.pr_number,
-                slot: slot.clone(),
-            });
+            map.insert(
+                entry.oid,
+                CommitMetadata {
+                    pr: entry.pr_number,
+                    slot: slot.clone(),
+                    annotation: entry.annotation.clone(),
+                    last_export: entry.last_export.clone(),
+                    owner: entry.owner.clone(),
+                    signature: None,
+                },
+            );
         }
     }
     map
 }
 
 fn build_refs_to_push(
-    git_repo: &Repository,
-    config: &Config,
     stack: &Stack,
     slot_assignments: &[SlotAssignment],
 ) -> Result<Vec<RefToPush>> {
-    let commits: Vec<(git2::Oid, String)> = stack.entries
+    let commits: Vec<(git2::Oid, String)> = stack
+        .entries
         .iter()
         .zip(slot_assignments.iter())
         .map(|(entry, assignment)| (entry.oid, assignment.head_ref.clone()))
         .collect();
 
-    let refs_status = refs::check_commits_to_push(git_repo, &config.remote, &commits)?;
+    // Remote refs were fetched during discovery; no need for another ls-remote
+    let refs_status = refs::compare_commits_to_remote(&stack.remote_refs, &commits);
 
     Ok(slot_assignments
         .iter()
@@ -265,9 +812,10 @@ fn build_refs_to_push(
         .collect())
 }
 
+#[allow(clippy::too_many_arguments)]
 async fn build_pr_actions(
     git_repo: &Repository,
-    gh_client: &Octocrab,
+    gh_client: &Client,
     stack: &Stack,
     owner: &str,
     repo_name: &str,
@@ -275,40 +823,74 @@ async fn build_pr_actions(
     slot_assignments: &[SlotAssignment],
     phase1_updates: &[(u64, String)],
     phase3_updates: &[(u64, String)],
-    _options: &ExportOptions,
+    options: &ExportOptions,
 ) -> Result<(Vec<PrToCreate>, Vec<PrToUpdate>)> {
+    let _span = profile::span("export: PR actions");
     let mut to_create = Vec::new();
     let mut to_update = Vec::new();
 
+    // Entries without a noted PR may still have one on their slot branch
+    let unknown_heads: Vec<String> = stack
+        .entries
+        .iter()
+        .zip(slot_assignments)
+        .filter(|(entry, _)| entry.pr_number.is_none())
+        .map(|(_, assignment)| assignment.head_ref.clone())
+        .collect();
+    let prs_by_head =
+        queries::find_prs_by_heads(gh_client, owner, repo_name, &unknown_heads).await?;
+
     for (i, entry) in stack.entries.iter().enumerate() {
         let assignment = &slot_assignments[i];
         let base_ref = if i == 0 {
-            config.base.clone()
+            config.pr_base()?.to_string()
         } else {
             slot_assignments[i - 1].head_ref.clone()
         };
 
         let commit = git_repo.find_commit(entry.oid)?;
-        let title = commit.summary().context("Failed to get commit summary")?.to_string();
-        let body = extract_commit_body(commit.message().unwrap_or(""));
+        let subject = options
+            .title
+            .clone()
+            .unwrap_or_else(|| entry.subject.clone());
+        let title = build_pr_title(&subject, config, i + 1, stack.len());
+        let metadata = pr_stack_metadata(stack, assignment, i);
+        let body = callout::inject_metadata_block(
+            &options.body.clone().unwrap_or_else(|| {
+                build_pr_body(
+                    commit.message().unwrap_or(""),
+                    entry.annotation.as_deref(),
+                    config,
+                )
+            }),
+            &metadata,
+        );
 
-        // Check for existing PR
-        let existing_pr = if let Some(pr_number) = entry.pr_number {
-            queries::get_pr(gh_client, owner, repo_name, pr_number).await.ok()
-        } else {
-            queries::find_pr_by_head(gh_client, owner, repo_name, &assignment.head_ref).await?
+        // Check for existing PR: discovery fetched the noted ones, except
+        // those it couldn't match up (e.g. recovered from a slot branch)
+        let existing_pr = match (entry.pr_number, &entry.pr_base_ref) {
+            (Some(pr_number), Some(pr_base_ref)) => Some((pr_number, pr_base_ref.clone())),
+            (Some(pr_number), None) => queries::get_pr(gh_client, owner, repo_name, pr_number)
+                .await
+                .ok()
+                .map(|pr| (pr.number, pr.base_ref)),
+            (None, _) => prs_by_head
+                .get(&assignment.head_ref)
+                .map(|pr| (pr.number, pr.base_ref.clone())),
         };
 
-        if let Some(pr_info) = existing_pr {
-            let is_reordered = phase1_updates.iter().any(|(pr, _)| *pr == pr_info.number)
-                || phase3_updates.iter().any(|(pr, _)| *pr == pr_info.number);
+        if let Some((pr_number, pr_base_ref)) = existing_pr {
+            let is_reordered = phase1_updates.iter().any(|(pr, _)| *pr == pr_number)
+                || phase3_updates.iter().any(|(pr, _)| *pr == pr_number);
 
             to_update.push(PrToUpdate {
-                pr_number: pr_info.number,
+                pr_number,
                 head_ref: assignment.head_ref.clone(),
                 base_ref: base_ref.clone(),
+                title_override: options.title.as_ref().map(|_| title.clone()),
                 title,
-                needs_base_update: pr_info.base_ref != base_ref && !is_reordered,
+                body_override: options.body.as_ref().map(|_| body.clone()),
+                needs_base_update: pr_base_ref != base_ref && !is_reordered,
                 is_reordered,
             });
         } else {
@@ -360,7 +942,10 @@ fn display_pr_tree(items: &[PrDisplayItem], has_actions: &mut bool) {
 
     // Check if any items have actions
     *has_actions = items.iter().any(|item| {
-        matches!(item.action, PrAction::Create | PrAction::Update | PrAction::Reorder)
+        matches!(
+            item.action,
+            PrAction::Create | PrAction::Update | PrAction::Reorder
+        )
     });
 
     let rows: Vec<RowData> = items
@@ -394,7 +979,15 @@ fn display_pr_tree(items: &[PrDisplayItem], has_actions: &mut bool) {
                 String::new()
             };
 
-            RowData { action_icon, action_color, pr_str, base, head, title, draft_str }
+            RowData {
+                action_icon,
+                action_color,
+                pr_str,
+                base,
+                head,
+                title,
+                draft_str,
+            }
         })
         .collect();
 
@@ -426,43 +1019,178 @@ fn display_pr_tree(items: &[PrDisplayItem], has_actions: &mut bool) {
 
 fn shorten_branch(branch: &str) -> String {
     // Remove common prefixes to make it shorter
-    let short = branch
-        .strip_prefix("refs/heads/")
-        .unwrap_or(branch);
+    let short = branch.strip_prefix("refs/heads/").unwrap_or(branch);
 
     // If still long, take last component after --
-    if short.len() > 20 {
-        if let Some(pos) = short.rfind("--") {
-            return format!("…{}", &short[pos..]);
-        }
+    if short.len() > 20
+        && let Some(pos) = short.rfind("--")
+    {
+        return format!("…{}", &short[pos..]);
     }
     short.to_string()
 }
 
+/// Warn about slots skipped because their remote branch holds unrelated commits
+fn display_slot_conflicts(conflicts: &[SlotConflict]) {
+    if conflicts.is_empty() {
+        return;
+    }
+
+    eprintln!(
+        "  {} {}",
+        style("⚠").yellow(),
+        style("Slot conflicts (stale slot cache?)").yellow().bold()
+    );
+    for conflict in conflicts {
+        eprintln!(
+            "    {} {} {} already exists on the remote at {}, skipping",
+            style("SKIP").yellow().bold(),
+            style(&conflict.slot).yellow(),
+            style(&conflict.head_ref).cyan(),
+            style(format!("{:.7}", conflict.remote_oid)).red().dim()
+        );
+    }
+    eprintln!();
+}
+
+/// The export plan as JSON, for bots validating what an export would do
+/// (`--dry-run --json`) and for pre-export hooks
+fn dry_run_plan_json(
+    stack: &Stack,
+    plan: &ExportPlan,
+    options: &ExportOptions,
+) -> serde_json::Value {
+    let base_updates = |updates: &[(u64, String)]| -> Vec<serde_json::Value> {
+        updates
+            .iter()
+            .map(|(pr, base)| json!({ "pr": pr, "base": base }))
+            .collect()
+    };
+
+    let refs_to_push: Vec<_> = if options.pr_only {
+        Vec::new()
+    } else {
+        plan.refs_to_push
+            .iter()
+            .map(|r| json!({ "commit": r.oid.to_string(), "head_ref": r.head_ref, "needs_push": r.needs_push }))
+            .collect()
+    };
+
+    let (prs_to_create, prs_to_update): (Vec<_>, Vec<_>) = if options.push_only {
+        (Vec::new(), Vec::new())
+    } else {
+        (
+            plan.prs_to_create
+                .iter()
+                .map(|p| {
+                    json!({
+                        "commit": p.oid.to_string(),
+                        "head_ref": p.head_ref,
+                        "base_ref": p.base_ref,
+                        "title": p.title,
+                        "body": p.body,
+                        "draft": options.draft,
+                    })
+                })
+                .collect(),
+            plan.prs_to_update
+                .iter()
+                .map(|p| {
+                    json!({
+                        "pr": p.pr_number,
+                        "head_ref": p.head_ref,
+                        "base_ref": p.base_ref,
+                        "title": p.title_override.as_ref().unwrap_or(&p.title),
+                        "body": p.body_override,
+                        "needs_base_update": p.needs_base_update,
+                        "reordered": p.is_reordered,
+                    })
+                })
+                .collect(),
+        )
+    };
+
+    json!({
+        "branch": stack.current_branch,
+        "base": stack.base_branch,
+        "slot_assignments": plan.slot_assignments.iter().map(|a| json!({
+            "commit": a.oid.to_string(),
+            "slot": a.slot,
+            "head_ref": a.head_ref,
+            "new": a.is_new,
+        })).collect::<Vec<_>>(),
+        "slot_conflicts": plan.slot_conflicts.iter().map(|c| json!({
+            "slot": c.slot,
+            "head_ref": c.head_ref,
+            "remote_commit": c.remote_oid.to_string(),
+        })).collect::<Vec<_>>(),
+        "phase1_base_updates": base_updates(&plan.phase1_base_updates),
+        "refs_to_push": refs_to_push,
+        "prs_to_create": prs_to_create,
+        "prs_to_update": prs_to_update,
+        "phase3_base_updates": base_updates(&plan.phase3_base_updates),
+        "prs_to_close": plan.prs_to_close.iter().map(|p| json!({
+            "pr": p.pr_number,
+            "head_ref": p.head_ref,
+            "title": p.title,
+        })).collect::<Vec<_>>(),
+        "warnings": plan.warnings,
+    })
+}
+
+/// The stack's PRs after an export, for post-export hooks
+fn export_result_json(stack: &Stack) -> serde_json::Value {
+    json!({
+        "branch": stack.current_branch,
+        "base": stack.base_branch,
+        "prs": stack.entries.iter().filter_map(|e| {
+            let pr = e.pr_number?;
+            Some(json!({
+                "commit": e.oid.to_string(),
+                "subject": e.subject,
+                "head_ref": e.head_ref,
+                "pr": pr,
+                "url": e.pr_url(),
+            }))
+        }).collect::<Vec<_>>(),
+    })
+}
+
 fn display_dry_run_plan(plan: &ExportPlan, options: &ExportOptions) {
     println!();
-    println!("{}", style("╔══════════════════════════════════════════════════════════════╗").cyan());
-    println!("{}  {}  {}",
+    println!(
+        "{}",
+        style("╔══════════════════════════════════════════════════════════════╗").cyan()
+    );
+    println!(
+        "{}  {}  {}",
         style("║").cyan(),
         style("DRY RUN").cyan().bold(),
         style("No changes will be made                            ║").cyan()
     );
-    println!("{}", style("╚══════════════════════════════════════════════════════════════╝").cyan());
+    println!(
+        "{}",
+        style("╚══════════════════════════════════════════════════════════════╝").cyan()
+    );
     println!();
 
     let mut has_actions = false;
 
+    display_slot_conflicts(&plan.slot_conflicts);
+
     // Section 1: Slot Assignments
     let new_slots: Vec<_> = plan.slot_assignments.iter().filter(|s| s.is_new).collect();
     if !new_slots.is_empty() {
         has_actions = true;
-        println!("  {} {}",
+        println!(
+            "  {} {}",
             style("📦").cyan(),
             style("Slot Assignments").bold()
         );
         println!("  {}", style("─".repeat(50)).dim());
         for slot in &new_slots {
-            println!("    {} {} {} {}",
+            println!(
+                "    {} {} {} {}",
                 style("NEW").magenta().bold(),
                 style(&slot.slot).yellow().bold(),
                 style("→").dim(),
@@ -475,13 +1203,15 @@ fn display_dry_run_plan(plan: &ExportPlan, options: &ExportOptions) {
     // Section 2: Phase 1 Base Updates (Pre-push reorder)
     if !plan.phase1_base_updates.is_empty() {
         has_actions = true;
-        println!("  {} {}",
+        println!(
+            "  {} {}",
             style("🔄").cyan(),
             style("Pre-Push Base Updates (Reorder Safety)").bold()
         );
         println!("  {}", style("─".repeat(50)).dim());
         for (pr_num, new_base) in &plan.phase1_base_updates {
-            println!("    PR {} {} {}",
+            println!(
+                "    PR {} {} {}",
                 style(format!("#{}", pr_num)).yellow().bold(),
                 style("→ base:").dim(),
                 style(new_base).cyan()
@@ -495,21 +1225,20 @@ fn display_dry_run_plan(plan: &ExportPlan, options: &ExportOptions) {
         let refs_needing_push: Vec<_> = plan.refs_to_push.iter().filter(|r| r.needs_push).collect();
         let refs_up_to_date: Vec<_> = plan.refs_to_push.iter().filter(|r| !r.needs_push).collect();
 
-        println!("  {} {}",
-            style("🚀").cyan(),
-            style("Push Refs").bold()
-        );
+        println!("  {} {}", style("🚀").cyan(), style("Push Refs").bold());
         println!("  {}", style("─".repeat(50)).dim());
 
         if refs_needing_push.is_empty() {
-            println!("    {} {}",
+            println!(
+                "    {} {}",
                 style("✓").green(),
                 style("All refs up-to-date").dim()
             );
         } else {
             has_actions = true;
             for r in &refs_needing_push {
-                println!("    {} {} {}",
+                println!(
+                    "    {} {} {}",
                     style("PUSH").green().bold(),
                     style(&r.head_ref).cyan(),
                     style("--force").yellow().dim()
@@ -519,7 +1248,8 @@ fn display_dry_run_plan(plan: &ExportPlan, options: &ExportOptions) {
 
         if !refs_up_to_date.is_empty() && options.verbose {
             for r in &refs_up_to_date {
-                println!("    {} {} {}",
+                println!(
+                    "    {} {} {}",
                     style("SKIP").dim(),
                     style(&r.head_ref).dim(),
                     style("(up-to-date)").dim()
@@ -534,7 +1264,8 @@ fn display_dry_run_plan(plan: &ExportPlan, options: &ExportOptions) {
         let total_prs = plan.prs_to_create.len() + plan.prs_to_update.len();
 
         if total_prs > 0 {
-            println!("  {} {} {}",
+            println!(
+                "  {} {} {}",
                 style("📝").cyan(),
                 style("Pull Requests").bold(),
                 style(format!("({} total)", total_prs)).dim()
@@ -556,7 +1287,11 @@ fn display_dry_run_plan(plan: &ExportPlan, options: &ExportOptions) {
                         base_ref: create.base_ref.clone(),
                         is_draft: options.draft,
                     });
-                } else if let Some(update) = plan.prs_to_update.iter().find(|p| p.head_ref == assignment.head_ref) {
+                } else if let Some(update) = plan
+                    .prs_to_update
+                    .iter()
+                    .find(|p| p.head_ref == assignment.head_ref)
+                {
                     let action = if update.is_reordered {
                         PrAction::Reorder
                     } else if update.needs_base_update {
@@ -585,13 +1320,15 @@ fn display_dry_run_plan(plan: &ExportPlan, options: &ExportOptions) {
     // Section 5: Phase 3 Base Updates (Post-push reorder)
     if !plan.phase3_base_updates.is_empty() {
         has_actions = true;
-        println!("  {} {}",
+        println!(
+            "  {} {}",
             style("🔗").cyan(),
             style("Post-Push Base Updates (Final Chain)").bold()
         );
         println!("  {}", style("─".repeat(50)).dim());
         for (pr_num, new_base) in &plan.phase3_base_updates {
-            println!("    PR {} {} {}",
+            println!(
+                "    PR {} {} {}",
                 style(format!("#{}", pr_num)).yellow().bold(),
                 style("→ base:").dim(),
                 style(new_base).cyan()
@@ -600,15 +1337,38 @@ fn display_dry_run_plan(plan: &ExportPlan, options: &ExportOptions) {
         println!();
     }
 
+    // Section 5b: PRs of entries dropped from the stack
+    if !plan.prs_to_close.is_empty() {
+        has_actions = true;
+        println!(
+            "  {} {}",
+            style("🔒").cyan(),
+            style("Close PRs of Removed Entries").bold()
+        );
+        println!("  {}", style("─".repeat(50)).dim());
+        for pr_close in &plan.prs_to_close {
+            println!(
+                "    {} PR {} {} {}",
+                style("CLOSE").red().bold(),
+                style(format!("#{}", pr_close.pr_number)).yellow().bold(),
+                pr_close.title,
+                style(format!("(slot {})", pr_close.slot)).dim()
+            );
+        }
+        println!();
+    }
+
     // Section 6: Callout Updates (only for multi-PR stacks)
     let total_prs = plan.prs_to_create.len() + plan.prs_to_update.len();
     if !options.push_only && total_prs > 1 {
-        println!("  {} {}",
+        println!(
+            "  {} {}",
             style("💬").cyan(),
             style("PR Description Updates").bold()
         );
         println!("  {}", style("─".repeat(50)).dim());
-        println!("    {} {} PR descriptions with stack callout",
+        println!(
+            "    {} {} PR descriptions with stack callout",
             style("UPDATE").blue().bold(),
             total_prs
         );
@@ -616,17 +1376,22 @@ fn display_dry_run_plan(plan: &ExportPlan, options: &ExportOptions) {
     }
 
     // Summary
-    println!("{}", style("══════════════════════════════════════════════════════════════").cyan());
+    println!(
+        "{}",
+        style("══════════════════════════════════════════════════════════════").cyan()
+    );
 
     if has_actions {
         println!();
-        println!("  {} Run {} to execute these changes.",
+        println!(
+            "  {} Run {} to execute these changes.",
             style("→").dim(),
             style("git stk export").green().bold()
         );
     } else {
         println!();
-        println!("  {} {}",
+        println!(
+            "  {} {}",
             style("✓").green(),
             style("Everything is up-to-date!").green()
         );
@@ -638,16 +1403,31 @@ fn display_dry_run_plan(plan: &ExportPlan, options: &ExportOptions) {
 // Plan Execution
 // =============================================================================
 
+#[allow(clippy::too_many_arguments)]
 async fn execute_export_plan(
     git_repo: &Repository,
     config: &Config,
-    gh_client: &Octocrab,
+    gh_client: &Client,
     stack: &mut Stack,
     owner: &str,
     repo_name: &str,
     plan: &ExportPlan,
     options: &ExportOptions,
-) -> Result<()> {
+) -> Result<Vec<String>> {
+    let _span = profile::span("export: execute");
+    if options.atomic && !plan.phase1_base_updates.is_empty() {
+        bail!(
+            "--atomic cannot be used while the stack is being reordered: PR bases must change before the push. Run a regular export."
+        );
+    }
+    // The existing PRs as they are, for a rollback to put their base, title and body back
+    let mut originals = Vec::new();
+    if options.atomic && !options.push_only {
+        for update in &plan.prs_to_update {
+            originals.push(queries::get_pr(gh_client, owner, repo_name, update.pr_number).await?);
+        }
+    }
+
     // Step 1: Save slot assignments
     save_slot_assignments(git_repo, config, plan, stack, options)?;
 
@@ -658,76 +1438,681 @@ async fn execute_export_plan(
 
     // Step 3: Push refs
     if !options.pr_only {
-        execute_push_refs(git_repo, config, plan, options)?;
+        // Stamped first: the push carries the archive refs of the new revisions
+        stamp_exports(
+            stack,
+            plan,
+            repo::user_email(git_repo),
+            options.take_ownership,
+        );
+        execute_push_refs(git_repo, config, stack, plan, options)?;
     }
 
     // Step 4: Create/update PRs
+    let mut warnings = Vec::new();
     if !options.push_only {
-        let pr_urls = execute_pr_operations(git_repo, config, gh_client, stack, owner, repo_name, plan, options).await?;
-
-        // Step 5: Base updates (regular + phase3 reorder finalization)
-        execute_base_updates(gh_client, owner, repo_name, plan, options).await?;
-
-        // Step 6: Update PR descriptions with callouts
-        execute_callout_updates(git_repo, gh_client, stack, owner, repo_name, options).await?;
+        // Filled as the updates go, so a rollback knows what a failure left behind
+        let mut operations = PrOperations::default();
+        let result = execute_github_updates(
+            git_repo,
+            config,
+            gh_client,
+            stack,
+            owner,
+            repo_name,
+            plan,
+            options,
+            &mut operations,
+        )
+        .await;
+        if options.atomic
+            && let Err(e) = result
+        {
+            rollback_atomic_export(
+                git_repo,
+                config,
+                gh_client,
+                stack,
+                owner,
+                repo_name,
+                plan,
+                &originals,
+                &operations,
+            )
+            .await?;
+            return Err(e);
+        }
+        result?;
+        warnings = operations.warnings;
+
+        if !options.pr_only {
+            if config.range_diff_comments {
+                post_range_diff_comments(
+                    git_repo, config, gh_client, stack, owner, repo_name, plan, options,
+                )
+                .await;
+            }
+            record_exports(git_repo, config, stack, plan)?;
+        }
 
-        // Step 7: Push notes
-        push_notes_to_remote(git_repo, config, options)?;
+        // Step 8: Push notes
+        push_notes_to_remote(git_repo, config, gh_client, options).await?;
 
-        // Step 8: Open URLs if requested
+        // Step 9: Open URLs if requested
         if options.open {
-            open_pr_urls(&pr_urls);
+            open_pr_urls(&operations.urls);
         }
     }
 
-    Ok(())
+    Ok(warnings)
 }
 
-fn save_slot_assignments(
+/// Keep the local `stk/<branch>/<slot>` mirrors at the exported commits;
+/// returns the mirrors left as they were as warnings
+fn sync_local_mirrors(
     git_repo: &Repository,
-    config: &Config,
-    plan: &ExportPlan,
     stack: &Stack,
+    plan: &ExportPlan,
     options: &ExportOptions,
-) -> Result<()> {
-    let new_slots: Vec<_> = plan.slot_assignments.iter().filter(|s| s.is_new).collect();
-    if !new_slots.is_empty() && !options.json {
-        println!("📦 Assigning {} new slot{}...", new_slots.len(), if new_slots.len() == 1 { "" } else { "s" });
-    }
-
-    let mut slot_cache = slots::SlotCache::load(git_repo)?;
-    let current_branch = &stack.current_branch;
-
-    for assignment in &plan.slot_assignments {
-        slot_cache.ensure_slot(current_branch, &assignment.slot);
-    }
-    slot_cache.save(git_repo)?;
+) -> Result<Vec<String>> {
+    // A mirror still at the slot's commit before this export wasn't worked on
+    let slots: Vec<(git2::Oid, String, Option<git2::Oid>)> = plan
+        .slot_assignments
+        .iter()
+        .map(|a| {
+            (
+                a.oid,
+                a.slot.clone(),
+                stack.remote_refs.get(&a.head_ref).copied(),
+            )
+        })
+        .collect();
+    let sync = mirror::sync_mirrors(git_repo, &stack.current_branch, &slots)?;
+    let warnings: Vec<String> = sync
+        .skipped
+        .iter()
+        .map(|skipped| format!("Left {}", skipped))
+        .collect();
 
-    // Write notes for slot assignments
-    if !options.push_only {
-        for (entry, assignment) in stack.entries.iter().zip(plan.slot_assignments.iter()) {
-            let metadata = CommitMetadata {
-                pr: entry.pr_number,
-                slot: assignment.slot.clone(),
-            };
-            notes::write_note(git_repo, entry.oid, &metadata, &config.notes_ref)?;
+    if !options.json {
+        if sync.updated > 0 || sync.removed > 0 {
+            println!(
+                "🪞 Local mirrors under {}: {} updated, {} removed",
+                style(mirror::mirror_prefix(&stack.current_branch)).cyan(),
+                sync.updated,
+                sync.removed
+            );
+        }
+        for warning in &warnings {
+            eprintln!("   ⚠ {}", warning);
         }
     }
-
-    Ok(())
+    Ok(warnings)
 }
 
-async fn execute_phase1_updates(
-    gh_client: &Octocrab,
-    owner: &str,
-    repo_name: &str,
+/// Push the slot branches, and the notes when export pushed them, to the
+/// backup remote (`git-stk.mirrorRemote`). The export already went through on
+/// the main remote, so failures are only reported, as warnings.
+fn push_to_mirror_remote(
+    git_repo: &Repository,
+    config: &Config,
+    mirror_remote: &str,
+    plan: &ExportPlan,
+    options: &ExportOptions,
+) -> Vec<String> {
+    // Every slot, not only the ones pushed now: the mirror may have missed earlier exports
+    let commits: Vec<(git2::Oid, String)> = plan
+        .refs_to_push
+        .iter()
+        .map(|r| (r.oid, r.head_ref.clone()))
+        .collect();
+    let push_notes =
+        !options.push_only && options.push_notes.unwrap_or(config.push_notes) != PushNotes::Never;
+    if commits.is_empty() && !push_notes {
+        return Vec::new();
+    }
+
+    if !options.json {
+        println!("🪞 Mirroring to {}...", style(mirror_remote).cyan());
+    }
+    let mut failures = Vec::new();
+    if !commits.is_empty() {
+        let refspecs = refs::build_refspecs_from_oids(&commits);
+        let mut capabilities = refs::RemoteCapabilities::load(git_repo, mirror_remote);
+        // Hooks already ran for the main remote
+        match refs::push_refs(
+            git_repo,
+            mirror_remote,
+            &refspecs,
+            &mut capabilities,
+            true,
+            config.max_refs_per_push,
+            &config.transport,
+            &mut |_| {},
+        ) {
+            Ok(result) if result.success => {}
+            Ok(result) => failures.push(format!(
+                "slot branches: {}",
+                result.error.unwrap_or_default()
+            )),
+            Err(e) => failures.push(format!("slot branches: {}", e)),
+        }
+    }
+    if push_notes
+        && let Err(e) = notes::push_notes(
+            git_repo,
+            mirror_remote,
+            &config.notes_ref,
+            &config.notes_ref,
+            true,
+            &config.transport,
+        )
+    {
+        failures.push(format!("notes: {}", e));
+    }
+
+    let warnings: Vec<String> = failures
+        .iter()
+        .map(|failure| format!("Failed to mirror {} to {}", failure.trim(), mirror_remote))
+        .collect();
+    if !options.json {
+        if warnings.is_empty() {
+            println!("   ✓ Done");
+        }
+        for warning in &warnings {
+            eprintln!("   ⚠ {}", warning);
+        }
+    }
+    warnings
+}
+
+/// Steps 4-7: every GitHub mutation of the export
+#[allow(clippy::too_many_arguments)]
+async fn execute_github_updates(
+    git_repo: &Repository,
+    config: &Config,
+    gh_client: &Client,
+    stack: &mut Stack,
+    owner: &str,
+    repo_name: &str,
+    plan: &ExportPlan,
+    options: &ExportOptions,
+    operations: &mut PrOperations,
+) -> Result<()> {
+    execute_pr_operations(
+        git_repo, config, gh_client, stack, owner, repo_name, plan, options, operations,
+    )
+    .await?;
+    notify_created_prs(
+        git_repo,
+        config,
+        stack,
+        owner,
+        repo_name,
+        plan,
+        &operations.created,
+        options,
+    )
+    .await;
+
+    // Step 4b: Required policy labels on the existing PRs (new ones got them when created)
+    let existing: Vec<u64> = plan.prs_to_update.iter().map(|u| u.pr_number).collect();
+    let label_warnings =
+        workflows::policy::add_required_labels(config, gh_client, owner, repo_name, &existing)
+            .await;
+    if !options.json {
+        for warning in &label_warnings {
+            eprintln!("   ⚠ {}", warning);
+        }
+    }
+    operations.warnings.extend(label_warnings);
+
+    // Step 5: Base updates (regular + phase3 reorder finalization)
+    execute_base_updates(gh_client, owner, repo_name, plan, options).await?;
+
+    // Step 5b: Close PRs of removed entries, now that nothing is based on them
+    let closure_warnings = execute_pr_closures(
+        git_repo, config, gh_client, stack, owner, repo_name, plan, options,
+    )
+    .await?;
+    operations.warnings.extend(closure_warnings);
+
+    // Step 6: Update PR descriptions with callouts
+    execute_callout_updates(
+        git_repo,
+        config,
+        gh_client,
+        stack,
+        &plan.slot_assignments,
+        owner,
+        repo_name,
+        options,
+    )
+    .await?;
+
+    // Step 6b: Keep position prefixes in PR titles in sync
+    execute_title_updates(
+        git_repo, config, gh_client, stack, owner, repo_name, options,
+    )
+    .await?;
+
+    // Step 7: Post/update stack summary on the top PR
+    // Single-commit stacks have nothing to summarize
+    if (options.summary_comment || config.summary_comment) && stack.len() > 1 {
+        execute_summary_comment(gh_client, stack, owner, repo_name, options).await?;
+    }
+
+    Ok(())
+}
+
+/// Undo an `--atomic` export after a GitHub update failed: close the PRs it
+/// created, restore the base, title and body of the existing ones from
+/// `originals` and of the adopted ones, and put the pushed slot branches back
+/// where they were
+#[allow(clippy::too_many_arguments)]
+async fn rollback_atomic_export(
+    git_repo: &Repository,
+    config: &Config,
+    gh_client: &Client,
+    stack: &Stack,
+    owner: &str,
+    repo_name: &str,
+    plan: &ExportPlan,
+    originals: &[queries::PrInfo],
+    operations: &PrOperations,
+) -> Result<()> {
+    eprintln!("\n{} Rolling back export...", style("↩️").yellow());
+
+    let (restore, close) = rollback_selection(originals, operations);
+    for pr in restore {
+        match mutations::update_pull_request(
+            gh_client,
+            owner,
+            repo_name,
+            pr.number,
+            Some(&pr.base_ref),
+            Some(&pr.title),
+            Some(&pr.body),
+        )
+        .await
+        {
+            Ok(()) => eprintln!("   ✓ Restored PR #{}", pr.number),
+            Err(e) => eprintln!("   ⚠ Failed to restore PR #{}: {}", pr.number, e),
+        }
+    }
+
+    for (oid, pr_number) in close {
+        match mutations::close_pull_request(gh_client, owner, repo_name, pr_number).await {
+            Ok(_) => eprintln!("   ✓ Closed PR #{}", pr_number),
+            Err(e) => eprintln!("   ⚠ Failed to close PR #{}: {}", pr_number, e),
+        }
+
+        if let Some(mut metadata) = notes::read_note(git_repo, oid, &config.notes_ref)? {
+            metadata.pr = None;
+            notes::write_note(
+                git_repo,
+                oid,
+                &metadata,
+                &config.notes_ref,
+                config.sign_notes,
+            )?;
+        }
+    }
+
+    let restores: Vec<(String, Option<git2::Oid>, git2::Oid)> = plan
+        .refs_to_push
+        .iter()
+        .filter(|r| r.needs_push)
+        .map(|r| {
+            (
+                r.head_ref.clone(),
+                stack.remote_refs.get(&r.head_ref).copied(),
+                r.oid,
+            )
+        })
+        .collect();
+    refs::restore_remote_refs(git_repo, &config.remote, &restores, &config.transport)?;
+    if !restores.is_empty() {
+        eprintln!(
+            "   ✓ Restored {} remote branch{}",
+            restores.len(),
+            if restores.len() == 1 { "" } else { "es" }
+        );
+    }
+
+    Ok(())
+}
+
+/// The PRs a rollback puts back as they were, the existing ones and those
+/// adopted, and the ones it closes: only those the export opened
+fn rollback_selection<'a>(
+    originals: &'a [queries::PrInfo],
+    operations: &'a PrOperations,
+) -> (Vec<&'a queries::PrInfo>, Vec<(git2::Oid, u64)>) {
+    let restore = originals.iter().chain(&operations.adopted).collect();
+    (restore, operations.created.clone())
+}
+
+/// Comment on each existing PR whose slot branch was just force-pushed with the
+/// range-diff between the old and new commit, collapsed, like a new patchset
+/// in Gerrit. Rebases that left the patch unchanged get no comment.
+#[allow(clippy::too_many_arguments)]
+async fn post_range_diff_comments(
+    git_repo: &Repository,
+    config: &Config,
+    gh_client: &Client,
+    stack: &Stack,
+    owner: &str,
+    repo_name: &str,
+    plan: &ExportPlan,
+    options: &ExportOptions,
+) {
+    for update in &plan.prs_to_update {
+        let Some(pushed) = plan
+            .refs_to_push
+            .iter()
+            .find(|r| r.needs_push && r.head_ref == update.head_ref)
+        else {
+            continue;
+        };
+        let Some(&old) = stack.remote_refs.get(&pushed.head_ref) else {
+            continue;
+        };
+        // The replaced commit may have been pushed from another machine
+        if git_repo.find_commit(old).is_err() {
+            let _ = refs::fetch_refspecs(
+                git_repo,
+                &config.remote,
+                &[old.to_string()],
+                &config.transport,
+            );
+        }
+
+        let diff = match range_diff::range_diff(git_repo, old, pushed.oid) {
+            Ok(diff) if range_diff::is_unchanged(&diff) => continue,
+            Ok(diff) => diff,
+            Err(e) => {
+                eprintln!(
+                    "  Warning: No range-diff for PR #{}: {}",
+                    update.pr_number, e
+                );
+                continue;
+            }
+        };
+        let body = range_diff_comment(old, pushed.oid, &diff);
+        match mutations::add_pr_comment(gh_client, owner, repo_name, update.pr_number, &body).await
+        {
+            Ok(()) if !options.json => {
+                println!("  💬 Commented the range-diff on PR #{}", update.pr_number)
+            }
+            Ok(()) => {}
+            Err(e) => eprintln!(
+                "  Warning: Failed to comment the range-diff on PR #{}: {}",
+                update.pr_number, e
+            ),
+        }
+    }
+}
+
+/// Comment body for a force-push, with the range-diff in a collapsed section
+/// (truncated to stay within GitHub's comment size limit)
+fn range_diff_comment(old: git2::Oid, new: git2::Oid, diff: &str) -> String {
+    const MAX_DIFF: usize = 60_000;
+    let mut diff = diff.trim_end().to_string();
+    if diff.len() > MAX_DIFF {
+        let mut end = MAX_DIFF;
+        while !diff.is_char_boundary(end) {
+            end -= 1;
+        }
+        diff.truncate(end);
+        diff.push_str("\n… (truncated, run `git stk interdiff` locally for the full diff)");
+    }
+    format!(
+        "Force-pushed `{:.7}` → `{:.7}`\n\n<details>\n<summary>Range-diff</summary>\n\n```diff\n{}\n```\n\n</details>",
+        old, new, diff
+    )
+}
+
+/// Stamp the entries pushed by this export with the time, commit and next
+/// revision number, so view can tell how stale each slot branch is and the
+/// callouts name the revision (also across machines, through the notes)
+fn stamp_exports(
+    stack: &mut Stack,
+    plan: &ExportPlan,
+    user_email: Option<String>,
+    take_ownership: bool,
+) {
+    let now = timeline::now_seconds();
+    for pushed in plan.refs_to_push.iter().filter(|r| r.needs_push) {
+        let Some(entry) = stack.entries.iter_mut().find(|e| e.oid == pushed.oid) else {
+            continue;
+        };
+        // A push that found no remote branch keeps the last export as the previous version
+        let previous = stack
+            .remote_refs
+            .get(&pushed.head_ref)
+            .map(|oid| oid.to_string())
+            .or_else(|| entry.last_export.as_ref().map(|last| last.oid.clone()))
+            .filter(|previous| *previous != pushed.oid.to_string());
+        let revision = entry.last_export.as_ref().map_or(0, |last| last.revision) + 1;
+        entry.last_export = Some(LastExport {
+            at: now,
+            oid: pushed.oid.to_string(),
+            previous,
+            revision,
+        });
+        if entry.owner.is_none() || take_ownership {
+            entry.owner = user_email.clone();
+        }
+    }
+}
+
+/// A warning per PR whose diff includes context commits (`git-stk.onlyMine`,
+/// `git-stk.onlySince`): reviewers see others' changes in it
+fn context_in_pr_warnings(stack: &Stack) -> Vec<String> {
+    stack
+        .entries
+        .iter()
+        .filter_map(|entry| {
+            let commits: Vec<_> = stack
+                .context_in_pr(entry)
+                .map(|c| c.short_sha.as_str())
+                .collect();
+            if commits.is_empty() {
+                return None;
+            }
+            let pr = entry.pr_number.map_or_else(
+                || format!("The PR of {}", entry.short_sha),
+                |n| format!("PR #{}", n),
+            );
+            Some(format!(
+                "{} also shows commits by others: {}",
+                pr,
+                commits.join(", ")
+            ))
+        })
+        .collect()
+}
+
+/// Refuse to force-push slot branches owned by someone else (`CommitMetadata::owner`)
+fn ensure_slots_owned(
+    stack: &Stack,
+    refs_to_push: &[RefToPush],
+    user_email: Option<&str>,
+) -> Result<()> {
+    let foreign = foreign_owned_slots(stack, refs_to_push, user_email);
+    if foreign.is_empty() {
+        return Ok(());
+    }
+    let list = foreign
+        .iter()
+        .map(|(entry, owner)| {
+            format!(
+                "  {} {} (owned by {})",
+                entry.short_sha, entry.subject, owner
+            )
+        })
+        .collect::<Vec<_>>()
+        .join("\n");
+    bail!(
+        "Export would force-push slot branches owned by someone else:\n{}\n\
+         Coordinate with them, or export with --take-ownership to push anyway and own these slots from now on.",
+        list
+    );
+}
+
+/// Entries whose existing slot branch would be force-pushed but is owned by
+/// another email, with that owner
+fn foreign_owned_slots<'a>(
+    stack: &'a Stack,
+    refs_to_push: &[RefToPush],
+    user_email: Option<&str>,
+) -> Vec<(&'a Entry, &'a str)> {
+    refs_to_push
+        .iter()
+        .filter(|r| r.needs_push && stack.remote_refs.contains_key(&r.head_ref))
+        .filter_map(|r| {
+            let entry = stack.entries.iter().find(|e| e.oid == r.oid)?;
+            let owner = entry.owner.as_deref()?;
+            (!user_email.is_some_and(|me| me.eq_ignore_ascii_case(owner))).then_some((entry, owner))
+        })
+        .collect()
+}
+
+/// The revisions stamped by `stamp_exports`, each kept reachable after later
+/// force-pushes under `refs/git-stk/archive/<branch>/<slot>/v<N>`
+fn archive_refs(stack: &Stack, plan: &ExportPlan) -> Vec<(git2::Oid, String)> {
+    plan.refs_to_push
+        .iter()
+        .filter(|r| r.needs_push)
+        .filter_map(|pushed| {
+            let entry = stack.entries.iter().find(|e| e.oid == pushed.oid)?;
+            let assignment = plan.slot_assignments.iter().find(|a| a.oid == entry.oid)?;
+            let last = entry.last_export.as_ref()?;
+            Some((
+                entry.oid,
+                slots::archive_ref(&stack.current_branch, &assignment.slot, last.revision),
+            ))
+        })
+        .collect()
+}
+
+/// Write the stamps of `stamp_exports` to the notes, and the archive refs
+/// pushed with the slot branches locally
+fn record_exports(
+    git_repo: &Repository,
+    config: &Config,
+    stack: &Stack,
+    plan: &ExportPlan,
+) -> Result<()> {
+    for (oid, archive_ref) in archive_refs(stack, plan) {
+        git_repo
+            .reference(
+                &archive_ref,
+                oid,
+                true,
+                "git-stk: archive exported revision",
+            )
+            .with_context(|| format!("Failed to write {}", archive_ref))?;
+    }
+    for pushed in plan.refs_to_push.iter().filter(|r| r.needs_push) {
+        let Some(entry) = stack.entries.iter().find(|e| e.oid == pushed.oid) else {
+            continue;
+        };
+        let Some(assignment) = plan.slot_assignments.iter().find(|a| a.oid == entry.oid) else {
+            continue;
+        };
+        let metadata = CommitMetadata {
+            pr: entry.pr_number,
+            slot: assignment.slot.clone(),
+            annotation: entry.annotation.clone(),
+            last_export: entry.last_export.clone(),
+            owner: entry.owner.clone(),
+            signature: None,
+        };
+        notes::write_note(
+            git_repo,
+            entry.oid,
+            &metadata,
+            &config.notes_ref,
+            config.sign_notes,
+        )?;
+    }
+    Ok(())
+}
+
+fn save_slot_assignments(
+    git_repo: &Repository,
+    config: &Config,
     plan: &ExportPlan,
+    stack: &Stack,
     options: &ExportOptions,
 ) -> Result<()> {
     if !options.json {
-        println!("🔄 Preparing {} PR{} for reorder...", plan.phase1_base_updates.len(), if plan.phase1_base_updates.len() == 1 { "" } else { "s" });
+        display_slot_conflicts(&plan.slot_conflicts);
     }
-    mutations::batch_update_pr_bases(gh_client, owner, repo_name, &plan.phase1_base_updates).await?;
+
+    let new_slots: Vec<_> = plan.slot_assignments.iter().filter(|s| s.is_new).collect();
+    if !new_slots.is_empty() && !options.json {
+        println!(
+            "📦 Assigning {} new slot{}...",
+            new_slots.len(),
+            if new_slots.len() == 1 { "" } else { "s" }
+        );
+    }
+
+    let mut slot_cache = slots::SlotCache::load(git_repo)?;
+    let current_branch = &stack.current_branch;
+
+    for assignment in &plan.slot_assignments {
+        slot_cache.ensure_slot(current_branch, &assignment.slot);
+    }
+    slot_cache.save(git_repo)?;
+
+    // Write notes for slot assignments
+    if !options.push_only {
+        for (entry, assignment) in stack.entries.iter().zip(plan.slot_assignments.iter()) {
+            let metadata = CommitMetadata {
+                pr: entry.pr_number,
+                slot: assignment.slot.clone(),
+                annotation: entry.annotation.clone(),
+                last_export: entry.last_export.clone(),
+                owner: entry.owner.clone(),
+                signature: None,
+            };
+            notes::write_note(
+                git_repo,
+                entry.oid,
+                &metadata,
+                &config.notes_ref,
+                config.sign_notes,
+            )?;
+        }
+    }
+
+    Ok(())
+}
+
+async fn execute_phase1_updates(
+    gh_client: &Client,
+    owner: &str,
+    repo_name: &str,
+    plan: &ExportPlan,
+    options: &ExportOptions,
+) -> Result<()> {
+    if !options.json {
+        println!(
+            "🔄 Preparing {} PR{} for reorder...",
+            plan.phase1_base_updates.len(),
+            if plan.phase1_base_updates.len() == 1 {
+                ""
+            } else {
+                "s"
+            }
+        );
+    }
+    mutations::batch_update_pr_bases(gh_client, owner, repo_name, &plan.phase1_base_updates)
+        .await?;
     if !options.json {
         println!("   ✓ Ready");
     }
@@ -737,29 +2122,109 @@ async fn execute_phase1_updates(
 fn execute_push_refs(
     git_repo: &Repository,
     config: &Config,
+    stack: &Stack,
     plan: &ExportPlan,
     options: &ExportOptions,
 ) -> Result<()> {
+    let remote_refs = &stack.remote_refs;
     let refs_to_push: Vec<_> = plan.refs_to_push.iter().filter(|r| r.needs_push).collect();
 
     if refs_to_push.is_empty() {
         return Ok(());
     }
 
-    if !options.json {
-        println!("🚀 Pushing {} ref{}...", refs_to_push.len(), if refs_to_push.len() == 1 { "" } else { "s" });
-    }
-
     let commits: Vec<(git2::Oid, String)> = refs_to_push
         .iter()
         .map(|r| (r.oid, r.head_ref.clone()))
         .collect();
-    let refspecs = refs::build_refspecs_from_oids(&commits);
+    let mut refspecs = refs::build_refspecs_from_oids(&commits);
+    // Archived revisions go along so other clones can reach them too
+    refspecs.extend(
+        archive_refs(stack, plan)
+            .into_iter()
+            .map(|(oid, archive_ref)| format!("{}:{}", oid, archive_ref)),
+    );
 
-    let mut capabilities = refs::RemoteCapabilities::default();
-    let result = refs::push_refs(git_repo, &config.remote, &refspecs, &mut capabilities)?;
+    if !options.json {
+        println!(
+            "🚀 Pushing {} ref{}...",
+            refspecs.len(),
+            if refspecs.len() == 1 { "" } else { "s" }
+        );
+    }
+
+    let push_hooks = if options.no_verify {
+        PushHooks::None
+    } else {
+        config.push_hooks
+    };
+    if push_hooks == PushHooks::Once {
+        let updates: Vec<_> = refs_to_push
+            .iter()
+            .map(|r| {
+                (
+                    r.oid,
+                    r.head_ref.clone(),
+                    remote_refs.get(&r.head_ref).copied(),
+                )
+            })
+            .collect();
+        refs::run_pre_push_hook(git_repo, &config.remote, &updates)?;
+    }
+
+    let mut capabilities = refs::RemoteCapabilities::load(git_repo, &config.remote);
+    if options.atomic {
+        if !capabilities.atomic_support(git_repo, &config.remote, &refspecs, &config.transport)? {
+            bail!("--atomic requires a remote that supports atomic pushes");
+        }
+
+        // Rolling back means pushing the previous tips again, so they must be available locally
+        let missing: Vec<String> = refs_to_push
+            .iter()
+            .filter_map(|r| remote_refs.get(&r.head_ref))
+            .filter(|oid| git_repo.find_commit(**oid).is_err())
+            .map(|oid| oid.to_string())
+            .collect();
+        if !missing.is_empty() {
+            refs::fetch_refspecs(git_repo, &config.remote, &missing, &config.transport)?;
+        }
+    }
+
+    let result = refs::push_refs(
+        git_repo,
+        &config.remote,
+        &refspecs,
+        &mut capabilities,
+        push_hooks != PushHooks::Each,
+        config.max_refs_per_push,
+        &config.transport,
+        &mut |progress| {
+            if !options.json {
+                println!("   ✓ {}/{} refs", progress.pushed, progress.total);
+            }
+        },
+    )?;
 
     if !result.success {
+        // A chunked push is only atomic per chunk: undo the chunks that went through
+        if options.atomic && !result.pushed_refs.is_empty() {
+            let restores: Vec<_> = refs_to_push
+                .iter()
+                .filter(|r| {
+                    result
+                        .pushed_refs
+                        .contains(&format!("{}:refs/heads/{}", r.oid, r.head_ref))
+                })
+                .map(|r| {
+                    (
+                        r.head_ref.clone(),
+                        remote_refs.get(&r.head_ref).copied(),
+                        r.oid,
+                    )
+                })
+                .collect();
+            refs::restore_remote_refs(git_repo, &config.remote, &restores, &config.transport)?;
+        }
         bail!("Failed to push refs: {}", result.error.unwrap_or_default());
     }
 
@@ -770,82 +2235,569 @@ fn execute_push_refs(
     Ok(())
 }
 
+/// What `execute_pr_operations` did
+#[derive(Default)]
+struct PrOperations {
+    /// URLs of the stack's PRs, created or existing
+    urls: Vec<String>,
+    /// PRs opened by this export, by the commit they're for (adopted ones aren't)
+    created: Vec<(git2::Oid, u64)>,
+    /// PRs that already existed for a slot branch and were adopted, as they
+    /// were before the adoption changed them
+    adopted: Vec<queries::PrInfo>,
+    /// Failures to set up a PR that didn't stop the export
+    warnings: Vec<String>,
+}
+
+#[allow(clippy::too_many_arguments)]
 async fn execute_pr_operations(
     git_repo: &Repository,
     config: &Config,
-    gh_client: &Octocrab,
+    gh_client: &Client,
     stack: &mut Stack,
     owner: &str,
     repo_name: &str,
     plan: &ExportPlan,
     options: &ExportOptions,
-) -> Result<Vec<String>> {
-    let mut pr_urls = Vec::new();
-    let mut created_pr_nums = Vec::new();
+    operations: &mut PrOperations,
+) -> Result<()> {
+    let PrOperations {
+        urls: pr_urls,
+        created,
+        adopted,
+        warnings,
+    } = operations;
 
     // Track existing PRs
     for pr_update in &plan.prs_to_update {
-        pr_urls.push(format!("https://github.com/{}/{}/pull/{}", owner, repo_name, pr_update.pr_number));
+        pr_urls.push(config.forge.pr_url(owner, repo_name, pr_update.pr_number));
 
         // Update stack entry
         if let Some(entry) = stack.entries.iter_mut().find(|e| {
-            plan.slot_assignments.iter().any(|a| a.oid == e.oid && a.head_ref == pr_update.head_ref)
+            plan.slot_assignments
+                .iter()
+                .any(|a| a.oid == e.oid && a.head_ref == pr_update.head_ref)
         }) {
             entry.pr_number = Some(pr_update.pr_number);
             entry.head_ref = Some(pr_update.head_ref.clone());
         }
+
+        if pr_update.title_override.is_some() || pr_update.body_override.is_some() {
+            mutations::update_pull_request(
+                gh_client,
+                owner,
+                repo_name,
+                pr_update.pr_number,
+                None,
+                pr_update.title_override.as_deref(),
+                pr_update.body_override.as_deref(),
+            )
+            .await?;
+
+            if !options.json {
+                println!("✏️  Updated PR #{} title/description", pr_update.pr_number);
+            }
+        }
     }
 
     // Process creates (new PRs)
     if !plan.prs_to_create.is_empty() {
         if !options.json {
-            println!("📝 Creating {} PR{}...", plan.prs_to_create.len(), if plan.prs_to_create.len() == 1 { "" } else { "s" });
+            println!(
+                "📝 Creating {} PR{}...",
+                plan.prs_to_create.len(),
+                if plan.prs_to_create.len() == 1 {
+                    ""
+                } else {
+                    "s"
+                }
+            );
         }
 
+        let assignees = resolve_assignees(gh_client, config, options).await?;
+        // Resolved first, so a milestone or project that doesn't exist fails before any PR is opened
+        let targets = resolve_attach_targets(gh_client, config, owner, repo_name, options).await?;
+
+        let mut adopted_pr_nums = Vec::new();
         for pr_create in &plan.prs_to_create {
-            let initial_body = if pr_create.body.is_empty() { " ".to_string() } else { pr_create.body.clone() };
+            let initial_body = if pr_create.body.is_empty() {
+                " ".to_string()
+            } else {
+                pr_create.body.clone()
+            };
 
-            let pr_num = mutations::create_pull_request(
-                gh_client, owner, repo_name,
-                &pr_create.head_ref, &pr_create.base_ref,
-                &pr_create.title, &initial_body,
+            let outcome = mutations::create_pull_request(
+                gh_client,
+                owner,
+                repo_name,
+                &pr_create.head_ref,
+                &pr_create.base_ref,
+                &pr_create.title,
+                &initial_body,
                 options.draft,
-            ).await?;
+            )
+            .await?;
+            let pr_num = match outcome {
+                mutations::CreatedPr::New(number) => number,
+                mutations::CreatedPr::Existing(existing) => {
+                    adopted.push(existing.clone());
+                    warnings.extend(
+                        adopt_existing_pr(
+                            git_repo,
+                            config,
+                            gh_client,
+                            stack,
+                            owner,
+                            repo_name,
+                            plan,
+                            pr_create,
+                            &existing,
+                            &initial_body,
+                            &assignees,
+                            options,
+                        )
+                        .await?,
+                    );
+                    adopted_pr_nums.push(existing.number);
+                    pr_urls.push(config.forge.pr_url(owner, repo_name, existing.number));
+                    continue;
+                }
+            };
+
+            let subject = stack
+                .entries
+                .iter()
+                .find(|e| e.oid == pr_create.oid)
+                .map(|e| e.subject.clone());
+            warnings.extend(
+                apply_new_pr_attributes(
+                    gh_client,
+                    config,
+                    owner,
+                    repo_name,
+                    pr_num,
+                    subject.as_deref(),
+                    &assignees,
+                    options,
+                )
+                .await,
+            );
 
-            created_pr_nums.push(pr_num);
-            pr_urls.push(format!("https://github.com/{}/{}/pull/{}", owner, repo_name, pr_num));
+            created.push((pr_create.oid, pr_num));
+            let url = config.forge.pr_url(owner, repo_name, pr_num);
+            workflows::run_hooks(
+                git_repo,
+                config,
+                HookEvent::PrCreated,
+                json!({
+                    "pr": pr_num,
+                    "url": url,
+                    "commit": pr_create.oid.to_string(),
+                    "head_ref": pr_create.head_ref,
+                    "base_ref": pr_create.base_ref,
+                    "title": pr_create.title,
+                    "draft": options.draft,
+                }),
+            )?;
+            pr_urls.push(url);
 
             // Update stack entry and write note
             if let Some(entry) = stack.entries.iter_mut().find(|e| e.oid == pr_create.oid) {
                 entry.pr_number = Some(pr_num);
                 entry.head_ref = Some(pr_create.head_ref.clone());
-                entry.pr_state = Some(if options.draft { PrState::Draft } else { PrState::Open });
-
-                let slot = plan.slot_assignments.iter().find(|a| a.oid == entry.oid).unwrap();
-                let metadata = CommitMetadata { pr: Some(pr_num), slot: slot.slot.clone() };
-                notes::write_note(git_repo, entry.oid, &metadata, &config.notes_ref)?;
+                entry.pr_state = Some(if options.draft {
+                    PrState::Draft
+                } else {
+                    PrState::Open
+                });
+
+                let slot = plan
+                    .slot_assignments
+                    .iter()
+                    .find(|a| a.oid == entry.oid)
+                    .with_context(|| format!("No slot planned for {}", entry.short_sha))?;
+                let metadata = CommitMetadata {
+                    pr: Some(pr_num),
+                    slot: slot.slot.clone(),
+                    annotation: entry.annotation.clone(),
+                    last_export: entry.last_export.clone(),
+                    owner: entry.owner.clone(),
+                    signature: None,
+                };
+                notes::write_note(
+                    git_repo,
+                    entry.oid,
+                    &metadata,
+                    &config.notes_ref,
+                    config.sign_notes,
+                )?;
             }
         }
 
+        let created_pr_nums: Vec<u64> = created.iter().map(|(_, number)| *number).collect();
         if !options.json {
-            let pr_list: Vec<String> = created_pr_nums.iter().map(|n| format!("#{}", n)).collect();
-            println!("   ✓ Created {}", pr_list.join(", "));
+            let pr_list = |numbers: &[u64]| {
+                numbers
+                    .iter()
+                    .map(|n| format!("#{}", n))
+                    .collect::<Vec<_>>()
+                    .join(", ")
+            };
+            if !created_pr_nums.is_empty() {
+                println!("   ✓ Created {}", pr_list(&created_pr_nums));
+            }
+            if !adopted_pr_nums.is_empty() {
+                println!("   ✓ Adopted existing {}", pr_list(&adopted_pr_nums));
+            }
+        }
+
+        let attach: Vec<u64> = created_pr_nums
+            .iter()
+            .chain(&adopted_pr_nums)
+            .copied()
+            .collect();
+        warnings.extend(
+            attach_to_milestone_and_project(
+                gh_client, owner, repo_name, &targets, &attach, options,
+            )
+            .await,
+        );
+    }
+
+    Ok(())
+}
+
+/// Tell the webhook and issue trackers about the PRs this export opened
+#[allow(clippy::too_many_arguments)]
+async fn notify_created_prs(
+    git_repo: &Repository,
+    config: &Config,
+    stack: &Stack,
+    owner: &str,
+    repo_name: &str,
+    plan: &ExportPlan,
+    created: &[(git2::Oid, u64)],
+    options: &ExportOptions,
+) {
+    if created.is_empty() {
+        return;
+    }
+    let summaries: Vec<PrSummary> = created
+        .iter()
+        .filter_map(|(oid, number)| {
+            let pr_create = plan.prs_to_create.iter().find(|p| p.oid == *oid)?;
+            Some(PrSummary {
+                number: *number,
+                title: pr_create.title.clone(),
+                url: config.forge.pr_url(owner, repo_name, *number),
+            })
+        })
+        .collect();
+    let activity = Activity {
+        repository: &format!("{}/{}", owner, repo_name),
+        branch: &stack.current_branch,
+        author: &webhook::user_name(git_repo),
+    };
+    webhook::notify(
+        config,
+        WebhookEvent::PrsCreated,
+        activity,
+        &summaries,
+        options.json,
+    )
+    .await;
+    webhook::notify_issues(config, activity.repository, &summaries, options.json).await;
+}
+
+/// Take over a PR GitHub already had open for the slot branch: retarget it and
+/// give it the attributes of a PR export creates, then record it in the entry's
+/// note. A title or body written by hand is kept; an empty one gets the planned one.
+#[allow(clippy::too_many_arguments)]
+async fn adopt_existing_pr(
+    git_repo: &Repository,
+    config: &Config,
+    gh_client: &Client,
+    stack: &mut Stack,
+    owner: &str,
+    repo_name: &str,
+    plan: &ExportPlan,
+    pr_create: &PrToCreate,
+    existing: &queries::PrInfo,
+    body: &str,
+    assignees: &[String],
+    options: &ExportOptions,
+) -> Result<Vec<String>> {
+    if !options.json {
+        println!(
+            "   {} PR #{} already exists for {}, adopting it",
+            style("↪").cyan(),
+            existing.number,
+            pr_create.head_ref
+        );
+    }
+
+    let base = (existing.base_ref != pr_create.base_ref).then_some(pr_create.base_ref.as_str());
+    let title = existing
+        .title
+        .trim()
+        .is_empty()
+        .then_some(pr_create.title.as_str());
+    let body = existing.body.trim().is_empty().then_some(body);
+    if base.is_some() || title.is_some() || body.is_some() {
+        mutations::update_pull_request(
+            gh_client,
+            owner,
+            repo_name,
+            existing.number,
+            base,
+            title,
+            body,
+        )
+        .await?;
+    }
+    let mut warnings = Vec::new();
+    let mut state = existing.state.clone();
+    if options.draft && state == PrState::Open {
+        match mutations::convert_to_draft(gh_client, owner, repo_name, existing.number).await {
+            Ok(()) => state = PrState::Draft,
+            Err(e) => {
+                if !options.json {
+                    eprintln!("   ⚠ {}", e);
+                }
+                warnings.push(e.to_string());
+            }
         }
     }
+    let subject = stack
+        .entries
+        .iter()
+        .find(|e| e.oid == pr_create.oid)
+        .map(|e| e.subject.clone());
+    warnings.extend(
+        apply_new_pr_attributes(
+            gh_client,
+            config,
+            owner,
+            repo_name,
+            existing.number,
+            subject.as_deref(),
+            assignees,
+            options,
+        )
+        .await,
+    );
+
+    if let Some(entry) = stack.entries.iter_mut().find(|e| e.oid == pr_create.oid) {
+        entry.pr_number = Some(existing.number);
+        entry.head_ref = Some(pr_create.head_ref.clone());
+        entry.pr_state = Some(state);
+
+        let slot = plan
+            .slot_assignments
+            .iter()
+            .find(|a| a.oid == entry.oid)
+            .with_context(|| format!("No slot planned for {}", entry.short_sha))?;
+        let metadata = CommitMetadata {
+            pr: Some(existing.number),
+            slot: slot.slot.clone(),
+            annotation: entry.annotation.clone(),
+            last_export: entry.last_export.clone(),
+            owner: entry.owner.clone(),
+            signature: None,
+        };
+        notes::write_note(
+            git_repo,
+            entry.oid,
+            &metadata,
+            &config.notes_ref,
+            config.sign_notes,
+        )?;
+    }
 
-    Ok(pr_urls)
+    Ok(warnings)
+}
+
+/// Give a PR export opened (or adopted instead of opening one) its assignees,
+/// the label of its subject's conventional-commit type and the policy's
+/// required labels. Failures are only warnings, which are returned: the PR
+/// exists either way.
+#[allow(clippy::too_many_arguments)]
+async fn apply_new_pr_attributes(
+    gh_client: &Client,
+    config: &Config,
+    owner: &str,
+    repo_name: &str,
+    pr_num: u64,
+    subject: Option<&str>,
+    assignees: &[String],
+    options: &ExportOptions,
+) -> Vec<String> {
+    let mut warnings = Vec::new();
+    if !assignees.is_empty()
+        && let Err(e) =
+            mutations::add_assignees(gh_client, owner, repo_name, pr_num, assignees).await
+    {
+        warnings.push(e.to_string());
+    }
+
+    // Label by conventional-commit type of the subject (the title may carry a prefix)
+    if let Some(label) = subject
+        .and_then(conventional::parse)
+        .and_then(|c| config.type_labels.get(&c.kind))
+        && let Err(e) = mutations::add_labels(
+            gh_client,
+            owner,
+            repo_name,
+            pr_num,
+            std::slice::from_ref(label),
+        )
+        .await
+    {
+        warnings.push(e.to_string());
+    }
+    if let Some(policy) = config
+        .policy
+        .as_ref()
+        .filter(|p| !p.required_labels.is_empty())
+        && let Err(e) =
+            mutations::add_labels(gh_client, owner, repo_name, pr_num, &policy.required_labels)
+                .await
+    {
+        warnings.push(e.to_string());
+    }
+
+    if !options.json {
+        for warning in &warnings {
+            eprintln!("   ⚠ {}", warning);
+        }
+    }
+    warnings
+}
+
+/// The milestone and ProjectV2 to attach created PRs to, as (name, node ID)
+#[derive(Default)]
+struct AttachTargets {
+    milestone: Option<(String, String)>,
+    project: Option<(String, String)>,
+}
+
+/// Resolve the configured milestone and/or ProjectV2 to their node IDs
+async fn resolve_attach_targets(
+    gh_client: &Client,
+    config: &Config,
+    owner: &str,
+    repo_name: &str,
+    options: &ExportOptions,
+) -> Result<AttachTargets> {
+    let mut targets = AttachTargets::default();
+    if let Some(milestone) = options.milestone.as_ref().or(config.milestone.as_ref()) {
+        let id = queries::find_milestone_id(gh_client, owner, repo_name, milestone).await?;
+        targets.milestone = Some((milestone.clone(), id));
+    }
+    if let Some(project) = options.project.as_ref().or(config.project.as_ref()) {
+        let id = queries::find_project_id(gh_client, owner, project).await?;
+        targets.project = Some((project.clone(), id));
+    }
+    Ok(targets)
+}
+
+/// Attach created PRs to the resolved milestone and/or ProjectV2
+///
+/// The PRs are open by now, so a failure is a warning rather than an error.
+async fn attach_to_milestone_and_project(
+    gh_client: &Client,
+    owner: &str,
+    repo_name: &str,
+    targets: &AttachTargets,
+    pr_numbers: &[u64],
+    options: &ExportOptions,
+) -> Vec<String> {
+    if pr_numbers.is_empty() || (targets.milestone.is_none() && targets.project.is_none()) {
+        return Vec::new();
+    }
+
+    let attached = mutations::batch_attach_prs(
+        gh_client,
+        owner,
+        repo_name,
+        pr_numbers,
+        targets.milestone.as_ref().map(|(_, id)| id.as_str()),
+        targets.project.as_ref().map(|(_, id)| id.as_str()),
+    )
+    .await;
+
+    let names: Vec<String> = targets
+        .milestone
+        .iter()
+        .map(|(m, _)| format!("milestone {}", m))
+        .chain(
+            targets
+                .project
+                .iter()
+                .map(|(p, _)| format!("project {}", p)),
+        )
+        .collect();
+    match attached {
+        Ok(()) => {
+            if !options.json {
+                println!("   ✓ Added to {}", names.join(" and "));
+            }
+            Vec::new()
+        }
+        Err(e) => {
+            let warning = format!("Failed to add PRs to {}: {}", names.join(" and "), e);
+            if !options.json {
+                eprintln!("   ⚠ {}", warning);
+            }
+            vec![warning]
+        }
+    }
+}
+
+/// Resolve users to assign to created PRs: explicit `--assignee` values
+/// ("me" is the authenticated user), or the authenticated user with `git-stk.selfAssign`
+async fn resolve_assignees(
+    gh_client: &Client,
+    config: &Config,
+    options: &ExportOptions,
+) -> Result<Vec<String>> {
+    let wants_viewer = options.assignees.iter().any(|a| a == "me")
+        || (options.assignees.is_empty() && config.self_assign);
+
+    if !wants_viewer {
+        return Ok(options.assignees.clone());
+    }
+
+    let viewer = queries::get_viewer(gh_client).await?;
+    if options.assignees.is_empty() {
+        return Ok(vec![viewer]);
+    }
+
+    let mut assignees: Vec<String> = Vec::new();
+    for assignee in &options.assignees {
+        let login = if assignee == "me" {
+            viewer.clone()
+        } else {
+            assignee.clone()
+        };
+        if !assignees.contains(&login) {
+            assignees.push(login);
+        }
+    }
+    Ok(assignees)
 }
 
 /// Execute all post-push base updates (regular base changes + phase3 reorder updates)
 async fn execute_base_updates(
-    gh_client: &Octocrab,
+    gh_client: &Client,
     owner: &str,
     repo_name: &str,
     plan: &ExportPlan,
     options: &ExportOptions,
 ) -> Result<()> {
     // Collect all base updates: regular (needs_base_update) + phase3 (reorder finalization)
-    let mut all_updates: Vec<(u64, String)> = plan.prs_to_update
+    let mut all_updates: Vec<(u64, String)> = plan
+        .prs_to_update
         .iter()
         .filter(|u| u.needs_base_update)
         .map(|u| (u.pr_number, u.base_ref.clone()))
@@ -858,7 +2810,11 @@ async fn execute_base_updates(
     }
 
     if !options.json {
-        println!("🔗 Updating {} PR base{}...", all_updates.len(), if all_updates.len() == 1 { "" } else { "s" });
+        println!(
+            "🔗 Updating {} PR base{}...",
+            all_updates.len(),
+            if all_updates.len() == 1 { "" } else { "s" }
+        );
     }
 
     mutations::batch_update_pr_bases(gh_client, owner, repo_name, &all_updates).await?;
@@ -870,10 +2826,86 @@ async fn execute_base_updates(
     Ok(())
 }
 
+/// Close the PRs of entries dropped from the stack (`--close-dropped`) and
+/// delete their slot branches. Failures are only warnings: the rest of the
+/// stack is already exported. Orphaned PRs left open get a hint to prune them.
+#[allow(clippy::too_many_arguments)]
+async fn execute_pr_closures(
+    git_repo: &Repository,
+    config: &Config,
+    gh_client: &Client,
+    stack: &Stack,
+    owner: &str,
+    repo_name: &str,
+    plan: &ExportPlan,
+    options: &ExportOptions,
+) -> Result<Vec<String>> {
+    let mut warnings = Vec::new();
+    if !plan.prs_to_close.is_empty() {
+        if !options.json {
+            println!(
+                "🔒 Closing {} PR{} of removed entries...",
+                plan.prs_to_close.len(),
+                if plan.prs_to_close.len() == 1 {
+                    ""
+                } else {
+                    "s"
+                }
+            );
+        }
+        let (_, close_warnings) = workflows::orphans::close_orphaned_prs(
+            git_repo,
+            config,
+            gh_client,
+            owner,
+            repo_name,
+            &stack.current_branch,
+            &plan.prs_to_close,
+            options.json,
+        )
+        .await?;
+        warnings.extend(close_warnings);
+    }
+
+    let left_open = stack
+        .orphaned_prs
+        .iter()
+        .filter(|pr| {
+            !plan
+                .prs_to_close
+                .iter()
+                .any(|c| c.pr_number == pr.pr_number)
+        })
+        .count();
+    if left_open > 0 && !options.push_only {
+        warnings.push(format!(
+            "{} orphaned PR{} left open: close {} with 'git stk prune'{}",
+            left_open,
+            if left_open == 1 { "" } else { "s" },
+            if left_open == 1 { "it" } else { "them" },
+            if options.close_dropped {
+                ""
+            } else {
+                " (or export --close-dropped)"
+            }
+        ));
+    }
+
+    if !options.json {
+        for warning in &warnings {
+            eprintln!("   ⚠ {}", warning);
+        }
+    }
+    Ok(warnings)
+}
+
+#[allow(clippy::too_many_arguments)]
 async fn execute_callout_updates(
     git_repo: &Repository,
-    gh_client: &Octocrab,
+    config: &Config,
+    gh_client: &Client,
     stack: &Stack,
+    slot_assignments: &[SlotAssignment],
     owner: &str,
     repo_name: &str,
     options: &ExportOptions,
@@ -893,7 +2925,11 @@ async fn execute_callout_updates(
         let pr_number = entry.pr_number.expect("PR number should exist");
 
         let commit = git_repo.find_commit(entry.oid)?;
-        let body_text = extract_commit_body(commit.message().unwrap_or(""));
+        let body_text = build_pr_body(
+            commit.message().unwrap_or(""),
+            entry.annotation.as_deref(),
+            config,
+        );
 
         let callout_text = callout::generate_callout(&stack.entries, i + 1, owner, repo_name);
         let full_body = if body_text.is_empty() {
@@ -901,6 +2937,10 @@ async fn execute_callout_updates(
         } else {
             callout::inject_callout(&body_text, &callout_text)
         };
+        let full_body = callout::inject_metadata_block(
+            &full_body,
+            &pr_stack_metadata(stack, &slot_assignments[i], i),
+        );
 
         body_updates.push((pr_number, full_body));
     }
@@ -915,11 +2955,125 @@ async fn execute_callout_updates(
     Ok(())
 }
 
-fn push_notes_to_remote(git_repo: &Repository, config: &Config, options: &ExportOptions) -> Result<()> {
+/// Re-apply `git-stk.titlePrefix` to every open PR, since positions shift
+/// whenever entries are added, removed, or reordered
+async fn execute_title_updates(
+    git_repo: &Repository,
+    config: &Config,
+    gh_client: &Client,
+    stack: &Stack,
+    owner: &str,
+    repo_name: &str,
+    options: &ExportOptions,
+) -> Result<()> {
+    if config.title_prefix.is_none() {
+        return Ok(());
+    }
+
+    let mut title_updates: Vec<(u64, String)> = Vec::new();
+    for (i, entry) in stack.entries.iter().enumerate() {
+        let Some(pr_number) = entry.pr_number else {
+            continue;
+        };
+        if matches!(
+            entry.pr_state,
+            Some(PrState::Merged) | Some(PrState::Closed)
+        ) {
+            continue;
+        }
+
+        let subject = match &options.title {
+            Some(title) => title.clone(),
+            None => git_repo
+                .find_commit(entry.oid)?
+                .summary()
+                .unwrap_or("")
+                .to_string(),
+        };
+        title_updates.push((
+            pr_number,
+            build_pr_title(&subject, config, i + 1, stack.len()),
+        ));
+    }
+
+    if title_updates.is_empty() {
+        return Ok(());
+    }
+
+    if !options.json {
+        println!(
+            "🔢 Syncing {} PR title{}...",
+            title_updates.len(),
+            if title_updates.len() == 1 { "" } else { "s" }
+        );
+    }
+
+    mutations::batch_update_pr_titles(gh_client, owner, repo_name, &title_updates).await?;
+
+    if !options.json {
+        println!("   ✓ Synced");
+    }
+
+    Ok(())
+}
+
+async fn execute_summary_comment(
+    gh_client: &Client,
+    stack: &Stack,
+    owner: &str,
+    repo_name: &str,
+    options: &ExportOptions,
+) -> Result<()> {
+    let Some(top_pr) = stack.entries.last().and_then(|e| e.pr_number) else {
+        return Ok(());
+    };
+
+    if !options.json {
+        println!("📋 Updating stack summary on PR #{}...", top_pr);
+    }
+
+    // Entries were hydrated before the push; everything pushed is now in sync
+    let mut entries = stack.entries.clone();
+    if !options.pr_only {
+        for entry in &mut entries {
+            entry.status = UpdateStatus::UpToDate;
+        }
+    }
+
+    let summary = callout::generate_summary_comment(&entries, owner, repo_name);
+    mutations::upsert_pr_comment(
+        gh_client,
+        owner,
+        repo_name,
+        top_pr,
+        callout::SUMMARY_MARKER,
+        &summary,
+    )
+    .await?;
+
+    if !options.json {
+        println!("   ✓ Updated");
+    }
+
+    Ok(())
+}
+
+async fn push_notes_to_remote(
+    git_repo: &Repository,
+    config: &Config,
+    gh_client: &Client,
+    options: &ExportOptions,
+) -> Result<()> {
+    if options.push_notes.unwrap_or(config.push_notes) == PushNotes::Never {
+        return Ok(());
+    }
+
     if !options.json {
         println!("☁️  Pushing metadata...");
     }
-    if let Err(e) = notes::push_notes(git_repo, &config.remote, &config.notes_ref) {
+    let no_verify = options.no_verify || config.push_hooks != PushHooks::Each;
+    if let Err(e) = workflows::notes_sync::push_notes(git_repo, config, gh_client, no_verify).await
+    {
         if !options.json {
             eprintln!("   ⚠ Failed: {}", e);
         }
@@ -941,12 +3095,13 @@ fn open_pr_urls(pr_urls: &[String]) {
 // Helpers
 // =============================================================================
 
-fn extract_commit_body(message: &str) -> String {
+pub(crate) fn extract_commit_body(message: &str) -> String {
     let lines: Vec<&str> = message.lines().collect();
     if lines.len() <= 1 {
         return String::new();
     }
-    lines.into_iter()
+    lines
+        .into_iter()
         .skip(1)
         .skip_while(|l| l.trim().is_empty())
         .collect::<Vec<_>>()
@@ -954,3 +3109,422 @@ fn extract_commit_body(message: &str) -> String {
         .trim()
         .to_string()
 }
+
+/// Hidden metadata embedded in the PR body of the entry at `index`
+fn pr_stack_metadata(stack: &Stack, assignment: &SlotAssignment, index: usize) -> PrStackMetadata {
+    PrStackMetadata {
+        branch: stack.current_branch.clone(),
+        slot: assignment.slot.clone(),
+        position: index + 1,
+        total: stack.len(),
+    }
+}
+
+/// Build the PR title, prefixed with the stack position when `git-stk.titlePrefix` is set
+fn build_pr_title(subject: &str, config: &Config, position: usize, total: usize) -> String {
+    match &config.title_prefix {
+        Some(template) => format!(
+            "{}{}",
+            template
+                .replace("{position}", &position.to_string())
+                .replace("{total}", &total.to_string()),
+            subject
+        ),
+        None => subject.to_string(),
+    }
+}
+
+/// Build the PR body from a commit message.
+///
+/// Recognized trailers (Co-authored-by, Signed-off-by, ...) are stripped, or moved
+/// to a footer with `git-stk.trailersInFooter`. Issue keys in the subject are
+/// linked when `.git-stk.toml` configures a tracker. The annotation is appended when enabled.
+fn build_pr_body(message: &str, annotation: Option<&str>, config: &Config) -> String {
+    let (body, trailers) = trailers::split_trailers(&extract_commit_body(message));
+
+    let mut sections = Vec::new();
+    if !body.is_empty() {
+        sections.push(body);
+    }
+    let subject = message.lines().next().unwrap_or_default();
+    if let Some(line) = config
+        .issue_links
+        .as_ref()
+        .and_then(|links| issue_keys::links_line(subject, links))
+    {
+        sections.push(line);
+    }
+    if let Some(text) = annotation.filter(|_| config.annotations_in_pr_body) {
+        sections.push(format!("> 📌 {}", text));
+    }
+    if config.trailers_in_footer && !trailers.is_empty() {
+        sections.push(format!("---\n{}", trailers.join("  \n")));
+    }
+
+    sections.join("\n\n")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_dry_run_plan_json() {
+        let oid = git2::Oid::from_str("1234567890abcdef1234567890abcdef12345678").unwrap();
+        let stack = Stack::new("main".to_string(), "feat".to_string());
+        let plan = ExportPlan {
+            slot_assignments: vec![SlotAssignment {
+                oid,
+                slot: "01".to_string(),
+                head_ref: "feat--01".to_string(),
+                is_new: true,
+            }],
+            slot_conflicts: Vec::new(),
+            refs_to_push: vec![RefToPush {
+                oid,
+                head_ref: "feat--01".to_string(),
+                needs_push: true,
+            }],
+            prs_to_create: vec![PrToCreate {
+                oid,
+                head_ref: "feat--01".to_string(),
+                base_ref: "main".to_string(),
+                title: "Add feature".to_string(),
+                body: String::new(),
+            }],
+            prs_to_update: Vec::new(),
+            phase1_base_updates: Vec::new(),
+            phase3_base_updates: vec![(7, "feat--01".to_string())],
+            prs_to_close: vec![OrphanedPr {
+                slot: "02".to_string(),
+                head_ref: "feat--02".to_string(),
+                pr_number: 5,
+                title: "Dropped".to_string(),
+                dropped_here: true,
+            }],
+            warnings: vec![
+                "0000001 points vendor at 0000002, which isn't on the submodule's remote"
+                    .to_string(),
+            ],
+        };
+
+        let value = dry_run_plan_json(&stack, &plan, &ExportOptions::default());
+        assert_eq!(value["slot_assignments"][0]["slot"], "01");
+        assert_eq!(value["refs_to_push"][0]["commit"], oid.to_string());
+        assert_eq!(value["prs_to_create"][0]["draft"], false);
+        assert_eq!(
+            value["phase3_base_updates"][0],
+            json!({ "pr": 7, "base": "feat--01" })
+        );
+        assert_eq!(value["prs_to_close"][0]["pr"], 5);
+        assert_eq!(value["warnings"].as_array().unwrap().len(), 1);
+
+        let push_only = ExportOptions {
+            push_only: true,
+            ..Default::default()
+        };
+        let value = dry_run_plan_json(&stack, &plan, &push_only);
+        assert_eq!(value["prs_to_create"], json!([]));
+    }
+
+    fn exported_entry(index: usize, pr: u64, base_ref: &str) -> crate::model::Entry {
+        let entry = crate::model::Entry::for_test(index);
+        crate::model::Entry {
+            head_ref: Some(format!("feat--0{}", index)),
+            pr_number: Some(pr),
+            pr_state: Some(PrState::Open),
+            status: UpdateStatus::UpToDate,
+            base_ref: base_ref.to_string(),
+            remote_oid: Some(entry.oid),
+            slot: Some(format!("0{}", index)),
+            remote_branch_exists: true,
+            pr_base_ref: Some(base_ref.to_string()),
+            ..entry
+        }
+    }
+
+    #[test]
+    fn test_export_mismatches() {
+        let mut stack = Stack::new("main".to_string(), "feat".to_string());
+        stack.add_entry(exported_entry(1, 10, "main"));
+        stack.add_entry(exported_entry(2, 11, "feat--01"));
+        // Left out of the plan, e.g. a slot the export didn't take over
+        stack.add_entry(exported_entry(3, 12, "feat--02"));
+        stack.entries[2].status = UpdateStatus::NeedsUpdate;
+        let plan = ExportPlan {
+            refs_to_push: stack.entries[..2]
+                .iter()
+                .map(|e| RefToPush {
+                    oid: e.oid,
+                    head_ref: e.head_ref.clone().unwrap(),
+                    needs_push: true,
+                })
+                .collect(),
+            ..Default::default()
+        };
+        let options = ExportOptions::default();
+        assert!(export_mismatches(&stack, "origin", &plan, &options).is_empty());
+
+        let stale = git2::Oid::from_str(&format!("{:040}", 9)).unwrap();
+        stack.entries[0].status = UpdateStatus::NeedsUpdate;
+        stack.entries[0].remote_oid = Some(stale);
+        stack.entries[1].pr_base_ref = Some("main".to_string());
+        assert_eq!(
+            export_mismatches(&stack, "origin", &plan, &options),
+            vec![
+                "0000000 commit 1: origin/feat--01 is at 0000000".to_string(),
+                "#11 targets main, expected feat--01".to_string(),
+            ]
+        );
+
+        let push_only = ExportOptions {
+            push_only: true,
+            ..Default::default()
+        };
+        assert_eq!(
+            export_mismatches(&stack, "origin", &plan, &push_only).len(),
+            1
+        );
+        let pr_only = ExportOptions {
+            pr_only: true,
+            ..Default::default()
+        };
+        assert_eq!(
+            export_mismatches(&stack, "origin", &plan, &pr_only).len(),
+            1
+        );
+
+        stack.entries[1].pr_state = Some(PrState::Merged);
+        stack.entries[1].merged_into_main = true;
+        assert_eq!(
+            export_mismatches(&stack, "origin", &plan, &pr_only),
+            Vec::<String>::new()
+        );
+    }
+
+    #[test]
+    fn test_archive_refs_follow_stamps() {
+        let mut stack = Stack::new("main".to_string(), "feat".to_string());
+        stack.add_entry(exported_entry(1, 10, "main"));
+        stack.add_entry(exported_entry(2, 11, "feat--01"));
+        let plan = ExportPlan {
+            slot_assignments: stack
+                .entries
+                .iter()
+                .map(|e| SlotAssignment {
+                    oid: e.oid,
+                    slot: format!("0{}", e.index),
+                    head_ref: e.head_ref.clone().unwrap(),
+                    is_new: false,
+                })
+                .collect(),
+            refs_to_push: stack
+                .entries
+                .iter()
+                .map(|e| RefToPush {
+                    oid: e.oid,
+                    head_ref: e.head_ref.clone().unwrap(),
+                    needs_push: e.index == 2,
+                })
+                .collect(),
+            ..Default::default()
+        };
+        assert!(archive_refs(&stack, &plan).is_empty());
+
+        stamp_exports(&mut stack, &plan, None, false);
+        assert_eq!(
+            archive_refs(&stack, &plan),
+            vec![(
+                stack.entries[1].oid,
+                "refs/git-stk/archive/feat/02/v1".to_string()
+            )]
+        );
+    }
+
+    #[test]
+    fn test_rollback_selection() {
+        let created = git2::Oid::from_str(&format!("{:040}", 2)).unwrap();
+        // PR 11 was open for its slot branch before the export adopted and retargeted it
+        let adopted = queries::PrInfo::for_test(11, "main", "feat--01");
+        let operations = PrOperations {
+            created: vec![(created, 12)],
+            adopted: vec![adopted],
+            ..Default::default()
+        };
+        let originals = vec![queries::PrInfo::for_test(10, "main", "feat--03")];
+
+        let (restore, close) = rollback_selection(&originals, &operations);
+        let restored: Vec<(u64, &str)> = restore
+            .iter()
+            .map(|pr| (pr.number, pr.base_ref.as_str()))
+            .collect();
+        assert_eq!(restored, vec![(10, "main"), (11, "main")]);
+        assert_eq!(close, vec![(created, 12)]);
+    }
+
+    #[test]
+    fn test_select_entries() {
+        let dir = tempfile::tempdir().unwrap();
+        let repo = Repository::init(dir.path()).unwrap();
+        let mut stack = Stack::new("main".to_string(), "feat".to_string());
+        stack.add_entry(exported_entry(1, 10, "main"));
+        stack.add_entry(crate::model::Entry {
+            status: UpdateStatus::NeedsUpdate,
+            ..exported_entry(2, 11, "feat--01")
+        });
+        stack.add_entry(crate::model::Entry::for_test(3));
+        let oids: Vec<git2::Oid> = stack.entries.iter().map(|e| e.oid).collect();
+
+        assert_eq!(
+            select_entries(&repo, &stack, "2..", false).unwrap(),
+            oids[1..]
+        );
+        assert_eq!(
+            select_entries(&repo, &stack, "1", false).unwrap(),
+            oids[..1]
+        );
+        // Entry 2 isn't pushed at its commit, so entry 3's PR can't be based on it yet
+        let error = select_entries(&repo, &stack, "3", false)
+            .unwrap_err()
+            .to_string();
+        assert!(error.contains("'git stk export ..3'"), "{}", error);
+    }
+
+    #[test]
+    fn test_restrict_plan() {
+        let oid = |index: usize| git2::Oid::from_str(&format!("{:040}", index)).unwrap();
+        let update = |pr_number: u64, head_ref: &str| PrToUpdate {
+            pr_number,
+            head_ref: head_ref.to_string(),
+            base_ref: "main".to_string(),
+            title: String::new(),
+            title_override: None,
+            body_override: None,
+            needs_base_update: true,
+            is_reordered: false,
+        };
+        let mut plan = ExportPlan {
+            slot_assignments: (1..=3)
+                .map(|i| SlotAssignment {
+                    oid: oid(i),
+                    slot: format!("0{}", i),
+                    head_ref: format!("feat--0{}", i),
+                    is_new: i == 3,
+                })
+                .collect(),
+            refs_to_push: (1..=3)
+                .map(|i| RefToPush {
+                    oid: oid(i),
+                    head_ref: format!("feat--0{}", i),
+                    needs_push: true,
+                })
+                .collect(),
+            prs_to_create: vec![PrToCreate {
+                oid: oid(3),
+                head_ref: "feat--03".to_string(),
+                base_ref: "feat--02".to_string(),
+                title: String::new(),
+                body: String::new(),
+            }],
+            prs_to_update: vec![update(10, "feat--01"), update(11, "feat--02")],
+            phase1_base_updates: vec![(10, "main".to_string()), (11, "main".to_string())],
+            ..Default::default()
+        };
+
+        restrict_plan(&mut plan, &[oid(2), oid(3)]);
+        assert_eq!(plan.slot_assignments.len(), 3);
+        assert_eq!(
+            plan.refs_to_push.iter().map(|r| r.oid).collect::<Vec<_>>(),
+            vec![oid(2), oid(3)]
+        );
+        assert_eq!(plan.prs_to_create.len(), 1);
+        assert_eq!(
+            plan.prs_to_update
+                .iter()
+                .map(|pr| pr.pr_number)
+                .collect::<Vec<_>>(),
+            vec![11]
+        );
+        assert_eq!(plan.phase1_base_updates, vec![(11, "main".to_string())]);
+    }
+
+    #[test]
+    fn test_foreign_owned_slots() {
+        let mut stack = Stack::new("main".to_string(), "feat".to_string());
+        for (index, owner) in [
+            (1, None),
+            (2, Some("me@example.com")),
+            (3, Some("them@example.com")),
+            (4, Some("them@example.com")),
+        ] {
+            let mut entry = exported_entry(index, index as u64, "main");
+            entry.owner = owner.map(String::from);
+            stack.add_entry(entry);
+        }
+        // Slot 04 was never pushed: creating it overwrites nobody's work
+        for entry in &stack.entries[..3] {
+            stack
+                .remote_refs
+                .insert(entry.head_ref.clone().unwrap(), entry.oid);
+        }
+        let refs_to_push: Vec<_> = stack
+            .entries
+            .iter()
+            .map(|e| RefToPush {
+                oid: e.oid,
+                head_ref: e.head_ref.clone().unwrap(),
+                needs_push: true,
+            })
+            .collect();
+
+        let foreign = foreign_owned_slots(&stack, &refs_to_push, Some("ME@example.com"));
+        assert_eq!(
+            foreign
+                .iter()
+                .map(|(e, owner)| (e.index, *owner))
+                .collect::<Vec<_>>(),
+            vec![(3, "them@example.com")]
+        );
+        assert_eq!(foreign_owned_slots(&stack, &refs_to_push, None).len(), 2);
+        assert!(foreign_owned_slots(&stack, &refs_to_push[..2], Some("me@example.com")).is_empty());
+    }
+
+    #[test]
+    fn test_context_in_pr_warnings() {
+        let mut stack = Stack::new("main".to_string(), "shared".to_string());
+        stack.add_entry(exported_entry(1, 11, "main"));
+        stack.add_entry(exported_entry(2, 12, "feat--01"));
+        for (below, sha) in [(1, "aaaaaaa"), (1, "bbbbbbb"), (2, "ccccccc")] {
+            stack.context.push(crate::model::ContextCommit {
+                below,
+                oid: git2::Oid::zero(),
+                short_sha: sha.to_string(),
+                subject: "theirs".to_string(),
+                author_name: "Them".to_string(),
+            });
+        }
+        // The commit above the top entry is in no PR
+        assert_eq!(
+            context_in_pr_warnings(&stack),
+            vec!["PR #12 also shows commits by others: aaaaaaa, bbbbbbb".to_string()]
+        );
+    }
+
+    #[test]
+    fn test_range_diff_comment() {
+        let old = git2::Oid::from_str("1111111111111111111111111111111111111111").unwrap();
+        let new = git2::Oid::from_str("2222222222222222222222222222222222222222").unwrap();
+        let body = range_diff_comment(
+            old,
+            new,
+            "1:  1111111 ! 1:  2222222 one\n    -+a\n    ++b\n",
+        );
+        assert!(body.starts_with("Force-pushed `1111111` → `2222222`\n\n<details>"));
+        assert!(body.contains("```diff\n1:  1111111 ! 1:  2222222 one\n    -+a\n    ++b\n```"));
+
+        let long = "é".repeat(40_000);
+        let body = range_diff_comment(old, new, &long);
+        assert!(body.len() < 61_000);
+        assert!(body.contains("(truncated"));
+    }
+}
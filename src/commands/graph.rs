@@ -0,0 +1,103 @@
+use crate::gh::client;
+use crate::model::Config;
+use crate::stack::discover_stack;
+use crate::ui::graph::{BaseCommit, BaseLane, render_graph};
+use anyhow::{Context, Result};
+use console::style;
+use git2::{Oid, Repository};
+
+#[derive(Debug, Clone, Default)]
+pub struct GraphOptions {
+    /// Most base branch commits shown beside the stack
+    pub base_commits: usize,
+    /// `--fetch`/`--no-fetch`, overriding `git-stk.fetch` when given
+    pub fetch: Option<bool>,
+}
+
+/// Show the stack as a graph: slot branches and PR chain above the base
+/// branch commits the stack forked from
+pub async fn graph(options: GraphOptions) -> Result<()> {
+    let git_repo = Repository::open(".").context("Failed to open git repository")?;
+    let mut config = Config::load(&git_repo)?;
+    config.fetch = options.fetch.unwrap_or(config.fetch);
+    let gh_client = client::create_configured_client(&config)?;
+
+    let stack = discover_stack(&git_repo, &config, &gh_client).await?;
+    if stack.is_empty() {
+        println!("{}", style("No commits in stack").dim());
+        return Ok(());
+    }
+
+    let head = git_repo
+        .head()
+        .and_then(|head| head.peel_to_commit())
+        .context("Failed to resolve HEAD")?
+        .id();
+    let lane = base_lane(&git_repo, &config, head, options.base_commits)?;
+
+    render_graph(&stack, &lane, &config.remote);
+    Ok(())
+}
+
+/// Base commits between the merge-base with `head` and the tip of the base,
+/// preferring the remote-tracking branch since that's what PRs merge into
+fn base_lane(repo: &Repository, config: &Config, head: Oid, limit: usize) -> Result<BaseLane> {
+    let mut decorations: Vec<(String, Oid)> = Vec::new();
+    if let Some(branch) = &config.base_branch {
+        for name in [
+            format!("refs/heads/{}", branch),
+            format!("refs/remotes/{}/{}", config.remote, branch),
+        ] {
+            if let Ok(oid) = repo.refname_to_id(&name) {
+                let short = name
+                    .trim_start_matches("refs/heads/")
+                    .trim_start_matches("refs/remotes/");
+                decorations.push((short.to_string(), oid));
+            }
+        }
+    }
+
+    let tip = match decorations.last() {
+        Some((_, oid)) => *oid,
+        None => repo
+            .revparse_single(&config.base)
+            .and_then(|object| object.peel_to_commit())
+            .with_context(|| format!("Failed to resolve base '{}' to a commit", config.base))?
+            .id(),
+    };
+    let merge_base = repo
+        .merge_base(head, tip)
+        .with_context(|| format!("HEAD has no common history with {}", config.base))?;
+
+    let mut revwalk = repo.revwalk().context("Failed to create revwalk")?;
+    revwalk.push(tip)?;
+    revwalk.hide(merge_base)?;
+    revwalk.simplify_first_parent()?;
+    let ahead: Vec<Oid> = revwalk
+        .collect::<Result<_, _>>()
+        .context("Failed to walk base commits")?;
+
+    let describe = |oid: Oid| -> Result<BaseCommit> {
+        let commit = repo.find_commit(oid).context("Failed to find commit")?;
+        Ok(BaseCommit {
+            short_sha: format!("{:.7}", oid),
+            subject: commit.summary().unwrap_or("<no subject>").to_string(),
+            refs: decorations
+                .iter()
+                .filter(|(_, target)| *target == oid)
+                .map(|(name, _)| name.clone())
+                .collect(),
+        })
+    };
+
+    let commits = ahead
+        .iter()
+        .take(limit)
+        .map(|&oid| describe(oid))
+        .collect::<Result<_>>()?;
+    Ok(BaseLane {
+        commits,
+        hidden: ahead.len().saturating_sub(limit),
+        merge_base: describe(merge_base)?,
+    })
+}
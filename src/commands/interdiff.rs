@@ -0,0 +1,147 @@
+use crate::git::{commit_ref, range_diff, refs, slots};
+use crate::model::{Config, LastExport};
+use crate::stack::discover_local_stack;
+use anyhow::{Context, Result, bail};
+use console::style;
+use git2::{Oid, Repository};
+use std::process::Command;
+
+/// Show what changed in an entry since reviewers last saw it, as a range-diff
+///
+/// The entry is compared with the commit its last export pushed, or, when it
+/// wasn't rewritten since, with the commit that export replaced on the slot
+/// branch (recorded in the notes since that export). With `revision`, it's
+/// compared with that archived revision of its slot instead.
+pub async fn interdiff(commit_ref: &str, revision: Option<u32>) -> Result<()> {
+    let git_repo = Repository::open(".").context("Failed to open git repository")?;
+    let config = Config::load(&git_repo)?;
+
+    let stack = discover_local_stack(&git_repo, &config)?;
+    let commit_oid = commit_ref::resolve_commit_ref(&git_repo, &stack, commit_ref)?;
+    let entry = stack
+        .entries
+        .iter()
+        .find(|e| e.oid == commit_oid)
+        .with_context(|| format!("Commit {} is not part of the current stack", commit_ref))?;
+
+    let old = match revision {
+        Some(revision) => {
+            let slot = entry
+                .slot
+                .as_deref()
+                .with_context(|| format!("{} has no slot yet, so no revisions", entry.short_sha))?;
+            let archive_ref = slots::archive_ref(&stack.current_branch, slot, revision);
+            match git_repo.refname_to_id(&archive_ref) {
+                Ok(oid) => oid,
+                // Exported from another clone: export pushes the archive refs too
+                Err(_) => {
+                    refs::fetch_refspecs(
+                        &git_repo,
+                        &config.remote,
+                        std::slice::from_ref(&archive_ref),
+                        &config.transport,
+                    )
+                    .with_context(|| {
+                        format!(
+                            "Revision {} of slot {} isn't archived here or on {}",
+                            revision, slot, config.remote
+                        )
+                    })?;
+                    git_repo.refname_to_id("FETCH_HEAD")?
+                }
+            }
+        }
+        None => {
+            let Some(old) = interdiff_base(entry.last_export.as_ref(), entry.oid) else {
+                bail!(
+                    "No earlier version of {} was exported. Interdiffs are recorded from its next export on.",
+                    entry.short_sha
+                );
+            };
+            Oid::from_str(&old).context("Invalid commit in the notes")?
+        }
+    };
+
+    // The old version may only exist on the remote, e.g. when exported from another machine
+    if git_repo.find_commit(old).is_err() {
+        refs::fetch_refspecs(
+            &git_repo,
+            &config.remote,
+            &[old.to_string()],
+            &config.transport,
+        )
+        .with_context(|| {
+            format!(
+                "Commit {:.7} is neither local nor on {}",
+                old, config.remote
+            )
+        })?;
+    }
+
+    println!(
+        "🔀 {} {}: {} → {}\n",
+        style(entry.slot.as_deref().unwrap_or(&entry.short_sha)).yellow(),
+        style(&entry.subject).bold(),
+        style(format!("{:.7}", old)).dim(),
+        style(&entry.short_sha).cyan()
+    );
+
+    let repo_path = git_repo
+        .workdir()
+        .context("Repository has no working directory")?;
+    let status = Command::new("git")
+        .current_dir(repo_path)
+        .arg("range-diff")
+        .args(range_diff::range_diff_args(old, entry.oid))
+        .status()
+        .context("Failed to execute git range-diff")?;
+    if !status.success() {
+        bail!("git range-diff failed");
+    }
+
+    Ok(())
+}
+
+/// The version of an entry to diff against: the commit of the last export when
+/// the entry was rewritten since, the commit that export replaced otherwise
+fn interdiff_base(last_export: Option<&LastExport>, oid: Oid) -> Option<String> {
+    let last = last_export?;
+    if last.oid != oid.to_string() {
+        Some(last.oid.clone())
+    } else {
+        last.previous.clone()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_interdiff_base() {
+        let oid = Oid::from_str("1111111111111111111111111111111111111111").unwrap();
+        let export = |exported: &str, previous: Option<&str>| LastExport {
+            at: 0,
+            oid: exported.to_string(),
+            previous: previous.map(String::from),
+            revision: 1,
+        };
+
+        assert_eq!(interdiff_base(None, oid), None);
+        // Unchanged since the export: diff against what it replaced
+        let unchanged = export(&oid.to_string(), Some("2222222"));
+        assert_eq!(
+            interdiff_base(Some(&unchanged), oid),
+            Some("2222222".to_string())
+        );
+        assert_eq!(
+            interdiff_base(Some(&export(&oid.to_string(), None)), oid),
+            None
+        );
+        // Rewritten locally: diff against the exported version
+        assert_eq!(
+            interdiff_base(Some(&export("3333333", Some("2222222"))), oid),
+            Some("3333333".to_string())
+        );
+    }
+}
@@ -1,47 +1,121 @@
-use crate::gh::{client, mutations, queries};
-use crate::model::{Config, PrState};
-use crate::stack::discover_stack;
+use crate::gh::mutations::MergeOutcome;
+use crate::gh::{
+    client::{self, Client},
+    mutations, queries,
+};
+use crate::git::issue_keys;
+use crate::model::{Config, PrState, Stack};
+use crate::stack::{discover_stack, ensure_online};
 use crate::ui::callout;
-use crate::workflows;
-use anyhow::{bail, Context, Result};
-use console::style;
+use crate::workflows::{self, autostash, pending_land};
+use anyhow::{Context, Result, bail};
+use console::{Term, style};
 use git2::Repository;
-use std::time::Duration;
+use std::time::{Duration, Instant};
+use tracing::debug;
 
-pub async fn land(skip_wait: bool) -> Result<()> {
+#[derive(Debug, Clone, Default)]
+pub struct LandOptions {
+    /// Skip waiting for the merge to complete
+    pub skip_wait: bool,
+    /// Re-sign commits rewritten by the post-merge rebase
+    pub gpg_sign: bool,
+    /// Add Signed-off-by to commits rewritten by the post-merge rebase
+    pub signoff: bool,
+    /// After each landing, offer to land the next PR of the restacked stack
+    pub then_next: bool,
+    /// Stash uncommitted changes before rebasing and restore them after
+    pub autostash: bool,
+    /// Merge commit title template, overrides `git-stk.mergeCommitTitle`
+    pub commit_title: Option<String>,
+    /// Merge commit message template, overrides `git-stk.mergeCommitMessage`
+    pub commit_message: Option<String>,
+    /// `--fetch`/`--no-fetch`, overriding `git-stk.fetch` when given
+    pub fetch: Option<bool>,
+}
+
+pub async fn land(options: LandOptions) -> Result<()> {
     let git_repo = Repository::open(".")
         .context("Failed to open git repository. Are you in a git repository?")?;
 
-    // Check for uncommitted changes (excluding ignored files)
-    if git_repo.statuses(None)?.iter().any(|s| {
-        let status = s.status();
-        status.intersects(
-            git2::Status::INDEX_NEW
-                | git2::Status::INDEX_MODIFIED
-                | git2::Status::INDEX_DELETED
-                | git2::Status::INDEX_RENAMED
-                | git2::Status::INDEX_TYPECHANGE
-                | git2::Status::WT_NEW
-                | git2::Status::WT_MODIFIED
-                | git2::Status::WT_DELETED
-                | git2::Status::WT_RENAMED
-                | git2::Status::WT_TYPECHANGE
-                | git2::Status::CONFLICTED,
-        )
-    }) {
-        bail!("You have uncommitted changes. Please commit or stash them before landing.");
-    }
+    let mut config = Config::load(&git_repo)?;
+    config.fetch = options.fetch.unwrap_or(config.fetch);
+    config.gpg_sign |= options.gpg_sign;
+    config.signoff |= options.signoff;
+    config.autostash |= options.autostash;
+    let gh_client = client::create_configured_client(&config)?;
 
-    let config = Config::load(&git_repo)?;
-    let gh_client = client::create_client()?;
+    workflows::ensure_on_branch(&git_repo, &config, false)?;
 
+    // Uncommitted changes (excluding ignored files) would block the rebase
+    let stash = autostash::stash_if_dirty(&git_repo, &config, "landing")?;
+    let result = land_stack(&git_repo, &config, &gh_client, &options).await;
+    autostash::restore(&git_repo, stash, result.is_ok());
+    result
+}
+
+/// Land the bottom PR, then the next ones with `--then-next`
+async fn land_stack(
+    git_repo: &Repository,
+    config: &Config,
+    gh_client: &Client,
+    options: &LandOptions,
+) -> Result<()> {
     // Discover the stack with full state (includes remote refs and PR state)
-    let stack = discover_stack(&git_repo, &config, &gh_client).await?;
+    let mut stack = discover_stack(git_repo, config, gh_client).await?;
 
     if stack.is_empty() {
         bail!("No commits in stack to land.");
     }
+    ensure_online(&stack, config, "land")?;
+
+    loop {
+        if !land_bottom(git_repo, config, gh_client, &stack, options).await? {
+            return Ok(());
+        }
+
+        println!("\n{} Successfully landed!", style("🎉").green());
+        if !options.then_next {
+            return Ok(());
+        }
+
+        // The post-merge export retargeted the next PR at the base
+        stack = discover_stack(git_repo, config, gh_client).await?;
+        ensure_online(&stack, config, "land")?;
+        let Some(next) = stack.entries.first() else {
+            println!("\n{} The whole stack has landed", style("✨").green());
+            return Ok(());
+        };
 
+        let prompt = match next.pr_number {
+            Some(pr) => format!("Land next PR #{} ({})?", pr, next.subject),
+            None => format!("Land next commit {} ({})?", next.short_sha, next.subject),
+        };
+        let confirmed = dialoguer::Confirm::new()
+            .with_prompt(prompt)
+            .default(false)
+            .interact()?;
+        if !confirmed {
+            println!(
+                "\n{} Stopped with {} PR{} left. Run 'git stk land' to continue.",
+                style("ℹ").blue(),
+                stack.len(),
+                if stack.len() == 1 { "" } else { "s" }
+            );
+            return Ok(());
+        }
+    }
+}
+
+/// Merge the bottom PR of `stack` and restack on top of it. Returns false when
+/// the merge was only initiated (`--skip-wait`).
+async fn land_bottom(
+    git_repo: &Repository,
+    config: &Config,
+    gh_client: &Client,
+    stack: &Stack,
+    options: &LandOptions,
+) -> Result<bool> {
     // Get the bottom commit (first in the stack)
     let bottom_entry = &stack.entries[0];
 
@@ -72,15 +146,15 @@ pub async fn land(skip_wait: bool) -> Result<()> {
         );
     }
 
-    if let Some(remote_oid) = bottom_entry.remote_oid {
-        if remote_oid != bottom_entry.oid {
-            bail!(
-                "Remote branch {} points to {} but expected {}. Run 'git stk export' to push your changes.",
-                head_ref,
-                &remote_oid.to_string()[..7],
-                &bottom_entry.oid.to_string()[..7]
-            );
-        }
+    if let Some(remote_oid) = bottom_entry.remote_oid
+        && remote_oid != bottom_entry.oid
+    {
+        bail!(
+            "Remote branch {} points to {} but expected {}. Run 'git stk export' to push your changes.",
+            head_ref,
+            &remote_oid.to_string()[..7],
+            &bottom_entry.oid.to_string()[..7]
+        );
     }
 
     println!("  {} Remote branch is in sync", style("✓").green());
@@ -123,71 +197,464 @@ pub async fn land(skip_wait: bool) -> Result<()> {
             );
 
             // Fetch PR body for cleanup (we need the full body, not just state)
-            let pr_info = queries::get_pr(&gh_client, owner, repo_name, pr_number).await?;
+            let pr_info = queries::get_pr(gh_client, owner, repo_name, pr_number).await?;
 
             // Strip stack callout from PR body
             let clean_body = callout::strip_callout(&pr_info.body);
 
-            // Merge the PR with cleaned body
+            let projects = config
+                .issue_links
+                .as_ref()
+                .map(|l| l.projects.as_slice())
+                .unwrap_or_default();
+            let fields = MergeFields {
+                number: pr_number,
+                title: &pr_info.title,
+                subject: &bottom_entry.subject,
+                body: &clean_body,
+                issue_keys: issue_keys::find_issue_keys(&bottom_entry.subject, projects),
+            };
+            let title = options
+                .commit_title
+                .as_ref()
+                .or(config.merge_commit_title.as_ref())
+                .map(|template| render_merge_template(template, &fields));
+            let message = match options
+                .commit_message
+                .as_ref()
+                .or(config.merge_commit_message.as_ref())
+            {
+                Some(template) => render_merge_template(template, &fields),
+                None => clean_body.clone(),
+            };
+
+            // Merge the PR with the templated (or cleaned) message
             println!("\n🔀 Merging PR #{}...", pr_number);
-            mutations::merge_pull_request(
-                &gh_client,
+            let outcome = mutations::merge_pull_request(
+                gh_client,
                 owner,
                 repo_name,
                 pr_number,
-                None, // Use default title
-                if clean_body.is_empty() {
-                    None
-                } else {
-                    Some(&clean_body)
-                },
+                title.as_deref().filter(|t| !t.is_empty()),
+                Some(message.as_str()).filter(|m| !m.is_empty()),
             )
             .await?;
+            if let MergeOutcome::Blocked(message) = outcome {
+                return Err(
+                    merge_blocked_error(gh_client, owner, repo_name, pr_number, &message).await,
+                );
+            }
             println!("  {} Merge initiated", style("✓").green());
 
-            if !skip_wait {
-                // Poll until merged
+            if !options.skip_wait {
                 println!(
                     "\n⏳ Waiting for merge to complete (timeout: {} minutes)...",
                     config.land_timeout_minutes
                 );
-                let mut attempts = 0;
-                let max_attempts = config.land_timeout_minutes * 12; // 5 second intervals
+                wait_for_merge(config, gh_client, owner, repo_name, pr_number).await?;
+            } else {
+                pending_land::record(git_repo, &stack.current_branch, pr_number, bottom_entry.oid)?;
+                println!(
+                    "\n{} Skipping merge wait. Run 'git stk landed' after the PR is merged.",
+                    style("ℹ").blue()
+                );
+                return Ok(false);
+            }
+        }
+    }
 
-                loop {
-                    attempts += 1;
-                    if attempts > max_attempts {
-                        bail!("Timeout waiting for PR to merge. Check GitHub for status.");
-                    }
+    // Run post-merge operations with the landed commit OID
+    workflows::run_post_merge_operations(git_repo, config, bottom_entry.oid).await?;
 
-                    tokio::time::sleep(Duration::from_secs(5)).await;
+    Ok(true)
+}
 
-                    let pr_status =
-                        queries::get_pr(&gh_client, owner, repo_name, pr_number).await?;
+/// Poll the PR every `git-stk.landPollSeconds` until it merges, with a spinner
+/// showing what it waits on and the time left. Fails early when the PR can't
+/// merge any more (closed, or conflicting with its base).
+async fn wait_for_merge(
+    config: &Config,
+    gh_client: &Client,
+    owner: &str,
+    repo_name: &str,
+    pr_number: u64,
+) -> Result<()> {
+    const SPINNER: [&str; 10] = ["⠋", "⠙", "⠹", "⠸", "⠼", "⠴", "⠦", "⠧", "⠇", "⠏"];
+    const TICK: Duration = Duration::from_millis(100);
 
-                    if pr_status.state == PrState::Merged {
-                        println!("  {} PR merged successfully!", style("✓").green());
-                        break;
-                    }
+    let term = Term::stdout();
+    let interactive = term.is_term();
+    let timeout = Duration::from_secs(config.land_timeout_minutes * 60);
+    let poll_interval = Duration::from_secs(config.land_poll_seconds);
+    let started = Instant::now();
+    let mut next_poll = started;
+    let mut status = String::new();
+    let mut frame = 0;
 
-                    print!(".");
-                    use std::io::Write;
-                    std::io::stdout().flush().ok();
+    loop {
+        if Instant::now() >= next_poll {
+            let progress =
+                queries::get_merge_progress(gh_client, owner, repo_name, pr_number).await?;
+            match progress.state {
+                PrState::Merged => {
+                    if interactive {
+                        term.clear_line().ok();
+                    }
+                    println!(
+                        "  {} PR merged successfully! ({})",
+                        style("✓").green(),
+                        format_wait(started.elapsed())
+                    );
+                    return Ok(());
                 }
-            } else {
-                println!(
-                    "\n{} Skipping merge wait. Run 'git stk landed' after the PR is merged.",
-                    style("ℹ").blue()
+                PrState::Closed => {
+                    if interactive {
+                        term.clear_line().ok();
+                    }
+                    bail!("PR #{} was closed without being merged", pr_number);
+                }
+                PrState::Open | PrState::Draft => {}
+            }
+            if progress.merge_state == "DIRTY" {
+                if interactive {
+                    term.clear_line().ok();
+                }
+                bail!(
+                    "PR #{} conflicts with its base branch and can't merge. Rebase the stack, export, and land again.",
+                    pr_number
                 );
-                return Ok(());
             }
+
+            let described = describe_merge_progress(&progress);
+            if !interactive && described != status {
+                println!("  {}", described);
+            }
+            status = described;
+            next_poll = Instant::now() + poll_interval;
+        }
+
+        let elapsed = started.elapsed();
+        if elapsed >= timeout {
+            if interactive {
+                term.clear_line().ok();
+            }
+            bail!(
+                "Timeout waiting for PR to merge ({}). Check the PR for status.",
+                status
+            );
+        }
+        if interactive {
+            term.clear_line().ok();
+            term.write_str(&format!(
+                "  {} {} {}",
+                style(SPINNER[frame % SPINNER.len()]).cyan(),
+                status,
+                style(format!(
+                    "· {} elapsed, {} left",
+                    format_wait(elapsed),
+                    format_wait(timeout - elapsed)
+                ))
+                .dim()
+            ))
+            .ok();
+            frame += 1;
+        }
+        tokio::time::sleep(TICK.min(next_poll.saturating_duration_since(Instant::now()))).await;
+    }
+}
+
+/// Explain a merge refused by branch protection: the requirements the PR
+/// doesn't meet yet, or the forge's own message when they can't be determined
+async fn merge_blocked_error(
+    gh_client: &Client,
+    owner: &str,
+    repo_name: &str,
+    pr_number: u64,
+    message: &str,
+) -> anyhow::Error {
+    let blockers =
+        match queries::get_merge_requirements(gh_client, owner, repo_name, pr_number).await {
+            Ok(requirements) => merge_blockers(&requirements),
+            Err(e) => {
+                debug!(error = %e, "merge requirements unavailable");
+                Vec::new()
+            }
+        };
+
+    let forge = gh_client.forge().name();
+    let mut error = format!(
+        "{} refused to merge PR #{}: {}",
+        forge,
+        pr_number,
+        message.trim_end_matches('.')
+    );
+    if blockers.is_empty() {
+        error.push_str(&format!(
+            "\n\n💡 Check the merge box of the PR on {} for what's missing, then land again.",
+            forge
+        ));
+    } else {
+        error.push_str("\n\nStill needed:");
+        for blocker in &blockers {
+            error.push_str(&format!("\n  - {}", blocker));
         }
+        error.push_str("\n\n💡 Land again once these are met.");
     }
+    anyhow::anyhow!(error)
+}
 
-    // Run post-merge operations with the landed commit OID
-    workflows::run_post_merge_operations(&git_repo, &config, bottom_entry.oid).await?;
+/// Requirements of the base branch's protection that the PR doesn't meet yet
+fn merge_blockers(requirements: &queries::MergeRequirements) -> Vec<String> {
+    let mut blockers = Vec::new();
 
-    println!("\n{} Successfully landed!", style("🎉").green());
+    match requirements.required_approvals {
+        Some(required) if (requirements.approvals as u64) < required => blockers.push(format!(
+            "{} of {} required approval{}",
+            requirements.approvals,
+            required,
+            if required == 1 { "" } else { "s" }
+        )),
+        _ if requirements.review_decision.as_deref() == Some("REVIEW_REQUIRED") => {
+            blockers.push("An approving review".to_string())
+        }
+        _ => {}
+    }
+    if requirements.code_owner_reviews
+        && requirements.review_decision.as_deref() == Some("REVIEW_REQUIRED")
+    {
+        blockers.push("A review from a code owner".to_string());
+    }
+    if !requirements.changes_requested.is_empty() {
+        let reviewers: Vec<String> = requirements
+            .changes_requested
+            .iter()
+            .map(|r| format!("@{}", r))
+            .collect();
+        blockers.push(format!(
+            "Changes requested by {} to be addressed",
+            reviewers.join(", ")
+        ));
+    }
 
-    Ok(())
+    // Only required checks block a merge; without the rule, every check may
+    let required = |name: &String| {
+        requirements.required_checks.is_empty() || requirements.required_checks.contains(name)
+    };
+    let label = if requirements.required_checks.is_empty() {
+        "Check"
+    } else {
+        "Required check"
+    };
+    for check in requirements.failed_checks.iter().filter(|c| required(c)) {
+        blockers.push(format!("{} \"{}\" failed", label, check));
+    }
+    for check in requirements.pending_checks.iter().filter(|c| required(c)) {
+        blockers.push(format!("{} \"{}\" hasn't finished", label, check));
+    }
+    let reported = |name: &String| {
+        [
+            &requirements.failed_checks,
+            &requirements.pending_checks,
+            &requirements.passed_checks,
+        ]
+        .iter()
+        .any(|checks| checks.contains(name))
+    };
+    for check in requirements.required_checks.iter().filter(|c| !reported(c)) {
+        blockers.push(format!("Required check \"{}\" hasn't run", check));
+    }
+
+    if requirements.strict_checks && requirements.merge_state == "BEHIND" {
+        blockers.push(format!(
+            "Being up to date with {} (run 'git stk export --update-behind')",
+            requirements.base_ref
+        ));
+    }
+    if requirements.conversation_resolution && requirements.unresolved_threads > 0 {
+        blockers.push(format!(
+            "{} unresolved conversation{} (see 'git stk threads')",
+            requirements.unresolved_threads,
+            if requirements.unresolved_threads == 1 {
+                ""
+            } else {
+                "s"
+            }
+        ));
+    }
+
+    blockers
+}
+
+/// What a PR waits on before merging, e.g. "blocked · checks 3/5 done"
+fn describe_merge_progress(progress: &queries::MergeProgress) -> String {
+    let merge = match progress.merge_state.as_str() {
+        "CLEAN" | "HAS_HOOKS" => "ready, merging".to_string(),
+        "UNSTABLE" => "merging despite failing optional checks".to_string(),
+        "BLOCKED" => "blocked by required reviews or checks".to_string(),
+        "BEHIND" => "behind its base branch".to_string(),
+        "DRAFT" => "still a draft".to_string(),
+        "UNKNOWN" => "mergeability is being computed".to_string(),
+        other => other.to_lowercase().replace('_', " "),
+    };
+    if progress.checks_total == 0 {
+        return merge;
+    }
+    let checks = if progress.checks_failed > 0 {
+        format!(
+            "{} of {} checks failed",
+            progress.checks_failed, progress.checks_total
+        )
+    } else if progress.checks_done < progress.checks_total {
+        format!(
+            "checks {}/{} done",
+            progress.checks_done, progress.checks_total
+        )
+    } else {
+        "checks passed".to_string()
+    };
+    format!("{} · {}", merge, checks)
+}
+
+/// Compact duration for the merge wait: "45s", "3m05s"
+fn format_wait(duration: Duration) -> String {
+    let secs = duration.as_secs();
+    if secs < 60 {
+        format!("{}s", secs)
+    } else {
+        format!("{}m{:02}s", secs / 60, secs % 60)
+    }
+}
+
+/// Values for the placeholders of merge commit templates
+struct MergeFields<'a> {
+    number: u64,
+    title: &'a str,
+    subject: &'a str,
+    body: &'a str,
+    issue_keys: Vec<String>,
+}
+
+/// Fill a merge commit title or message template: `{number}`, `{title}` (the
+/// PR's), `{subject}` (the commit's), `{body}` (the PR body without the stack
+/// callout) and `{issue_keys}` (from the subject, comma-separated). A literal
+/// `\n`, as git config and shells keep it, is a line break.
+fn render_merge_template(template: &str, fields: &MergeFields) -> String {
+    template
+        .replace("\\n", "\n")
+        .replace("{number}", &fields.number.to_string())
+        .replace("{title}", fields.title)
+        .replace("{subject}", fields.subject)
+        .replace("{issue_keys}", &fields.issue_keys.join(", "))
+        // Last, so placeholders typed in the body are left alone
+        .replace("{body}", fields.body)
+        .trim()
+        .to_string()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_describe_merge_progress() {
+        let mut progress = queries::MergeProgress {
+            state: PrState::Open,
+            merge_state: "BLOCKED".to_string(),
+            checks_total: 5,
+            checks_done: 3,
+            checks_failed: 0,
+        };
+        assert_eq!(
+            describe_merge_progress(&progress),
+            "blocked by required reviews or checks · checks 3/5 done"
+        );
+        progress.checks_failed = 1;
+        assert_eq!(
+            describe_merge_progress(&progress),
+            "blocked by required reviews or checks · 1 of 5 checks failed"
+        );
+        progress.checks_total = 0;
+        progress.merge_state = "CLEAN".to_string();
+        assert_eq!(describe_merge_progress(&progress), "ready, merging");
+    }
+
+    #[test]
+    fn test_merge_blockers() {
+        let requirements = queries::MergeRequirements {
+            base_ref: "main".to_string(),
+            merge_state: "BEHIND".to_string(),
+            review_decision: Some("REVIEW_REQUIRED".to_string()),
+            required_approvals: Some(2),
+            required_checks: vec![
+                "build".to_string(),
+                "lint".to_string(),
+                "deploy".to_string(),
+            ],
+            strict_checks: true,
+            conversation_resolution: true,
+            approvals: 1,
+            changes_requested: vec!["bob".to_string()],
+            failed_checks: vec!["build".to_string(), "flaky".to_string()],
+            pending_checks: vec!["lint".to_string()],
+            unresolved_threads: 2,
+            ..Default::default()
+        };
+        assert_eq!(
+            merge_blockers(&requirements),
+            vec![
+                "1 of 2 required approvals",
+                "Changes requested by @bob to be addressed",
+                "Required check \"build\" failed",
+                "Required check \"lint\" hasn't finished",
+                "Required check \"deploy\" hasn't run",
+                "Being up to date with main (run 'git stk export --update-behind')",
+                "2 unresolved conversations (see 'git stk threads')",
+            ]
+        );
+
+        // Without access to the rule, failing checks are all suspects
+        let unreadable = queries::MergeRequirements {
+            review_decision: Some("REVIEW_REQUIRED".to_string()),
+            failed_checks: vec!["flaky".to_string()],
+            ..Default::default()
+        };
+        assert_eq!(
+            merge_blockers(&unreadable),
+            vec!["An approving review", "Check \"flaky\" failed"]
+        );
+    }
+
+    #[test]
+    fn test_format_wait() {
+        assert_eq!(format_wait(Duration::from_secs(45)), "45s");
+        assert_eq!(format_wait(Duration::from_secs(185)), "3m05s");
+    }
+
+    #[test]
+    fn test_render_merge_template() {
+        let fields = MergeFields {
+            number: 42,
+            title: "Add parser",
+            subject: "ABC-7: Add parser",
+            body: "Parses {title} strings.",
+            issue_keys: vec!["ABC-7".to_string()],
+        };
+        assert_eq!(
+            render_merge_template("{title} (#{number})", &fields),
+            "Add parser (#42)"
+        );
+        assert_eq!(
+            render_merge_template("{body}\\n\\nRefs: {issue_keys}", &fields),
+            "Parses {title} strings.\n\nRefs: ABC-7"
+        );
+        let no_keys = MergeFields {
+            issue_keys: Vec::new(),
+            body: "",
+            ..fields
+        };
+        assert_eq!(
+            render_merge_template("{subject}\n{body}", &no_keys),
+            "ABC-7: Add parser"
+        );
+    }
 }
@@ -1,17 +1,41 @@
-use crate::gh::client;
-use crate::model::Config;
+use crate::gh::{
+    client::{self, Client},
+    queries,
+};
+use crate::git::{patch_id, refs, repo};
+use crate::model::{Config, Entry, PrState};
 use crate::stack::discover_stack;
-use crate::workflows;
-use anyhow::{bail, Context};
+use crate::workflows::{self, autostash, pending_land, pending_land::PendingLands};
+use anyhow::{Context, bail};
 use console::style;
 use git2::Repository;
 
-pub async fn landed() -> anyhow::Result<()> {
+#[derive(Debug, Clone, Default)]
+pub struct LandedOptions {
+    /// Re-sign commits rewritten by the post-merge rebase
+    pub gpg_sign: bool,
+    /// Add Signed-off-by to commits rewritten by the post-merge rebase
+    pub signoff: bool,
+    /// Skip verifying that the merged content matches the stack commit
+    pub force: bool,
+    /// Stash uncommitted changes before rebasing and restore them after
+    pub autostash: bool,
+    /// `--fetch`/`--no-fetch`, overriding `git-stk.fetch` when given
+    pub fetch: Option<bool>,
+}
+
+pub async fn landed(options: LandedOptions) -> anyhow::Result<()> {
     println!("\n{} Checking for merged commits...", style("🔧").cyan());
 
     let git_repo = Repository::open(".").context("Failed to open git repository")?;
-    let config = Config::load(&git_repo)?;
-    let gh_client = client::create_client()?;
+    let mut config = Config::load(&git_repo)?;
+    config.fetch = options.fetch.unwrap_or(config.fetch);
+    config.gpg_sign |= options.gpg_sign;
+    config.signoff |= options.signoff;
+    config.autostash |= options.autostash;
+    let gh_client = client::create_configured_client(&config)?;
+
+    workflows::ensure_on_branch(&git_repo, &config, false)?;
 
     // Discover stack with full state (includes PR merged status from GitHub)
     let stack = discover_stack(&git_repo, &config, &gh_client).await?;
@@ -20,16 +44,57 @@ pub async fn landed() -> anyhow::Result<()> {
         bail!("No commits in stack - nothing to clean up after landing");
     }
 
-    // Find the first merged commit (should be at the bottom of the stack)
-    let merged_entry = stack.entries.iter().find(|e| e.merged_into_main);
+    // A `land --skip-wait` names the PR to finish; otherwise look for the first merged commit
+    let pending = PendingLands::load(&git_repo)?
+        .get(&stack.current_branch)
+        .cloned();
+    let pending_entry = pending.as_ref().and_then(|land| {
+        stack
+            .entries
+            .iter()
+            .find(|e| e.pr_number == Some(land.pr) || e.oid.to_string() == land.commit)
+    });
 
-    let landed_commit_oid = if let Some(entry) = merged_entry {
+    let landed_commit_oid = if let (Some(land), Some(entry)) = (&pending, pending_entry) {
+        println!(
+            "  {} Finishing landing of PR #{}: {} {}",
+            style("✓").green(),
+            land.pr,
+            style(&entry.short_sha).yellow(),
+            style(&entry.subject).dim()
+        );
+
+        if entry.pr_state != Some(PrState::Merged) && !options.force {
+            bail!(
+                "PR #{} is not merged yet. Run 'git stk landed' again once it is (or --force to clean up anyway).",
+                land.pr
+            );
+        }
+        if !options.force {
+            verify_merged_content(&git_repo, &config, &gh_client, entry).await?;
+        }
+
+        entry.oid
+    } else if let Some(entry) = stack.entries.iter().find(|e| e.merged_into_main) {
+        if let Some(land) = &pending {
+            println!(
+                "  {} PR #{} from 'land --skip-wait' is no longer in the stack, forgetting it",
+                style("ℹ").blue(),
+                land.pr
+            );
+            pending_land::clear(&git_repo, &stack.current_branch)?;
+        }
         println!(
             "  {} Found merged commit: {} {}",
             style("✓").green(),
             style(&entry.short_sha).yellow(),
             style(&entry.subject).dim()
         );
+
+        if !options.force {
+            verify_merged_content(&git_repo, &config, &gh_client, entry).await?;
+        }
+
         entry.oid
     } else {
         // Fallback: assume bottom commit was landed (for backwards compatibility)
@@ -41,9 +106,89 @@ pub async fn landed() -> anyhow::Result<()> {
     };
 
     // Run post-merge operations with the landed commit OID
-    workflows::run_post_merge_operations(&git_repo, &config, landed_commit_oid).await?;
+    let stash = autostash::stash_if_dirty(&git_repo, &config, "rebasing")?;
+    let result = workflows::run_post_merge_operations(&git_repo, &config, landed_commit_oid).await;
+    autostash::restore(&git_repo, stash, result.is_ok());
+    result?;
 
     println!("\n{} Post-merge operations completed!", style("✓").green());
 
     Ok(())
 }
+
+/// Verify the PR's merge commit carries the same change as the stack commit.
+///
+/// With squash merges the merge commit SHA never matches the stack commit, so
+/// PR state alone can't tell whether the entry we'd clean up is the one that
+/// actually landed (e.g., the commit was rewritten after merging). Compare
+/// trees/patch IDs instead. Verification is skipped (with a warning) when the
+/// merge commit can't be obtained.
+async fn verify_merged_content(
+    git_repo: &Repository,
+    config: &Config,
+    gh_client: &Client,
+    entry: &Entry,
+) -> anyhow::Result<()> {
+    let (Some(pr_number), Some(owner), Some(repo_name)) =
+        (entry.pr_number, &entry.repo_owner, &entry.repo_name)
+    else {
+        return Ok(());
+    };
+
+    let pr_info = queries::get_pr(gh_client, owner, repo_name, pr_number).await?;
+    let Some(merge_oid) = pr_info
+        .merge_commit_sha
+        .as_deref()
+        .and_then(|sha| repo::resolve_sha(git_repo, sha))
+    else {
+        println!(
+            "  {} PR #{} has no merge commit, skipping content verification",
+            style("⚠").yellow(),
+            pr_number
+        );
+        return Ok(());
+    };
+
+    if git_repo.find_commit(merge_oid).is_err() {
+        fetch_base(git_repo, config);
+    }
+    if git_repo.find_commit(merge_oid).is_err() {
+        println!(
+            "  {} Merge commit {:.7} not available locally, skipping content verification",
+            style("⚠").yellow(),
+            merge_oid
+        );
+        return Ok(());
+    }
+
+    if patch_id::commits_equivalent(git_repo, entry.oid, merge_oid)? {
+        println!(
+            "  {} Merged content matches {} ({:.7})",
+            style("✓").green(),
+            config.base_name(),
+            merge_oid
+        );
+        Ok(())
+    } else {
+        bail!(
+            "PR #{} is merged, but its merge commit {:.7} doesn't match commit {}. \
+             The commit may have changed after the PR was merged. \
+             Re-run with --force to clean up anyway.",
+            pr_number,
+            merge_oid,
+            entry.short_sha
+        )
+    }
+}
+
+/// Best-effort fetch of the base branch so the merge commit is available locally
+fn fetch_base(git_repo: &Repository, config: &Config) {
+    if let Some(repo_path) = git_repo.workdir() {
+        let _ = refs::git_command(repo_path, &config.transport)
+            .arg("fetch")
+            .arg("--quiet")
+            .arg(&config.remote)
+            .arg(config.base_name())
+            .output();
+    }
+}
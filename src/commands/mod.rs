@@ -1,11 +1,61 @@
+pub mod abandon;
+pub mod adopt_branch;
+pub mod annotate;
+pub mod bisect;
+pub mod checkout_pr;
+pub mod collapse;
+pub mod comment;
+pub mod doctor;
 pub mod export;
+pub mod graph;
+pub mod interdiff;
 pub mod land;
 pub mod landed;
+pub mod navigate;
+pub mod open_pr;
+pub mod prompt;
+pub mod prune;
+pub mod rename_branch;
+pub mod restore;
+pub mod review;
+pub mod set_config;
 pub mod set_slot;
+pub mod state;
+pub mod stats;
+pub mod suggest;
+pub mod summary;
+pub mod test;
+pub mod threads;
 pub mod view;
+pub mod web;
 
-pub use export::{export, ExportOptions};
-pub use land::land;
-pub use landed::landed;
-pub use set_slot::set_slot;
-pub use view::view;
+pub use abandon::abandon;
+pub use adopt_branch::adopt_branch;
+pub use annotate::annotate;
+pub use bisect::bisect;
+pub use checkout_pr::checkout_pr;
+pub use collapse::collapse;
+pub use comment::comment;
+pub use doctor::doctor;
+pub use export::{ExportFormat, ExportOptions, export};
+pub use graph::{GraphOptions, graph};
+pub use interdiff::interdiff;
+pub use land::{LandOptions, land};
+pub use landed::{LandedOptions, landed};
+pub use navigate::{bottom, top};
+pub use open_pr::open_pr;
+pub use prompt::{PromptFormat, PromptState, prompt};
+pub use prune::prune;
+pub use rename_branch::rename_branch;
+pub use restore::restore;
+pub use review::review;
+pub use set_config::{set_base, set_remote};
+pub use set_slot::{parse_assignments, renumber_slots, set_slots};
+pub use state::{pull_state, push_state};
+pub use stats::{StatsOptions, stats};
+pub use suggest::{SuggestOptions, suggest};
+pub use summary::{SummaryOptions, summary};
+pub use test::test;
+pub use threads::{ThreadsOptions, threads};
+pub use view::{ViewFilter, ViewOptions, hold_clipboard, view};
+pub use web::{WebOptions, web};
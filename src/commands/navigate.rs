@@ -0,0 +1,115 @@
+use crate::git::repo;
+use crate::model::{Config, Stack};
+use crate::stack::{discover_branch_stack, discover_local_stack};
+use anyhow::{Context, Result, bail};
+use console::style;
+use git2::{Oid, Repository};
+
+/// The stack HEAD is in and HEAD's position in it (1-based; None when HEAD
+/// isn't one of its entries). A detached HEAD belongs to the branch it was
+/// checked out from, e.g. by `git stk bottom`.
+pub(crate) fn stack_at_head(
+    git_repo: &Repository,
+    config: &Config,
+) -> Result<(Stack, Option<usize>)> {
+    let head = git_repo
+        .head()
+        .context("Failed to get HEAD")?
+        .peel_to_commit()?
+        .id();
+    let stack = if repo::is_detached(git_repo)? {
+        let branch = repo::previous_branch(git_repo)?.context(
+            "HEAD is detached and the branch it was checked out from wasn't found. Check out the branch first.",
+        )?;
+        discover_branch_stack(git_repo, config, &branch)?
+    } else {
+        discover_local_stack(git_repo, config)?
+    };
+    let position = position_of(&stack, head);
+    Ok((stack, position))
+}
+
+fn position_of(stack: &Stack, oid: Oid) -> Option<usize> {
+    stack
+        .entries
+        .iter()
+        .position(|e| e.oid == oid)
+        .map(|idx| idx + 1)
+}
+
+/// Check out the bottom entry of the stack (detached), e.g. to amend it
+pub async fn bottom() -> Result<()> {
+    let git_repo = Repository::open(".").context("Failed to open git repository")?;
+    let config = Config::load(&git_repo)?;
+
+    let (stack, position) = stack_at_head(&git_repo, &config)?;
+    let Some(entry) = stack.entries.first() else {
+        bail!("No commits in stack");
+    };
+    if position == Some(1) {
+        println!(
+            "Already at the bottom of {}",
+            style(&stack.current_branch).cyan()
+        );
+        return Ok(());
+    }
+
+    repo::checkout(&git_repo, &entry.oid.to_string(), true)?;
+    println!(
+        "⤓ At entry 1/{} of {}: {} {}",
+        stack.len(),
+        style(&stack.current_branch).cyan(),
+        style(&entry.short_sha).yellow(),
+        entry.subject
+    );
+    println!(
+        "  {}",
+        style(format!(
+            "HEAD is detached; run 'git stk top' to return to {}",
+            stack.current_branch
+        ))
+        .dim()
+    );
+    Ok(())
+}
+
+/// Return to the top of the stack: the branch a detached HEAD was checked out from
+pub async fn top() -> Result<()> {
+    let git_repo = Repository::open(".").context("Failed to open git repository")?;
+    let config = Config::load(&git_repo)?;
+
+    if !repo::is_detached(&git_repo)? {
+        println!(
+            "Already at the top of {}",
+            style(repo::current_branch(&git_repo)?).cyan()
+        );
+        return Ok(());
+    }
+
+    let (stack, _) = stack_at_head(&git_repo, &config)?;
+    let head = git_repo.head()?.peel_to_commit()?.id();
+    let tip = git_repo
+        .find_branch(&stack.current_branch, git2::BranchType::Local)?
+        .get()
+        .peel_to_commit()?
+        .id();
+    // Commits made on the detached HEAD (e.g. an amended entry) aren't on the branch
+    if head != tip && !git_repo.graph_descendant_of(tip, head)? {
+        bail!(
+            "HEAD ({:.7}) has commits that aren't on {} and would be left behind. \
+             Rebase the branch onto them, or run 'git checkout {}' to leave them.",
+            head,
+            stack.current_branch,
+            stack.current_branch
+        );
+    }
+
+    repo::checkout(&git_repo, &stack.current_branch, false)?;
+    println!(
+        "⤒ At the top of {} ({} entr{})",
+        style(&stack.current_branch).cyan(),
+        stack.len(),
+        if stack.len() == 1 { "y" } else { "ies" }
+    );
+    Ok(())
+}
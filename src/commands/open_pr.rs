@@ -0,0 +1,30 @@
+use crate::commands::view::entry_pr_url;
+use crate::gh::client;
+use crate::git::commit_ref;
+use crate::model::Config;
+use crate::stack::discover_stack;
+use anyhow::{Context, Result};
+use console::style;
+use git2::Repository;
+
+/// Open the PRs of stack entries in the browser. `commit_ref` is a single
+/// reference or a range like `2..4`
+pub async fn open_pr(commit_ref: &str, fetch: Option<bool>) -> Result<()> {
+    let git_repo = Repository::open(".").context("Failed to open git repository")?;
+    let mut config = Config::load(&git_repo)?;
+    config.fetch = fetch.unwrap_or(config.fetch);
+    let gh_client = client::create_configured_client(&config)?;
+
+    let stack = discover_stack(&git_repo, &config, &gh_client).await?;
+    let urls = commit_ref::resolve_commit_refs(&git_repo, &stack, commit_ref)?
+        .into_iter()
+        .map(|oid| entry_pr_url(&stack, oid, commit_ref))
+        .collect::<Result<Vec<_>>>()?;
+
+    for url in urls {
+        println!("🌐 Opening {}", style(&url).cyan());
+        open::that(&url).with_context(|| format!("Failed to open {}", url))?;
+    }
+
+    Ok(())
+}
@@ -0,0 +1,215 @@
+use crate::commands::navigate::stack_at_head;
+use crate::git::{refs, repo, slots};
+use crate::model::{Config, Stack};
+use anyhow::{Context, Result};
+use git2::Repository;
+use serde::{Deserialize, Serialize};
+use std::fs;
+use tracing::debug;
+
+/// Output of `git stk prompt` (`--format`)
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, clap::ValueEnum)]
+pub enum PromptFormat {
+    /// The compact segment, e.g. `⇪3/7±2` (default)
+    #[default]
+    Text,
+    /// A JSON object for prompt frameworks, see [`PromptState`]
+    Json,
+}
+
+/// Version of the `--format json` object; bumped only on breaking changes
+const PROMPT_STATE_VERSION: u32 = 1;
+
+/// Stack status for prompts. Serialized as-is by `--format json`: fields are
+/// only ever added, anything else bumps `version`.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct PromptState {
+    pub version: u32,
+    /// The stack's branch (the one a detached HEAD was checked out from)
+    pub branch: String,
+    /// Whether HEAD is detached, e.g. after `git stk bottom`
+    pub detached: bool,
+    /// HEAD's 1-based position in the stack
+    pub position: usize,
+    /// Number of entries in the stack
+    pub total: usize,
+    /// Entries whose slot branch is missing or behind, as of the last fetch or push
+    pub needs_export: usize,
+}
+
+impl PromptState {
+    fn segment(&self) -> String {
+        format_segment(self.position, self.total, self.needs_export)
+    }
+}
+
+/// The last state computed, with the key of what it was computed from
+#[derive(Debug, Default, Serialize, Deserialize)]
+struct PromptCache {
+    key: String,
+    state: Option<PromptState>,
+}
+
+/// Print stack status for shell prompts: a compact segment, e.g. `⇪3/7±2`
+/// (HEAD's position, the stack size, and entries whose slot branch is missing
+/// or behind), or a JSON object. Offline and cached; prints nothing outside a
+/// stack or on errors.
+pub async fn prompt(format: PromptFormat) -> Result<()> {
+    match prompt_state() {
+        Ok(Some(state)) => match format {
+            PromptFormat::Text => println!("{}", state.segment()),
+            PromptFormat::Json => println!("{}", serde_json::to_string(&state)?),
+        },
+        Ok(None) => {}
+        Err(e) => debug!(error = %e, "no prompt state"),
+    }
+    Ok(())
+}
+
+fn prompt_state() -> Result<Option<PromptState>> {
+    let git_repo = Repository::open_from_env().context("Failed to open git repository")?;
+    let config = Config::load(&git_repo)?;
+
+    let key = cache_key(&git_repo, &config)?;
+    let path = Config::prompt_cache_path(&git_repo)?;
+    if let Some(cache) = fs::read_to_string(&path)
+        .ok()
+        .and_then(|contents| serde_json::from_str::<PromptCache>(&contents).ok())
+        && cache.key == key
+    {
+        return Ok(cache.state);
+    }
+
+    let (stack, position) = stack_at_head(&git_repo, &config)?;
+    let tracking_refs = refs::get_tracking_refs(&git_repo, &config.remote)?;
+    let state = position.map(|position| PromptState {
+        version: PROMPT_STATE_VERSION,
+        branch: stack.current_branch.clone(),
+        detached: repo::is_detached(&git_repo).unwrap_or(false),
+        position,
+        total: stack.len(),
+        needs_export: needs_export(&stack, &tracking_refs),
+    });
+
+    // A failed write only costs the next prompt a walk
+    if let Some(parent) = path.parent() {
+        let _ = fs::create_dir_all(parent);
+    }
+    let cache = PromptCache {
+        key,
+        state: state.clone(),
+    };
+    let _ = fs::write(&path, serde_json::to_string(&cache)?);
+
+    Ok(state)
+}
+
+/// Everything the state depends on: HEAD, the branch tip, the base, the
+/// notes, and the remote-tracking slot branches
+fn cache_key(git_repo: &Repository, config: &Config) -> Result<String> {
+    let head = git_repo.head().context("Failed to get HEAD")?;
+    let detached = repo::is_detached(git_repo)?;
+    let branch = if detached {
+        repo::previous_branch(git_repo)?.unwrap_or_default()
+    } else {
+        repo::current_branch(git_repo)?
+    };
+    let oid = |spec: &str| {
+        git_repo
+            .revparse_single(spec)
+            .map(|o| o.id().to_string())
+            .unwrap_or_default()
+    };
+
+    let mut parts = vec![
+        format!("v{}", PROMPT_STATE_VERSION),
+        branch.clone(),
+        detached.to_string(),
+        head.target().map(|o| o.to_string()).unwrap_or_default(),
+        oid(&format!("refs/heads/{}", branch)),
+        oid(config.base_name()),
+        oid(&format!("{}/{}", config.remote, config.base_name())),
+        oid(&config.notes_ref),
+    ];
+    let slot_prefix = format!(
+        "refs/remotes/{}/{}--",
+        config.remote,
+        slots::sanitize_branch_name(&branch)
+    );
+    for reference in git_repo.references_glob(&format!("{}*", slot_prefix))? {
+        let reference = reference?;
+        if let (Some(name), Some(target)) = (reference.name(), reference.target()) {
+            parts.push(format!("{}={}", name, target));
+        }
+    }
+    Ok(parts.join(" "))
+}
+
+/// Entries whose slot branch, as of the last fetch or push, is missing or
+/// points at another commit
+fn needs_export(
+    stack: &Stack,
+    tracking_refs: &std::collections::HashMap<String, git2::Oid>,
+) -> usize {
+    stack
+        .entries
+        .iter()
+        .filter(|entry| {
+            entry
+                .head_ref
+                .as_ref()
+                .and_then(|head_ref| tracking_refs.get(head_ref))
+                .is_none_or(|&oid| oid != entry.oid)
+        })
+        .count()
+}
+
+fn format_segment(position: usize, total: usize, needs_export: usize) -> String {
+    if needs_export == 0 {
+        format!("⇪{}/{}", position, total)
+    } else {
+        format!("⇪{}/{}±{}", position, total, needs_export)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_format_segment() {
+        assert_eq!(format_segment(3, 7, 2), "⇪3/7±2");
+        assert_eq!(format_segment(7, 7, 0), "⇪7/7");
+    }
+
+    #[test]
+    fn test_prompt_state_json() {
+        let state = PromptState {
+            version: PROMPT_STATE_VERSION,
+            branch: "feat".to_string(),
+            detached: true,
+            position: 1,
+            total: 3,
+            needs_export: 0,
+        };
+        assert_eq!(
+            serde_json::to_string(&state).unwrap(),
+            r#"{"version":1,"branch":"feat","detached":true,"position":1,"total":3,"needs_export":0}"#
+        );
+        assert_eq!(state.segment(), "⇪1/3");
+    }
+
+    #[test]
+    fn test_prompt_format_values() {
+        use clap::ValueEnum;
+        assert_eq!(
+            PromptFormat::from_str("text", false).unwrap(),
+            PromptFormat::Text
+        );
+        assert_eq!(
+            PromptFormat::from_str("json", false).unwrap(),
+            PromptFormat::Json
+        );
+        assert!(PromptFormat::from_str("yaml", false).is_err());
+    }
+}
@@ -0,0 +1,109 @@
+use crate::gh::client;
+use crate::model::Config;
+use crate::stack::discover_stack;
+use crate::workflows;
+use anyhow::{Context, Result, bail};
+use console::style;
+use git2::Repository;
+
+/// Close orphaned PRs and delete their slot branches
+///
+/// Orphaned PRs are open PRs on slot branches of the current branch that no
+/// entry uses any more, typically because their commit was dropped with
+/// `git rebase -i`, and that hold a commit of yours no longer on the branch.
+/// Their slots are released so they can be assigned again.
+pub async fn prune(skip_confirm: bool, fetch: Option<bool>) -> Result<()> {
+    let git_repo = Repository::open(".").context("Failed to open git repository")?;
+    let mut config = Config::load(&git_repo)?;
+    config.fetch = fetch.unwrap_or(config.fetch);
+    let gh_client = client::create_configured_client(&config)?;
+
+    let stack = discover_stack(&git_repo, &config, &gh_client).await?;
+    if stack.orphaned_prs.is_empty() {
+        println!("{} No orphaned PRs", style("✓").green());
+        return Ok(());
+    }
+
+    // GitHub closes PRs whose base branch is deleted
+    if let Some(entry) = stack.entries.iter().find(|e| {
+        stack
+            .orphaned_prs
+            .iter()
+            .any(|pr| e.pr_base_ref.as_ref() == Some(&pr.head_ref))
+    }) {
+        bail!(
+            "PR #{} is still based on an orphaned slot branch. Run 'git stk export' first to retarget it.",
+            entry.pr_number.unwrap_or_default()
+        );
+    }
+
+    println!(
+        "🧹 Pruning orphaned PRs of {}:",
+        style(&stack.current_branch).bold()
+    );
+    for pr in &stack.orphaned_prs {
+        println!(
+            "  {} Close PR {} {} and delete {}/{}",
+            style("→").dim(),
+            style(format!("#{}", pr.pr_number)).cyan(),
+            pr.title,
+            config.remote,
+            pr.head_ref
+        );
+    }
+
+    let confirmed = if skip_confirm {
+        true
+    } else {
+        use dialoguer::Confirm;
+        Confirm::new()
+            .with_prompt("Prune these PRs?")
+            .default(false)
+            .interact()?
+    };
+    if !confirmed {
+        println!("\n{}", style("✗ Operation cancelled").red());
+        return Ok(());
+    }
+
+    let remote = git_repo
+        .find_remote(&config.remote)
+        .context("Failed to find remote")?;
+    let (owner, repo_name) =
+        client::parse_repo_from_url(remote.url().context("Remote URL is not valid UTF-8")?)?;
+
+    println!(
+        "\n🔒 Closing {} PR{}...",
+        stack.orphaned_prs.len(),
+        if stack.orphaned_prs.len() == 1 {
+            ""
+        } else {
+            "s"
+        }
+    );
+    let (closed, warnings) = workflows::orphans::close_orphaned_prs(
+        &git_repo,
+        &config,
+        &gh_client,
+        &owner,
+        &repo_name,
+        &stack.current_branch,
+        &stack.orphaned_prs,
+        false,
+    )
+    .await?;
+    for warning in &warnings {
+        eprintln!("   ⚠ {}", warning);
+    }
+    if closed.is_empty() {
+        bail!("No PR could be closed");
+    }
+
+    println!(
+        "\n{} Pruned {} PR{}",
+        style("✨").green(),
+        closed.len(),
+        if closed.len() == 1 { "" } else { "s" }
+    );
+    Ok(())
+}
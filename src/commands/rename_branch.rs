@@ -0,0 +1,233 @@
+use crate::commands::{ExportOptions, export};
+use crate::gh::{client, mutations};
+use crate::git::{notes, refs, repo, slots};
+use crate::model::{CommitMetadata, Config, Entry, PrState, PushHooks};
+use crate::stack::discover_stack;
+use anyhow::{Context, Result, bail};
+use console::style;
+use git2::{Branch, BranchType, Repository};
+
+/// Rename the working branch and migrate its slot branches and PRs
+///
+/// Head refs are derived from the branch name (`{branch}--{slot}`), and GitHub
+/// does not allow changing a PR's head branch, so open PRs are closed with a
+/// pointer to the new branch and recreated by re-exporting under the new name.
+/// Slots are kept, so each commit keeps its slot suffix.
+pub async fn rename_branch(new_name: &str, skip_confirm: bool, fetch: Option<bool>) -> Result<()> {
+    let git_repo = Repository::open(".").context("Failed to open git repository")?;
+    let mut config = Config::load(&git_repo)?;
+    config.fetch = fetch.unwrap_or(config.fetch);
+    let gh_client = client::create_configured_client(&config)?;
+
+    if !Branch::name_is_valid(new_name)? {
+        bail!("'{}' is not a valid branch name", new_name);
+    }
+    if git_repo.find_branch(new_name, BranchType::Local).is_ok() {
+        bail!("Branch '{}' already exists", new_name);
+    }
+
+    // Renaming needs an existing branch; a detached HEAD has none
+    repo::current_branch(&git_repo)?;
+
+    println!("🔍 Discovering stack...\n");
+
+    let stack = discover_stack(&git_repo, &config, &gh_client).await?;
+    let old_name = stack.current_branch.clone();
+
+    if old_name == new_name {
+        bail!("Already on branch '{}'", new_name);
+    }
+
+    let is_open = |entry: &Entry| {
+        entry.pr_number.is_some()
+            && matches!(
+                entry.pr_state,
+                Some(PrState::Open) | Some(PrState::Draft) | None
+            )
+    };
+
+    println!(
+        "  {} Branch: {} → {}",
+        style("→").dim(),
+        style(&old_name).cyan(),
+        style(new_name).cyan().bold()
+    );
+    for entry in &stack.entries {
+        let Some(slot) = &entry.slot else { continue };
+        println!(
+            "  {} {}  {} → {}{}",
+            style("→").dim(),
+            style(&entry.short_sha).yellow(),
+            style(slots::generate_head_ref(&old_name, slot)).dim(),
+            style(slots::generate_head_ref(new_name, slot)).cyan(),
+            entry
+                .pr_number
+                .filter(|_| is_open(entry))
+                .map(|pr| format!("  (replaces PR #{})", pr))
+                .unwrap_or_default()
+        );
+    }
+
+    let prs_to_replace: Vec<&Entry> = stack.entries.iter().filter(|e| is_open(e)).collect();
+
+    let confirmed = if skip_confirm {
+        true
+    } else {
+        use dialoguer::Confirm;
+        let prompt = if prs_to_replace.is_empty() {
+            format!("Rename {} to {}?", old_name, new_name)
+        } else {
+            format!(
+                "Rename {} to {} and replace {} open PR{}?",
+                old_name,
+                new_name,
+                prs_to_replace.len(),
+                if prs_to_replace.len() == 1 { "" } else { "s" }
+            )
+        };
+        Confirm::new()
+            .with_prompt(prompt)
+            .default(false)
+            .interact()?
+    };
+
+    if !confirmed {
+        println!("\n{}", style("✗ Operation cancelled").red());
+        return Ok(());
+    }
+
+    println!();
+
+    // Forget the PRs on the old head branches so the export opens new ones
+    for entry in &prs_to_replace {
+        let metadata = CommitMetadata {
+            pr: None,
+            slot: entry.slot.clone().unwrap_or_default(),
+            annotation: entry.annotation.clone(),
+            last_export: None,
+            owner: entry.owner.clone(),
+            signature: None,
+        };
+        notes::write_note(
+            &git_repo,
+            entry.oid,
+            &metadata,
+            &config.notes_ref,
+            config.sign_notes,
+        )
+        .context("Failed to write note to commit")?;
+    }
+
+    // Rename the local branch and carry its slot history over
+    let mut branch = git_repo
+        .find_branch(&old_name, BranchType::Local)
+        .with_context(|| format!("Failed to find branch '{}'", old_name))?;
+    branch
+        .rename(new_name, false)
+        .with_context(|| format!("Failed to rename branch to '{}'", new_name))?;
+
+    let mut slot_cache = slots::SlotCache::load(&git_repo)?;
+    slot_cache.rename_branch(&old_name, new_name);
+    slot_cache.save(&git_repo)?;
+
+    println!(
+        "✏️  Renamed {} → {}",
+        style(&old_name).dim(),
+        style(new_name).cyan()
+    );
+
+    if stack.is_empty() {
+        return Ok(());
+    }
+
+    // Push renamed slot branches and recreate PRs against them
+    println!("\n📤 Re-exporting stack under the new name...\n");
+    if let Err(e) = export::export(ExportOptions::default()).await {
+        eprintln!(
+            "\n{} Failed to re-export stack after renaming:",
+            style("✗").red()
+        );
+        eprintln!("  {}", e);
+        eprintln!(
+            "\n{} The old PRs and branches of {} are left as they were: close and delete them once 'git stk export' succeeds.",
+            style("💡").yellow(),
+            old_name
+        );
+        return Err(e);
+    }
+
+    // Only closed once the stack is exported under the new name, so a failed
+    // export leaves the old PRs and their branches as they were
+    if !prs_to_replace.is_empty() {
+        let owner = stack.entries[0].repo_owner.clone().unwrap_or_default();
+        let repo_name = stack.entries[0].repo_name.clone().unwrap_or_default();
+
+        println!(
+            "\n🔒 Closing {} PR{}...",
+            prs_to_replace.len(),
+            if prs_to_replace.len() == 1 { "" } else { "s" }
+        );
+        for entry in prs_to_replace.iter().rev() {
+            let Some(pr_number) = entry.pr_number else {
+                continue;
+            };
+            let slot = entry.slot.as_deref().unwrap_or_default();
+            let comment = format!(
+                "This PR is being closed because the branch `{}` was renamed to `{}`.\n\n\
+                 It continues as a new PR from `{}`.",
+                old_name,
+                new_name,
+                slots::generate_head_ref(new_name, slot)
+            );
+
+            if let Err(e) =
+                mutations::add_pr_comment(&gh_client, &owner, &repo_name, pr_number, &comment).await
+            {
+                eprintln!(
+                    "  Warning: Failed to add comment to PR #{}: {}",
+                    pr_number, e
+                );
+            }
+            match mutations::close_pull_request(&gh_client, &owner, &repo_name, pr_number).await {
+                Ok(_) => println!("  ✓ PR #{} closed", pr_number),
+                Err(e) => eprintln!("  Warning: Failed to close PR #{}: {}", pr_number, e),
+            }
+        }
+    }
+
+    // Delete the old slot branches on the remote
+    let old_refspecs: Vec<String> = stack
+        .entries
+        .iter()
+        .filter(|e| e.remote_branch_exists)
+        .filter_map(|e| e.slot.as_ref())
+        .map(|slot| format!(":refs/heads/{}", slots::generate_head_ref(&old_name, slot)))
+        .collect();
+    if !old_refspecs.is_empty() {
+        println!(
+            "🗑️  Deleting {} old branch{}...",
+            old_refspecs.len(),
+            if old_refspecs.len() == 1 { "" } else { "es" }
+        );
+        let mut capabilities = refs::RemoteCapabilities::load(&git_repo, &config.remote);
+        let result = refs::push_refs(
+            &git_repo,
+            &config.remote,
+            &old_refspecs,
+            &mut capabilities,
+            config.push_hooks != PushHooks::Each,
+            config.max_refs_per_push,
+            &config.transport,
+            &mut |_| {},
+        )?;
+        match result.error {
+            None => println!("  ✓ Deleted"),
+            Some(e) => eprintln!(
+                "  Warning: Failed to delete some branches:\n{}",
+                e.trim_end()
+            ),
+        }
+    }
+
+    Ok(())
+}
@@ -0,0 +1,106 @@
+use crate::git::{backup, repo};
+use crate::model::Config;
+use crate::stack::resolve_base_commit;
+use anyhow::{Context, Result, bail};
+use console::style;
+use git2::Repository;
+
+/// List stack snapshots, or roll back to one
+pub async fn restore(snapshot_id: Option<i64>, skip_confirm: bool) -> Result<()> {
+    let git_repo = Repository::open(".").context("Failed to open git repository")?;
+    let config = Config::load(&git_repo)?;
+
+    let Some(id) = snapshot_id else {
+        return list_snapshots(&git_repo);
+    };
+
+    if repo::has_uncommitted_changes(&git_repo)? {
+        bail!("You have uncommitted changes. Please commit or stash them before restoring.");
+    }
+
+    let snapshot = backup::find_snapshot(&git_repo, id)?;
+    snapshot.ensure_checked_out(&git_repo)?;
+    let current_branch = snapshot.branch.clone();
+    let base = resolve_base_commit(&git_repo, &config)?.id();
+
+    println!(
+        "\n⏪ Restoring {} to snapshot {} ({} {})",
+        style(&current_branch).cyan(),
+        style(snapshot.id).cyan(),
+        style(format!("{:.7}", snapshot.head)).yellow(),
+        style(commit_subject(&git_repo, snapshot.head)).dim()
+    );
+
+    let confirmed = if skip_confirm {
+        true
+    } else {
+        use dialoguer::Confirm;
+        Confirm::new()
+            .with_prompt(format!(
+                "Reset {} and its git-stk metadata to this snapshot?",
+                current_branch
+            ))
+            .default(false)
+            .interact()?
+    };
+
+    if !confirmed {
+        println!("\n{}", style("✗ Operation cancelled").red());
+        return Ok(());
+    }
+
+    backup::restore_snapshot(&git_repo, &snapshot, &config.notes_ref, base)?;
+
+    println!(
+        "  {} Branch reset to {:.7}",
+        style("✓").green(),
+        snapshot.head
+    );
+    if snapshot.notes.is_some() {
+        println!(
+            "  {} Restored the metadata of {}'s commits",
+            style("✓").green(),
+            current_branch
+        );
+    }
+    println!(
+        "\n{}",
+        style("Run 'git-stk export' to push the restored stack.").dim()
+    );
+
+    Ok(())
+}
+
+fn list_snapshots(git_repo: &Repository) -> Result<()> {
+    let snapshots = backup::list_snapshots(git_repo)?;
+
+    if snapshots.is_empty() {
+        println!("{}", style("No snapshots found").dim());
+        return Ok(());
+    }
+
+    println!("\n💾 Snapshots (newest first):\n");
+    for snapshot in &snapshots {
+        println!(
+            "  {}  {}  {}  {}",
+            style(snapshot.id).cyan(),
+            style(&snapshot.branch).dim(),
+            style(format!("{:.7}", snapshot.head)).yellow(),
+            style(commit_subject(git_repo, snapshot.head)).bold()
+        );
+    }
+    println!(
+        "\n{}",
+        style("Run 'git stk restore <id>' to roll back to a snapshot.").dim()
+    );
+
+    Ok(())
+}
+
+fn commit_subject(git_repo: &Repository, oid: git2::Oid) -> String {
+    git_repo
+        .find_commit(oid)
+        .ok()
+        .and_then(|c| c.summary().map(String::from))
+        .unwrap_or_else(|| "<missing commit>".to_string())
+}
@@ -0,0 +1,59 @@
+use crate::gh::client;
+use crate::gh::mutations::{self, ReviewEvent};
+use crate::git::commit_ref;
+use crate::model::Config;
+use crate::stack::discover_stack;
+use anyhow::{Context, Result, bail};
+use console::style;
+use git2::Repository;
+
+/// Submit a review on the PR of a stack entry
+pub async fn review(
+    commit_ref: &str,
+    event: ReviewEvent,
+    body: Option<&str>,
+    fetch: Option<bool>,
+) -> Result<()> {
+    let git_repo = Repository::open(".").context("Failed to open git repository")?;
+    let mut config = Config::load(&git_repo)?;
+    config.fetch = fetch.unwrap_or(config.fetch);
+    let gh_client = client::create_configured_client(&config)?;
+
+    let body = body.map(str::trim).filter(|b| !b.is_empty());
+    if body.is_none() && event != ReviewEvent::Approve {
+        bail!("A review body (--body) is required to request changes or comment");
+    }
+
+    let stack = discover_stack(&git_repo, &config, &gh_client).await?;
+
+    let commit_oid = commit_ref::resolve_commit_ref(&git_repo, &stack, commit_ref)?;
+    let entry = stack
+        .entries
+        .iter()
+        .find(|e| e.oid == commit_oid)
+        .with_context(|| format!("Commit {} is not part of the current stack", commit_ref))?;
+
+    let pr_number = entry.pr_number.with_context(|| {
+        format!(
+            "Commit {} has no PR yet. Run 'git stk export' first.",
+            entry.short_sha
+        )
+    })?;
+    let owner = entry.repo_owner.as_ref().context("Missing repo owner")?;
+    let repo_name = entry.repo_name.as_ref().context("Missing repo name")?;
+
+    mutations::submit_pr_review(&gh_client, owner, repo_name, pr_number, event, body).await?;
+
+    let verdict = match event {
+        ReviewEvent::Approve => style("✓ Approved").green(),
+        ReviewEvent::RequestChanges => style("✗ Requested changes on").red(),
+        ReviewEvent::Comment => style("💬 Reviewed").cyan(),
+    };
+    println!(
+        "{} {}",
+        verdict,
+        style(entry.pr_url().unwrap_or_else(|| format!("#{}", pr_number))).cyan()
+    );
+
+    Ok(())
+}
@@ -0,0 +1,120 @@
+use crate::commands::{ViewOptions, view};
+use crate::git::refs;
+use crate::model::Config;
+use crate::model::config::base_branch_name;
+use crate::stack::discover_local_stack;
+use anyhow::{Context, Result, anyhow, bail};
+use console::style;
+use git2::Repository;
+
+/// Set `git-stk.base` once it resolves to a commit the stack can sit on, then
+/// show the resulting stack
+pub async fn set_base(base: &str) -> Result<()> {
+    let git_repo = Repository::open(".").context("Failed to open git repository")?;
+    let mut config = Config::load(&git_repo)?;
+
+    if config.base == base {
+        println!(
+            "{} Base is already {}",
+            style("✓").green(),
+            style(base).yellow()
+        );
+        return Ok(());
+    }
+
+    config.base = base.to_string();
+    config.base_branch = base_branch_name(&git_repo, base, &config.remote);
+
+    // Discovering the stack checks that the base resolves and shares history with HEAD
+    let stack = discover_local_stack(&git_repo, &config)
+        .map_err(|e| anyhow!("'{}' can't be used as the base of this stack: {}", base, e))?;
+
+    if config.base_branch.is_none() {
+        println!(
+            "{} {} is not a branch: local commands work, but export and land need a branch base.",
+            style("⚠").yellow(),
+            style(base).yellow()
+        );
+    }
+
+    write_local_config(&git_repo, "git-stk.base", base)?;
+    println!(
+        "{} Base set to {} ({} commit{} in the stack)\n",
+        style("✓").green(),
+        style(base).yellow(),
+        stack.len(),
+        if stack.len() == 1 { "" } else { "s" }
+    );
+
+    view(ViewOptions::default()).await
+}
+
+/// Set `git-stk.remote` once the remote is configured and reachable, then show
+/// the resulting stack
+pub async fn set_remote(remote: &str) -> Result<()> {
+    let git_repo = Repository::open(".").context("Failed to open git repository")?;
+    let config = Config::load(&git_repo)?;
+
+    if config.remote == remote {
+        println!(
+            "{} Remote is already {}",
+            style("✓").green(),
+            style(remote).yellow()
+        );
+        return Ok(());
+    }
+
+    if git_repo.find_remote(remote).is_err() {
+        let remotes = git_repo.remotes().context("Failed to list remotes")?;
+        let names: Vec<&str> = remotes.iter().flatten().collect();
+        bail!(
+            "No remote named '{}' (configured: {})",
+            remote,
+            if names.is_empty() {
+                "none".to_string()
+            } else {
+                names.join(", ")
+            }
+        );
+    }
+
+    println!("🔍 Checking {}...", style(remote).cyan());
+    let base_branch = base_branch_name(&git_repo, &config.base, remote);
+    let patterns: Vec<String> = base_branch
+        .iter()
+        .map(|b| format!("refs/heads/{}", b))
+        .collect();
+    let remote_refs =
+        refs::get_remote_refs_matching(&git_repo, remote, &patterns, &config.transport)
+            .map_err(|e| anyhow!("Remote '{}' is not reachable: {}", remote, e))?;
+
+    if let Some(branch) = &base_branch
+        && !remote_refs.contains_key(branch)
+    {
+        println!(
+            "{} {} has no {} branch; set a base that exists there with 'git stk set base'.",
+            style("⚠").yellow(),
+            style(remote).yellow(),
+            style(branch).yellow()
+        );
+    }
+
+    write_local_config(&git_repo, "git-stk.remote", remote)?;
+    println!(
+        "{} Remote set to {}\n",
+        style("✓").green(),
+        style(remote).yellow()
+    );
+
+    view(ViewOptions::default()).await
+}
+
+fn write_local_config(repo: &Repository, key: &str, value: &str) -> Result<()> {
+    let config = repo.config().context("Failed to load git config")?;
+    let mut local_config = config
+        .open_level(git2::ConfigLevel::Local)
+        .context("Failed to open local git config for writing")?;
+    local_config
+        .set_str(key, value)
+        .with_context(|| format!("Failed to set {}", key))
+}
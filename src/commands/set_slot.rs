@@ -1,24 +1,100 @@
-use crate::gh::{client, mutations};
+use crate::gh::{
+    client::{self, Client},
+    mutations,
+};
 use crate::git::{commit_ref, notes, slots};
-use crate::model::{CommitMetadata, Config};
-use crate::stack::discover_stack;
-use anyhow::{Context, Result};
+use crate::model::{CommitMetadata, Config, Entry, PrState};
+use crate::stack::{discover_stack, ensure_online};
+use crate::workflows;
+use anyhow::{Context, Result, bail};
 use console::style;
-use git2::Repository;
+use git2::{Oid, Repository};
+
+/// Split `set slot` arguments into `(commit, slot)` pairs: either `<commit> <slot>`,
+/// or any number of `<commit>=<slot>` assignments
+pub fn parse_assignments(args: &[String]) -> Result<Vec<(String, String)>> {
+    if let [commit, slot] = args
+        && !commit.contains('=')
+        && !slot.contains('=')
+    {
+        return Ok(vec![(commit.clone(), slot.clone())]);
+    }
+    if args.is_empty() {
+        bail!("Expected <commit> <slot>, or one or more <commit>=<slot> assignments");
+    }
+
+    args.iter()
+        .map(|arg| {
+            // Slots can't contain '=', so the last one separates the two
+            match arg.rsplit_once('=') {
+                Some((commit, slot)) if !commit.is_empty() && !slot.is_empty() => {
+                    Ok((commit.to_string(), slot.to_string()))
+                }
+                _ => bail!("Invalid assignment '{}': expected <commit>=<slot>", arg),
+            }
+        })
+        .collect()
+}
+
+/// A resolved `set slot` assignment
+struct Assignment<'a> {
+    oid: Oid,
+    short_sha: String,
+    subject: String,
+    slot: &'a str,
+    entry: Option<&'a Entry>,
+}
+
+impl Assignment<'_> {
+    /// The commit's current slot, if it has one and this assignment changes it
+    fn replaced_slot(&self) -> Option<&str> {
+        self.entry
+            .and_then(|e| e.slot.as_deref())
+            .filter(|existing| *existing != self.slot)
+    }
+
+    /// PR closed by the change: GitHub PR head branches are immutable
+    fn closed_pr(&self) -> Option<u64> {
+        self.replaced_slot()
+            .and(self.entry.and_then(|e| e.pr_number))
+    }
+
+    /// Whether the change leaves a pushed branch behind under the old name
+    fn renames_remote_branch(&self) -> bool {
+        self.replaced_slot().is_some() && self.entry.is_some_and(|e| e.remote_branch_exists)
+    }
+}
 
-/// Manually assign a slot to a commit
-pub async fn set_slot(commit_ref: &str, slot: &str, skip_confirm: bool) -> Result<()> {
+/// Manually assign slots to commits
+///
+/// All assignments are resolved against one stack discovery and confirmed
+/// together, then written with a single slot cache save.
+pub async fn set_slots(
+    assignments: &[(String, String)],
+    skip_confirm: bool,
+    fetch: Option<bool>,
+) -> Result<()> {
     let git_repo = Repository::open(".").context("Failed to open git repository")?;
-    let config = Config::load(&git_repo)?;
-    let gh_client = client::create_client()?;
+    let mut config = Config::load(&git_repo)?;
+    config.fetch = fetch.unwrap_or(config.fetch);
+    let gh_client = client::create_configured_client(&config)?;
 
-    println!("🔍 Looking up commit and validating slot...\n");
+    println!(
+        "🔍 Looking up commit{} and validating slot{}...\n",
+        if assignments.len() == 1 { "" } else { "s" },
+        if assignments.len() == 1 { "" } else { "s" }
+    );
 
-    // Validate slot name
-    slots::validate_slot_name(slot)?;
+    // Validate slot names
+    for (_, slot) in assignments {
+        slots::validate_slot_name(slot)?;
+    }
+
+    workflows::ensure_on_branch(&git_repo, &config, skip_confirm)?;
 
     // Discover stack with full state
     let stack = discover_stack(&git_repo, &config, &gh_client).await?;
+    ensure_online(&stack, &config, "set the slot")?;
 
     // Get owner/repo from stack (already derived during discovery)
     let owner = stack
@@ -32,203 +108,428 @@ pub async fn set_slot(commit_ref: &str, slot: &str, skip_confirm: bool) -> Resul
         .and_then(|e| e.repo_name.clone())
         .unwrap_or_default();
 
-    // Resolve commit reference to OID using the stack
-    let commit_oid = commit_ref::resolve_commit_ref(&git_repo, &stack, commit_ref)?;
-
-    let commit = git_repo.find_commit(commit_oid)?;
-    let short_sha = &commit_oid.to_string()[..7];
-    let commit_subject = commit.summary().unwrap_or("<no subject>").to_string();
+    // Resolve every commit reference before changing anything
+    let mut resolved: Vec<Assignment> = Vec::with_capacity(assignments.len());
+    for (commit_ref, slot) in assignments {
+        let oid = commit_ref::resolve_commit_ref(&git_repo, &stack, commit_ref)?;
+        if resolved.iter().any(|a| a.oid == oid) {
+            bail!("Commit {} is assigned more than one slot", commit_ref);
+        }
+        if resolved.iter().any(|a| a.slot == slot) {
+            bail!("Slot '{}' is assigned to more than one commit", slot);
+        }
 
-    println!(
-        "  {} Commit: {} ({})",
-        style("→").dim(),
-        style(short_sha).yellow(),
-        style(&commit_subject).dim()
-    );
+        let commit = git_repo.find_commit(oid)?;
+        resolved.push(Assignment {
+            oid,
+            short_sha: oid.to_string()[..7].to_string(),
+            subject: commit.summary().unwrap_or("<no subject>").to_string(),
+            slot,
+            entry: stack.entries.iter().find(|e| e.oid == oid),
+        });
+    }
 
     let current_branch = &stack.current_branch;
 
     // Load slot cache
     let mut slot_cache = slots::SlotCache::load(&git_repo)?;
 
-    // Check if slot is available
-    if !slot_cache.is_slot_available(current_branch, slot) {
-        eprintln!("\n{}", style("⚠️  Warning").yellow().bold());
-        eprintln!(
-            "  Slot '{}' is already in use on branch '{}'",
-            slot, current_branch
+    for assignment in &resolved {
+        println!(
+            "  {} {}  {} → {}  {}{}",
+            style("→").dim(),
+            style(&assignment.short_sha).yellow(),
+            style(
+                assignment
+                    .entry
+                    .and_then(|e| e.slot.as_deref())
+                    .unwrap_or("-")
+            )
+            .dim(),
+            style(assignment.slot).yellow().bold(),
+            style(&assignment.subject).dim(),
+            assignment
+                .closed_pr()
+                .map(|pr| format!("  (closes PR #{})", pr))
+                .unwrap_or_default()
         );
-        eprintln!("  This may cause conflicts with existing commits.");
     }
 
-    // Generate head_ref for this slot
-    let head_ref = slots::generate_head_ref(current_branch, slot);
+    // Slots this command reassigns are not conflicts
+    let freed: Vec<&str> = resolved.iter().filter_map(|a| a.replaced_slot()).collect();
+    for assignment in &resolved {
+        if !slot_cache.is_slot_available(current_branch, assignment.slot)
+            && !freed.contains(&assignment.slot)
+        {
+            eprintln!("\n{}", style("⚠️  Warning").yellow().bold());
+            eprintln!(
+                "  Slot '{}' is already in use on branch '{}'",
+                assignment.slot, current_branch
+            );
+            eprintln!("  This may cause conflicts with existing commits.");
+        }
+    }
 
-    println!(
-        "  {} Slot: {} → {}",
-        style("→").dim(),
-        style(slot).yellow().bold(),
-        style(&head_ref).cyan()
-    );
+    let closed_prs: Vec<u64> = resolved.iter().filter_map(Assignment::closed_pr).collect();
+    let renamed = resolved
+        .iter()
+        .filter(|a| a.renames_remote_branch())
+        .count();
+    if !closed_prs.is_empty() || renamed > 0 {
+        println!(
+            "\n{}",
+            style("⚠️  Warning: Slot Change Detected").yellow().bold()
+        );
+        println!(
+            "  {}",
+            style("Changing a slot changes the branch name of its commit.").yellow()
+        );
+        if !closed_prs.is_empty() {
+            println!(
+                "  {}",
+                style("Existing PRs cannot be updated to use a new branch: they will be closed and new PRs will be created on export.").yellow()
+            );
+        }
 
-    // Find existing entry in stack for this commit
-    let existing_entry = stack.entries.iter().find(|e| e.oid == commit_oid);
-
-    let preserve_pr = if let Some(entry) = existing_entry {
-        if let Some(existing_slot) = &entry.slot {
-            let existing_head_ref = slots::generate_head_ref(current_branch, existing_slot);
-            let slot_changed = existing_slot != slot;
-            let has_pr = entry.pr_number.is_some();
-
-            if slot_changed && has_pr {
-                let pr_number = entry.pr_number.unwrap();
-                println!(
-                    "\n{}",
-                    style("⚠️  Warning: Slot Change Detected").yellow().bold()
-                );
-                println!("  Commit {} already has:", short_sha);
-                println!("    PR: #{}", pr_number);
-                println!("    Slot: {} ({})", existing_slot, existing_head_ref);
-                println!(
-                    "\n  Changing the slot will change the branch name to: {}",
-                    style(&head_ref).cyan()
-                );
-                println!(
-                    "  {}",
-                    style("The existing PR cannot be updated to use the new branch.").yellow()
-                );
-                println!(
-                    "  {}",
-                    style("The PR will be closed and a new PR will be created on export.").yellow()
-                );
-
-                let confirmed = if skip_confirm {
-                    true
+        let confirmed = if skip_confirm {
+            true
+        } else {
+            use dialoguer::Confirm;
+            let pr_list: Vec<String> = closed_prs.iter().map(|n| format!("#{}", n)).collect();
+            Confirm::new()
+                .with_prompt(if pr_list.is_empty() {
+                    "Do you want to change these slots?".to_string()
                 } else {
-                    use dialoguer::Confirm;
-                    Confirm::new()
-                        .with_prompt(format!(
-                            "Do you want to change slot from {} to {} and close PR #{}?",
-                            existing_slot, slot, pr_number
-                        ))
-                        .default(false)
-                        .interact()?
-                };
-
-                if !confirmed {
-                    println!("\n{}", style("✗ Operation cancelled").red());
-                    return Ok(());
-                }
+                    format!(
+                        "Do you want to change these slots and close PR{} {}?",
+                        if pr_list.len() == 1 { "" } else { "s" },
+                        pr_list.join(", ")
+                    )
+                })
+                .default(false)
+                .interact()?
+        };
+
+        if !confirmed {
+            println!("\n{}", style("✗ Operation cancelled").red());
+            return Ok(());
+        }
+    }
 
-                println!();
-
-                // Close the PR on GitHub
-                println!("🔒 Closing PR #{}...", pr_number);
-                let comment = format!(
-                    "This PR is being closed because the commit slot was changed from `{}` to `{}`.\n\n\
-                     The branch name will change from `{}` to `{}`.\n\n\
-                     A new PR will be created with the updated branch name.",
-                    existing_slot, slot, existing_head_ref, head_ref
-                );
-
-                if let Err(e) =
-                    mutations::add_pr_comment(&gh_client, &owner, &repo_name, pr_number, &comment)
-                        .await
-                {
-                    eprintln!("  Warning: Failed to add comment to PR: {}", e);
-                } else {
-                    println!("  ✓ Added comment to PR");
-                }
+    println!();
+    for assignment in &resolved {
+        let closed_pr = assignment.closed_pr();
+        if let (Some(pr_number), Some(existing_slot)) = (closed_pr, assignment.replaced_slot()) {
+            close_pr_for_slot_change(
+                &gh_client,
+                &owner,
+                &repo_name,
+                pr_number,
+                (
+                    existing_slot,
+                    &slots::generate_head_ref(current_branch, existing_slot),
+                ),
+                (
+                    assignment.slot,
+                    &slots::generate_head_ref(current_branch, assignment.slot),
+                ),
+            )
+            .await;
+            println!();
+        }
 
-                match mutations::close_pull_request(&gh_client, &owner, &repo_name, pr_number).await
-                {
-                    Ok(_) => println!("  ✓ PR #{} closed", pr_number),
-                    Err(e) => {
-                        eprintln!("  Warning: Failed to close PR: {}", e);
-                        eprintln!("  You may need to close it manually.");
-                    }
-                }
+        // Create metadata with the specified slot, keeping the PR unless it was closed
+        let metadata = CommitMetadata {
+            pr: assignment
+                .entry
+                .and_then(|e| e.pr_number)
+                .filter(|_| closed_pr.is_none()),
+            slot: assignment.slot.to_string(),
+            annotation: assignment.entry.and_then(|e| e.annotation.clone()),
+            last_export: None,
+            owner: assignment.entry.and_then(|e| e.owner.clone()),
+            signature: None,
+        };
+
+        // Write metadata to the commit
+        println!(
+            "📝 Assigning slot {} to commit {}...",
+            assignment.slot, assignment.short_sha
+        );
+        notes::write_note(
+            &git_repo,
+            assignment.oid,
+            &metadata,
+            &config.notes_ref,
+            config.sign_notes,
+        )
+        .context("Failed to write note to commit")?;
+        slot_cache.mark_slot_used(current_branch, assignment.slot);
+    }
 
-                println!();
-                false // Don't preserve PR when slot changes
-            } else if slot_changed && entry.remote_branch_exists {
-                println!(
-                    "\n{}",
-                    style("⚠️  Warning: Slot Change Detected").yellow().bold()
-                );
-                println!("  Commit {} already has:", short_sha);
-                println!("    Slot: {} ({})", existing_slot, existing_head_ref);
-                println!(
-                    "\n  Changing the slot will change the branch name to: {}",
-                    style(&head_ref).cyan()
-                );
-
-                let confirmed = if skip_confirm {
-                    true
-                } else {
-                    use dialoguer::Confirm;
-                    Confirm::new()
-                        .with_prompt(format!(
-                            "Do you want to change slot from {} to {}?",
-                            existing_slot, slot
-                        ))
-                        .default(false)
-                        .interact()?
-                };
-
-                if !confirmed {
-                    println!("\n{}", style("✗ Operation cancelled").red());
-                    return Ok(());
-                }
+    println!("  ✓ Updated local metadata");
 
-                println!();
-                true
-            } else {
-                true
-            }
-        } else {
-            true
-        }
+    // Save slot cache
+    slot_cache.save(&git_repo)?;
+    workflows::notes_sync::push_notes_if_always(&git_repo, &config, &gh_client).await?;
+
+    if let [assignment] = resolved.as_slice() {
+        println!(
+            "\n{} Commit {} is now using slot {}!",
+            style("✨").green(),
+            assignment.short_sha,
+            assignment.slot
+        );
     } else {
-        true
+        println!(
+            "\n{} Assigned {} slots!",
+            style("✨").green(),
+            resolved.len()
+        );
+    }
+    println!(
+        "\n{}",
+        style("Run 'git-stk export' to push the commits and create/update PRs.").dim()
+    );
+
+    Ok(())
+}
+
+/// Renumber numeric slots in the stack to 01..N (custom slots are kept)
+///
+/// Changing a slot changes the PR head branch, which GitHub does not allow, so
+/// entries with open PRs are rejected unless `recreate_prs` is set, in which case
+/// those PRs are closed and recreated on the next export.
+pub async fn renumber_slots(
+    skip_confirm: bool,
+    recreate_prs: bool,
+    fetch: Option<bool>,
+) -> Result<()> {
+    let git_repo = Repository::open(".").context("Failed to open git repository")?;
+    let mut config = Config::load(&git_repo)?;
+    config.fetch = fetch.unwrap_or(config.fetch);
+    let gh_client = client::create_configured_client(&config)?;
+
+    workflows::ensure_on_branch(&git_repo, &config, skip_confirm)?;
+
+    println!("🔍 Computing compacted slots...\n");
+
+    let stack = discover_stack(&git_repo, &config, &gh_client).await?;
+    if stack.is_empty() {
+        println!("{}", style("No commits in stack").dim());
+        return Ok(());
+    }
+    ensure_online(&stack, &config, "renumber")?;
+
+    let current_branch = &stack.current_branch;
+    let current: Vec<Option<String>> = stack.entries.iter().map(|e| e.slot.clone()).collect();
+    let compacted = slots::compact_slots(&current);
+
+    let changes: Vec<_> = stack
+        .entries
+        .iter()
+        .zip(compacted.iter())
+        .filter(|(entry, new_slot)| entry.slot.as_deref() != Some(new_slot.as_str()))
+        .collect();
+
+    if changes.is_empty() {
+        println!("{} Slots are already compact", style("✓").green());
+        return Ok(());
+    }
+
+    let is_open = |entry: &crate::model::Entry| {
+        entry.pr_number.is_some()
+            && matches!(
+                entry.pr_state,
+                Some(PrState::Open) | Some(PrState::Draft) | None
+            )
     };
 
-    // Get existing PR number if preserving
-    let existing_pr = if preserve_pr {
-        existing_entry.and_then(|e| e.pr_number)
+    for (entry, new_slot) in &changes {
+        println!(
+            "  {} {}  {} → {}{}",
+            style("→").dim(),
+            style(&entry.short_sha).yellow(),
+            style(entry.slot.as_deref().unwrap_or("-")).dim(),
+            style(new_slot).yellow().bold(),
+            entry
+                .pr_number
+                .filter(|_| is_open(entry))
+                .map(|pr| format!("  (closes PR #{})", pr))
+                .unwrap_or_default()
+        );
+    }
+
+    let prs_to_close: Vec<u64> = changes
+        .iter()
+        .filter(|(entry, _)| is_open(entry))
+        .filter_map(|(entry, _)| entry.pr_number)
+        .collect();
+
+    if !prs_to_close.is_empty() && !recreate_prs {
+        let pr_list: Vec<String> = prs_to_close.iter().map(|n| format!("#{}", n)).collect();
+        bail!(
+            "Renumbering would change the branch of open PR{} {}. \
+             Re-run with --recreate-prs to close and recreate them on the next export.",
+            if prs_to_close.len() == 1 { "" } else { "s" },
+            pr_list.join(", ")
+        );
+    }
+
+    let confirmed = if skip_confirm {
+        true
     } else {
-        None
+        use dialoguer::Confirm;
+        Confirm::new()
+            .with_prompt(format!(
+                "Renumber {} slot{}?",
+                changes.len(),
+                if changes.len() == 1 { "" } else { "s" }
+            ))
+            .default(false)
+            .interact()?
     };
 
-    // Create metadata with the specified slot
-    let metadata = CommitMetadata {
-        pr: existing_pr,
-        slot: slot.to_string(),
-    };
+    if !confirmed {
+        println!("\n{}", style("✗ Operation cancelled").red());
+        return Ok(());
+    }
 
-    // Mark slot as used in cache
-    slot_cache.mark_slot_used(current_branch, slot);
+    let owner = stack.entries[0].repo_owner.clone().unwrap_or_default();
+    let repo_name = stack.entries[0].repo_name.clone().unwrap_or_default();
+    let mut slot_cache = slots::SlotCache::load(&git_repo)?;
 
-    // Write metadata to the commit
-    println!("\n📝 Assigning slot {} to commit {}...", slot, short_sha);
-    notes::write_note(&git_repo, commit_oid, &metadata, &config.notes_ref)
+    println!();
+    for (entry, new_slot) in &changes {
+        let new_head_ref = slots::generate_head_ref(current_branch, new_slot);
+
+        let keep_pr = if is_open(entry) {
+            if let (Some(pr_number), Some(old_slot)) = (entry.pr_number, &entry.slot) {
+                let old_head_ref = slots::generate_head_ref(current_branch, old_slot);
+                close_pr_for_slot_change(
+                    &gh_client,
+                    &owner,
+                    &repo_name,
+                    pr_number,
+                    (old_slot, &old_head_ref),
+                    (new_slot, &new_head_ref),
+                )
+                .await;
+            }
+            false
+        } else {
+            // Merged/closed PRs keep their number for reference
+            entry.pr_number.is_some()
+        };
+
+        let metadata = CommitMetadata {
+            pr: if keep_pr { entry.pr_number } else { None },
+            slot: new_slot.to_string(),
+            annotation: entry.annotation.clone(),
+            last_export: None,
+            owner: entry.owner.clone(),
+            signature: None,
+        };
+        notes::write_note(
+            &git_repo,
+            entry.oid,
+            &metadata,
+            &config.notes_ref,
+            config.sign_notes,
+        )
         .context("Failed to write note to commit")?;
+        slot_cache.mark_slot_used(current_branch, new_slot);
+    }
 
-    println!("  ✓ Updated local metadata");
-
-    // Save slot cache
+    let numeric_count = compacted
+        .iter()
+        .filter(|s| s.parse::<u32>().is_ok())
+        .count();
+    slot_cache.reset_counter(current_branch, numeric_count as u32);
     slot_cache.save(&git_repo)?;
+    workflows::notes_sync::push_notes_if_always(&git_repo, &config, &gh_client).await?;
 
     println!(
-        "\n{} Commit {} is now using slot {}!",
+        "\n{} Renumbered {} slot{}!",
         style("✨").green(),
-        short_sha,
-        slot
+        changes.len(),
+        if changes.len() == 1 { "" } else { "s" }
     );
     println!(
         "\n{}",
-        style("Run 'git-stk export' to push the commit and create/update PRs.").dim()
+        style("Run 'git-stk export' to push the renamed branches and create/update PRs.").dim()
     );
 
     Ok(())
 }
+
+/// Comment on and close a PR whose slot (and therefore head branch) changed
+async fn close_pr_for_slot_change(
+    gh_client: &Client,
+    owner: &str,
+    repo_name: &str,
+    pr_number: u64,
+    (old_slot, old_head_ref): (&str, &str),
+    (new_slot, new_head_ref): (&str, &str),
+) {
+    println!("🔒 Closing PR #{}...", pr_number);
+    let comment = format!(
+        "This PR is being closed because the commit slot was changed from `{}` to `{}`.\n\n\
+         The branch name will change from `{}` to `{}`.\n\n\
+         A new PR will be created with the updated branch name.",
+        old_slot, new_slot, old_head_ref, new_head_ref
+    );
+
+    if let Err(e) =
+        mutations::add_pr_comment(gh_client, owner, repo_name, pr_number, &comment).await
+    {
+        eprintln!("  Warning: Failed to add comment to PR: {}", e);
+    } else {
+        println!("  ✓ Added comment to PR");
+    }
+
+    match mutations::close_pull_request(gh_client, owner, repo_name, pr_number).await {
+        Ok(_) => println!("  ✓ PR #{} closed", pr_number),
+        Err(e) => {
+            eprintln!("  Warning: Failed to close PR: {}", e);
+            eprintln!("  You may need to close it manually.");
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn args(values: &[&str]) -> Vec<String> {
+        values.iter().map(|v| v.to_string()).collect()
+    }
+
+    #[test]
+    fn test_parse_assignments() {
+        let pair = |commit: &str, slot: &str| (commit.to_string(), slot.to_string());
+
+        assert_eq!(
+            parse_assignments(&args(&["2", "handlers"])).unwrap(),
+            vec![pair("2", "handlers")]
+        );
+        assert_eq!(
+            parse_assignments(&args(&["1=api-layer", "2=handlers", "3=tests"])).unwrap(),
+            vec![
+                pair("1", "api-layer"),
+                pair("2", "handlers"),
+                pair("3", "tests")
+            ]
+        );
+        // A single assignment in the batch form
+        assert_eq!(
+            parse_assignments(&args(&["slot:02=better-name"])).unwrap(),
+            vec![pair("slot:02", "better-name")]
+        );
+
+        assert!(parse_assignments(&args(&["1=api", "handlers"])).is_err());
+        assert!(parse_assignments(&args(&["1="])).is_err());
+        assert!(parse_assignments(&args(&["api"])).is_err());
+        assert!(parse_assignments(&[]).is_err());
+    }
+}
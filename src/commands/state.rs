@@ -0,0 +1,57 @@
+use crate::git::{repo, shared_state};
+use crate::model::Config;
+use anyhow::{Context, Result};
+use console::style;
+use git2::Repository;
+
+/// Share the current branch's stack metadata (notes, slot registry, pending landing) via the remote
+pub async fn push_state() -> Result<()> {
+    let git_repo = Repository::open(".").context("Failed to open git repository")?;
+    let config = Config::load(&git_repo)?;
+    let branch = repo::current_branch(&git_repo)?;
+
+    println!("📤 Pushing stack state for {}...", style(&branch).cyan());
+    shared_state::push_state(&git_repo, &config, &branch)?;
+    println!(
+        "  {} Pushed notes, slot registry and pending landing",
+        style("✓").green()
+    );
+    println!(
+        "\n{}",
+        style(format!(
+            "Collaborators can run 'git stk pull-state' on {} to pick it up.",
+            branch
+        ))
+        .dim()
+    );
+
+    Ok(())
+}
+
+/// Fetch and merge stack metadata pushed by a collaborator for the current branch
+pub async fn pull_state() -> Result<()> {
+    let git_repo = Repository::open(".").context("Failed to open git repository")?;
+    let config = Config::load(&git_repo)?;
+    let branch = repo::current_branch(&git_repo)?;
+
+    Config::ensure_notes_rewrite_config(&git_repo, &config.notes_ref)?;
+
+    println!("📥 Pulling stack state for {}...", style(&branch).cyan());
+    let pulled = shared_state::pull_state(&git_repo, &config, &branch)?;
+
+    if pulled.slots {
+        println!("  {} Merged slot registry", style("✓").green());
+    }
+    if pulled.notes {
+        println!("  {} Merged commit metadata", style("✓").green());
+    }
+    if pulled.land {
+        println!(
+            "  {} Picked up a pending landing: run 'git stk landed' once it merges",
+            style("✓").green()
+        );
+    }
+    println!("\n{}", style("Run 'git stk view' to see the stack.").dim());
+
+    Ok(())
+}
@@ -0,0 +1,100 @@
+use crate::git::diff_stats::{self, DiffStats};
+use crate::model::Config;
+use crate::stack::discover_local_stack;
+use anyhow::{Context, Result};
+use console::style;
+use git2::Repository;
+
+#[derive(Debug, Clone, Default)]
+pub struct StatsOptions {
+    /// Changed-line threshold above which an entry is flagged, overrides
+    /// `git-stk.maxEntryLines` (0 disables the warning)
+    pub max_lines: Option<usize>,
+}
+
+/// Show per-entry diff stats and the total size of the stack, flagging entries
+/// too large to review comfortably
+pub async fn stats(options: StatsOptions) -> Result<()> {
+    let git_repo = Repository::open(".").context("Failed to open git repository")?;
+    let config = Config::load(&git_repo)?;
+    let max_lines = options.max_lines.unwrap_or(config.max_entry_lines);
+
+    let stack = discover_local_stack(&git_repo, &config)?;
+    let (Some(bottom), Some(top)) = (stack.entries.first(), stack.entries.last()) else {
+        println!("{}", style("No commits in stack").dim());
+        return Ok(());
+    };
+
+    let mut entry_stats = Vec::with_capacity(stack.len());
+    for entry in &stack.entries {
+        entry_stats.push(diff_stats::commit_diff_stats(&git_repo, entry.oid)?);
+    }
+    let total = diff_stats::range_diff_stats(&git_repo, bottom.oid, top.oid)?;
+
+    println!(
+        "\n📊 {} on {} ({} commit{})\n",
+        style(&stack.current_branch).cyan(),
+        style(&stack.base_branch).yellow(),
+        stack.len(),
+        if stack.len() == 1 { "" } else { "s" }
+    );
+
+    let index_width = stack.len().to_string().len();
+    let subject_width = stack
+        .entries
+        .iter()
+        .map(|e| e.subject.chars().count().min(50))
+        .max()
+        .unwrap_or(0);
+
+    let mut oversized = 0;
+    for (entry, stats) in stack.entries.iter().zip(&entry_stats) {
+        let subject: String = entry.subject.chars().take(50).collect();
+        let too_large = max_lines > 0 && stats.lines() > max_lines;
+        if too_large {
+            oversized += 1;
+        }
+
+        println!(
+            "  {:>iw$}. {}  {:sw$}  {}{}",
+            entry.index,
+            style(&entry.short_sha).yellow(),
+            subject,
+            format_stats(stats),
+            if too_large {
+                format!("  {}", style(format!("⚠ {} lines", stats.lines())).red())
+            } else {
+                String::new()
+            },
+            iw = index_width,
+            sw = subject_width,
+        );
+    }
+
+    println!("\n  {} {}", style("Total:").bold(), format_stats(&total));
+
+    if oversized > 0 {
+        println!(
+            "\n{} {} entr{} exceed{} {} changed lines; consider splitting {} for review.",
+            style("⚠").yellow(),
+            oversized,
+            if oversized == 1 { "y" } else { "ies" },
+            if oversized == 1 { "s" } else { "" },
+            max_lines,
+            if oversized == 1 { "it" } else { "them" }
+        );
+    }
+
+    Ok(())
+}
+
+/// Aligned `  3 files   +120 -4` column
+fn format_stats(stats: &DiffStats) -> String {
+    format!(
+        "{:>3} file{}  {} {}",
+        stats.files,
+        if stats.files == 1 { " " } else { "s" },
+        style(format!("{:>+5}", stats.insertions as i64)).green(),
+        style(format!("-{}", stats.deletions)).red()
+    )
+}
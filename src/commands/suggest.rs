@@ -0,0 +1,177 @@
+use crate::git::suggest::{self, HunkSuggestion, SuggestSource, Target};
+use crate::model::{Config, Stack};
+use crate::stack::discover_local_stack;
+use anyhow::{Context, Result, bail};
+use console::style;
+use git2::Repository;
+use std::collections::BTreeMap;
+
+#[derive(Debug, Clone, Default)]
+pub struct SuggestOptions {
+    /// Analyze the top commit of the stack instead of uncommitted changes
+    pub top: bool,
+}
+
+/// Suggest which stack entry each hunk should be folded into, without changing anything
+pub async fn suggest(options: SuggestOptions) -> Result<()> {
+    let git_repo = Repository::open(".").context("Failed to open git repository")?;
+    let config = Config::load(&git_repo)?;
+    let stack = discover_local_stack(&git_repo, &config)?;
+
+    let source = if options.top {
+        if stack.len() < 2 {
+            bail!("--top needs at least two commits in the stack");
+        }
+        SuggestSource::TopCommit
+    } else {
+        if stack.is_empty() {
+            bail!("No commits in stack - there is nothing to fold changes into");
+        }
+        SuggestSource::Uncommitted
+    };
+
+    let suggestions = suggest::suggest_fixups(&git_repo, &stack, source)?;
+    if suggestions.is_empty() {
+        match source {
+            SuggestSource::Uncommitted => {
+                println!("{}", style("No uncommitted changes to analyze").dim())
+            }
+            SuggestSource::TopCommit => println!(
+                "{}",
+                style("The top commit has no changes to analyze").dim()
+            ),
+        }
+        return Ok(());
+    }
+
+    println!(
+        "\n🔍 Suggested targets for {}:\n",
+        match source {
+            SuggestSource::Uncommitted => "uncommitted changes",
+            SuggestSource::TopCommit => "the top commit",
+        }
+    );
+
+    let location_width = suggestions
+        .iter()
+        .map(|s| format_location(s).len())
+        .max()
+        .unwrap_or(0);
+    for suggestion in &suggestions {
+        println!(
+            "  {:lw$}  {} {}",
+            format_location(suggestion),
+            style("→").dim(),
+            format_target(&stack, suggestion),
+            lw = location_width
+        );
+    }
+
+    print_next_steps(&stack, &config, &suggestions, source);
+
+    Ok(())
+}
+
+/// `path:12-14`, or `path:12` for a single line or a deletion
+fn format_location(suggestion: &HunkSuggestion) -> String {
+    match suggestion.new_lines {
+        0 | 1 => format!("{}:{}", suggestion.path, suggestion.new_start),
+        n => format!(
+            "{}:{}-{}",
+            suggestion.path,
+            suggestion.new_start,
+            suggestion.new_start + n - 1
+        ),
+    }
+}
+
+fn format_target(stack: &Stack, suggestion: &HunkSuggestion) -> String {
+    match &suggestion.target {
+        Target::Entry(position) => {
+            let entry = &stack.entries[*position];
+            format!(
+                "{}. {} {}{}",
+                entry.index,
+                style(&entry.short_sha).yellow(),
+                entry.subject,
+                if suggestion.insertion {
+                    style(" (next to its lines)").dim().to_string()
+                } else {
+                    String::new()
+                }
+            )
+        }
+        Target::Base => style("no entry (changes lines older than the stack)")
+            .dim()
+            .to_string(),
+        Target::NewFile => style("no entry (new file)").dim().to_string(),
+        Target::Ambiguous(positions) => {
+            let entries: Vec<String> = positions
+                .iter()
+                .map(|p| stack.entries[*p].index.to_string())
+                .collect();
+            style(format!(
+                "ambiguous (lines from {}; split the hunk)",
+                entries.join(", ")
+            ))
+            .yellow()
+            .to_string()
+        }
+    }
+}
+
+/// Print the commands that would apply the suggestions
+fn print_next_steps(
+    stack: &Stack,
+    config: &Config,
+    suggestions: &[HunkSuggestion],
+    source: SuggestSource,
+) {
+    // Files per target entry, in stack order
+    let mut by_entry: BTreeMap<usize, Vec<&str>> = BTreeMap::new();
+    for suggestion in suggestions {
+        if let Target::Entry(position) = suggestion.target {
+            let files = by_entry.entry(position).or_default();
+            if !files.contains(&suggestion.path.as_str()) {
+                files.push(&suggestion.path);
+            }
+        }
+    }
+    if by_entry.is_empty() {
+        return;
+    }
+
+    println!("\n{} To apply:", style("💡").yellow());
+    if source == SuggestSource::TopCommit {
+        println!(
+            "  git reset HEAD^              # turn the top commit back into uncommitted changes"
+        );
+    }
+    for (position, files) in &by_entry {
+        println!(
+            "  git add -p {} && git commit --fixup {}",
+            files.join(" "),
+            stack.entries[*position].short_sha
+        );
+    }
+    println!("  git rebase -i --autosquash {}", config.base_name());
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_format_location() {
+        let suggestion = |new_start, new_lines| HunkSuggestion {
+            path: "src/lib.rs".to_string(),
+            new_start,
+            new_lines,
+            insertion: false,
+            target: Target::Base,
+        };
+        assert_eq!(format_location(&suggestion(12, 3)), "src/lib.rs:12-14");
+        assert_eq!(format_location(&suggestion(12, 1)), "src/lib.rs:12");
+        assert_eq!(format_location(&suggestion(12, 0)), "src/lib.rs:12");
+    }
+}
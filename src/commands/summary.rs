@@ -0,0 +1,64 @@
+use crate::commands::export::extract_commit_body;
+use crate::git::trailers;
+use crate::model::Config;
+use crate::stack::discover_local_stack;
+use crate::ui::summary::render_summary;
+use anyhow::{Context, Result, bail};
+use console::style;
+use git2::Repository;
+use std::fs;
+use std::path::PathBuf;
+
+#[derive(Debug, Clone, Default)]
+pub struct SummaryOptions {
+    /// Group entries by conventional-commit type (feat, fix, ...)
+    pub conventional: bool,
+    /// Only list subjects, leave out commit bodies
+    pub no_body: bool,
+    /// Write the summary to this file instead of stdout
+    pub output: Option<PathBuf>,
+}
+
+/// Print a markdown summary of the stack's commits, for the top PR or release notes
+pub async fn summary(options: SummaryOptions) -> Result<()> {
+    let git_repo = Repository::open(".").context("Failed to open git repository")?;
+    let config = Config::load(&git_repo)?;
+
+    // Local only: PR numbers come from notes, so no GitHub round-trip is needed
+    let stack = discover_local_stack(&git_repo, &config)?;
+    if stack.is_empty() {
+        bail!("No commits in stack - nothing to summarize");
+    }
+
+    let mut items = Vec::with_capacity(stack.len());
+    for entry in &stack.entries {
+        let commit = git_repo
+            .find_commit(entry.oid)
+            .context("Failed to find commit")?;
+        let (body, _) =
+            trailers::split_trailers(&extract_commit_body(commit.message().unwrap_or("")));
+        items.push((entry, body));
+    }
+
+    let markdown = render_summary(
+        &stack.current_branch,
+        &items,
+        options.conventional,
+        !options.no_body,
+    );
+
+    match options.output {
+        Some(path) => {
+            fs::write(&path, markdown)
+                .with_context(|| format!("Failed to write {}", path.display()))?;
+            println!(
+                "{} Wrote {}",
+                style("✓").green(),
+                style(path.display()).cyan()
+            );
+        }
+        None => print!("{}", markdown),
+    }
+
+    Ok(())
+}
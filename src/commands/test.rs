@@ -0,0 +1,88 @@
+use crate::git::commit_ref;
+use crate::git::worktree::TempWorktree;
+use crate::model::Config;
+use crate::stack::discover_local_stack;
+use crate::workflows::test_run::{self, TestCache};
+use anyhow::{Context, Result, bail};
+use console::style;
+use git2::Repository;
+
+/// Number of output lines shown for a failing entry
+const FAILURE_TAIL_LINES: usize = 20;
+
+/// Run a command (`test_run::shell_command`) at every commit in the stack (or
+/// the entries `only` selects) and report pass/fail per entry
+pub async fn test(command: &[String], no_cache: bool, only: Option<&str>) -> Result<()> {
+    let command = &test_run::shell_command(command)?;
+    let git_repo = Repository::open(".").context("Failed to open git repository")?;
+    let config = Config::load(&git_repo)?;
+
+    let mut stack = discover_local_stack(&git_repo, &config)?;
+    if stack.is_empty() {
+        bail!("No commits in stack to test.");
+    }
+    if let Some(only) = only {
+        let selected = commit_ref::resolve_commit_refs(&git_repo, &stack, only)?;
+        stack.entries.retain(|e| selected.contains(&e.oid));
+        if stack.is_empty() {
+            bail!("'{}' selects no commits in the stack", only);
+        }
+    }
+
+    println!(
+        "\n🧪 Running {} on {} commit{}...\n",
+        style(command).cyan(),
+        stack.len(),
+        if stack.len() == 1 { "" } else { "s" }
+    );
+
+    let worktree = TempWorktree::create(&git_repo, "test", stack.entries[0].oid)?;
+    let mut cache = TestCache::load(&git_repo)?;
+    let max_index_width = stack
+        .entries
+        .last()
+        .map_or(1, |e| e.index.to_string().len());
+
+    let mut failed = 0;
+    for entry in &stack.entries {
+        let outcome = test_run::run_command_at(
+            &git_repo, &worktree, &mut cache, entry.oid, command, !no_cache,
+        )?;
+
+        let status = if outcome.passed {
+            style("✓ pass").green()
+        } else {
+            style("✗ fail").red()
+        };
+        println!(
+            "  {} {}  {}  {}{}",
+            style(format!("{:>width$}.", entry.index, width = max_index_width)).dim(),
+            status,
+            style(&entry.short_sha).yellow(),
+            style(&entry.subject).bold(),
+            if outcome.cached {
+                style(" (cached)").dim().to_string()
+            } else {
+                String::new()
+            }
+        );
+
+        if !outcome.passed {
+            failed += 1;
+            let lines: Vec<&str> = outcome.output.lines().collect();
+            for line in &lines[lines.len().saturating_sub(FAILURE_TAIL_LINES)..] {
+                println!("       {}", style(line).dim());
+            }
+        }
+    }
+
+    cache.save(&git_repo)?;
+    println!();
+
+    if failed > 0 {
+        bail!("{} of {} entries failed", failed, stack.len());
+    }
+
+    println!("{} All {} entries passed", style("✓").green(), stack.len());
+    Ok(())
+}
@@ -0,0 +1,138 @@
+use crate::gh::queries::{self, ReviewThread};
+use crate::gh::{client, mutations};
+use crate::git::commit_ref;
+use crate::model::Config;
+use crate::stack::discover_stack;
+use anyhow::{Context, Result};
+use console::style;
+use git2::Repository;
+
+#[derive(Debug, Clone, Default)]
+pub struct ThreadsOptions {
+    /// Also list resolved threads
+    pub all: bool,
+    /// Thread IDs to mark resolved instead of listing
+    pub resolve: Vec<String>,
+    /// `--fetch`/`--no-fetch`, overriding `git-stk.fetch` when given
+    pub fetch: Option<bool>,
+}
+
+/// List the unresolved review threads on the PR of a stack entry, or resolve some
+pub async fn threads(commit_ref: &str, options: ThreadsOptions) -> Result<()> {
+    let git_repo = Repository::open(".").context("Failed to open git repository")?;
+    let mut config = Config::load(&git_repo)?;
+    config.fetch = options.fetch.unwrap_or(config.fetch);
+    let gh_client = client::create_configured_client(&config)?;
+
+    let stack = discover_stack(&git_repo, &config, &gh_client).await?;
+
+    let commit_oid = commit_ref::resolve_commit_ref(&git_repo, &stack, commit_ref)?;
+    let entry = stack
+        .entries
+        .iter()
+        .find(|e| e.oid == commit_oid)
+        .with_context(|| format!("Commit {} is not part of the current stack", commit_ref))?;
+
+    let pr_number = entry.pr_number.with_context(|| {
+        format!(
+            "Commit {} has no PR yet. Run 'git stk export' first.",
+            entry.short_sha
+        )
+    })?;
+    let owner = entry.repo_owner.as_ref().context("Missing repo owner")?;
+    let repo_name = entry.repo_name.as_ref().context("Missing repo name")?;
+
+    let threads = queries::get_review_threads(&gh_client, owner, repo_name, pr_number).await?;
+
+    if !options.resolve.is_empty() {
+        // Only resolve threads of this PR, so a stale ID can't touch another one
+        let targets = options
+            .resolve
+            .iter()
+            .map(|id| {
+                threads
+                    .iter()
+                    .find(|t| &t.id == id)
+                    .with_context(|| format!("No review thread {} on PR #{}", id, pr_number))
+            })
+            .collect::<Result<Vec<_>>>()?;
+        for thread in targets {
+            if thread.is_resolved {
+                println!(
+                    "{} {} was already resolved",
+                    style("ℹ").blue(),
+                    location(thread)
+                );
+                continue;
+            }
+            mutations::resolve_review_thread(&gh_client, &thread.id).await?;
+            println!("{} Resolved {}", style("✓").green(), location(thread));
+        }
+        return Ok(());
+    }
+
+    let shown: Vec<&ReviewThread> = threads
+        .iter()
+        .filter(|t| options.all || !t.is_resolved)
+        .collect();
+    let unresolved = threads.iter().filter(|t| !t.is_resolved).count();
+    println!(
+        "💬 {} unresolved thread{} on {}",
+        unresolved,
+        if unresolved == 1 { "" } else { "s" },
+        style(entry.pr_url().unwrap_or_else(|| format!("#{}", pr_number))).cyan()
+    );
+    if shown.is_empty() {
+        return Ok(());
+    }
+    println!();
+
+    for thread in shown {
+        let mut flags = Vec::new();
+        if thread.is_resolved {
+            flags.push(style("resolved").green().to_string());
+        }
+        if thread.is_outdated {
+            flags.push(style("outdated").dim().to_string());
+        }
+        let flags = if flags.is_empty() {
+            String::new()
+        } else {
+            format!(" ({})", flags.join(", "))
+        };
+        println!(
+            "  {}{}  {}",
+            style(location(thread)).bold(),
+            flags,
+            style(&thread.id).dim()
+        );
+        for comment in &thread.comments {
+            println!("    {}:", style(&comment.author).cyan());
+            for line in comment.body.trim().lines() {
+                println!("      {}", line);
+            }
+        }
+        println!();
+    }
+
+    if unresolved > 0 {
+        println!(
+            "{}",
+            style(format!(
+                "Resolve with 'git stk threads {} --resolve <thread-id>'",
+                commit_ref
+            ))
+            .dim()
+        );
+    }
+
+    Ok(())
+}
+
+/// `path:line` of a thread
+fn location(thread: &ReviewThread) -> String {
+    match thread.line {
+        Some(line) => format!("{}:{}", thread.path, line),
+        None => thread.path.clone(),
+    }
+}
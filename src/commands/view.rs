@@ -1,18 +1,446 @@
 use crate::gh::client;
-use crate::model::Config;
-use crate::stack::discover_stack;
-use crate::ui::render_timeline;
+use crate::git::{commit_ref, overlap, repo};
+use crate::model::{Config, Entry, OrphanedPr, PrState, RemoteRefsSource, Stack, UpdateStatus};
+use crate::stack::{discover_remote_stack, discover_stack};
+use crate::ui::{render_overlaps, render_timeline};
+use crate::workflows::pending_land::PendingLands;
 use anyhow::{Context, Result};
+use console::style;
 use git2::Repository;
+use serde_json::json;
 
-pub async fn view() -> Result<()> {
+/// Narrows the view to entries needing action (`--only`)
+#[derive(Debug, Clone, Copy, PartialEq, Eq, clap::ValueEnum)]
+pub enum ViewFilter {
+    /// Not merged, and the slot branch is missing or behind the commit
+    NeedsExport,
+    /// PR merged
+    Merged,
+    /// Open as a draft PR
+    Draft,
+}
+
+impl ViewFilter {
+    pub fn matches(self, entry: &Entry) -> bool {
+        match self {
+            Self::NeedsExport => !entry.merged_into_main && entry.status != UpdateStatus::UpToDate,
+            Self::Merged => entry.merged_into_main,
+            Self::Draft => !entry.merged_into_main && entry.pr_state == Some(PrState::Draft),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct ViewOptions {
+    /// Copy the PR URL of this entry to the clipboard instead of rendering the timeline
+    pub copy: Option<String>,
+    /// Print only the PR URL of this entry (for scripting)
+    pub url_only: Option<String>,
+    /// Show author initials and relative commit age per entry
+    pub details: bool,
+    /// Render the stack of a branch that only exists on the remote (read-only)
+    pub remote: Option<String>,
+    /// Only show entries matching any of these filters (all entries when empty)
+    pub only: Vec<ViewFilter>,
+    /// Print the stack as JSON instead of rendering the timeline
+    pub json: bool,
+    /// Show at most this many entries (default: `git-stk.viewLimit`)
+    pub limit: Option<usize>,
+    /// Center the shown entries on this entry
+    pub around: Option<String>,
+    /// `--fetch`/`--no-fetch`, overriding `git-stk.fetch` when given
+    pub fetch: Option<bool>,
+}
+
+/// Entries shown around `--around` when no limit is set
+const DEFAULT_AROUND_LIMIT: usize = 10;
+
+pub async fn view(options: ViewOptions) -> Result<()> {
     let git_repo = Repository::open(".").context("Failed to open git repository")?;
-    let config = Config::load(&git_repo)?;
-    let gh_client = client::create_client()?;
+    let mut config = Config::load(&git_repo)?;
+    config.fetch = options.fetch.unwrap_or(config.fetch);
+    let gh_client = client::create_configured_client(&config)?;
+
+    let stack = match &options.remote {
+        Some(branch) => discover_remote_stack(&git_repo, &config, &gh_client, branch).await?,
+        None => discover_stack(&git_repo, &config, &gh_client).await?,
+    };
+
+    show_stack(&git_repo, &config, &stack, &options)
+}
+
+/// Show a discovered stack as `view` does (e.g. the one export verified)
+pub fn show_stack(
+    git_repo: &Repository,
+    config: &Config,
+    stack: &Stack,
+    options: &ViewOptions,
+) -> Result<()> {
+    if let Some(reference) = &options.url_only {
+        let url = resolve_pr_url(git_repo, stack, reference)?;
+        println!("{}", url);
+        return Ok(());
+    }
+
+    if let Some(reference) = &options.copy {
+        let url = resolve_pr_url(git_repo, stack, reference)?;
+        copy_to_clipboard(&url).context("Failed to copy PR URL to the clipboard")?;
+        println!("{} Copied {}", style("✓").green(), style(&url).cyan());
+        return Ok(());
+    }
+
+    let mut shown = filter_stack(stack, &options.only);
+    let filtered_len = shown.len();
+
+    let around = match &options.around {
+        Some(reference) => {
+            let oid = commit_ref::resolve_commit_ref(git_repo, stack, reference)?;
+            let position = shown
+                .entries
+                .iter()
+                .position(|e| e.oid == oid)
+                .with_context(|| {
+                    format!(
+                        "Commit {} is not among the entries shown (check --only)",
+                        reference
+                    )
+                })?;
+            Some(position)
+        }
+        None => None,
+    };
+    let limit = options
+        .limit
+        .or((config.view_limit > 0).then_some(config.view_limit));
+    let range = window(shown.len(), limit, around);
+    let (hidden_before, hidden_after) = (range.start, shown.len() - range.end);
+    shown.entries = shown.entries[range].to_vec();
 
-    let stack = discover_stack(&git_repo, &config, &gh_client).await?;
+    if options.json {
+        println!("{}", serde_json::to_string_pretty(&stack_json(&shown))?);
+        return Ok(());
+    }
 
-    render_timeline(&stack);
+    if let Some(branch) = &options.remote {
+        println!(
+            "{}",
+            style(format!("Stack of {}/{} (read-only)", config.remote, branch)).dim()
+        );
+    } else if repo::is_detached(git_repo)? {
+        println!(
+            "{}",
+            style(
+                "HEAD is detached: showing a read-only view. Export will offer to create a branch."
+            )
+            .dim()
+        );
+    }
+
+    if options.remote.is_none()
+        && let Some(land) = PendingLands::load(git_repo)?.get(&stack.current_branch)
+    {
+        println!(
+            "⏳ Landing PR #{} (started with --skip-wait). Run 'git stk landed' once it merges.",
+            style(land.pr).cyan()
+        );
+    }
+
+    if !options.only.is_empty() {
+        println!(
+            "{}",
+            style(format!(
+                "Showing {} of {} entries",
+                shown.len(),
+                stack.len()
+            ))
+            .dim()
+        );
+        if shown.is_empty() {
+            println!("{}", style("No entries match the filter").dim());
+            return Ok(());
+        }
+    }
+
+    if hidden_before + hidden_after > 0
+        && let (Some(first), Some(last)) = (shown.entries.first(), shown.entries.last())
+    {
+        println!(
+            "{}",
+            style(format!(
+                "Showing entries {}–{} ({} of {}); page with --around <index> and --limit <n>",
+                first.index,
+                last.index,
+                shown.len(),
+                filtered_len
+            ))
+            .dim()
+        );
+    }
+
+    render_timeline(&shown, options.details);
+
+    if config.overlap_warnings {
+        // Overlaps index the full stack; keep those whose later entry is shown
+        let overlaps: Vec<_> = overlap::stack_overlaps(git_repo, stack)?
+            .into_iter()
+            .filter(|o| {
+                shown
+                    .entries
+                    .iter()
+                    .any(|e| e.oid == stack.entries[o.later].oid)
+            })
+            .collect();
+        render_overlaps(stack, &overlaps, options.details);
+    }
+
+    render_orphaned_prs(&stack.orphaned_prs);
 
     Ok(())
 }
+
+/// Open PRs on slot branches that no entry uses any more
+fn render_orphaned_prs(orphaned: &[OrphanedPr]) {
+    for pr in orphaned {
+        println!(
+            "{} Orphaned PR {} on slot {} — run {}",
+            style("⚠").yellow(),
+            style(format!("#{}", pr.pr_number)).cyan(),
+            style(&pr.slot).yellow(),
+            style("git stk prune").bold()
+        );
+    }
+    if !orphaned.is_empty() {
+        println!();
+    }
+}
+
+/// Put `text` on the clipboard
+///
+/// X11 and Wayland drop a clipboard when the process that owns it exits, so on
+/// Linux a detached `git stk hold-clipboard` owns it until something else is copied.
+#[cfg(target_os = "linux")]
+fn copy_to_clipboard(text: &str) -> Result<()> {
+    use std::io::Write;
+    use std::os::unix::process::CommandExt;
+    use std::process::{Command, Stdio};
+
+    // Fails here, rather than unseen in the holder, without a display
+    arboard::Clipboard::new().context("Failed to access the clipboard")?;
+    let mut holder =
+        Command::new(std::env::current_exe().context("Failed to find the git-stk executable")?)
+            .arg("hold-clipboard")
+            .stdin(Stdio::piped())
+            .stdout(Stdio::null())
+            .stderr(Stdio::null())
+            // Its own process group, so Ctrl-C in the terminal leaves it running
+            .process_group(0)
+            .spawn()
+            .context("Failed to start the clipboard holder")?;
+    holder
+        .stdin
+        .take()
+        .context("Failed to pass the text to the clipboard holder")?
+        .write_all(text.as_bytes())
+        .context("Failed to pass the text to the clipboard holder")?;
+    Ok(())
+}
+
+/// Put `text` on the clipboard
+#[cfg(not(target_os = "linux"))]
+fn copy_to_clipboard(text: &str) -> Result<()> {
+    let mut clipboard = arboard::Clipboard::new().context("Failed to access the clipboard")?;
+    clipboard
+        .set_text(text)
+        .context("Failed to set the clipboard")?;
+    Ok(())
+}
+
+/// Own the clipboard with the text read from stdin until something else is
+/// copied (`git stk hold-clipboard`, started by `view --copy` on Linux)
+pub fn hold_clipboard() -> Result<()> {
+    use std::io::Read;
+
+    let mut text = String::new();
+    std::io::stdin()
+        .read_to_string(&mut text)
+        .context("Failed to read the text to copy")?;
+    let mut clipboard = arboard::Clipboard::new().context("Failed to access the clipboard")?;
+    #[cfg(target_os = "linux")]
+    {
+        use arboard::SetExtLinux;
+        clipboard
+            .set()
+            .wait()
+            .text(text)
+            .context("Failed to set the clipboard")?;
+    }
+    #[cfg(not(target_os = "linux"))]
+    clipboard
+        .set_text(text)
+        .context("Failed to set the clipboard")?;
+    Ok(())
+}
+
+/// The entries matching any of `filters`, keeping their stack positions
+fn filter_stack(stack: &Stack, filters: &[ViewFilter]) -> Stack {
+    let mut shown = stack.clone();
+    if !filters.is_empty() {
+        shown
+            .entries
+            .retain(|entry| filters.iter().any(|f| f.matches(entry)));
+    }
+    shown
+}
+
+/// Positions of the entries to show out of `len`: `limit` entries centered on
+/// `around`, or the top `limit` entries of the stack. A limit of 0 shows all.
+fn window(len: usize, limit: Option<usize>, around: Option<usize>) -> std::ops::Range<usize> {
+    let limit = limit.filter(|&n| n > 0);
+    match (around, limit) {
+        (Some(position), limit) => {
+            let size = limit.unwrap_or(DEFAULT_AROUND_LIMIT).min(len);
+            let start = position.saturating_sub(size / 2).min(len - size);
+            start..start + size
+        }
+        (None, Some(limit)) => len.saturating_sub(limit)..len,
+        (None, None) => 0..len,
+    }
+}
+
+/// The stack as printed by `view --json`
+fn stack_json(stack: &Stack) -> serde_json::Value {
+    json!({
+        "branch": stack.current_branch,
+        "base": stack.base_branch,
+        "offline": stack.offline,
+        "remote_refs": match stack.remote_refs_source {
+            RemoteRefsSource::Remote => json!({ "source": "remote" }),
+            RemoteRefsSource::Tracking { fetched_at } => json!({ "source": "tracking", "fetched_at": fetched_at }),
+        },
+        "entries": stack.entries.iter().map(|e| json!({
+            "index": e.index,
+            "commit": e.oid.to_string(),
+            "subject": e.subject,
+            "slot": e.slot.as_ref().or(e.predicted_slot.as_ref()),
+            "head_ref": e.head_ref,
+            "pr": e.pr_number,
+            "url": e.pr_url(),
+            "state": e.pr_state,
+            "needs_export": ViewFilter::NeedsExport.matches(e),
+            "merged": e.merged_into_main,
+            "conflicts": e.has_conflicts,
+            "unresolved_threads": e.unresolved_threads,
+            "review": e.review_decision,
+            "ci": e.ci_status,
+            "remote_commit": e.remote_oid.map(|oid| oid.to_string()),
+            "annotation": e.annotation,
+            "last_export": e.last_export,
+            "owner": e.owner,
+            "context_in_pr": stack.context_in_pr(e).map(|c| c.oid.to_string()).collect::<Vec<_>>(),
+        })).collect::<Vec<_>>(),
+        "context": stack.context.iter().map(|c| json!({
+            "below": c.below,
+            "commit": c.oid.to_string(),
+            "subject": c.subject,
+            "author": c.author_name,
+        })).collect::<Vec<_>>(),
+        "orphaned_prs": stack.orphaned_prs,
+    })
+}
+
+/// Resolve a commit reference to the PR URL of its stack entry
+fn resolve_pr_url(git_repo: &Repository, stack: &Stack, reference: &str) -> Result<String> {
+    let oid = commit_ref::resolve_commit_ref(git_repo, stack, reference)?;
+    entry_pr_url(stack, oid, reference)
+}
+
+/// PR URL of the stack entry for `oid`, which `reference` resolved to
+pub(crate) fn entry_pr_url(stack: &Stack, oid: git2::Oid, reference: &str) -> Result<String> {
+    let entry = find_entry(stack, oid, reference)?;
+
+    entry.pr_url().with_context(|| {
+        format!(
+            "Commit {} has no PR yet. Run 'git stk export' first.",
+            entry.short_sha
+        )
+    })
+}
+
+fn find_entry<'a>(stack: &'a Stack, oid: git2::Oid, reference: &str) -> Result<&'a Entry> {
+    stack
+        .entries
+        .iter()
+        .find(|e| e.oid == oid)
+        .with_context(|| format!("Commit {} is not part of the current stack", reference))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_view_filter_values() {
+        use clap::ValueEnum;
+        assert_eq!(
+            ViewFilter::from_str("needs-export", false).unwrap(),
+            ViewFilter::NeedsExport
+        );
+        assert_eq!(
+            ViewFilter::from_str("merged", false).unwrap(),
+            ViewFilter::Merged
+        );
+        assert_eq!(
+            ViewFilter::from_str("draft", false).unwrap(),
+            ViewFilter::Draft
+        );
+        assert!(ViewFilter::from_str("open", false).is_err());
+    }
+
+    #[test]
+    fn test_filter_stack() {
+        let mut stack = Stack::new("main".to_string(), "feat".to_string());
+
+        let mut merged = Entry::for_test(1);
+        merged.merged_into_main = true;
+        merged.pr_state = Some(PrState::Merged);
+        let mut synced = Entry::for_test(2);
+        synced.status = UpdateStatus::UpToDate;
+        synced.pr_state = Some(PrState::Open);
+        let mut draft = Entry::for_test(3);
+        draft.status = UpdateStatus::UpToDate;
+        draft.pr_state = Some(PrState::Draft);
+        let behind = Entry::for_test(4);
+        for e in [merged, synced, draft, behind] {
+            stack.add_entry(e);
+        }
+
+        let indices = |filters: &[ViewFilter]| -> Vec<usize> {
+            filter_stack(&stack, filters)
+                .entries
+                .iter()
+                .map(|e| e.index)
+                .collect()
+        };
+        assert_eq!(indices(&[]), vec![1, 2, 3, 4]);
+        assert_eq!(indices(&[ViewFilter::NeedsExport]), vec![4]);
+        assert_eq!(indices(&[ViewFilter::Merged]), vec![1]);
+        assert_eq!(
+            indices(&[ViewFilter::Draft, ViewFilter::NeedsExport]),
+            vec![3, 4]
+        );
+    }
+
+    #[test]
+    fn test_window() {
+        assert_eq!(window(42, None, None), 0..42);
+        // The top of the stack
+        assert_eq!(window(42, Some(10), None), 32..42);
+        assert_eq!(window(5, Some(10), None), 0..5);
+        assert_eq!(window(42, Some(0), None), 0..42);
+        // Centered, clamped to the stack
+        assert_eq!(window(42, Some(10), Some(20)), 15..25);
+        assert_eq!(window(42, Some(10), Some(1)), 0..10);
+        assert_eq!(window(42, None, Some(41)), 32..42);
+        assert_eq!(window(3, None, Some(1)), 0..3);
+    }
+}
@@ -0,0 +1,144 @@
+use crate::gh::client::{self, Client};
+use crate::model::Config;
+use crate::stack::discover_stack;
+use crate::ui::html::render_html;
+use anyhow::{Context, Result};
+use console::style;
+use git2::Repository;
+use std::fs;
+use std::path::PathBuf;
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+use tokio::net::{TcpListener, TcpStream};
+use tracing::debug;
+
+#[derive(Debug, Clone, Default)]
+pub struct WebOptions {
+    /// Where to write the page (default: .git/git-stk/stack.html)
+    pub output: Option<PathBuf>,
+    /// Don't open the page in the browser
+    pub no_open: bool,
+    /// Serve the page on localhost on this port, re-discovering the stack on every load
+    pub serve: Option<u16>,
+    /// `--fetch`/`--no-fetch`, overriding `git-stk.fetch` when given
+    pub fetch: Option<bool>,
+}
+
+/// Render the stack as an HTML dashboard, written to a file or served on localhost
+pub async fn web(options: WebOptions) -> Result<()> {
+    let git_repo = Repository::open(".").context("Failed to open git repository")?;
+    let mut config = Config::load(&git_repo)?;
+    config.fetch = options.fetch.unwrap_or(config.fetch);
+    let gh_client = client::create_configured_client(&config)?;
+
+    if let Some(port) = options.serve {
+        return serve(&git_repo, &config, &gh_client, port, options.no_open).await;
+    }
+
+    let html = render_page(&git_repo, &config, &gh_client).await?;
+
+    let path = match options.output {
+        Some(path) => path,
+        None => Config::git_stack_dir(&git_repo)?.join("stack.html"),
+    };
+    if let Some(parent) = path.parent()
+        && !parent.as_os_str().is_empty()
+    {
+        fs::create_dir_all(parent)
+            .with_context(|| format!("Failed to create directory {}", parent.display()))?;
+    }
+    fs::write(&path, html).with_context(|| format!("Failed to write {}", path.display()))?;
+
+    println!(
+        "{} Wrote {}",
+        style("✓").green(),
+        style(path.display()).cyan()
+    );
+    if !options.no_open
+        && let Err(e) = open::that(&path)
+    {
+        eprintln!("  ⚠ Failed to open browser: {}", e);
+    }
+
+    Ok(())
+}
+
+/// Discover the stack and render it
+async fn render_page(git_repo: &Repository, config: &Config, gh_client: &Client) -> Result<String> {
+    let stack = discover_stack(git_repo, config, gh_client).await?;
+    Ok(render_html(&stack))
+}
+
+/// Serve the dashboard until interrupted
+async fn serve(
+    git_repo: &Repository,
+    config: &Config,
+    gh_client: &Client,
+    port: u16,
+    no_open: bool,
+) -> Result<()> {
+    let listener = TcpListener::bind(("127.0.0.1", port))
+        .await
+        .with_context(|| format!("Failed to listen on port {}", port))?;
+    let url = format!("http://{}/", listener.local_addr()?);
+
+    println!("🌐 Serving stack at {}", style(&url).cyan());
+    println!(
+        "   {}",
+        style("Reload the page to refresh; press Ctrl-C to stop").dim()
+    );
+    if !no_open && let Err(e) = open::that(&url) {
+        eprintln!("  ⚠ Failed to open browser: {}", e);
+    }
+
+    loop {
+        let (stream, peer) = listener.accept().await?;
+        debug!(%peer, "dashboard request");
+        // Requests are handled one at a time: the repository isn't shareable across tasks
+        if let Err(e) = handle_request(stream, git_repo, config, gh_client).await {
+            eprintln!("  ⚠ {}", e);
+        }
+    }
+}
+
+async fn handle_request(
+    mut stream: TcpStream,
+    git_repo: &Repository,
+    config: &Config,
+    gh_client: &Client,
+) -> Result<()> {
+    let mut request = Vec::new();
+    let mut chunk = [0u8; 1024];
+    while !request.windows(4).any(|w| w == b"\r\n\r\n") && request.len() < 16 * 1024 {
+        let n = stream.read(&mut chunk).await?;
+        if n == 0 {
+            break;
+        }
+        request.extend_from_slice(&chunk[..n]);
+    }
+
+    let request = String::from_utf8_lossy(&request);
+    let path = request.split_whitespace().nth(1).unwrap_or("/");
+
+    let (status, content_type, body) = if path == "/" {
+        match render_page(git_repo, config, gh_client).await {
+            Ok(html) => ("200 OK", "text/html", html),
+            Err(e) => (
+                "500 Internal Server Error",
+                "text/plain",
+                format!("{:#}", e),
+            ),
+        }
+    } else {
+        ("404 Not Found", "text/plain", "Not found".to_string())
+    };
+
+    let response = format!(
+        "HTTP/1.1 {}\r\nContent-Type: {}; charset=utf-8\r\nContent-Length: {}\r\nCache-Control: no-store\r\nConnection: close\r\n\r\n{}",
+        status,
+        content_type,
+        body.len(),
+        body
+    );
+    stream.write_all(response.as_bytes()).await?;
+    Ok(())
+}
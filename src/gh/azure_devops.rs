@@ -0,0 +1,811 @@
+//! Azure DevOps (Azure Repos) backend, over its REST API 7.1.
+//!
+//! The repository owner is `organization/project`. Landing a PR sets it to
+//! auto-complete: Azure DevOps completes it once its branch policies pass, like
+//! GitHub's auto-merge, so `land` waits on the PR as usual. Draft PRs can't be
+//! completed until they're published. PRs are abandoned rather than closed, and
+//! comments are posted as closed threads so they never block a "comments must
+//! be resolved" policy.
+
+use crate::gh::client::Client;
+use crate::gh::forge::{self, ForgeApi, RestError};
+use crate::gh::mutations::MergeOutcome;
+use crate::gh::queries::{MergeProgress, PrInfo};
+use crate::model::{Forge, PrState};
+use crate::profile;
+use anyhow::{Context, Result};
+use async_trait::async_trait;
+use http::{Method, StatusCode};
+use serde::Deserialize;
+use serde::de::DeserializeOwned;
+use serde_json::{Value, json};
+use std::collections::HashMap;
+use tracing::instrument;
+
+/// Base URI of the API
+pub const API_URI: &str = "https://dev.azure.com";
+
+const API_VERSION: &str = "api-version=7.1";
+
+/// PRs per page when listing, and pages followed at most
+const PAGE_SIZE: usize = 100;
+const MAX_PAGES: usize = 20;
+
+/// Longest PR description Azure DevOps accepts
+const MAX_DESCRIPTION: usize = 4000;
+
+pub struct AzureDevOps {
+    /// Organization of the remote, for requests outside a project
+    pub organization: String,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(bound(deserialize = "T: Deserialize<'de>"))]
+struct List<T> {
+    #[serde(default)]
+    value: Vec<T>,
+}
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(default, rename_all = "camelCase")]
+struct PullRequest {
+    pull_request_id: u64,
+    /// active, abandoned or completed
+    status: String,
+    is_draft: bool,
+    title: String,
+    description: String,
+    source_ref_name: String,
+    target_ref_name: String,
+    last_merge_source_commit: Option<CommitRef>,
+    last_merge_commit: Option<CommitRef>,
+    /// notSet, queued, conflicts, succeeded, rejectedByPolicy or failure
+    merge_status: String,
+    reviewers: Vec<Reviewer>,
+    /// Set on PRs from a fork
+    fork_source: Option<Value>,
+    auto_complete_set_by: Option<Identity>,
+}
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(default, rename_all = "camelCase")]
+struct CommitRef {
+    commit_id: String,
+}
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(default)]
+struct Reviewer {
+    /// 10 approved, 5 approved with suggestions, -5 waiting for author, -10 rejected
+    vote: i64,
+}
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(default)]
+struct Identity {
+    id: String,
+}
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(default, rename_all = "camelCase")]
+struct ConnectionData {
+    authenticated_user: Identity,
+}
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(default, rename_all = "camelCase")]
+struct Thread {
+    id: u64,
+    comments: Vec<Comment>,
+    is_deleted: bool,
+}
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(default, rename_all = "camelCase")]
+struct Comment {
+    id: u64,
+    author: Identity,
+    content: String,
+    is_deleted: bool,
+}
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(default)]
+struct Status {
+    id: u64,
+    /// notSet, pending, succeeded, failed, error or notApplicable
+    state: String,
+    context: StatusContext,
+}
+
+#[derive(Debug, Default, Deserialize, PartialEq, Eq, Hash, Clone)]
+#[serde(default)]
+struct StatusContext {
+    name: String,
+    genre: String,
+}
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(default)]
+struct Label {
+    name: String,
+    active: bool,
+}
+
+/// `/{organization}/{project}/_apis/git/repositories/{repo}`
+fn repo_uri(owner: &str, repo: &str) -> Result<String> {
+    let (organization, project) = owner.split_once('/').with_context(|| {
+        format!(
+            "Invalid Azure DevOps owner '{}': expected organization/project",
+            owner
+        )
+    })?;
+    Ok(format!(
+        "/{}/{}/_apis/git/repositories/{}",
+        forge::encode(organization),
+        forge::encode(project),
+        forge::encode(repo)
+    ))
+}
+
+fn pr_uri(owner: &str, repo: &str, pr_number: u64, path: &str) -> Result<String> {
+    Ok(format!(
+        "{}/pullrequests/{}{}?{}",
+        repo_uri(owner, repo)?,
+        pr_number,
+        path,
+        API_VERSION
+    ))
+}
+
+async fn get<T: DeserializeOwned>(http: &Client, uri: &str, action: &str) -> Result<T> {
+    forge::send(http, Method::GET, uri, None, true)
+        .await
+        .map_err(|e| e.context(Forge::AzureDevOps, action))
+}
+
+async fn send(
+    http: &Client,
+    method: Method,
+    uri: &str,
+    body: &Value,
+    retry: bool,
+    action: &str,
+) -> Result<Value> {
+    forge::send(http, method, uri, Some(body), retry)
+        .await
+        .map_err(|e| e.context(Forge::AzureDevOps, action))
+}
+
+async fn get_pull_request(
+    http: &Client,
+    owner: &str,
+    repo: &str,
+    pr_number: u64,
+) -> Result<PullRequest> {
+    get(
+        http,
+        &pr_uri(owner, repo, pr_number, "")?,
+        &format!("Failed to get PR #{}", pr_number),
+    )
+    .await
+}
+
+/// PRs matching `criteria` (`searchCriteria.*` query parameters), newest first
+async fn search(
+    http: &Client,
+    owner: &str,
+    repo: &str,
+    criteria: &str,
+    max: usize,
+) -> Result<Vec<PullRequest>> {
+    let mut prs = Vec::new();
+    for page in 0..MAX_PAGES {
+        let uri = format!(
+            "{}/pullrequests?{}&%24top={}&%24skip={}&{}",
+            repo_uri(owner, repo)?,
+            criteria,
+            PAGE_SIZE.min(max),
+            page * PAGE_SIZE,
+            API_VERSION
+        );
+        let list: List<PullRequest> = get(http, &uri, "Failed to list pull requests").await?;
+        let count = list.value.len();
+        prs.extend(list.value);
+        if count < PAGE_SIZE || prs.len() >= max {
+            break;
+        }
+    }
+    prs.sort_by_key(|pr| std::cmp::Reverse(pr.pull_request_id));
+    Ok(prs)
+}
+
+fn branch_name(ref_name: &str) -> String {
+    ref_name
+        .strip_prefix("refs/heads/")
+        .unwrap_or(ref_name)
+        .to_string()
+}
+
+fn pr_state(pr: &PullRequest) -> PrState {
+    match pr.status.as_str() {
+        "completed" => PrState::Merged,
+        "active" if pr.is_draft => PrState::Draft,
+        "active" => PrState::Open,
+        _ => PrState::Closed,
+    }
+}
+
+fn pr_info(pr: PullRequest) -> PrInfo {
+    let state = pr_state(&pr);
+    let open = matches!(state, PrState::Open | PrState::Draft);
+    let merged = state == PrState::Merged;
+    let review_decision = if pr.reviewers.iter().any(|r| r.vote < 0) {
+        Some("CHANGES_REQUESTED".to_string())
+    } else if pr.reviewers.iter().any(|r| r.vote > 0) {
+        Some("APPROVED".to_string())
+    } else {
+        None
+    };
+
+    PrInfo {
+        number: pr.pull_request_id,
+        state,
+        title: pr.title,
+        body: pr.description,
+        base_ref: branch_name(&pr.target_ref_name),
+        head_ref: branch_name(&pr.source_ref_name),
+        head_sha: pr
+            .last_merge_source_commit
+            .map(|c| c.commit_id)
+            .unwrap_or_default(),
+        merge_commit_sha: pr.last_merge_commit.map(|c| c.commit_id).filter(|_| merged),
+        has_conflicts: open && pr.merge_status == "conflicts",
+        behind_base: false,
+        unresolved_threads: 0,
+        review_decision,
+        ci_status: None,
+    }
+}
+
+/// Progress of a PR from its state and its statuses (the latest of each context)
+fn merge_progress(pr: &PullRequest, statuses: Vec<Status>) -> MergeProgress {
+    let mut latest: HashMap<StatusContext, Status> = HashMap::new();
+    for status in statuses.into_iter().filter(|s| s.state != "notApplicable") {
+        if latest
+            .get(&status.context)
+            .is_none_or(|seen| seen.id < status.id)
+        {
+            latest.insert(status.context.clone(), status);
+        }
+    }
+
+    let state = pr_state(pr);
+    let merge_state = if pr.merge_status == "conflicts" {
+        "DIRTY"
+    } else if pr.is_draft {
+        "DRAFT"
+    } else if pr.auto_complete_set_by.is_some() || pr.merge_status == "rejectedByPolicy" {
+        // Auto-complete waits for the branch policies
+        "BLOCKED"
+    } else if pr.merge_status == "succeeded" {
+        "CLEAN"
+    } else if pr.merge_status == "failure" {
+        "FAILURE"
+    } else {
+        "UNKNOWN"
+    };
+
+    MergeProgress {
+        state,
+        merge_state: merge_state.to_string(),
+        checks_total: latest.len(),
+        checks_done: latest
+            .values()
+            .filter(|s| !matches!(s.state.as_str(), "pending" | "notSet"))
+            .count(),
+        checks_failed: latest
+            .values()
+            .filter(|s| matches!(s.state.as_str(), "failed" | "error"))
+            .count(),
+    }
+}
+
+/// A PR description within Azure DevOps' limit
+fn description(body: &str) -> String {
+    body.chars().take(MAX_DESCRIPTION).collect()
+}
+
+impl AzureDevOps {
+    async fn viewer_id(&self, http: &Client) -> Result<String> {
+        let data: ConnectionData = get(
+            http,
+            &format!(
+                "/{}/_apis/connectionData",
+                forge::encode(&self.organization)
+            ),
+            "Failed to get the authenticated Azure DevOps user",
+        )
+        .await?;
+        Ok(data.authenticated_user.id)
+    }
+}
+
+#[async_trait]
+impl ForgeApi for AzureDevOps {
+    fn forge(&self) -> Forge {
+        Forge::AzureDevOps
+    }
+
+    #[instrument(level = "debug", skip_all)]
+    async fn get_viewer(&self, http: &Client) -> Result<String> {
+        let _span = profile::span("azure: get_viewer");
+        self.viewer_id(http).await
+    }
+
+    #[instrument(level = "debug", skip(self, http))]
+    async fn find_prs_by_head(
+        &self,
+        http: &Client,
+        owner: &str,
+        repo: &str,
+        head_ref: &str,
+        open_only: bool,
+    ) -> Result<Vec<PrInfo>> {
+        let _span = profile::span("azure: find_prs_by_head");
+        let criteria = format!(
+            "searchCriteria.sourceRefName={}&searchCriteria.status={}",
+            forge::encode(&format!("refs/heads/{}", head_ref)),
+            if open_only { "active" } else { "all" }
+        );
+        let prs = search(http, owner, repo, &criteria, 10).await?;
+        Ok(prs
+            .into_iter()
+            .filter(|pr| pr.fork_source.is_none())
+            .map(pr_info)
+            .collect())
+    }
+
+    #[instrument(level = "debug", skip(self, http))]
+    async fn get_pr(
+        &self,
+        http: &Client,
+        owner: &str,
+        repo: &str,
+        pr_number: u64,
+    ) -> Result<Option<PrInfo>> {
+        let _span = profile::span("azure: get_pr");
+        match get_pull_request(http, owner, repo, pr_number).await {
+            Ok(pr) => Ok(Some(pr_info(pr))),
+            Err(e) if forge::is_not_found(&e) => Ok(None),
+            Err(e) => Err(e),
+        }
+    }
+
+    #[instrument(level = "debug", skip(self, http))]
+    async fn list_open_prs(&self, http: &Client, owner: &str, repo: &str) -> Result<Vec<PrInfo>> {
+        let _span = profile::span("azure: list_open_prs");
+        let prs = search(
+            http,
+            owner,
+            repo,
+            "searchCriteria.status=active",
+            PAGE_SIZE * MAX_PAGES,
+        )
+        .await?;
+        Ok(prs
+            .into_iter()
+            .filter(|pr| pr.fork_source.is_none())
+            .map(pr_info)
+            .collect())
+    }
+
+    #[instrument(level = "debug", skip(self, http))]
+    async fn get_merge_progress(
+        &self,
+        http: &Client,
+        owner: &str,
+        repo: &str,
+        pr_number: u64,
+    ) -> Result<MergeProgress> {
+        let _span = profile::span("azure: get_merge_progress");
+        let pr = get_pull_request(http, owner, repo, pr_number).await?;
+        let statuses: List<Status> = get(
+            http,
+            &pr_uri(owner, repo, pr_number, "/statuses")?,
+            &format!("Failed to get the statuses of PR #{}", pr_number),
+        )
+        .await?;
+        Ok(merge_progress(&pr, statuses.value))
+    }
+
+    #[instrument(level = "debug", skip(self, http, title, body))]
+    async fn create_pull_request(
+        &self,
+        http: &Client,
+        owner: &str,
+        repo: &str,
+        head: &str,
+        base: &str,
+        title: &str,
+        body: &str,
+        draft: bool,
+    ) -> Result<u64> {
+        let _span = profile::span("azure: create_pull_request");
+        let request = json!({
+            "sourceRefName": format!("refs/heads/{}", head),
+            "targetRefName": format!("refs/heads/{}", base),
+            "title": title,
+            "description": description(body),
+            "isDraft": draft,
+        });
+        let created = send(
+            http,
+            Method::POST,
+            &format!("{}/pullrequests?{}", repo_uri(owner, repo)?, API_VERSION),
+            &request,
+            false,
+            &format!(
+                "Failed to create pull request {} → {} in {}/{}",
+                head, base, owner, repo
+            ),
+        )
+        .await?;
+        created["pullRequestId"]
+            .as_u64()
+            .context("Azure DevOps didn't return the number of the PR it created")
+    }
+
+    #[instrument(level = "debug", skip(self, http, body))]
+    async fn update_pull_request(
+        &self,
+        http: &Client,
+        owner: &str,
+        repo: &str,
+        pr_number: u64,
+        base: Option<&str>,
+        title: Option<&str>,
+        body: Option<&str>,
+    ) -> Result<()> {
+        let _span = profile::span("azure: update_pull_request");
+        let uri = pr_uri(owner, repo, pr_number, "")?;
+        let action = format!("Failed to update PR #{}", pr_number);
+        // Retargeting can't be combined with other changes
+        if let Some(base) = base {
+            let request = json!({ "targetRefName": format!("refs/heads/{}", base) });
+            send(http, Method::PATCH, &uri, &request, true, &action).await?;
+        }
+        let mut request = json!({});
+        if let Some(title) = title {
+            request["title"] = json!(title);
+        }
+        if let Some(body) = body {
+            request["description"] = json!(description(body));
+        }
+        if request.as_object().is_some_and(|fields| !fields.is_empty()) {
+            send(http, Method::PATCH, &uri, &request, true, &action).await?;
+        }
+        Ok(())
+    }
+
+    #[instrument(level = "debug", skip(self, http, body))]
+    async fn add_pr_comment(
+        &self,
+        http: &Client,
+        owner: &str,
+        repo: &str,
+        pr_number: u64,
+        body: &str,
+    ) -> Result<()> {
+        let _span = profile::span("azure: add_pr_comment");
+        let request = json!({
+            "comments": [{ "parentCommentId": 0, "content": body, "commentType": "text" }],
+            "status": "closed",
+        });
+        send(
+            http,
+            Method::POST,
+            &pr_uri(owner, repo, pr_number, "/threads")?,
+            &request,
+            false,
+            "Failed to add comment to pull request",
+        )
+        .await?;
+        Ok(())
+    }
+
+    #[instrument(level = "debug", skip(self, http, marker, body))]
+    async fn upsert_pr_comment(
+        &self,
+        http: &Client,
+        owner: &str,
+        repo: &str,
+        pr_number: u64,
+        marker: &str,
+        body: &str,
+    ) -> Result<()> {
+        let _span = profile::span("azure: upsert_pr_comment");
+        let viewer = self.viewer_id(http).await?;
+        let threads: List<Thread> = get(
+            http,
+            &pr_uri(owner, repo, pr_number, "/threads")?,
+            &format!("Failed to list comments on PR #{}", pr_number),
+        )
+        .await?;
+        let existing = threads
+            .value
+            .iter()
+            .filter(|t| !t.is_deleted)
+            .find_map(|thread| {
+                let comment = thread.comments.first()?;
+                (!comment.is_deleted
+                    && comment.author.id == viewer
+                    && comment.content.contains(marker))
+                .then_some((thread.id, comment.id))
+            });
+        match existing {
+            Some((thread_id, comment_id)) => {
+                send(
+                    http,
+                    Method::PATCH,
+                    &pr_uri(
+                        owner,
+                        repo,
+                        pr_number,
+                        &format!("/threads/{}/comments/{}", thread_id, comment_id),
+                    )?,
+                    &json!({ "content": body }),
+                    true,
+                    "Failed to update pull request comment",
+                )
+                .await?;
+                Ok(())
+            }
+            None => {
+                self.add_pr_comment(http, owner, repo, pr_number, body)
+                    .await
+            }
+        }
+    }
+
+    #[instrument(level = "debug", skip(self, http, body))]
+    async fn close_pull_request(
+        &self,
+        http: &Client,
+        owner: &str,
+        repo: &str,
+        pr_number: u64,
+        body: Option<&str>,
+    ) -> Result<()> {
+        let _span = profile::span("azure: close_pull_request");
+        let mut request = json!({ "status": "abandoned" });
+        if let Some(body) = body {
+            request["description"] = json!(description(body));
+        }
+        send(
+            http,
+            Method::PATCH,
+            &pr_uri(owner, repo, pr_number, "")?,
+            &request,
+            true,
+            "Failed to abandon pull request",
+        )
+        .await?;
+        Ok(())
+    }
+
+    #[instrument(level = "debug", skip(self, http, commit_message))]
+    async fn merge_pull_request(
+        &self,
+        http: &Client,
+        owner: &str,
+        repo: &str,
+        pr_number: u64,
+        commit_title: Option<&str>,
+        commit_message: Option<&str>,
+    ) -> Result<MergeOutcome> {
+        let _span = profile::span("azure: merge_pull_request");
+        let viewer = self.viewer_id(http).await?;
+        // The merge strategy is left to the branch policy
+        let mut completion = json!({ "deleteSourceBranch": false });
+        let message: Vec<&str> = [commit_title, commit_message]
+            .into_iter()
+            .flatten()
+            .collect();
+        if !message.is_empty() {
+            completion["mergeCommitMessage"] = json!(message.join("\n\n"));
+        }
+        let request =
+            json!({ "autoCompleteSetBy": { "id": viewer }, "completionOptions": completion });
+        let result = forge::send::<Value>(
+            http,
+            Method::PATCH,
+            &pr_uri(owner, repo, pr_number, "")?,
+            Some(&request),
+            true,
+        )
+        .await;
+        match result {
+            Ok(_) => Ok(MergeOutcome::Initiated),
+            // E.g. a draft, which can't be set to auto-complete
+            Err(RestError::Status {
+                status: StatusCode::BAD_REQUEST,
+                message,
+            }) => Ok(MergeOutcome::Blocked(message)),
+            Err(e) => Err(e.context(
+                Forge::AzureDevOps,
+                "Failed to set the pull request to auto-complete",
+            )),
+        }
+    }
+
+    #[instrument(level = "debug", skip(self, http))]
+    async fn convert_to_draft(
+        &self,
+        http: &Client,
+        owner: &str,
+        repo: &str,
+        pr_number: u64,
+    ) -> Result<()> {
+        let _span = profile::span("azure: convert_to_draft");
+        send(
+            http,
+            Method::PATCH,
+            &pr_uri(owner, repo, pr_number, "")?,
+            &json!({ "isDraft": true }),
+            true,
+            &format!("Failed to convert PR #{} to draft", pr_number),
+        )
+        .await?;
+        Ok(())
+    }
+
+    #[instrument(level = "debug", skip(self, http))]
+    async fn get_labels(
+        &self,
+        http: &Client,
+        owner: &str,
+        repo: &str,
+        pr_number: u64,
+    ) -> Result<Vec<String>> {
+        let _span = profile::span("azure: get_labels");
+        let labels: List<Label> = get(
+            http,
+            &pr_uri(owner, repo, pr_number, "/labels")?,
+            &format!("Failed to get the labels of PR #{}", pr_number),
+        )
+        .await?;
+        Ok(labels
+            .value
+            .into_iter()
+            .filter(|l| l.active)
+            .map(|l| l.name)
+            .collect())
+    }
+
+    #[instrument(level = "debug", skip(self, http))]
+    async fn add_labels(
+        &self,
+        http: &Client,
+        owner: &str,
+        repo: &str,
+        pr_number: u64,
+        labels: &[String],
+    ) -> Result<()> {
+        let _span = profile::span("azure: add_labels");
+        let uri = pr_uri(owner, repo, pr_number, "/labels")?;
+        for label in labels {
+            // Adding a label the PR has is a no-op
+            send(
+                http,
+                Method::POST,
+                &uri,
+                &json!({ "name": label }),
+                true,
+                &format!("Failed to label PR #{}", pr_number),
+            )
+            .await?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn decode<T: DeserializeOwned>(value: Value) -> T {
+        serde_json::from_value(value).unwrap()
+    }
+
+    #[test]
+    fn test_pr_info() {
+        let pr = pr_info(decode(json!({
+            "pullRequestId": 42,
+            "status": "active",
+            "isDraft": false,
+            "title": "Add parser",
+            "description": "Body",
+            "sourceRefName": "refs/heads/feat--01",
+            "targetRefName": "refs/heads/main",
+            "lastMergeSourceCommit": { "commitId": "0123456789abcdef0123456789abcdef01234567" },
+            "mergeStatus": "conflicts",
+            "reviewers": [{ "vote": 10 }, { "vote": 0 }],
+        })));
+        assert_eq!(pr.number, 42);
+        assert_eq!(pr.state, PrState::Open);
+        assert_eq!(
+            (pr.head_ref.as_str(), pr.base_ref.as_str()),
+            ("feat--01", "main")
+        );
+        assert_eq!(pr.head_sha, "0123456789abcdef0123456789abcdef01234567");
+        assert!(pr.has_conflicts);
+        assert_eq!(pr.review_decision.as_deref(), Some("APPROVED"));
+        assert_eq!(pr.merge_commit_sha, None);
+
+        let merged = pr_info(decode(json!({
+            "status": "completed",
+            "lastMergeCommit": { "commitId": "abc" },
+            "mergeStatus": "conflicts",
+            "reviewers": [{ "vote": 10 }, { "vote": -5 }],
+        })));
+        assert_eq!(merged.state, PrState::Merged);
+        assert_eq!(merged.merge_commit_sha.as_deref(), Some("abc"));
+        assert!(!merged.has_conflicts);
+        assert_eq!(merged.review_decision.as_deref(), Some("CHANGES_REQUESTED"));
+
+        assert_eq!(
+            pr_info(decode(json!({ "status": "active", "isDraft": true }))).state,
+            PrState::Draft
+        );
+        assert_eq!(
+            pr_info(decode(json!({ "status": "abandoned" }))).state,
+            PrState::Closed
+        );
+    }
+
+    #[test]
+    fn test_merge_progress() {
+        let statuses: List<Status> = decode(json!({ "value": [
+            { "id": 1, "state": "failed", "context": { "name": "build", "genre": "ci" } },
+            { "id": 2, "state": "pending", "context": { "name": "build", "genre": "ci" } },
+            { "id": 3, "state": "succeeded", "context": { "name": "lint", "genre": "ci" } },
+            { "id": 4, "state": "notApplicable", "context": { "name": "deploy", "genre": "cd" } },
+        ]}));
+        let pr = decode(
+            json!({ "status": "active", "mergeStatus": "succeeded", "autoCompleteSetBy": { "id": "me" } }),
+        );
+        assert_eq!(
+            merge_progress(&pr, statuses.value),
+            MergeProgress {
+                state: PrState::Open,
+                merge_state: "BLOCKED".to_string(),
+                checks_total: 2,
+                checks_done: 1,
+                checks_failed: 0,
+            }
+        );
+
+        let conflicting =
+            decode(json!({ "status": "active", "mergeStatus": "conflicts", "isDraft": true }));
+        assert_eq!(
+            merge_progress(&conflicting, Vec::new()).merge_state,
+            "DIRTY"
+        );
+        let ready = decode(json!({ "status": "active", "mergeStatus": "succeeded" }));
+        assert_eq!(merge_progress(&ready, Vec::new()).merge_state, "CLEAN");
+    }
+
+    #[test]
+    fn test_repo_uri() {
+        assert_eq!(
+            repo_uri("contoso/Web Apps", "api").unwrap(),
+            "/contoso/Web%20Apps/_apis/git/repositories/api"
+        );
+        assert!(repo_uri("contoso", "api").is_err());
+        assert_eq!(
+            pr_uri("contoso/web", "api", 7, "/threads").unwrap(),
+            "/contoso/web/_apis/git/repositories/api/pullrequests/7/threads?api-version=7.1"
+        );
+    }
+}
@@ -0,0 +1,659 @@
+//! Bitbucket Cloud backend, over its REST API 2.0.
+//!
+//! PRs are declined rather than closed, and have no labels or assignees. The
+//! PR payload only carries abbreviated commit hashes (`PrInfo::head_sha` and
+//! `merge_commit_sha` are 12 characters long), so callers resolve them in the
+//! local repository.
+
+use crate::gh::client::Client;
+use crate::gh::forge::{self, ForgeApi, RestError};
+use crate::gh::mutations::MergeOutcome;
+use crate::gh::queries::{MergeProgress, PrInfo};
+use crate::model::{Forge, PrState};
+use crate::profile;
+use anyhow::Result;
+use async_trait::async_trait;
+use http::{Method, StatusCode};
+use serde::Deserialize;
+use serde::de::DeserializeOwned;
+use serde_json::{Value, json};
+use tracing::instrument;
+
+/// Base URI of the API
+pub const API_URI: &str = "https://api.bitbucket.org/2.0";
+
+/// Pages followed when listing, at most 50 items each
+const MAX_PAGES: usize = 20;
+
+pub struct Bitbucket;
+
+#[derive(Debug, Deserialize)]
+#[serde(bound(deserialize = "T: Deserialize<'de>"))]
+struct Page<T> {
+    #[serde(default)]
+    values: Vec<T>,
+    #[serde(default)]
+    next: Option<String>,
+}
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(default)]
+struct PullRequest {
+    id: u64,
+    title: String,
+    description: String,
+    /// OPEN, MERGED, DECLINED or SUPERSEDED
+    state: String,
+    draft: bool,
+    source: Endpoint,
+    destination: Endpoint,
+    merge_commit: Option<CommitRef>,
+    participants: Vec<Participant>,
+}
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(default)]
+struct Endpoint {
+    branch: BranchRef,
+    commit: Option<CommitRef>,
+    repository: Option<RepositoryRef>,
+}
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(default)]
+struct BranchRef {
+    name: String,
+}
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(default)]
+struct CommitRef {
+    hash: String,
+}
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(default)]
+struct RepositoryRef {
+    full_name: String,
+}
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(default)]
+struct Participant {
+    approved: bool,
+    /// approved, changes_requested or null
+    state: Option<String>,
+}
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(default)]
+struct User {
+    account_id: String,
+    nickname: String,
+}
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(default)]
+struct Comment {
+    id: u64,
+    content: Content,
+    user: Option<User>,
+    deleted: bool,
+}
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(default)]
+struct Content {
+    raw: String,
+}
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(default)]
+struct Status {
+    /// SUCCESSFUL, FAILED, INPROGRESS or STOPPED
+    state: String,
+}
+
+fn pulls_uri(owner: &str, repo: &str) -> String {
+    format!(
+        "/repositories/{}/{}/pullrequests",
+        forge::encode(owner),
+        forge::encode(repo)
+    )
+}
+
+async fn get<T: DeserializeOwned>(http: &Client, uri: &str, action: &str) -> Result<T> {
+    forge::send(http, Method::GET, uri, None, true)
+        .await
+        .map_err(|e| e.context(Forge::Bitbucket, action))
+}
+
+async fn send(
+    http: &Client,
+    method: Method,
+    uri: &str,
+    body: &Value,
+    retry: bool,
+    action: &str,
+) -> Result<Value> {
+    forge::send(http, method, uri, Some(body), retry)
+        .await
+        .map_err(|e| e.context(Forge::Bitbucket, action))
+}
+
+/// Every item of a paginated listing
+async fn get_all<T: DeserializeOwned>(http: &Client, uri: &str, action: &str) -> Result<Vec<T>> {
+    let mut items = Vec::new();
+    let mut next = Some(uri.to_string());
+    for _ in 0..MAX_PAGES {
+        let Some(uri) = next else { break };
+        let page: Page<T> = get(http, &uri, action).await?;
+        items.extend(page.values);
+        next = page.next;
+    }
+    Ok(items)
+}
+
+async fn get_pull_request(
+    http: &Client,
+    owner: &str,
+    repo: &str,
+    pr_number: u64,
+) -> Result<PullRequest> {
+    get(
+        http,
+        &format!("{}/{}", pulls_uri(owner, repo), pr_number),
+        &format!("Failed to get PR #{}", pr_number),
+    )
+    .await
+}
+
+fn pr_state(pr: &PullRequest) -> PrState {
+    match pr.state.as_str() {
+        "MERGED" => PrState::Merged,
+        "OPEN" if pr.draft => PrState::Draft,
+        "OPEN" => PrState::Open,
+        _ => PrState::Closed,
+    }
+}
+
+fn pr_info(pr: PullRequest) -> PrInfo {
+    let state = pr_state(&pr);
+    let merged = state == PrState::Merged;
+    let review_decision = if pr
+        .participants
+        .iter()
+        .any(|p| p.state.as_deref() == Some("changes_requested"))
+    {
+        Some("CHANGES_REQUESTED".to_string())
+    } else if pr.participants.iter().any(|p| p.approved) {
+        Some("APPROVED".to_string())
+    } else {
+        None
+    };
+
+    PrInfo {
+        number: pr.id,
+        state,
+        title: pr.title,
+        body: pr.description,
+        base_ref: pr.destination.branch.name,
+        head_ref: pr.source.branch.name,
+        head_sha: pr.source.commit.map(|c| c.hash).unwrap_or_default(),
+        merge_commit_sha: pr.merge_commit.map(|c| c.hash).filter(|_| merged),
+        has_conflicts: false,
+        behind_base: false,
+        unresolved_threads: 0,
+        review_decision,
+        ci_status: None,
+    }
+}
+
+/// Progress of a PR from its state and the build statuses of its head commit
+fn merge_progress(pr: &PullRequest, statuses: &[Status]) -> MergeProgress {
+    let state = pr_state(pr);
+    // Bitbucket accepted the merge already, or doesn't report why it can't
+    let merge_state = if state == PrState::Draft {
+        "DRAFT"
+    } else {
+        "CLEAN"
+    };
+    MergeProgress {
+        state,
+        merge_state: merge_state.to_string(),
+        checks_total: statuses.len(),
+        checks_done: statuses.iter().filter(|s| s.state != "INPROGRESS").count(),
+        checks_failed: statuses
+            .iter()
+            .filter(|s| matches!(s.state.as_str(), "FAILED" | "STOPPED"))
+            .count(),
+    }
+}
+
+#[async_trait]
+impl ForgeApi for Bitbucket {
+    fn forge(&self) -> Forge {
+        Forge::Bitbucket
+    }
+
+    #[instrument(level = "debug", skip_all)]
+    async fn get_viewer(&self, http: &Client) -> Result<String> {
+        let _span = profile::span("bitbucket: get_viewer");
+        let user: User = get(
+            http,
+            "/user",
+            "Failed to get the authenticated Bitbucket user",
+        )
+        .await?;
+        Ok(user.nickname)
+    }
+
+    #[instrument(level = "debug", skip(self, http))]
+    async fn find_prs_by_head(
+        &self,
+        http: &Client,
+        owner: &str,
+        repo: &str,
+        head_ref: &str,
+        open_only: bool,
+    ) -> Result<Vec<PrInfo>> {
+        let _span = profile::span("bitbucket: find_prs_by_head");
+        let states = if open_only {
+            "state=OPEN"
+        } else {
+            "state=OPEN&state=MERGED&state=DECLINED&state=SUPERSEDED"
+        };
+        let query = format!("source.branch.name = \"{}\"", head_ref.replace('"', "\\\""));
+        let uri = format!(
+            "{}?q={}&{}&sort=-created_on&pagelen=10",
+            pulls_uri(owner, repo),
+            forge::encode(&query),
+            states
+        );
+        let page: Page<PullRequest> =
+            get(http, &uri, "Failed to look up PRs by source branch").await?;
+        let full_name = format!("{}/{}", owner, repo);
+        Ok(page
+            .values
+            .into_iter()
+            .filter(|pr| {
+                pr.source
+                    .repository
+                    .as_ref()
+                    .is_none_or(|r| r.full_name.eq_ignore_ascii_case(&full_name))
+            })
+            .map(pr_info)
+            .collect())
+    }
+
+    #[instrument(level = "debug", skip(self, http))]
+    async fn get_pr(
+        &self,
+        http: &Client,
+        owner: &str,
+        repo: &str,
+        pr_number: u64,
+    ) -> Result<Option<PrInfo>> {
+        let _span = profile::span("bitbucket: get_pr");
+        match get_pull_request(http, owner, repo, pr_number).await {
+            Ok(pr) => Ok(Some(pr_info(pr))),
+            Err(e) if forge::is_not_found(&e) => Ok(None),
+            Err(e) => Err(e),
+        }
+    }
+
+    #[instrument(level = "debug", skip(self, http))]
+    async fn list_open_prs(&self, http: &Client, owner: &str, repo: &str) -> Result<Vec<PrInfo>> {
+        let _span = profile::span("bitbucket: list_open_prs");
+        let uri = format!(
+            "{}?state=OPEN&pagelen=50&fields=%2Bvalues.description",
+            pulls_uri(owner, repo)
+        );
+        let prs: Vec<PullRequest> =
+            get_all(http, &uri, "Failed to list open pull requests").await?;
+        Ok(prs.into_iter().map(pr_info).collect())
+    }
+
+    #[instrument(level = "debug", skip(self, http))]
+    async fn get_merge_progress(
+        &self,
+        http: &Client,
+        owner: &str,
+        repo: &str,
+        pr_number: u64,
+    ) -> Result<MergeProgress> {
+        let _span = profile::span("bitbucket: get_merge_progress");
+        let pr = get_pull_request(http, owner, repo, pr_number).await?;
+        let statuses: Vec<Status> = get_all(
+            http,
+            &format!(
+                "{}/{}/statuses?pagelen=50",
+                pulls_uri(owner, repo),
+                pr_number
+            ),
+            &format!("Failed to get the build statuses of PR #{}", pr_number),
+        )
+        .await?;
+        Ok(merge_progress(&pr, &statuses))
+    }
+
+    #[instrument(level = "debug", skip(self, http, title, body))]
+    async fn create_pull_request(
+        &self,
+        http: &Client,
+        owner: &str,
+        repo: &str,
+        head: &str,
+        base: &str,
+        title: &str,
+        body: &str,
+        draft: bool,
+    ) -> Result<u64> {
+        let _span = profile::span("bitbucket: create_pull_request");
+        let request = json!({
+            "title": title,
+            "description": body,
+            "source": { "branch": { "name": head } },
+            "destination": { "branch": { "name": base } },
+            "draft": draft,
+        });
+        let created = send(
+            http,
+            Method::POST,
+            &pulls_uri(owner, repo),
+            &request,
+            false,
+            &format!(
+                "Failed to create pull request {} → {} in {}/{}",
+                head, base, owner, repo
+            ),
+        )
+        .await?;
+        created["id"].as_u64().ok_or_else(|| {
+            anyhow::anyhow!("Bitbucket didn't return the number of the PR it created")
+        })
+    }
+
+    #[instrument(level = "debug", skip(self, http, body))]
+    async fn update_pull_request(
+        &self,
+        http: &Client,
+        owner: &str,
+        repo: &str,
+        pr_number: u64,
+        base: Option<&str>,
+        title: Option<&str>,
+        body: Option<&str>,
+    ) -> Result<()> {
+        let _span = profile::span("bitbucket: update_pull_request");
+        // Updates must carry the title
+        let title = match title {
+            Some(title) => title.to_string(),
+            None => get_pull_request(http, owner, repo, pr_number).await?.title,
+        };
+        let mut request = json!({ "title": title });
+        if let Some(body) = body {
+            request["description"] = json!(body);
+        }
+        if let Some(base) = base {
+            request["destination"] = json!({ "branch": { "name": base } });
+        }
+        send(
+            http,
+            Method::PUT,
+            &format!("{}/{}", pulls_uri(owner, repo), pr_number),
+            &request,
+            true,
+            &format!("Failed to update PR #{}", pr_number),
+        )
+        .await?;
+        Ok(())
+    }
+
+    #[instrument(level = "debug", skip(self, http, body))]
+    async fn add_pr_comment(
+        &self,
+        http: &Client,
+        owner: &str,
+        repo: &str,
+        pr_number: u64,
+        body: &str,
+    ) -> Result<()> {
+        let _span = profile::span("bitbucket: add_pr_comment");
+        send(
+            http,
+            Method::POST,
+            &format!("{}/{}/comments", pulls_uri(owner, repo), pr_number),
+            &json!({ "content": { "raw": body } }),
+            false,
+            "Failed to add comment to pull request",
+        )
+        .await?;
+        Ok(())
+    }
+
+    #[instrument(level = "debug", skip(self, http, marker, body))]
+    async fn upsert_pr_comment(
+        &self,
+        http: &Client,
+        owner: &str,
+        repo: &str,
+        pr_number: u64,
+        marker: &str,
+        body: &str,
+    ) -> Result<()> {
+        let _span = profile::span("bitbucket: upsert_pr_comment");
+        let viewer: User = get(
+            http,
+            "/user",
+            "Failed to get the authenticated Bitbucket user",
+        )
+        .await?;
+        let comments_uri = format!("{}/{}/comments", pulls_uri(owner, repo), pr_number);
+        let comments: Vec<Comment> = get_all(
+            http,
+            &format!("{}?pagelen=100", comments_uri),
+            &format!("Failed to list comments on PR #{}", pr_number),
+        )
+        .await?;
+        let existing = comments.iter().find(|c| {
+            !c.deleted
+                && c.user
+                    .as_ref()
+                    .is_some_and(|u| u.account_id == viewer.account_id)
+                && c.content.raw.contains(marker)
+        });
+        match existing {
+            Some(comment) => {
+                send(
+                    http,
+                    Method::PUT,
+                    &format!("{}/{}", comments_uri, comment.id),
+                    &json!({ "content": { "raw": body } }),
+                    true,
+                    "Failed to update pull request comment",
+                )
+                .await?;
+                Ok(())
+            }
+            None => {
+                self.add_pr_comment(http, owner, repo, pr_number, body)
+                    .await
+            }
+        }
+    }
+
+    #[instrument(level = "debug", skip(self, http, body))]
+    async fn close_pull_request(
+        &self,
+        http: &Client,
+        owner: &str,
+        repo: &str,
+        pr_number: u64,
+        body: Option<&str>,
+    ) -> Result<()> {
+        let _span = profile::span("bitbucket: close_pull_request");
+        if body.is_some() {
+            self.update_pull_request(http, owner, repo, pr_number, None, None, body)
+                .await?;
+        }
+        send(
+            http,
+            Method::POST,
+            &format!("{}/{}/decline", pulls_uri(owner, repo), pr_number),
+            &json!({}),
+            true,
+            "Failed to decline pull request",
+        )
+        .await?;
+        Ok(())
+    }
+
+    #[instrument(level = "debug", skip(self, http, commit_message))]
+    async fn merge_pull_request(
+        &self,
+        http: &Client,
+        owner: &str,
+        repo: &str,
+        pr_number: u64,
+        commit_title: Option<&str>,
+        commit_message: Option<&str>,
+    ) -> Result<MergeOutcome> {
+        let _span = profile::span("bitbucket: merge_pull_request");
+        // The merge strategy is the repository's default
+        let mut request = json!({ "type": "pullrequest", "close_source_branch": false });
+        let message: Vec<&str> = [commit_title, commit_message]
+            .into_iter()
+            .flatten()
+            .collect();
+        if !message.is_empty() {
+            request["message"] = json!(message.join("\n\n"));
+        }
+        // Long merges complete in the background; land waits for the PR's state
+        let result = forge::send::<Value>(
+            http,
+            Method::POST,
+            &format!("{}/{}/merge?async=true", pulls_uri(owner, repo), pr_number),
+            Some(&request),
+            false,
+        )
+        .await;
+        match result {
+            Ok(_) => Ok(MergeOutcome::Initiated),
+            // Merge checks (approvals, builds, tasks) aren't met
+            Err(RestError::Status {
+                status: StatusCode::BAD_REQUEST,
+                message,
+            }) => Ok(MergeOutcome::Blocked(message)),
+            Err(e) => Err(e.context(Forge::Bitbucket, "Failed to merge pull request")),
+        }
+    }
+
+    #[instrument(level = "debug", skip(self, http))]
+    async fn convert_to_draft(
+        &self,
+        http: &Client,
+        owner: &str,
+        repo: &str,
+        pr_number: u64,
+    ) -> Result<()> {
+        let _span = profile::span("bitbucket: convert_to_draft");
+        let pr = get_pull_request(http, owner, repo, pr_number).await?;
+        send(
+            http,
+            Method::PUT,
+            &format!("{}/{}", pulls_uri(owner, repo), pr_number),
+            &json!({ "title": pr.title, "draft": true }),
+            true,
+            &format!("Failed to convert PR #{} to draft", pr_number),
+        )
+        .await?;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn decode<T: DeserializeOwned>(value: Value) -> T {
+        serde_json::from_value(value).unwrap()
+    }
+
+    #[test]
+    fn test_pr_info() {
+        let pr = pr_info(decode(json!({
+            "id": 12,
+            "title": "Add parser",
+            "description": "Body",
+            "state": "OPEN",
+            "draft": false,
+            "source": {
+                "branch": { "name": "feat--01" },
+                "commit": { "hash": "0123456789ab" },
+                "repository": { "full_name": "acme/api" },
+            },
+            "destination": { "branch": { "name": "main" } },
+            "merge_commit": null,
+            "participants": [
+                { "role": "REVIEWER", "approved": true, "state": "approved" },
+                { "role": "REVIEWER", "approved": false, "state": null },
+            ],
+        })));
+        assert_eq!(pr.number, 12);
+        assert_eq!(pr.state, PrState::Open);
+        assert_eq!(
+            (pr.head_ref.as_str(), pr.base_ref.as_str()),
+            ("feat--01", "main")
+        );
+        assert_eq!(pr.head_sha, "0123456789ab");
+        assert_eq!(pr.review_decision.as_deref(), Some("APPROVED"));
+
+        let merged = pr_info(decode(json!({
+            "id": 9,
+            "state": "MERGED",
+            "merge_commit": { "hash": "fedcba987654" },
+            "participants": [{ "approved": true, "state": "changes_requested" }],
+        })));
+        assert_eq!(merged.state, PrState::Merged);
+        assert_eq!(merged.merge_commit_sha.as_deref(), Some("fedcba987654"));
+        assert_eq!(merged.review_decision.as_deref(), Some("CHANGES_REQUESTED"));
+
+        assert_eq!(
+            pr_info(decode(json!({ "state": "OPEN", "draft": true }))).state,
+            PrState::Draft
+        );
+        assert_eq!(
+            pr_info(decode(json!({ "state": "DECLINED" }))).state,
+            PrState::Closed
+        );
+        assert_eq!(
+            pr_info(decode(json!({ "state": "SUPERSEDED" }))).state,
+            PrState::Closed
+        );
+    }
+
+    #[test]
+    fn test_merge_progress() {
+        let statuses: Vec<Status> = decode(json!([
+            { "state": "SUCCESSFUL" },
+            { "state": "FAILED" },
+            { "state": "INPROGRESS" },
+        ]));
+        let progress = merge_progress(&decode(json!({ "state": "OPEN" })), &statuses);
+        assert_eq!(
+            progress,
+            MergeProgress {
+                state: PrState::Open,
+                merge_state: "CLEAN".to_string(),
+                checks_total: 3,
+                checks_done: 2,
+                checks_failed: 1,
+            }
+        );
+        assert_eq!(
+            merge_progress(&decode(json!({ "state": "MERGED" })), &[]).state,
+            PrState::Merged
+        );
+    }
+}
@@ -1,5 +1,18 @@
-use anyhow::{anyhow, Context, Result};
+use anyhow::{Context, Result, anyhow};
+use base64::Engine;
 use octocrab::Octocrab;
+use std::collections::HashMap;
+use std::ops::Deref;
+use std::sync::Mutex;
+use std::time::Duration;
+
+use crate::gh::azure_devops::{self, AzureDevOps};
+use crate::gh::bitbucket::{self, Bitbucket};
+use crate::gh::forge::ForgeApi;
+use crate::gh::proxy::{self, GITHUB_API_URI};
+use crate::gh::queries::PrInfo;
+use crate::gh::retry::RetryPolicy;
+use crate::model::{Config, Forge, Transport};
 use std::process::Command;
 
 /// Get GitHub token from gh CLI
@@ -31,14 +44,227 @@ fn get_gh_token() -> Result<String> {
     Ok(token)
 }
 
-/// Create an authenticated Octocrab instance
-pub fn create_client() -> Result<Octocrab> {
-    let token = get_gh_token()?;
+/// Client for the remote's forge: GitHub through octocrab, or another forge
+/// through its [`ForgeApi`] on the same HTTP stack. Derefs to the octocrab
+/// instance for GitHub-only code.
+pub struct Client {
+    http: Octocrab,
+    api: Option<Box<dyn ForgeApi>>,
+    /// PRs found by `queries::find_prs_by_heads` with this client, keyed by
+    /// `owner/repo:head_ref` (`None`: no PR on that branch)
+    head_prs: Mutex<HashMap<String, Option<PrInfo>>>,
+    retry: RetryPolicy,
+}
+
+fn head_key(owner: &str, repo: &str, head_ref: &str) -> String {
+    format!("{}/{}:{}", owner, repo, head_ref)
+}
+
+impl Client {
+    fn new(http: Octocrab, api: Option<Box<dyn ForgeApi>>) -> Self {
+        Self {
+            http,
+            api,
+            head_prs: Mutex::default(),
+            retry: RetryPolicy::default(),
+        }
+    }
+
+    fn with_retry_policy(self, retry: RetryPolicy) -> Self {
+        Self { retry, ..self }
+    }
+
+    /// How this client retries failed requests
+    pub fn retry_policy(&self) -> RetryPolicy {
+        self.retry
+    }
+
+    /// The REST backend of a forge other than GitHub; `None` on GitHub
+    pub fn api(&self) -> Option<&dyn ForgeApi> {
+        self.api.as_deref()
+    }
+
+    pub fn forge(&self) -> Forge {
+        self.api().map_or(Forge::GitHub, ForgeApi::forge)
+    }
+
+    /// The cached PR of a head branch: `None` when it wasn't looked up yet,
+    /// `Some(None)` when it has no PR
+    pub(crate) fn cached_head_pr(
+        &self,
+        owner: &str,
+        repo: &str,
+        head_ref: &str,
+    ) -> Option<Option<PrInfo>> {
+        self.head_prs
+            .lock()
+            .unwrap()
+            .get(&head_key(owner, repo, head_ref))
+            .cloned()
+    }
+
+    pub(crate) fn cache_head_pr(
+        &self,
+        owner: &str,
+        repo: &str,
+        head_ref: &str,
+        pr: Option<PrInfo>,
+    ) {
+        self.head_prs
+            .lock()
+            .unwrap()
+            .insert(head_key(owner, repo, head_ref), pr);
+    }
+
+    /// Forget the cached PR of a head branch, e.g. once a PR was created for it
+    pub fn forget_head(&self, owner: &str, repo: &str, head_ref: &str) {
+        self.head_prs
+            .lock()
+            .unwrap()
+            .remove(&head_key(owner, repo, head_ref));
+    }
+}
+
+impl From<Octocrab> for Client {
+    fn from(http: Octocrab) -> Self {
+        Self::new(http, None)
+    }
+}
+
+impl Deref for Client {
+    type Target = Octocrab;
 
-    Octocrab::builder()
-        .personal_token(token)
-        .build()
-        .context("Failed to create GitHub client")
+    fn deref(&self) -> &Octocrab {
+        &self.http
+    }
+}
+
+/// Create an authenticated client for the remote's forge using the configured
+/// HTTP timeout, retry policy (`git-stk.httpTimeoutSeconds`, `git-stk.maxRetries`,
+/// ...), proxy and CA bundle
+pub fn create_configured_client(config: &Config) -> Result<Client> {
+    Ok(create_forge_client(config)?.with_retry_policy(RetryPolicy::from_config(config)))
+}
+
+fn create_forge_client(config: &Config) -> Result<Client> {
+    let timeout =
+        (config.http_timeout_seconds > 0).then(|| Duration::from_secs(config.http_timeout_seconds));
+
+    match config.forge {
+        Forge::GitHub => {
+            let token = get_gh_token()?;
+            if config.transport != Transport::default() {
+                let http = proxy::build_client(
+                    config,
+                    GITHUB_API_URI,
+                    &format!("Bearer {}", token),
+                    timeout,
+                )?;
+                return Ok(http.into());
+            }
+
+            let http = Octocrab::builder()
+                .personal_token(token)
+                .set_connect_timeout(timeout)
+                .set_read_timeout(timeout)
+                .set_write_timeout(timeout)
+                .build()
+                .context("Failed to create GitHub client")?;
+            Ok(http.into())
+        }
+        Forge::Bitbucket => {
+            let http = proxy::build_client(
+                config,
+                bitbucket::API_URI,
+                &bitbucket_authorization()?,
+                timeout,
+            )?;
+            Ok(Client::new(http, Some(Box::new(Bitbucket))))
+        }
+        Forge::AzureDevOps => {
+            let url = config
+                .remote_url
+                .as_deref()
+                .with_context(|| format!("Remote '{}' not found", config.remote))?;
+            let (owner, _) = parse_repo_from_url(url)?;
+            let organization = owner.split('/').next().unwrap_or_default().to_string();
+            let http = proxy::build_client(
+                config,
+                azure_devops::API_URI,
+                &azure_devops_authorization()?,
+                timeout,
+            )?;
+            Ok(Client::new(
+                http,
+                Some(Box::new(AzureDevOps { organization })),
+            ))
+        }
+    }
+}
+
+fn basic_authorization(username: &str, password: &str) -> String {
+    format!(
+        "Basic {}",
+        base64::engine::general_purpose::STANDARD.encode(format!("{}:{}", username, password))
+    )
+}
+
+fn non_empty_var(key: &str) -> Option<String> {
+    std::env::var(key).ok().filter(|value| !value.is_empty())
+}
+
+/// `Authorization` header for Bitbucket: an access token (`BITBUCKET_TOKEN`),
+/// else a username and app password
+fn bitbucket_authorization() -> Result<String> {
+    if let Some(token) = non_empty_var("BITBUCKET_TOKEN") {
+        return Ok(format!("Bearer {}", token));
+    }
+    match (
+        non_empty_var("BITBUCKET_USERNAME"),
+        non_empty_var("BITBUCKET_APP_PASSWORD"),
+    ) {
+        (Some(username), Some(password)) => Ok(basic_authorization(&username, &password)),
+        _ => Err(anyhow!(
+            "No Bitbucket credentials. Set BITBUCKET_TOKEN to an access token with pull request scope, \
+             or BITBUCKET_USERNAME and BITBUCKET_APP_PASSWORD."
+        )),
+    }
+}
+
+/// Azure DevOps' resource id, to get an access token for it from the Azure CLI
+const AZURE_DEVOPS_RESOURCE: &str = "499b84ac-1321-427f-aa17-267ca6975798";
+
+/// `Authorization` header for Azure DevOps: a personal access token
+/// (`AZURE_DEVOPS_EXT_PAT`, as the Azure CLI's devops extension reads it), else
+/// a token from the Azure CLI's login
+fn azure_devops_authorization() -> Result<String> {
+    if let Some(pat) = non_empty_var("AZURE_DEVOPS_EXT_PAT") {
+        return Ok(basic_authorization("", &pat));
+    }
+
+    let output = Command::new("az")
+        .args(["account", "get-access-token", "--resource", AZURE_DEVOPS_RESOURCE])
+        .args(["--query", "accessToken", "--output", "tsv"])
+        .output()
+        .context("No Azure DevOps credentials. Set AZURE_DEVOPS_EXT_PAT, or install the Azure CLI and run 'az login'.")?;
+    if !output.status.success() {
+        let stderr = String::from_utf8_lossy(&output.stderr);
+        return Err(anyhow!(
+            "az account get-access-token failed: {}. Run 'az login' or set AZURE_DEVOPS_EXT_PAT",
+            stderr.trim()
+        ));
+    }
+
+    let token = String::from_utf8(output.stdout)
+        .context("Failed to parse az token output")?
+        .trim()
+        .to_string();
+    if token.is_empty() {
+        return Err(anyhow!(
+            "az returned an empty token. Run 'az login' or set AZURE_DEVOPS_EXT_PAT"
+        ));
+    }
+    Ok(format!("Bearer {}", token))
 }
 
 /// Parse owner and repo from a remote URL
@@ -47,13 +273,17 @@ pub fn parse_repo_from_url(url: &str) -> Result<(String, String)> {
     // HTTPS: https://github.com/owner/repo.git
     // SSH: git@github.com:owner/repo.git
     // file:// URLs are used in tests and return default test values
+    // Azure DevOps URLs have an organization and project: see parse_azure_devops_url
 
     let url = url.trim();
 
     let (owner, repo) = if url.starts_with("file://") {
         // Test URL - return default test owner/repo
         ("test-owner".to_string(), "test-repo".to_string())
-    } else if url.starts_with("https://") || url.starts_with("http://") {
+    } else if Forge::detect(url) == Forge::AzureDevOps {
+        parse_azure_devops_url(url).with_context(|| format!("Invalid Azure DevOps URL: {}", url))?
+    } else if url.starts_with("https://") || url.starts_with("http://") || url.starts_with("ssh://")
+    {
         // HTTPS URL
         let parts: Vec<&str> = url.split('/').collect();
         if parts.len() < 5 {
@@ -65,7 +295,8 @@ pub fn parse_repo_from_url(url: &str) -> Result<(String, String)> {
     } else if url.starts_with("git@") {
         // SSH URL: git@<host>:owner/repo.git
         // Extract everything after the colon to support custom GitHub hosts
-        let colon_pos = url.find(':')
+        let colon_pos = url
+            .find(':')
             .context("Invalid SSH URL format: missing ':'")?;
         let after_colon = &url[colon_pos + 1..];
         let parts: Vec<&str> = after_colon.split('/').collect();
@@ -82,10 +313,64 @@ pub fn parse_repo_from_url(url: &str) -> Result<(String, String)> {
     Ok((owner, repo))
 }
 
+/// `organization/project` and repository of an Azure DevOps remote:
+/// - `https://[user@]dev.azure.com/org/project/_git/repo`
+/// - `https://org.visualstudio.com/[DefaultCollection/]project/_git/repo`
+/// - `git@ssh.dev.azure.com:v3/org/project/repo` (or `vs-ssh.visualstudio.com`)
+fn parse_azure_devops_url(url: &str) -> Option<(String, String)> {
+    let decode = |part: &str| {
+        percent_encoding::percent_decode_str(part)
+            .decode_utf8_lossy()
+            .into_owned()
+    };
+
+    if let Some((_, path)) = url.split_once(":v3/") {
+        let parts: Vec<&str> = path.trim_end_matches('/').split('/').collect();
+        let [organization, project, repo] = parts[..] else {
+            return None;
+        };
+        return Some((
+            format!("{}/{}", decode(organization), decode(project)),
+            decode(repo),
+        ));
+    }
+
+    let (before, repo) = url.split_once("/_git/")?;
+    let repo = repo.trim_end_matches('/').trim_end_matches(".git");
+    let rest = before.split_once("://").map_or(before, |(_, rest)| rest);
+    let (host, path) = rest.split_once('/')?;
+    let host = host.rsplit_once('@').map_or(host, |(_, host)| host);
+    let mut segments = path
+        .split('/')
+        .filter(|s| !s.is_empty() && *s != "DefaultCollection");
+    let (organization, project) = match host.strip_suffix(".visualstudio.com") {
+        Some(organization) => (organization, segments.next_back()?),
+        None => (segments.next()?, segments.next_back()?),
+    };
+    Some((
+        format!("{}/{}", decode(organization), decode(project)),
+        decode(repo),
+    ))
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
 
+    #[tokio::test]
+    async fn test_retry_policy_per_client() {
+        let patient = RetryPolicy {
+            max_retries: 5,
+            ..RetryPolicy::default()
+        };
+        let first = Client::from(Octocrab::builder().build().unwrap()).with_retry_policy(patient);
+        let second = Client::from(Octocrab::builder().build().unwrap())
+            .with_retry_policy(RetryPolicy::default());
+
+        assert_eq!(first.retry_policy(), patient);
+        assert_eq!(second.retry_policy(), RetryPolicy::default());
+    }
+
     #[test]
     fn test_parse_ssh_url() {
         let url = "git@github.com:cohere-ai/north.git";
@@ -110,6 +395,38 @@ mod tests {
         assert_eq!(repo, "north");
     }
 
+    #[test]
+    fn test_parse_bitbucket_urls() {
+        for url in [
+            "git@bitbucket.org:team/repo.git",
+            "https://me@bitbucket.org/team/repo.git",
+            "ssh://git@bitbucket.org/team/repo.git",
+        ] {
+            assert_eq!(
+                parse_repo_from_url(url).unwrap(),
+                ("team".to_string(), "repo".to_string()),
+                "{}",
+                url
+            );
+        }
+    }
+
+    #[test]
+    fn test_parse_azure_devops_urls() {
+        let expected = ("contoso/Web Apps".to_string(), "api".to_string());
+        for url in [
+            "https://contoso@dev.azure.com/contoso/Web%20Apps/_git/api",
+            "https://dev.azure.com/contoso/Web%20Apps/_git/api/",
+            "https://contoso.visualstudio.com/Web%20Apps/_git/api",
+            "https://contoso.visualstudio.com/DefaultCollection/Web%20Apps/_git/api",
+            "git@ssh.dev.azure.com:v3/contoso/Web%20Apps/api",
+            "contoso@vs-ssh.visualstudio.com:v3/contoso/Web%20Apps/api",
+        ] {
+            assert_eq!(parse_repo_from_url(url).unwrap(), expected, "{}", url);
+        }
+        assert!(parse_repo_from_url("https://dev.azure.com/contoso").is_err());
+    }
+
     #[test]
     fn test_parse_file_url() {
         let url = "file:///path/to/repo";
@@ -0,0 +1,261 @@
+//! Readable messages for failed GitHub API calls.
+//!
+//! GitHub explains a rejected request in its response body: a `message`, plus
+//! an `errors` list for validation failures (REST) or one entry per failed
+//! field (GraphQL). These helpers print one line per problem instead of the
+//! raw error, and add a suggestion for the failures stacks commonly run into.
+
+use anyhow::anyhow;
+use serde_json::Value;
+
+/// Attach a readable explanation of a GitHub API failure, like anyhow's `context`
+pub trait GitHubContext<T> {
+    fn gh_context(self, action: &str) -> anyhow::Result<T>;
+}
+
+impl<T> GitHubContext<T> for Result<T, octocrab::Error> {
+    fn gh_context(self, action: &str) -> anyhow::Result<T> {
+        self.map_err(|e| api_error(e, action))
+    }
+}
+
+/// Describe an octocrab error: GitHub's own explanation when the request was
+/// rejected, the underlying error otherwise (e.g. connection failures)
+pub fn api_error(error: octocrab::Error, action: &str) -> anyhow::Error {
+    let octocrab::Error::GitHub { source, .. } = &error else {
+        return anyhow::Error::new(error).context(action.to_string());
+    };
+
+    let problems = rest_problems(
+        &source.message,
+        source.errors.as_deref().unwrap_or_default(),
+    );
+    let status = source.status_code.as_u16();
+    let mut message = format!("{}: GitHub returned {}", action, source.status_code);
+    append_problems(&mut message, &problems);
+    if let Some(hint) = hint(Some(status), &problems) {
+        message.push_str(&format!("\n\n💡 {}", hint));
+    }
+    anyhow!(message)
+}
+
+/// Whether GitHub refused to create a PR because one is already open for its head branch
+pub fn is_pr_already_exists(error: &octocrab::Error) -> bool {
+    let octocrab::Error::GitHub { source, .. } = error else {
+        return false;
+    };
+    source.status_code.as_u16() == 422
+        && rest_problems(
+            &source.message,
+            source.errors.as_deref().unwrap_or_default(),
+        )
+        .iter()
+        .any(|p| p.to_lowercase().contains("a pull request already exists"))
+}
+
+/// GitHub's explanation when it refused a merge because the base branch's
+/// protection isn't satisfied (HTTP 405)
+pub fn merge_blocked(error: &octocrab::Error) -> Option<String> {
+    let octocrab::Error::GitHub { source, .. } = error else {
+        return None;
+    };
+    (source.status_code.as_u16() == 405).then(|| source.message.clone())
+}
+
+/// Whether a request failed because GitHub couldn't be reached (DNS, refused
+/// connection, timeout) rather than because it rejected the request
+pub fn is_unreachable(error: &anyhow::Error) -> bool {
+    error.chain().any(|cause| {
+        matches!(
+            cause.downcast_ref::<octocrab::Error>(),
+            Some(octocrab::Error::Hyper { .. } | octocrab::Error::Service { .. })
+        )
+    })
+}
+
+/// Describe the `errors` of a GraphQL response
+pub fn graphql_error(action: &str, errors: &Value) -> anyhow::Error {
+    let problems: Vec<String> = match errors.as_array() {
+        Some(errors) => errors
+            .iter()
+            .map(|e| match e.get("message").and_then(Value::as_str) {
+                Some(message) => message.to_string(),
+                None => e.to_string(),
+            })
+            .collect(),
+        None => vec![errors.to_string()],
+    };
+
+    let mut message = format!("{}: GitHub rejected the request", action);
+    append_problems(&mut message, &problems);
+    if let Some(hint) = hint(None, &problems) {
+        message.push_str(&format!("\n\n💡 {}", hint));
+    }
+    anyhow!(message)
+}
+
+fn append_problems(message: &mut String, problems: &[String]) {
+    for problem in problems {
+        message.push_str("\n  - ");
+        message.push_str(problem);
+    }
+}
+
+/// One line per validation error, or the top-level message when there are none.
+/// Errors without a message are spelled out from their resource, field and code
+/// (`{"resource":"PullRequest","field":"base","code":"invalid"}`).
+fn rest_problems(message: &str, errors: &[Value]) -> Vec<String> {
+    let problems: Vec<String> = errors
+        .iter()
+        .map(|e| {
+            if let Some(message) = e.get("message").and_then(Value::as_str) {
+                return message.to_string();
+            }
+            let field = |key: &str| e.get(key).and_then(Value::as_str);
+            match (field("resource"), field("field"), field("code")) {
+                (Some(resource), Some(name), Some(code)) => {
+                    format!("{} {} is {}", resource, name, code.replace('_', " "))
+                }
+                _ => e.to_string(),
+            }
+        })
+        .collect();
+
+    if problems.is_empty() {
+        vec![message.to_string()]
+    } else {
+        problems
+    }
+}
+
+/// A suggestion for failures with a known cause
+fn hint(status: Option<u16>, problems: &[String]) -> Option<&'static str> {
+    let text = problems.join("\n").to_lowercase();
+    let mentions = |needle: &str| text.contains(needle);
+
+    if mentions("a pull request already exists") {
+        Some(
+            "A PR for this slot branch exists already, but it couldn't be looked up to adopt it (e.g. it comes from a fork). Close it on GitHub and export again.",
+        )
+    } else if mentions("no commits between") {
+        Some(
+            "The commit is already part of its base branch, so the PR would be empty. If the PR below it landed, run 'git stk landed'.",
+        )
+    } else if mentions("pullrequest base is invalid") {
+        Some(
+            "The PR's base branch doesn't exist on the remote. Check git-stk.base, and that the entry below was pushed ('git stk export --push-only').",
+        )
+    } else if mentions("pullrequest head is invalid") {
+        Some(
+            "The slot branch isn't on the remote. Push it with 'git stk export --push-only', then export again.",
+        )
+    } else if mentions("closed pull request") {
+        Some("The PR is closed. Reopen it on GitHub, or abandon the entry with 'git stk abandon'.")
+    } else if mentions("required status check")
+        || mentions("approving review")
+        || mentions("protected branch")
+    {
+        Some(
+            "Branch protection blocks this: wait for the required checks and reviews, then try again.",
+        )
+    } else if mentions("head branch was modified") {
+        Some("The slot branch changed while merging. Run 'git stk export' and try again.")
+    } else if mentions("not mergeable") {
+        Some(
+            "GitHub can't merge the PR cleanly. Rebase the stack onto the base branch and export again.",
+        )
+    } else if mentions("rate limit") || status == Some(429) {
+        Some("GitHub's rate limit was hit. Wait a few minutes and try again.")
+    } else if status == Some(401) || mentions("bad credentials") {
+        Some("The GitHub token was rejected. Run 'gh auth login' to log in again.")
+    } else if matches!(status, Some(403 | 404)) || mentions("resource not accessible") {
+        Some(
+            "The token can't reach this repository. Check 'gh auth status' (including its scopes) and that the remote points at the right repository.",
+        )
+    } else {
+        None
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn test_rest_problems() {
+        let errors = vec![
+            json!({"resource": "PullRequest", "code": "custom", "message": "A pull request already exists for me:feat--01."}),
+            json!({"resource": "PullRequest", "field": "base", "code": "invalid"}),
+        ];
+        assert_eq!(
+            rest_problems("Validation Failed", &errors),
+            vec![
+                "A pull request already exists for me:feat--01.",
+                "PullRequest base is invalid"
+            ]
+        );
+        assert_eq!(rest_problems("Not Found", &[]), vec!["Not Found"]);
+    }
+
+    #[test]
+    fn test_hint() {
+        let problems = |p: &str| vec![p.to_string()];
+        assert!(
+            hint(
+                Some(422),
+                &problems("A pull request already exists for me:feat--01.")
+            )
+            .unwrap()
+            .contains("Close it")
+        );
+        assert!(
+            hint(Some(422), &problems("PullRequest base is invalid"))
+                .unwrap()
+                .contains("git-stk.base")
+        );
+        assert!(
+            hint(
+                Some(405),
+                &problems("Required status check \"ci\" is expected.")
+            )
+            .unwrap()
+            .contains("protection")
+        );
+        assert!(
+            hint(Some(404), &problems("Not Found"))
+                .unwrap()
+                .contains("gh auth status")
+        );
+        assert_eq!(hint(Some(422), &problems("Something else")), None);
+    }
+
+    #[test]
+    fn test_is_unreachable() {
+        let connection = octocrab::Error::Service {
+            source: Box::new(std::io::Error::new(
+                std::io::ErrorKind::ConnectionRefused,
+                "connection refused",
+            )),
+            backtrace: std::backtrace::Backtrace::capture(),
+        };
+        assert!(is_unreachable(&api_error(
+            connection,
+            "Failed to fetch PRs"
+        )));
+        assert!(!is_unreachable(&graphql_error(
+            "Failed to fetch PRs",
+            &json!([{"message": "Not Found"}])
+        )));
+    }
+
+    #[test]
+    fn test_graphql_error() {
+        let errors = json!([{"type": "UNPROCESSABLE", "message": "Cannot change the base branch of a closed pull request."}]);
+        let message = graphql_error("Failed to update PR bases", &errors).to_string();
+        assert!(message.starts_with(
+            "Failed to update PR bases: GitHub rejected the request\n  - Cannot change"
+        ));
+        assert!(message.contains("git stk abandon"));
+    }
+}
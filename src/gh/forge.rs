@@ -0,0 +1,407 @@
+//! Forges other than GitHub, reached through their REST APIs.
+//!
+//! GitHub is implemented directly in `queries` and `mutations`. For a remote on
+//! Bitbucket Cloud or Azure DevOps, the client carries a [`ForgeApi`] and those
+//! functions hand over to it: discovery, export, land and the other commands
+//! work the same, on that forge's PRs. Features only GitHub has (milestones,
+//! projects, review threads, assignees, ...) fail with [`unsupported`].
+//!
+//! Requests go through the same octocrab HTTP stack as GitHub's (timeouts,
+//! proxy, CA bundle), pointed at the forge's API, with responses decoded here.
+
+use crate::gh::client::Client;
+use crate::gh::queries::{MergeProgress, PrInfo};
+use crate::gh::{mutations::MergeOutcome, retry};
+use crate::model::Forge;
+use anyhow::anyhow;
+use async_trait::async_trait;
+use http::{Method, StatusCode};
+use serde::de::DeserializeOwned;
+use serde_json::Value;
+
+/// Pull request operations of a forge other than GitHub. `owner` and `repo`
+/// come from the remote URL: a workspace and repository slug on Bitbucket, an
+/// `organization/project` and repository name on Azure DevOps.
+#[async_trait]
+pub trait ForgeApi: Send + Sync {
+    fn forge(&self) -> Forge;
+
+    /// Identifier of the authenticated user, as comment authors are reported
+    async fn get_viewer(&self, http: &Client) -> anyhow::Result<String>;
+
+    /// PRs from `head_ref` of the repository itself (not a fork), newest first
+    async fn find_prs_by_head(
+        &self,
+        http: &Client,
+        owner: &str,
+        repo: &str,
+        head_ref: &str,
+        open_only: bool,
+    ) -> anyhow::Result<Vec<PrInfo>>;
+
+    /// A PR by number; `None` when it doesn't exist
+    async fn get_pr(
+        &self,
+        http: &Client,
+        owner: &str,
+        repo: &str,
+        pr_number: u64,
+    ) -> anyhow::Result<Option<PrInfo>>;
+
+    /// Every open PR of the repository
+    async fn list_open_prs(
+        &self,
+        http: &Client,
+        owner: &str,
+        repo: &str,
+    ) -> anyhow::Result<Vec<PrInfo>>;
+
+    /// Where a PR stands while `land` waits for it to merge
+    async fn get_merge_progress(
+        &self,
+        http: &Client,
+        owner: &str,
+        repo: &str,
+        pr_number: u64,
+    ) -> anyhow::Result<MergeProgress>;
+
+    /// Open a PR and return its number
+    #[allow(clippy::too_many_arguments)]
+    async fn create_pull_request(
+        &self,
+        http: &Client,
+        owner: &str,
+        repo: &str,
+        head: &str,
+        base: &str,
+        title: &str,
+        body: &str,
+        draft: bool,
+    ) -> anyhow::Result<u64>;
+
+    /// Change a PR's base, title and body; `None` leaves it as is
+    #[allow(clippy::too_many_arguments)]
+    async fn update_pull_request(
+        &self,
+        http: &Client,
+        owner: &str,
+        repo: &str,
+        pr_number: u64,
+        base: Option<&str>,
+        title: Option<&str>,
+        body: Option<&str>,
+    ) -> anyhow::Result<()>;
+
+    async fn add_pr_comment(
+        &self,
+        http: &Client,
+        owner: &str,
+        repo: &str,
+        pr_number: u64,
+        body: &str,
+    ) -> anyhow::Result<()>;
+
+    /// Edit the authenticated user's comment containing `marker`, or add one
+    #[allow(clippy::too_many_arguments)]
+    async fn upsert_pr_comment(
+        &self,
+        http: &Client,
+        owner: &str,
+        repo: &str,
+        pr_number: u64,
+        marker: &str,
+        body: &str,
+    ) -> anyhow::Result<()>;
+
+    /// Close a PR without merging it, replacing its body with `body` if given
+    async fn close_pull_request(
+        &self,
+        http: &Client,
+        owner: &str,
+        repo: &str,
+        pr_number: u64,
+        body: Option<&str>,
+    ) -> anyhow::Result<()>;
+
+    #[allow(clippy::too_many_arguments)]
+    async fn merge_pull_request(
+        &self,
+        http: &Client,
+        owner: &str,
+        repo: &str,
+        pr_number: u64,
+        commit_title: Option<&str>,
+        commit_message: Option<&str>,
+    ) -> anyhow::Result<MergeOutcome>;
+
+    async fn convert_to_draft(
+        &self,
+        http: &Client,
+        owner: &str,
+        repo: &str,
+        pr_number: u64,
+    ) -> anyhow::Result<()>;
+
+    async fn get_labels(
+        &self,
+        _http: &Client,
+        _owner: &str,
+        _repo: &str,
+        _pr_number: u64,
+    ) -> anyhow::Result<Vec<String>> {
+        Err(unsupported(self.forge(), "PR labels"))
+    }
+
+    async fn add_labels(
+        &self,
+        _http: &Client,
+        _owner: &str,
+        _repo: &str,
+        _pr_number: u64,
+        _labels: &[String],
+    ) -> anyhow::Result<()> {
+        Err(unsupported(self.forge(), "PR labels"))
+    }
+}
+
+/// Error for a feature `forge` doesn't have, or git-stk only implements for GitHub
+pub fn unsupported(forge: Forge, feature: &str) -> anyhow::Error {
+    anyhow!(
+        "{} are only supported on GitHub, not on {}",
+        feature,
+        forge.name()
+    )
+}
+
+/// A failed request to a forge's REST API
+#[derive(Debug, thiserror::Error)]
+pub enum RestError {
+    /// The request didn't get a response (connection, TLS, timeout)
+    #[error("{0}")]
+    Transport(#[from] octocrab::Error),
+    /// The forge answered with an error status and, usually, a message
+    #[error("{status}: {message}")]
+    Status { status: StatusCode, message: String },
+}
+
+impl RestError {
+    /// Transport failures, rate limiting and 5xx responses
+    pub fn is_transient(&self) -> bool {
+        match self {
+            Self::Transport(e) => retry::is_transient(e),
+            Self::Status { status, .. } => {
+                status.is_server_error() || *status == StatusCode::TOO_MANY_REQUESTS
+            }
+        }
+    }
+
+    pub fn status(&self) -> Option<StatusCode> {
+        match self {
+            Self::Transport(_) => None,
+            Self::Status { status, .. } => Some(*status),
+        }
+    }
+
+    /// Describe the failure of `action` on `forge`, with a hint for credentials problems
+    pub fn context(self, forge: Forge, action: &str) -> anyhow::Error {
+        let message = match &self {
+            Self::Transport(_) => action.to_string(),
+            Self::Status { status, message } => {
+                let mut text = format!("{}: {} returned {}", action, forge.name(), status);
+                if !message.is_empty() {
+                    text.push_str(&format!("\n  - {}", message));
+                }
+                if let Some(hint) = credentials_hint(forge, *status) {
+                    text.push_str(&format!("\n\n💡 {}", hint));
+                }
+                text
+            }
+        };
+        anyhow::Error::new(self).context(message)
+    }
+}
+
+fn credentials_hint(forge: Forge, status: StatusCode) -> Option<&'static str> {
+    match (forge, status.as_u16()) {
+        (Forge::Bitbucket, 401 | 403) => Some(
+            "Bitbucket rejected the credentials. Set BITBUCKET_TOKEN to an access token with pull request scope \
+             (or BITBUCKET_USERNAME and BITBUCKET_APP_PASSWORD).",
+        ),
+        (Forge::AzureDevOps, 401 | 203) => Some(
+            "Azure DevOps rejected the credentials. Set AZURE_DEVOPS_EXT_PAT to a personal access token with \
+             Code (Read & write) scope, or run 'az login'.",
+        ),
+        (_, 404) => Some(
+            "The repository or PR wasn't found. Check that the remote points at the right repository.",
+        ),
+        _ => None,
+    }
+}
+
+/// Whether `error` is a 404 from a forge's REST API
+pub fn is_not_found(error: &anyhow::Error) -> bool {
+    error.chain().any(|cause| {
+        cause
+            .downcast_ref::<RestError>()
+            .and_then(RestError::status)
+            == Some(StatusCode::NOT_FOUND)
+    })
+}
+
+/// Send a request with a JSON body (if any) and decode the JSON response.
+/// Only idempotent requests may `retry` transient failures.
+pub async fn send<T: DeserializeOwned>(
+    http: &Client,
+    method: Method,
+    uri: &str,
+    body: Option<&Value>,
+    retry: bool,
+) -> Result<T, RestError> {
+    let attempt = || async {
+        let request = http::Request::builder()
+            .method(method.clone())
+            .uri(uri)
+            .header(http::header::ACCEPT, "application/json");
+        let request = match body {
+            Some(body) => request
+                .header(http::header::CONTENT_TYPE, "application/json")
+                .body(body.to_string()),
+            None => request.body(String::new()),
+        }
+        .map_err(|e| RestError::Status {
+            status: StatusCode::BAD_REQUEST,
+            message: e.to_string(),
+        })?;
+
+        let response = http.execute(request).await?;
+        let status = response.status();
+        let text = http.body_to_string(response).await?;
+        // Azure DevOps answers 203 with a sign-in page to rejected credentials
+        if status == StatusCode::NON_AUTHORITATIVE_INFORMATION {
+            return Err(RestError::Status {
+                status,
+                message: "The credentials were rejected".to_string(),
+            });
+        }
+        if !status.is_success() {
+            return Err(RestError::Status {
+                status,
+                message: error_message(&text),
+            });
+        }
+        // Some endpoints answer 204 without a body
+        serde_json::from_str(if text.is_empty() { "null" } else { &text }).map_err(|e| {
+            RestError::Status {
+                status,
+                message: format!("Unexpected response: {}", e),
+            }
+        })
+    };
+
+    if retry {
+        retry::with_retry_if(http.retry_policy(), uri, RestError::is_transient, attempt).await
+    } else {
+        attempt().await
+    }
+}
+
+/// The message of an error response: Bitbucket's `{"error": {"message"}}`,
+/// Azure DevOps' `{"message"}`, or the first line of the body
+fn error_message(body: &str) -> String {
+    let Ok(json) = serde_json::from_str::<Value>(body) else {
+        return body
+            .trim()
+            .lines()
+            .next()
+            .unwrap_or_default()
+            .chars()
+            .take(200)
+            .collect();
+    };
+    let error = json.get("error").unwrap_or(&json);
+    let message = error.get("message").and_then(Value::as_str);
+    let detail = error
+        .get("detail")
+        .and_then(Value::as_str)
+        .filter(|d| !d.is_empty());
+    match (message, detail) {
+        (Some(message), Some(detail)) => format!("{} ({})", message, detail),
+        (Some(message), None) => message.to_string(),
+        _ => body.trim().chars().take(200).collect(),
+    }
+}
+
+/// Characters escaped in a URL path segment or query parameter: all but the
+/// unreserved ones
+const ESCAPED: &percent_encoding::AsciiSet = &percent_encoding::NON_ALPHANUMERIC
+    .remove(b'-')
+    .remove(b'.')
+    .remove(b'_')
+    .remove(b'~');
+
+/// Percent-encode a value for a URL path segment or query parameter
+pub fn encode(value: &str) -> String {
+    percent_encoding::utf8_percent_encode(value, ESCAPED).to_string()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_error_message() {
+        assert_eq!(
+            error_message(
+                r#"{"type": "error", "error": {"message": "Bad request", "detail": "Branch not found"}}"#
+            ),
+            "Bad request (Branch not found)"
+        );
+        assert_eq!(
+            error_message(
+                r#"{"$id": "1", "message": "TF401179: An active pull request already exists."}"#
+            ),
+            "TF401179: An active pull request already exists."
+        );
+        assert_eq!(error_message("Service Unavailable"), "Service Unavailable");
+    }
+
+    #[test]
+    fn test_rest_error() {
+        let error = RestError::Status {
+            status: StatusCode::UNAUTHORIZED,
+            message: "Unauthorized".to_string(),
+        };
+        assert!(!error.is_transient());
+        let message = error
+            .context(Forge::Bitbucket, "Failed to get PR #3")
+            .to_string();
+        assert!(message.starts_with(
+            "Failed to get PR #3: Bitbucket returned 401 Unauthorized\n  - Unauthorized"
+        ));
+        assert!(message.contains("BITBUCKET_TOKEN"));
+
+        let not_found = RestError::Status {
+            status: StatusCode::NOT_FOUND,
+            message: String::new(),
+        };
+        assert!(is_not_found(
+            &not_found.context(Forge::AzureDevOps, "Failed to get PR #3")
+        ));
+        assert!(
+            RestError::Status {
+                status: StatusCode::BAD_GATEWAY,
+                message: String::new()
+            }
+            .is_transient()
+        );
+    }
+
+    #[test]
+    fn test_encode() {
+        assert_eq!(encode("feat/x--01"), "feat%2Fx--01");
+        assert_eq!(
+            encode("source.branch.name = \"a\""),
+            "source.branch.name%20%3D%20%22a%22"
+        );
+    }
+}
@@ -0,0 +1,534 @@
+//! Typed GitHub GraphQL operations.
+//!
+//! Operation documents live in `graphql/*.graphql`. graphql_client checks each
+//! against GitHub's schema (`graphql/schema.graphql`) at build time and
+//! generates its variables and response types (see [`query`] and [`mutate`]).
+//! The schema is GitHub's public one, from
+//! <https://docs.github.com/public/fpt/schema.docs.graphql>.
+//! Values are always passed as typed variables, never formatted into the
+//! document.
+//!
+//! Batched operations alias the same field once per item (`pr0:
+//! pullRequest(number: $pr0)`, ...), with one variable per alias. Their
+//! document depends on the number of items, so it's built at run time around
+//! the `PrState` fragment (checked as part of `OpenPrs`), and decoded into the
+//! hand-written types below: every field is optional or defaulted so one type
+//! serves all batched queries of an object, and aliased fields decode into a
+//! map keyed by alias.
+
+use crate::gh::client::Client;
+use crate::gh::errors::{self, GitHubContext};
+use crate::gh::retry;
+use anyhow::anyhow;
+use graphql_client::GraphQLQuery;
+use serde::de::DeserializeOwned;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::collections::{BTreeMap, HashMap};
+
+/// Most items (PRs, branches) in one batched operation. Longer batches are
+/// split, so very long stacks stay within GitHub's query complexity limits.
+pub const MAX_BATCH: usize = 50;
+
+/// Fields of a pull request most queries need, as the `PrState` fragment
+pub const PR_STATE_FRAGMENT: &str = include_str!("graphql/pr_state.graphql");
+
+/// An operation document and its variables, as sent to GitHub
+#[derive(Debug, Serialize)]
+pub struct Request<V> {
+    query: String,
+    variables: V,
+}
+
+impl<V: Serialize + Sync> Request<V> {
+    pub fn new(query: impl Into<String>, variables: V) -> Self {
+        Self {
+            query: query.into(),
+            variables,
+        }
+    }
+
+    /// The document and variables of a generated operation
+    fn generated<Q: GraphQLQuery<Variables = V>>(variables: V) -> Self {
+        let body = Q::build_query(variables);
+        Self::new(body.query, body.variables)
+    }
+
+    /// Run a query, retrying transient failures
+    pub async fn query<T: DeserializeOwned>(
+        &self,
+        client: &Client,
+        action: &str,
+    ) -> anyhow::Result<T> {
+        let response: Response<T> =
+            retry::with_retry(client.retry_policy(), "graphql query", || {
+                client.graphql(self)
+            })
+            .await
+            .gh_context(action)?;
+        response.into_data(action)
+    }
+
+    /// Run a mutation. Unlike queries, any error GitHub reports fails it, even
+    /// when other aliases went through. Only idempotent mutations may `retry`.
+    pub async fn mutate(&self, client: &Client, action: &str, retry: bool) -> anyhow::Result<()> {
+        let response: Response<Value> = if retry {
+            retry::with_retry(client.retry_policy(), "graphql", || client.graphql(self)).await
+        } else {
+            client.graphql(self).await
+        }
+        .gh_context(action)?;
+
+        match response.errors {
+            Some(errors) => Err(errors::graphql_error(action, &errors)),
+            None => Ok(()),
+        }
+    }
+}
+
+/// Run a generated query, retrying transient failures
+pub async fn query<Q: GraphQLQuery>(
+    client: &Client,
+    variables: Q::Variables,
+    action: &str,
+) -> anyhow::Result<Q::ResponseData>
+where
+    Q::Variables: Sync,
+{
+    Request::generated::<Q>(variables)
+        .query(client, action)
+        .await
+}
+
+/// Run a generated mutation. Only idempotent mutations may `retry`.
+pub async fn mutate<Q: GraphQLQuery>(
+    client: &Client,
+    variables: Q::Variables,
+    action: &str,
+    retry: bool,
+) -> anyhow::Result<()>
+where
+    Q::Variables: Sync,
+{
+    Request::generated::<Q>(variables)
+        .mutate(client, action, retry)
+        .await
+}
+
+/// Variables of an operation on one repository
+#[derive(Debug, Serialize)]
+pub struct RepositoryVariables<'a, V> {
+    pub owner: &'a str,
+    pub name: &'a str,
+    #[serde(flatten)]
+    pub variables: V,
+}
+
+/// One variable per alias of a batched operation: `$pr0`, `$pr1`, ...
+#[derive(Debug, Serialize)]
+#[serde(transparent)]
+pub struct Aliased<V> {
+    values: BTreeMap<String, V>,
+    #[serde(skip)]
+    prefix: &'static str,
+    #[serde(skip)]
+    graphql_type: &'static str,
+}
+
+impl<V> Aliased<V> {
+    /// Variables `{prefix}0`, `{prefix}1`, ... of type `graphql_type`
+    pub fn new(
+        prefix: &'static str,
+        graphql_type: &'static str,
+        values: impl IntoIterator<Item = V>,
+    ) -> Self {
+        let values = values
+            .into_iter()
+            .enumerate()
+            .map(|(idx, value)| (alias(prefix, idx), value))
+            .collect();
+        Self {
+            values,
+            prefix,
+            graphql_type,
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.values.is_empty()
+    }
+
+    /// Variable declarations for the operation, e.g. `$pr0: Int!, $pr1: Int!`
+    pub fn declarations(&self) -> String {
+        (0..self.values.len())
+            .map(|idx| format!("${}: {}", alias(self.prefix, idx), self.graphql_type))
+            .collect::<Vec<_>>()
+            .join(", ")
+    }
+
+    /// The aliased selections, `selection(alias)` for each alias in order
+    pub fn selections(&self, selection: impl Fn(&str) -> String) -> String {
+        (0..self.values.len())
+            .map(|idx| selection(&alias(self.prefix, idx)))
+            .collect::<Vec<_>>()
+            .join("\n")
+    }
+}
+
+/// Name of the `idx`th alias (and variable) of a batch
+pub fn alias(prefix: &str, idx: usize) -> String {
+    format!("{}{}", prefix, idx)
+}
+
+/// A GraphQL response: `data`, and the `errors` GitHub reported
+#[derive(Debug, Deserialize)]
+pub struct Response<T> {
+    pub data: Option<T>,
+    #[serde(default)]
+    pub errors: Option<Value>,
+}
+
+impl<T> Response<T> {
+    /// The data. Partial failures (e.g. one PR of a batch not found) leave
+    /// nulls in it; only a response without data is an error.
+    pub fn into_data(self, action: &str) -> anyhow::Result<T> {
+        match (self.data, self.errors) {
+            (Some(data), _) => Ok(data),
+            (None, Some(errors)) => Err(errors::graphql_error(action, &errors)),
+            (None, None) => Err(anyhow!("{}: GitHub returned no data", action)),
+        }
+    }
+}
+
+/// `data` of a query on one repository
+#[derive(Debug, Deserialize)]
+pub struct RepositoryData<T> {
+    pub repository: Option<T>,
+}
+
+/// Aliased fields of a batched query, e.g. `pr0: pullRequest(number: 12)`
+pub type Aliases<T> = HashMap<String, Option<T>>;
+
+/// The `nodes` of a connection; GitHub may return null nodes, which are skipped
+#[derive(Debug, Clone, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Connection<T> {
+    #[serde(default = "Vec::new")]
+    nodes: Vec<Option<T>>,
+    pub total_count: Option<u64>,
+}
+
+impl<T> Default for Connection<T> {
+    fn default() -> Self {
+        Self {
+            nodes: Vec::new(),
+            total_count: None,
+        }
+    }
+}
+
+impl<T> Connection<T> {
+    pub fn nodes(&self) -> impl Iterator<Item = &T> {
+        self.nodes.iter().flatten()
+    }
+
+    pub fn into_nodes(self) -> impl Iterator<Item = T> {
+        self.nodes.into_iter().flatten()
+    }
+}
+
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(default, rename_all = "camelCase")]
+pub struct PullRequest {
+    pub number: u64,
+    pub title: String,
+    pub body: String,
+    /// OPEN, CLOSED or MERGED
+    pub state: String,
+    pub is_draft: bool,
+    pub merged: bool,
+    pub base_ref_name: String,
+    pub head_ref_name: String,
+    pub head_ref_oid: String,
+    pub merge_commit: Option<GitObject>,
+    /// MERGEABLE, CONFLICTING or UNKNOWN (while GitHub computes it)
+    pub mergeable: Option<String>,
+    /// CLEAN, BLOCKED, BEHIND, DIRTY, UNSTABLE, ...
+    pub merge_state_status: Option<String>,
+    /// REVIEW_REQUIRED, CHANGES_REQUESTED or APPROVED
+    pub review_decision: Option<String>,
+    pub head_repository_owner: Option<Actor>,
+    pub review_threads: Connection<ReviewThread>,
+    pub labels: Connection<Label>,
+    /// The head commit, with `commits(last: 1)`
+    pub commits: Connection<PullRequestCommit>,
+}
+
+impl PullRequest {
+    /// Status checks of the head commit
+    pub fn status_check_rollup(&self) -> Option<&StatusCheckRollup> {
+        self.commits
+            .nodes()
+            .next()?
+            .commit
+            .status_check_rollup
+            .as_ref()
+    }
+}
+
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(default)]
+pub struct GitObject {
+    pub oid: String,
+}
+
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(default)]
+pub struct Actor {
+    pub login: String,
+}
+
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(default, rename_all = "camelCase")]
+pub struct ReviewThread {
+    pub is_resolved: bool,
+}
+
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(default)]
+pub struct Label {
+    pub name: String,
+}
+
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(default)]
+pub struct PullRequestCommit {
+    pub commit: Commit,
+}
+
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(default, rename_all = "camelCase")]
+pub struct Commit {
+    pub status_check_rollup: Option<StatusCheckRollup>,
+}
+
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(default)]
+pub struct StatusCheckRollup {
+    /// SUCCESS, FAILURE, ERROR, PENDING or EXPECTED
+    pub state: Option<String>,
+}
+
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(default)]
+pub struct Node {
+    pub id: String,
+}
+
+// Custom scalars and enums selected by the operations, decoded as strings
+#[allow(clippy::upper_case_acronyms)]
+type GitObjectID = String;
+type CheckConclusionState = String;
+type CheckStatusState = String;
+type MergeableState = String;
+type MergeStateStatus = String;
+type PullRequestReviewDecision = String;
+type PullRequestReviewState = String;
+type PullRequestState = String;
+type StatusState = String;
+use crate::gh::mutations::ReviewEvent as PullRequestReviewEvent;
+
+#[derive(GraphQLQuery)]
+#[graphql(
+    schema_path = "src/gh/graphql/schema.graphql",
+    query_path = "src/gh/graphql/milestones.graphql",
+    response_derives = "Debug"
+)]
+pub struct Milestones;
+
+#[derive(GraphQLQuery)]
+#[graphql(
+    schema_path = "src/gh/graphql/schema.graphql",
+    query_path = "src/gh/graphql/project.graphql",
+    response_derives = "Debug"
+)]
+pub struct Project;
+
+#[derive(GraphQLQuery)]
+#[graphql(
+    schema_path = "src/gh/graphql/schema.graphql",
+    query_path = "src/gh/graphql/open_prs.graphql",
+    extern_enums("PullRequestState", "MergeableState", "MergeStateStatus"),
+    response_derives = "Debug"
+)]
+pub struct OpenPrs;
+
+#[derive(GraphQLQuery)]
+#[graphql(
+    schema_path = "src/gh/graphql/schema.graphql",
+    query_path = "src/gh/graphql/merge_progress.graphql",
+    extern_enums(
+        "PullRequestState",
+        "MergeStateStatus",
+        "CheckStatusState",
+        "CheckConclusionState",
+        "StatusState"
+    ),
+    response_derives = "Debug"
+)]
+pub struct MergeProgress;
+
+#[derive(GraphQLQuery)]
+#[graphql(
+    schema_path = "src/gh/graphql/schema.graphql",
+    query_path = "src/gh/graphql/merge_requirements.graphql",
+    extern_enums(
+        "MergeStateStatus",
+        "PullRequestReviewDecision",
+        "PullRequestReviewState",
+        "CheckStatusState",
+        "CheckConclusionState",
+        "StatusState"
+    ),
+    response_derives = "Debug"
+)]
+pub struct MergeRequirements;
+
+#[derive(GraphQLQuery)]
+#[graphql(
+    schema_path = "src/gh/graphql/schema.graphql",
+    query_path = "src/gh/graphql/review_threads.graphql",
+    response_derives = "Debug"
+)]
+pub struct ReviewThreads;
+
+#[derive(GraphQLQuery)]
+#[graphql(
+    schema_path = "src/gh/graphql/schema.graphql",
+    query_path = "src/gh/graphql/add_review.graphql",
+    extern_enums("PullRequestReviewEvent"),
+    response_derives = "Debug"
+)]
+pub struct AddReview;
+
+#[derive(GraphQLQuery)]
+#[graphql(
+    schema_path = "src/gh/graphql/schema.graphql",
+    query_path = "src/gh/graphql/convert_to_draft.graphql",
+    response_derives = "Debug"
+)]
+pub struct ConvertToDraft;
+
+#[derive(GraphQLQuery)]
+#[graphql(
+    schema_path = "src/gh/graphql/schema.graphql",
+    query_path = "src/gh/graphql/resolve_thread.graphql",
+    response_derives = "Debug"
+)]
+pub struct ResolveThread;
+
+impl From<open_prs::PrState> for PullRequest {
+    fn from(pr: open_prs::PrState) -> Self {
+        Self {
+            number: pr.number as u64,
+            title: pr.title,
+            body: pr.body,
+            state: pr.state,
+            is_draft: pr.is_draft,
+            merged: pr.merged,
+            base_ref_name: pr.base_ref_name,
+            head_ref_name: pr.head_ref_name,
+            head_ref_oid: pr.head_ref_oid,
+            merge_commit: pr.merge_commit.map(|commit| GitObject { oid: commit.oid }),
+            mergeable: Some(pr.mergeable),
+            merge_state_status: Some(pr.merge_state_status),
+            ..Default::default()
+        }
+    }
+}
+
+/// The nodes of a generated connection, without GitHub's null nodes
+pub fn nodes<T>(nodes: Option<Vec<Option<T>>>) -> impl Iterator<Item = T> {
+    nodes.into_iter().flatten().flatten()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn test_decode_aliases() {
+        let response: Response<RepositoryData<Aliases<PullRequest>>> = serde_json::from_value(json!({
+            "data": { "repository": {
+                "pr0": { "number": 12, "state": "OPEN", "reviewThreads": { "nodes": [null, { "isResolved": true }] } },
+                "pr1": null,
+            }},
+            "errors": [{ "message": "Could not resolve to a PullRequest with the number of 13." }],
+        }))
+        .unwrap();
+
+        let prs = response.into_data("test").unwrap().repository.unwrap();
+        let pr = prs["pr0"].as_ref().unwrap();
+        assert_eq!(pr.number, 12);
+        assert_eq!(pr.review_threads.nodes().count(), 1);
+        assert!(pr.status_check_rollup().is_none());
+        assert!(prs["pr1"].is_none());
+
+        let failed: Response<RepositoryData<Node>> =
+            serde_json::from_value(json!({ "errors": [{ "message": "Bad credentials" }] }))
+                .unwrap();
+        assert!(
+            failed
+                .into_data("Failed to query")
+                .unwrap_err()
+                .to_string()
+                .contains("Bad credentials")
+        );
+    }
+
+    #[test]
+    fn test_pr_state_fragment_checked() {
+        // Batched queries use the fragment `OpenPrs` was generated with
+        assert!(include_str!("graphql/open_prs.graphql").ends_with(PR_STATE_FRAGMENT));
+    }
+
+    #[test]
+    fn test_batched_variables() {
+        let prs = Aliased::new("pr", "Int!", [12u64, 13]);
+        assert_eq!(prs.declarations(), "$pr0: Int!, $pr1: Int!");
+        assert_eq!(
+            prs.selections(|alias| format!("{alias}: pullRequest(number: ${alias}) {{ id }}")),
+            "pr0: pullRequest(number: $pr0) { id }\npr1: pullRequest(number: $pr1) { id }"
+        );
+
+        let request = Request::new(
+            "query",
+            RepositoryVariables {
+                owner: "acme",
+                name: "widgets",
+                variables: prs,
+            },
+        );
+        assert_eq!(
+            serde_json::to_value(&request).unwrap(),
+            json!({
+                "query": "query",
+                "variables": { "owner": "acme", "name": "widgets", "pr0": 12, "pr1": 13 },
+            })
+        );
+
+        let request = Request::new(
+            "query",
+            RepositoryVariables {
+                owner: "acme",
+                name: "widgets",
+                variables: (),
+            },
+        );
+        assert_eq!(
+            serde_json::to_value(&request).unwrap()["variables"],
+            json!({ "owner": "acme", "name": "widgets" })
+        );
+    }
+}
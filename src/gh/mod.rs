@@ -1,3 +1,10 @@
+pub mod azure_devops;
+pub mod bitbucket;
 pub mod client;
+pub mod errors;
+pub mod forge;
+pub mod graphql;
 pub mod mutations;
+pub mod proxy;
 pub mod queries;
+pub mod retry;
@@ -1,12 +1,38 @@
+use crate::gh::client::Client;
+use crate::gh::errors::{self, GitHubContext};
+use crate::gh::{forge, graphql, queries, retry};
+use crate::profile;
 use crate::ui::callout;
 use anyhow::{Context, Result};
-use octocrab::Octocrab;
-use serde_json::json;
-use std::time::Duration;
+use serde::Serialize;
+use std::collections::HashMap;
+use tracing::{debug, instrument};
+
+/// Outcome of `merge_pull_request`
+#[derive(Debug)]
+pub enum MergeOutcome {
+    /// The forge accepted the merge (it may still be queued, or wait to auto-complete)
+    Initiated,
+    /// Branch protection isn't satisfied yet, with the forge's message
+    Blocked(String),
+}
+
+/// Outcome of `create_pull_request`
+#[derive(Debug)]
+pub enum CreatedPr {
+    /// A new PR was opened
+    New(u64),
+    /// GitHub already had an open PR for the head branch (opened by hand or from
+    /// another clone); it was left as is
+    Existing(queries::PrInfo),
+}
 
-/// Create a new pull request with retry logic for race conditions
+/// Create a new pull request with retry logic for race conditions. If a PR is
+/// already open for `head`, that one is returned instead of failing.
+#[allow(clippy::too_many_arguments)]
+#[instrument(level = "debug", skip(client, title, body))]
 pub async fn create_pull_request(
-    client: &Octocrab,
+    client: &Client,
     owner: &str,
     repo: &str,
     head: &str,
@@ -14,56 +40,71 @@ pub async fn create_pull_request(
     title: &str,
     body: &str,
     draft: bool,
-) -> Result<u64> {
-    // Retry up to 3 times with exponential backoff to handle race conditions
-    // where GitHub hasn't fully processed the pushed branch yet
-    let max_retries = 3;
-    let mut last_error_msg = String::new();
-
-    for attempt in 0..max_retries {
-        // Add a small delay on retries to give GitHub time to process the push
-        if attempt > 0 {
-            let delay_ms = 1000 * (2_u64.pow(attempt - 1)); // 1s, 2s
-            tokio::time::sleep(Duration::from_millis(delay_ms)).await;
-        }
-
-        // Use REST API since octocrab's GraphQL support is limited
-        match client
-            .pulls(owner, repo)
-            .create(title, head, base)
-            .body(body)
-            .draft(draft)
-            .send()
-            .await
+) -> Result<CreatedPr> {
+    let _span = profile::span("gh: create_pull_request");
+    if let Some(api) = client.api() {
+        let result = api
+            .create_pull_request(client, owner, repo, head, base, title, body, draft)
+            .await;
+        // Creating fails when the branch already has an open PR
+        if result.is_err()
+            && let Some(existing) = api
+                .find_prs_by_head(client, owner, repo, head, true)
+                .await?
+                .into_iter()
+                .next()
         {
-            Ok(pr) => return Ok(pr.number),
-            Err(e) => {
-                // Capture detailed error information
-                last_error_msg = format!("{:#?}", e);
-
-                // Check if it's a retryable error
-                let error_str = format!("{:?}", e);
-                if error_str.contains("422") || error_str.contains("Validation Failed") {
-                    // 422 is validation error - not retryable
-                    break;
-                }
-                // Continue to next retry for other errors
-            }
+            debug!(pr = existing.number, head, "PR already exists for head");
+            return Ok(CreatedPr::Existing(existing));
         }
+        client.forget_head(owner, repo, head);
+        return Ok(CreatedPr::New(result?));
+    }
+    // Retry transient failures with exponential backoff (see `retry`), and 404s,
+    // which GitHub returns while it hasn't fully processed the pushed branch yet.
+    // A validation error (422, e.g. "already exists") or a permission error is final
+    let result = retry::with_retry_if(
+        client.retry_policy(),
+        "create PR",
+        |e| retry::is_transient(e) || is_not_found(e),
+        || async {
+            // Use REST API since octocrab's GraphQL support is limited
+            client
+                .pulls(owner, repo)
+                .create(title, head, base)
+                .body(body)
+                .draft(draft)
+                .send()
+                .await
+        },
+    )
+    .await;
+
+    if let Err(e) = &result
+        && errors::is_pr_already_exists(e)
+        && let Some(existing) = queries::find_pr_by_head(client, owner, repo, head).await?
+    {
+        debug!(pr = existing.number, head, "PR already exists for head");
+        return Ok(CreatedPr::Existing(existing));
     }
 
-    // All retries failed - provide detailed error info
-    Err(anyhow::anyhow!(
-        "Failed to create pull request for {}/{}. Head: {}, Base: {}.\n\n\
-        GitHub API error:\n{}\n\n\
-        Make sure the head branch has been pushed to the remote and you have write access to the repository.",
-        owner, repo, head, base, last_error_msg
-    ))
+    let pr = result.gh_context(&format!(
+        "Failed to create pull request {} → {} in {}/{}",
+        head, base, owner, repo
+    ))?;
+    client.forget_head(owner, repo, head);
+    Ok(CreatedPr::New(pr.number))
+}
+
+/// Whether GitHub answered 404, e.g. for a branch it hasn't processed yet
+fn is_not_found(error: &octocrab::Error) -> bool {
+    matches!(error, octocrab::Error::GitHub { source, .. } if source.status_code.as_u16() == 404)
 }
 
 /// Update an existing pull request
+#[instrument(level = "debug", skip(client, body), fields(has_body = body.is_some()))]
 pub async fn update_pull_request(
-    client: &Octocrab,
+    client: &Client,
     owner: &str,
     repo: &str,
     pr_number: u64,
@@ -71,213 +112,614 @@ pub async fn update_pull_request(
     title: Option<&str>,
     body: Option<&str>,
 ) -> Result<()> {
+    let _span = profile::span("gh: update_pull_request");
+    if let Some(api) = client.api() {
+        return api
+            .update_pull_request(client, owner, repo, pr_number, base, title, body)
+            .await;
+    }
     // Use REST API to update PR
-    let pulls = client.pulls(owner, repo);
-    let mut update = pulls.update(pr_number);
+    retry::with_retry(client.retry_policy(), "update PR", || async {
+        let pulls = client.pulls(owner, repo);
+        let mut update = pulls.update(pr_number);
 
-    if let Some(base_ref) = base {
-        update = update.base(base_ref);
-    }
+        if let Some(base_ref) = base {
+            update = update.base(base_ref);
+        }
 
-    if let Some(pr_title) = title {
-        update = update.title(pr_title);
-    }
+        if let Some(pr_title) = title {
+            update = update.title(pr_title);
+        }
 
-    if let Some(pr_body) = body {
-        update = update.body(pr_body);
-    }
+        if let Some(pr_body) = body {
+            update = update.body(pr_body);
+        }
 
-    update
-        .send()
-        .await
-        .context("Failed to update pull request")?;
+        update.send().await
+    })
+    .await
+    .gh_context("Failed to update pull request")?;
 
     Ok(())
 }
 
 /// Batch update multiple PR bases in a single GraphQL mutation
 /// Updates are executed in the order provided (important for maintaining chain integrity)
+#[instrument(level = "debug", skip(client))]
 pub async fn batch_update_pr_bases(
-    client: &Octocrab,
+    client: &Client,
     owner: &str,
     repo: &str,
     updates: &[(u64, String)], // Vec of (pr_number, new_base)
 ) -> Result<()> {
-    if updates.is_empty() {
+    let _span = profile::span("gh: batch_update_pr_bases");
+    if let Some(api) = client.api() {
+        // No batch mutation over REST: one request per PR, in order
+        for (pr_number, new) in updates {
+            api.update_pull_request(
+                client,
+                owner,
+                repo,
+                *pr_number,
+                Some(new.as_str()),
+                None,
+                None,
+            )
+            .await?;
+        }
         return Ok(());
     }
+    let inputs = updates
+        .iter()
+        .map(|(pr_number, base)| {
+            (
+                *pr_number,
+                UpdatePullRequestInput {
+                    base_ref_name: Some(base.clone()),
+                    ..Default::default()
+                },
+            )
+        })
+        .collect();
+    update_pull_requests(
+        client,
+        owner,
+        repo,
+        inputs,
+        "Failed to execute batched PR base updates",
+    )
+    .await
+}
 
-    // First, get PR node IDs for all PRs (required for GraphQL mutations)
-    let pr_node_ids = get_pr_node_ids(client, owner, repo, updates).await?;
+/// Batch update multiple PR bodies in a single GraphQL mutation
+#[instrument(level = "debug", skip(client, updates), fields(prs = ?updates.iter().map(|(n, _)| n).collect::<Vec<_>>()))]
+pub async fn batch_update_pr_bodies(
+    client: &Client,
+    owner: &str,
+    repo: &str,
+    updates: &[(u64, String)], // Vec of (pr_number, new_body)
+) -> Result<()> {
+    let _span = profile::span("gh: batch_update_pr_bodies");
+    if let Some(api) = client.api() {
+        // No batch mutation over REST: one request per PR, in order
+        for (pr_number, new) in updates {
+            api.update_pull_request(
+                client,
+                owner,
+                repo,
+                *pr_number,
+                None,
+                None,
+                Some(new.as_str()),
+            )
+            .await?;
+        }
+        return Ok(());
+    }
+    let inputs = updates
+        .iter()
+        .map(|(pr_number, body)| {
+            (
+                *pr_number,
+                UpdatePullRequestInput {
+                    body: Some(body.clone()),
+                    ..Default::default()
+                },
+            )
+        })
+        .collect();
+    update_pull_requests(client, owner, repo, inputs, "Failed to update PR bodies")
+        .await
+        .context("Failed to execute batched PR body updates")
+}
 
-    // Build GraphQL mutation with all updates
-    let mut mutation_parts = Vec::new();
-    for (idx, (pr_number, new_base)) in updates.iter().enumerate() {
-        let node_id = pr_node_ids
-            .get(pr_number)
-            .ok_or_else(|| anyhow::anyhow!("Failed to get node ID for PR #{}", pr_number))?;
-
-        mutation_parts.push(format!(
-            r#"
-            update{}: updatePullRequest(input: {{
-                pullRequestId: "{}"
-                baseRefName: "{}"
-            }}) {{
-                pullRequest {{
-                    number
-                }}
-            }}
-            "#,
-            idx, node_id, new_base
-        ));
+/// Batch update multiple PR titles in a single GraphQL mutation
+#[instrument(level = "debug", skip(client, updates), fields(prs = ?updates.iter().map(|(n, _)| n).collect::<Vec<_>>()))]
+pub async fn batch_update_pr_titles(
+    client: &Client,
+    owner: &str,
+    repo: &str,
+    updates: &[(u64, String)], // Vec of (pr_number, new_title)
+) -> Result<()> {
+    let _span = profile::span("gh: batch_update_pr_titles");
+    if let Some(api) = client.api() {
+        // No batch mutation over REST: one request per PR, in order
+        for (pr_number, new) in updates {
+            api.update_pull_request(
+                client,
+                owner,
+                repo,
+                *pr_number,
+                None,
+                Some(new.as_str()),
+                None,
+            )
+            .await?;
+        }
+        return Ok(());
     }
+    let inputs = updates
+        .iter()
+        .map(|(pr_number, title)| {
+            (
+                *pr_number,
+                UpdatePullRequestInput {
+                    title: Some(title.clone()),
+                    ..Default::default()
+                },
+            )
+        })
+        .collect();
+    update_pull_requests(client, owner, repo, inputs, "Failed to update PR titles")
+        .await
+        .context("Failed to execute batched PR title updates")
+}
 
-    let mutation = format!("mutation {{ {} }}", mutation_parts.join("\n"));
+/// GraphQL's `UpdatePullRequestInput`; fields left out aren't changed
+#[derive(Debug, Clone, Default, Serialize)]
+#[serde(rename_all = "camelCase")]
+struct UpdatePullRequestInput {
+    pull_request_id: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    base_ref_name: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    title: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    body: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    milestone_id: Option<String>,
+}
 
-    // Execute the batched mutation
-    let response: serde_json::Value = client
-        .graphql(&json!({ "query": mutation }))
-        .await
-        .context("Failed to execute batched PR base updates")?;
-
-    // Check for errors in the response
-    if let Some(errors) = response.get("errors") {
-        return Err(anyhow::anyhow!(
-            "GraphQL mutation failed: {}",
-            serde_json::to_string_pretty(errors)?
-        ));
+/// GraphQL's `AddProjectV2ItemByIdInput`
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+struct AddProjectV2ItemByIdInput {
+    project_id: String,
+    content_id: String,
+}
+
+/// Apply `updatePullRequest` to several PRs in a single mutation, in the order given
+async fn update_pull_requests(
+    client: &Client,
+    owner: &str,
+    repo: &str,
+    mut inputs: Vec<(u64, UpdatePullRequestInput)>,
+    action: &str,
+) -> Result<()> {
+    if inputs.is_empty() {
+        return Ok(());
     }
 
+    // PR node IDs are required for GraphQL mutations
+    let pr_numbers: Vec<u64> = inputs.iter().map(|(num, _)| *num).collect();
+    let pr_node_ids = get_pr_node_ids(client, owner, repo, &pr_numbers).await?;
+    for (pr_number, input) in &mut inputs {
+        input.pull_request_id = pr_node_ids
+            .get(pr_number)
+            .ok_or_else(|| anyhow::anyhow!("Failed to get node ID for PR #{}", pr_number))?
+            .clone();
+    }
+
+    // One mutation per MAX_BATCH PRs, in order
+    for inputs in inputs.chunks(graphql::MAX_BATCH) {
+        let updates = graphql::Aliased::new(
+            "update",
+            "UpdatePullRequestInput!",
+            inputs.iter().map(|(_, input)| input),
+        );
+        let mutation = format!(
+            "mutation UpdatePrs({}) {{\n{}\n}}",
+            updates.declarations(),
+            updates.selections(|alias| format!(
+                "  {alias}: updatePullRequest(input: ${alias}) {{ pullRequest {{ number }} }}"
+            ))
+        );
+        graphql::Request::new(mutation, updates)
+            .mutate(client, action, true)
+            .await?;
+    }
     Ok(())
 }
 
-/// Batch update multiple PR bodies in a single GraphQL mutation
-pub async fn batch_update_pr_bodies(
-    client: &Octocrab,
+/// Attach multiple PRs to a milestone and/or a ProjectV2 in a single GraphQL mutation
+#[instrument(level = "debug", skip(client))]
+pub async fn batch_attach_prs(
+    client: &Client,
     owner: &str,
     repo: &str,
-    updates: &[(u64, String)], // Vec of (pr_number, new_body)
+    pr_numbers: &[u64],
+    milestone_id: Option<&str>,
+    project_id: Option<&str>,
 ) -> Result<()> {
-    if updates.is_empty() {
+    let _span = profile::span("gh: batch_attach_prs");
+    if pr_numbers.is_empty() || (milestone_id.is_none() && project_id.is_none()) {
         return Ok(());
     }
+    if let Some(api) = client.api() {
+        return Err(forge::unsupported(api.forge(), "Milestones and projects"));
+    }
 
-    // First, get PR node IDs for all PRs (required for GraphQL mutations)
-    let pr_node_ids = get_pr_node_ids(client, owner, repo, updates).await?;
-
-    // Build GraphQL mutation with all updates
-    let mut mutation_parts = Vec::new();
-    for (idx, (pr_number, new_body)) in updates.iter().enumerate() {
-        let node_id = pr_node_ids
-            .get(pr_number)
-            .ok_or_else(|| anyhow::anyhow!("Failed to get node ID for PR #{}", pr_number))?;
-
-        // Escape the body for GraphQL
-        let escaped_body = escape_graphql_string(new_body);
-
-        mutation_parts.push(format!(
-            r#"
-            update{}: updatePullRequest(input: {{
-                pullRequestId: "{}"
-                body: "{}"
-            }}) {{
-                pullRequest {{
-                    number
-                }}
-            }}
-            "#,
-            idx, node_id, escaped_body
-        ));
+    let pr_node_ids = get_pr_node_ids(client, owner, repo, pr_numbers).await?;
+    let node_ids = pr_numbers
+        .iter()
+        .map(|pr_number| {
+            pr_node_ids
+                .get(pr_number)
+                .cloned()
+                .ok_or_else(|| anyhow::anyhow!("Failed to get node ID for PR #{}", pr_number))
+        })
+        .collect::<Result<Vec<String>>>()?;
+
+    for node_ids in node_ids.chunks(graphql::MAX_BATCH) {
+        attach_prs(client, node_ids, milestone_id, project_id).await?;
     }
+    Ok(())
+}
 
-    let mutation = format!("mutation {{ {} }}", mutation_parts.join("\n"));
+async fn attach_prs(
+    client: &Client,
+    node_ids: &[String],
+    milestone_id: Option<&str>,
+    project_id: Option<&str>,
+) -> Result<()> {
+    let variables = AttachVariables {
+        milestones: graphql::Aliased::new(
+            "milestone",
+            "UpdatePullRequestInput!",
+            milestone_id.into_iter().flat_map(|milestone_id| {
+                node_ids.iter().map(move |node_id| UpdatePullRequestInput {
+                    pull_request_id: node_id.clone(),
+                    milestone_id: Some(milestone_id.to_string()),
+                    ..Default::default()
+                })
+            }),
+        ),
+        projects: graphql::Aliased::new(
+            "project",
+            "AddProjectV2ItemByIdInput!",
+            project_id.into_iter().flat_map(|project_id| {
+                node_ids
+                    .iter()
+                    .map(move |node_id| AddProjectV2ItemByIdInput {
+                        project_id: project_id.to_string(),
+                        content_id: node_id.clone(),
+                    })
+            }),
+        ),
+    };
+
+    let declarations: Vec<String> = [
+        &variables.milestones.declarations(),
+        &variables.projects.declarations(),
+    ]
+    .into_iter()
+    .filter(|d| !d.is_empty())
+    .cloned()
+    .collect();
+    let mutation = format!(
+        "mutation AttachPrs({}) {{\n{}\n{}\n}}",
+        declarations.join(", "),
+        variables.milestones.selections(|alias| format!(
+            "  {alias}: updatePullRequest(input: ${alias}) {{ pullRequest {{ number }} }}"
+        )),
+        variables.projects.selections(|alias| format!(
+            "  {alias}: addProjectV2ItemById(input: ${alias}) {{ item {{ id }} }}"
+        ))
+    );
 
-    // Execute the batched mutation
-    let response: serde_json::Value = client
-        .graphql(&json!({ "query": mutation }))
+    graphql::Request::new(mutation, variables)
+        .mutate(client, "Failed to attach PRs to milestone/project", true)
         .await
-        .context("Failed to execute batched PR body updates")?;
-
-    // Check for errors in the response
-    if let Some(errors) = response.get("errors") {
-        return Err(anyhow::anyhow!(
-            "GraphQL mutation failed: {}",
-            serde_json::to_string_pretty(errors)?
-        ));
+}
+
+/// Variables of the `batch_attach_prs` mutation
+#[derive(Serialize)]
+struct AttachVariables {
+    #[serde(flatten)]
+    milestones: graphql::Aliased<UpdatePullRequestInput>,
+    #[serde(flatten)]
+    projects: graphql::Aliased<AddProjectV2ItemByIdInput>,
+}
+
+/// Verdict of a submitted PR review, as GraphQL's `PullRequestReviewEvent`
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "SCREAMING_SNAKE_CASE")]
+pub enum ReviewEvent {
+    Approve,
+    RequestChanges,
+    Comment,
+}
+
+/// Submit a review on a pull request
+///
+/// Not retried: a retry after a timed-out but applied request would submit a second review.
+#[instrument(level = "debug", skip(client, body))]
+pub async fn submit_pr_review(
+    client: &Client,
+    owner: &str,
+    repo: &str,
+    pr_number: u64,
+    event: ReviewEvent,
+    body: Option<&str>,
+) -> Result<()> {
+    let _span = profile::span("gh: submit_pr_review");
+    if let Some(api) = client.api() {
+        return Err(forge::unsupported(api.forge(), "Reviews"));
     }
+    let pr_node_ids = get_pr_node_ids(client, owner, repo, &[pr_number]).await?;
+    let node_id = pr_node_ids
+        .get(&pr_number)
+        .ok_or_else(|| anyhow::anyhow!("Failed to get node ID for PR #{}", pr_number))?;
+
+    let variables = graphql::add_review::Variables {
+        pull_request_id: node_id.clone(),
+        event,
+        body: body.map(str::to_string),
+    };
+    graphql::mutate::<graphql::AddReview>(
+        client,
+        variables,
+        "Failed to submit pull request review",
+        false,
+    )
+    .await
+}
 
-    Ok(())
+/// Mark a review thread as resolved
+#[instrument(level = "debug", skip(client))]
+pub async fn resolve_review_thread(client: &Client, thread_id: &str) -> Result<()> {
+    let _span = profile::span("gh: resolve_review_thread");
+    if let Some(api) = client.api() {
+        return Err(forge::unsupported(api.forge(), "Review threads"));
+    }
+
+    // Resolving is idempotent, so retrying is safe
+    let variables = graphql::resolve_thread::Variables {
+        thread_id: thread_id.to_string(),
+    };
+    graphql::mutate::<graphql::ResolveThread>(
+        client,
+        variables,
+        "Failed to resolve review thread",
+        true,
+    )
+    .await
 }
 
-/// Escape a string for use in a GraphQL query
-fn escape_graphql_string(s: &str) -> String {
-    s.replace('\\', "\\\\")
-        .replace('"', "\\\"")
-        .replace('\n', "\\n")
-        .replace('\r', "\\r")
-        .replace('\t', "\\t")
+/// Turn an open pull request back into a draft
+#[instrument(level = "debug", skip(client))]
+pub async fn convert_to_draft(
+    client: &Client,
+    owner: &str,
+    repo: &str,
+    pr_number: u64,
+) -> Result<()> {
+    let _span = profile::span("gh: convert_to_draft");
+    if let Some(api) = client.api() {
+        return api.convert_to_draft(client, owner, repo, pr_number).await;
+    }
+    let pr_node_ids = get_pr_node_ids(client, owner, repo, &[pr_number]).await?;
+    let node_id = pr_node_ids
+        .get(&pr_number)
+        .ok_or_else(|| anyhow::anyhow!("Failed to get node ID for PR #{}", pr_number))?;
+
+    // Converting an already-draft PR is a no-op, so retrying is safe
+    let variables = graphql::convert_to_draft::Variables {
+        pull_request_id: node_id.clone(),
+    };
+    let action = format!("Failed to convert PR #{} to draft", pr_number);
+    graphql::mutate::<graphql::ConvertToDraft>(client, variables, &action, true).await
 }
 
-/// Close a pull request
 /// Add a comment to a pull request
+#[instrument(level = "debug", skip(client, body))]
 pub async fn add_pr_comment(
-    client: &Octocrab,
+    client: &Client,
     owner: &str,
     repo: &str,
     pr_number: u64,
     body: &str,
 ) -> Result<()> {
+    let _span = profile::span("gh: add_pr_comment");
+    if let Some(api) = client.api() {
+        return api
+            .add_pr_comment(client, owner, repo, pr_number, body)
+            .await;
+    }
     client
         .issues(owner, repo)
         .create_comment(pr_number, body)
         .await
-        .context("Failed to add comment to pull request")?;
+        .gh_context("Failed to add comment to pull request")?;
+
+    Ok(())
+}
+
+/// Assign users to a pull request
+#[instrument(level = "debug", skip(client))]
+pub async fn add_assignees(
+    client: &Client,
+    owner: &str,
+    repo: &str,
+    pr_number: u64,
+    assignees: &[String],
+) -> Result<()> {
+    let _span = profile::span("gh: add_assignees");
+    if let Some(api) = client.api() {
+        return Err(forge::unsupported(api.forge(), "Assignees"));
+    }
+    let assignees: Vec<&str> = assignees.iter().map(String::as_str).collect();
+
+    retry::with_retry(client.retry_policy(), "add assignees", || async {
+        client
+            .issues(owner, repo)
+            .add_assignees(pr_number, &assignees)
+            .await
+    })
+    .await
+    .gh_context(&format!("Failed to assign PR #{}", pr_number))?;
+
+    Ok(())
+}
+
+/// Add labels to a pull request
+#[instrument(level = "debug", skip(client))]
+pub async fn add_labels(
+    client: &Client,
+    owner: &str,
+    repo: &str,
+    pr_number: u64,
+    labels: &[String],
+) -> Result<()> {
+    let _span = profile::span("gh: add_labels");
+    if let Some(api) = client.api() {
+        return api.add_labels(client, owner, repo, pr_number, labels).await;
+    }
+
+    retry::with_retry(client.retry_policy(), "add labels", || async {
+        client
+            .issues(owner, repo)
+            .add_labels(pr_number, labels)
+            .await
+    })
+    .await
+    .gh_context(&format!("Failed to label PR #{}", pr_number))?;
+
+    Ok(())
+}
+
+/// Update an existing PR comment
+#[instrument(level = "debug", skip(client, body))]
+pub async fn update_pr_comment(
+    client: &Client,
+    owner: &str,
+    repo: &str,
+    comment_id: u64,
+    body: &str,
+) -> Result<()> {
+    let _span = profile::span("gh: update_pr_comment");
+    if let Some(api) = client.api() {
+        return Err(forge::unsupported(api.forge(), "Comment edits by id"));
+    }
+    retry::with_retry(client.retry_policy(), "update comment", || async {
+        client
+            .issues(owner, repo)
+            .update_comment(comment_id.into(), body)
+            .await
+    })
+    .await
+    .gh_context("Failed to update pull request comment")?;
 
     Ok(())
 }
 
+/// Add a comment to a PR, or edit it in place if the authenticated user already
+/// wrote one containing `marker` (anyone can paste the marker into a comment,
+/// and only the author's comments can be edited anyway)
+pub async fn upsert_pr_comment(
+    client: &Client,
+    owner: &str,
+    repo: &str,
+    pr_number: u64,
+    marker: &str,
+    body: &str,
+) -> Result<()> {
+    if let Some(api) = client.api() {
+        return api
+            .upsert_pr_comment(client, owner, repo, pr_number, marker, body)
+            .await;
+    }
+    let viewer = queries::get_viewer(client).await?;
+    match queries::find_comment_with_marker(client, owner, repo, pr_number, marker, &viewer).await?
+    {
+        Some(comment_id) => update_pr_comment(client, owner, repo, comment_id, body).await,
+        None => add_pr_comment(client, owner, repo, pr_number, body).await,
+    }
+}
+
+/// Close a pull request
+#[instrument(level = "debug", skip(client))]
 pub async fn close_pull_request(
-    client: &Octocrab,
+    client: &Client,
     owner: &str,
     repo: &str,
     pr_number: u64,
 ) -> Result<()> {
+    let _span = profile::span("gh: close_pull_request");
+    if let Some(api) = client.api() {
+        let pr = queries::get_pr(client, owner, repo, pr_number).await?;
+        let clean_body = callout::strip_callout(&pr.body);
+        let body = (!clean_body.is_empty()).then_some(clean_body.as_str());
+        return api
+            .close_pull_request(client, owner, repo, pr_number, body)
+            .await;
+    }
     // Get current PR to retrieve the body
-    let pr = client
-        .pulls(owner, repo)
-        .get(pr_number)
-        .await
-        .context("Failed to get pull request")?;
+    let pr = retry::with_retry(client.retry_policy(), "get PR", || async {
+        client.pulls(owner, repo).get(pr_number).await
+    })
+    .await
+    .gh_context("Failed to get pull request")?;
 
     // Strip the callout from the body
     let body = pr.body.unwrap_or_default();
     let clean_body = callout::strip_callout(&body);
 
     // Close the PR and update body to remove callout
-    let pulls = client.pulls(owner, repo);
-    let mut update = pulls.update(pr_number).state(octocrab::params::pulls::State::Closed);
-
-    if !clean_body.is_empty() {
-        update = update.body(&clean_body);
-    }
+    retry::with_retry(client.retry_policy(), "close PR", || async {
+        let pulls = client.pulls(owner, repo);
+        let mut update = pulls
+            .update(pr_number)
+            .state(octocrab::params::pulls::State::Closed);
+
+        if !clean_body.is_empty() {
+            update = update.body(&clean_body);
+        }
 
-    update
-        .send()
-        .await
-        .context("Failed to close pull request")?;
+        update.send().await
+    })
+    .await
+    .gh_context("Failed to close pull request")?;
 
     Ok(())
 }
 
 /// Merge a pull request with optional custom commit message
+#[instrument(level = "debug", skip(client, commit_message))]
 pub async fn merge_pull_request(
-    client: &Octocrab,
+    client: &Client,
     owner: &str,
     repo: &str,
     pr_number: u64,
     commit_title: Option<&str>,
     commit_message: Option<&str>,
-) -> Result<()> {
+) -> Result<MergeOutcome> {
+    let _span = profile::span("gh: merge_pull_request");
+    if let Some(api) = client.api() {
+        return api
+            .merge_pull_request(client, owner, repo, pr_number, commit_title, commit_message)
+            .await;
+    }
     // Use REST API to merge the PR
     let pulls = client.pulls(owner, repo);
     let mut merge_builder = pulls.merge(pr_number);
@@ -290,69 +732,73 @@ pub async fn merge_pull_request(
         merge_builder = merge_builder.message(message);
     }
 
-    merge_builder
-        .send()
-        .await
-        .context("Failed to merge pull request")?;
+    let result = merge_builder.send().await;
+    if let Err(e) = &result
+        && let Some(message) = errors::merge_blocked(e)
+    {
+        debug!(pr_number, message, "merge blocked");
+        return Ok(MergeOutcome::Blocked(message));
+    }
+    result.gh_context("Failed to merge pull request")?;
 
-    Ok(())
+    Ok(MergeOutcome::Initiated)
 }
 
 /// Helper function to get PR node IDs for GraphQL mutations
+#[instrument(level = "debug", skip(client))]
 async fn get_pr_node_ids(
-    client: &Octocrab,
+    client: &Client,
     owner: &str,
     repo: &str,
-    updates: &[(u64, String)],
-) -> Result<std::collections::HashMap<u64, String>> {
-    use std::collections::HashMap;
-
-    // Build a query to fetch all PR node IDs at once
-    let pr_numbers: Vec<u64> = updates.iter().map(|(num, _)| *num).collect();
-    let mut pr_queries = Vec::new();
-
-    for (idx, pr_number) in pr_numbers.iter().enumerate() {
-        pr_queries.push(format!(
-            r#"
-            pr{}: pullRequest(number: {}) {{
-                id
-                number
-            }}
-            "#,
-            idx, pr_number
-        ));
-    }
-
-    let query = format!(
-        r#"
-        query {{
-            repository(owner: "{}", name: "{}") {{
-                {}
-            }}
-        }}
-        "#,
-        owner,
-        repo,
-        pr_queries.join("\n")
-    );
-
-    let response: serde_json::Value = client
-        .graphql(&json!({ "query": query }))
-        .await
-        .context("Failed to fetch PR node IDs")?;
+    pr_numbers: &[u64],
+) -> Result<HashMap<u64, String>> {
+    let _span = profile::span("gh: get_pr_node_ids");
 
-    // Parse the response to extract node IDs
     let mut node_ids = HashMap::new();
-    if let Some(repo_data) = response.get("data").and_then(|d| d.get("repository")) {
-        for (idx, pr_number) in pr_numbers.iter().enumerate() {
-            let pr_key = format!("pr{}", idx);
-            if let Some(pr_data) = repo_data.get(&pr_key) {
-                if let Some(node_id) = pr_data.get("id").and_then(|v| v.as_str()) {
-                    node_ids.insert(*pr_number, node_id.to_string());
-                }
-            }
-        }
+    for pr_numbers in pr_numbers.chunks(graphql::MAX_BATCH) {
+        node_ids.extend(get_pr_node_ids_chunk(client, owner, repo, pr_numbers).await?);
     }
-
     Ok(node_ids)
 }
+
+/// Fetch the node IDs of up to MAX_BATCH PRs at once
+async fn get_pr_node_ids_chunk(
+    client: &Client,
+    owner: &str,
+    repo: &str,
+    pr_numbers: &[u64],
+) -> Result<HashMap<u64, String>> {
+    let prs = graphql::Aliased::new("pr", "Int!", pr_numbers.iter().copied());
+    let query = format!(
+        "query PrNodeIds($owner: String!, $name: String!, {}) {{\n  repository(owner: $owner, name: $name) {{\n{}\n  }}\n}}",
+        prs.declarations(),
+        prs.selections(|alias| format!("    {alias}: pullRequest(number: ${alias}) {{ id }}"))
+    );
+    let request = graphql::Request::new(
+        query,
+        graphql::RepositoryVariables {
+            owner,
+            name: repo,
+            variables: prs,
+        },
+    );
+    let mut nodes = request
+        .query::<graphql::RepositoryData<graphql::Aliases<graphql::Node>>>(
+            client,
+            "Failed to fetch PR node IDs",
+        )
+        .await?
+        .repository
+        .unwrap_or_default();
+
+    Ok(pr_numbers
+        .iter()
+        .enumerate()
+        .filter_map(|(idx, pr_number)| {
+            Some((
+                *pr_number,
+                nodes.remove(&graphql::alias("pr", idx)).flatten()?.id,
+            ))
+        })
+        .collect())
+}
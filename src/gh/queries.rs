@@ -1,9 +1,13 @@
-use crate::model::PrState;
+use crate::gh::client::Client;
+use crate::gh::forge::{self, ForgeApi};
+use crate::gh::{graphql, retry};
+use crate::model::{CiStatus, PrState};
+use crate::profile;
 use anyhow::{Context, Result};
-use octocrab::Octocrab;
 use octocrab::models::pulls::PullRequest;
-use serde_json::json;
+use serde::{Deserialize, Serialize};
 use std::collections::HashMap;
+use tracing::{debug, instrument};
 
 /// Information about a pull request
 #[derive(Debug, Clone)]
@@ -14,26 +18,162 @@ pub struct PrInfo {
     pub body: String,
     pub base_ref: String,
     pub head_ref: String,
+    /// SHA of the head commit, abbreviated on Bitbucket: resolve it with
+    /// `repo::resolve_sha`
     pub head_sha: String,
+    /// SHA of the commit created on the base branch when the PR was merged
+    pub merge_commit_sha: Option<String>,
+    /// Whether GitHub reports conflicts with the base branch
+    pub has_conflicts: bool,
+    /// GitHub reports the PR out of date with its base branch
+    pub behind_base: bool,
+    /// Review threads not marked resolved (0 when not fetched)
+    pub unresolved_threads: usize,
+    /// REVIEW_REQUIRED, CHANGES_REQUESTED or APPROVED (None when not fetched)
+    pub review_decision: Option<String>,
+    /// Combined status of the head commit's checks (None when not fetched or no checks)
+    pub ci_status: Option<CiStatus>,
+}
+
+#[cfg(test)]
+impl PrInfo {
+    /// Open PR `number` from `head_ref` into `base_ref`, for tests to adjust
+    pub fn for_test(number: u64, base_ref: &str, head_ref: &str) -> Self {
+        PrInfo {
+            number,
+            state: PrState::Open,
+            title: format!("PR {}", number),
+            body: String::new(),
+            base_ref: base_ref.to_string(),
+            head_ref: head_ref.to_string(),
+            head_sha: String::new(),
+            merge_commit_sha: None,
+            has_conflicts: false,
+            behind_base: false,
+            unresolved_threads: 0,
+            review_decision: None,
+            ci_status: None,
+        }
+    }
+}
+
+/// Get the login of the authenticated user
+#[instrument(level = "debug", skip(client))]
+pub async fn get_viewer(client: &Client) -> Result<String> {
+    let _span = profile::span("gh: get_viewer");
+    if let Some(api) = client.api() {
+        return api.get_viewer(client).await;
+    }
+    let user = retry::with_retry(client.retry_policy(), "get viewer", || async {
+        client.current().user().await
+    })
+    .await
+    .context("Failed to get the authenticated GitHub user")?;
+
+    Ok(user.login)
+}
+
+/// Resolve a milestone (by number or title) to its GraphQL node ID
+#[instrument(level = "debug", skip(client))]
+pub async fn find_milestone_id(
+    client: &Client,
+    owner: &str,
+    repo: &str,
+    milestone: &str,
+) -> Result<String> {
+    let _span = profile::span("gh: find_milestone_id");
+    if let Some(api) = client.api() {
+        return Err(forge::unsupported(api.forge(), "Milestones"));
+    }
+    let variables = graphql::milestones::Variables {
+        owner: owner.to_string(),
+        name: repo.to_string(),
+    };
+    let milestones =
+        graphql::query::<graphql::Milestones>(client, variables, "Failed to query milestones")
+            .await?
+            .repository
+            .and_then(|repository| repository.milestones)
+            .and_then(|milestones| milestones.nodes);
+
+    let number = milestone.parse::<i64>().ok();
+    graphql::nodes(milestones)
+        .find(|m| m.title == milestone || number == Some(m.number))
+        .map(|m| m.id)
+        .with_context(|| {
+            format!(
+                "Open milestone '{}' not found in {}/{}",
+                milestone, owner, repo
+            )
+        })
+}
+
+/// Resolve a ProjectV2 to its GraphQL node ID.
+/// Accepts a project number owned by `owner` (user or organization), or a node ID ("PVT_...").
+#[instrument(level = "debug", skip(client))]
+pub async fn find_project_id(client: &Client, owner: &str, project: &str) -> Result<String> {
+    let _span = profile::span("gh: find_project_id");
+    if let Some(api) = client.api() {
+        return Err(forge::unsupported(api.forge(), "Projects"));
+    }
+    if project.starts_with("PVT_") {
+        return Ok(project.to_string());
+    }
+
+    let number: i64 = project.parse().with_context(|| {
+        format!(
+            "Invalid project '{}': expected a project number or node ID",
+            project
+        )
+    })?;
+
+    use graphql::project::ProjectRepositoryOwner as Owner;
+    let variables = graphql::project::Variables {
+        owner: owner.to_string(),
+        number,
+    };
+    let project =
+        match graphql::query::<graphql::Project>(client, variables, "Failed to query project")
+            .await?
+            .repository_owner
+        {
+            Some(Owner::Organization(org)) => org.project_v2.map(|project| project.id),
+            Some(Owner::User(user)) => user.project_v2.map(|project| project.id),
+            None => None,
+        };
+    project.with_context(|| format!("Project #{} not found for {}", number, owner))
 }
 
 /// Look up a PR by head ref name
+#[instrument(level = "debug", skip(client))]
 pub async fn find_pr_by_head(
-    client: &Octocrab,
+    client: &Client,
     owner: &str,
     repo: &str,
     head_ref: &str,
 ) -> Result<Option<PrInfo>> {
+    let _span = profile::span("gh: find_pr_by_head");
+    if let Some(api) = client.api() {
+        return Ok(api
+            .find_prs_by_head(client, owner, repo, head_ref, false)
+            .await?
+            .into_iter()
+            .next());
+    }
     // Search for PRs with this head ref
-    let pulls = client
-        .pulls(owner, repo)
-        .list()
-        .state(octocrab::params::State::All)
-        .head(format!("{}:{}", owner, head_ref))
-        .per_page(1)
-        .send()
-        .await
-        .context("Failed to query GitHub for pull requests")?;
+    let head = format!("{}:{}", owner, head_ref);
+    let pulls = retry::with_retry(client.retry_policy(), "list PRs by head", || async {
+        client
+            .pulls(owner, repo)
+            .list()
+            .state(octocrab::params::State::All)
+            .head(head.clone())
+            .per_page(1)
+            .send()
+            .await
+    })
+    .await
+    .context("Failed to query GitHub for pull requests")?;
 
     if let Some(pr) = pulls.items.first() {
         Ok(Some(pr_info_from_octocrab(pr)))
@@ -42,147 +182,807 @@ pub async fn find_pr_by_head(
     }
 }
 
+/// Look up the PRs of several head branches in one GraphQL query, like
+/// `find_pr_by_head` for each (the newest PR of any state, from the repository
+/// itself rather than a fork). Results are cached in the client.
+#[instrument(level = "debug", skip(client))]
+pub async fn find_prs_by_heads(
+    client: &Client,
+    owner: &str,
+    repo: &str,
+    head_refs: &[String],
+) -> Result<HashMap<String, PrInfo>> {
+    let _span = profile::span("gh: find_prs_by_heads");
+    let cached = |head_ref: &String| client.cached_head_pr(owner, repo, head_ref);
+    let missing: Vec<String> = head_refs
+        .iter()
+        .filter(|h| cached(h).is_none())
+        .cloned()
+        .collect();
+    debug!(
+        owner,
+        repo,
+        ?missing,
+        cached = head_refs.len() - missing.len(),
+        "batch PRs by head query"
+    );
+
+    if let Some(api) = client.api() {
+        // No batch lookup over REST: one request per branch
+        for head_ref in &missing {
+            let pr = api
+                .find_prs_by_head(client, owner, repo, head_ref, false)
+                .await?
+                .into_iter()
+                .next();
+            client.cache_head_pr(owner, repo, head_ref, pr);
+        }
+    }
+
+    for missing in missing
+        .chunks(graphql::MAX_BATCH)
+        .filter(|_| client.api().is_none())
+    {
+        let heads = graphql::Aliased::new("head", "String!", missing.iter());
+        let query = format!(
+            "query PrsByHeads($owner: String!, $name: String!, {}) {{\n  repository(owner: $owner, name: $name) {{\n{}\n  }}\n}}\n{}",
+            heads.declarations(),
+            heads.selections(|alias| format!(
+                "    {alias}: pullRequests(headRefName: ${alias}, first: 10, orderBy: {{ field: CREATED_AT, direction: DESC }}) {{ nodes {{ ...PrState headRepositoryOwner {{ login }} }} }}"
+            )),
+            graphql::PR_STATE_FRAGMENT
+        );
+        let request = graphql::Request::new(
+            query,
+            graphql::RepositoryVariables {
+                owner,
+                name: repo,
+                variables: heads,
+            },
+        );
+        let aliases = request
+            .query::<graphql::RepositoryData<graphql::Aliases<graphql::Connection<graphql::PullRequest>>>>(
+                client,
+                "Failed to look up PRs by head branch",
+            )
+            .await?
+            .repository
+            .unwrap_or_default();
+
+        let found = parse_prs_by_heads(aliases, owner, missing);
+        for head_ref in missing {
+            client.cache_head_pr(owner, repo, head_ref, found.get(head_ref).cloned());
+        }
+    }
+
+    Ok(head_refs
+        .iter()
+        .filter_map(|head_ref| Some((head_ref.clone(), cached(head_ref)??)))
+        .collect())
+}
+
+/// The PR of each head ref in a `find_prs_by_heads` response: the newest one
+/// opened from `owner`'s repository
+fn parse_prs_by_heads(
+    mut aliases: graphql::Aliases<graphql::Connection<graphql::PullRequest>>,
+    owner: &str,
+    head_refs: &[String],
+) -> HashMap<String, PrInfo> {
+    let mut results = HashMap::new();
+    for (idx, head_ref) in head_refs.iter().enumerate() {
+        let pr = aliases
+            .remove(&format!("head{}", idx))
+            .flatten()
+            .into_iter()
+            .flat_map(graphql::Connection::into_nodes)
+            .find(|pr| {
+                pr.head_repository_owner
+                    .as_ref()
+                    .is_some_and(|o| o.login.eq_ignore_ascii_case(owner))
+            });
+        if let Some(pr) = pr {
+            results.insert(head_ref.clone(), pr_info_from_graphql(pr));
+        }
+    }
+    results
+}
+
 /// Get PR information by PR number
-pub async fn get_pr(
-    client: &Octocrab,
+#[instrument(level = "debug", skip(client))]
+pub async fn get_pr(client: &Client, owner: &str, repo: &str, pr_number: u64) -> Result<PrInfo> {
+    let _span = profile::span("gh: get_pr");
+    if let Some(api) = client.api() {
+        return api
+            .get_pr(client, owner, repo, pr_number)
+            .await?
+            .with_context(|| format!("PR #{} not found", pr_number));
+    }
+    let pr = retry::with_retry(client.retry_policy(), "get PR", || async {
+        client.pulls(owner, repo).get(pr_number).await
+    })
+    .await
+    .with_context(|| format!("Failed to get PR #{}", pr_number))?;
+
+    Ok(pr_info_from_octocrab(&pr))
+}
+
+/// Find the first comment on a PR written by `author` whose body contains `marker`.
+/// Returns the comment ID.
+#[instrument(level = "debug", skip(client, marker))]
+pub async fn find_comment_with_marker(
+    client: &Client,
     owner: &str,
     repo: &str,
     pr_number: u64,
-) -> Result<PrInfo> {
-    let pr = client
-        .pulls(owner, repo)
-        .get(pr_number)
-        .await
-        .with_context(|| format!("Failed to get PR #{}", pr_number))?;
+    marker: &str,
+    author: &str,
+) -> Result<Option<u64>> {
+    let _span = profile::span("gh: find_comment_with_marker");
+    if let Some(api) = client.api() {
+        return Err(forge::unsupported(api.forge(), "Comment lookups"));
+    }
+    let comments = retry::with_retry(client.retry_policy(), "list PR comments", || async {
+        let first_page = client
+            .issues(owner, repo)
+            .list_comments(pr_number)
+            .per_page(100)
+            .send()
+            .await?;
+        client.all_pages(first_page).await
+    })
+    .await
+    .with_context(|| format!("Failed to list comments on PR #{}", pr_number))?;
 
-    Ok(pr_info_from_octocrab(&pr))
+    Ok(comments
+        .into_iter()
+        .find(|c| c.user.login == author && c.body.as_deref().is_some_and(|b| b.contains(marker)))
+        .map(|c| c.id.into_inner()))
+}
+
+/// GitHub's side of a stack, fetched by `get_stack_state`
+#[derive(Debug, Default)]
+pub struct StackState {
+    /// The stack's PRs by number, with review decision and CI status
+    pub prs: HashMap<u64, PrInfo>,
+    /// Open PRs of the head branches asked for; branches without one are left out
+    pub open_prs_by_head: HashMap<String, OpenPr>,
 }
 
-/// Batch fetch multiple PRs using GraphQL
-pub async fn get_prs_batch(
-    client: &Octocrab,
+/// Fetch everything discovery reads from GitHub in one GraphQL round-trip:
+/// the stack's PRs (state, mergeability, review threads and decision, CI
+/// rollup) and the open PRs of `head_refs`
+#[instrument(level = "debug", skip(client))]
+pub async fn get_stack_state(
+    client: &Client,
     owner: &str,
     repo: &str,
     pr_numbers: &[u64],
-) -> Result<HashMap<u64, PrInfo>> {
-    if pr_numbers.is_empty() {
-        return Ok(HashMap::new());
+    head_refs: &[String],
+) -> Result<StackState> {
+    let _span = profile::span("gh: get_stack_state");
+    if let Some(api) = client.api() {
+        return get_stack_state_rest(api, client, owner, repo, pr_numbers, head_refs).await;
+    }
+    // Usually a single query; very long stacks take one per MAX_BATCH PRs
+    let mut state = StackState::default();
+    let mut pr_chunks = pr_numbers.chunks(graphql::MAX_BATCH);
+    let mut head_chunks = head_refs.chunks(graphql::MAX_BATCH);
+    loop {
+        let (pr_numbers, head_refs) = (
+            pr_chunks.next().unwrap_or_default(),
+            head_chunks.next().unwrap_or_default(),
+        );
+        if pr_numbers.is_empty() && head_refs.is_empty() {
+            return Ok(state);
+        }
+        let chunk = get_stack_state_chunk(client, owner, repo, pr_numbers, head_refs).await?;
+        state.prs.extend(chunk.prs);
+        state.open_prs_by_head.extend(chunk.open_prs_by_head);
     }
+}
 
-    // Build GraphQL query with aliases for each PR
-    let mut query_parts = Vec::new();
-    for (idx, pr_number) in pr_numbers.iter().enumerate() {
-        query_parts.push(format!(
-            r#"pr{}: pullRequest(number: {}) {{
-                number
-                title
-                body
-                state
-                isDraft
-                merged
-                baseRefName
-                headRefName
-                headRefOid
-            }}"#,
-            idx, pr_number
+/// `get_stack_state` over a forge's REST API: a request per PR and per branch
+async fn get_stack_state_rest(
+    api: &dyn ForgeApi,
+    client: &Client,
+    owner: &str,
+    repo: &str,
+    pr_numbers: &[u64],
+    head_refs: &[String],
+) -> Result<StackState> {
+    let mut state = StackState::default();
+    for &pr_number in pr_numbers {
+        if let Some(pr) = api.get_pr(client, owner, repo, pr_number).await? {
+            state.prs.insert(pr_number, pr);
+        }
+    }
+    for head_ref in head_refs {
+        if let Some(pr) = api
+            .find_prs_by_head(client, owner, repo, head_ref, true)
+            .await?
+            .into_iter()
+            .next()
+        {
+            state.open_prs_by_head.insert(
+                head_ref.clone(),
+                OpenPr {
+                    number: pr.number,
+                    title: pr.title,
+                },
+            );
+        }
+    }
+    Ok(state)
+}
+
+async fn get_stack_state_chunk(
+    client: &Client,
+    owner: &str,
+    repo: &str,
+    pr_numbers: &[u64],
+    head_refs: &[String],
+) -> Result<StackState> {
+    let variables = StackStateVariables {
+        prs: graphql::Aliased::new("pr", "Int!", pr_numbers.iter().copied()),
+        heads: graphql::Aliased::new("head", "String!", head_refs.iter()),
+    };
+    let request = graphql::Request::new(
+        stack_state_query(&variables),
+        graphql::RepositoryVariables {
+            owner,
+            name: repo,
+            variables: &variables,
+        },
+    );
+    let data = request
+        .query::<StackStateData>(client, "Failed to fetch PR states from GitHub")
+        .await?;
+    Ok(parse_stack_state(data, pr_numbers, head_refs))
+}
+
+/// Variables of the `get_stack_state` query: `$pr0`, ... and `$head0`, ...
+#[derive(Serialize)]
+struct StackStateVariables<'a> {
+    #[serde(flatten)]
+    prs: graphql::Aliased<u64>,
+    #[serde(flatten)]
+    heads: graphql::Aliased<&'a String>,
+}
+
+/// The query of `get_stack_state`, built from the PRs and branches asked for.
+/// Each part aliases the repository, so parts without anything to ask are left out.
+fn stack_state_query(variables: &StackStateVariables) -> String {
+    let mut declarations = vec!["$owner: String!, $name: String!".to_string()];
+    let mut parts = Vec::new();
+    if !variables.prs.is_empty() {
+        declarations.push(variables.prs.declarations());
+        parts.push(format!(
+            "  prs: repository(owner: $owner, name: $name) {{\n{}\n  }}",
+            variables.prs.selections(|alias| format!(
+                "    {alias}: pullRequest(number: ${alias}) {{ ...PrState reviewDecision \
+                 reviewThreads(first: 100) {{ nodes {{ isResolved }} }} \
+                 commits(last: 1) {{ nodes {{ commit {{ statusCheckRollup {{ state }} }} }} }} }}"
+            ))
+        ));
+    }
+    if !variables.heads.is_empty() {
+        declarations.push(variables.heads.declarations());
+        parts.push(format!(
+            "  heads: repository(owner: $owner, name: $name) {{\n{}\n  }}",
+            variables.heads.selections(|alias| format!(
+                "    {alias}: pullRequests(headRefName: ${alias}, states: [OPEN], first: 1) {{ nodes {{ number title }} }}"
+            ))
         ));
     }
 
-    let query = format!(
-        r#"query {{
-            repository(owner: "{}", name: "{}") {{
-                {}
-            }}
-        }}"#,
-        owner,
-        repo,
-        query_parts.join("\n                ")
+    let mut query = format!(
+        "query StackState({}) {{\n{}\n}}\n",
+        declarations.join(", "),
+        parts.join("\n")
     );
+    if !variables.prs.is_empty() {
+        query.push_str(graphql::PR_STATE_FRAGMENT);
+    }
+    query
+}
+
+/// `data` of the `get_stack_state` query
+#[derive(Debug, Default, Deserialize)]
+#[serde(default)]
+struct StackStateData {
+    prs: Option<graphql::Aliases<graphql::PullRequest>>,
+    heads: Option<graphql::Aliases<graphql::Connection<graphql::PullRequest>>>,
+}
+
+/// Map the aliases of a `get_stack_state` response back to PR numbers and head refs
+fn parse_stack_state(data: StackStateData, pr_numbers: &[u64], head_refs: &[String]) -> StackState {
+    let mut prs = data.prs.unwrap_or_default();
+    let mut heads = data.heads.unwrap_or_default();
+    let mut state = StackState::default();
+
+    for (idx, pr_number) in pr_numbers.iter().enumerate() {
+        // Null when the PR doesn't exist (any more)
+        if let Some(pr) = prs.remove(&format!("pr{}", idx)).flatten() {
+            state.prs.insert(*pr_number, pr_info_from_graphql(pr));
+        }
+    }
+    for (idx, head_ref) in head_refs.iter().enumerate() {
+        let pr = heads
+            .remove(&format!("head{}", idx))
+            .flatten()
+            .and_then(|prs| prs.into_nodes().next());
+        if let Some(pr) = pr {
+            state.open_prs_by_head.insert(
+                head_ref.clone(),
+                OpenPr {
+                    number: pr.number,
+                    title: pr.title,
+                },
+            );
+        }
+    }
+
+    state
+}
+
+/// An open PR found by its head branch
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct OpenPr {
+    pub number: u64,
+    pub title: String,
+}
 
-    // Execute GraphQL query
-    let response: serde_json::Value = client
-        .graphql(&json!({ "query": query }))
-        .await
-        .context("Failed to execute GraphQL query for batch PR fetch")?;
+/// The CI status of a status check rollup `state`
+fn ci_status(rollup_state: &str) -> Option<CiStatus> {
+    match rollup_state {
+        "SUCCESS" => Some(CiStatus::Success),
+        "FAILURE" | "ERROR" => Some(CiStatus::Failure),
+        "PENDING" | "EXPECTED" => Some(CiStatus::Pending),
+        _ => None,
+    }
+}
 
-    // Parse results
+/// Fetch the label names of several PRs
+#[instrument(level = "debug", skip(client))]
+pub async fn get_pr_labels(
+    client: &Client,
+    owner: &str,
+    repo: &str,
+    pr_numbers: &[u64],
+) -> Result<HashMap<u64, Vec<String>>> {
+    let _span = profile::span("gh: get_pr_labels");
+    if let Some(api) = client.api() {
+        let mut results = HashMap::new();
+        for &pr_number in pr_numbers {
+            results.insert(
+                pr_number,
+                api.get_labels(client, owner, repo, pr_number).await?,
+            );
+        }
+        return Ok(results);
+    }
     let mut results = HashMap::new();
+    for pr_numbers in pr_numbers.chunks(graphql::MAX_BATCH) {
+        results.extend(get_pr_labels_chunk(client, owner, repo, pr_numbers).await?);
+    }
+    Ok(results)
+}
 
-    if let Some(repository) = response.get("data").and_then(|d| d.get("repository")) {
-        for (idx, pr_number) in pr_numbers.iter().enumerate() {
-            let pr_key = format!("pr{}", idx);
-            if let Some(pr_data) = repository.get(&pr_key) {
-                if !pr_data.is_null() {
-                    if let Ok(pr_info) = parse_graphql_pr(pr_data, *pr_number) {
-                        results.insert(*pr_number, pr_info);
-                    }
-                }
-            }
+async fn get_pr_labels_chunk(
+    client: &Client,
+    owner: &str,
+    repo: &str,
+    pr_numbers: &[u64],
+) -> Result<HashMap<u64, Vec<String>>> {
+    let prs = graphql::Aliased::new("pr", "Int!", pr_numbers.iter().copied());
+    let query = format!(
+        "query PrLabels($owner: String!, $name: String!, {}) {{\n  repository(owner: $owner, name: $name) {{\n{}\n  }}\n}}",
+        prs.declarations(),
+        prs.selections(|alias| format!(
+            "    {alias}: pullRequest(number: ${alias}) {{ labels(first: 100) {{ nodes {{ name }} }} }}"
+        ))
+    );
+    let request = graphql::Request::new(
+        query,
+        graphql::RepositoryVariables {
+            owner,
+            name: repo,
+            variables: prs,
+        },
+    );
+    let mut prs = request
+        .query::<graphql::RepositoryData<graphql::Aliases<graphql::PullRequest>>>(
+            client,
+            "Failed to fetch PR labels",
+        )
+        .await?
+        .repository
+        .unwrap_or_default();
+
+    let mut results = HashMap::new();
+    for (idx, pr_number) in pr_numbers.iter().enumerate() {
+        if let Some(pr) = prs.remove(&format!("pr{}", idx)).flatten() {
+            results.insert(
+                *pr_number,
+                pr.labels.into_nodes().map(|label| label.name).collect(),
+            );
         }
     }
 
     Ok(results)
 }
 
-/// Parse PR info from GraphQL response
-fn parse_graphql_pr(data: &serde_json::Value, pr_number: u64) -> Result<PrInfo> {
-    let state_str = data
-        .get("state")
-        .and_then(|s| s.as_str())
-        .unwrap_or("CLOSED");
-    let merged = data
-        .get("merged")
-        .and_then(|m| m.as_bool())
-        .unwrap_or(false);
-    let is_draft = data
-        .get("isDraft")
-        .and_then(|d| d.as_bool())
-        .unwrap_or(false);
-
-    let state = if merged {
+/// How far a PR is from merging, polled while `land` waits for it
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct MergeProgress {
+    pub state: PrState,
+    /// GitHub's `mergeStateStatus`: CLEAN, BLOCKED, BEHIND, DIRTY, UNSTABLE, ...
+    pub merge_state: String,
+    /// Check runs and commit statuses on the head commit
+    pub checks_total: usize,
+    /// Checks that completed, failed ones included
+    pub checks_done: usize,
+    pub checks_failed: usize,
+}
+
+/// Fetch the merge state and check progress of a PR
+#[instrument(level = "debug", skip(client))]
+pub async fn get_merge_progress(
+    client: &Client,
+    owner: &str,
+    repo: &str,
+    pr_number: u64,
+) -> Result<MergeProgress> {
+    let _span = profile::span("gh: get_merge_progress");
+    if let Some(api) = client.api() {
+        return api.get_merge_progress(client, owner, repo, pr_number).await;
+    }
+    let variables = graphql::merge_progress::Variables {
+        owner: owner.to_string(),
+        name: repo.to_string(),
+        number: pr_number as i64,
+    };
+    let action = format!("Failed to get the merge state of PR #{}", pr_number);
+    let pr = graphql::query::<graphql::MergeProgress>(client, variables, &action)
+        .await?
+        .repository
+        .and_then(|repository| repository.pull_request)
+        .with_context(|| format!("PR #{} not found", pr_number))?;
+    Ok(parse_merge_progress(pr))
+}
+
+fn parse_merge_progress(
+    pr: graphql::merge_progress::MergeProgressRepositoryPullRequest,
+) -> MergeProgress {
+    use graphql::merge_progress::MergeProgressRepositoryPullRequestCommitsNodesCommitStatusCheckRollupContextsNodes as Check;
+
+    let state = if pr.merged {
+        PrState::Merged
+    } else if pr.state != "OPEN" {
+        PrState::Closed
+    } else if pr.is_draft {
+        PrState::Draft
+    } else {
+        PrState::Open
+    };
+
+    let contexts = graphql::nodes(pr.commits.nodes)
+        .next()
+        .and_then(|node| node.commit.status_check_rollup)
+        .map(|rollup| rollup.contexts);
+    let total_count = contexts.as_ref().map(|contexts| contexts.total_count);
+    let mut checks_total = 0;
+    let mut checks_done = 0;
+    let mut checks_failed = 0;
+    for check in graphql::nodes(contexts.and_then(|contexts| contexts.nodes)) {
+        let (done, failed) = match check {
+            Check::CheckRun(run) => (
+                run.status == "COMPLETED",
+                matches!(
+                    run.conclusion.as_deref(),
+                    Some(
+                        "FAILURE"
+                            | "TIMED_OUT"
+                            | "CANCELLED"
+                            | "ACTION_REQUIRED"
+                            | "STARTUP_FAILURE"
+                    )
+                ),
+            ),
+            Check::StatusContext(status) => match status.state.as_str() {
+                "SUCCESS" => (true, false),
+                "FAILURE" | "ERROR" => (true, true),
+                _ => (false, false),
+            },
+        };
+        checks_total += 1;
+        checks_done += usize::from(done);
+        checks_failed += usize::from(failed);
+    }
+
+    MergeProgress {
+        state,
+        merge_state: pr.merge_state_status,
+        checks_total: total_count.map_or(checks_total, |t| t as usize),
+        checks_done,
+        checks_failed,
+    }
+}
+
+/// What the base branch's protection requires of a PR, and where the PR stands
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct MergeRequirements {
+    pub base_ref: String,
+    /// `mergeStateStatus`, e.g. BLOCKED or BEHIND
+    pub merge_state: String,
+    /// `reviewDecision`: REVIEW_REQUIRED, CHANGES_REQUESTED or APPROVED
+    pub review_decision: Option<String>,
+    /// Approvals the protection rule requires (None when it can't be read)
+    pub required_approvals: Option<u64>,
+    pub code_owner_reviews: bool,
+    /// Checks that must pass; empty when none are required or the rule can't be read
+    pub required_checks: Vec<String>,
+    /// The branch must be up to date with its base before merging
+    pub strict_checks: bool,
+    pub conversation_resolution: bool,
+    pub approvals: usize,
+    /// Logins of reviewers whose latest review requests changes
+    pub changes_requested: Vec<String>,
+    pub failed_checks: Vec<String>,
+    pub pending_checks: Vec<String>,
+    pub passed_checks: Vec<String>,
+    pub unresolved_threads: usize,
+}
+
+/// Fetch the protection rule of a PR's base branch together with the PR's
+/// reviews, checks and conversations
+#[instrument(level = "debug", skip(client))]
+pub async fn get_merge_requirements(
+    client: &Client,
+    owner: &str,
+    repo: &str,
+    pr_number: u64,
+) -> Result<MergeRequirements> {
+    let _span = profile::span("gh: get_merge_requirements");
+    if let Some(api) = client.api() {
+        return Err(forge::unsupported(api.forge(), "Merge requirements"));
+    }
+    let variables = graphql::merge_requirements::Variables {
+        owner: owner.to_string(),
+        name: repo.to_string(),
+        number: pr_number as i64,
+    };
+    let action = format!("Failed to get the merge requirements of PR #{}", pr_number);
+    let pr = graphql::query::<graphql::MergeRequirements>(client, variables, &action)
+        .await?
+        .repository
+        .and_then(|repository| repository.pull_request)
+        .with_context(|| format!("PR #{} not found", pr_number))?;
+    Ok(parse_merge_requirements(pr))
+}
+
+fn parse_merge_requirements(
+    pr: graphql::merge_requirements::MergeRequirementsRepositoryPullRequest,
+) -> MergeRequirements {
+    use graphql::merge_requirements::MergeRequirementsRepositoryPullRequestCommitsNodesCommitStatusCheckRollupContextsNodes as Check;
+
+    let mut requirements = MergeRequirements {
+        base_ref: pr.base_ref_name,
+        merge_state: pr.merge_state_status,
+        review_decision: pr.review_decision,
+        ..Default::default()
+    };
+
+    // Reading protection rules may need more access than the token has
+    if let Some(rule) = pr.base_ref.and_then(|r| r.branch_protection_rule) {
+        if rule.requires_approving_reviews {
+            requirements.required_approvals = rule
+                .required_approving_review_count
+                .map(|count| count as u64);
+        }
+        requirements.code_owner_reviews = rule.requires_code_owner_reviews;
+        if rule.requires_status_checks {
+            requirements.required_checks = rule
+                .required_status_check_contexts
+                .into_iter()
+                .flatten()
+                .flatten()
+                .collect();
+            requirements.strict_checks = rule.requires_strict_status_checks;
+        }
+        requirements.conversation_resolution = rule.requires_conversation_resolution;
+    }
+
+    for review in graphql::nodes(
+        pr.latest_opinionated_reviews
+            .and_then(|reviews| reviews.nodes),
+    ) {
+        match review.state.as_str() {
+            "APPROVED" => requirements.approvals += 1,
+            "CHANGES_REQUESTED" => requirements.changes_requested.push(
+                review
+                    .author
+                    .map_or_else(|| "ghost".to_string(), |a| a.login),
+            ),
+            _ => {}
+        }
+    }
+
+    requirements.unresolved_threads = graphql::nodes(pr.review_threads.nodes)
+        .filter(|t| !t.is_resolved)
+        .count();
+
+    let contexts = graphql::nodes(pr.commits.nodes)
+        .next()
+        .and_then(|node| node.commit.status_check_rollup)
+        .and_then(|rollup| rollup.contexts.nodes);
+    for check in graphql::nodes(contexts) {
+        let (name, bucket) = match check {
+            Check::CheckRun(run) => {
+                let bucket = match (run.status.as_str(), run.conclusion.as_deref()) {
+                    ("COMPLETED", Some("SUCCESS" | "NEUTRAL" | "SKIPPED")) => {
+                        &mut requirements.passed_checks
+                    }
+                    ("COMPLETED", _) => &mut requirements.failed_checks,
+                    _ => &mut requirements.pending_checks,
+                };
+                (run.name, bucket)
+            }
+            Check::StatusContext(status) => {
+                let bucket = match status.state.as_str() {
+                    "SUCCESS" => &mut requirements.passed_checks,
+                    "FAILURE" | "ERROR" => &mut requirements.failed_checks,
+                    _ => &mut requirements.pending_checks,
+                };
+                (status.context, bucket)
+            }
+        };
+        bucket.push(name);
+    }
+
+    requirements
+}
+
+/// A review thread on a pull request
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ReviewThread {
+    /// GraphQL node ID, used to resolve the thread
+    pub id: String,
+    pub path: String,
+    /// Line in the current diff, or in the original one when the thread is outdated
+    pub line: Option<u64>,
+    pub is_resolved: bool,
+    /// The lines commented on have changed since
+    pub is_outdated: bool,
+    pub comments: Vec<ThreadComment>,
+}
+
+/// A comment in a review thread
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ThreadComment {
+    pub author: String,
+    pub body: String,
+}
+
+/// Fetch the review threads of a PR (up to 100, with their first 50 comments)
+#[instrument(level = "debug", skip(client))]
+pub async fn get_review_threads(
+    client: &Client,
+    owner: &str,
+    repo: &str,
+    pr_number: u64,
+) -> Result<Vec<ReviewThread>> {
+    let _span = profile::span("gh: get_review_threads");
+    if let Some(api) = client.api() {
+        return Err(forge::unsupported(api.forge(), "Review threads"));
+    }
+    let variables = graphql::review_threads::Variables {
+        owner: owner.to_string(),
+        name: repo.to_string(),
+        number: pr_number as i64,
+    };
+    let pr = graphql::query::<graphql::ReviewThreads>(
+        client,
+        variables,
+        "Failed to fetch review threads",
+    )
+    .await?
+    .repository
+    .and_then(|repository| repository.pull_request)
+    .with_context(|| format!("PR #{} not found", pr_number))?;
+    Ok(parse_review_threads(pr.review_threads))
+}
+
+fn parse_review_threads(
+    threads: graphql::review_threads::ReviewThreadsRepositoryPullRequestReviewThreads,
+) -> Vec<ReviewThread> {
+    graphql::nodes(threads.nodes)
+        .map(|thread| ReviewThread {
+            id: thread.id,
+            path: thread.path,
+            line: thread.line.or(thread.original_line).map(|line| line as u64),
+            is_resolved: thread.is_resolved,
+            is_outdated: thread.is_outdated,
+            comments: graphql::nodes(thread.comments.nodes)
+                .map(|comment| ThreadComment {
+                    // Deleted accounts have no author
+                    author: comment
+                        .author
+                        .map_or_else(|| "ghost".to_string(), |a| a.login),
+                    body: comment.body,
+                })
+                .collect(),
+        })
+        .collect()
+}
+
+/// Fetch the most recently updated open PRs (up to 100), including their bodies
+#[instrument(level = "debug", skip(client))]
+pub async fn list_open_prs(client: &Client, owner: &str, repo: &str) -> Result<Vec<PrInfo>> {
+    let _span = profile::span("gh: list_open_prs");
+    if let Some(api) = client.api() {
+        return api.list_open_prs(client, owner, repo).await;
+    }
+    let variables = graphql::open_prs::Variables {
+        owner: owner.to_string(),
+        name: repo.to_string(),
+    };
+    let prs =
+        graphql::query::<graphql::OpenPrs>(client, variables, "Failed to list open pull requests")
+            .await?
+            .repository
+            .and_then(|repository| repository.pull_requests.nodes);
+    Ok(graphql::nodes(prs)
+        .map(|pr| pr_info_from_graphql(pr.into()))
+        .collect())
+}
+
+/// Convert a GraphQL pull request to our PrInfo
+fn pr_info_from_graphql(pr: graphql::PullRequest) -> PrInfo {
+    let state = if pr.merged {
         PrState::Merged
-    } else if is_draft {
+    } else if pr.is_draft {
         PrState::Draft
-    } else if state_str == "OPEN" {
+    } else if pr.state == "OPEN" {
         PrState::Open
     } else {
         PrState::Closed
     };
+    let open = matches!(state, PrState::Open | PrState::Draft);
+
+    // `mergeable` is UNKNOWN while GitHub computes it in the background;
+    // only flag PRs it has positively found to conflict
+    let has_conflicts = open
+        && (pr.mergeable.as_deref() == Some("CONFLICTING")
+            || pr.merge_state_status.as_deref() == Some("DIRTY"));
+    let behind_base = open && pr.merge_state_status.as_deref() == Some("BEHIND");
 
-    Ok(PrInfo {
-        number: pr_number,
+    let unresolved_threads = pr.review_threads.nodes().filter(|t| !t.is_resolved).count();
+    let ci_status = pr
+        .status_check_rollup()
+        .and_then(|rollup| ci_status(rollup.state.as_deref()?));
+
+    PrInfo {
+        number: pr.number,
         state,
-        title: data
-            .get("title")
-            .and_then(|t| t.as_str())
-            .unwrap_or("")
-            .to_string(),
-        body: data
-            .get("body")
-            .and_then(|b| b.as_str())
-            .unwrap_or("")
-            .to_string(),
-        base_ref: data
-            .get("baseRefName")
-            .and_then(|r| r.as_str())
-            .unwrap_or("")
-            .to_string(),
-        head_ref: data
-            .get("headRefName")
-            .and_then(|r| r.as_str())
-            .unwrap_or("")
-            .to_string(),
-        head_sha: data
-            .get("headRefOid")
-            .and_then(|s| s.as_str())
-            .unwrap_or("")
-            .to_string(),
-    })
+        title: pr.title,
+        body: pr.body,
+        base_ref: pr.base_ref_name,
+        head_ref: pr.head_ref_name,
+        head_sha: pr.head_ref_oid,
+        merge_commit_sha: pr.merge_commit.map(|commit| commit.oid),
+        has_conflicts,
+        behind_base,
+        unresolved_threads,
+        review_decision: pr.review_decision,
+        ci_status,
+    }
 }
 
-/// Convert octocrab PullRequest to our PrInfo
+/// Convert octocrab PullRequest to our PrInfo (review threads are only available through GraphQL)
 fn pr_info_from_octocrab(pr: &PullRequest) -> PrInfo {
     use octocrab::models::IssueState;
+    use octocrab::models::pulls::MergeableState;
 
     let state = if pr.merged_at.is_some() {
         PrState::Merged
@@ -205,5 +1005,270 @@ fn pr_info_from_octocrab(pr: &PullRequest) -> PrInfo {
         base_ref: pr.base.ref_field.clone(),
         head_ref: pr.head.ref_field.clone(),
         head_sha: pr.head.sha.clone(),
+        merge_commit_sha: pr.merged_at.and(pr.merge_commit_sha.clone()),
+        has_conflicts: matches!(pr.mergeable_state, Some(MergeableState::Dirty)),
+        behind_base: matches!(pr.mergeable_state, Some(MergeableState::Behind)),
+        unresolved_threads: 0,
+        review_decision: None,
+        ci_status: None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn decode<T: serde::de::DeserializeOwned>(value: serde_json::Value) -> T {
+        serde_json::from_value(value).unwrap()
+    }
+
+    #[test]
+    fn test_pr_info_from_graphql() {
+        let pr = pr_info_from_graphql(decode(json!({
+            "number": 7,
+            "state": "OPEN",
+            "isDraft": false,
+            "merged": false,
+            "mergeable": "CONFLICTING",
+            "mergeStateStatus": "DIRTY",
+            "reviewDecision": "APPROVED",
+            "reviewThreads": { "nodes": [
+                { "isResolved": false },
+                { "isResolved": true },
+                { "isResolved": false },
+            ]},
+            "commits": { "nodes": [{ "commit": { "statusCheckRollup": { "state": "ERROR" } } }] },
+        })));
+        assert_eq!(pr.state, PrState::Open);
+        assert!(pr.has_conflicts);
+        assert_eq!(pr.unresolved_threads, 2);
+        assert_eq!(pr.review_decision.as_deref(), Some("APPROVED"));
+        assert_eq!(pr.ci_status, Some(CiStatus::Failure));
+
+        let merged = pr_info_from_graphql(decode(json!({
+            "state": "MERGED",
+            "merged": true,
+            "mergeable": "CONFLICTING",
+            "mergeCommit": { "oid": "abc123" },
+        })));
+        assert_eq!(merged.state, PrState::Merged);
+        assert!(!merged.has_conflicts);
+        assert_eq!(merged.merge_commit_sha.as_deref(), Some("abc123"));
+        assert_eq!(merged.unresolved_threads, 0);
+        assert_eq!(merged.ci_status, None);
+    }
+
+    #[test]
+    fn test_parse_stack_state() {
+        let data: StackStateData = decode(json!({
+            "prs": {
+                "pr0": { "number": 12, "state": "OPEN", "commits": { "nodes": [
+                    { "commit": { "statusCheckRollup": { "state": "PENDING" } } },
+                ]}},
+                "pr1": null,
+            },
+            "heads": {
+                "head0": { "nodes": [{ "number": 9, "title": "Dropped" }] },
+                "head1": { "nodes": [] },
+            },
+        }));
+        let heads = vec!["feat--03".to_string(), "feat--04".to_string()];
+
+        let state = parse_stack_state(data, &[12, 13], &heads);
+        assert_eq!(state.prs.len(), 1);
+        assert_eq!(state.prs[&12].ci_status, Some(CiStatus::Pending));
+        assert_eq!(state.open_prs_by_head.len(), 1);
+        assert_eq!(state.open_prs_by_head["feat--03"].number, 9);
+        assert_eq!(state.open_prs_by_head["feat--03"].title, "Dropped");
+
+        // Only the parts asked for are queried
+        let query = stack_state_query(&StackStateVariables {
+            prs: graphql::Aliased::new("pr", "Int!", [12]),
+            heads: graphql::Aliased::new("head", "String!", []),
+        });
+        assert!(query.starts_with("query StackState($owner: String!, $name: String!, $pr0: Int!)"));
+        assert!(query.contains("pr0: pullRequest(number: $pr0)"));
+        assert!(query.contains("fragment PrState on PullRequest"));
+        assert!(!query.contains("heads:"));
+        assert!(
+            parse_stack_state(StackStateData::default(), &[12], &heads)
+                .prs
+                .is_empty()
+        );
+    }
+
+    #[test]
+    fn test_parse_prs_by_heads() {
+        let pr = |number: u64, login: &str| {
+            json!({
+                "number": number,
+                "title": format!("PR {}", number),
+                "state": "OPEN",
+                "isDraft": false,
+                "merged": false,
+                "baseRefName": "main",
+                "headRefName": "feat--01",
+                "headRepositoryOwner": { "login": login },
+            })
+        };
+        let aliases = decode(json!({
+            "head0": { "nodes": [pr(14, "someone-else"), pr(12, "Acme")] },
+            "head1": { "nodes": [] },
+        }));
+        let heads = vec!["feat--01".to_string(), "feat--02".to_string()];
+
+        let found = parse_prs_by_heads(aliases, "acme", &heads);
+        assert_eq!(found.len(), 1);
+        // The fork's PR on a branch of the same name is skipped
+        assert_eq!(found["feat--01"].number, 12);
+        assert_eq!(found["feat--01"].state, PrState::Open);
+    }
+
+    #[test]
+    fn test_parse_merge_progress() {
+        let data = json!({
+            "state": "OPEN",
+            "isDraft": false,
+            "merged": false,
+            "mergeStateStatus": "BLOCKED",
+            "commits": { "nodes": [{ "commit": { "statusCheckRollup": { "contexts": {
+                "totalCount": 4,
+                "nodes": [
+                    { "__typename": "CheckRun", "status": "COMPLETED", "conclusion": "SUCCESS" },
+                    { "__typename": "CheckRun", "status": "COMPLETED", "conclusion": "FAILURE" },
+                    { "__typename": "CheckRun", "status": "IN_PROGRESS", "conclusion": null },
+                    { "__typename": "StatusContext", "state": "PENDING" },
+                ],
+            }}}}]},
+        });
+        assert_eq!(
+            parse_merge_progress(decode(data)),
+            MergeProgress {
+                state: PrState::Open,
+                merge_state: "BLOCKED".to_string(),
+                checks_total: 4,
+                checks_done: 2,
+                checks_failed: 1,
+            }
+        );
+
+        let merged = parse_merge_progress(decode(json!({
+            "state": "MERGED",
+            "isDraft": false,
+            "merged": true,
+            "mergeStateStatus": "UNKNOWN",
+            "commits": { "nodes": [{ "commit": { "statusCheckRollup": null } }] },
+        })));
+        assert_eq!(merged.state, PrState::Merged);
+        assert_eq!(merged.checks_total, 0);
+    }
+
+    #[test]
+    fn test_parse_merge_requirements() {
+        let data = json!({
+            "baseRefName": "main",
+            "mergeStateStatus": "BLOCKED",
+            "reviewDecision": "REVIEW_REQUIRED",
+            "baseRef": { "branchProtectionRule": {
+                "requiresApprovingReviews": true,
+                "requiredApprovingReviewCount": 2,
+                "requiresCodeOwnerReviews": false,
+                "requiresStatusChecks": true,
+                "requiresStrictStatusChecks": true,
+                "requiredStatusCheckContexts": ["build", "lint"],
+                "requiresConversationResolution": true,
+            }},
+            "latestOpinionatedReviews": { "nodes": [
+                { "state": "APPROVED", "author": { "__typename": "User", "login": "alice" } },
+                { "state": "CHANGES_REQUESTED", "author": { "__typename": "User", "login": "bob" } },
+            ]},
+            "reviewThreads": { "nodes": [{ "isResolved": false }, { "isResolved": true }] },
+            "commits": { "nodes": [{ "commit": { "statusCheckRollup": { "contexts": { "nodes": [
+                { "__typename": "CheckRun", "name": "build", "status": "COMPLETED", "conclusion": "FAILURE" },
+                { "__typename": "CheckRun", "name": "test", "status": "COMPLETED", "conclusion": "SUCCESS" },
+                { "__typename": "StatusContext", "context": "lint", "state": "PENDING" },
+            ]}}}}]},
+        });
+
+        assert_eq!(
+            parse_merge_requirements(decode(data)),
+            MergeRequirements {
+                base_ref: "main".to_string(),
+                merge_state: "BLOCKED".to_string(),
+                review_decision: Some("REVIEW_REQUIRED".to_string()),
+                required_approvals: Some(2),
+                code_owner_reviews: false,
+                required_checks: vec!["build".to_string(), "lint".to_string()],
+                strict_checks: true,
+                conversation_resolution: true,
+                approvals: 1,
+                changes_requested: vec!["bob".to_string()],
+                failed_checks: vec!["build".to_string()],
+                pending_checks: vec!["lint".to_string()],
+                passed_checks: vec!["test".to_string()],
+                unresolved_threads: 1,
+            }
+        );
+
+        // Without access to the protection rule
+        let unreadable = parse_merge_requirements(decode(json!({
+            "baseRefName": "main",
+            "mergeStateStatus": "UNKNOWN",
+            "reviewDecision": null,
+            "baseRef": { "branchProtectionRule": null },
+            "latestOpinionatedReviews": null,
+            "reviewThreads": { "nodes": [] },
+            "commits": { "nodes": [] },
+        })));
+        assert_eq!(unreadable.required_approvals, None);
+        assert!(unreadable.required_checks.is_empty());
+    }
+
+    #[test]
+    fn test_parse_review_threads() {
+        let threads = json!({ "nodes": [
+            {
+                "id": "PRRT_1",
+                "isResolved": false,
+                "isOutdated": false,
+                "path": "src/lib.rs",
+                "line": 42,
+                "originalLine": 40,
+                "comments": { "nodes": [
+                    { "author": { "__typename": "User", "login": "alice" }, "body": "Why not X?" },
+                    { "author": null, "body": "Agreed" },
+                ]},
+            },
+            {
+                "id": "PRRT_2",
+                "isResolved": true,
+                "isOutdated": true,
+                "path": "README.md",
+                "line": null,
+                "originalLine": 7,
+                "comments": { "nodes": [] },
+            },
+        ]});
+
+        let threads = parse_review_threads(decode(threads));
+        assert_eq!(threads.len(), 2);
+        assert_eq!(threads[0].id, "PRRT_1");
+        assert_eq!(threads[0].line, Some(42));
+        assert_eq!(
+            threads[0].comments,
+            vec![
+                ThreadComment {
+                    author: "alice".to_string(),
+                    body: "Why not X?".to_string()
+                },
+                ThreadComment {
+                    author: "ghost".to_string(),
+                    body: "Agreed".to_string()
+                },
+            ]
+        );
+        assert!(threads[1].is_resolved && threads[1].is_outdated);
+        assert_eq!(threads[1].line, Some(7));
     }
 }
@@ -0,0 +1,130 @@
+//! Shared retry policy for GitHub API calls (and those of other forges, see `forge`).
+//!
+//! Each client carries its policy (from `git-stk.maxRetries`,
+//! `git-stk.retryBackoffMs`, `git-stk.retryMaxBackoffMs`), which is applied to idempotent requests; non-idempotent ones such as posting a
+//! comment or merging a PR are never retried. The exception is creating a PR:
+//! a retry after a request that did go through fails with "already exists",
+//! which `mutations::create_pull_request` turns into the PR it created.
+
+use std::future::Future;
+use std::time::Duration;
+use tracing::warn;
+
+use crate::model::Config;
+
+/// How often and how patiently to retry failed requests
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct RetryPolicy {
+    /// Retries after the first attempt (0 disables retrying)
+    pub max_retries: u32,
+    /// Delay before the first retry; doubled for each further retry
+    pub initial_backoff: Duration,
+    /// Upper bound for a single delay
+    pub max_backoff: Duration,
+}
+
+impl Default for RetryPolicy {
+    fn default() -> Self {
+        Self {
+            max_retries: 3,
+            initial_backoff: Duration::from_millis(1000),
+            max_backoff: Duration::from_millis(10_000),
+        }
+    }
+}
+
+impl RetryPolicy {
+    pub fn from_config(config: &Config) -> Self {
+        Self {
+            max_retries: config.max_retries,
+            initial_backoff: Duration::from_millis(config.retry_backoff_ms),
+            max_backoff: Duration::from_millis(config.retry_max_backoff_ms),
+        }
+    }
+
+    /// Delay before retry number `retry` (1-based): exponential, capped at `max_backoff`
+    pub fn backoff(&self, retry: u32) -> Duration {
+        let factor = 2u32.saturating_pow(retry.saturating_sub(1));
+        self.initial_backoff
+            .checked_mul(factor)
+            .unwrap_or(self.max_backoff)
+            .min(self.max_backoff)
+    }
+}
+
+/// Whether an error is worth retrying: transport failures, timeouts,
+/// rate limiting, and 5xx responses
+pub fn is_transient(error: &octocrab::Error) -> bool {
+    match error {
+        octocrab::Error::GitHub { source, .. } => {
+            let status = source.status_code;
+            status.is_server_error() || status.as_u16() == 429
+        }
+        octocrab::Error::Hyper { .. }
+        | octocrab::Error::Service { .. }
+        | octocrab::Error::Http { .. } => true,
+        _ => false,
+    }
+}
+
+/// Run `op`, retrying transient failures according to `policy`
+pub async fn with_retry<T, F, Fut>(
+    policy: RetryPolicy,
+    what: &str,
+    op: F,
+) -> Result<T, octocrab::Error>
+where
+    F: FnMut() -> Fut,
+    Fut: Future<Output = Result<T, octocrab::Error>>,
+{
+    with_retry_if(policy, what, is_transient, op).await
+}
+
+/// Run `op`, retrying failures accepted by `should_retry` according to `policy`
+pub async fn with_retry_if<T, E, F, Fut, P>(
+    policy: RetryPolicy,
+    what: &str,
+    should_retry: P,
+    mut op: F,
+) -> Result<T, E>
+where
+    E: std::fmt::Display,
+    F: FnMut() -> Fut,
+    Fut: Future<Output = Result<T, E>>,
+    P: Fn(&E) -> bool,
+{
+    let mut retry = 0;
+
+    loop {
+        match op().await {
+            Ok(value) => return Ok(value),
+            Err(e) if retry < policy.max_retries && should_retry(&e) => {
+                retry += 1;
+                let delay = policy.backoff(retry);
+                warn!(what, retry, delay_ms = delay.as_millis() as u64, error = %e, "API request failed, retrying");
+                tokio::time::sleep(delay).await;
+            }
+            Err(e) => return Err(e),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_backoff() {
+        let policy = RetryPolicy {
+            max_retries: 5,
+            initial_backoff: Duration::from_millis(500),
+            max_backoff: Duration::from_millis(3000),
+        };
+
+        assert_eq!(policy.backoff(1), Duration::from_millis(500));
+        assert_eq!(policy.backoff(2), Duration::from_millis(1000));
+        assert_eq!(policy.backoff(3), Duration::from_millis(2000));
+        assert_eq!(policy.backoff(4), Duration::from_millis(3000));
+        assert_eq!(policy.backoff(40), Duration::from_millis(3000));
+    }
+}
@@ -0,0 +1,404 @@
+use crate::git::notes;
+use anyhow::{Context, Result, anyhow, bail};
+use git2::{Oid, Repository};
+use std::process::Command;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+/// Namespace under which stack snapshots are stored
+const BACKUP_REF_PREFIX: &str = "refs/git-stk/backup/";
+
+/// A snapshot of a branch's stack taken before a destructive operation
+#[derive(Debug, Clone)]
+pub struct Snapshot {
+    /// Snapshot identifier (Unix timestamp when it was taken, bumped past any
+    /// snapshot taken in the same second)
+    pub id: i64,
+    /// Branch the snapshot was taken on, the only one it can be restored onto
+    pub branch: String,
+    /// HEAD commit at the time of the snapshot
+    pub head: Oid,
+    /// Notes ref commit at the time of the snapshot, if notes existed
+    pub notes: Option<Oid>,
+}
+
+impl Snapshot {
+    fn head_ref_name(id: i64, branch: &str) -> String {
+        format!("{}{}/head/{}", BACKUP_REF_PREFIX, id, branch)
+    }
+
+    fn notes_ref_name(id: i64) -> String {
+        format!("{}{}/notes", BACKUP_REF_PREFIX, id)
+    }
+
+    /// Fail unless the snapshot's branch is checked out
+    pub fn ensure_checked_out(&self, repo: &Repository) -> Result<()> {
+        let current = crate::git::repo::current_branch(repo)?;
+        if current != self.branch {
+            bail!(
+                "Snapshot {} was taken on {}, not {}. Check out {} to restore it.",
+                self.id,
+                self.branch,
+                current,
+                self.branch
+            );
+        }
+        Ok(())
+    }
+}
+
+/// Snapshot HEAD and the notes ref under refs/git-stk/backup/<id>, then
+/// delete all but the newest `keep` snapshots (0 keeps all)
+pub fn create_snapshot(
+    repo: &Repository,
+    notes_ref: &str,
+    reason: &str,
+    keep: usize,
+) -> Result<Snapshot> {
+    let branch = crate::git::repo::current_branch(repo)?;
+    let taken = list_snapshots(repo)?;
+    let mut id = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .context("System clock is before the Unix epoch")?
+        .as_secs() as i64;
+    while taken.iter().any(|s| s.id == id)
+        || repo.refname_to_id(&Snapshot::notes_ref_name(id)).is_ok()
+    {
+        id += 1;
+    }
+
+    let head = repo
+        .head()
+        .context("Failed to get HEAD")?
+        .peel_to_commit()
+        .context("Failed to resolve HEAD")?
+        .id();
+
+    let log_message = format!("git-stk: snapshot before {}", reason);
+    repo.reference(
+        &Snapshot::head_ref_name(id, &branch),
+        head,
+        false,
+        &log_message,
+    )
+    .context("Failed to write snapshot ref for HEAD")?;
+
+    let notes = match repo.refname_to_id(notes_ref) {
+        Ok(notes_oid) => {
+            repo.reference(
+                &Snapshot::notes_ref_name(id),
+                notes_oid,
+                false,
+                &log_message,
+            )
+            .context("Failed to write snapshot ref for notes")?;
+            Some(notes_oid)
+        }
+        Err(e) if e.code() == git2::ErrorCode::NotFound => None,
+        Err(e) => return Err(e).context("Failed to resolve notes ref"),
+    };
+
+    if keep > 0 {
+        prune_snapshots(repo, keep)?;
+    }
+
+    Ok(Snapshot {
+        id,
+        branch,
+        head,
+        notes,
+    })
+}
+
+/// Delete all but the newest `keep` snapshots, so their refs don't pin old
+/// commits forever
+fn prune_snapshots(repo: &Repository, keep: usize) -> Result<()> {
+    for snapshot in list_snapshots(repo)?.into_iter().skip(keep) {
+        let mut names = vec![Snapshot::head_ref_name(snapshot.id, &snapshot.branch)];
+        names.extend(
+            snapshot
+                .notes
+                .map(|_| Snapshot::notes_ref_name(snapshot.id)),
+        );
+        for name in names {
+            repo.find_reference(&name)
+                .and_then(|mut reference| reference.delete())
+                .with_context(|| format!("Failed to delete old snapshot ref {}", name))?;
+        }
+    }
+    Ok(())
+}
+
+/// List all snapshots, newest first
+pub fn list_snapshots(repo: &Repository) -> Result<Vec<Snapshot>> {
+    let mut snapshots = Vec::new();
+
+    let glob = format!("{}*", BACKUP_REF_PREFIX);
+    for reference in repo
+        .references_glob(&glob)
+        .context("Failed to list snapshot refs")?
+    {
+        let reference = reference.context("Failed to read snapshot ref")?;
+        let Some(name) = reference.name() else {
+            continue;
+        };
+        let Some((id, branch)) = name
+            .strip_prefix(BACKUP_REF_PREFIX)
+            .and_then(|rest| rest.split_once("/head/"))
+            .and_then(|(id, branch)| Some((id.parse::<i64>().ok()?, branch.to_string())))
+        else {
+            continue;
+        };
+        let Some(head) = reference.target() else {
+            continue;
+        };
+        let notes = repo.refname_to_id(&Snapshot::notes_ref_name(id)).ok();
+
+        snapshots.push(Snapshot {
+            id,
+            branch,
+            head,
+            notes,
+        });
+    }
+
+    snapshots.sort_by_key(|s| std::cmp::Reverse(s.id));
+    Ok(snapshots)
+}
+
+/// Find a snapshot by identifier
+pub fn find_snapshot(repo: &Repository, id: i64) -> Result<Snapshot> {
+    list_snapshots(repo)?
+        .into_iter()
+        .find(|s| s.id == id)
+        .ok_or_else(|| {
+            anyhow!(
+                "Snapshot {} not found. Run 'git stk restore' to list snapshots.",
+                id
+            )
+        })
+}
+
+/// Roll the snapshot's branch, which must be checked out, and the notes of
+/// its commits back to a snapshot. The commits are those above `base` at the
+/// snapshot or now; the notes of other commits (other branches' stacks) are
+/// left alone.
+pub fn restore_snapshot(
+    repo: &Repository,
+    snapshot: &Snapshot,
+    notes_ref: &str,
+    base: Oid,
+) -> Result<()> {
+    snapshot.ensure_checked_out(repo)?;
+    let repo_path = repo
+        .workdir()
+        .ok_or_else(|| anyhow!("Repository has no working directory"))?;
+
+    let mut walk = repo.revwalk().context("Failed to walk the stack")?;
+    walk.push(snapshot.head)?;
+    walk.push_head()?;
+    walk.hide(base)?;
+    let commits = walk
+        .collect::<Result<Vec<Oid>, _>>()
+        .context("Failed to walk the stack")?;
+
+    // Reset through git so the working tree, index and reflog are all updated
+    let output = Command::new("git")
+        .current_dir(repo_path)
+        .arg("reset")
+        .arg("--hard")
+        .arg(snapshot.head.to_string())
+        .output()
+        .context("Failed to execute git reset")?;
+
+    if !output.status.success() {
+        let stderr = String::from_utf8_lossy(&output.stderr);
+        return Err(anyhow!("Failed to reset to snapshot: {}", stderr));
+    }
+
+    let saved_notes = Snapshot::notes_ref_name(snapshot.id);
+    let signature = repo.signature().context("Failed to create signature")?;
+    for oid in commits {
+        let saved = match snapshot.notes {
+            Some(_) => note_message(repo, &saved_notes, oid)?,
+            None => None,
+        };
+        if saved == note_message(repo, notes_ref, oid)? {
+            continue;
+        }
+        match saved {
+            Some(message) => {
+                repo.note(&signature, &signature, Some(notes_ref), oid, &message, true)
+                    .context("Failed to restore note")?;
+            }
+            None => notes::remove_note(repo, oid, notes_ref)?,
+        }
+    }
+
+    Ok(())
+}
+
+/// The raw note of `oid` under `notes_ref`
+fn note_message(repo: &Repository, notes_ref: &str, oid: Oid) -> Result<Option<String>> {
+    match repo.find_note(Some(notes_ref), oid) {
+        Ok(note) => Ok(note.message().map(String::from)),
+        Err(e) if e.code() == git2::ErrorCode::NotFound => Ok(None),
+        Err(e) => Err(e).context("Failed to read note"),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn init_repo_with_commit() -> (tempfile::TempDir, Repository) {
+        let dir = tempfile::tempdir().unwrap();
+        let repo = Repository::init(dir.path()).unwrap();
+        {
+            let signature = git2::Signature::now("Test", "test@example.com").unwrap();
+            let tree_id = repo.index().unwrap().write_tree().unwrap();
+            let tree = repo.find_tree(tree_id).unwrap();
+            repo.commit(Some("HEAD"), &signature, &signature, "initial", &tree, &[])
+                .unwrap();
+        }
+        (dir, repo)
+    }
+
+    #[test]
+    fn test_create_and_list_snapshots() {
+        let (_dir, repo) = init_repo_with_commit();
+        let head = repo.head().unwrap().target().unwrap();
+        let branch = repo.head().unwrap().shorthand().unwrap().to_string();
+
+        let snapshot = create_snapshot(&repo, "refs/notes/git-stk", "test", 0).unwrap();
+        assert_eq!(snapshot.head, head);
+        assert_eq!(snapshot.branch, branch);
+        assert!(snapshot.notes.is_none());
+
+        // Taken in the same second, yet distinct
+        let second = create_snapshot(&repo, "refs/notes/git-stk", "test", 0).unwrap();
+        assert_ne!(second.id, snapshot.id);
+
+        let snapshots = list_snapshots(&repo).unwrap();
+        assert_eq!(snapshots.len(), 2);
+        assert_eq!(snapshots[1].id, snapshot.id);
+        assert_eq!(snapshots[1].branch, branch);
+        assert_eq!(snapshots[1].head, head);
+
+        assert!(find_snapshot(&repo, snapshot.id).is_ok());
+        assert!(find_snapshot(&repo, second.id + 1).is_err());
+    }
+
+    #[test]
+    fn test_create_snapshot_prunes_old_ones() {
+        let (_dir, repo) = init_repo_with_commit();
+        let head = repo.head().unwrap().target().unwrap();
+        let signature = git2::Signature::now("Test", "test@example.com").unwrap();
+        repo.note(
+            &signature,
+            &signature,
+            Some("refs/notes/git-stk"),
+            head,
+            "note",
+            false,
+        )
+        .unwrap();
+
+        let first = create_snapshot(&repo, "refs/notes/git-stk", "test", 2).unwrap();
+        let second = create_snapshot(&repo, "refs/notes/git-stk", "test", 2).unwrap();
+        let third = create_snapshot(&repo, "refs/notes/git-stk", "test", 2).unwrap();
+
+        let ids: Vec<i64> = list_snapshots(&repo)
+            .unwrap()
+            .iter()
+            .map(|s| s.id)
+            .collect();
+        assert_eq!(ids, vec![third.id, second.id]);
+        assert!(
+            repo.refname_to_id(&Snapshot::notes_ref_name(first.id))
+                .is_err()
+        );
+        assert!(
+            repo.refname_to_id(&Snapshot::notes_ref_name(second.id))
+                .is_ok()
+        );
+    }
+
+    #[test]
+    fn test_restore_snapshot_only_touches_its_branch() {
+        let (_dir, repo) = init_repo_with_commit();
+        repo.config().unwrap().set_str("user.name", "Test").unwrap();
+        repo.config()
+            .unwrap()
+            .set_str("user.email", "test@example.com")
+            .unwrap();
+        let notes_ref = "refs/notes/git-stk";
+        let signature = git2::Signature::now("Test", "test@example.com").unwrap();
+        let base = repo.head().unwrap().peel_to_commit().unwrap();
+        let tree = base.tree().unwrap();
+        let entry = repo
+            .commit(
+                Some("HEAD"),
+                &signature,
+                &signature,
+                "entry",
+                &tree,
+                &[&base],
+            )
+            .unwrap();
+        let other = repo
+            .commit(
+                None,
+                &signature,
+                &signature,
+                "other branch",
+                &tree,
+                &[&base],
+            )
+            .unwrap();
+        repo.branch("other", &repo.find_commit(other).unwrap(), false)
+            .unwrap();
+        repo.note(
+            &signature,
+            &signature,
+            Some(notes_ref),
+            entry,
+            "before",
+            false,
+        )
+        .unwrap();
+
+        let snapshot = create_snapshot(&repo, notes_ref, "test", 0).unwrap();
+        repo.note(
+            &signature,
+            &signature,
+            Some(notes_ref),
+            entry,
+            "after",
+            true,
+        )
+        .unwrap();
+        repo.note(
+            &signature,
+            &signature,
+            Some(notes_ref),
+            other,
+            "other stack",
+            false,
+        )
+        .unwrap();
+
+        restore_snapshot(&repo, &snapshot, notes_ref, base.id()).unwrap();
+        assert_eq!(
+            note_message(&repo, notes_ref, entry).unwrap().as_deref(),
+            Some("before")
+        );
+        // Written by another branch's stack after the snapshot: kept
+        assert_eq!(
+            note_message(&repo, notes_ref, other).unwrap().as_deref(),
+            Some("other stack")
+        );
+
+        repo.set_head("refs/heads/other").unwrap();
+        assert!(restore_snapshot(&repo, &snapshot, notes_ref, base.id()).is_err());
+    }
+}
@@ -0,0 +1,80 @@
+//! Gerrit-style `Change-Id` trailers.
+//!
+//! With `git-stk.changeId` enabled, export gives every commit in the stack a
+//! `Change-Id` trailer. The id survives rebases and amends even when the git
+//! notes don't, so discovery can re-match a commit to its slot branch (and PR)
+//! by comparing it with the trailers of the remote slot branch tips.
+
+use anyhow::{Context, Result};
+use git2::{Commit, ObjectType, Oid, Repository};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use crate::git::{rewrite, trailers};
+use crate::model::{Config, Stack};
+
+/// Trailer key holding the id
+pub const CHANGE_ID_TRAILER: &str = "Change-Id";
+
+/// The `Change-Id` of a commit message, if any
+pub fn change_id(message: &str) -> Option<String> {
+    trailers::find_trailer(message, CHANGE_ID_TRAILER)
+}
+
+/// Build a Change-Id ("I" followed by 40 hex digits) from arbitrary seed data
+pub fn change_id_from_seed(seed: &str) -> Result<String> {
+    let hash = Oid::hash_object(ObjectType::Blob, seed.as_bytes())
+        .context("Failed to hash Change-Id seed")?;
+    Ok(format!("I{}", hash))
+}
+
+/// Generate a fresh Change-Id for a commit
+fn generate(commit: &Commit) -> Result<String> {
+    let now = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_nanos())
+        .unwrap_or_default();
+    change_id_from_seed(&format!(
+        "{}\n{}\n{}\n{}",
+        commit.id(),
+        commit.tree_id(),
+        commit.message().unwrap_or(""),
+        now
+    ))
+}
+
+/// Give every stack commit without a `Change-Id` one, rewriting the branch.
+///
+/// Trees are unchanged, so the index and working tree stay as they are. Notes
+/// are carried over to the rewritten commits. Returns how many ids were added.
+pub fn ensure_change_ids(repo: &Repository, config: &Config, stack: &Stack) -> Result<usize> {
+    rewrite::rewrite_messages(
+        repo,
+        config,
+        stack,
+        "git-stk: add Change-Id trailers",
+        |commit, message| {
+            if change_id(message).is_some() {
+                return Ok(None);
+            }
+            let id = generate(commit)?;
+            Ok(Some(trailers::append_trailer(
+                message,
+                &format!("{}: {}", CHANGE_ID_TRAILER, id),
+            )))
+        },
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_change_id_from_seed() {
+        let id = change_id_from_seed("seed").unwrap();
+        assert_eq!(id.len(), 41);
+        assert!(id.starts_with('I'));
+        assert_eq!(id, change_id_from_seed("seed").unwrap());
+        assert_ne!(id, change_id_from_seed("other").unwrap());
+    }
+}
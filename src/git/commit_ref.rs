@@ -1,13 +1,18 @@
-use anyhow::{bail, Context, Result};
-use git2::{Oid, Repository};
 use crate::model::Stack;
+use anyhow::{Context, Result, bail};
+use git2::{Oid, Repository};
 
 /// Resolve a commit reference to an OID using the stack
 ///
 /// Supports:
 /// - SHA (full or short): "abc123" or full SHA
 /// - Stack index: "1", "2", "3", ... (1-indexed, where 1 is the bottom of the stack)
+/// - Negative index: "-1" is the top commit, "-2" the one below it, ...
 /// - "last": the last (top) commit in the stack
+/// - Slot: "slot:add-tests", or a bare slot name ("add-tests", "02")
+///
+/// Plain numbers are indices; zero-padded ones ("02") are slots first and
+/// indices only when no entry has that slot.
 pub fn resolve_commit_ref(git_repo: &Repository, stack: &Stack, commit_ref: &str) -> Result<Oid> {
     if commit_ref == "last" {
         if stack.is_empty() {
@@ -16,22 +21,39 @@ pub fn resolve_commit_ref(git_repo: &Repository, stack: &Stack, commit_ref: &str
         return Ok(stack.entries.last().unwrap().oid);
     }
 
-    if let Ok(index) = commit_ref.parse::<usize>() {
-        if index == 0 {
-            bail!("Stack index must be 1 or greater (1 is the bottom of the stack)");
-        }
-        if stack.is_empty() {
-            bail!("No commits in stack");
-        }
-        if index > stack.entries.len() {
+    if let Some(from_top) = commit_ref
+        .strip_prefix('-')
+        .and_then(|n| n.parse::<usize>().ok())
+    {
+        if from_top == 0 || from_top > stack.len() {
             bail!(
-                "Stack index {} is out of range. Stack has {} commit{}",
-                index,
-                stack.entries.len(),
-                if stack.entries.len() == 1 { "" } else { "s" }
+                "Stack index -{} is out of range. Stack has {} commit{}",
+                from_top,
+                stack.len(),
+                if stack.len() == 1 { "" } else { "s" }
             );
         }
-        return Ok(stack.entries[index - 1].oid);
+        return Ok(stack.entries[stack.len() - from_top].oid);
+    }
+
+    if let Some(slot) = commit_ref.strip_prefix("slot:") {
+        return find_by_slot(stack, slot)
+            .with_context(|| format!("No commit in the stack has slot '{}'", slot));
+    }
+
+    let index = commit_ref.parse::<usize>().ok();
+    let zero_padded = commit_ref.len() > 1 && commit_ref.starts_with('0');
+    if let Some(index) = index
+        && !zero_padded
+    {
+        return resolve_index(stack, index);
+    }
+
+    if let Some(oid) = find_by_slot(stack, commit_ref) {
+        return Ok(oid);
+    }
+    if let Some(index) = index {
+        return resolve_index(stack, index);
     }
 
     // Try to resolve as a git reference (SHA, branch name, HEAD, etc.)
@@ -40,3 +62,145 @@ pub fn resolve_commit_ref(git_repo: &Repository, stack: &Stack, commit_ref: &str
         .with_context(|| format!("Failed to resolve commit reference: {}", commit_ref))?
         .id())
 }
+
+/// Resolve a commit reference or an inclusive range of stack entries to OIDs,
+/// bottom to top
+///
+/// A range is `<from>..<to>` with either side a commit reference in the stack;
+/// an omitted side extends to the bottom or top ("2..4", "-2..", "..03").
+/// Anything else resolves to the single commit `resolve_commit_ref` finds.
+pub fn resolve_commit_refs(git_repo: &Repository, stack: &Stack, spec: &str) -> Result<Vec<Oid>> {
+    let Some((from, to)) = spec.split_once("..") else {
+        return Ok(vec![resolve_commit_ref(git_repo, stack, spec)?]);
+    };
+    if stack.is_empty() {
+        bail!("No commits in stack");
+    }
+
+    let position = |commit_ref: &str, default: usize| -> Result<usize> {
+        if commit_ref.is_empty() {
+            return Ok(default);
+        }
+        let oid = resolve_commit_ref(git_repo, stack, commit_ref)?;
+        stack
+            .entries
+            .iter()
+            .position(|e| e.oid == oid)
+            .with_context(|| format!("'{}' is not a commit in the stack", commit_ref))
+    };
+    let start = position(from, 0)?;
+    let end = position(to, stack.len() - 1)?;
+    if start > end {
+        bail!(
+            "Empty range '{}': {} is above {} in the stack",
+            spec,
+            from,
+            to
+        );
+    }
+
+    Ok(stack.entries[start..=end].iter().map(|e| e.oid).collect())
+}
+
+fn resolve_index(stack: &Stack, index: usize) -> Result<Oid> {
+    if index == 0 {
+        bail!("Stack index must be 1 or greater (1 is the bottom of the stack)");
+    }
+    if stack.is_empty() {
+        bail!("No commits in stack");
+    }
+    if index > stack.entries.len() {
+        bail!(
+            "Stack index {} is out of range. Stack has {} commit{}",
+            index,
+            stack.entries.len(),
+            if stack.entries.len() == 1 { "" } else { "s" }
+        );
+    }
+    Ok(stack.entries[index - 1].oid)
+}
+
+/// The commit whose assigned slot is `slot`
+fn find_by_slot(stack: &Stack, slot: &str) -> Option<Oid> {
+    stack
+        .entries
+        .iter()
+        .find(|e| e.slot.as_deref() == Some(slot))
+        .map(|e| e.oid)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::model::Entry;
+
+    /// Entries 1 to 3 with slots "02", "add-tests" and "01"
+    fn stack() -> Stack {
+        let mut stack = Stack::new("main".to_string(), "feat".to_string());
+        for (index, slot) in [(1, "02"), (2, "add-tests"), (3, "01")] {
+            stack.add_entry(Entry {
+                slot: Some(slot.to_string()),
+                ..Entry::for_test(index)
+            });
+        }
+        stack
+    }
+
+    fn oid(stack: &Stack, index: usize) -> Oid {
+        stack.entries[index - 1].oid
+    }
+
+    #[test]
+    fn test_resolve_slot_precedence() {
+        let dir = tempfile::tempdir().unwrap();
+        let repo = Repository::init(dir.path()).unwrap();
+        let stack = stack();
+        let resolve = |commit_ref: &str| resolve_commit_ref(&repo, &stack, commit_ref);
+
+        assert_eq!(resolve("slot:add-tests").unwrap(), oid(&stack, 2));
+        assert_eq!(resolve("add-tests").unwrap(), oid(&stack, 2));
+        // Zero-padded numbers are slots first, plain numbers always indices
+        assert_eq!(resolve("02").unwrap(), oid(&stack, 1));
+        assert_eq!(resolve("01").unwrap(), oid(&stack, 3));
+        assert_eq!(resolve("2").unwrap(), oid(&stack, 2));
+        assert_eq!(resolve("03").unwrap(), oid(&stack, 3));
+        // `slot:` never falls back to an index or a git reference
+        assert!(resolve("slot:03").is_err());
+        assert!(resolve("slot:2").is_err());
+        assert!(resolve("missing").is_err());
+    }
+
+    #[test]
+    fn test_resolve_negative_indices_and_ranges() {
+        let dir = tempfile::tempdir().unwrap();
+        let repo = Repository::init(dir.path()).unwrap();
+        let stack = stack();
+        let resolve = |commit_ref: &str| resolve_commit_ref(&repo, &stack, commit_ref);
+        let resolve_all = |spec: &str| resolve_commit_refs(&repo, &stack, spec);
+
+        assert_eq!(resolve("-1").unwrap(), oid(&stack, 3));
+        assert_eq!(resolve("-3").unwrap(), oid(&stack, 1));
+        assert_eq!(resolve("last").unwrap(), resolve("-1").unwrap());
+        assert!(resolve("-0").is_err());
+        assert!(resolve("-4").is_err());
+
+        assert_eq!(resolve_all("2").unwrap(), vec![oid(&stack, 2)]);
+        assert_eq!(
+            resolve_all("2..3").unwrap(),
+            vec![oid(&stack, 2), oid(&stack, 3)]
+        );
+        assert_eq!(
+            resolve_all("-2..").unwrap(),
+            vec![oid(&stack, 2), oid(&stack, 3)]
+        );
+        // Range ends resolve like single references, slots included
+        assert_eq!(resolve_all("..02").unwrap(), vec![oid(&stack, 1)]);
+        assert_eq!(
+            resolve_all("add-tests..-1").unwrap(),
+            vec![oid(&stack, 2), oid(&stack, 3)]
+        );
+        assert_eq!(resolve_all("..").unwrap().len(), 3);
+        assert!(resolve_all("3..1").is_err());
+        assert!(resolve_all("1..5").is_err());
+    }
+}
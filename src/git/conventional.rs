@@ -0,0 +1,57 @@
+/// A parsed conventional-commit subject (`type(scope)!: description`)
+#[derive(Debug, PartialEq, Eq)]
+pub struct Conventional<'a> {
+    /// Commit type, lowercased (feat, fix, chore, ...)
+    pub kind: String,
+    pub scope: Option<&'a str>,
+    /// Marked breaking with `!` before the colon
+    pub breaking: bool,
+    pub description: &'a str,
+}
+
+/// Parse a `type(scope)!: description` subject
+pub fn parse(subject: &str) -> Option<Conventional<'_>> {
+    let (prefix, description) = subject.split_once(':')?;
+    let description = description.trim();
+    let (prefix, breaking) = match prefix.strip_suffix('!') {
+        Some(prefix) => (prefix, true),
+        None => (prefix, false),
+    };
+    let (kind, scope) = match prefix.split_once('(') {
+        Some((kind, rest)) => (kind, Some(rest.strip_suffix(')')?)),
+        None => (prefix, None),
+    };
+
+    if kind.is_empty() || !kind.chars().all(|c| c.is_ascii_alphabetic()) || description.is_empty() {
+        return None;
+    }
+
+    Some(Conventional {
+        kind: kind.to_ascii_lowercase(),
+        scope: scope.filter(|s| !s.is_empty()),
+        breaking,
+        description,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse() {
+        assert_eq!(
+            parse("feat(api)!: drop v1 endpoints"),
+            Some(Conventional {
+                kind: "feat".to_string(),
+                scope: Some("api"),
+                breaking: true,
+                description: "drop v1 endpoints",
+            })
+        );
+        assert_eq!(parse("Fix: typo").map(|c| c.kind), Some("fix".to_string()));
+        assert_eq!(parse("Add retries to the client"), None);
+        assert_eq!(parse("docs(readme: missing paren"), None);
+        assert_eq!(parse("see http://example.com"), None);
+    }
+}
@@ -0,0 +1,124 @@
+use anyhow::{Context, Result};
+use git2::{Commit, Oid, Repository, Tree};
+
+/// Size of a change: files touched and lines added/removed
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct DiffStats {
+    pub files: usize,
+    pub insertions: usize,
+    pub deletions: usize,
+}
+
+impl DiffStats {
+    /// Changed lines, counting both additions and deletions
+    pub fn lines(&self) -> usize {
+        self.insertions + self.deletions
+    }
+}
+
+/// Stats of a commit's diff against its first parent
+pub fn commit_diff_stats(repo: &Repository, oid: Oid) -> Result<DiffStats> {
+    let commit = repo.find_commit(oid).context("Failed to find commit")?;
+    let parent_tree = parent_tree(&commit)?;
+    let tree = commit.tree().context("Failed to get commit tree")?;
+    tree_diff_stats(repo, parent_tree.as_ref(), &tree)
+}
+
+/// Stats of the combined diff of a range of commits: from the parent of `bottom` to `top`
+pub fn range_diff_stats(repo: &Repository, bottom: Oid, top: Oid) -> Result<DiffStats> {
+    let bottom = repo.find_commit(bottom).context("Failed to find commit")?;
+    let parent_tree = parent_tree(&bottom)?;
+    let tree = repo
+        .find_commit(top)
+        .and_then(|c| c.tree())
+        .context("Failed to get commit tree")?;
+    tree_diff_stats(repo, parent_tree.as_ref(), &tree)
+}
+
+fn parent_tree<'r>(commit: &Commit<'r>) -> Result<Option<Tree<'r>>> {
+    if commit.parent_count() == 0 {
+        return Ok(None);
+    }
+    commit
+        .parent(0)
+        .and_then(|p| p.tree())
+        .map(Some)
+        .context("Failed to get parent tree")
+}
+
+fn tree_diff_stats(repo: &Repository, old: Option<&Tree>, new: &Tree) -> Result<DiffStats> {
+    let stats = repo
+        .diff_tree_to_tree(old, Some(new), None)
+        .and_then(|diff| diff.stats())
+        .context("Failed to compute diff stats")?;
+
+    Ok(DiffStats {
+        files: stats.files_changed(),
+        insertions: stats.insertions(),
+        deletions: stats.deletions(),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn commit_files(repo: &Repository, parent: Option<Oid>, files: &[(&str, &str)]) -> Oid {
+        let workdir = repo.workdir().unwrap();
+        let mut index = repo.index().unwrap();
+        if let Some(parent) = parent {
+            index
+                .read_tree(&repo.find_commit(parent).unwrap().tree().unwrap())
+                .unwrap();
+        }
+        for (path, content) in files {
+            std::fs::write(workdir.join(path), content).unwrap();
+            index.add_path(std::path::Path::new(path)).unwrap();
+        }
+        let tree = repo.find_tree(index.write_tree().unwrap()).unwrap();
+
+        let signature = git2::Signature::now("Test", "test@example.com").unwrap();
+        let parents: Vec<Commit> = parent
+            .map(|p| repo.find_commit(p).unwrap())
+            .into_iter()
+            .collect();
+        let parent_refs: Vec<&Commit> = parents.iter().collect();
+        repo.commit(None, &signature, &signature, "commit", &tree, &parent_refs)
+            .unwrap()
+    }
+
+    #[test]
+    fn test_diff_stats() {
+        let dir = tempfile::tempdir().unwrap();
+        let repo = Repository::init(dir.path()).unwrap();
+
+        let base = commit_files(&repo, None, &[("a.txt", "1\n2\n3\n")]);
+        let first = commit_files(
+            &repo,
+            Some(base),
+            &[("a.txt", "1\ntwo\n3\n"), ("b.txt", "b\n")],
+        );
+        let second = commit_files(&repo, Some(first), &[("b.txt", "b\nc\n")]);
+
+        let stats = commit_diff_stats(&repo, first).unwrap();
+        assert_eq!(
+            stats,
+            DiffStats {
+                files: 2,
+                insertions: 2,
+                deletions: 1
+            }
+        );
+        assert_eq!(stats.lines(), 3);
+
+        let total = range_diff_stats(&repo, first, second).unwrap();
+        assert_eq!(
+            total,
+            DiffStats {
+                files: 2,
+                insertions: 3,
+                deletions: 1
+            }
+        );
+    }
+}
@@ -0,0 +1,122 @@
+//! Mailing-list export: turn the stack into a `git format-patch` series.
+
+use anyhow::{Context, Result, anyhow};
+use git2::Repository;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+use tracing::{debug, instrument};
+
+use crate::model::Stack;
+use crate::profile;
+
+/// Placeholders `git format-patch --cover-letter` leaves for the author to fill in
+const SUBJECT_PLACEHOLDER: &str = "*** SUBJECT HERE ***";
+const BLURB_PLACEHOLDER: &str = "*** BLURB HERE ***";
+
+/// Write the stack as a patch series with a cover letter into `output_dir`.
+/// Returns the patch files in order, cover letter first.
+#[instrument(level = "debug", skip(repo, stack, cover_blurb), fields(entries = stack.len()))]
+pub fn write_series(
+    repo: &Repository,
+    stack: &Stack,
+    output_dir: &Path,
+    cover_subject: &str,
+    cover_blurb: &str,
+) -> Result<Vec<PathBuf>> {
+    let _span = profile::span("git: format-patch");
+    let repo_path = repo
+        .workdir()
+        .ok_or_else(|| anyhow!("Repository has no working directory"))?;
+    let (first, last) = match (stack.entries.first(), stack.entries.last()) {
+        (Some(first), Some(last)) => (first, last),
+        _ => return Ok(Vec::new()),
+    };
+    let base = repo
+        .find_commit(first.oid)?
+        .parent_id(0)
+        .context("Stack commit has no parent")?;
+
+    fs::create_dir_all(output_dir)
+        .with_context(|| format!("Failed to create directory {}", output_dir.display()))?;
+
+    let range = format!("{}..{}", base, last.oid);
+    debug!(%range, dir = %output_dir.display(), "git format-patch");
+    let output = Command::new("git")
+        .current_dir(repo_path)
+        .arg("format-patch")
+        .arg("--cover-letter")
+        .arg("--output-directory")
+        .arg(output_dir)
+        .arg(&range)
+        .output()
+        .context("Failed to execute git format-patch")?;
+
+    if !output.status.success() {
+        let stderr = String::from_utf8_lossy(&output.stderr);
+        return Err(anyhow!("git format-patch failed: {}", stderr.trim()));
+    }
+
+    let files: Vec<PathBuf> = String::from_utf8_lossy(&output.stdout)
+        .lines()
+        .filter(|line| !line.is_empty())
+        .map(|line| repo_path.join(line))
+        .collect();
+
+    if let Some(cover_letter) = files.first() {
+        let contents = fs::read_to_string(cover_letter)
+            .with_context(|| format!("Failed to read {}", cover_letter.display()))?;
+        fs::write(
+            cover_letter,
+            fill_cover_letter(&contents, cover_subject, cover_blurb),
+        )
+        .with_context(|| format!("Failed to write {}", cover_letter.display()))?;
+    }
+
+    Ok(files)
+}
+
+/// Replace the cover letter placeholders with the generated subject and blurb
+fn fill_cover_letter(contents: &str, subject: &str, blurb: &str) -> String {
+    contents.replacen(SUBJECT_PLACEHOLDER, subject, 1).replacen(
+        BLURB_PLACEHOLDER,
+        blurb.trim_end(),
+        1,
+    )
+}
+
+/// Hand the series to `git send-email`, which prompts for anything not configured
+pub fn send_email(repo: &Repository, files: &[PathBuf]) -> Result<()> {
+    let repo_path = repo
+        .workdir()
+        .ok_or_else(|| anyhow!("Repository has no working directory"))?;
+
+    let status = Command::new("git")
+        .current_dir(repo_path)
+        .arg("send-email")
+        .args(files)
+        .status()
+        .context("Failed to execute git send-email")?;
+
+    if !status.success() {
+        return Err(anyhow!("git send-email failed"));
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_fill_cover_letter() {
+        let template =
+            "Subject: [PATCH 0/2] *** SUBJECT HERE ***\n\n*** BLURB HERE ***\n\nJane (2):\n";
+        let filled = fill_cover_letter(template, "feature", "Two patches.\n");
+        assert_eq!(
+            filled,
+            "Subject: [PATCH 0/2] feature\n\nTwo patches.\n\nJane (2):\n"
+        );
+    }
+}
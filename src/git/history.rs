@@ -0,0 +1,208 @@
+//! Shallow and partial clones. A shallow clone may cut the history between the
+//! base and HEAD, which would make the stack walk run into the shallow boundary
+//! and pick up unrelated commits; a partial clone lacks the blobs libgit2 needs
+//! to diff stack commits, and unlike git, libgit2 can't fetch them on demand.
+
+use crate::git::refs;
+use crate::model::Transport;
+use crate::profile;
+use anyhow::{Context, Result, bail};
+use git2::{Oid, Repository};
+use std::process::Stdio;
+use tracing::{debug, instrument};
+
+/// Commits fetched by the first `git fetch --deepen`, doubled on every round
+const DEEPEN_STEP: u32 = 50;
+const DEEPEN_ROUNDS: u32 = 5;
+
+/// Make sure the history between `base` and `tip` is present
+///
+/// In a shallow clone whose history doesn't connect the two yet, fetch deeper
+/// history from `remote` until it does; fail with what's needed if it never
+/// does (e.g. offline). Full clones are left alone.
+///
+/// libgit2 reads shallow boundaries when a repository is opened, so `repo`
+/// keeps seeing the truncated history; after deepening, the reopened
+/// repository is returned to walk the history with.
+#[instrument(level = "debug", skip(repo, transport))]
+pub fn ensure_history(
+    repo: &Repository,
+    remote: &str,
+    tip: Oid,
+    base: Oid,
+    transport: &Transport,
+) -> Result<Option<Repository>> {
+    if !repo.is_shallow() || connects(repo, tip, base)? {
+        return Ok(None);
+    }
+    let _span = profile::span("git: deepen shallow clone");
+    let workdir = repo.workdir().unwrap_or_else(|| repo.path());
+
+    let mut fetched = 0;
+    for round in 0..DEEPEN_ROUNDS {
+        let depth = DEEPEN_STEP << round;
+        debug!(depth, "shallow clone doesn't reach the base, deepening");
+        let output = refs::git_command(workdir, transport)
+            .args([
+                "fetch",
+                "--quiet",
+                "--no-tags",
+                &format!("--deepen={}", depth),
+                remote,
+            ])
+            .stdin(Stdio::null())
+            .output()
+            .context("Failed to execute git fetch --deepen")?;
+        if !output.status.success() {
+            bail!(
+                "This is a shallow clone and HEAD's history doesn't reach the base, and fetching more failed: {}\n\
+                 Fetch the history with 'git fetch --unshallow {}' and try again.",
+                String::from_utf8_lossy(&output.stderr).trim(),
+                remote
+            );
+        }
+        fetched += depth;
+
+        // Reopen so the updated shallow boundary is read
+        let repo = Repository::open(repo.path()).context("Failed to reopen repository")?;
+        if connects(&repo, tip, base)? {
+            return Ok(Some(repo));
+        }
+    }
+
+    bail!(
+        "This is a shallow clone and HEAD's history doesn't reach the base, even after fetching {} more commits.\n\
+         The stack needs every commit between the base and HEAD. Fetch the full history with \
+         'git fetch --unshallow {}' (or more of it with 'git fetch --deepen=<commits> {}').",
+        fetched,
+        remote,
+        remote
+    )
+}
+
+/// Whether `tip` and `base` share a commit in the history that is present
+fn connects(repo: &Repository, tip: Oid, base: Oid) -> Result<bool> {
+    match repo.merge_base(tip, base) {
+        Ok(_) => Ok(true),
+        Err(e) if e.code() == git2::ErrorCode::NotFound => Ok(false),
+        Err(e) => Err(e).context("Failed to find the merge base of HEAD and the base"),
+    }
+}
+
+/// Whether objects may be missing locally because the clone was made with `--filter`
+pub fn is_partial_clone(repo: &Repository) -> bool {
+    let Ok(config) = repo.config() else {
+        return false;
+    };
+    if config.get_string("extensions.partialClone").is_ok() {
+        return true;
+    }
+    let Ok(remotes) = repo.remotes() else {
+        return false;
+    };
+    remotes.iter().flatten().any(|remote| {
+        config
+            .get_bool(&format!("remote.{}.promisor", remote))
+            .unwrap_or(false)
+    })
+}
+
+/// In a partial clone, fetch the blobs the commits in `base..tip` change, so
+/// libgit2 can diff them. git fetches them in one batch per commit while
+/// computing the diffs; once present, this is a cheap local walk.
+#[instrument(level = "debug", skip(repo, transport))]
+pub fn prefetch_blobs(repo: &Repository, tip: Oid, base: Oid, transport: &Transport) -> Result<()> {
+    if !is_partial_clone(repo) {
+        return Ok(());
+    }
+    let _span = profile::span("git: prefetch partial clone blobs");
+    let workdir = repo.workdir().unwrap_or_else(|| repo.path());
+
+    debug!(%base, %tip, "prefetching blobs of a partial clone");
+    // git fetches the missing blobs from the promisor remote on the way
+    let output = refs::git_command(workdir, transport)
+        .args(["log", "--format=", "--numstat", "--no-renames"])
+        .arg(format!("{}..{}", base, tip))
+        .stdin(Stdio::null())
+        .stdout(Stdio::null())
+        .output()
+        .context("Failed to execute git log")?;
+    if !output.status.success() {
+        bail!(
+            "Failed to fetch the files changed by the stack in this partial clone: {}",
+            String::from_utf8_lossy(&output.stderr).trim()
+        );
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::git::testing::git;
+
+    #[test]
+    fn test_ensure_history_deepens_shallow_clone() {
+        let dir = tempfile::tempdir().unwrap();
+        let origin = dir.path().join("origin");
+        std::fs::create_dir(&origin).unwrap();
+        git(&origin, &["init", "-q", "-b", "main"]);
+        for i in 0..3 {
+            git(
+                &origin,
+                &[
+                    "commit",
+                    "-q",
+                    "--allow-empty",
+                    "-m",
+                    &format!("base {}", i),
+                ],
+            );
+        }
+        git(&origin, &["checkout", "-q", "-b", "feat"]);
+        for i in 0..3 {
+            git(
+                &origin,
+                &[
+                    "commit",
+                    "-q",
+                    "--allow-empty",
+                    "-m",
+                    &format!("stack {}", i),
+                ],
+            );
+        }
+
+        let url = format!("file://{}", origin.display());
+        git(
+            dir.path(),
+            &[
+                "clone",
+                "-q",
+                "--depth",
+                "1",
+                "--no-single-branch",
+                &url,
+                "clone",
+            ],
+        );
+        let clone = Repository::open(dir.path().join("clone")).unwrap();
+        assert!(clone.is_shallow());
+        assert!(!is_partial_clone(&clone));
+
+        let tip = clone.refname_to_id("refs/remotes/origin/feat").unwrap();
+        let base = clone.refname_to_id("refs/remotes/origin/main").unwrap();
+        assert!(!connects(&clone, tip, base).unwrap());
+
+        let clone = ensure_history(&clone, "origin", tip, base, &Transport::default())
+            .unwrap()
+            .unwrap();
+        assert_eq!(clone.merge_base(tip, base).unwrap(), base);
+        assert!(
+            ensure_history(&clone, "origin", tip, base, &Transport::default())
+                .unwrap()
+                .is_none()
+        );
+    }
+}
@@ -0,0 +1,78 @@
+//! Issue tracker keys (`ABC-123`) mentioned in commit subjects
+
+use crate::model::IssueLinks;
+use regex::Regex;
+use std::sync::LazyLock;
+
+static ISSUE_KEY: LazyLock<Regex> =
+    LazyLock::new(|| Regex::new(r"\b([A-Z][A-Z0-9]+)-[1-9][0-9]*\b").unwrap());
+
+/// Issue keys in `subject`, in order and without duplicates. With `projects`,
+/// only keys of those projects count, which keeps `UTF-8` or `SHA-256` out.
+pub fn find_issue_keys(subject: &str, projects: &[String]) -> Vec<String> {
+    let mut keys: Vec<String> = Vec::new();
+    for captures in ISSUE_KEY.captures_iter(subject) {
+        let key = &captures[0];
+        let project = &captures[1];
+        if (projects.is_empty() || projects.iter().any(|p| p == project))
+            && !keys.iter().any(|k| k == key)
+        {
+            keys.push(key.to_string());
+        }
+    }
+    keys
+}
+
+/// Link to `key` from a URL template with a `{key}` placeholder
+pub fn issue_url(template: &str, key: &str) -> String {
+    template.replace("{key}", key)
+}
+
+/// `Issues: [ABC-123](…), …` line for a PR body, if the subject mentions any
+pub fn links_line(subject: &str, links: &IssueLinks) -> Option<String> {
+    let keys = find_issue_keys(subject, &links.projects);
+    if keys.is_empty() {
+        return None;
+    }
+    let linked: Vec<String> = keys
+        .iter()
+        .map(|key| format!("[{}]({})", key, issue_url(&links.url, key)))
+        .collect();
+    Some(format!("Issues: {}", linked.join(", ")))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_find_issue_keys() {
+        let projects = |names: &[&str]| names.iter().map(|n| n.to_string()).collect::<Vec<_>>();
+
+        assert_eq!(
+            find_issue_keys("ABC-123: fix login (LIN-45)", &[]),
+            ["ABC-123", "LIN-45"]
+        );
+        assert_eq!(find_issue_keys("ABC-123 ABC-123 again", &[]), ["ABC-123"]);
+        assert_eq!(
+            find_issue_keys("Use SHA-256 for ABC-9", &projects(&["ABC"])),
+            ["ABC-9"]
+        );
+        assert!(find_issue_keys("abc-123, A-1, ABC-0, XABC-12x", &[]).is_empty());
+    }
+
+    #[test]
+    fn test_links_line() {
+        let links = IssueLinks {
+            url: "https://acme.atlassian.net/browse/{key}".to_string(),
+            ..Default::default()
+        };
+        assert_eq!(
+            links_line("ABC-1: fix login (LIN-45)", &links).as_deref(),
+            Some(
+                "Issues: [ABC-1](https://acme.atlassian.net/browse/ABC-1), [LIN-45](https://acme.atlassian.net/browse/LIN-45)"
+            )
+        );
+        assert_eq!(links_line("Fix login", &links), None);
+    }
+}
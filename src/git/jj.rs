@@ -0,0 +1,189 @@
+//! Jujutsu (jj) colocated repositories.
+//!
+//! jj keeps git's HEAD detached at the parent of the working-copy change (`@-`)
+//! and exports its bookmarks as git branches. In jj mode the bookmark at HEAD
+//! is treated as the current branch, and jj's change-ids (written to the
+//! `change-id` commit header) re-match rewritten commits to their slots, since
+//! jj doesn't carry git notes across rewrites.
+
+use anyhow::{Context, Result, bail};
+use git2::{BranchType, Commit, Repository};
+
+/// Commit header in which jj records a commit's change-id
+const CHANGE_ID_HEADER: &str = "change-id";
+
+/// Whether jj mode is on: `git-stk.jj`, defaulting to whether the repository is
+/// colocated with jj (has a `.jj` directory next to `.git`)
+pub fn is_enabled(repo: &Repository) -> bool {
+    repo.config()
+        .and_then(|config| config.get_bool("git-stk.jj"))
+        .unwrap_or_else(|_| {
+            repo.workdir()
+                .is_some_and(|workdir| workdir.join(".jj").is_dir())
+        })
+}
+
+/// The jj change-id of a commit, if jj recorded one
+pub fn change_id(commit: &Commit) -> Option<String> {
+    let header = commit.header_field_bytes(CHANGE_ID_HEADER).ok()?;
+    let id = header.as_str()?.trim();
+    (!id.is_empty()).then(|| id.to_string())
+}
+
+/// In jj mode with a detached HEAD, the bookmark (local branch) pointing at HEAD.
+/// Returns `None` outside jj mode, when HEAD is attached, or when no bookmark
+/// points at HEAD.
+pub fn bookmark_at_head(repo: &Repository) -> Result<Option<String>> {
+    if !repo.head_detached().unwrap_or(false) || !is_enabled(repo) {
+        return Ok(None);
+    }
+
+    let head = repo
+        .head()
+        .context("Failed to get HEAD")?
+        .peel_to_commit()
+        .context("Failed to resolve HEAD")?
+        .id();
+
+    let mut bookmarks = Vec::new();
+    for branch in repo.branches(Some(BranchType::Local))? {
+        let (branch, _) = branch?;
+        if branch.get().target() == Some(head)
+            && let Some(name) = branch.name()?
+        {
+            bookmarks.push(name.to_string());
+        }
+    }
+
+    match bookmarks.len() {
+        0 => Ok(None),
+        1 => Ok(bookmarks.pop()),
+        _ => bail!(
+            "Several bookmarks point at @- ({}). Move all but one away, e.g. with 'jj bookmark move'.",
+            bookmarks.join(", ")
+        ),
+    }
+}
+
+/// Create a bookmark at HEAD. jj imports the git branch on its next command.
+pub fn create_bookmark_at_head(repo: &Repository, name: &str) -> Result<()> {
+    if repo.find_branch(name, BranchType::Local).is_ok() {
+        bail!("Bookmark '{}' already exists", name);
+    }
+
+    let head = repo
+        .head()
+        .context("Failed to get HEAD")?
+        .peel_to_commit()
+        .context("Failed to resolve HEAD")?;
+    repo.branch(name, &head, false)
+        .with_context(|| format!("Failed to create bookmark '{}'", name))?;
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use git2::{ObjectType, Oid, Signature};
+
+    fn init() -> (tempfile::TempDir, Repository, Oid) {
+        let dir = tempfile::tempdir().unwrap();
+        let repo = Repository::init(dir.path()).unwrap();
+        repo.config().unwrap().set_bool("git-stk.jj", true).unwrap();
+        let signature = Signature::now("Ada", "ada@example.com").unwrap();
+        let tree = repo.treebuilder(None).unwrap().write().unwrap();
+        let oid = {
+            let tree = repo.find_tree(tree).unwrap();
+            repo.commit(Some("HEAD"), &signature, &signature, "first", &tree, &[])
+                .unwrap()
+        };
+        (dir, repo, oid)
+    }
+
+    #[test]
+    fn test_is_enabled() {
+        let dir = tempfile::tempdir().unwrap();
+        let repo = Repository::init(dir.path()).unwrap();
+        assert!(!is_enabled(&repo));
+
+        std::fs::create_dir(dir.path().join(".jj")).unwrap();
+        assert!(is_enabled(&repo));
+
+        repo.config()
+            .unwrap()
+            .set_bool("git-stk.jj", false)
+            .unwrap();
+        assert!(!is_enabled(&repo));
+    }
+
+    #[test]
+    fn test_change_id() {
+        let (_dir, repo, oid) = init();
+        assert_eq!(change_id(&repo.find_commit(oid).unwrap()), None);
+
+        // jj writes the change-id as an extra header after the committer
+        let raw = repo.odb().unwrap().read(oid).unwrap().data().to_vec();
+        let raw = String::from_utf8(raw).unwrap();
+        let (headers, message) = raw.split_once("\n\n").unwrap();
+        let with_id = format!("{}\nchange-id zyxwvutsrqponmlk\n\n{}", headers, message);
+        let rewritten = repo
+            .odb()
+            .unwrap()
+            .write(ObjectType::Commit, with_id.as_bytes())
+            .unwrap();
+        assert_eq!(
+            change_id(&repo.find_commit(rewritten).unwrap()).as_deref(),
+            Some("zyxwvutsrqponmlk")
+        );
+    }
+
+    #[test]
+    fn test_bookmark_at_head() {
+        let (_dir, repo, oid) = init();
+        let commit = repo.find_commit(oid).unwrap();
+        repo.branch("feat", &commit, false).unwrap();
+
+        // HEAD attached (to the init branch): not jj's layout
+        assert_eq!(bookmark_at_head(&repo).unwrap(), None);
+
+        repo.set_head_detached(oid).unwrap();
+        let init_branch = repo
+            .branches(Some(BranchType::Local))
+            .unwrap()
+            .flatten()
+            .filter_map(|(b, _)| b.name().ok().flatten().map(String::from))
+            .find(|name| name != "feat")
+            .unwrap();
+        repo.find_branch(&init_branch, BranchType::Local)
+            .unwrap()
+            .delete()
+            .unwrap();
+        assert_eq!(bookmark_at_head(&repo).unwrap().as_deref(), Some("feat"));
+
+        repo.branch("other", &commit, false).unwrap();
+        assert!(bookmark_at_head(&repo).is_err());
+
+        repo.config()
+            .unwrap()
+            .set_bool("git-stk.jj", false)
+            .unwrap();
+        assert_eq!(bookmark_at_head(&repo).unwrap(), None);
+    }
+
+    #[test]
+    fn test_create_bookmark_at_head() {
+        let (_dir, repo, oid) = init();
+        repo.set_head_detached(oid).unwrap();
+
+        create_bookmark_at_head(&repo, "feat").unwrap();
+        assert_eq!(
+            repo.find_branch("feat", BranchType::Local)
+                .unwrap()
+                .get()
+                .target(),
+            Some(oid)
+        );
+        assert!(create_bookmark_at_head(&repo, "feat").is_err());
+    }
+}
@@ -0,0 +1,163 @@
+//! Local branches mirroring the slot branches (`export --keep-branches`), for
+//! IDEs and tools that only work with local branches.
+
+use anyhow::{Context, Result};
+use git2::{BranchType, Oid, Repository};
+
+/// Prefix of the local mirrors of `branch`'s slot branches: `stk/<branch>/`
+pub fn mirror_prefix(branch: &str) -> String {
+    format!("stk/{}/", branch)
+}
+
+/// Outcome of `sync_mirrors`
+#[derive(Debug, Default, PartialEq, Eq)]
+pub struct MirrorSync {
+    /// Mirrors created or moved to their entry's commit
+    pub updated: usize,
+    /// Mirrors of slots no longer in the stack that were deleted
+    pub removed: usize,
+    /// Mirrors that couldn't be changed (e.g. checked out), with the reason
+    pub skipped: Vec<String>,
+}
+
+/// Point `stk/<branch>/<slot>` at the commit of each `(commit, slot, previous)`
+/// and delete the mirrors of slots that are no longer in the stack
+///
+/// A mirror is only moved from `previous` (the commit the last export left the
+/// slot at) or from an ancestor of its new commit: one that has commits of its
+/// own, e.g. from working on it, is skipped.
+pub fn sync_mirrors(
+    repo: &Repository,
+    branch: &str,
+    slots: &[(Oid, String, Option<Oid>)],
+) -> Result<MirrorSync> {
+    let prefix = mirror_prefix(branch);
+    let mut sync = MirrorSync::default();
+
+    for (oid, slot, previous) in slots {
+        let name = format!("{}{}", prefix, slot);
+        let current = repo
+            .find_branch(&name, BranchType::Local)
+            .ok()
+            .and_then(|b| b.get().target());
+        if current == Some(*oid) {
+            continue;
+        }
+        if let Some(current) = current
+            && Some(current) != *previous
+            && !repo.graph_descendant_of(*oid, current).unwrap_or(false)
+        {
+            sync.skipped
+                .push(format!("{}: has commits that aren't in {:.7}", name, oid));
+            continue;
+        }
+        let commit = repo.find_commit(*oid).context("Failed to find commit")?;
+        match repo.branch(&name, &commit, true) {
+            Ok(_) => sync.updated += 1,
+            Err(e) => sync.skipped.push(format!("{}: {}", name, e.message())),
+        }
+    }
+
+    let stale: Vec<String> = repo
+        .branches(Some(BranchType::Local))
+        .context("Failed to list branches")?
+        .filter_map(|b| b.ok()?.0.name().ok().flatten().map(String::from))
+        .filter(|name| {
+            name.strip_prefix(&prefix)
+                .is_some_and(|slot| !slot.contains('/') && !slots.iter().any(|(_, s, _)| s == slot))
+        })
+        .collect();
+    for name in stale {
+        let deleted = repo
+            .find_branch(&name, BranchType::Local)
+            .and_then(|mut b| b.delete());
+        match deleted {
+            Ok(()) => sync.removed += 1,
+            Err(e) => sync.skipped.push(format!("{}: {}", name, e.message())),
+        }
+    }
+
+    Ok(sync)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_sync_mirrors() {
+        let dir = tempfile::tempdir().unwrap();
+        let repo = Repository::init(dir.path()).unwrap();
+        let signature = git2::Signature::now("Test", "test@example.com").unwrap();
+        let tree = repo
+            .find_tree(repo.treebuilder(None).unwrap().write().unwrap())
+            .unwrap();
+        let first = repo
+            .commit(None, &signature, &signature, "first", &tree, &[])
+            .unwrap();
+        let parent = repo.find_commit(first).unwrap();
+        let second = repo
+            .commit(None, &signature, &signature, "second", &tree, &[&parent])
+            .unwrap();
+        let target = |name: &str| {
+            repo.find_branch(name, BranchType::Local)
+                .ok()
+                .and_then(|b| b.get().target())
+        };
+
+        let sync = sync_mirrors(
+            &repo,
+            "feat",
+            &[(first, "01".into(), None), (second, "02".into(), None)],
+        )
+        .unwrap();
+        assert_eq!(sync.updated, 2);
+        assert_eq!(target("stk/feat/01"), Some(first));
+        assert_eq!(target("stk/feat/02"), Some(second));
+
+        // Slot 01 dropped from the stack, slot 02 rewritten from its last export
+        let sync = sync_mirrors(&repo, "feat", &[(first, "02".into(), Some(second))]).unwrap();
+        assert_eq!(
+            sync,
+            MirrorSync {
+                updated: 1,
+                removed: 1,
+                skipped: Vec::new()
+            }
+        );
+        assert_eq!(target("stk/feat/01"), None);
+        assert_eq!(target("stk/feat/02"), Some(first));
+
+        // Unchanged mirrors are left alone
+        assert_eq!(
+            sync_mirrors(&repo, "feat", &[(first, "02".into(), None)]).unwrap(),
+            MirrorSync::default()
+        );
+
+        // Fast-forwarded: moved
+        assert_eq!(
+            sync_mirrors(&repo, "feat", &[(second, "02".into(), None)])
+                .unwrap()
+                .updated,
+            1
+        );
+
+        // Committed on: left as is
+        let work = repo
+            .commit(
+                None,
+                &signature,
+                &signature,
+                "work",
+                &tree,
+                &[&repo.find_commit(second).unwrap()],
+            )
+            .unwrap();
+        repo.branch("stk/feat/02", &repo.find_commit(work).unwrap(), true)
+            .unwrap();
+        let sync = sync_mirrors(&repo, "feat", &[(first, "02".into(), Some(second))]).unwrap();
+        assert_eq!(sync.updated, 0);
+        assert_eq!(sync.skipped.len(), 1);
+        assert_eq!(target("stk/feat/02"), Some(work));
+    }
+}
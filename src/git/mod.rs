@@ -1,6 +1,26 @@
+pub mod backup;
+pub mod change_id;
 pub mod commit_ref;
+pub mod conventional;
+pub mod diff_stats;
+pub mod format_patch;
+pub mod history;
+pub mod issue_keys;
+pub mod jj;
+pub mod mirror;
+pub mod note_signature;
 pub mod notes;
+pub mod overlap;
+pub mod patch_id;
+pub mod range_diff;
 pub mod refs;
 pub mod reorder_detect;
 pub mod repo;
+pub mod rewrite;
+pub mod shared_state;
 pub mod slots;
+pub mod suggest;
+#[cfg(test)]
+pub mod testing;
+pub mod trailers;
+pub mod worktree;
@@ -0,0 +1,399 @@
+//! Signatures over git-stk notes. Notes are shared through a plain ref anyone
+//! with push access can rewrite, so with `git-stk.signNotes` each note carries
+//! a detached signature (made with the key git signs commits with) over the
+//! metadata and the commit it annotates, and discovery checks it.
+//!
+//! The commit is identified by its author and author date rather than its ID:
+//! those survive the rebases and amends that carry notes along
+//! (`notes.rewriteRef`), while a note moved onto another commit fails.
+
+use crate::model::CommitMetadata;
+use anyhow::{Context, Result, anyhow, bail};
+use git2::{Oid, Repository, Signature};
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::process::{Command, Stdio};
+
+/// SSH signature namespace, so note signatures can't pass for commit signatures
+const SSH_NAMESPACE: &str = "git-stk-notes";
+
+/// Outcome of checking a note's signature
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum NoteTrust {
+    /// Signed by `signer` (a GPG user ID or an SSH principal)
+    Valid { signer: String },
+    /// The note carries no signature
+    Unsigned,
+    /// The signature doesn't check out, or can't be checked
+    Invalid(String),
+}
+
+/// Signing tool, picked by `gpg.format` when signing and by the armor header when verifying
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Format {
+    OpenPgp,
+    X509,
+    Ssh,
+}
+
+impl Format {
+    fn from_config(repo: &Repository) -> Result<Self> {
+        let config = repo.config()?;
+        match config.get_string("gpg.format").as_deref() {
+            Err(_) | Ok("openpgp") => Ok(Self::OpenPgp),
+            Ok("x509") => Ok(Self::X509),
+            Ok("ssh") => Ok(Self::Ssh),
+            Ok(other) => bail!("Unsupported gpg.format '{}' for signing notes", other),
+        }
+    }
+
+    fn from_signature(signature: &str) -> Option<Self> {
+        let header = signature.trim_start().lines().next()?;
+        match header {
+            "-----BEGIN PGP SIGNATURE-----" => Some(Self::OpenPgp),
+            "-----BEGIN SIGNED MESSAGE-----" => Some(Self::X509),
+            "-----BEGIN SSH SIGNATURE-----" => Some(Self::Ssh),
+            _ => None,
+        }
+    }
+
+    /// Program from `gpg.<format>.program`, like git
+    fn program(self, repo: &Repository) -> Result<String> {
+        let config = repo.config()?;
+        let (key, default) = match self {
+            Self::OpenPgp => ("gpg.openpgp.program", "gpg"),
+            Self::X509 => ("gpg.x509.program", "gpgsm"),
+            Self::Ssh => ("gpg.ssh.program", "ssh-keygen"),
+        };
+        Ok(config
+            .get_string(key)
+            .or_else(|_| match self {
+                Self::OpenPgp => config.get_string("gpg.program"),
+                _ => Err(git2::Error::from_str("unset")),
+            })
+            .unwrap_or_else(|_| default.to_string()))
+    }
+}
+
+/// The signed text: the metadata without its signature, bound to the author of
+/// the commit it annotates
+pub fn payload(author: &Signature, metadata: &CommitMetadata) -> Result<String> {
+    let mut unsigned = metadata.clone();
+    unsigned.signature = None;
+    let json = serde_json::to_string(&unsigned).context("Failed to serialize metadata")?;
+    Ok(format!(
+        "git-stk note\nauthor {} <{}> {} {}\n{}\n",
+        author.name().unwrap_or(""),
+        author.email().unwrap_or(""),
+        author.when().seconds(),
+        author.when().offset_minutes(),
+        json
+    ))
+}
+
+fn commit_payload(repo: &Repository, oid: Oid, metadata: &CommitMetadata) -> Result<String> {
+    let commit = repo.find_commit(oid).context("Failed to find commit")?;
+    payload(&commit.author(), metadata)
+}
+
+/// Sign the note for `oid` with the user's git signing key
+pub fn sign(repo: &Repository, oid: Oid, metadata: &CommitMetadata) -> Result<String> {
+    let payload = commit_payload(repo, oid, metadata)?;
+    let format = Format::from_config(repo)?;
+    let program = format.program(repo)?;
+    let key = repo.config()?.get_string("user.signingKey").ok();
+
+    let output = match format {
+        Format::OpenPgp | Format::X509 => {
+            let key = match key {
+                Some(key) => key,
+                None => {
+                    let committer = repo.signature().context("Failed to create signature")?;
+                    format!(
+                        "{} <{}>",
+                        committer.name().unwrap_or(""),
+                        committer.email().unwrap_or("")
+                    )
+                }
+            };
+            let output = run(
+                Command::new(&program).args(["--status-fd=2", "-bsau", &key]),
+                &payload,
+            )?;
+            if output.status.success()
+                && !String::from_utf8_lossy(&output.stderr).contains("[GNUPG:] SIG_CREATED ")
+            {
+                bail!(
+                    "{} did not report a signature for the note on {}",
+                    program,
+                    oid
+                );
+            }
+            output
+        }
+        Format::Ssh => {
+            let key = key.context(
+                "gpg.format is ssh but user.signingKey is not set; it's needed to sign notes",
+            )?;
+            let literal = key
+                .strip_prefix("key::")
+                .or_else(|| key.starts_with("ssh-").then_some(key.as_str()));
+            match literal {
+                // A public key: the private half is in the agent, ssh-keygen wants it in a file
+                Some(public_key) => {
+                    let key_file = TempFile::write(oid, "pub", public_key)?;
+                    let mut command = Command::new(&program);
+                    command
+                        .args(["-Y", "sign", "-n", SSH_NAMESPACE, "-U", "-f"])
+                        .arg(&key_file.0);
+                    run(&mut command, &payload)?
+                }
+                None => {
+                    let mut command = Command::new(&program);
+                    command
+                        .args(["-Y", "sign", "-n", SSH_NAMESPACE, "-f"])
+                        .arg(expand_home(&key));
+                    run(&mut command, &payload)?
+                }
+            }
+        }
+    };
+
+    if !output.status.success() {
+        bail!(
+            "Failed to sign the note on {} with {}: {}",
+            oid,
+            program,
+            String::from_utf8_lossy(&output.stderr).trim()
+        );
+    }
+    String::from_utf8(output.stdout).context("Signature is not valid UTF-8")
+}
+
+/// Check the signature carried by a note on `oid`
+///
+/// GPG signatures must come from a key in the local keyring; SSH signatures
+/// from a principal in `gpg.ssh.allowedSignersFile`, as for `git verify-commit`.
+pub fn check(repo: &Repository, oid: Oid, metadata: &CommitMetadata) -> NoteTrust {
+    let Some(signature) = &metadata.signature else {
+        return NoteTrust::Unsigned;
+    };
+    match verify(repo, oid, metadata, signature) {
+        Ok(signer) => NoteTrust::Valid { signer },
+        Err(e) => NoteTrust::Invalid(e.to_string()),
+    }
+}
+
+fn verify(
+    repo: &Repository,
+    oid: Oid,
+    metadata: &CommitMetadata,
+    signature: &str,
+) -> Result<String> {
+    let format = Format::from_signature(signature).context("unrecognized signature format")?;
+    let program = format.program(repo)?;
+    let payload = commit_payload(repo, oid, metadata)?;
+    let signature_file = TempFile::write(oid, "sig", signature)?;
+
+    match format {
+        Format::OpenPgp | Format::X509 => {
+            let mut command = Command::new(&program);
+            command
+                .args(["--status-fd=1", "--verify"])
+                .arg(&signature_file.0)
+                .arg("-");
+            let output = run(&mut command, &payload)?;
+            good_signer(&String::from_utf8_lossy(&output.stdout))
+        }
+        Format::Ssh => {
+            let allowed_signers = repo
+                .config()?
+                .get_path("gpg.ssh.allowedSignersFile")
+                .map_err(|_| anyhow!("gpg.ssh.allowedSignersFile is not set"))?;
+
+            let output = Command::new(&program)
+                .args(["-Y", "find-principals", "-f"])
+                .arg(&allowed_signers)
+                .arg("-s")
+                .arg(&signature_file.0)
+                .stdin(Stdio::null())
+                .output()
+                .with_context(|| format!("Failed to execute {}", program))?;
+            let principals = String::from_utf8_lossy(&output.stdout);
+            let principal = principals
+                .lines()
+                .next()
+                .filter(|_| output.status.success())
+                .context("signed by a key that is not in gpg.ssh.allowedSignersFile")?
+                .to_string();
+
+            let mut command = Command::new(&program);
+            command
+                .args(["-Y", "verify", "-n", SSH_NAMESPACE, "-f"])
+                .arg(&allowed_signers)
+                .args(["-I", &principal, "-s"])
+                .arg(&signature_file.0);
+            let output = run(&mut command, &payload)?;
+            if !output.status.success() {
+                bail!("bad signature");
+            }
+            Ok(principal)
+        }
+    }
+}
+
+/// The signer of a good signature in GPG `--status-fd` output
+fn good_signer(status: &str) -> Result<String> {
+    let mut signer = None;
+    for line in status.lines() {
+        let Some(line) = line.strip_prefix("[GNUPG:] ") else {
+            continue;
+        };
+        let (keyword, rest) = line.split_once(' ').unwrap_or((line, ""));
+        match keyword {
+            "GOODSIG" => {
+                signer = Some(
+                    rest.split_once(' ')
+                        .map_or(rest, |(_, uid)| uid)
+                        .to_string(),
+                );
+            }
+            "BADSIG" => bail!("bad signature"),
+            "NO_PUBKEY" => bail!("signed by unknown key {}", rest),
+            "EXPKEYSIG" | "REVKEYSIG" => bail!("signed by an expired or revoked key"),
+            _ => {}
+        }
+    }
+    signer.context("no valid signature")
+}
+
+/// Run `command` with `input` on stdin
+fn run(command: &mut Command, input: &str) -> Result<std::process::Output> {
+    let program = command.get_program().to_string_lossy().into_owned();
+    let mut child = command
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+        .with_context(|| format!("Failed to execute {}", program))?;
+
+    child
+        .stdin
+        .take()
+        .with_context(|| format!("Failed to open {} stdin", program))?
+        .write_all(input.as_bytes())
+        .with_context(|| format!("Failed to write to {}", program))?;
+
+    child
+        .wait_with_output()
+        .with_context(|| format!("Failed to wait for {}", program))
+}
+
+/// Expand a leading `~/` like git does for `user.signingKey`
+fn expand_home(path: &str) -> PathBuf {
+    match (path.strip_prefix("~/"), std::env::var_os("HOME")) {
+        (Some(rest), Some(home)) => Path::new(&home).join(rest),
+        _ => PathBuf::from(path),
+    }
+}
+
+/// File handed to a signing tool, removed on drop
+struct TempFile(PathBuf);
+
+impl TempFile {
+    fn write(oid: Oid, extension: &str, contents: &str) -> Result<Self> {
+        let path = std::env::temp_dir().join(format!(
+            "git-stk-{}-{}.{}",
+            std::process::id(),
+            oid,
+            extension
+        ));
+        std::fs::write(&path, contents).with_context(|| format!("Failed to write {:?}", path))?;
+        Ok(Self(path))
+    }
+}
+
+impl Drop for TempFile {
+    fn drop(&mut self) {
+        let _ = std::fs::remove_file(&self.0);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn metadata(pr: Option<u64>, slot: &str) -> CommitMetadata {
+        CommitMetadata {
+            pr,
+            slot: slot.to_string(),
+            annotation: None,
+            last_export: None,
+            owner: None,
+            signature: None,
+        }
+    }
+
+    #[test]
+    fn test_payload_binds_author_and_ignores_signature() {
+        let time = git2::Time::new(1_700_000_000, 60);
+        let a = Signature::new("Jane Doe", "jane@example.com", &time).unwrap();
+        let b = Signature::new(
+            "Jane Doe",
+            "jane@example.com",
+            &git2::Time::new(1_700_000_001, 60),
+        )
+        .unwrap();
+        let note = metadata(Some(7), "01");
+
+        let mut signed = note.clone();
+        signed.signature = Some("sig".to_string());
+        assert_eq!(payload(&a, &note).unwrap(), payload(&a, &signed).unwrap());
+
+        assert_ne!(payload(&a, &note).unwrap(), payload(&b, &note).unwrap());
+        assert_ne!(
+            payload(&a, &note).unwrap(),
+            payload(&a, &metadata(Some(8), "01")).unwrap()
+        );
+        assert_ne!(
+            payload(&a, &note).unwrap(),
+            payload(&a, &metadata(Some(7), "02")).unwrap()
+        );
+    }
+
+    #[test]
+    fn test_format_from_signature() {
+        assert_eq!(
+            Format::from_signature("-----BEGIN PGP SIGNATURE-----\n\nabc\n"),
+            Some(Format::OpenPgp)
+        );
+        assert_eq!(
+            Format::from_signature("-----BEGIN SSH SIGNATURE-----\nabc\n"),
+            Some(Format::Ssh)
+        );
+        assert_eq!(
+            Format::from_signature("-----BEGIN SIGNED MESSAGE-----\nabc\n"),
+            Some(Format::X509)
+        );
+        assert_eq!(Format::from_signature("garbage"), None);
+    }
+
+    #[test]
+    fn test_good_signer() {
+        let good = "[GNUPG:] NEWSIG\n[GNUPG:] GOODSIG 0123456789ABCDEF Jane Doe <jane@example.com>\n[GNUPG:] VALIDSIG ...\n";
+        assert_eq!(good_signer(good).unwrap(), "Jane Doe <jane@example.com>");
+
+        let bad = "[GNUPG:] BADSIG 0123456789ABCDEF Jane Doe <jane@example.com>\n";
+        assert!(good_signer(bad).is_err());
+
+        let unknown = "[GNUPG:] ERRSIG 0123456789ABCDEF 1 8 00 1700000000 9\n[GNUPG:] NO_PUBKEY 0123456789ABCDEF\n";
+        assert!(
+            good_signer(unknown)
+                .unwrap_err()
+                .to_string()
+                .contains("unknown key")
+        );
+
+        assert!(good_signer("").is_err());
+    }
+}
@@ -1,15 +1,15 @@
-use crate::model::CommitMetadata;
-use anyhow::{anyhow, Context, Result};
+use crate::git::{note_signature, refs};
+use crate::model::{CommitMetadata, Transport};
+use crate::profile;
+use anyhow::{Context, Result, anyhow};
 use git2::{Oid, Repository};
-use std::process::Command;
+use tracing::{debug, instrument, warn};
 
 /// Read metadata from git notes for a commit
 pub fn read_note(repo: &Repository, oid: Oid, notes_ref: &str) -> Result<Option<CommitMetadata>> {
     match repo.find_note(Some(notes_ref), oid) {
         Ok(note) => {
-            let message = note
-                .message()
-                .context("Failed to read note message")?;
+            let message = note.message().context("Failed to read note message")?;
 
             // Try to parse the note normally
             match serde_json::from_str::<CommitMetadata>(message) {
@@ -24,7 +24,8 @@ pub fn read_note(repo: &Repository, oid: Oid, notes_ref: &str) -> Result<Option<
                         // Only rewrite if there's extra content after the first JSON
                         // (i.e., corrupted from concatenation)
                         if first_json.trim() != message.trim() {
-                            write_note(repo, oid, &metadata, notes_ref)?;
+                            warn!(%oid, "rewriting note corrupted by concatenation");
+                            store_note(repo, oid, &metadata, notes_ref)?;
                         }
 
                         Ok(Some(metadata))
@@ -39,18 +40,33 @@ pub fn read_note(repo: &Repository, oid: Oid, notes_ref: &str) -> Result<Option<
     }
 }
 
-/// Write metadata to git notes for a commit
+/// Write metadata to git notes for a commit, signed when `sign` is set
+/// (`git-stk.signNotes`); any signature carried over from another commit is dropped
 pub fn write_note(
     repo: &Repository,
     oid: Oid,
     metadata: &CommitMetadata,
     notes_ref: &str,
+    sign: bool,
 ) -> Result<()> {
-    let json = serde_json::to_string_pretty(metadata)
-        .context("Failed to serialize metadata")?;
+    let mut metadata = metadata.clone();
+    metadata.signature = None;
+    if sign {
+        metadata.signature = Some(note_signature::sign(repo, oid, &metadata)?);
+    }
+    store_note(repo, oid, &metadata, notes_ref)
+}
+
+/// Write metadata as-is, keeping its signature
+fn store_note(
+    repo: &Repository,
+    oid: Oid,
+    metadata: &CommitMetadata,
+    notes_ref: &str,
+) -> Result<()> {
+    let json = serde_json::to_string_pretty(metadata).context("Failed to serialize metadata")?;
 
-    let signature = repo.signature()
-        .context("Failed to create signature")?;
+    let signature = repo.signature().context("Failed to create signature")?;
 
     repo.note(&signature, &signature, Some(notes_ref), oid, &json, true)
         .context("Failed to write note")?;
@@ -60,8 +76,7 @@ pub fn write_note(
 
 /// Remove a note for a commit
 pub fn remove_note(repo: &Repository, oid: Oid, notes_ref: &str) -> Result<()> {
-    let signature = repo.signature()
-        .context("Failed to create signature")?;
+    let signature = repo.signature().context("Failed to create signature")?;
 
     repo.note_delete(oid, Some(notes_ref), &signature, &signature)
         .context("Failed to delete note")?;
@@ -69,20 +84,70 @@ pub fn remove_note(repo: &Repository, oid: Oid, notes_ref: &str) -> Result<()> {
     Ok(())
 }
 
-/// Push notes to remote to share metadata
-pub fn push_notes(repo: &Repository, remote: &str, notes_ref: &str) -> Result<()> {
+/// Local mirror of every user's namespaced notes (`<notesRef>/<login>` on the remote)
+const USER_NOTES_PREFIX: &str = "refs/git-stk/user-notes";
+
+/// Remote ref the local notes are pushed to: the notes ref itself, or
+/// `<notes_ref>/<login>` when notes are namespaced per user
+pub fn remote_notes_ref(notes_ref: &str, login: Option<&str>) -> String {
+    match login {
+        Some(login) => format!("{}/{}", notes_ref, login),
+        None => notes_ref.to_string(),
+    }
+}
+
+/// Fetch all per-user notes refs from the remote into the local mirror
+pub fn fetch_user_notes(
+    repo: &Repository,
+    remote: &str,
+    notes_ref: &str,
+    transport: &Transport,
+) -> Result<()> {
+    refs::fetch_refspecs(
+        repo,
+        remote,
+        &[format!("+{}/*:{}/*", notes_ref, USER_NOTES_PREFIX)],
+        transport,
+    )
+}
+
+/// Mirrored per-user notes refs (see [`fetch_user_notes`]), sorted by name
+pub fn user_notes_refs(repo: &Repository) -> Result<Vec<String>> {
+    let mut names: Vec<String> = repo
+        .references_glob(&format!("{}/*", USER_NOTES_PREFIX))?
+        .filter_map(|r| r.ok().and_then(|r| r.name().map(String::from)))
+        .collect();
+    names.sort();
+    Ok(names)
+}
+
+/// Push notes to `remote_ref` on the remote to share metadata; `no_verify` skips local pre-push hooks
+#[instrument(level = "debug", skip(repo))]
+pub fn push_notes(
+    repo: &Repository,
+    remote: &str,
+    notes_ref: &str,
+    remote_ref: &str,
+    no_verify: bool,
+    transport: &Transport,
+) -> Result<()> {
+    let _span = profile::span("git: push notes");
     let repo_path = repo
         .workdir()
         .ok_or_else(|| anyhow!("Repository has no working directory"))?;
 
     // Push notes using refspec with --force to handle deletions properly
     // This ensures that notes deleted locally (e.g., after reconciliation) are also deleted on the remote
-    let refspec = format!("{}:{}", notes_ref, notes_ref);
+    let refspec = format!("{}:{}", notes_ref, remote_ref);
+
+    debug!(remote, notes_ref, remote_ref, no_verify, "pushing notes");
+    let mut cmd = refs::git_command(repo_path, transport);
+    cmd.arg("push").arg("--force");
+    if no_verify {
+        cmd.arg("--no-verify");
+    }
 
-    let output = Command::new("git")
-        .current_dir(repo_path)
-        .arg("push")
-        .arg("--force")
+    let output = cmd
         .arg(remote)
         .arg(&refspec)
         .output()
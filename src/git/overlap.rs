@@ -0,0 +1,193 @@
+//! Detect stack entries that touch the same files, and entries that rewrite
+//! lines an earlier entry just introduced, so they can be squashed or reordered
+//! before review.
+
+use crate::model::Stack;
+use anyhow::{Context, Result};
+use git2::{DiffOptions, Oid, Repository};
+use std::cell::RefCell;
+use std::collections::HashMap;
+
+/// Lines a commit changes in one file: `(start, count)` ranges on the old
+/// (parent) side and the new (commit) side of each hunk
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct FileChange {
+    pub path: String,
+    pub old_ranges: Vec<(u32, u32)>,
+    pub new_ranges: Vec<(u32, u32)>,
+}
+
+/// Two entries changing the same file
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Overlap {
+    pub path: String,
+    /// Position in the stack (0-based) of the earlier entry
+    pub earlier: usize,
+    /// Position in the stack (0-based) of the later entry
+    pub later: usize,
+    /// Whether the later entry changes lines the earlier one added or modified
+    pub same_lines: bool,
+}
+
+/// Per-file hunks of a commit's diff against its first parent
+pub fn commit_file_changes(repo: &Repository, oid: Oid) -> Result<Vec<FileChange>> {
+    let commit = repo.find_commit(oid).context("Failed to find commit")?;
+    let tree = commit.tree().context("Failed to get commit tree")?;
+    let parent_tree = match commit.parent_count() {
+        0 => None,
+        _ => Some(
+            commit
+                .parent(0)
+                .and_then(|p| p.tree())
+                .context("Failed to get parent tree")?,
+        ),
+    };
+
+    let mut options = DiffOptions::new();
+    options.context_lines(0);
+    let diff = repo
+        .diff_tree_to_tree(parent_tree.as_ref(), Some(&tree), Some(&mut options))
+        .context("Failed to diff commit against its parent")?;
+
+    // Both callbacks need the list; foreach takes them as separate closures
+    let changes: RefCell<Vec<FileChange>> = RefCell::new(Vec::new());
+    diff.foreach(
+        &mut |delta, _| {
+            let path = delta.new_file().path().or_else(|| delta.old_file().path());
+            changes.borrow_mut().push(FileChange {
+                path: path
+                    .map(|p| p.to_string_lossy().into_owned())
+                    .unwrap_or_default(),
+                ..Default::default()
+            });
+            true
+        },
+        None,
+        Some(&mut |_, hunk| {
+            if let Some(change) = changes.borrow_mut().last_mut() {
+                change.old_ranges.push((hunk.old_start(), hunk.old_lines()));
+                change.new_ranges.push((hunk.new_start(), hunk.new_lines()));
+            }
+            true
+        }),
+        None,
+    )
+    .context("Failed to read commit diff")?;
+
+    Ok(changes.into_inner())
+}
+
+/// Overlaps between the entries of a stack
+pub fn stack_overlaps(repo: &Repository, stack: &Stack) -> Result<Vec<Overlap>> {
+    let changes = stack
+        .entries
+        .iter()
+        .map(|entry| commit_file_changes(repo, entry.oid))
+        .collect::<Result<Vec<_>>>()?;
+    Ok(find_overlaps(&changes))
+}
+
+/// Overlaps between entries, given each entry's file changes in stack order.
+///
+/// Each entry touching a file is paired with the previous entry that touched
+/// it. Since nothing in between changed the file, the later entry's old-side
+/// line numbers match the earlier entry's new side, so line overlap is exact.
+pub fn find_overlaps(entries: &[Vec<FileChange>]) -> Vec<Overlap> {
+    let mut last_change: HashMap<&str, (usize, &FileChange)> = HashMap::new();
+    let mut overlaps = Vec::new();
+
+    for (index, changes) in entries.iter().enumerate() {
+        for change in changes {
+            if let Some((earlier, previous)) = last_change.get(change.path.as_str()) {
+                let same_lines = change.old_ranges.iter().any(|later_range| {
+                    previous
+                        .new_ranges
+                        .iter()
+                        .any(|earlier_range| ranges_overlap(*later_range, *earlier_range))
+                });
+                overlaps.push(Overlap {
+                    path: change.path.clone(),
+                    earlier: *earlier,
+                    later: index,
+                    same_lines,
+                });
+            }
+            last_change.insert(change.path.as_str(), (index, change));
+        }
+    }
+
+    overlaps
+}
+
+/// Whether a later hunk's old-side range touches lines an earlier hunk introduced.
+/// Pure insertions (count 0) sit after their start line, so they count as touching it.
+fn ranges_overlap(later_old: (u32, u32), earlier_new: (u32, u32)) -> bool {
+    let (later_start, later_count) = later_old;
+    let (earlier_start, earlier_count) = earlier_new;
+    if earlier_count == 0 {
+        // The earlier hunk only deleted lines; nothing of it is left to rewrite
+        return false;
+    }
+    let later_end = later_start + later_count.max(1);
+    let earlier_end = earlier_start + earlier_count;
+    later_start < earlier_end && earlier_start < later_end
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn change(path: &str, old: &[(u32, u32)], new: &[(u32, u32)]) -> FileChange {
+        FileChange {
+            path: path.to_string(),
+            old_ranges: old.to_vec(),
+            new_ranges: new.to_vec(),
+        }
+    }
+
+    #[test]
+    fn test_find_overlaps() {
+        let entries = vec![
+            // Adds lines 10-14 of a.rs
+            vec![
+                change("a.rs", &[(9, 0)], &[(10, 5)]),
+                change("b.rs", &[(1, 1)], &[(1, 1)]),
+            ],
+            // Rewrites line 12 of a.rs, which the first entry added
+            vec![change("a.rs", &[(12, 1)], &[(12, 1)])],
+            // Edits a.rs far away, and c.rs
+            vec![
+                change("a.rs", &[(40, 2)], &[(40, 3)]),
+                change("c.rs", &[(0, 0)], &[(1, 3)]),
+            ],
+        ];
+
+        assert_eq!(
+            find_overlaps(&entries),
+            vec![
+                Overlap {
+                    path: "a.rs".to_string(),
+                    earlier: 0,
+                    later: 1,
+                    same_lines: true
+                },
+                Overlap {
+                    path: "a.rs".to_string(),
+                    earlier: 1,
+                    later: 2,
+                    same_lines: false
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn test_ranges_overlap() {
+        assert!(ranges_overlap((12, 1), (10, 5)));
+        assert!(!ranges_overlap((15, 1), (10, 5)));
+        // Inserting right after the last added line extends the same block
+        assert!(ranges_overlap((14, 0), (10, 5)));
+        assert!(!ranges_overlap((9, 0), (10, 5)));
+        assert!(!ranges_overlap((10, 3), (10, 0)));
+    }
+}
@@ -0,0 +1,102 @@
+use anyhow::{Context, Result};
+use git2::{Oid, Repository};
+
+/// Compute the patch ID of a commit (its diff against the first parent).
+///
+/// Patch IDs ignore line numbers and whitespace, so a commit and its
+/// squash-merged or rebased copy share the same ID when the change is identical.
+pub fn commit_patch_id(repo: &Repository, oid: Oid) -> Result<Oid> {
+    let commit = repo.find_commit(oid).context("Failed to find commit")?;
+    let tree = commit.tree().context("Failed to get commit tree")?;
+    let parent_tree = if commit.parent_count() > 0 {
+        Some(
+            commit
+                .parent(0)
+                .and_then(|p| p.tree())
+                .context("Failed to get parent tree")?,
+        )
+    } else {
+        None
+    };
+
+    let diff = repo
+        .diff_tree_to_tree(parent_tree.as_ref(), Some(&tree), None)
+        .context("Failed to diff commit against its parent")?;
+
+    diff.patchid(None).context("Failed to compute patch ID")
+}
+
+/// Whether two commits carry the same change: identical trees, or identical patch IDs
+pub fn commits_equivalent(repo: &Repository, a: Oid, b: Oid) -> Result<bool> {
+    if a == b {
+        return Ok(true);
+    }
+
+    let tree_a = repo
+        .find_commit(a)
+        .context("Failed to find commit")?
+        .tree_id();
+    let tree_b = repo
+        .find_commit(b)
+        .context("Failed to find commit")?
+        .tree_id();
+    if tree_a == tree_b {
+        return Ok(true);
+    }
+
+    Ok(commit_patch_id(repo, a)? == commit_patch_id(repo, b)?)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::path::Path;
+
+    fn commit_file(
+        repo: &Repository,
+        parent: Option<Oid>,
+        path: &str,
+        content: &str,
+        message: &str,
+    ) -> Oid {
+        let workdir = repo.workdir().unwrap();
+        std::fs::write(workdir.join(path), content).unwrap();
+
+        let mut index = repo.index().unwrap();
+        if let Some(parent) = parent {
+            index
+                .read_tree(&repo.find_commit(parent).unwrap().tree().unwrap())
+                .unwrap();
+        }
+        index.add_path(Path::new(path)).unwrap();
+        let tree = repo.find_tree(index.write_tree().unwrap()).unwrap();
+
+        let signature = git2::Signature::now("Test", "test@example.com").unwrap();
+        let parents: Vec<git2::Commit> = parent
+            .map(|p| repo.find_commit(p).unwrap())
+            .into_iter()
+            .collect();
+        let parent_refs: Vec<&git2::Commit> = parents.iter().collect();
+        repo.commit(None, &signature, &signature, message, &tree, &parent_refs)
+            .unwrap()
+    }
+
+    #[test]
+    fn test_commits_equivalent_across_bases() {
+        let dir = tempfile::tempdir().unwrap();
+        let repo = Repository::init(dir.path()).unwrap();
+
+        let base = commit_file(&repo, None, "a.txt", "a\n", "base");
+        let feature = commit_file(&repo, Some(base), "b.txt", "b\n", "feature");
+
+        // Same change applied on top of a moved base (like a squash merge)
+        let moved_base = commit_file(&repo, Some(base), "c.txt", "c\n", "other work");
+        let squashed = commit_file(&repo, Some(moved_base), "b.txt", "b\n", "feature (#1)");
+
+        // A different change on the same base
+        let unrelated = commit_file(&repo, Some(moved_base), "b.txt", "different\n", "unrelated");
+
+        assert!(commits_equivalent(&repo, feature, squashed).unwrap());
+        assert!(!commits_equivalent(&repo, feature, unrelated).unwrap());
+    }
+}
@@ -0,0 +1,58 @@
+//! `git range-diff` between two versions of a stack entry, as reviewers see
+//! it after a force-push.
+
+use anyhow::{Context, Result, anyhow};
+use git2::{Oid, Repository};
+use std::process::Command;
+
+/// Arguments of `git range-diff` (after the subcommand) comparing two
+/// versions of a single commit
+pub fn range_diff_args(old: Oid, new: Oid) -> Vec<String> {
+    vec![
+        // Two versions of one commit: always pair them, however much they differ
+        "--creation-factor=999".to_string(),
+        format!("{}^..{}", old, old),
+        format!("{}^..{}", new, new),
+    ]
+}
+
+/// The uncolored range-diff between two versions of a commit
+pub fn range_diff(repo: &Repository, old: Oid, new: Oid) -> Result<String> {
+    let repo_path = repo
+        .workdir()
+        .ok_or_else(|| anyhow!("Repository has no working directory"))?;
+    let output = Command::new("git")
+        .current_dir(repo_path)
+        .args(["--no-pager", "range-diff", "--no-color"])
+        .args(range_diff_args(old, new))
+        .output()
+        .context("Failed to execute git range-diff")?;
+
+    if !output.status.success() {
+        let stderr = String::from_utf8_lossy(&output.stderr);
+        return Err(anyhow!("git range-diff failed: {}", stderr.trim()));
+    }
+    Ok(String::from_utf8_lossy(&output.stdout).into_owned())
+}
+
+/// Whether a range-diff only pairs identical patches (`1: abc = 1: def`),
+/// i.e. the commit was rebased without changes
+pub fn is_unchanged(range_diff: &str) -> bool {
+    range_diff
+        .lines()
+        .filter(|line| !line.trim().is_empty())
+        .all(|line| line.split_whitespace().nth(2) == Some("="))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_is_unchanged() {
+        assert!(is_unchanged("1:  1074b31 = 1:  8d57c63 one\n"));
+        assert!(!is_unchanged(
+            "1:  1074b31 ! 1:  8d57c63 one\n    @@ Commit message\n    -+a\n    ++b\n"
+        ));
+    }
+}
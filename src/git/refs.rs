@@ -1,47 +1,318 @@
-use anyhow::{anyhow, Context, Result};
-use git2::{Oid, Repository};
-use std::collections::HashMap;
+use crate::model::{Config, Transport};
+use crate::profile;
+use anyhow::{Context, Result, anyhow};
+use git2::{
+    Cred, CredentialType, Direction, Oid, ProxyOptions, Remote, RemoteCallbacks, RemoteConnection,
+    Repository,
+};
+use serde::{Deserialize, Serialize};
+use std::collections::{HashMap, VecDeque};
+use std::fs;
+use std::path::Path;
 use std::process::{Command, Stdio};
+use tracing::{debug, instrument, warn};
 
 /// Get all remote refs in a single connection
 /// Returns a map of ref_name -> oid (without "refs/heads/" prefix)
-pub fn get_all_remote_refs(repo: &Repository, remote_name: &str) -> Result<HashMap<String, Oid>> {
+pub fn get_all_remote_refs(
+    repo: &Repository,
+    remote_name: &str,
+    transport: &Transport,
+) -> Result<HashMap<String, Oid>> {
+    get_remote_refs_matching(repo, remote_name, &[], transport)
+}
+
+/// Get remote branch refs matching ls-remote patterns (e.g., "refs/heads/feature--*")
+/// An empty pattern list lists all branches.
+/// Returns a map of ref_name -> oid (without "refs/heads/" prefix)
+#[instrument(level = "debug", skip(repo, transport))]
+pub fn get_remote_refs_matching(
+    repo: &Repository,
+    remote_name: &str,
+    patterns: &[String],
+    transport: &Transport,
+) -> Result<HashMap<String, Oid>> {
+    let _span = profile::span("git: ls-remote");
+
+    let remote_refs: HashMap<String, Oid> = ls_remote(repo, remote_name, patterns, transport)?
+        .into_iter()
+        .filter_map(|(name, oid)| Some((name.strip_prefix("refs/heads/")?.to_string(), oid)))
+        .collect();
+
+    debug!(count = remote_refs.len(), "listed remote refs");
+    Ok(remote_refs)
+}
+
+/// Full names of the remote's refs that are `prefix` or below it, e.g. the
+/// notes ref and its per-user namespaces
+#[instrument(level = "debug", skip(repo, transport))]
+pub fn get_remote_refs_under(
+    repo: &Repository,
+    remote_name: &str,
+    prefix: &str,
+    transport: &Transport,
+) -> Result<Vec<String>> {
+    let _span = profile::span("git: ls-remote");
+
+    let below = format!("{}/", prefix);
+    Ok(ls_remote(
+        repo,
+        remote_name,
+        &[prefix.to_string(), format!("{}*", below)],
+        transport,
+    )?
+    .into_iter()
+    .map(|(name, _)| name)
+    .filter(|name| *name == prefix || name.starts_with(&below))
+    .collect())
+}
+
+/// List the remote's refs matching ls-remote style `patterns` (all of them
+/// when empty) over one libgit2 connection, as (ref name, oid) pairs
+fn ls_remote(
+    repo: &Repository,
+    remote_name: &str,
+    patterns: &[String],
+    transport: &Transport,
+) -> Result<Vec<(String, Oid)>> {
+    debug!(remote = remote_name, ?patterns, "listing remote refs");
+
+    let mut remote = open_remote(repo, remote_name)?;
+    let connection = connect(repo, &mut remote, Direction::Fetch, transport)
+        .with_context(|| format!("Failed to list refs of remote '{}'", remote_name))?;
+
+    Ok(connection
+        .list()
+        .with_context(|| format!("Failed to list refs of remote '{}'", remote_name))?
+        .iter()
+        .filter(|head| patterns.is_empty() || patterns.iter().any(|p| glob_matches(p, head.name())))
+        .map(|head| (head.name().to_string(), head.oid()))
+        .collect())
+}
+
+/// Look up a remote by name (or URL). Relative local paths are resolved against
+/// the working directory, like git does, since libgit2 would use the process cwd.
+fn open_remote<'repo>(repo: &'repo Repository, remote_name: &str) -> Result<Remote<'repo>> {
+    let url = match repo.find_remote(remote_name) {
+        Ok(remote) => remote.url().unwrap_or(remote_name).to_string(),
+        Err(_) => remote_name.to_string(),
+    };
+
+    if let Some(workdir) = repo.workdir()
+        && !url.contains("://")
+        && !url.contains(':')
+        && std::path::Path::new(&url).is_relative()
+    {
+        let path = workdir.join(&url);
+        if path.exists() {
+            return Ok(repo.remote_anonymous(&path.to_string_lossy())?);
+        }
+    }
+
+    repo.find_remote(remote_name)
+        .or_else(|_| repo.remote_anonymous(remote_name))
+        .with_context(|| format!("Unknown remote '{}'", remote_name))
+}
+
+/// Open a connection to `remote`, authenticating the way git would: the
+/// ssh-agent, then the default ssh keys for SSH remotes, the configured
+/// credential helper for HTTPS ones. Goes through `transport`'s proxy, else
+/// the one git would use.
+fn connect<'repo, 'conn>(
+    repo: &Repository,
+    remote: &'conn mut Remote<'repo>,
+    direction: Direction,
+    transport: &Transport,
+) -> Result<RemoteConnection<'repo, 'conn, 'static>> {
+    let git_config = repo.config()?;
+    let mut attempts = 0;
+    let mut callbacks = RemoteCallbacks::new();
+    callbacks.credentials(move |url, username, allowed| {
+        // libgit2 calls back again after a rejected credential; give up eventually
+        attempts += 1;
+        if attempts > 4 {
+            return Err(git2::Error::from_str("authentication failed"));
+        }
+        let user = username.unwrap_or("git");
+        if allowed.contains(CredentialType::USERNAME) {
+            return Cred::username(user);
+        }
+        if allowed.contains(CredentialType::SSH_KEY) {
+            // The agent first, then the keys ssh would try by default
+            if attempts == 1 {
+                return Cred::ssh_key_from_agent(user);
+            }
+            let ssh_dir =
+                std::env::var_os("HOME").map(|home| std::path::Path::new(&home).join(".ssh"));
+            let key = ssh_dir
+                .iter()
+                .flat_map(|dir| ["id_ed25519", "id_ecdsa", "id_rsa"].map(|name| dir.join(name)))
+                .filter(|path| path.exists())
+                .nth(attempts - 2);
+            if let Some(key) = key {
+                return Cred::ssh_key(user, None, &key, None);
+            }
+        }
+        if allowed.contains(CredentialType::USER_PASS_PLAINTEXT)
+            && let Ok(cred) = Cred::credential_helper(&git_config, url, username)
+        {
+            return Ok(cred);
+        }
+        Cred::default()
+    });
+
+    if let Some(ca_info) = &transport.ca_info {
+        trust_ca_bundle(ca_info);
+    }
+
+    let mut proxy = ProxyOptions::new();
+    if let Some(url) = &transport.proxy {
+        proxy.url(url);
+    } else {
+        proxy.auto();
+    }
+
+    Ok(remote.connect_auth(direction, Some(callbacks), Some(proxy))?)
+}
+
+/// Make libgit2 trust the CA bundle at `path` (process-wide, set once)
+fn trust_ca_bundle(path: &Path) {
+    static ONCE: std::sync::Once = std::sync::Once::new();
+    ONCE.call_once(|| {
+        // SAFETY: runs once, before this process opens any libgit2 TLS connection
+        if let Err(e) = unsafe { git2::opts::set_ssl_cert_file(path) } {
+            warn!(error = %e, path = %path.display(), "failed to load CA bundle for libgit2");
+        }
+    });
+}
+
+/// What git prints when it can't reach a remote at all, over HTTP(S) or ssh
+const UNREACHABLE_MESSAGES: &[&str] = &[
+    "Could not resolve host",
+    "failed to resolve address",
+    "Could not resolve hostname",
+    "Could not resolve proxy",
+    "Failed to connect to",
+    "Connection refused",
+    "Connection timed out",
+    "Operation timed out",
+    "Network is unreachable",
+    "No route to host",
+    "Temporary failure in name resolution",
+];
+
+/// Whether listing or fetching a remote failed because it couldn't be reached
+/// (DNS, refused connection, timeout) rather than because it refused access
+pub fn is_unreachable(error: &anyhow::Error) -> bool {
+    error.chain().any(|cause| {
+        UNREACHABLE_MESSAGES
+            .iter()
+            .any(|message| cause.to_string().contains(message))
+    })
+}
+
+/// The remote's branches as of the last fetch or push (`refs/remotes/<remote>/*`),
+/// for when the remote can't be reached. Same shape as `get_remote_refs_matching`.
+pub fn get_tracking_refs(repo: &Repository, remote_name: &str) -> Result<HashMap<String, Oid>> {
+    let prefix = format!("refs/remotes/{}/", remote_name);
+    let mut tracking_refs = HashMap::new();
+    for reference in repo
+        .references_glob(&format!("{}*", prefix))
+        .context("Failed to list remote-tracking branches")?
+    {
+        let reference = reference?;
+        if let (Some(name), Some(oid)) = (reference.name(), reference.target())
+            && let Some(branch_name) = name.strip_prefix(&prefix)
+            && branch_name != "HEAD"
+        {
+            tracking_refs.insert(branch_name.to_string(), oid);
+        }
+    }
+    Ok(tracking_refs)
+}
+
+/// When the repository last fetched (the time `FETCH_HEAD` was written), in
+/// seconds since the Unix epoch
+pub fn last_fetch_time(repo: &Repository) -> Option<i64> {
+    let modified = fs::metadata(repo.path().join("FETCH_HEAD"))
+        .ok()?
+        .modified()
+        .ok()?;
+    let seconds = modified
+        .duration_since(std::time::UNIX_EPOCH)
+        .ok()?
+        .as_secs();
+    i64::try_from(seconds).ok()
+}
+
+/// Match a ref name against an ls-remote style glob, where `*` matches any run of characters
+pub(crate) fn glob_matches(pattern: &str, name: &str) -> bool {
+    let mut parts = pattern.split('*');
+    let first = parts.next().unwrap_or("");
+    let Some(mut rest) = name.strip_prefix(first) else {
+        return false;
+    };
+    let parts: Vec<&str> = parts.collect();
+    let Some((last, middle)) = parts.split_last() else {
+        // No wildcard: exact match
+        return rest.is_empty();
+    };
+    for part in middle {
+        match rest.find(part) {
+            Some(i) => rest = &rest[i + part.len()..],
+            None => return false,
+        }
+    }
+    rest.ends_with(last)
+}
+
+/// `git` in `repo_path` for a command that reaches a remote, going through
+/// `transport`'s proxy and CA bundle
+pub fn git_command(repo_path: &Path, transport: &Transport) -> Command {
+    let mut cmd = Command::new("git");
+    cmd.current_dir(repo_path);
+    if let Some(proxy) = &transport.proxy {
+        cmd.arg("-c").arg(format!("http.proxy={}", proxy));
+    }
+    if let Some(ca_info) = &transport.ca_info {
+        cmd.arg("-c")
+            .arg(format!("http.sslCAInfo={}", ca_info.display()));
+    }
+    cmd
+}
+
+/// Fetch refspecs from a remote (e.g., "+refs/heads/foo:refs/git-stk/...")
+#[instrument(level = "debug", skip(repo, transport))]
+pub fn fetch_refspecs(
+    repo: &Repository,
+    remote_name: &str,
+    refspecs: &[String],
+    transport: &Transport,
+) -> Result<()> {
+    let _span = profile::span("git: fetch");
     let repo_path = repo
         .workdir()
         .ok_or_else(|| anyhow!("Repository has no working directory"))?;
 
-    // Use git ls-remote to list all remote refs
-    let output = Command::new("git")
-        .current_dir(repo_path)
-        .arg("ls-remote")
-        .arg("--heads")
+    debug!(remote = remote_name, ?refspecs, "git fetch");
+    let output = git_command(repo_path, transport)
+        .arg("fetch")
+        .arg("--quiet")
         .arg(remote_name)
+        .args(refspecs)
         .output()
-        .context("Failed to execute git ls-remote")?;
+        .context("Failed to execute git fetch")?;
 
     if !output.status.success() {
         let stderr = String::from_utf8_lossy(&output.stderr);
-        return Err(anyhow!("Failed to list remote refs: {}", stderr));
-    }
-
-    let stdout = String::from_utf8_lossy(&output.stdout);
-    let mut remote_refs: HashMap<String, Oid> = HashMap::new();
-
-    for line in stdout.lines() {
-        let parts: Vec<&str> = line.split_whitespace().collect();
-        if parts.len() >= 2 {
-            let oid_str = parts[0];
-            let ref_name = parts[1];
-
-            if let Some(branch_name) = ref_name.strip_prefix("refs/heads/") {
-                if let Ok(oid) = Oid::from_str(oid_str) {
-                    remote_refs.insert(branch_name.to_string(), oid);
-                }
-            }
-        }
+        return Err(anyhow!(
+            "Failed to fetch {}: {}",
+            refspecs.join(" "),
+            stderr.trim()
+        ));
     }
 
-    Ok(remote_refs)
+    Ok(())
 }
 
 /// Result of pushing refs to remote
@@ -56,33 +327,128 @@ pub struct PushResult {
 }
 
 /// Capability cache for remote features
-#[derive(Debug, Clone, Default)]
+///
+/// Persisted in `.git/git-stk/capabilities.json`, keyed by remote URL so that
+/// pointing a remote elsewhere triggers a new probe.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
 pub struct RemoteCapabilities {
     /// Whether the remote supports atomic pushes
     pub supports_atomic: Option<bool>,
 }
 
+/// On-disk format of the capabilities cache
+#[derive(Debug, Default, Serialize, Deserialize)]
+struct CapabilitiesFile {
+    /// Remote URL -> capabilities
+    remotes: HashMap<String, RemoteCapabilities>,
+}
+
+impl CapabilitiesFile {
+    fn load(repo: &Repository) -> Result<Self> {
+        let path = Config::capabilities_cache_path(repo)?;
+        if !path.exists() {
+            return Ok(Self::default());
+        }
+
+        let contents = fs::read_to_string(&path)
+            .with_context(|| format!("Failed to read capabilities cache from {:?}", path))?;
+        // A corrupt cache is only a missed optimization
+        Ok(serde_json::from_str(&contents).unwrap_or_default())
+    }
+
+    fn save(&self, repo: &Repository) -> Result<()> {
+        let path = Config::capabilities_cache_path(repo)?;
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent)
+                .with_context(|| format!("Failed to create directory {:?}", parent))?;
+        }
+
+        let json =
+            serde_json::to_string_pretty(self).context("Failed to serialize capabilities cache")?;
+        fs::write(&path, json)
+            .with_context(|| format!("Failed to write capabilities cache to {:?}", path))
+    }
+}
+
+/// URL of a configured remote; a remote given directly as a URL or path is its own key
+fn remote_url(repo: &Repository, remote: &str) -> String {
+    repo.find_remote(remote)
+        .ok()
+        .and_then(|r| r.url().map(String::from))
+        .unwrap_or_else(|| remote.to_string())
+}
+
+/// Whether `git push --atomic` output says the remote can't do atomic pushes
+/// (as opposed to an atomic push that was rejected or couldn't reach the remote):
+/// git's "the receiving end does not support --atomic push", or a remote
+/// helper's "helper <name> does not support --atomic"
+fn is_atomic_unsupported(stderr: &str) -> bool {
+    stderr.contains("does not support --atomic")
+}
+
 impl RemoteCapabilities {
-    /// Detect if remote supports atomic push
-    pub fn detect_atomic_support(
+    /// Load the cached capabilities of `remote` (empty if never probed or the URL changed)
+    pub fn load(repo: &Repository, remote: &str) -> Self {
+        CapabilitiesFile::load(repo)
+            .ok()
+            .and_then(|mut file| file.remotes.remove(&remote_url(repo, remote)))
+            .unwrap_or_default()
+    }
+
+    /// Persist these capabilities for `remote`'s current URL
+    pub fn save(&self, repo: &Repository, remote: &str) -> Result<()> {
+        let mut file = CapabilitiesFile::load(repo)?;
+        file.remotes.insert(remote_url(repo, remote), self.clone());
+        file.save(repo)
+    }
+
+    /// Whether the remote supports atomic pushes, probing and persisting the answer if unknown
+    ///
+    /// A probe that answered neither way (e.g., the remote was unreachable) is
+    /// not remembered, and atomic pushes are tried.
+    pub fn atomic_support(
+        &mut self,
         repo: &Repository,
         remote: &str,
         refspecs: &[String],
+        transport: &Transport,
     ) -> Result<bool> {
+        if let Some(supports) = self.supports_atomic {
+            return Ok(supports);
+        }
+
+        let Some(supports) = Self::detect_atomic_support(repo, remote, refspecs, transport)? else {
+            return Ok(!refspecs.is_empty());
+        };
+        self.supports_atomic = Some(supports);
+        if let Err(e) = self.save(repo, remote) {
+            warn!(error = %e, "failed to persist remote capabilities");
+        }
+        Ok(supports)
+    }
+
+    /// Detect if remote supports atomic push: `None` when the probe couldn't tell
+    pub fn detect_atomic_support(
+        repo: &Repository,
+        remote: &str,
+        refspecs: &[String],
+        transport: &Transport,
+    ) -> Result<Option<bool>> {
+        // Nothing to probe with
         if refspecs.is_empty() {
-            return Ok(false);
+            return Ok(None);
         }
 
         let repo_path = repo
             .workdir()
             .ok_or_else(|| anyhow!("Repository has no working directory"))?;
 
-        // Try a dry-run atomic push
-        let mut cmd = Command::new("git");
-        cmd.current_dir(repo_path)
-            .arg("push")
+        // Try a dry-run atomic push (hooks would otherwise run for the probe too)
+        let mut cmd = git_command(repo_path, transport);
+        cmd.arg("push")
             .arg("--atomic")
             .arg("--dry-run")
+            .arg("--no-verify")
             .arg("--porcelain")
             .arg(remote)
             .arg(&refspecs[0]) // Just test with one refspec
@@ -91,51 +457,252 @@ impl RemoteCapabilities {
 
         let output = cmd.output().context("Failed to execute git push dry-run")?;
 
-        // Check stderr for atomic support error
+        // Only the server's capability error is an answer: a rejected ref, a
+        // failed hook or a network error says nothing about atomic support
         let stderr = String::from_utf8_lossy(&output.stderr);
-
-        if stderr.contains("does not support --atomic")
-            || stderr.contains("support for the --atomic option")
-            || stderr.contains("atomic push") {
-            Ok(false)
+        if is_atomic_unsupported(&stderr) {
+            debug!(remote, "remote does not support atomic pushes");
+            Ok(Some(false))
+        } else if output.status.success() {
+            Ok(Some(true))
         } else {
-            // If no error about atomic, assume it's supported
-            Ok(true)
+            debug!(remote, stderr = %stderr.trim(), "atomic push probe failed, support unknown");
+            Ok(None)
         }
     }
 }
 
+/// Progress of a push split into several chunks
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ChunkProgress {
+    /// Refs pushed so far, including this chunk
+    pub pushed: usize,
+    pub total: usize,
+}
+
 /// Push refs to remote with atomic support (preferred) or fallback
-/// Always uses --force for pushing
+/// Always uses --force for pushing; `no_verify` skips local pre-push hooks
+///
+/// Pushes at most `max_refs_per_push` refs at a time (0 for no limit). A chunk
+/// the remote rejects as too large is split in half and retried, so huge
+/// pushes go through without configuration; `progress` is called after each
+/// chunk once a push is split. Atomicity only holds within a chunk.
+#[allow(clippy::too_many_arguments)]
+#[instrument(level = "debug", skip(repo, capabilities, transport, progress))]
 pub fn push_refs(
     repo: &Repository,
     remote: &str,
     refspecs: &[String],
     capabilities: &mut RemoteCapabilities,
+    no_verify: bool,
+    max_refs_per_push: usize,
+    transport: &Transport,
+    progress: &mut dyn FnMut(ChunkProgress),
 ) -> Result<PushResult> {
-    if refspecs.is_empty() {
-        return Ok(PushResult {
-            success: true,
-            pushed_refs: vec![],
-            error: None,
-        });
+    let _span = profile::span("git: push");
+    let mut queue: VecDeque<&[String]> = chunk_refspecs(refspecs, max_refs_per_push).into();
+    let mut chunked = queue.len() > 1;
+    let mut pushed_refs = Vec::new();
+
+    while let Some(chunk) = queue.pop_front() {
+        let result = push_chunk(repo, remote, chunk, capabilities, no_verify, transport)?;
+
+        if let Some(error) = result.error {
+            if result.pushed_refs.is_empty() && chunk.len() > 1 && is_push_too_large(&error) {
+                debug!(remote, refs = chunk.len(), "push too large, splitting it");
+                let (first, second) = chunk.split_at(chunk.len() / 2);
+                queue.push_front(second);
+                queue.push_front(first);
+                chunked = true;
+                continue;
+            }
+
+            pushed_refs.extend(result.pushed_refs);
+            let hint = if is_push_too_large(&error) {
+                "\nThe remote rejected the push as too large, even for a single branch."
+            } else {
+                ""
+            };
+            return Ok(PushResult {
+                success: false,
+                pushed_refs,
+                error: Some(format!("{}{}", error, hint)),
+            });
+        }
+
+        pushed_refs.extend(result.pushed_refs);
+        if chunked {
+            progress(ChunkProgress {
+                pushed: pushed_refs.len(),
+                total: refspecs.len(),
+            });
+        }
     }
 
-    // Detect atomic support if not cached
-    if capabilities.supports_atomic.is_none() {
-        let supports = RemoteCapabilities::detect_atomic_support(repo, remote, refspecs)?;
-        capabilities.supports_atomic = Some(supports);
+    Ok(PushResult {
+        success: true,
+        pushed_refs,
+        error: None,
+    })
+}
+
+/// Split refspecs into pushes of at most `max` refs (0 for a single push)
+fn chunk_refspecs(refspecs: &[String], max: usize) -> Vec<&[String]> {
+    match max {
+        0 => vec![refspecs]
+            .into_iter()
+            .filter(|c| !c.is_empty())
+            .collect(),
+        max => refspecs.chunks(max).collect(),
     }
+}
+
+/// Whether a push failed because the remote refused its size, rather than a ref
+///
+/// Only size-specific messages count: a dropped connection ("RPC failed", "the
+/// remote end hung up unexpectedly") has many causes, and splitting won't fix them.
+fn is_push_too_large(error: &str) -> bool {
+    let error = error.to_lowercase();
+    [
+        "pack exceeds maximum allowed size",
+        "http 413",
+        "returned error: 413",
+        "request entity too large",
+    ]
+    .iter()
+    .any(|pattern| error.contains(pattern))
+}
 
-    let supports_atomic = capabilities.supports_atomic.unwrap_or(false);
+/// Push one chunk of refs atomically if the remote supports it, one by one otherwise
+fn push_chunk(
+    repo: &Repository,
+    remote: &str,
+    refspecs: &[String],
+    capabilities: &mut RemoteCapabilities,
+    no_verify: bool,
+    transport: &Transport,
+) -> Result<PushResult> {
+    // Detect atomic support if not cached
+    let supports_atomic = capabilities.atomic_support(repo, remote, refspecs, transport)?;
 
     if supports_atomic {
         // Try atomic push
-        push_atomic(repo, remote, refspecs)
+        let result = push_atomic(repo, remote, refspecs, no_verify, transport)?;
+        if result.error.as_deref().is_some_and(is_atomic_unsupported) {
+            // The cached answer went stale (e.g., the server changed). Nothing was
+            // pushed, so push one by one; the next run probes again rather than
+            // trusting a single failed push
+            debug!(remote, "remote no longer supports atomic pushes");
+            capabilities.supports_atomic = None;
+            if let Err(e) = capabilities.save(repo, remote) {
+                warn!(error = %e, "failed to persist remote capabilities");
+            }
+            capabilities.supports_atomic = Some(false);
+            return push_top_down(repo, remote, refspecs, no_verify, transport);
+        }
+        Ok(result)
     } else {
         // Fallback to top-down individual pushes
-        push_top_down(repo, remote, refspecs)
+        push_top_down(repo, remote, refspecs, no_verify, transport)
+    }
+}
+
+/// Reset remote branches to earlier OIDs in one atomic push, deleting those
+/// that didn't exist before. Each update is leased on the OID we pushed, so
+/// branches someone else changed in the meantime are left alone.
+#[instrument(level = "debug", skip(repo, transport))]
+pub fn restore_remote_refs(
+    repo: &Repository,
+    remote: &str,
+    restores: &[(String, Option<Oid>, Oid)], // (head ref, previous oid, pushed oid)
+    transport: &Transport,
+) -> Result<()> {
+    let _span = profile::span("git: push");
+    if restores.is_empty() {
+        return Ok(());
+    }
+
+    let repo_path = repo
+        .workdir()
+        .ok_or_else(|| anyhow!("Repository has no working directory"))?;
+
+    let mut cmd = git_command(repo_path, transport);
+    cmd.arg("push")
+        .arg("--atomic")
+        .arg("--no-verify")
+        .arg("--porcelain");
+    for (head_ref, _, pushed) in restores {
+        cmd.arg(format!(
+            "--force-with-lease=refs/heads/{}:{}",
+            head_ref, pushed
+        ));
+    }
+    cmd.arg(remote);
+    for (head_ref, previous, _) in restores {
+        match previous {
+            Some(oid) => cmd.arg(format!("{}:refs/heads/{}", oid, head_ref)),
+            None => cmd.arg(format!(":refs/heads/{}", head_ref)),
+        };
+    }
+
+    debug!(remote, ?restores, "restoring remote refs");
+    let output = cmd
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .output()
+        .context("Failed to execute git push")?;
+
+    if !output.status.success() {
+        let stderr = String::from_utf8_lossy(&output.stderr);
+        return Err(anyhow!("Failed to restore remote refs: {}", stderr.trim()));
+    }
+
+    Ok(())
+}
+
+/// Push `(source, destination ref, expected oid)` updates, each leased on
+/// the oid the destination had when last fetched (`None`: it didn't exist),
+/// so an update someone else pushed in the meantime is never overwritten
+#[instrument(level = "debug", skip(repo, transport))]
+pub fn push_leased(
+    repo: &Repository,
+    remote: &str,
+    updates: &[(String, String, Option<Oid>)],
+    no_verify: bool,
+    transport: &Transport,
+) -> Result<()> {
+    let _span = profile::span("git: push");
+    let repo_path = repo
+        .workdir()
+        .ok_or_else(|| anyhow!("Repository has no working directory"))?;
+
+    let mut cmd = git_command(repo_path, transport);
+    cmd.arg("push").arg("--porcelain");
+    if no_verify {
+        cmd.arg("--no-verify");
+    }
+    for (_, destination, expected) in updates {
+        let expected = expected.map(|oid| oid.to_string()).unwrap_or_default();
+        cmd.arg(format!("--force-with-lease={}:{}", destination, expected));
     }
+    cmd.arg(remote);
+    for (source, destination, _) in updates {
+        cmd.arg(format!("{}:{}", source, destination));
+    }
+
+    debug!(remote, ?updates, "leased push");
+    let output = cmd
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .output()
+        .context("Failed to execute git push")?;
+
+    if !output.status.success() {
+        let stderr = String::from_utf8_lossy(&output.stderr);
+        return Err(anyhow!("{}", stderr.trim()));
+    }
+
+    Ok(())
 }
 
 /// Push all refs atomically (all succeed or all fail)
@@ -144,17 +711,21 @@ fn push_atomic(
     repo: &Repository,
     remote: &str,
     refspecs: &[String],
+    no_verify: bool,
+    transport: &Transport,
 ) -> Result<PushResult> {
     let repo_path = repo
         .workdir()
         .ok_or_else(|| anyhow!("Repository has no working directory"))?;
 
-    let mut cmd = Command::new("git");
-    cmd.current_dir(repo_path)
-        .arg("push")
+    let mut cmd = git_command(repo_path, transport);
+    cmd.arg("push")
         .arg("--atomic")
         .arg("--force")
         .arg("--porcelain");
+    if no_verify {
+        cmd.arg("--no-verify");
+    }
 
     cmd.arg(remote);
 
@@ -164,6 +735,7 @@ fn push_atomic(
 
     cmd.stdout(Stdio::piped()).stderr(Stdio::piped());
 
+    debug!(remote, ?refspecs, no_verify, "git push --atomic");
     let output = cmd
         .output()
         .context("Failed to execute git push --atomic")?;
@@ -176,6 +748,7 @@ fn push_atomic(
         })
     } else {
         let stderr = String::from_utf8_lossy(&output.stderr);
+        warn!(remote, stderr = %stderr.trim(), "atomic push failed");
         Ok(PushResult {
             success: false,
             pushed_refs: vec![],
@@ -190,6 +763,8 @@ fn push_top_down(
     repo: &Repository,
     remote: &str,
     refspecs: &[String],
+    no_verify: bool,
+    transport: &Transport,
 ) -> Result<PushResult> {
     let repo_path = repo
         .workdir()
@@ -204,15 +779,16 @@ fn push_top_down(
     sorted_refspecs.sort_by(|a, b| b.cmp(a));
 
     for refspec in sorted_refspecs {
-        let mut cmd = Command::new("git");
-        cmd.current_dir(repo_path)
-            .arg("push")
-            .arg("--force")
-            .arg("--porcelain");
+        let mut cmd = git_command(repo_path, transport);
+        cmd.arg("push").arg("--force").arg("--porcelain");
+        if no_verify {
+            cmd.arg("--no-verify");
+        }
 
         cmd.arg(remote).arg(&refspec);
         cmd.stdout(Stdio::piped()).stderr(Stdio::piped());
 
+        debug!(remote, %refspec, no_verify, "git push");
         let output = cmd
             .output()
             .with_context(|| format!("Failed to execute git push for {}", refspec))?;
@@ -221,6 +797,7 @@ fn push_top_down(
             pushed.push(refspec.clone());
         } else {
             let stderr = String::from_utf8_lossy(&output.stderr);
+            warn!(%refspec, stderr = %stderr.trim(), "push failed");
             errors.push(format!("{}: {}", refspec, stderr));
         }
     }
@@ -240,6 +817,83 @@ fn push_top_down(
     }
 }
 
+/// Run the local `pre-push` hook once for a whole set of ref updates
+///
+/// Mirrors what git feeds the hook: `<remote> <url>` as arguments and one
+/// `<local ref> <local oid> <remote ref> <remote oid>` line per update on stdin.
+/// Does nothing if no executable hook is installed.
+pub fn run_pre_push_hook(
+    repo: &Repository,
+    remote: &str,
+    updates: &[(Oid, String, Option<Oid>)], // (local oid, head ref, current remote oid)
+) -> Result<()> {
+    let repo_path = repo
+        .workdir()
+        .ok_or_else(|| anyhow!("Repository has no working directory"))?;
+
+    let hooks_dir = match repo.config()?.get_path("core.hooksPath") {
+        Ok(path) => repo_path.join(path),
+        Err(_) => repo.path().join("hooks"),
+    };
+    let hook = hooks_dir.join("pre-push");
+    if !is_executable(&hook) || updates.is_empty() {
+        return Ok(());
+    }
+
+    let url = repo
+        .find_remote(remote)
+        .ok()
+        .and_then(|r| r.url().map(String::from))
+        .unwrap_or_else(|| remote.to_string());
+
+    let stdin: String = updates
+        .iter()
+        .map(|(oid, head_ref, remote_oid)| {
+            format!(
+                "{} {} refs/heads/{} {}\n",
+                oid,
+                oid,
+                head_ref,
+                remote_oid.unwrap_or_else(Oid::zero)
+            )
+        })
+        .collect();
+
+    debug!(hook = %hook.display(), refs = updates.len(), "running pre-push hook once");
+    let mut child = Command::new(&hook)
+        .current_dir(repo_path)
+        .arg(remote)
+        .arg(&url)
+        .stdin(Stdio::piped())
+        .spawn()
+        .with_context(|| format!("Failed to run {}", hook.display()))?;
+
+    if let Some(mut hook_stdin) = child.stdin.take() {
+        use std::io::Write;
+        // The hook may exit without reading its input; that's not an error
+        let _ = hook_stdin.write_all(stdin.as_bytes());
+    }
+
+    let status = child.wait().context("Failed to wait for pre-push hook")?;
+    if !status.success() {
+        return Err(anyhow!("pre-push hook failed ({})", status));
+    }
+
+    Ok(())
+}
+
+#[cfg(unix)]
+fn is_executable(path: &Path) -> bool {
+    use std::os::unix::fs::PermissionsExt;
+    path.metadata()
+        .is_ok_and(|m| m.is_file() && m.permissions().mode() & 0o111 != 0)
+}
+
+#[cfg(not(unix))]
+fn is_executable(path: &Path) -> bool {
+    path.is_file()
+}
+
 /// Build refspecs for pushing commits directly to remote refs (no local branch needed)
 /// Format: {oid}:refs/heads/{head_ref}
 pub fn build_refspecs_from_oids(commits: &[(Oid, String)]) -> Vec<String> {
@@ -255,30 +909,171 @@ pub fn check_commits_to_push(
     repo: &Repository,
     remote_name: &str,
     commits: &[(Oid, String)], // (commit_oid, head_ref)
+    transport: &Transport,
 ) -> Result<HashMap<String, (bool, Option<Oid>)>> {
-    let mut result = HashMap::new();
+    let remote_refs = get_all_remote_refs(repo, remote_name, transport)?;
 
-    // Get all remote refs using git ls-remote
-    let remote_refs = get_all_remote_refs(repo, remote_name)?;
+    Ok(compare_commits_to_remote(&remote_refs, commits))
+}
+
+/// Compare commits against already-fetched remote refs
+/// Returns a map of head_ref -> (needs_push, remote_oid)
+pub fn compare_commits_to_remote(
+    remote_refs: &HashMap<String, Oid>,
+    commits: &[(Oid, String)], // (commit_oid, head_ref)
+) -> HashMap<String, (bool, Option<Oid>)> {
+    let mut result = HashMap::new();
 
     for (commit_oid, head_ref) in commits {
         let remote_oid = remote_refs.get(head_ref).copied();
 
         let needs_push = match remote_oid {
-            None => true, // Remote ref doesn't exist
+            None => true,                          // Remote ref doesn't exist
             Some(remote) => *commit_oid != remote, // Commit differs from remote
         };
 
         result.insert(head_ref.clone(), (needs_push, remote_oid));
     }
 
-    Ok(result)
+    result
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
 
+    #[test]
+    fn test_chunk_refspecs() {
+        let refspecs: Vec<String> = (1..=5)
+            .map(|i| format!("{}:refs/heads/feat--0{}", i, i))
+            .collect();
+        let sizes = |max| {
+            chunk_refspecs(&refspecs, max)
+                .iter()
+                .map(|c| c.len())
+                .collect::<Vec<_>>()
+        };
+
+        assert_eq!(sizes(0), vec![5]);
+        assert_eq!(sizes(2), vec![2, 2, 1]);
+        assert_eq!(sizes(10), vec![5]);
+        assert!(chunk_refspecs(&[], 0).is_empty());
+    }
+
+    #[test]
+    fn test_is_push_too_large() {
+        assert!(is_push_too_large(
+            "remote: fatal: pack exceeds maximum allowed size (2.00 GiB)"
+        ));
+        assert!(is_push_too_large(
+            "error: RPC failed; HTTP 413 curl 22 The requested URL returned error: 413"
+        ));
+        assert!(!is_push_too_large(
+            "! [remote rejected] feat--01 (protected branch hook declined)"
+        ));
+        assert!(!is_push_too_large(
+            "error: RPC failed; curl 56 Recv failure: Connection reset by peer"
+        ));
+        assert!(!is_push_too_large(
+            "fatal: the remote end hung up unexpectedly"
+        ));
+    }
+
+    #[test]
+    fn test_is_unreachable() {
+        let error = |stderr: &str| anyhow!("Failed to list refs of remote 'origin': {}", stderr);
+        assert!(is_unreachable(&error(
+            "fatal: unable to access 'https://github.com/acme/widgets.git/': Could not resolve host: github.com"
+        )));
+        assert!(is_unreachable(&error(
+            "ssh: connect to host github.com port 22: Connection timed out"
+        )));
+        assert!(is_unreachable(&error(
+            "failed to resolve address for github.com: Name or service not known; class=Net (12)"
+        )));
+        assert!(!is_unreachable(&error(
+            "git@github.com: Permission denied (publickey).\nfatal: Could not read from remote repository."
+        )));
+        assert!(!is_unreachable(&error(
+            "fatal: 'upstream' does not appear to be a git repository"
+        )));
+    }
+
+    #[test]
+    fn test_git_command() {
+        let args = |transport: &Transport| -> Vec<String> {
+            git_command(Path::new("."), transport)
+                .get_args()
+                .map(|a| a.to_string_lossy().into_owned())
+                .collect()
+        };
+        assert!(args(&Transport::default()).is_empty());
+        let transport = Transport {
+            proxy: Some("http://proxy.corp:3128".to_string()),
+            ca_info: Some("/etc/ssl/corp-ca.pem".into()),
+        };
+        assert_eq!(
+            args(&transport),
+            vec![
+                "-c",
+                "http.proxy=http://proxy.corp:3128",
+                "-c",
+                "http.sslCAInfo=/etc/ssl/corp-ca.pem"
+            ]
+        );
+    }
+
+    #[test]
+    fn test_glob_matches() {
+        assert!(glob_matches("refs/heads/feat--*", "refs/heads/feat--01"));
+        assert!(!glob_matches(
+            "refs/heads/feat--*",
+            "refs/heads/feature--01"
+        ));
+        assert!(glob_matches("refs/heads/feat", "refs/heads/feat"));
+        assert!(!glob_matches("refs/heads/feat", "refs/heads/feat--01"));
+        assert!(glob_matches("refs/*/a*b", "refs/heads/a-x-b"));
+        assert!(!glob_matches("refs/heads/a*a", "refs/heads/a"));
+    }
+
+    #[test]
+    fn test_compare_commits_to_remote() {
+        let oid1 = Oid::from_str("1234567890abcdef1234567890abcdef12345678").unwrap();
+        let oid2 = Oid::from_str("abcdef1234567890abcdef1234567890abcdef12").unwrap();
+
+        let mut remote_refs = HashMap::new();
+        remote_refs.insert("feature--01".to_string(), oid1);
+        remote_refs.insert("feature--02".to_string(), oid1);
+
+        let commits = vec![
+            (oid1, "feature--01".to_string()),
+            (oid2, "feature--02".to_string()),
+            (oid2, "feature--03".to_string()),
+        ];
+
+        let result = compare_commits_to_remote(&remote_refs, &commits);
+
+        assert_eq!(result["feature--01"], (false, Some(oid1)));
+        assert_eq!(result["feature--02"], (true, Some(oid1)));
+        assert_eq!(result["feature--03"], (true, None));
+    }
+
+    #[test]
+    fn test_is_atomic_unsupported() {
+        assert!(is_atomic_unsupported(
+            "fatal: the receiving end does not support --atomic push"
+        ));
+        assert!(is_atomic_unsupported(
+            "fatal: helper https does not support --atomic"
+        ));
+        assert!(!is_atomic_unsupported(
+            "error: atomic push failed for ref refs/heads/a--01. status: 2"
+        ));
+        assert!(!is_atomic_unsupported(
+            "fatal: unable to access 'https://example.com/repo.git/': Could not resolve host"
+        ));
+    }
+
     #[test]
     fn test_build_refspecs_from_oids() {
         let oid1 = Oid::from_str("1234567890abcdef1234567890abcdef12345678").unwrap();
@@ -301,4 +1096,95 @@ mod tests {
             "abcdef1234567890abcdef1234567890abcdef12:refs/heads/feature/foo/s002"
         );
     }
+
+    #[test]
+    fn test_get_tracking_refs() {
+        let dir = tempfile::tempdir().unwrap();
+        let repo = Repository::init(dir.path()).unwrap();
+        let signature = git2::Signature::now("Test", "test@example.com").unwrap();
+        let tree = repo
+            .find_tree(repo.treebuilder(None).unwrap().write().unwrap())
+            .unwrap();
+        let oid = repo
+            .commit(None, &signature, &signature, "first", &tree, &[])
+            .unwrap();
+        for name in [
+            "origin/feat--01",
+            "origin/user/fix--02",
+            "upstream/feat--01",
+        ] {
+            repo.reference(&format!("refs/remotes/{}", name), oid, false, "test")
+                .unwrap();
+        }
+        repo.reference_symbolic(
+            "refs/remotes/origin/HEAD",
+            "refs/remotes/origin/feat--01",
+            false,
+            "test",
+        )
+        .unwrap();
+
+        let tracking_refs = get_tracking_refs(&repo, "origin").unwrap();
+        assert_eq!(
+            tracking_refs,
+            HashMap::from([
+                ("feat--01".to_string(), oid),
+                ("user/fix--02".to_string(), oid)
+            ])
+        );
+    }
+
+    #[test]
+    fn test_get_remote_refs_under() {
+        let remote_dir = tempfile::tempdir().unwrap();
+        let remote = Repository::init_bare(remote_dir.path()).unwrap();
+        let signature = git2::Signature::now("Test", "test@example.com").unwrap();
+        let tree = remote
+            .find_tree(remote.treebuilder(None).unwrap().write().unwrap())
+            .unwrap();
+        let oid = remote
+            .commit(None, &signature, &signature, "first", &tree, &[])
+            .unwrap();
+        for name in [
+            "refs/heads/feat--01",
+            "refs/notes/git-stk/alice",
+            "refs/notes/git-stk-other",
+        ] {
+            remote.reference(name, oid, false, "test").unwrap();
+        }
+
+        let dir = tempfile::tempdir().unwrap();
+        let repo = Repository::init(dir.path()).unwrap();
+        repo.remote("origin", remote_dir.path().to_str().unwrap())
+            .unwrap();
+
+        assert_eq!(
+            get_remote_refs_matching(
+                &repo,
+                "origin",
+                &["refs/heads/feat--*".to_string()],
+                &Transport::default()
+            )
+            .unwrap(),
+            HashMap::from([("feat--01".to_string(), oid)])
+        );
+        assert_eq!(
+            get_remote_refs_under(&repo, "origin", "refs/notes/git-stk", &Transport::default())
+                .unwrap(),
+            vec!["refs/notes/git-stk/alice".to_string()]
+        );
+        remote
+            .find_reference("refs/notes/git-stk/alice")
+            .unwrap()
+            .delete()
+            .unwrap();
+        remote
+            .reference("refs/notes/git-stk", oid, false, "test")
+            .unwrap();
+        assert_eq!(
+            get_remote_refs_under(&repo, "origin", "refs/notes/git-stk", &Transport::default())
+                .unwrap(),
+            vec!["refs/notes/git-stk".to_string()]
+        );
+    }
 }
@@ -81,6 +81,7 @@ pub fn detect_reordering(
 }
 
 /// Calculate which PR base updates are needed for the 3-phase approach
+#[allow(clippy::type_complexity)]
 pub fn calculate_base_updates(
     current_branch: &str,
     entries: &[Entry],
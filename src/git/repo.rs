@@ -1,7 +1,10 @@
-use anyhow::{anyhow, Context, Result};
-use git2::Repository;
+use anyhow::{Context, Result, anyhow, bail};
+use git2::{Oid, Repository};
+use std::process::{Command, Stdio};
 
-/// Get the current branch name
+use crate::git::jj;
+
+/// Get the current branch name (in jj mode, the bookmark at the detached HEAD)
 pub fn current_branch(repo: &Repository) -> Result<String> {
     let head = repo.head().context("Failed to get HEAD")?;
 
@@ -9,8 +12,178 @@ pub fn current_branch(repo: &Repository) -> Result<String> {
         head.shorthand()
             .context("Failed to get branch name")
             .map(String::from)
+    } else if let Some(bookmark) = jj::bookmark_at_head(repo)? {
+        Ok(bookmark)
     } else {
         Err(anyhow!("HEAD is not pointing to a branch (detached HEAD?)"))
     }
 }
 
+/// Check whether HEAD is detached (not pointing to a branch). In jj mode, a
+/// HEAD with a bookmark on it counts as attached to that bookmark.
+pub fn is_detached(repo: &Repository) -> Result<bool> {
+    Ok(repo.head_detached().context("Failed to read HEAD")?
+        && jj::bookmark_at_head(repo)?.is_none())
+}
+
+/// The configured `user.email`, which git-stk identifies you by on shared branches
+pub fn user_email(repo: &Repository) -> Option<String> {
+    repo.config().and_then(|c| c.get_string("user.email")).ok()
+}
+
+/// Seconds since the epoch of a date as `git log --since` takes it, e.g.
+/// "2 weeks ago" or "2024-05-01"
+pub fn parse_date(repo: &Repository, date: &str) -> Result<i64> {
+    let output = Command::new("git")
+        .current_dir(repo.path())
+        .arg("rev-parse")
+        .arg(format!("--since={}", date))
+        .output()
+        .context("Failed to execute git rev-parse")?;
+    if !output.status.success() {
+        bail!("{}", String::from_utf8_lossy(&output.stderr).trim());
+    }
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    stdout
+        .trim()
+        .strip_prefix("--max-age=")
+        .and_then(|seconds| seconds.parse().ok())
+        .ok_or_else(|| anyhow!("Unexpected git rev-parse output: {}", stdout.trim()))
+}
+
+/// The commit a forge reports by SHA. Bitbucket abbreviates SHAs to 12
+/// characters, which resolve only once the commit is available locally.
+pub fn resolve_sha(repo: &Repository, sha: &str) -> Option<Oid> {
+    if sha.len() == 40 {
+        return Oid::from_str(sha).ok();
+    }
+    if sha.len() < 7 || !sha.chars().all(|c| c.is_ascii_hexdigit()) {
+        return None;
+    }
+    repo.revparse_single(sha)
+        .ok()?
+        .peel_to_commit()
+        .ok()
+        .map(|commit| commit.id())
+}
+
+/// Expand a detached-branch name template (`{sha}` becomes the short SHA)
+pub fn detached_branch_name(template: &str, oid: Oid) -> String {
+    template.replace("{sha}", &oid.to_string()[..7])
+}
+
+/// Create a branch at the detached HEAD commit and check it out
+pub fn attach_head_to_new_branch(repo: &Repository, name: &str) -> Result<()> {
+    if repo.find_branch(name, git2::BranchType::Local).is_ok() {
+        bail!("Branch '{}' already exists", name);
+    }
+
+    let head_commit = repo
+        .head()
+        .context("Failed to get HEAD")?
+        .peel_to_commit()
+        .context("Failed to resolve HEAD")?;
+
+    repo.branch(name, &head_commit, false)
+        .with_context(|| format!("Failed to create branch '{}'", name))?;
+    repo.set_head(&format!("refs/heads/{}", name))
+        .with_context(|| format!("Failed to check out branch '{}'", name))?;
+
+    Ok(())
+}
+
+/// The branch HEAD was last checked out from, like `git checkout -`: found in
+/// HEAD's reflog, and only if that branch still exists
+pub fn previous_branch(repo: &Repository) -> Result<Option<String>> {
+    let reflog = repo
+        .reflog("HEAD")
+        .context("Failed to read HEAD's reflog")?;
+    for entry in reflog.iter() {
+        let Some(branch) = entry.message().and_then(branch_moved_from) else {
+            continue;
+        };
+        if repo.find_branch(branch, git2::BranchType::Local).is_ok() {
+            return Ok(Some(branch.to_string()));
+        }
+    }
+    Ok(None)
+}
+
+/// The branch (or commit) a `checkout: moving from <from> to <to>` reflog message left
+fn branch_moved_from(message: &str) -> Option<&str> {
+    message
+        .strip_prefix("checkout: moving from ")?
+        .split(" to ")
+        .next()
+}
+
+/// Check out a branch, or with `detach` a commit, the way `git checkout` does:
+/// local changes are carried over unless they conflict
+pub fn checkout(repo: &Repository, target: &str, detach: bool) -> Result<()> {
+    let workdir = repo
+        .workdir()
+        .context("Repository has no working directory")?;
+    let mut command = Command::new("git");
+    command.current_dir(workdir).args(["checkout", "-q"]);
+    if detach {
+        command.arg("--detach");
+    }
+    let output = command
+        .arg(target)
+        .stdin(Stdio::null())
+        .output()
+        .context("Failed to execute git checkout")?;
+    if !output.status.success() {
+        bail!(
+            "Failed to check out {}: {}",
+            target,
+            String::from_utf8_lossy(&output.stderr).trim()
+        );
+    }
+    Ok(())
+}
+
+/// Check for uncommitted changes (excluding ignored files)
+pub fn has_uncommitted_changes(repo: &Repository) -> Result<bool> {
+    Ok(repo.statuses(None)?.iter().any(|s| {
+        let status = s.status();
+        status.intersects(
+            git2::Status::INDEX_NEW
+                | git2::Status::INDEX_MODIFIED
+                | git2::Status::INDEX_DELETED
+                | git2::Status::INDEX_RENAMED
+                | git2::Status::INDEX_TYPECHANGE
+                | git2::Status::WT_NEW
+                | git2::Status::WT_MODIFIED
+                | git2::Status::WT_DELETED
+                | git2::Status::WT_RENAMED
+                | git2::Status::WT_TYPECHANGE
+                | git2::Status::CONFLICTED,
+        )
+    }))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_detached_branch_name() {
+        let oid = Oid::from_str("abc1234def5678abc1234def5678abc1234def56").unwrap();
+        assert_eq!(detached_branch_name("stk/{sha}", oid), "stk/abc1234");
+        assert_eq!(detached_branch_name("wip", oid), "wip");
+    }
+
+    #[test]
+    fn test_branch_moved_from() {
+        assert_eq!(
+            branch_moved_from("checkout: moving from feat to 1a2b3c4"),
+            Some("feat")
+        );
+        assert_eq!(
+            branch_moved_from("checkout: moving from main to feat"),
+            Some("main")
+        );
+        assert_eq!(branch_moved_from("commit (amend): Fix typo"), None);
+    }
+}
@@ -0,0 +1,298 @@
+//! Rewriting commit messages across the stack (e.g. to add trailers) without
+//! touching trees, so the index and working tree stay as they are.
+
+use anyhow::{Context, Result, anyhow};
+use git2::{Commit, Oid, Repository};
+use std::collections::HashMap;
+use std::io::Write;
+use std::process::{Command, Stdio};
+use tracing::debug;
+
+use crate::git::notes;
+use crate::model::{Config, Stack};
+
+/// Rewrite the messages of stack commits, updating the current branch
+///
+/// `edit` returns the new message of a commit, or `None` to keep it. Commits
+/// from the first edited one up, context commits included, are recreated on
+/// top of each other, keeping their tree and author, and their notes are
+/// carried over. Returns how many messages were edited.
+pub fn rewrite_messages<F>(
+    repo: &Repository,
+    config: &Config,
+    stack: &Stack,
+    reflog_message: &str,
+    mut edit: F,
+) -> Result<usize>
+where
+    F: FnMut(&Commit, &str) -> Result<Option<String>>,
+{
+    let mut edits = Vec::with_capacity(stack.len());
+    for entry in &stack.entries {
+        let commit = repo.find_commit(entry.oid)?;
+        let message = commit
+            .message()
+            .context("Commit message is not valid UTF-8")?;
+        edits.push(edit(&commit, message)?);
+    }
+    let Some(first_edited) = edits.iter().position(Option::is_some) else {
+        return Ok(0);
+    };
+
+    let repo_path = repo
+        .workdir()
+        .ok_or_else(|| anyhow!("Repository has no working directory"))?;
+
+    let first = repo.find_commit(stack.entries[first_edited].oid)?;
+    let mut parent = first.parent_id(0).context("Stack commit has no parent")?;
+    let edited: HashMap<Oid, &String> = stack
+        .entries
+        .iter()
+        .zip(&edits)
+        .filter_map(|(entry, edited)| Some((entry.oid, edited.as_ref()?)))
+        .collect();
+
+    // Every commit above the first edited one, context commits (`git-stk.onlyMine`) included
+    let top = stack.top_oid().context("Stack has no commits")?;
+    let mut revwalk = repo.revwalk()?;
+    revwalk.push(top)?;
+    revwalk.hide(parent)?;
+    revwalk.set_sorting(git2::Sort::TOPOLOGICAL | git2::Sort::REVERSE)?;
+
+    for oid in revwalk {
+        let oid = oid?;
+        let commit = repo.find_commit(oid)?;
+        let message = match edited.get(&oid) {
+            Some(message) => message.as_str(),
+            None => commit
+                .message()
+                .context("Commit message is not valid UTF-8")?,
+        };
+
+        let new_oid = commit_tree(
+            repo_path,
+            &commit,
+            commit.tree_id(),
+            parent,
+            message,
+            config.gpg_sign,
+        )?;
+        if let Some(metadata) = notes::read_note(repo, oid, &config.notes_ref)? {
+            notes::write_note(
+                repo,
+                new_oid,
+                &metadata,
+                &config.notes_ref,
+                config.sign_notes,
+            )?;
+        }
+        debug!(old = %oid, new = %new_oid, "rewrote commit message");
+        parent = new_oid;
+    }
+
+    repo.reference(
+        &format!("refs/heads/{}", stack.current_branch),
+        parent,
+        true,
+        reflog_message,
+    )
+    .context("Failed to update branch")?;
+
+    Ok(edits.iter().flatten().count())
+}
+
+/// Squash the whole stack into one commit with `message`, updating the current branch
+///
+/// The commit has the top entry's tree, so the index and working tree stay as
+/// they are, and the author of `keep`, whose note it takes over. Returns the
+/// new commit.
+pub fn squash_stack(
+    repo: &Repository,
+    config: &Config,
+    stack: &Stack,
+    keep: Oid,
+    message: &str,
+    reflog_message: &str,
+) -> Result<Oid> {
+    let repo_path = repo
+        .workdir()
+        .ok_or_else(|| anyhow!("Repository has no working directory"))?;
+    let (Some(bottom), Some(top)) = (stack.entries.first(), stack.entries.last()) else {
+        return Err(anyhow!("No commits in stack"));
+    };
+
+    let parent = repo
+        .find_commit(bottom.oid)?
+        .parent_id(0)
+        .context("Stack commit has no parent")?;
+    let tree = repo.find_commit(top.oid)?.tree_id();
+    let new_oid = commit_tree(
+        repo_path,
+        &repo.find_commit(keep)?,
+        tree,
+        parent,
+        message,
+        config.gpg_sign,
+    )?;
+    if let Some(metadata) = notes::read_note(repo, keep, &config.notes_ref)? {
+        notes::write_note(
+            repo,
+            new_oid,
+            &metadata,
+            &config.notes_ref,
+            config.sign_notes,
+        )?;
+    }
+    debug!(entries = stack.len(), new = %new_oid, "squashed stack");
+
+    repo.reference(
+        &format!("refs/heads/{}", stack.current_branch),
+        new_oid,
+        true,
+        reflog_message,
+    )
+    .context("Failed to update branch")?;
+
+    Ok(new_oid)
+}
+
+/// Create a commit of `tree` on top of `parent` with `message`, keeping the author of `commit`
+fn commit_tree(
+    repo_path: &std::path::Path,
+    commit: &Commit,
+    tree: Oid,
+    parent: Oid,
+    message: &str,
+    gpg_sign: bool,
+) -> Result<Oid> {
+    let author = commit.author();
+    let offset = author.when().offset_minutes();
+    let date = format!(
+        "@{} {}{:02}{:02}",
+        author.when().seconds(),
+        if offset < 0 { '-' } else { '+' },
+        offset.abs() / 60,
+        offset.abs() % 60
+    );
+
+    let mut command = Command::new("git");
+    command
+        .current_dir(repo_path)
+        .arg("commit-tree")
+        .arg(tree.to_string())
+        .arg("-p")
+        .arg(parent.to_string());
+    if gpg_sign {
+        command.arg("-S");
+    }
+
+    let mut child = command
+        .arg("-F")
+        .arg("-")
+        .env("GIT_AUTHOR_NAME", author.name().unwrap_or(""))
+        .env("GIT_AUTHOR_EMAIL", author.email().unwrap_or(""))
+        .env("GIT_AUTHOR_DATE", date)
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+        .context("Failed to execute git commit-tree")?;
+
+    child
+        .stdin
+        .take()
+        .context("Failed to open git commit-tree stdin")?
+        .write_all(message.as_bytes())
+        .context("Failed to write commit message")?;
+
+    let output = child
+        .wait_with_output()
+        .context("Failed to wait for git commit-tree")?;
+    if !output.status.success() {
+        let stderr = String::from_utf8_lossy(&output.stderr);
+        return Err(anyhow!(
+            "Failed to rewrite commit {}: {}",
+            commit.id(),
+            stderr.trim()
+        ));
+    }
+
+    Oid::from_str(String::from_utf8_lossy(&output.stdout).trim())
+        .context("Failed to parse rewritten commit id")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::git::testing::git;
+    use crate::model::CommitMetadata;
+    use crate::stack::discover_local_stack;
+
+    #[test]
+    fn test_squash_stack() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path();
+        git(path, &["init", "-q", "-b", "main"]);
+        git(path, &["config", "user.name", "Ada"]);
+        git(path, &["config", "user.email", "ada@example.com"]);
+        git(path, &["commit", "-q", "--allow-empty", "-m", "base"]);
+        git(path, &["checkout", "-q", "-b", "feat"]);
+        for (i, author) in [
+            "Ada <ada@example.com>",
+            "Grace <grace@example.com>",
+            "Ada <ada@example.com>",
+        ]
+        .iter()
+        .enumerate()
+        {
+            std::fs::write(path.join(format!("file{}", i)), "x").unwrap();
+            git(path, &["add", "."]);
+            git(
+                path,
+                &[
+                    "commit",
+                    "-q",
+                    "--author",
+                    author,
+                    "-m",
+                    &format!("stack {}", i),
+                ],
+            );
+        }
+
+        let repo = Repository::open(path).unwrap();
+        let config = Config::load(&repo).unwrap();
+        let stack = discover_local_stack(&repo, &config).unwrap();
+        assert_eq!(stack.len(), 3);
+        let keep = stack.entries[1].oid;
+        let metadata = CommitMetadata {
+            pr: Some(12),
+            slot: "02".to_string(),
+            annotation: None,
+            last_export: None,
+            owner: None,
+            signature: None,
+        };
+        notes::write_note(&repo, keep, &metadata, &config.notes_ref, false).unwrap();
+
+        let new_oid =
+            squash_stack(&repo, &config, &stack, keep, "squashed\n", "test: squash").unwrap();
+
+        let commit = repo.find_commit(new_oid).unwrap();
+        assert_eq!(repo.head().unwrap().target(), Some(new_oid));
+        assert_eq!(commit.message(), Some("squashed\n"));
+        assert_eq!(commit.author().name(), Some("Grace"));
+        assert_eq!(
+            commit.parent_id(0).unwrap(),
+            repo.revparse_single("main").unwrap().id()
+        );
+        assert_eq!(
+            commit.tree_id(),
+            repo.find_commit(stack.entries[2].oid).unwrap().tree_id()
+        );
+        let note = notes::read_note(&repo, new_oid, &config.notes_ref)
+            .unwrap()
+            .unwrap();
+        assert_eq!((note.pr, note.slot.as_str()), (Some(12), "02"));
+    }
+}
@@ -0,0 +1,323 @@
+use anyhow::{Context, Result, anyhow};
+use git2::{Oid, Repository};
+use std::process::Command;
+
+use crate::git::{refs, slots::SlotCache};
+use crate::model::{Config, PushHooks};
+use crate::workflows::pending_land::{PendingLand, PendingLands};
+
+/// Namespace under which a branch's shared state is pushed
+const STATE_REF_PREFIX: &str = "refs/git-stk/state/";
+
+/// File holding the slot registry in the state commit's tree
+const SLOTS_FILE: &str = "slots.json";
+
+/// File holding the branch's pending landing (`land --skip-wait`), if any
+const LAND_FILE: &str = "land.json";
+
+fn slots_ref_name(branch: &str) -> String {
+    format!("{}{}/slots", STATE_REF_PREFIX, branch)
+}
+
+fn notes_ref_name(branch: &str) -> String {
+    format!("{}{}/notes", STATE_REF_PREFIX, branch)
+}
+
+fn pulled_slots_ref(branch: &str) -> String {
+    format!("{}{}/slots-pulled", STATE_REF_PREFIX, branch)
+}
+
+/// Where pulled notes are fetched before merging; `git notes merge` only
+/// accepts refs under refs/notes/
+fn pulled_notes_ref(notes_ref: &str) -> String {
+    format!("{}-pulled", notes_ref)
+}
+
+/// What `pull_state` brought in
+#[derive(Debug, Clone, Default)]
+pub struct PulledState {
+    /// Whether remote notes were found and merged
+    pub notes: bool,
+    /// Whether a remote slot registry was found and merged
+    pub slots: bool,
+    /// Whether a pending landing was picked up
+    pub land: bool,
+}
+
+/// The branch's state on the remote, fetched to local refs
+struct RemoteState {
+    /// State commit holding the slot registry and pending landing
+    commit: Option<Oid>,
+    /// Notes commit
+    notes: Option<Oid>,
+}
+
+/// Fetch the branch's state refs, if it has any on the remote
+fn fetch_remote_state(repo: &Repository, config: &Config, branch: &str) -> Result<RemoteState> {
+    let pulled_slots = pulled_slots_ref(branch);
+    let pulled_notes = pulled_notes_ref(&config.notes_ref);
+    let fetch = |source: String, destination: &str| -> Result<Option<Oid>> {
+        // A leftover from an earlier fetch mustn't pass for the remote's state
+        if let Ok(mut reference) = repo.find_reference(destination) {
+            reference.delete()?;
+        }
+        Ok(refs::fetch_refspecs(
+            repo,
+            &config.remote,
+            &[format!("+{}:{}", source, destination)],
+            &config.transport,
+        )
+        .ok()
+        .and_then(|_| repo.refname_to_id(destination).ok()))
+    };
+
+    Ok(RemoteState {
+        commit: fetch(slots_ref_name(branch), &pulled_slots)?,
+        notes: fetch(notes_ref_name(branch), &pulled_notes)?,
+    })
+}
+
+/// Merge the slot registry and pending landing of a state commit into the local ones
+fn merge_state_commit(
+    repo: &Repository,
+    branch: &str,
+    state_commit: Oid,
+    pulled: &mut PulledState,
+) -> Result<()> {
+    let tree = repo.find_commit(state_commit)?.tree()?;
+    if let Some(entry) = tree.get_name(SLOTS_FILE) {
+        let blob = repo.find_blob(entry.id())?;
+        let remote_cache: SlotCache = serde_json::from_slice(blob.content())
+            .context("Failed to parse shared slot registry")?;
+
+        let mut cache = SlotCache::load(repo)?;
+        cache.merge(&remote_cache);
+        cache.save(repo)?;
+        pulled.slots = true;
+    }
+    if let Some(entry) = tree.get_name(LAND_FILE) {
+        let blob = repo.find_blob(entry.id())?;
+        let land: PendingLand = serde_json::from_slice(blob.content())
+            .context("Failed to parse shared pending landing")?;
+
+        // A landing recorded here is newer than the shared one, or finished
+        let mut lands = PendingLands::load(repo)?;
+        if lands.get(branch).is_none() {
+            lands.insert(branch, land);
+            lands.save(repo)?;
+            pulled.land = true;
+        }
+    }
+    Ok(())
+}
+
+/// Push the branch's slot registry, pending landing and the notes ref to
+/// refs/git-stk/state/<branch>/{slots,notes} on the remote
+///
+/// The state already there is fetched and merged first (local notes win on
+/// conflict), so the push is a fast-forward of it, leased on what was fetched:
+/// a collaborator's push in between is never overwritten.
+pub fn push_state(repo: &Repository, config: &Config, branch: &str) -> Result<()> {
+    let remote = fetch_remote_state(repo, config, branch)?;
+    if let Some(state_commit) = remote.commit {
+        merge_state_commit(repo, branch, state_commit, &mut PulledState::default())?;
+    }
+    if let Some(notes_oid) = remote.notes {
+        merge_notes(
+            repo,
+            &config.notes_ref,
+            &pulled_notes_ref(&config.notes_ref),
+            notes_oid,
+            "ours",
+        )?;
+    }
+
+    let cache = SlotCache::load(repo)?.for_branch(branch);
+    let json = serde_json::to_string_pretty(&cache).context("Failed to serialize slot cache")?;
+
+    let blob = repo
+        .blob(json.as_bytes())
+        .context("Failed to write slot registry blob")?;
+    let mut tree_builder = repo.treebuilder(None)?;
+    tree_builder.insert(SLOTS_FILE, blob, git2::FileMode::Blob.into())?;
+    if let Some(land) = PendingLands::load(repo)?.get(branch) {
+        let json =
+            serde_json::to_string_pretty(land).context("Failed to serialize pending landing")?;
+        let blob = repo
+            .blob(json.as_bytes())
+            .context("Failed to write pending landing blob")?;
+        tree_builder.insert(LAND_FILE, blob, git2::FileMode::Blob.into())?;
+    }
+    let tree = repo.find_tree(tree_builder.write()?)?;
+
+    let signature = repo.signature().context("Failed to create signature")?;
+    let parent = remote.commit.map(|oid| repo.find_commit(oid)).transpose()?;
+    let state_commit = repo
+        .commit(
+            None,
+            &signature,
+            &signature,
+            &format!("git-stk: state of {}", branch),
+            &tree,
+            &parent.iter().collect::<Vec<_>>(),
+        )
+        .context("Failed to create state commit")?;
+
+    let mut updates = vec![(
+        state_commit.to_string(),
+        slots_ref_name(branch),
+        remote.commit,
+    )];
+    if repo.refname_to_id(&config.notes_ref).is_ok() {
+        updates.push((
+            config.notes_ref.clone(),
+            notes_ref_name(branch),
+            remote.notes,
+        ));
+    }
+    refs::push_leased(
+        repo,
+        &config.remote,
+        &updates,
+        config.push_hooks != PushHooks::Each,
+        &config.transport,
+    )
+    .map_err(|e| {
+        anyhow!(
+            "Failed to push stack state: {}. Run push-state again to merge the new state.",
+            e
+        )
+    })?;
+
+    Ok(())
+}
+
+/// Fetch the branch's shared state and merge it into the local notes, slot
+/// registry and pending landing
+///
+/// Notes are merged with the `theirs` strategy, so on conflict the pushed
+/// metadata wins. Slot registries are unioned (see [`SlotCache::merge`]).
+pub fn pull_state(repo: &Repository, config: &Config, branch: &str) -> Result<PulledState> {
+    let remote = fetch_remote_state(repo, config, branch)?;
+    let state_commit = remote.commit.ok_or_else(|| {
+        anyhow!(
+            "No shared state found for '{}' on {}",
+            branch,
+            config.remote
+        )
+    })?;
+
+    let mut pulled = PulledState::default();
+    merge_state_commit(repo, branch, state_commit, &mut pulled)?;
+
+    // Notes (optional: a stack that was never exported has none)
+    if let Some(notes_oid) = remote.notes {
+        merge_notes(
+            repo,
+            &config.notes_ref,
+            &pulled_notes_ref(&config.notes_ref),
+            notes_oid,
+            "theirs",
+        )?;
+        pulled.notes = true;
+    }
+
+    Ok(pulled)
+}
+
+/// Merge `other_ref` into `notes_ref` with a `git notes merge` strategy, or
+/// adopt it when there are no local notes yet
+fn merge_notes(
+    repo: &Repository,
+    notes_ref: &str,
+    other_ref: &str,
+    other_oid: Oid,
+    strategy: &str,
+) -> Result<()> {
+    if repo.refname_to_id(notes_ref).is_err() {
+        repo.reference(notes_ref, other_oid, true, "git-stk: pull-state")
+            .context("Failed to write notes ref")?;
+        return Ok(());
+    }
+
+    let repo_path = repo
+        .workdir()
+        .ok_or_else(|| anyhow!("Repository has no working directory"))?;
+
+    let output = Command::new("git")
+        .current_dir(repo_path)
+        .arg("notes")
+        .arg(format!("--ref={}", notes_ref))
+        .arg("merge")
+        .arg("--quiet")
+        .arg(format!("--strategy={}", strategy))
+        .arg(other_ref)
+        .output()
+        .context("Failed to execute git notes merge")?;
+
+    if !output.status.success() {
+        let stderr = String::from_utf8_lossy(&output.stderr);
+        return Err(anyhow!("Failed to merge notes: {}", stderr.trim()));
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// A clone of `remote` with one commit on `feat`
+    fn clone_with_branch(remote: &std::path::Path) -> (tempfile::TempDir, Repository) {
+        let dir = tempfile::tempdir().unwrap();
+        let repo = Repository::init(dir.path()).unwrap();
+        repo.config().unwrap().set_str("user.name", "Test").unwrap();
+        repo.config()
+            .unwrap()
+            .set_str("user.email", "test@example.com")
+            .unwrap();
+        repo.remote("origin", remote.to_str().unwrap()).unwrap();
+        {
+            let signature = repo.signature().unwrap();
+            let tree = repo
+                .find_tree(repo.treebuilder(None).unwrap().write().unwrap())
+                .unwrap();
+            repo.commit(
+                Some("refs/heads/feat"),
+                &signature,
+                &signature,
+                "entry",
+                &tree,
+                &[],
+            )
+            .unwrap();
+        }
+        repo.set_head("refs/heads/feat").unwrap();
+        (dir, repo)
+    }
+
+    #[test]
+    fn test_push_state_merges_the_remote_state() {
+        let remote = tempfile::tempdir().unwrap();
+        Repository::init_bare(remote.path()).unwrap();
+        let (_first_dir, first) = clone_with_branch(remote.path());
+        let (_second_dir, second) = clone_with_branch(remote.path());
+        let config = Config::load(&first).unwrap();
+
+        let mut cache = SlotCache::default();
+        cache.mark_slot_used("feat", "01");
+        cache.save(&first).unwrap();
+        push_state(&first, &config, "feat").unwrap();
+
+        // Pushed without pulling first: the first clone's slot survives
+        let mut cache = SlotCache::default();
+        cache.mark_slot_used("feat", "02");
+        cache.save(&second).unwrap();
+        push_state(&second, &config, "feat").unwrap();
+
+        pull_state(&first, &config, "feat").unwrap();
+        let cache = SlotCache::load(&first).unwrap();
+        assert!(!cache.is_slot_available("feat", "01"));
+        assert!(!cache.is_slot_available("feat", "02"));
+    }
+}
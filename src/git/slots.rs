@@ -1,4 +1,4 @@
-use anyhow::{bail, Context, Result};
+use anyhow::{Context, Result, bail};
 use git2::Repository;
 use serde::{Deserialize, Serialize};
 use std::collections::{HashMap, HashSet};
@@ -28,8 +28,8 @@ impl SlotCache {
         let contents = fs::read_to_string(&path)
             .with_context(|| format!("Failed to read slot cache from {:?}", path))?;
 
-        let cache: SlotCache = serde_json::from_str(&contents)
-            .context("Failed to parse slot cache JSON")?;
+        let cache: SlotCache =
+            serde_json::from_str(&contents).context("Failed to parse slot cache JSON")?;
 
         Ok(cache)
     }
@@ -44,8 +44,7 @@ impl SlotCache {
                 .with_context(|| format!("Failed to create directory {:?}", parent))?;
         }
 
-        let json = serde_json::to_string_pretty(self)
-            .context("Failed to serialize slot cache")?;
+        let json = serde_json::to_string_pretty(self).context("Failed to serialize slot cache")?;
 
         fs::write(&path, json)
             .with_context(|| format!("Failed to write slot cache to {:?}", path))?;
@@ -60,10 +59,7 @@ impl SlotCache {
 
     /// Get the set of used slots for a branch
     fn get_used_slots(&self, branch: &str) -> HashSet<String> {
-        self.used_slots
-            .get(branch)
-            .cloned()
-            .unwrap_or_default()
+        self.used_slots.get(branch).cloned().unwrap_or_default()
     }
 
     /// Allocate a new numeric slot for a branch
@@ -79,23 +75,48 @@ impl SlotCache {
         // Mark as used
         self.used_slots
             .entry(branch.to_string())
-            .or_insert_with(HashSet::new)
+            .or_default()
             .insert(slot.clone());
 
         slot
     }
 
+    /// Allocate a new numeric slot, skipping slots for which `is_taken` returns true
+    /// (e.g., a remote branch already exists for it from another machine).
+    /// Returns the allocated slot and the skipped slots, which stay marked as used.
+    pub fn allocate_skipping<F>(&mut self, branch: &str, is_taken: F) -> (String, Vec<String>)
+    where
+        F: Fn(&str) -> bool,
+    {
+        let mut skipped = Vec::new();
+        loop {
+            let slot = self.allocate(branch);
+            if is_taken(&slot) {
+                skipped.push(slot);
+            } else {
+                return (slot, skipped);
+            }
+        }
+    }
+
     /// Check if a slot is available for a branch
     pub fn is_slot_available(&self, branch: &str, slot: &str) -> bool {
         let used = self.get_used_slots(branch);
         !used.contains(slot)
     }
 
+    /// The slots used for a branch, sorted
+    pub fn used_slots(&self, branch: &str) -> Vec<String> {
+        let mut used: Vec<String> = self.get_used_slots(branch).into_iter().collect();
+        used.sort();
+        used
+    }
+
     /// Mark a slot as used for a branch
     pub fn mark_slot_used(&mut self, branch: &str, slot: &str) {
         self.used_slots
             .entry(branch.to_string())
-            .or_insert_with(HashSet::new)
+            .or_default()
             .insert(slot.to_string());
 
         // If it's a numeric slot, update counter
@@ -107,12 +128,83 @@ impl SlotCache {
         }
     }
 
+    /// Forget that a slot was used, so it can be assigned again (e.g. after its
+    /// commit was abandoned). Numeric slots stay below the counter and are not
+    /// handed out by `allocate` again.
+    pub fn release_slot(&mut self, branch: &str, slot: &str) {
+        if let Some(used) = self.used_slots.get_mut(branch) {
+            used.remove(slot);
+        }
+    }
+
+    /// Reset the numeric slot counter for a branch (e.g., after renumbering)
+    pub fn reset_counter(&mut self, branch: &str, value: u32) {
+        self.counters.insert(branch.to_string(), value);
+    }
+
+    /// Move a branch's counter and used slots to a new branch name
+    pub fn rename_branch(&mut self, old: &str, new: &str) {
+        if let Some(counter) = self.counters.remove(old) {
+            self.counters.insert(new.to_string(), counter);
+        }
+        if let Some(used) = self.used_slots.remove(old) {
+            self.used_slots
+                .entry(new.to_string())
+                .or_default()
+                .extend(used);
+        }
+    }
+
+    /// Copy of the cache restricted to a single branch (for sharing its state)
+    pub fn for_branch(&self, branch: &str) -> SlotCache {
+        let mut cache = SlotCache::default();
+        if let Some(&counter) = self.counters.get(branch) {
+            cache.counters.insert(branch.to_string(), counter);
+        }
+        if let Some(used) = self.used_slots.get(branch) {
+            cache.used_slots.insert(branch.to_string(), used.clone());
+        }
+        cache
+    }
+
+    /// Merge another cache into this one: used slots are unioned and counters take the max,
+    /// so no slot known to either side is handed out again
+    pub fn merge(&mut self, other: &SlotCache) {
+        for (branch, &counter) in &other.counters {
+            let current = self.counters.entry(branch.clone()).or_default();
+            *current = (*current).max(counter);
+        }
+        for (branch, used) in &other.used_slots {
+            self.used_slots
+                .entry(branch.clone())
+                .or_default()
+                .extend(used.iter().cloned());
+        }
+    }
+
     /// Ensure a slot is tracked (called during reconciliation)
     pub fn ensure_slot(&mut self, branch: &str, slot: &str) {
         self.mark_slot_used(branch, slot);
     }
 }
 
+/// Compute compacted slots for a stack, bottom to top.
+/// Numeric and unassigned slots are renumbered 01..N in stack order;
+/// custom (non-numeric) slots are kept as-is.
+pub fn compact_slots(current: &[Option<String>]) -> Vec<String> {
+    let mut next = 0;
+    current
+        .iter()
+        .map(|slot| match slot {
+            Some(custom) if custom.parse::<u32>().is_err() => custom.clone(),
+            _ => {
+                next += 1;
+                format!("{:02}", next)
+            }
+        })
+        .collect()
+}
+
 /// Validate a slot name for branch compatibility
 /// Slots must:
 /// - Not be empty
@@ -130,7 +222,10 @@ pub fn validate_slot_name(slot: &str) -> Result<()> {
     // Check for valid characters
     for c in slot.chars() {
         if !c.is_alphanumeric() && c != '-' && c != '_' {
-            bail!("Slot name can only contain alphanumeric characters, hyphens, and underscores. Invalid character: '{}'", c);
+            bail!(
+                "Slot name can only contain alphanumeric characters, hyphens, and underscores. Invalid character: '{}'",
+                c
+            );
         }
     }
 
@@ -153,11 +248,10 @@ pub fn sanitize_branch_name(name: &str) -> String {
     // Replace illegal git ref characters
     // Git ref names cannot contain: \x00-\x1F, \x7F, ~, ^, :, ?, *, [, \, space, .., @{
     let illegal_chars = [
-        '\0', '\x01', '\x02', '\x03', '\x04', '\x05', '\x06', '\x07',
-        '\x08', '\x09', '\x0A', '\x0B', '\x0C', '\x0D', '\x0E', '\x0F',
-        '\x10', '\x11', '\x12', '\x13', '\x14', '\x15', '\x16', '\x17',
-        '\x18', '\x19', '\x1A', '\x1B', '\x1C', '\x1D', '\x1E', '\x1F',
-        '\x7F', '~', '^', ':', '?', '*', '[', '\\', ' ',
+        '\0', '\x01', '\x02', '\x03', '\x04', '\x05', '\x06', '\x07', '\x08', '\x09', '\x0A',
+        '\x0B', '\x0C', '\x0D', '\x0E', '\x0F', '\x10', '\x11', '\x12', '\x13', '\x14', '\x15',
+        '\x16', '\x17', '\x18', '\x19', '\x1A', '\x1B', '\x1C', '\x1D', '\x1E', '\x1F', '\x7F',
+        '~', '^', ':', '?', '*', '[', '\\', ' ',
     ];
 
     for c in illegal_chars {
@@ -189,12 +283,24 @@ pub fn sanitize_branch_name(name: &str) -> String {
 /// Examples:
 ///   - "feature/foo--01" (numeric slot)
 ///   - "feature/foo--add-tests" (custom slot)
+///
 /// Uses -- separator to avoid directory conflicts with current branch
 pub fn generate_head_ref(branch: &str, slot: &str) -> String {
     let sanitized = sanitize_branch_name(branch);
     format!("{}--{}", sanitized, slot)
 }
 
+/// Ref keeping revision `revision` of a slot reachable, locally and on the remote:
+/// `refs/git-stk/archive/{branch}/{slot}/v{revision}`
+pub fn archive_ref(branch: &str, slot: &str, revision: u32) -> String {
+    format!(
+        "refs/git-stk/archive/{}/{}/v{}",
+        sanitize_branch_name(branch),
+        slot,
+        revision
+    )
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -231,10 +337,21 @@ mod tests {
     fn test_generate_head_ref() {
         assert_eq!(generate_head_ref("feature/foo", "01"), "feature/foo--01");
         assert_eq!(generate_head_ref("feature/foo", "42"), "feature/foo--42");
-        assert_eq!(generate_head_ref("feature/foo", "add-tests"), "feature/foo--add-tests");
+        assert_eq!(
+            generate_head_ref("feature/foo", "add-tests"),
+            "feature/foo--add-tests"
+        );
         assert_eq!(generate_head_ref("feature//foo", "01"), "feature/foo--01");
     }
 
+    #[test]
+    fn test_archive_ref() {
+        assert_eq!(
+            archive_ref("feature//foo", "01", 3),
+            "refs/git-stk/archive/feature/foo/01/v3"
+        );
+    }
+
     #[test]
     fn test_slot_cache_allocation() {
         let mut cache = SlotCache::default();
@@ -245,6 +362,79 @@ mod tests {
         assert_eq!(cache.allocate("main"), "03");
     }
 
+    #[test]
+    fn test_slot_cache_allocate_skipping() {
+        let mut cache = SlotCache::default();
+
+        let (slot, skipped) = cache.allocate_skipping("main", |s| s == "01" || s == "02");
+        assert_eq!(slot, "03");
+        assert_eq!(skipped, vec!["01", "02"]);
+        assert!(!cache.is_slot_available("main", "01"));
+
+        let (slot, skipped) = cache.allocate_skipping("main", |_| false);
+        assert_eq!(slot, "04");
+        assert!(skipped.is_empty());
+    }
+
+    #[test]
+    fn test_slot_cache_rename_branch() {
+        let mut cache = SlotCache::default();
+        cache.mark_slot_used("old", "02");
+        cache.mark_slot_used("old", "custom");
+
+        cache.rename_branch("old", "new");
+
+        assert!(cache.is_slot_available("old", "02"));
+        assert!(!cache.is_slot_available("new", "custom"));
+        assert_eq!(cache.allocate("new"), "03");
+        assert_eq!(cache.allocate("old"), "01");
+    }
+
+    #[test]
+    fn test_slot_cache_release_slot() {
+        let mut cache = SlotCache::default();
+        cache.mark_slot_used("feature", "02");
+        cache.mark_slot_used("feature", "custom");
+
+        cache.release_slot("feature", "custom");
+        cache.release_slot("feature", "02");
+
+        assert!(cache.is_slot_available("feature", "custom"));
+        assert!(cache.is_slot_available("feature", "02"));
+        // Numeric slots are still not handed out again
+        assert_eq!(cache.allocate("feature"), "03");
+    }
+
+    #[test]
+    fn test_slot_cache_merge() {
+        let mut local = SlotCache::default();
+        local.mark_slot_used("feature", "01");
+        local.mark_slot_used("other", "05");
+
+        let mut remote = SlotCache::default();
+        remote.mark_slot_used("feature", "03");
+        remote.mark_slot_used("feature", "custom");
+
+        local.merge(&remote.for_branch("feature"));
+
+        assert!(!local.is_slot_available("feature", "custom"));
+        assert!(!local.is_slot_available("other", "05"));
+        assert_eq!(local.allocate("feature"), "04");
+        assert!(remote.for_branch("other").used_slots.is_empty());
+    }
+
+    #[test]
+    fn test_compact_slots() {
+        let current = vec![
+            Some("03".to_string()),
+            Some("add-tests".to_string()),
+            None,
+            Some("12".to_string()),
+        ];
+        assert_eq!(compact_slots(&current), vec!["01", "add-tests", "02", "03"]);
+        assert!(compact_slots(&[]).is_empty());
+    }
+
     #[test]
     fn test_slot_cache_availability() {
         let mut cache = SlotCache::default();
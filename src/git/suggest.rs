@@ -0,0 +1,254 @@
+//! Blame-based fixup suggestions: for each hunk of a change, find the stack
+//! entry that last touched the lines it modifies, like `git absorb` but without
+//! rewriting anything.
+
+use crate::model::Stack;
+use anyhow::{Context, Result};
+use git2::{BlameOptions, DiffOptions, Oid, Repository, Tree};
+use std::cell::RefCell;
+use std::collections::HashMap;
+use std::path::Path;
+
+/// Which changes to analyze
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SuggestSource {
+    /// Staged and unstaged changes against HEAD
+    Uncommitted,
+    /// The top commit of the stack, against the entries below it
+    TopCommit,
+}
+
+/// Where a hunk should be folded
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Target {
+    /// The stack entry at this position (0-based)
+    Entry(usize),
+    /// Only lines older than the stack: the hunk belongs in a new commit
+    Base,
+    /// The file doesn't exist yet below the analyzed change
+    NewFile,
+    /// The lines come from several entries (or an entry and the base); the
+    /// hunk has to be split by hand
+    Ambiguous(Vec<usize>),
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct HunkSuggestion {
+    pub path: String,
+    /// First line of the hunk in the new version, and its line count
+    pub new_start: u32,
+    pub new_lines: u32,
+    /// The hunk only adds lines, so the target was inferred from its neighbors
+    pub insertion: bool,
+    pub target: Target,
+}
+
+/// Suggest a target entry for every hunk of `source`
+pub fn suggest_fixups(
+    repo: &Repository,
+    stack: &Stack,
+    source: SuggestSource,
+) -> Result<Vec<HunkSuggestion>> {
+    let head = repo
+        .head()
+        .context("Failed to get HEAD")?
+        .peel_to_commit()
+        .context("Failed to resolve HEAD")?;
+
+    // `blamed` is the commit whose lines the change modifies
+    let (blamed, candidates) = match source {
+        SuggestSource::Uncommitted => (head.clone(), &stack.entries[..]),
+        SuggestSource::TopCommit => (
+            head.parent(0).context("The top commit has no parent")?,
+            &stack.entries[..stack.len().saturating_sub(1)],
+        ),
+    };
+    let blamed_tree = blamed.tree().context("Failed to get commit tree")?;
+    let positions: HashMap<Oid, usize> = candidates.iter().map(|e| (e.oid, e.index - 1)).collect();
+    let base = stack
+        .entries
+        .first()
+        .and_then(|e| repo.find_commit(e.oid).ok()?.parent_id(0).ok());
+
+    let hunks = collect_hunks(repo, &blamed_tree, source)?;
+
+    let mut suggestions = Vec::with_capacity(hunks.len());
+    for hunk in hunks {
+        let target = match blamed_tree.get_path(Path::new(&hunk.path)) {
+            Err(_) => Target::NewFile,
+            Ok(_) => {
+                let line_count = file_line_count(repo, &blamed_tree, &hunk.path)?;
+                let lines = blamed_lines(hunk.old_start, hunk.old_lines, line_count);
+                let owners = blame_owners(repo, &hunk.path, blamed.id(), base, &lines, &positions)?;
+                classify(&owners, hunk.old_lines == 0)
+            }
+        };
+        suggestions.push(HunkSuggestion {
+            path: hunk.path,
+            new_start: hunk.new_start,
+            new_lines: hunk.new_lines,
+            insertion: hunk.old_lines == 0,
+            target,
+        });
+    }
+
+    Ok(suggestions)
+}
+
+struct Hunk {
+    path: String,
+    old_start: u32,
+    old_lines: u32,
+    new_start: u32,
+    new_lines: u32,
+}
+
+fn collect_hunks(
+    repo: &Repository,
+    blamed_tree: &Tree,
+    source: SuggestSource,
+) -> Result<Vec<Hunk>> {
+    let mut options = DiffOptions::new();
+    options.context_lines(0);
+
+    let diff = match source {
+        SuggestSource::Uncommitted => {
+            repo.diff_tree_to_workdir_with_index(Some(blamed_tree), Some(&mut options))
+        }
+        SuggestSource::TopCommit => {
+            let head_tree = repo.head().and_then(|h| h.peel_to_tree())?;
+            repo.diff_tree_to_tree(Some(blamed_tree), Some(&head_tree), Some(&mut options))
+        }
+    }
+    .context("Failed to diff changes")?;
+
+    let hunks = RefCell::new(Vec::new());
+    diff.foreach(
+        &mut |_, _| true,
+        None,
+        Some(&mut |delta, hunk| {
+            let path = delta.new_file().path().or_else(|| delta.old_file().path());
+            hunks.borrow_mut().push(Hunk {
+                path: path
+                    .map(|p| p.to_string_lossy().into_owned())
+                    .unwrap_or_default(),
+                old_start: hunk.old_start(),
+                old_lines: hunk.old_lines(),
+                new_start: hunk.new_start(),
+                new_lines: hunk.new_lines(),
+            });
+            true
+        }),
+        None,
+    )
+    .context("Failed to read diff")?;
+
+    Ok(hunks.into_inner())
+}
+
+fn file_line_count(repo: &Repository, tree: &Tree, path: &str) -> Result<u32> {
+    let blob = tree
+        .get_path(Path::new(path))
+        .and_then(|entry| entry.to_object(repo))
+        .and_then(|object| object.peel_to_blob())
+        .with_context(|| format!("Failed to read {}", path))?;
+    let content = blob.content();
+    let lines = content.iter().filter(|b| **b == b'\n').count()
+        + usize::from(content.last().is_some_and(|b| *b != b'\n'));
+    Ok(lines as u32)
+}
+
+/// Lines (1-based) whose owner decides the target. A pure insertion after line
+/// `old_start` is judged by the lines around it.
+fn blamed_lines(old_start: u32, old_lines: u32, line_count: u32) -> Vec<u32> {
+    let lines: Vec<u32> = if old_lines == 0 {
+        vec![old_start, old_start + 1]
+    } else {
+        (old_start..old_start + old_lines).collect()
+    };
+    lines
+        .into_iter()
+        .filter(|l| (1..=line_count).contains(l))
+        .collect()
+}
+
+/// Stack position owning each of `lines`, `None` for lines older than the stack
+fn blame_owners(
+    repo: &Repository,
+    path: &str,
+    newest: Oid,
+    base: Option<Oid>,
+    lines: &[u32],
+    positions: &HashMap<Oid, usize>,
+) -> Result<Vec<Option<usize>>> {
+    let (Some(&min), Some(&max)) = (lines.iter().min(), lines.iter().max()) else {
+        return Ok(Vec::new());
+    };
+
+    let mut options = BlameOptions::new();
+    options
+        .newest_commit(newest)
+        .min_line(min as usize)
+        .max_line(max as usize);
+    if let Some(base) = base {
+        options.oldest_commit(base);
+    }
+    let blame = repo
+        .blame_file(Path::new(path), Some(&mut options))
+        .with_context(|| format!("Failed to blame {}", path))?;
+
+    Ok(lines
+        .iter()
+        .map(|line| {
+            blame
+                .get_line(*line as usize)
+                .and_then(|hunk| positions.get(&hunk.final_commit_id()).copied())
+        })
+        .collect())
+}
+
+/// Decide the target from the owners of the blamed lines. Insertions only
+/// need their stack-owned neighbors to agree; modifications need every line
+/// to come from the same entry.
+fn classify(owners: &[Option<usize>], insertion: bool) -> Target {
+    let mut entries: Vec<usize> = owners.iter().flatten().copied().collect();
+    entries.sort_unstable();
+    entries.dedup();
+
+    match entries.as_slice() {
+        [] => Target::Base,
+        [entry] if insertion || owners.iter().all(Option::is_some) => Target::Entry(*entry),
+        // Several entries, or an entry's lines mixed with older ones
+        _ => Target::Ambiguous(entries),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_classify() {
+        assert_eq!(classify(&[Some(1), Some(1)], false), Target::Entry(1));
+        assert_eq!(classify(&[None, None], false), Target::Base);
+        assert_eq!(
+            classify(&[Some(0), Some(2)], false),
+            Target::Ambiguous(vec![0, 2])
+        );
+        assert_eq!(
+            classify(&[Some(1), None], false),
+            Target::Ambiguous(vec![1])
+        );
+        // An insertion next to an entry's line belongs to that entry
+        assert_eq!(classify(&[None, Some(1)], true), Target::Entry(1));
+    }
+
+    #[test]
+    fn test_blamed_lines() {
+        assert_eq!(blamed_lines(3, 2, 10), vec![3, 4]);
+        assert_eq!(blamed_lines(3, 0, 10), vec![3, 4]);
+        // Insertions at the start and end of a file only have one neighbor
+        assert_eq!(blamed_lines(0, 0, 10), vec![1]);
+        assert_eq!(blamed_lines(10, 0, 10), vec![10]);
+    }
+}
@@ -0,0 +1,29 @@
+//! Helpers shared by the tests that drive real repositories
+
+use std::path::Path;
+use std::process::Command;
+
+/// Run git in `dir` as a fixed identity, with file remotes allowed for
+/// submodules, and return its trimmed stdout; panics if git fails
+pub fn git(dir: &Path, args: &[&str]) -> String {
+    let output = Command::new("git")
+        .current_dir(dir)
+        .args([
+            "-c",
+            "user.name=Ada",
+            "-c",
+            "user.email=ada@example.com",
+            "-c",
+            "protocol.file.allow=always",
+        ])
+        .args(args)
+        .output()
+        .unwrap();
+    assert!(
+        output.status.success(),
+        "git {:?}: {}",
+        args,
+        String::from_utf8_lossy(&output.stderr)
+    );
+    String::from_utf8_lossy(&output.stdout).trim().to_string()
+}
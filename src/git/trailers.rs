@@ -0,0 +1,178 @@
+/// Trailer keys stripped from PR bodies (compared case-insensitively)
+const RECOGNIZED_TRAILERS: &[&str] = &[
+    "Co-authored-by",
+    "Signed-off-by",
+    "Reviewed-by",
+    "Acked-by",
+    "Tested-by",
+    "Reported-by",
+    "Suggested-by",
+    "Helped-by",
+    "Change-Id",
+];
+
+/// Split recognized trailers (e.g., `Co-authored-by: ...`) off a commit body.
+///
+/// Only the last paragraph is considered, and only if every line in it looks
+/// like a `Key: value` trailer. Unrecognized trailers stay in the body.
+/// Returns the body without recognized trailers, and the trailer lines.
+pub fn split_trailers(body: &str) -> (String, Vec<String>) {
+    let trimmed = body.trim_end();
+    let (head, last_paragraph) = match trimmed.rfind("\n\n") {
+        Some(pos) => (&trimmed[..pos], &trimmed[pos + 2..]),
+        None => ("", trimmed),
+    };
+
+    let lines: Vec<&str> = last_paragraph.lines().collect();
+    if lines.is_empty() || !lines.iter().all(|l| trailer_key(l).is_some()) {
+        return (body.trim().to_string(), Vec::new());
+    }
+
+    let (trailers, kept): (Vec<&str>, Vec<&str>) = lines.into_iter().partition(|l| {
+        trailer_key(l).is_some_and(|key| {
+            RECOGNIZED_TRAILERS
+                .iter()
+                .any(|known| known.eq_ignore_ascii_case(key))
+        })
+    });
+
+    let mut remaining = head.trim().to_string();
+    if !kept.is_empty() {
+        if !remaining.is_empty() {
+            remaining.push_str("\n\n");
+        }
+        remaining.push_str(&kept.join("\n"));
+    }
+
+    (
+        remaining,
+        trailers.into_iter().map(|t| t.trim().to_string()).collect(),
+    )
+}
+
+/// Value of the trailer `key` in a commit message's trailer block (last one wins)
+pub fn find_trailer(message: &str, key: &str) -> Option<String> {
+    find_trailers(message, key).pop()
+}
+
+/// Values of every `key` trailer in a commit message's trailer block, in order
+pub fn find_trailers(message: &str, key: &str) -> Vec<String> {
+    // The subject line never holds trailers
+    let trimmed = message.trim_end();
+    let Some(pos) = trimmed.rfind("\n\n") else {
+        return Vec::new();
+    };
+    let lines: Vec<&str> = trimmed[pos + 2..].lines().collect();
+    if !lines.iter().all(|l| trailer_key(l).is_some()) {
+        return Vec::new();
+    }
+
+    lines
+        .iter()
+        .filter(|l| trailer_key(l).is_some_and(|k| k.eq_ignore_ascii_case(key)))
+        .filter_map(|l| l.split_once(':').map(|(_, value)| value.trim().to_string()))
+        .collect()
+}
+
+/// Append a `Key: value` trailer line to a commit message, joining an
+/// existing trailer block or starting a new paragraph
+pub fn append_trailer(message: &str, trailer: &str) -> String {
+    let trimmed = message.trim_end();
+    let has_trailer_block = trimmed
+        .rfind("\n\n")
+        .is_some_and(|pos| trimmed[pos + 2..].lines().all(|l| trailer_key(l).is_some()));
+
+    if has_trailer_block {
+        format!("{}\n{}\n", trimmed, trailer)
+    } else {
+        format!("{}\n\n{}\n", trimmed, trailer)
+    }
+}
+
+/// Get the key of a `Key: value` trailer line
+fn trailer_key(line: &str) -> Option<&str> {
+    let (key, value) = line.split_once(':')?;
+    let valid_key = !key.is_empty() && key.chars().all(|c| c.is_ascii_alphanumeric() || c == '-');
+    (valid_key && value.starts_with(' ')).then_some(key)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_split_trailers() {
+        let body =
+            "Some details.\n\nCo-authored-by: A <a@example.com>\nSigned-off-by: B <b@example.com>";
+        let (rest, trailers) = split_trailers(body);
+        assert_eq!(rest, "Some details.");
+        assert_eq!(
+            trailers,
+            vec![
+                "Co-authored-by: A <a@example.com>",
+                "Signed-off-by: B <b@example.com>"
+            ]
+        );
+    }
+
+    #[test]
+    fn test_split_trailers_only_trailers() {
+        let (rest, trailers) = split_trailers("signed-off-by: B <b@example.com>");
+        assert_eq!(rest, "");
+        assert_eq!(trailers.len(), 1);
+    }
+
+    #[test]
+    fn test_split_trailers_keeps_unrecognized() {
+        let body = "Details.\n\nFixes: #12\nCo-authored-by: A <a@example.com>";
+        let (rest, trailers) = split_trailers(body);
+        assert_eq!(rest, "Details.\n\nFixes: #12");
+        assert_eq!(trailers, vec!["Co-authored-by: A <a@example.com>"]);
+    }
+
+    #[test]
+    fn test_find_trailer() {
+        let message = "Subject\n\nBody.\n\nChange-Id: I1234\nSigned-off-by: B <b@example.com>\n";
+        assert_eq!(find_trailer(message, "change-id").as_deref(), Some("I1234"));
+        assert_eq!(find_trailer(message, "Reviewed-by"), None);
+        // The subject line is not a trailer block
+        assert_eq!(find_trailer("Change-Id: I1234", "Change-Id"), None);
+    }
+
+    #[test]
+    fn test_find_trailers() {
+        let message = "Subject\n\nSigned-off-by: A <a@example.com>\nAcked-by: C <c@example.com>\nSigned-off-by: B <b@example.com>";
+        assert_eq!(
+            find_trailers(message, "signed-off-by"),
+            vec!["A <a@example.com>", "B <b@example.com>"]
+        );
+        assert!(find_trailers("Subject", "Signed-off-by").is_empty());
+    }
+
+    #[test]
+    fn test_append_trailer() {
+        assert_eq!(
+            append_trailer("Subject\n\nBody.\n", "Change-Id: I1"),
+            "Subject\n\nBody.\n\nChange-Id: I1\n"
+        );
+        assert_eq!(
+            append_trailer(
+                "Subject\n\nSigned-off-by: B <b@example.com>",
+                "Change-Id: I1"
+            ),
+            "Subject\n\nSigned-off-by: B <b@example.com>\nChange-Id: I1\n"
+        );
+        assert_eq!(
+            append_trailer("Subject", "Change-Id: I1"),
+            "Subject\n\nChange-Id: I1\n"
+        );
+    }
+
+    #[test]
+    fn test_split_trailers_not_a_trailer_block() {
+        let body = "Details.\n\nNote: this is prose\nand continues here.";
+        let (rest, trailers) = split_trailers(body);
+        assert_eq!(rest, body);
+        assert!(trailers.is_empty());
+    }
+}
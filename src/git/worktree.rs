@@ -0,0 +1,108 @@
+use anyhow::{Context, Result, anyhow};
+use git2::{Oid, Repository};
+use std::path::{Path, PathBuf};
+use std::process::Command;
+
+use crate::model::Config;
+
+/// A detached worktree under .git/git-stk/worktrees, removed on drop.
+///
+/// Used to run commands against stack commits without touching the user's checkout.
+pub struct TempWorktree {
+    repo_path: PathBuf,
+    path: PathBuf,
+}
+
+impl TempWorktree {
+    /// Create a detached worktree named `name` at `oid`
+    pub fn create(repo: &Repository, name: &str, oid: Oid) -> Result<Self> {
+        let repo_path = repo
+            .workdir()
+            .ok_or_else(|| anyhow!("Repository has no working directory"))?
+            .to_path_buf();
+        let path = Config::git_stack_dir(repo)?.join("worktrees").join(name);
+
+        // A previous run may have been interrupted; clear any leftover worktree
+        if path.exists() {
+            remove_worktree(&repo_path, &path);
+        }
+
+        let output = Command::new("git")
+            .current_dir(&repo_path)
+            .arg("worktree")
+            .arg("add")
+            .arg("--detach")
+            .arg("--force")
+            .arg(&path)
+            .arg(oid.to_string())
+            .output()
+            .context("Failed to execute git worktree add")?;
+
+        if !output.status.success() {
+            let stderr = String::from_utf8_lossy(&output.stderr);
+            return Err(anyhow!("Failed to create worktree: {}", stderr));
+        }
+
+        Ok(Self { repo_path, path })
+    }
+
+    /// Path of the worktree
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// Check out `oid` in the worktree, discarding any changes left by a previous command
+    pub fn checkout(&self, oid: Oid) -> Result<()> {
+        let output = Command::new("git")
+            .current_dir(&self.path)
+            .arg("checkout")
+            .arg("--detach")
+            .arg("--force")
+            .arg(oid.to_string())
+            .output()
+            .context("Failed to execute git checkout in worktree")?;
+
+        if !output.status.success() {
+            let stderr = String::from_utf8_lossy(&output.stderr);
+            return Err(anyhow!(
+                "Failed to check out {} in worktree: {}",
+                oid,
+                stderr
+            ));
+        }
+
+        // Remove untracked build artifacts from the previous commit
+        Command::new("git")
+            .current_dir(&self.path)
+            .args(["clean", "-fdq"])
+            .output()
+            .context("Failed to execute git clean in worktree")?;
+
+        Ok(())
+    }
+}
+
+impl Drop for TempWorktree {
+    fn drop(&mut self) {
+        remove_worktree(&self.repo_path, &self.path);
+    }
+}
+
+fn remove_worktree(repo_path: &Path, path: &Path) {
+    let _ = Command::new("git")
+        .current_dir(repo_path)
+        .arg("worktree")
+        .arg("remove")
+        .arg("--force")
+        .arg(path)
+        .output();
+
+    // Fall back to deleting the directory if git no longer knows about it
+    if path.exists() {
+        let _ = std::fs::remove_dir_all(path);
+    }
+    let _ = Command::new("git")
+        .current_dir(repo_path)
+        .args(["worktree", "prune"])
+        .output();
+}
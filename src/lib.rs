@@ -1,7 +1,9 @@
 pub mod commands;
 pub mod gh;
 pub mod git;
+pub mod logging;
 pub mod model;
+pub mod profile;
 pub mod stack;
 pub mod ui;
 pub mod workflows;
@@ -0,0 +1,44 @@
+//! Diagnostic logging via `tracing`.
+//!
+//! Output goes to stderr. `RUST_LOG` takes precedence over the `-v` count, e.g.
+//! `RUST_LOG=git_stk::gh=trace git stk export`. GitHub and git calls are
+//! `#[instrument]`ed at debug level: events inside them carry their arguments,
+//! and each logs its duration when it closes.
+
+use tracing_subscriber::EnvFilter;
+use tracing_subscriber::fmt::format::FmtSpan;
+
+/// Default filter for a `-v` count: warnings only, then debug, then trace
+/// (including the GitHub client) for `-vv` and above
+pub fn default_filter(verbosity: u8) -> &'static str {
+    match verbosity {
+        0 => "git_stk=warn",
+        1 => "git_stk=debug",
+        _ => "git_stk=trace,octocrab=debug",
+    }
+}
+
+/// Install the global subscriber; call once at startup
+pub fn init(verbosity: u8) {
+    let filter = EnvFilter::try_from_default_env()
+        .unwrap_or_else(|_| EnvFilter::new(default_filter(verbosity)));
+
+    let _ = tracing_subscriber::fmt()
+        .with_env_filter(filter)
+        .with_writer(std::io::stderr)
+        .with_target(true)
+        .with_span_events(FmtSpan::CLOSE)
+        .try_init();
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_default_filter() {
+        assert_eq!(default_filter(0), "git_stk=warn");
+        assert_eq!(default_filter(1), "git_stk=debug");
+        assert_eq!(default_filter(3), "git_stk=trace,octocrab=debug");
+    }
+}
@@ -1,5 +1,8 @@
 use clap::{Parser, Subcommand};
 use git_stk::commands;
+use git_stk::gh::mutations::ReviewEvent;
+use git_stk::model::PushNotes;
+use std::path::PathBuf;
 
 #[derive(Parser)]
 #[command(name = "git-stk")]
@@ -7,12 +10,112 @@ use git_stk::commands;
 struct Cli {
     #[command(subcommand)]
     command: Commands,
+    /// Increase log verbosity (-v debug, -vv trace); RUST_LOG overrides
+    #[arg(long, short, action = clap::ArgAction::Count, global = true)]
+    verbose: u8,
+    /// List the remote's branches when discovering the stack, even with git-stk.fetch false
+    #[arg(long, global = true, conflicts_with = "no_fetch")]
+    fetch: bool,
+    /// Use the remote-tracking branches from the last fetch instead of listing the remote's
+    #[arg(long, global = true)]
+    no_fetch: bool,
 }
 
 #[derive(Subcommand)]
 enum Commands {
     /// View the current stack of commits and their PR status
-    View,
+    View {
+        /// Copy the PR URL of an entry to the clipboard (stack index, SHA, "last", or git ref).
+        /// Needs a display; on Linux, a background git-stk process keeps the URL on the
+        /// clipboard until something else is copied, since X11 and Wayland drop it on exit
+        #[arg(long, value_name = "INDEX", conflicts_with = "url_only")]
+        copy: Option<String>,
+        /// Print only the PR URL of an entry, for scripting
+        #[arg(long, value_name = "INDEX")]
+        url_only: Option<String>,
+        /// Show author initials and relative commit age for each entry
+        #[arg(long)]
+        details: bool,
+        /// Show the stack of a branch on the remote (e.g. a colleague's) without checking it out
+        #[arg(long, value_name = "BRANCH")]
+        remote: Option<String>,
+        /// Only show entries needing action: needs-export, merged, or draft (repeatable or comma-separated)
+        #[arg(long, value_name = "FILTER", value_enum, value_delimiter = ',')]
+        only: Vec<commands::ViewFilter>,
+        /// Print the stack as JSON
+        #[arg(long, conflicts_with_all = ["copy", "url_only"])]
+        json: bool,
+        /// Show at most N entries: the top of the stack, or around --around
+        #[arg(long, value_name = "N")]
+        limit: Option<usize>,
+        /// Show the entries around this one (stack index, SHA, "last", or git ref)
+        #[arg(long, value_name = "INDEX", allow_hyphen_values = true)]
+        around: Option<String>,
+    },
+    /// Draw the stack as a graph: slot branches, PR chain, and the base branch since the merge-base
+    Graph {
+        /// Most base branch commits to show above the merge-base
+        #[arg(long, value_name = "N", default_value_t = 5)]
+        base_commits: usize,
+    },
+    /// Open the PR of a stack entry in the browser
+    Open {
+        /// Commit reference: stack index (1, -1 for the top), slot (e.g. "02" or "slot:add-tests"),
+        /// SHA, "last", or git ref; or a range of entries like "2..4" or "-2.."
+        #[arg(default_value = "last", allow_hyphen_values = true)]
+        commit: String,
+    },
+    /// Check out the bottom entry of the stack (detached HEAD), e.g. to amend it
+    Bottom,
+    /// Return to the top of the stack: the branch a detached HEAD was checked out from
+    Top,
+    /// Print a compact stack segment for shell prompts, e.g. ⇪3/7±2 (offline, cached)
+    Prompt {
+        /// Output: the segment, or a JSON object for prompt frameworks
+        #[arg(long, value_enum, default_value = "text")]
+        format: commands::PromptFormat,
+    },
+    /// Check out the stack of a PR locally (reviewer's view): its whole chain, notes included
+    CheckoutPr {
+        /// Number of any PR in the stack
+        pr: u64,
+    },
+    /// Open an HTML dashboard of the stack (PR states, CI, links) in the browser
+    Web {
+        /// Write the page to this file (default: .git/git-stk/stack.html)
+        #[arg(long, short, value_name = "FILE", conflicts_with = "serve")]
+        output: Option<PathBuf>,
+        /// Don't open the page in the browser
+        #[arg(long)]
+        no_open: bool,
+        /// Serve the page on localhost instead, refreshing the stack on every reload
+        #[arg(long, value_name = "PORT", num_args = 0..=1, default_missing_value = "7878")]
+        serve: Option<u16>,
+    },
+    /// Show diff stats per entry and for the whole stack
+    Stats {
+        /// Flag entries changing more lines than this (default: git-stk.maxEntryLines or 400; 0 disables)
+        #[arg(long, value_name = "LINES")]
+        max_lines: Option<usize>,
+    },
+    /// Suggest which stack entry each uncommitted hunk belongs to (read-only)
+    Suggest {
+        /// Analyze the top commit of the stack instead of uncommitted changes
+        #[arg(long)]
+        top: bool,
+    },
+    /// Print a markdown summary of the stack's commits (for the top PR or release notes)
+    Summary {
+        /// Group commits by conventional-commit type (feat, fix, ...)
+        #[arg(long)]
+        conventional: bool,
+        /// List only commit subjects, without their bodies
+        #[arg(long)]
+        no_body: bool,
+        /// Write the summary to this file instead of stdout
+        #[arg(long, short, value_name = "FILE")]
+        output: Option<PathBuf>,
+    },
     /// Export the stack to GitHub by creating/updating branches and PRs
     Export {
         /// Create PRs as drafts
@@ -36,45 +139,365 @@ enum Commands {
         /// Output results as JSON
         #[arg(long)]
         json: bool,
-        /// Show verbose output including reconciliation details
-        #[arg(long, short)]
-        verbose: bool,
+        /// Post (or update) a stack summary comment on the top PR
+        #[arg(long)]
+        summary_comment: bool,
+        /// Assign created PRs to a user ("me" for yourself); can be repeated
+        #[arg(long = "assignee", value_name = "LOGIN")]
+        assignees: Vec<String>,
+        /// Attach created PRs to a milestone (number or title)
+        #[arg(long)]
+        milestone: Option<String>,
+        /// Add created PRs to a GitHub project (number or node ID)
+        #[arg(long)]
+        project: Option<String>,
+        /// Override the PR title (exporting a single commit only)
+        #[arg(long)]
+        title: Option<String>,
+        /// Override the PR body (exporting a single commit only)
+        #[arg(long)]
+        body: Option<String>,
+        /// Skip local pre-push hooks when pushing slot branches
+        #[arg(long)]
+        no_verify: bool,
+        /// Print timings of plan building, git operations, and GitHub API calls
+        #[arg(long)]
+        profile: bool,
+        /// All-or-nothing: push atomically and roll back the remote if a PR update fails
+        #[arg(long, conflicts_with_all = ["push_only", "pr_only"])]
+        atomic: bool,
+        /// When to push git-stk notes (overrides git-stk.pushNotes)
+        #[arg(long, value_name = "POLICY", value_enum)]
+        push_notes: Option<PushNotes>,
+        /// Export target: GitHub PRs, or a patch series for mailing-list review
+        #[arg(long, value_enum, default_value = "github")]
+        format: commands::ExportFormat,
+        /// Directory for the patch series (default: .git/git-stk/patches/<branch>)
+        #[arg(long, value_name = "DIR")]
+        output_dir: Option<PathBuf>,
+        /// Send the patch series with git send-email
+        #[arg(long)]
+        send_email: bool,
+        /// Add your Signed-off-by to commits lacking their author's (rewrites the stack)
+        #[arg(long)]
+        amend_signoff: bool,
+        /// Rebase the stack onto the remote base first if the base moved on beneath it
+        #[arg(long)]
+        update_behind: bool,
+        /// Keep local stk/<branch>/<slot> branches at each entry (default: git-stk.keepBranches)
+        #[arg(long)]
+        keep_branches: bool,
+        /// Export even if the stack breaks the stack policy ([policy] in .git-stk.toml or git-stk.policyFile)
+        #[arg(long)]
+        no_verify_policy: bool,
+        /// Force-push slot branches that others exported, and own them from now on
+        #[arg(long)]
+        take_ownership: bool,
+        /// Close the PRs of commits you dropped from the stack and delete their slot branches
+        #[arg(long)]
+        close_dropped: bool,
+        /// Only export these entries: a commit reference or a range like "2..4" or "..-2"
+        #[arg(value_name = "COMMITS", allow_hyphen_values = true)]
+        commits: Option<String>,
     },
     /// Land the bottom PR in the stack (merge, pull, rebase, re-export)
     Land {
         /// Skip waiting for merge to complete
         #[arg(long)]
         skip_wait: bool,
+        /// GPG/SSH-sign commits rewritten by the rebase (default: git-stk.gpgSign or commit.gpgSign)
+        #[arg(long)]
+        gpg_sign: bool,
+        /// Add a Signed-off-by trailer to commits rewritten by the rebase
+        #[arg(long)]
+        signoff: bool,
+        /// After landing, ask whether to land the next PR, and so on up the stack
+        #[arg(long, conflicts_with = "skip_wait")]
+        then_next: bool,
+        /// Stash uncommitted changes before rebasing and restore them after (default: git-stk.autostash or rebase.autoStash)
+        #[arg(long)]
+        autostash: bool,
+        /// Merge commit title template: {number}, {title}, {subject}, {body}, {issue_keys} (default: git-stk.mergeCommitTitle)
+        #[arg(long, value_name = "TEMPLATE")]
+        commit_title: Option<String>,
+        /// Merge commit message template, same placeholders (default: git-stk.mergeCommitMessage, else the PR body)
+        #[arg(long, value_name = "TEMPLATE")]
+        commit_message: Option<String>,
     },
     /// Run post-merge operations after a PR has been manually merged
-    Landed,
+    Landed {
+        /// GPG/SSH-sign commits rewritten by the rebase (default: git-stk.gpgSign or commit.gpgSign)
+        #[arg(long)]
+        gpg_sign: bool,
+        /// Add a Signed-off-by trailer to commits rewritten by the rebase
+        #[arg(long)]
+        signoff: bool,
+        /// Skip verifying that the merged content matches the stack commit
+        #[arg(long)]
+        force: bool,
+        /// Stash uncommitted changes before rebasing and restore them after (default: git-stk.autostash or rebase.autoStash)
+        #[arg(long)]
+        autostash: bool,
+    },
+    /// Attach a free-form note to a commit that travels with it (shown in view)
+    Annotate {
+        /// Commit reference: SHA, stack index (1, 2, 3..., or -1 for the top), slot, "last", or git ref
+        #[arg(allow_negative_numbers = true)]
+        commit: String,
+        /// Annotation text (e.g., "blocked on INFRA-42")
+        #[arg(required_unless_present = "clear")]
+        text: Option<String>,
+        /// Remove the existing annotation
+        #[arg(long, conflicts_with = "text")]
+        clear: bool,
+    },
+    /// Post a comment on the PR of a stack entry
+    Comment {
+        /// Commit reference: SHA, stack index (1, 2, 3..., or -1 for the top), slot, "last", or git ref
+        #[arg(allow_negative_numbers = true)]
+        commit: String,
+        /// Comment text (Markdown)
+        #[arg(required_unless_present = "file", conflicts_with = "file")]
+        message: Option<String>,
+        /// Read the comment body from a file
+        #[arg(long, short = 'F')]
+        file: Option<PathBuf>,
+    },
+    /// Submit a review on the PR of a stack entry
+    #[command(group(clap::ArgGroup::new("event").required(true)))]
+    Review {
+        /// Commit reference: SHA, stack index (1, 2, 3..., or -1 for the top), slot, "last", or git ref
+        #[arg(allow_negative_numbers = true)]
+        commit: String,
+        /// Approve the PR
+        #[arg(long, group = "event")]
+        approve: bool,
+        /// Request changes (requires --body)
+        #[arg(long, group = "event")]
+        request_changes: bool,
+        /// Leave a review comment without a verdict (requires --body)
+        #[arg(long, group = "event")]
+        comment: bool,
+        /// Review body (Markdown)
+        #[arg(long, short)]
+        body: Option<String>,
+    },
+    /// List the unresolved review threads on the PR of a stack entry
+    Threads {
+        /// Commit reference: SHA, stack index (1, 2, 3..., or -1 for the top), slot, "last", or git ref
+        #[arg(allow_negative_numbers = true)]
+        commit: String,
+        /// Also list resolved threads
+        #[arg(long)]
+        all: bool,
+        /// Mark a thread resolved (ID as listed); can be repeated
+        #[arg(long, value_name = "THREAD_ID")]
+        resolve: Vec<String>,
+    },
+    /// Show what changed in an entry since its last export (range-diff against the version reviewers saw)
+    Interdiff {
+        /// Commit reference: SHA, stack index (1, 2, 3..., or -1 for the top), slot, "last", or git ref
+        #[arg(default_value = "last", allow_negative_numbers = true)]
+        commit: String,
+        /// Compare with this archived revision of the entry instead (see the PR callout)
+        #[arg(long, value_name = "N")]
+        revision: Option<u32>,
+    },
+    /// Drop a commit from the stack, close its PR, and delete its branch
+    Abandon {
+        /// Commit reference: SHA, stack index (1, 2, 3..., or -1 for the top), slot, "last", or git ref
+        #[arg(allow_negative_numbers = true)]
+        commit: String,
+        /// Stash uncommitted changes before rebasing and restore them after (default: git-stk.autostash or rebase.autoStash)
+        #[arg(long)]
+        autostash: bool,
+        /// Skip confirmation prompts (automatically answer yes)
+        #[arg(long, short)]
+        yes: bool,
+    },
+    /// Close orphaned PRs (of commits dropped from the stack) and delete their slot branches
+    Prune {
+        /// Skip confirmation prompts (automatically answer yes)
+        #[arg(long, short)]
+        yes: bool,
+    },
+    /// Convert a classic feature branch into a stack: a slot and PR per commit
+    AdoptBranch {
+        /// Branch to adopt (checked out if it isn't the current one)
+        branch: String,
+        /// Skip confirmation prompts (automatically answer yes)
+        #[arg(long, short)]
+        yes: bool,
+    },
+    /// Squash the whole stack into one commit and PR, closing the other PRs
+    Collapse {
+        /// Skip confirmation prompts (automatically answer yes)
+        #[arg(long, short)]
+        yes: bool,
+    },
+    /// Rename the working branch and migrate its slot branches and PRs
+    RenameBranch {
+        /// New branch name
+        new_name: String,
+        /// Skip confirmation prompts (automatically answer yes)
+        #[arg(long, short)]
+        yes: bool,
+    },
+    /// Push the current branch's stack metadata (notes + slots) for collaborators
+    PushState,
+    /// Check the stack's metadata, e.g. a notes ref deleted on the remote, and rebuild it from PRs
+    Doctor {
+        /// Write the rebuilt notes and push them
+        #[arg(long)]
+        fix: bool,
+    },
+    /// Fetch and merge stack metadata pushed by a collaborator for the current branch
+    PullState,
+    /// List stack snapshots, or roll back to one taken before a rebase
+    Restore {
+        /// Snapshot identifier (omit to list snapshots)
+        snapshot: Option<i64>,
+        /// Skip confirmation prompts (automatically answer yes)
+        #[arg(long, short)]
+        yes: bool,
+    },
+    /// Run a command at every commit in the stack (in a temporary worktree)
+    Test {
+        /// Command to run, e.g. `git stk test cargo test`; a single argument is run by the shell as is (`"make && make check"`)
+        #[arg(required = true, trailing_var_arg = true, allow_hyphen_values = true)]
+        command: Vec<String>,
+        /// Ignore cached results and run the command at every commit
+        #[arg(long)]
+        no_cache: bool,
+        /// Only test these entries: a commit reference or a range like "2..4" or "-2.."
+        #[arg(long, value_name = "COMMITS", allow_hyphen_values = true)]
+        only: Option<String>,
+    },
+    /// Find the first stack entry where a command fails (binary search)
+    Bisect {
+        /// Command to run, e.g. `git stk bisect cargo build`; a single argument is run by the shell as is
+        #[arg(required = true, trailing_var_arg = true, allow_hyphen_values = true)]
+        command: Vec<String>,
+        /// Ignore cached results from previous `test`/`bisect` runs
+        #[arg(long)]
+        no_cache: bool,
+    },
     /// Set commit metadata (PR, slot, etc.)
     Set {
         #[command(subcommand)]
         command: SetCommands,
     },
+    /// Own the clipboard with the text on stdin until something else is copied (started by `view --copy`)
+    #[command(hide = true)]
+    HoldClipboard,
 }
 
 #[derive(Subcommand)]
 enum SetCommands {
     /// Manually assign a slot to a commit
     Slot {
-        /// Commit reference: SHA (abc123), stack index (1, 2, 3...), "last", or git ref (HEAD, branch name)
-        commit: String,
-        /// Slot identifier (e.g., "01", "02", or custom like "add-tests")
-        slot: String,
+        /// `<commit> <slot>`, or several `<commit>=<slot>` assignments (e.g. `1=api-layer 2=handlers`).
+        /// Commit: SHA (abc123), stack index (1, 2, 3..., or -1 for the top), slot (02, slot:add-tests),
+        /// "last", or git ref (HEAD, branch name). Slot: e.g. "01", "02", or custom like "add-tests"
+        #[arg(
+            value_name = "ASSIGNMENT",
+            required_unless_present = "auto_renumber",
+            allow_negative_numbers = true
+        )]
+        assignments: Vec<String>,
+        /// Renumber numeric slots of the whole stack to 01..N
+        #[arg(long, conflicts_with = "assignments")]
+        auto_renumber: bool,
+        /// With --auto-renumber, close PRs whose branch changes so export recreates them
+        #[arg(long, requires = "auto_renumber")]
+        recreate_prs: bool,
         /// Skip confirmation prompts (automatically answer yes)
         #[arg(long, short)]
         yes: bool,
     },
+    /// Set the base the stack sits on (git-stk.base) and show the resulting stack
+    Base {
+        /// Branch (e.g. "main", "origin/release-2.x") or any revision
+        base: String,
+    },
+    /// Set the remote to push to (git-stk.remote) and show the resulting stack
+    Remote {
+        /// Name of a configured remote
+        remote: String,
+    },
 }
 
 #[tokio::main]
 async fn main() {
     let cli = Cli::parse();
+    git_stk::logging::init(cli.verbose);
+    let fetch = (cli.fetch || cli.no_fetch).then_some(cli.fetch);
 
     let result = match cli.command {
-        Commands::View => commands::view().await,
+        Commands::Graph { base_commits } => {
+            commands::graph(commands::GraphOptions {
+                base_commits,
+                fetch,
+            })
+            .await
+        }
+        Commands::View {
+            copy,
+            url_only,
+            details,
+            remote,
+            only,
+            json,
+            limit,
+            around,
+        } => {
+            commands::view(commands::ViewOptions {
+                copy,
+                url_only,
+                details,
+                remote,
+                only,
+                json,
+                limit,
+                around,
+                fetch,
+            })
+            .await
+        }
+        Commands::Open { commit } => commands::open_pr(&commit, fetch).await,
+        Commands::Bottom => commands::bottom().await,
+        Commands::Top => commands::top().await,
+        Commands::Prompt { format } => commands::prompt(format).await,
+        Commands::CheckoutPr { pr } => commands::checkout_pr(pr, fetch).await,
+        Commands::Web {
+            output,
+            no_open,
+            serve,
+        } => {
+            commands::web(commands::WebOptions {
+                output,
+                no_open,
+                serve,
+                fetch,
+            })
+            .await
+        }
+        Commands::Stats { max_lines } => {
+            commands::stats(commands::StatsOptions { max_lines }).await
+        }
+        Commands::Suggest { top } => commands::suggest(commands::SuggestOptions { top }).await,
+        Commands::Summary {
+            conventional,
+            no_body,
+            output,
+        } => {
+            commands::summary(commands::SummaryOptions {
+                conventional,
+                no_body,
+                output,
+            })
+            .await
+        }
         Commands::Export {
             draft,
             ready: _,
@@ -83,7 +506,26 @@ async fn main() {
             open,
             dry_run,
             json,
-            verbose,
+            summary_comment,
+            assignees,
+            milestone,
+            project,
+            title,
+            body,
+            no_verify,
+            profile,
+            push_notes,
+            atomic,
+            format,
+            output_dir,
+            send_email,
+            amend_signoff,
+            update_behind,
+            keep_branches,
+            no_verify_policy,
+            take_ownership,
+            close_dropped,
+            commits,
         } => {
             let options = commands::ExportOptions {
                 draft,
@@ -92,14 +534,149 @@ async fn main() {
                 open,
                 dry_run,
                 json,
-                verbose,
+                verbose: cli.verbose > 0,
+                summary_comment,
+                assignees,
+                milestone,
+                project,
+                title,
+                body,
+                no_verify,
+                profile,
+                push_notes,
+                atomic,
+                format,
+                output_dir,
+                send_email,
+                amend_signoff,
+                update_behind,
+                keep_branches,
+                no_verify_policy,
+                take_ownership,
+                close_dropped,
+                fetch,
+                commits,
             };
             commands::export(options).await
         }
-        Commands::Land { skip_wait } => commands::land(skip_wait).await,
-        Commands::Landed => commands::landed().await,
+        Commands::Land {
+            skip_wait,
+            gpg_sign,
+            signoff,
+            then_next,
+            autostash,
+            commit_title,
+            commit_message,
+        } => {
+            commands::land(commands::LandOptions {
+                skip_wait,
+                gpg_sign,
+                signoff,
+                then_next,
+                autostash,
+                commit_title,
+                commit_message,
+                fetch,
+            })
+            .await
+        }
+        Commands::Landed {
+            gpg_sign,
+            signoff,
+            force,
+            autostash,
+        } => {
+            commands::landed(commands::LandedOptions {
+                gpg_sign,
+                signoff,
+                force,
+                autostash,
+                fetch,
+            })
+            .await
+        }
+        Commands::Annotate {
+            commit,
+            text,
+            clear,
+        } => commands::annotate(commit.as_str(), text.as_deref(), clear, fetch).await,
+        Commands::Comment {
+            commit,
+            message,
+            file,
+        } => commands::comment(commit.as_str(), message.as_deref(), file.as_deref(), fetch).await,
+        Commands::Review {
+            commit,
+            approve,
+            request_changes,
+            comment: _,
+            body,
+        } => {
+            let event = if approve {
+                ReviewEvent::Approve
+            } else if request_changes {
+                ReviewEvent::RequestChanges
+            } else {
+                ReviewEvent::Comment
+            };
+            commands::review(commit.as_str(), event, body.as_deref(), fetch).await
+        }
+        Commands::Threads {
+            commit,
+            all,
+            resolve,
+        } => {
+            commands::threads(
+                &commit,
+                commands::ThreadsOptions {
+                    all,
+                    resolve,
+                    fetch,
+                },
+            )
+            .await
+        }
+        Commands::Interdiff { commit, revision } => commands::interdiff(&commit, revision).await,
+        Commands::Abandon {
+            commit,
+            autostash,
+            yes,
+        } => commands::abandon(&commit, autostash, yes, fetch).await,
+        Commands::Prune { yes } => commands::prune(yes, fetch).await,
+        Commands::AdoptBranch { branch, yes } => commands::adopt_branch(&branch, yes, fetch).await,
+        Commands::Collapse { yes } => commands::collapse(yes, fetch).await,
+        Commands::RenameBranch { new_name, yes } => {
+            commands::rename_branch(&new_name, yes, fetch).await
+        }
+        Commands::PushState => commands::push_state().await,
+        Commands::Doctor { fix } => commands::doctor(fix, fetch).await,
+        Commands::PullState => commands::pull_state().await,
+        Commands::HoldClipboard => commands::hold_clipboard(),
+        Commands::Restore { snapshot, yes } => commands::restore(snapshot, yes).await,
+        Commands::Test {
+            command,
+            no_cache,
+            only,
+        } => commands::test(&command, no_cache, only.as_deref()).await,
+        Commands::Bisect { command, no_cache } => commands::bisect(&command, no_cache).await,
         Commands::Set { command } => match command {
-            SetCommands::Slot { commit, slot, yes } => commands::set_slot(commit.as_str(), slot.as_str(), yes).await,
+            SetCommands::Slot {
+                assignments,
+                auto_renumber,
+                recreate_prs,
+                yes,
+            } => {
+                if auto_renumber {
+                    commands::renumber_slots(yes, recreate_prs, fetch).await
+                } else {
+                    match commands::parse_assignments(&assignments) {
+                        Ok(assignments) => commands::set_slots(&assignments, yes, fetch).await,
+                        Err(e) => Err(e),
+                    }
+                }
+            }
+            SetCommands::Base { base } => commands::set_base(&base).await,
+            SetCommands::Remote { remote } => commands::set_remote(&remote).await,
         },
     };
 
@@ -1,18 +1,448 @@
 use anyhow::{Context, Result};
 use git2::Repository;
-use std::path::PathBuf;
+use serde::Deserialize;
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+
+/// How local `pre-push` hooks run for slot pushes (`git-stk.pushHooks`)
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum PushHooks {
+    /// Let git run hooks on every push (default)
+    #[default]
+    Each,
+    /// Run the hook once for the whole export with every ref, then push with --no-verify
+    Once,
+    /// Skip local hooks (--no-verify)
+    None,
+}
+
+impl PushHooks {
+    fn parse(value: &str) -> Result<Self> {
+        match value {
+            "each" => Ok(Self::Each),
+            "once" => Ok(Self::Once),
+            "none" => Ok(Self::None),
+            other => anyhow::bail!(
+                "Invalid git-stk.pushHooks value '{}' (expected each, once, or none)",
+                other
+            ),
+        }
+    }
+}
+
+/// When the notes ref is pushed to the remote (`git-stk.pushNotes`, `--push-notes`)
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, clap::ValueEnum)]
+pub enum PushNotes {
+    /// Never push notes (for remotes that reject non-branch refs)
+    Never,
+    /// Push notes at the end of each export (default)
+    #[default]
+    OnExport,
+    /// Also push after commands that change notes locally (annotate, set slot)
+    Always,
+}
+
+impl PushNotes {
+    pub fn parse(value: &str) -> Result<Self> {
+        match value {
+            "never" => Ok(Self::Never),
+            "on-export" => Ok(Self::OnExport),
+            "always" => Ok(Self::Always),
+            other => anyhow::bail!(
+                "Invalid push-notes policy '{}' (expected never, on-export, or always)",
+                other
+            ),
+        }
+    }
+}
+
+/// How discovery treats notes whose signature is missing or bad (`git-stk.verifyNotes`)
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum VerifyNotes {
+    /// Don't check signatures (default unless git-stk.signNotes is set)
+    #[default]
+    Off,
+    /// Warn about untrusted notes but use them
+    Warn,
+    /// Warn and ignore untrusted notes, as if the commit had none
+    Reject,
+}
+
+impl VerifyNotes {
+    fn parse(value: &str) -> Result<Self> {
+        match value {
+            "off" => Ok(Self::Off),
+            "warn" => Ok(Self::Warn),
+            "reject" => Ok(Self::Reject),
+            other => anyhow::bail!(
+                "Invalid git-stk.verifyNotes value '{}' (expected off, warn, or reject)",
+                other
+            ),
+        }
+    }
+}
+
+/// Code hosting service the remote lives on (`git-stk.forge`, else detected from
+/// the remote URL)
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Forge {
+    /// GitHub or GitHub Enterprise (default)
+    #[default]
+    GitHub,
+    /// Bitbucket Cloud
+    Bitbucket,
+    /// Azure Repos (Azure DevOps Services)
+    AzureDevOps,
+}
+
+impl Forge {
+    fn parse(value: &str) -> Result<Self> {
+        match value {
+            "github" => Ok(Self::GitHub),
+            "bitbucket" => Ok(Self::Bitbucket),
+            "azure-devops" => Ok(Self::AzureDevOps),
+            other => anyhow::bail!(
+                "Invalid git-stk.forge value '{}' (expected github, bitbucket, or azure-devops)",
+                other
+            ),
+        }
+    }
+
+    /// Display name for messages
+    pub fn name(self) -> &'static str {
+        match self {
+            Self::GitHub => "GitHub",
+            Self::Bitbucket => "Bitbucket",
+            Self::AzureDevOps => "Azure DevOps",
+        }
+    }
+
+    /// Web page of PR `number` in `owner/repo`
+    pub fn pr_url(self, owner: &str, repo: &str, number: u64) -> String {
+        match self {
+            Self::GitHub => format!("https://github.com/{}/{}/pull/{}", owner, repo, number),
+            Self::Bitbucket => format!(
+                "https://bitbucket.org/{}/{}/pull-requests/{}",
+                owner, repo, number
+            ),
+            Self::AzureDevOps => format!(
+                "https://dev.azure.com/{}/_git/{}/pullrequest/{}",
+                owner.replace(' ', "%20"),
+                repo.replace(' ', "%20"),
+                number
+            ),
+        }
+    }
+
+    /// Guess the forge from a remote URL. Unknown hosts (GitHub Enterprise, SSH
+    /// host aliases) are taken to be GitHub.
+    pub fn detect(url: &str) -> Self {
+        let rest = url.split_once("://").map_or(url, |(_, rest)| rest);
+        let rest = rest.split_once('@').map_or(rest, |(_, host)| host);
+        let host = rest.split([':', '/']).next().unwrap_or_default();
+        let on = |domain: &str| host == domain || host.ends_with(&format!(".{}", domain));
+        if on("bitbucket.org") {
+            Self::Bitbucket
+        } else if on("dev.azure.com") || on("visualstudio.com") {
+            Self::AzureDevOps
+        } else {
+            Self::GitHub
+        }
+    }
+}
+
+/// What export does when an entry breaks a size limit (`lint.mode` in `.git-stk.toml`)
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum LintMode {
+    /// Print the offending entries and continue (default)
+    #[default]
+    Warn,
+    /// Refuse to export
+    Error,
+}
+
+/// Size limits for stack entries, checked before export
+#[derive(Debug, Clone, Default, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub struct SizeLint {
+    /// Most changed lines (additions + deletions) an entry may have
+    pub max_lines: Option<usize>,
+    /// Most files an entry may touch
+    pub max_files: Option<usize>,
+    #[serde(default)]
+    pub mode: LintMode,
+}
+
+impl SizeLint {
+    /// The `[lint]` table, if it sets a limit; 0 means no limit
+    fn from_table(lint: Option<Self>) -> Option<Self> {
+        let mut lint = lint?;
+        lint.max_lines = lint.max_lines.filter(|v| *v > 0);
+        lint.max_files = lint.max_files.filter(|v| *v > 0);
+        (lint.max_lines.is_some() || lint.max_files.is_some()).then_some(lint)
+    }
+}
+
+/// Issue tracker links for keys like `ABC-123` in commit subjects
+#[derive(Debug, Clone, Default, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub struct IssueLinks {
+    /// Issue URL with a `{key}` placeholder, e.g. `https://acme.atlassian.net/browse/{key}`
+    #[serde(default)]
+    pub url: String,
+    /// Project prefixes keys must use; any `ABC-123`-shaped key when empty
+    #[serde(default)]
+    pub projects: Vec<String>,
+    /// URL receiving a JSON POST per linked issue when its PR is created
+    pub notify_url: Option<String>,
+}
+
+impl IssueLinks {
+    /// The `[issues]` table; linking is off without `url`
+    fn from_table(issues: Option<Self>) -> Result<Option<Self>> {
+        let Some(mut issues) = issues.filter(|i| !i.url.is_empty()) else {
+            return Ok(None);
+        };
+        if !issues.url.contains("{key}") {
+            anyhow::bail!(
+                "issues.url in {} must contain a {{key}} placeholder",
+                SHARED_CONFIG_FILE
+            );
+        }
+        issues.notify_url = issues.notify_url.filter(|u| !u.is_empty());
+        Ok(Some(issues))
+    }
+}
+
+/// How git-stk reaches remotes and the forge: an HTTP(S) proxy and an extra
+/// CA bundle to trust
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Transport {
+    /// Proxy URL (default: HTTPS_PROXY, then http.proxy)
+    pub proxy: Option<String>,
+    /// Extra CA bundle to trust (default: GIT_SSL_CAINFO, then http.sslCAInfo)
+    pub ca_info: Option<PathBuf>,
+}
+
+/// Organization rules for stacks, enforced before export
+#[derive(Debug, Clone, Default, PartialEq, Eq, Deserialize)]
+#[serde(default, rename_all = "kebab-case")]
+pub struct StackPolicy {
+    /// Most unmerged entries a stack may have
+    pub max_depth: Option<usize>,
+    /// Labels every PR of the stack must carry; export adds them to its PRs
+    pub required_labels: Vec<String>,
+    /// PRs must be created as drafts (`export --draft`)
+    pub draft_first: bool,
+    /// Branches (globs like `release/*`) stacks may not target
+    pub forbidden_bases: Vec<String>,
+}
+
+impl StackPolicy {
+    /// The `[policy]` table; no policy without it
+    fn from_table(policy: Option<Self>) -> Result<Option<Self>> {
+        let Some(policy) = policy else {
+            return Ok(None);
+        };
+        if policy.max_depth == Some(0) {
+            anyhow::bail!("policy.max-depth must be a positive number");
+        }
+        Ok((policy != StackPolicy::default()).then_some(policy))
+    }
+}
+
+/// Repository-wide settings file committed at the root of the working tree
+pub const SHARED_CONFIG_FILE: &str = ".git-stk.toml";
+
+/// The tables of `.git-stk.toml` (and of `git-stk.policyFile`) git-stk reads
+#[derive(Debug, Default, Deserialize)]
+struct SharedConfig {
+    lint: Option<SizeLint>,
+    issues: Option<IssueLinks>,
+    policy: Option<StackPolicy>,
+}
+
+impl SharedConfig {
+    /// Parse the TOML file at `path`; `None` if there's no such file
+    fn read(path: &Path) -> Result<Option<Self>> {
+        if !path.is_file() {
+            return Ok(None);
+        }
+        let contents = std::fs::read_to_string(path)
+            .with_context(|| format!("Failed to read {}", path.display()))?;
+        toml::from_str(&contents)
+            .map(Some)
+            .with_context(|| format!("Failed to parse {}", path.display()))
+    }
+}
+
+/// Settings from `.git-stk.toml`, with the `[policy]` of `policy_file`
+/// (`git-stk.policyFile`) replacing its own when one is configured
+fn load_shared_settings(
+    repo: &Repository,
+    policy_file: Option<&str>,
+) -> Result<(Option<SizeLint>, Option<IssueLinks>, Option<StackPolicy>)> {
+    let Some(workdir) = repo.workdir() else {
+        return Ok((None, None, None));
+    };
+    let shared = SharedConfig::read(&workdir.join(SHARED_CONFIG_FILE))?.unwrap_or_default();
+    let policy = match policy_file {
+        Some(path) => {
+            let path = workdir.join(path);
+            SharedConfig::read(&path)?
+                .with_context(|| {
+                    format!(
+                        "Policy file {} (git-stk.policyFile) not found",
+                        path.display()
+                    )
+                })?
+                .policy
+        }
+        None => shared.policy,
+    };
+
+    Ok((
+        SizeLint::from_table(shared.lint),
+        IssueLinks::from_table(shared.issues)?,
+        StackPolicy::from_table(policy)?,
+    ))
+}
 
 /// Configuration for git-stk
 #[derive(Debug, Clone)]
 pub struct Config {
-    /// Base branch (e.g., "main")
+    /// Base the stack is built on: a branch (e.g., "main"), or any revision such
+    /// as "refs/remotes/origin/release-2024" or a tag
     pub base: String,
+    /// Branch on the remote that `base` names, which bottom PRs target. `None`
+    /// when the base is a tag or commit, which PRs can't target.
+    pub base_branch: Option<String>,
     /// Remote name (e.g., "origin")
     pub remote: String,
+    /// Forge hosting the remote (default: detected from the remote URL)
+    pub forge: Forge,
+    /// URL of the remote, `None` when it doesn't exist
+    pub remote_url: Option<String>,
     /// Notes ref
     pub notes_ref: String,
+    /// Sign notes with the user's git signing key (default: false)
+    pub sign_notes: bool,
+    /// Signature checks on notes during discovery (default: warn with signNotes, else off)
+    pub verify_notes: VerifyNotes,
     /// Timeout in minutes when waiting for PR merge (default: 20)
     pub land_timeout_minutes: u64,
+    /// Seconds between checks of the PR while waiting for it to merge (default: 5)
+    pub land_poll_seconds: u64,
+    /// Template for the merge commit title when landing, see `land` (default: GitHub's)
+    pub merge_commit_title: Option<String>,
+    /// Template for the merge commit message when landing (default: the PR body)
+    pub merge_commit_message: Option<String>,
+    /// Append commit annotations to PR bodies on export (default: false)
+    pub annotations_in_pr_body: bool,
+    /// Post a stack summary comment on the top PR on export (default: false)
+    pub summary_comment: bool,
+    /// Assign created PRs to the authenticated user (default: false)
+    pub self_assign: bool,
+    /// Milestone (number or title) to attach created PRs to
+    pub milestone: Option<String>,
+    /// ProjectV2 (number or node ID) to add created PRs to
+    pub project: Option<String>,
+    /// Keep commit trailers in a PR body footer instead of stripping them (default: false)
+    pub trailers_in_footer: bool,
+    /// Re-sign commits rewritten by internal rebases (default: commit.gpgSign)
+    pub gpg_sign: bool,
+    /// Add a Signed-off-by trailer to commits rewritten by internal rebases (default: false)
+    pub signoff: bool,
+    /// Refuse to export commits without a Signed-off-by from their author (default: false)
+    pub require_signoff: bool,
+    /// Keep local `stk/<branch>/<slot>` branches in sync with the slot branches
+    /// on export (default: false)
+    pub keep_branches: bool,
+    /// List the remote's branches (`ls-remote`) when discovering the stack;
+    /// otherwise use the remote-tracking branches from the last fetch
+    /// (default: true, `--fetch`/`--no-fetch` override it)
+    pub fetch: bool,
+    /// Backup remote export also pushes the slot branches and notes to;
+    /// failures there are only warnings (default: none)
+    pub mirror_remote: Option<String>,
+    /// Comment the range-diff on PRs whose slot branch was force-pushed with a
+    /// changed patch (default: false)
+    pub range_diff_comments: bool,
+    /// Stash uncommitted changes around internal rebases and restore them after
+    /// (default: rebase.autoStash)
+    pub autostash: bool,
+    /// Name for the branch created when exporting from a detached HEAD; `{sha}` is
+    /// replaced by the short commit SHA (default: "stk/{sha}")
+    pub detached_branch: String,
+    /// Template prepended to PR titles, e.g. "[{position}/{total}] "
+    pub title_prefix: Option<String>,
+    /// Hook-running policy for pushes (default: each)
+    pub push_hooks: PushHooks,
+    /// Most refs sent in a single `git push`, 0 for no limit (default: 0)
+    pub max_refs_per_push: usize,
+    /// Most entries `view` shows when neither `--limit` nor `--around` is given,
+    /// 0 for all (default: 0)
+    pub view_limit: usize,
+    /// Stack snapshots kept under `refs/git-stk/backup/`, older ones are
+    /// deleted when a new one is taken; 0 keeps all (default: 50)
+    pub keep_snapshots: usize,
+    /// Timeout for GitHub HTTP requests in seconds, 0 for none (default: 30)
+    pub http_timeout_seconds: u64,
+    /// Retries for transient GitHub API failures (default: 3)
+    pub max_retries: u32,
+    /// Delay before the first retry in milliseconds, doubled per retry (default: 1000)
+    pub retry_backoff_ms: u64,
+    /// Upper bound for a single retry delay in milliseconds (default: 10000)
+    pub retry_max_backoff_ms: u64,
+    /// Give commits a `Change-Id` trailer on export and use it to re-match
+    /// commits whose notes were lost (default: false)
+    pub change_id: bool,
+    /// When to push the notes ref (default: on-export)
+    pub push_notes: PushNotes,
+    /// Push notes to a per-user ref (`<notesRef>/<login>`) and read everyone's (default: false)
+    pub notes_per_user: bool,
+    /// On shared branches, only your commits (author email = `user.email`) are
+    /// entries; others' are shown as context (default: false)
+    pub only_mine: bool,
+    /// On shared long-lived branches, only commits made since this date (as
+    /// `git log --since` takes it) are entries; older ones are shown as context
+    pub only_since: Option<String>,
+    /// Proxy and CA bundle for git operations and forge API calls
+    pub transport: Transport,
+    /// Branch patterns export refuses to run from, besides the base branch
+    /// (`git-stk.protectedBranch`, multi-valued, `*` wildcards)
+    pub protected_branches: Vec<String>,
+    /// Jujutsu mode: the bookmark at the detached HEAD is the current branch and
+    /// jj change-ids re-match rewritten commits (default: whether `.jj` exists)
+    pub jj: bool,
+    /// Labels added to created PRs by conventional-commit type
+    /// (`git-stk.label.<type>`, e.g. `git-stk.label.feat = enhancement`)
+    pub type_labels: HashMap<String, String>,
+    /// Regex every commit subject must match for export to proceed
+    pub subject_pattern: Option<String>,
+    /// Changed lines above which `stats` flags an entry as hard to review, 0 to
+    /// disable (default: 400)
+    pub max_entry_lines: usize,
+    /// Size limits checked before export, from `.git-stk.toml` (opt-in)
+    pub size_lint: Option<SizeLint>,
+    /// Issue links added to PR bodies, from `.git-stk.toml` (opt-in)
+    pub issue_links: Option<IssueLinks>,
+    /// Stack policy checked before export, from `git-stk.policyFile` or `.git-stk.toml` (opt-in)
+    pub policy: Option<StackPolicy>,
+    /// Why `.git-stk.toml` or the policy file couldn't be read, if it couldn't;
+    /// the three settings above are then unset
+    pub shared_config_error: Option<String>,
+    /// Flag entries that rewrite lines of an earlier entry in view and dry runs (default: true)
+    pub overlap_warnings: bool,
+    /// Create the local base branch from `<remote>/<base>` when it's missing (default: false)
+    pub create_base_branch: bool,
+    /// Commands run on stack events, by event name (`git-stk.hook.<event>`,
+    /// can be repeated, e.g. `git-stk.hook.post-export = ./notify.sh`)
+    pub hooks: HashMap<String, Vec<String>>,
+    /// URL receiving a JSON notification when PRs are created or land, e.g. a
+    /// Slack incoming webhook (`git-stk.webhookUrl`)
+    pub webhook_url: Option<String>,
 }
 
 impl Config {
@@ -40,6 +470,17 @@ impl Config {
             .get_string("git-stk.remote")
             .unwrap_or_else(|_| "origin".to_string());
 
+        let base_branch = base_branch_name(repo, &base, &remote);
+
+        let remote_url = repo
+            .find_remote(&remote)
+            .ok()
+            .and_then(|r| r.url().map(str::to_string));
+        let forge = match git_config.get_string("git-stk.forge") {
+            Ok(value) => Forge::parse(&value)?,
+            Err(_) => remote_url.as_deref().map(Forge::detect).unwrap_or_default(),
+        };
+
         let notes_ref = git_config
             .get_string("git-stk.notesRef")
             .unwrap_or_else(|_| "refs/notes/git-stk".to_string());
@@ -49,12 +490,261 @@ impl Config {
             .ok()
             .map(|v| v as u64)
             .unwrap_or(20);
+        let land_poll_seconds = git_config
+            .get_i64("git-stk.landPollSeconds")
+            .ok()
+            .map(|v| v.max(1) as u64)
+            .unwrap_or(5);
+        let merge_commit_title = git_config.get_string("git-stk.mergeCommitTitle").ok();
+        let merge_commit_message = git_config.get_string("git-stk.mergeCommitMessage").ok();
+
+        let annotations_in_pr_body = git_config
+            .get_bool("git-stk.annotationsInPrBody")
+            .unwrap_or(false);
+
+        let summary_comment = git_config
+            .get_bool("git-stk.summaryComment")
+            .unwrap_or(false);
+
+        let self_assign = git_config.get_bool("git-stk.selfAssign").unwrap_or(false);
+
+        let milestone = git_config.get_string("git-stk.milestone").ok();
+        let project = git_config.get_string("git-stk.project").ok();
+
+        let trailers_in_footer = git_config
+            .get_bool("git-stk.trailersInFooter")
+            .unwrap_or(false);
+
+        let gpg_sign = git_config
+            .get_bool("git-stk.gpgSign")
+            .or_else(|_| git_config.get_bool("commit.gpgSign"))
+            .unwrap_or(false);
+
+        let signoff = git_config.get_bool("git-stk.signoff").unwrap_or(false);
+        let require_signoff = git_config
+            .get_bool("git-stk.requireSignoff")
+            .unwrap_or(false);
+        let keep_branches = git_config.get_bool("git-stk.keepBranches").unwrap_or(false);
+        let fetch = git_config.get_bool("git-stk.fetch").unwrap_or(true);
+        let mirror_remote = git_config
+            .get_string("git-stk.mirrorRemote")
+            .ok()
+            .filter(|r| !r.is_empty() && *r != remote);
+        let range_diff_comments = git_config
+            .get_bool("git-stk.rangeDiffComments")
+            .unwrap_or(false);
+
+        let autostash = git_config
+            .get_bool("git-stk.autostash")
+            .or_else(|_| git_config.get_bool("rebase.autoStash"))
+            .unwrap_or(false);
+
+        let detached_branch = git_config
+            .get_string("git-stk.detachedBranch")
+            .unwrap_or_else(|_| "stk/{sha}".to_string());
+
+        let title_prefix = git_config.get_string("git-stk.titlePrefix").ok();
+
+        let sign_notes = git_config.get_bool("git-stk.signNotes").unwrap_or(false);
+        let verify_notes = match git_config.get_string("git-stk.verifyNotes") {
+            Ok(value) => VerifyNotes::parse(&value)?,
+            Err(_) if sign_notes => VerifyNotes::Warn,
+            Err(_) => VerifyNotes::Off,
+        };
+
+        let push_hooks = match git_config.get_string("git-stk.pushHooks") {
+            Ok(value) => PushHooks::parse(&value)?,
+            Err(_) => PushHooks::default(),
+        };
+
+        let get_u64 = |key: &str, default: u64| {
+            git_config
+                .get_i64(key)
+                .ok()
+                .and_then(|v| u64::try_from(v).ok())
+                .unwrap_or(default)
+        };
+        let max_refs_per_push = get_u64("git-stk.maxRefsPerPush", 0) as usize;
+        let view_limit = get_u64("git-stk.viewLimit", 0) as usize;
+        let keep_snapshots = get_u64("git-stk.keepSnapshots", 50) as usize;
+        let http_timeout_seconds = get_u64("git-stk.httpTimeoutSeconds", 30);
+        let max_retries = get_u64("git-stk.maxRetries", 3) as u32;
+        let retry_backoff_ms = get_u64("git-stk.retryBackoffMs", 1000);
+        let retry_max_backoff_ms = get_u64("git-stk.retryMaxBackoffMs", 10_000);
+
+        let change_id = git_config.get_bool("git-stk.changeId").unwrap_or(false);
+
+        let push_notes = match git_config.get_string("git-stk.pushNotes") {
+            Ok(value) => PushNotes::parse(&value)?,
+            Err(_) => PushNotes::default(),
+        };
+        let notes_per_user = git_config.get_bool("git-stk.notesPerUser").unwrap_or(false);
+        let only_mine = git_config.get_bool("git-stk.onlyMine").unwrap_or(false);
+        let only_since = git_config
+            .get_string("git-stk.onlySince")
+            .ok()
+            .filter(|date| !date.is_empty());
+
+        let env = |key: &str| std::env::var(key).ok().filter(|v| !v.is_empty());
+        let proxy = git_config
+            .get_string("git-stk.proxy")
+            .ok()
+            .or_else(|| env("HTTPS_PROXY"))
+            .or_else(|| env("https_proxy"))
+            .or_else(|| env("ALL_PROXY"))
+            .or_else(|| env("all_proxy"))
+            .or_else(|| git_config.get_string("http.proxy").ok())
+            .filter(|p| !p.is_empty());
+        let ca_info = env("GIT_SSL_CAINFO")
+            .map(PathBuf::from)
+            .or_else(|| git_config.get_path("http.sslCAInfo").ok());
+
+        let mut protected_branches = Vec::new();
+        if let Ok(mut entries) = git_config.multivar("git-stk.protectedBranch", None) {
+            while let Some(Ok(entry)) = entries.next() {
+                if let Some(value) = entry.value().filter(|v| !v.is_empty()) {
+                    protected_branches.push(value.to_string());
+                }
+            }
+        }
+
+        let jj = crate::git::jj::is_enabled(repo);
+
+        let mut type_labels = HashMap::new();
+        if let Ok(entries) = git_config.entries(Some(r"git-stk\.label\..*")) {
+            entries.for_each(|entry| {
+                if let (Some(name), Some(label)) = (entry.name(), entry.value())
+                    && let Some(kind) = name.strip_prefix("git-stk.label.")
+                    && !label.is_empty()
+                {
+                    type_labels.insert(kind.to_ascii_lowercase(), label.to_string());
+                }
+            })?;
+        }
+
+        let mut hooks: HashMap<String, Vec<String>> = HashMap::new();
+        if let Ok(entries) = git_config.entries(Some(r"git-stk\.hook\..*")) {
+            entries.for_each(|entry| {
+                if let (Some(name), Some(command)) = (entry.name(), entry.value())
+                    && let Some(event) = name.strip_prefix("git-stk.hook.")
+                    && !command.is_empty()
+                {
+                    hooks
+                        .entry(event.to_string())
+                        .or_default()
+                        .push(command.to_string());
+                }
+            })?;
+        }
+
+        let webhook_url = git_config
+            .get_string("git-stk.webhookUrl")
+            .ok()
+            .filter(|url| !url.is_empty());
+
+        let max_entry_lines = get_u64("git-stk.maxEntryLines", 400) as usize;
+
+        // A broken .git-stk.toml only stops the commands that rely on it
+        let policy_file = git_config.get_string("git-stk.policyFile").ok();
+        let (size_lint, issue_links, policy, shared_config_error) =
+            match load_shared_settings(repo, policy_file.as_deref()) {
+                Ok((size_lint, issue_links, policy)) => (size_lint, issue_links, policy, None),
+                Err(e) => (None, None, None, Some(format!("{:#}", e))),
+            };
+        let overlap_warnings = git_config
+            .get_bool("git-stk.overlapWarnings")
+            .unwrap_or(true);
+        let create_base_branch = git_config
+            .get_bool("git-stk.createBaseBranch")
+            .unwrap_or(false);
+
+        let subject_pattern = git_config
+            .get_string("git-stk.subjectPattern")
+            .ok()
+            .filter(|p| !p.is_empty());
 
         Ok(Self {
             base,
+            base_branch,
             remote,
+            forge,
+            remote_url,
             notes_ref,
+            sign_notes,
+            verify_notes,
             land_timeout_minutes,
+            land_poll_seconds,
+            merge_commit_title,
+            merge_commit_message,
+            annotations_in_pr_body,
+            summary_comment,
+            self_assign,
+            milestone,
+            project,
+            trailers_in_footer,
+            gpg_sign,
+            signoff,
+            require_signoff,
+            keep_branches,
+            fetch,
+            mirror_remote,
+            range_diff_comments,
+            autostash,
+            detached_branch,
+            title_prefix,
+            push_hooks,
+            max_refs_per_push,
+            view_limit,
+            keep_snapshots,
+            http_timeout_seconds,
+            max_retries,
+            retry_backoff_ms,
+            retry_max_backoff_ms,
+            change_id,
+            push_notes,
+            notes_per_user,
+            only_mine,
+            only_since,
+            transport: Transport { proxy, ca_info },
+            protected_branches,
+            jj,
+            type_labels,
+            subject_pattern,
+            max_entry_lines,
+            size_lint,
+            issue_links,
+            policy,
+            shared_config_error,
+            overlap_warnings,
+            create_base_branch,
+            hooks,
+            webhook_url,
+        })
+    }
+
+    /// Fail if `.git-stk.toml` or the policy file is broken, for commands that
+    /// would otherwise skip its checks
+    pub fn ensure_shared_config(&self) -> Result<()> {
+        match &self.shared_config_error {
+            Some(error) => anyhow::bail!("{}", error),
+            None => Ok(()),
+        }
+    }
+
+    /// The base by its branch name (`release` for `refs/remotes/origin/release`),
+    /// or as configured when it isn't a branch (e.g. a tag)
+    pub fn base_name(&self) -> &str {
+        self.base_branch.as_deref().unwrap_or(&self.base)
+    }
+
+    /// The branch bottom PRs target; fails when the base isn't a branch
+    pub fn pr_base(&self) -> Result<&str> {
+        self.base_branch.as_deref().with_context(|| {
+            format!(
+                "git-stk.base '{}' is not a branch, but PRs can only target branches. \
+                 Set it to a branch, e.g. 'git config git-stk.base release-2024'.",
+                self.base
+            )
         })
     }
 
@@ -76,6 +766,30 @@ impl Config {
         Ok(dir.join("slots.json"))
     }
 
+    /// Get the remote capabilities cache file path
+    pub fn capabilities_cache_path(repo: &Repository) -> Result<PathBuf> {
+        let dir = Self::git_stack_dir(repo)?;
+        Ok(dir.join("capabilities.json"))
+    }
+
+    /// Get the test results cache file path
+    pub fn test_cache_path(repo: &Repository) -> Result<PathBuf> {
+        let dir = Self::git_stack_dir(repo)?;
+        Ok(dir.join("test-cache.json"))
+    }
+
+    /// Get the path of the landings started with `land --skip-wait`
+    pub fn pending_lands_path(repo: &Repository) -> Result<PathBuf> {
+        let dir = Self::git_stack_dir(repo)?;
+        Ok(dir.join("pending-lands.json"))
+    }
+
+    /// Get the path of the cached `git stk prompt` segment
+    pub fn prompt_cache_path(repo: &Repository) -> Result<PathBuf> {
+        let dir = Self::git_stack_dir(repo)?;
+        Ok(dir.join("prompt.json"))
+    }
+
     /// Ensure git notes rewriting is configured for the repository
     /// This allows notes to follow commits during rebase, amend, and reorder operations
     pub fn ensure_notes_rewrite_config(repo: &Repository, notes_ref: &str) -> Result<()> {
@@ -91,10 +805,10 @@ impl Config {
         let mut existing_values = Vec::new();
         if let Ok(mut entries) = local_config.entries(Some(rewrite_ref_key)) {
             while let Some(entry) = entries.next() {
-                if let Ok(entry) = entry {
-                    if let Some(value) = entry.value() {
-                        existing_values.push(value.to_string());
-                    }
+                if let Ok(entry) = entry
+                    && let Some(value) = entry.value()
+                {
+                    existing_values.push(value.to_string());
                 }
             }
         }
@@ -108,14 +822,20 @@ impl Config {
         }
 
         // Enable rebase note rewriting
-        if local_config.get_bool("notes.rewrite.rebase").unwrap_or(false) == false {
+        if !local_config
+            .get_bool("notes.rewrite.rebase")
+            .unwrap_or(false)
+        {
             local_config
                 .set_bool("notes.rewrite.rebase", true)
                 .context("Failed to set notes.rewrite.rebase")?;
         }
 
         // Enable amend note rewriting
-        if local_config.get_bool("notes.rewrite.amend").unwrap_or(false) == false {
+        if !local_config
+            .get_bool("notes.rewrite.amend")
+            .unwrap_or(false)
+        {
             local_config
                 .set_bool("notes.rewrite.amend", true)
                 .context("Failed to set notes.rewrite.amend")?;
@@ -124,3 +844,256 @@ impl Config {
         Ok(())
     }
 }
+
+/// The remote branch a `git-stk.base` value names: `refs/heads/x`,
+/// `refs/remotes/<remote>/x`, `<remote>/x`, and plain names all mean `x`.
+/// Tags, other refs, commit IDs, and revision expressions aren't branches.
+pub(crate) fn base_branch_name(repo: &Repository, base: &str, remote: &str) -> Option<String> {
+    if let Some(branch) = base.strip_prefix("refs/heads/") {
+        return Some(branch.to_string());
+    }
+    if let Some(rest) = base.strip_prefix("refs/remotes/") {
+        return rest.split_once('/').map(|(_, branch)| branch.to_string());
+    }
+    if base.starts_with("refs/") {
+        return None;
+    }
+    if let Some(branch) = base.strip_prefix(&format!("{}/", remote)) {
+        return Some(branch.to_string());
+    }
+
+    // Revision expressions like `HEAD~2` or `main@{1}` can't name a branch
+    if !git2::Branch::name_is_valid(base).unwrap_or(false) {
+        return None;
+    }
+
+    // A plain name is a branch unless it only exists as a tag or commit
+    let is_branch = repo.find_branch(base, git2::BranchType::Local).is_ok()
+        || repo
+            .find_branch(&format!("{}/{}", remote, base), git2::BranchType::Remote)
+            .is_ok();
+    let is_tag = repo.find_reference(&format!("refs/tags/{}", base)).is_ok();
+    let is_commit_id = base.len() >= 7
+        && base.chars().all(|c| c.is_ascii_hexdigit())
+        && repo.revparse_single(base).is_ok();
+    (is_branch || !(is_tag || is_commit_id)).then(|| base.to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_forge_detect() {
+        assert_eq!(
+            Forge::detect("git@bitbucket.org:team/repo.git"),
+            Forge::Bitbucket
+        );
+        assert_eq!(
+            Forge::detect("https://user@bitbucket.org/team/repo.git"),
+            Forge::Bitbucket
+        );
+        assert_eq!(
+            Forge::detect("ssh://git@bitbucket.org/team/repo.git"),
+            Forge::Bitbucket
+        );
+        assert_eq!(
+            Forge::detect("https://org@dev.azure.com/org/project/_git/repo"),
+            Forge::AzureDevOps
+        );
+        assert_eq!(
+            Forge::detect("git@ssh.dev.azure.com:v3/org/project/repo"),
+            Forge::AzureDevOps
+        );
+        assert_eq!(
+            Forge::detect("https://org.visualstudio.com/project/_git/repo"),
+            Forge::AzureDevOps
+        );
+        assert_eq!(
+            Forge::detect("git@github.com:owner/repo.git"),
+            Forge::GitHub
+        );
+        assert_eq!(
+            Forge::detect("git@github-work.com:my-org/my-repo.git"),
+            Forge::GitHub
+        );
+        assert_eq!(
+            Forge::detect("https://github.example.com/bitbucket.org/repo"),
+            Forge::GitHub
+        );
+        assert_eq!(Forge::detect("file:///path/to/repo"), Forge::GitHub);
+    }
+
+    #[test]
+    fn test_forge_pr_url() {
+        assert_eq!(
+            Forge::GitHub.pr_url("o", "r", 3),
+            "https://github.com/o/r/pull/3"
+        );
+        assert_eq!(
+            Forge::Bitbucket.pr_url("team", "r", 3),
+            "https://bitbucket.org/team/r/pull-requests/3"
+        );
+        assert_eq!(
+            Forge::AzureDevOps.pr_url("org/Web Apps", "r", 3),
+            "https://dev.azure.com/org/Web%20Apps/_git/r/pullrequest/3"
+        );
+    }
+
+    #[test]
+    fn test_issue_links_load() {
+        let dir = tempfile::tempdir().unwrap();
+        let repo = Repository::init(dir.path()).unwrap();
+        let issue_links =
+            |repo: &Repository| load_shared_settings(repo, None).map(|(_, links, _)| links);
+        assert_eq!(issue_links(&repo).unwrap(), None);
+
+        std::fs::write(
+            dir.path().join(SHARED_CONFIG_FILE),
+            "[issues]\nurl = \"https://acme.atlassian.net/browse/{key}\"\nprojects = [\"ABC\", \"LIN\"]\nnotify-url = \"https://hooks.acme.dev/jira\"\n",
+        )
+        .unwrap();
+        assert_eq!(
+            issue_links(&repo).unwrap(),
+            Some(IssueLinks {
+                url: "https://acme.atlassian.net/browse/{key}".to_string(),
+                projects: vec!["ABC".to_string(), "LIN".to_string()],
+                notify_url: Some("https://hooks.acme.dev/jira".to_string()),
+            })
+        );
+
+        std::fs::write(
+            dir.path().join(SHARED_CONFIG_FILE),
+            "[issues]\nurl = \"https://acme.dev/issues\"\n",
+        )
+        .unwrap();
+        assert!(issue_links(&repo).is_err());
+    }
+
+    #[test]
+    fn test_size_lint_load() {
+        let dir = tempfile::tempdir().unwrap();
+        let repo = Repository::init(dir.path()).unwrap();
+        let size_lint =
+            |repo: &Repository| load_shared_settings(repo, None).map(|(lint, _, _)| lint);
+
+        std::fs::write(
+            dir.path().join(SHARED_CONFIG_FILE),
+            "[lint]\nmax-lines = 400\nmax-files = 0\nmode = \"error\"\n",
+        )
+        .unwrap();
+        assert_eq!(
+            size_lint(&repo).unwrap(),
+            Some(SizeLint {
+                max_lines: Some(400),
+                max_files: None,
+                mode: LintMode::Error
+            })
+        );
+
+        std::fs::write(
+            dir.path().join(SHARED_CONFIG_FILE),
+            "[lint]\nmax-lines = 400\nmode = \"fail\"\n",
+        )
+        .unwrap();
+        assert!(size_lint(&repo).is_err());
+    }
+
+    #[test]
+    fn test_stack_policy_load() {
+        let dir = tempfile::tempdir().unwrap();
+        let repo = Repository::init(dir.path()).unwrap();
+        let policy = |repo: &Repository, file: Option<&str>| {
+            load_shared_settings(repo, file).map(|(_, _, policy)| policy)
+        };
+        assert_eq!(policy(&repo, None).unwrap(), None);
+
+        std::fs::write(
+            dir.path().join(SHARED_CONFIG_FILE),
+            "[policy]\nmax-depth = 5\nrequired-labels = [\"stacked\", \"needs-review\"]\ndraft-first = true\nforbidden-bases = [\"release/*\"]\n",
+        )
+        .unwrap();
+        let expected = StackPolicy {
+            max_depth: Some(5),
+            required_labels: vec!["stacked".to_string(), "needs-review".to_string()],
+            draft_first: true,
+            forbidden_bases: vec!["release/*".to_string()],
+        };
+        assert_eq!(policy(&repo, None).unwrap(), Some(expected));
+
+        // An explicit policy file replaces the table of .git-stk.toml
+        std::fs::write(
+            dir.path().join("org-policy.toml"),
+            "[policy]\nmax-depth = 3\n",
+        )
+        .unwrap();
+        assert_eq!(
+            policy(&repo, Some("org-policy.toml")).unwrap(),
+            Some(StackPolicy {
+                max_depth: Some(3),
+                ..Default::default()
+            })
+        );
+        assert!(policy(&repo, Some("missing.toml")).is_err());
+
+        // A malformed value is an error, not a missing rule
+        for line in [
+            "max-depth = \"five\"",
+            "max-depth = -1",
+            "max-depth = 0",
+            "required-labels = \"stacked\"",
+        ] {
+            std::fs::write(
+                dir.path().join("org-policy.toml"),
+                format!("[policy]\n{}\n", line),
+            )
+            .unwrap();
+            assert!(policy(&repo, Some("org-policy.toml")).is_err(), "{}", line);
+        }
+    }
+
+    #[test]
+    fn test_broken_shared_config_only_fails_on_use() {
+        let dir = tempfile::tempdir().unwrap();
+        let repo = Repository::init(dir.path()).unwrap();
+        std::fs::write(
+            dir.path().join(SHARED_CONFIG_FILE),
+            "[lint\nmax-lines = 400\n",
+        )
+        .unwrap();
+
+        let config = Config::load(&repo).unwrap();
+        assert_eq!(config.size_lint, None);
+        assert!(config.ensure_shared_config().is_err());
+    }
+
+    #[test]
+    fn test_base_branch_name() {
+        let dir = tempfile::tempdir().unwrap();
+        let repo = Repository::init(dir.path()).unwrap();
+        let signature = git2::Signature::now("Test", "test@example.com").unwrap();
+        let tree = repo
+            .find_tree(repo.index().unwrap().write_tree().unwrap())
+            .unwrap();
+        let oid = repo
+            .commit(None, &signature, &signature, "base", &tree, &[])
+            .unwrap();
+        repo.reference("refs/tags/v1.0", oid, false, "tag").unwrap();
+
+        let name = |base: &str| base_branch_name(&repo, base, "origin");
+        assert_eq!(name("main"), Some("main".to_string()));
+        assert_eq!(name("refs/heads/develop"), Some("develop".to_string()));
+        assert_eq!(
+            name("refs/remotes/origin/release-2024"),
+            Some("release-2024".to_string())
+        );
+        assert_eq!(
+            name("origin/release-2024"),
+            Some("release-2024".to_string())
+        );
+        assert_eq!(name("v1.0"), None);
+        assert_eq!(name("refs/tags/v1.0"), None);
+        assert_eq!(name(&oid.to_string()[..10]), None);
+        assert_eq!(name("HEAD~2"), None);
+    }
+}
@@ -1,5 +1,11 @@
 pub mod config;
 pub mod stack;
 
-pub use config::Config;
-pub use stack::{CommitMetadata, Entry, PrState, Stack, UpdateStatus};
+pub use config::{
+    Config, Forge, IssueLinks, LintMode, PushHooks, PushNotes, SizeLint, StackPolicy, Transport,
+    VerifyNotes,
+};
+pub use stack::{
+    CiStatus, CommitMetadata, ContextCommit, Entry, LastExport, OrphanedPr, PrStackMetadata,
+    PrState, RemoteRefsSource, Stack, UpdateStatus,
+};
@@ -1,5 +1,7 @@
+use crate::model::Forge;
 use git2::Oid;
 use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
 
 /// Status of a stack entry relative to GitHub
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
@@ -12,6 +14,26 @@ pub enum UpdateStatus {
     CreatePr,
 }
 
+/// Where the remote branches of a discovered stack come from
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum RemoteRefsSource {
+    /// Listed on the remote during discovery (`ls-remote`)
+    #[default]
+    Remote,
+    /// The remote-tracking branches (`--no-fetch`, or the remote was
+    /// unreachable), with the time of the last fetch when known
+    Tracking { fetched_at: Option<i64> },
+}
+
+/// Combined CI status of a PR's head commit
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum CiStatus {
+    Success,
+    Failure,
+    Pending,
+}
+
 /// PR state from GitHub
 #[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
 #[serde(rename_all = "lowercase")]
@@ -22,6 +44,36 @@ pub enum PrState {
     Draft,
 }
 
+/// Stack metadata embedded in each PR body, used to rebuild notes when they are missing
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct PrStackMetadata {
+    /// Working branch the stack was exported from
+    pub branch: String,
+    /// Slot of the PR's commit
+    pub slot: String,
+    /// Position in the stack (1-indexed)
+    pub position: usize,
+    /// Number of entries in the stack
+    pub total: usize,
+}
+
+/// The last successful push of an entry's slot branch
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct LastExport {
+    /// When the push happened (seconds since Unix epoch)
+    pub at: i64,
+    /// Commit the slot branch was pushed to, which may be an older version of
+    /// the entry since notes follow rebases and amends
+    pub oid: String,
+    /// Commit the slot branch held before this push, what reviewers saw until then
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub previous: Option<String>,
+    /// Revision number of this push, counting from 1 (0 in notes written before
+    /// revisions were tracked)
+    #[serde(default)]
+    pub revision: u32,
+}
+
 /// Metadata stored in git notes for each commit
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct CommitMetadata {
@@ -30,6 +82,19 @@ pub struct CommitMetadata {
     /// Slot identifier (e.g., "01", "02", or custom like "add-tests")
     /// The head ref name can be derived as {current_branch}--{slot}
     pub slot: String,
+    /// Free-form annotation that travels with the commit (e.g., "blocked on INFRA-42")
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub annotation: Option<String>,
+    /// Last successful export of this entry, possibly from another machine
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub last_export: Option<LastExport>,
+    /// Email of whoever owns the slot branch: the commit's author, or the last
+    /// to export with `--take-ownership`. Others' exports won't force-push it.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub owner: Option<String>,
+    /// Detached signature over the note, see `git::note_signature`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub signature: Option<String>,
 }
 
 /// A single entry in the stack
@@ -63,10 +128,111 @@ pub struct Entry {
     pub remote_branch_exists: bool,
     /// Whether commit is merged into main
     pub merged_into_main: bool,
+    /// Whether the PR conflicts with its base branch
+    pub has_conflicts: bool,
+    /// Whether GitHub reports the PR out of date with its base branch
+    pub behind_base: bool,
+    /// Review threads on the PR not marked resolved
+    pub unresolved_threads: usize,
+    /// GitHub's review decision: REVIEW_REQUIRED, CHANGES_REQUESTED or APPROVED
+    pub review_decision: Option<String>,
+    /// Combined status of the checks on the PR's head commit
+    pub ci_status: Option<CiStatus>,
+    /// Branch the PR currently targets on GitHub (`base_ref` is where it should)
+    pub pr_base_ref: Option<String>,
     /// Repository owner (for PR links)
     pub repo_owner: Option<String>,
     /// Repository name (for PR links)
     pub repo_name: Option<String>,
+    /// Forge hosting the repository (for PR links)
+    pub forge: Forge,
+    /// Commit author name
+    pub author_name: String,
+    /// Commit time (seconds since Unix epoch)
+    pub commit_time: i64,
+    /// Free-form annotation from metadata
+    pub annotation: Option<String>,
+    /// Last successful export from metadata
+    pub last_export: Option<LastExport>,
+    /// Owner of the slot branch from metadata, else the commit author's email
+    pub owner: Option<String>,
+}
+
+impl Entry {
+    /// Web URL of this entry's PR, if one exists
+    pub fn pr_url(&self) -> Option<String> {
+        let pr_number = self.pr_number?;
+        let owner = self.repo_owner.as_ref()?;
+        let repo = self.repo_name.as_ref()?;
+        Some(self.forge.pr_url(owner, repo, pr_number))
+    }
+}
+
+#[cfg(test)]
+impl Entry {
+    /// Entry `index` of a stack on `main` that has no slot or PR yet; tests
+    /// set the fields they care about with struct update syntax
+    pub fn for_test(index: usize) -> Self {
+        let oid = Oid::from_str(&format!("{:040}", index)).unwrap();
+        Entry {
+            index,
+            oid,
+            short_sha: format!("{:.7}", oid),
+            subject: format!("commit {}", index),
+            head_ref: None,
+            pr_number: None,
+            pr_state: None,
+            status: UpdateStatus::CreatePr,
+            base_ref: "main".to_string(),
+            remote_oid: None,
+            slot: None,
+            predicted_slot: None,
+            remote_branch_exists: false,
+            merged_into_main: false,
+            has_conflicts: false,
+            behind_base: false,
+            unresolved_threads: 0,
+            review_decision: None,
+            ci_status: None,
+            pr_base_ref: None,
+            repo_owner: None,
+            repo_name: None,
+            forge: Forge::GitHub,
+            author_name: String::new(),
+            commit_time: 0,
+            annotation: None,
+            last_export: None,
+            owner: None,
+        }
+    }
+}
+
+/// An open PR on a slot branch of the current branch that no entry uses any
+/// more, e.g. because its commit was dropped with `git rebase -i`
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct OrphanedPr {
+    pub slot: String,
+    pub head_ref: String,
+    #[serde(rename = "pr")]
+    pub pr_number: u64,
+    pub title: String,
+    /// This clone exported the slot's commit and then dropped it, so export
+    /// may close the PR (`--close-dropped`); others are left to `prune`
+    #[serde(skip)]
+    pub dropped_here: bool,
+}
+
+/// A commit by someone else between the entries of a shared branch
+/// (`git-stk.onlyMine`): shown for context, never exported or rewritten
+/// on its own
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ContextCommit {
+    /// Number of entries below it: it sits between entries `below` and `below + 1`
+    pub below: usize,
+    pub oid: Oid,
+    pub short_sha: String,
+    pub subject: String,
+    pub author_name: String,
 }
 
 /// The complete stack of commits
@@ -78,6 +244,17 @@ pub struct Stack {
     pub current_branch: String,
     /// All entries in order (bottom to top)
     pub entries: Vec<Entry>,
+    /// Commits by others between or above the entries, in order (bottom to top)
+    pub context: Vec<ContextCommit>,
+    /// Remote branches fetched during discovery (branch name -> oid)
+    pub remote_refs: HashMap<String, Oid>,
+    /// Where `remote_refs` come from
+    pub remote_refs_source: RemoteRefsSource,
+    /// Open PRs left on slot branches no entry uses (filled by `discover_stack`)
+    pub orphaned_prs: Vec<OrphanedPr>,
+    /// GitHub or the remote couldn't be reached during discovery: PR states are
+    /// unknown and remote branches are as of the last fetch
+    pub offline: bool,
 }
 
 impl Stack {
@@ -86,6 +263,11 @@ impl Stack {
             base_branch,
             current_branch,
             entries: Vec::new(),
+            context: Vec::new(),
+            remote_refs: HashMap::new(),
+            remote_refs_source: RemoteRefsSource::Remote,
+            orphaned_prs: Vec::new(),
+            offline: false,
         }
     }
 
@@ -100,4 +282,19 @@ impl Stack {
     pub fn is_empty(&self) -> bool {
         self.entries.is_empty()
     }
+
+    /// Context commits `entry`'s PR shows in its diff: those between it and the
+    /// entry below (or the base)
+    pub fn context_in_pr<'a>(&'a self, entry: &Entry) -> impl Iterator<Item = &'a ContextCommit> {
+        let below = entry.index - 1;
+        self.context.iter().filter(move |c| c.below == below)
+    }
+
+    /// The topmost commit of the walked range, an entry or a context commit
+    pub fn top_oid(&self) -> Option<Oid> {
+        match self.context.last() {
+            Some(context) if context.below == self.entries.len() => Some(context.oid),
+            _ => self.entries.last().map(|e| e.oid),
+        }
+    }
 }
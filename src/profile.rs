@@ -0,0 +1,132 @@
+//! Lightweight timing spans for `--profile`.
+//!
+//! Spans are only recorded once profiling is enabled, so instrumented code paths
+//! cost a single atomic load otherwise.
+
+use console::style;
+use std::sync::Mutex;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::time::{Duration, Instant};
+
+static ENABLED: AtomicBool = AtomicBool::new(false);
+static SPANS: Mutex<Vec<(String, Duration)>> = Mutex::new(Vec::new());
+
+/// Start recording spans
+pub fn enable() {
+    ENABLED.store(true, Ordering::Relaxed);
+}
+
+fn is_enabled() -> bool {
+    ENABLED.load(Ordering::Relaxed)
+}
+
+/// Records the time from creation to drop under `name`
+pub struct Span {
+    name: &'static str,
+    start: Option<Instant>,
+}
+
+impl Drop for Span {
+    fn drop(&mut self) {
+        if let Some(start) = self.start
+            && let Ok(mut spans) = SPANS.lock()
+        {
+            spans.push((self.name.to_string(), start.elapsed()));
+        }
+    }
+}
+
+/// Time the enclosing scope, e.g. `let _span = profile::span("git: ls-remote");`
+pub fn span(name: &'static str) -> Span {
+    Span {
+        name,
+        start: is_enabled().then(Instant::now),
+    }
+}
+
+/// Per-name aggregate of recorded spans
+#[derive(Debug, Clone, PartialEq)]
+pub struct SpanSummary {
+    pub name: String,
+    pub count: usize,
+    pub total: Duration,
+    pub max: Duration,
+}
+
+/// Aggregate spans by name, slowest total first
+pub fn summarize(spans: &[(String, Duration)]) -> Vec<SpanSummary> {
+    let mut summaries: Vec<SpanSummary> = Vec::new();
+    for (name, duration) in spans {
+        match summaries.iter_mut().find(|s| &s.name == name) {
+            Some(summary) => {
+                summary.count += 1;
+                summary.total += *duration;
+                summary.max = summary.max.max(*duration);
+            }
+            None => summaries.push(SpanSummary {
+                name: name.clone(),
+                count: 1,
+                total: *duration,
+                max: *duration,
+            }),
+        }
+    }
+    summaries.sort_by_key(|s| std::cmp::Reverse(s.total));
+    summaries
+}
+
+/// Print the summary table of everything recorded so far (to stderr, so `--json` output stays clean)
+pub fn print_summary(wall_time: Duration) {
+    let spans = SPANS.lock().map(|s| s.clone()).unwrap_or_default();
+    let summaries = summarize(&spans);
+
+    eprintln!("\n{}", style("⏱️  Profile").bold());
+    eprintln!(
+        "   {:<32} {:>6} {:>10} {:>10}",
+        style("span").dim(),
+        style("calls").dim(),
+        style("total").dim(),
+        style("max").dim()
+    );
+    for summary in &summaries {
+        eprintln!(
+            "   {:<32} {:>6} {:>10} {:>10}",
+            summary.name,
+            summary.count,
+            format_duration(summary.total),
+            format_duration(summary.max)
+        );
+    }
+    eprintln!(
+        "   {:<32} {:>6} {:>10}",
+        style("wall time").bold(),
+        "",
+        format_duration(wall_time)
+    );
+}
+
+fn format_duration(duration: Duration) -> String {
+    format!("{:.0}ms", duration.as_secs_f64() * 1000.0)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_summarize() {
+        let ms = Duration::from_millis;
+        let spans = vec![
+            ("gh: get_pr".to_string(), ms(30)),
+            ("git: push".to_string(), ms(100)),
+            ("gh: get_pr".to_string(), ms(50)),
+        ];
+
+        let summaries = summarize(&spans);
+        assert_eq!(summaries.len(), 2);
+        assert_eq!(summaries[0].name, "git: push");
+        assert_eq!(summaries[1].count, 2);
+        assert_eq!(summaries[1].total, ms(80));
+        assert_eq!(summaries[1].max, ms(50));
+    }
+}
@@ -1,13 +1,27 @@
 //! Unified stack discovery with full remote and GitHub state hydration.
 
-use anyhow::{anyhow, Context, Result};
+use anyhow::{Context, Result, anyhow};
 use git2::{Oid, Repository};
-use octocrab::Octocrab;
 use std::collections::HashMap;
+use tracing::{debug, trace, warn};
 
-use crate::gh::{client, queries};
-use crate::git::{notes, refs, slots};
-use crate::model::{Config, Entry, PrState, Stack, UpdateStatus};
+use crate::gh::{
+    client::{self, Client},
+    errors, queries,
+};
+use crate::git::{change_id, history, jj, note_signature, notes, patch_id, refs, slots};
+use crate::model::{
+    CommitMetadata, Config, ContextCommit, Entry, OrphanedPr, PrStackMetadata, PrState,
+    RemoteRefsSource, Stack, UpdateStatus, VerifyNotes,
+};
+use crate::profile;
+use crate::ui::callout;
+
+/// Ref namespace for branches and notes fetched by `view --remote`
+const REMOTE_VIEW_PREFIX: &str = "refs/git-stk/remote-view";
+
+/// Ref namespace for slot branches fetched to match Change-Ids
+const CHANGE_ID_PREFIX: &str = "refs/git-stk/change-id";
 
 /// Discover the stack with full hydration from remote refs and GitHub PR state.
 ///
@@ -16,24 +30,140 @@ use crate::model::{Config, Entry, PrState, Stack, UpdateStatus};
 pub async fn discover_stack(
     git_repo: &Repository,
     config: &Config,
-    gh_client: &Octocrab,
+    gh_client: &Client,
+) -> Result<Stack> {
+    discover(git_repo, config, gh_client, None).await
+}
+
+/// Discover the stack again after rewriting its commits locally (restack,
+/// sign-offs, Change-Ids). Nothing was pushed since `previous` was discovered,
+/// so its remote branches and PR states are reused; only PRs it didn't know
+/// are fetched.
+pub async fn rediscover_stack(
+    git_repo: &Repository,
+    config: &Config,
+    gh_client: &Client,
+    previous: &Stack,
+) -> Result<Stack> {
+    discover(git_repo, config, gh_client, Some(previous)).await
+}
+
+async fn discover(
+    git_repo: &Repository,
+    config: &Config,
+    gh_client: &Client,
+    previous: Option<&Stack>,
 ) -> Result<Stack> {
+    let _span = profile::span("stack: discover");
     // Derive owner/repo from remote URL
     let (owner, repo_name) = get_repo_info(git_repo, config)?;
 
-    // Phase 1: Walk commits and load metadata from git notes
+    // Phase 1: Walk commits and load metadata from git notes, falling back to
+    // collaborators' per-user notes
+    if config.notes_per_user
+        && let Err(e) = notes::fetch_user_notes(
+            git_repo,
+            &config.remote,
+            &config.notes_ref,
+            &config.transport,
+        )
+    {
+        debug!(error = %e, "no per-user notes fetched");
+    }
     let mut stack = walk_commits(git_repo, config)?;
+    debug!(
+        branch = %stack.current_branch,
+        base = %config.base,
+        entries = stack.len(),
+        "walked stack commits"
+    );
 
     if stack.entries.is_empty() {
         return Ok(stack);
     }
 
-    // Phase 2: Fetch remote refs and PR states in parallel
+    // Phase 2: Fetch remote refs and PR states in parallel. The open PRs of
+    // slot branches no entry uses are asked for in the same query (phase 5).
     let pr_numbers: Vec<u64> = stack.entries.iter().filter_map(|e| e.pr_number).collect();
+    let slot_cache = slots::SlotCache::load(git_repo)?;
+    let unused_heads = unused_slot_heads(&stack, &slot_cache);
+
+    let RemoteState {
+        remote_refs,
+        remote_refs_source,
+        mut pr_states,
+        open_prs_by_head,
+        offline,
+    } = match previous {
+        Some(previous) => {
+            reuse_remote_state(previous, gh_client, &owner, &repo_name, &pr_numbers).await?
+        }
+        None => {
+            fetch_remote_and_pr_states(
+                git_repo,
+                config,
+                &stack.current_branch,
+                stack.len() == 1,
+                gh_client,
+                &owner,
+                &repo_name,
+                &pr_numbers,
+                &unused_heads,
+            )
+            .await?
+        }
+    };
+    debug!(
+        remote_refs = remote_refs.len(),
+        prs = pr_states.len(),
+        offline,
+        "fetched remote and PR state"
+    );
+    stack.remote_refs_source = remote_refs_source;
+    stack.offline = offline;
+
+    // Phase 2b: Re-match commits that lost their notes by Change-Id (or, with
+    // jj, by its change-id: jj rewrites don't carry notes along)
+    if (config.change_id || config.jj) && !offline {
+        recover_from_change_ids(
+            git_repo,
+            config,
+            gh_client,
+            &owner,
+            &repo_name,
+            &mut stack,
+            &remote_refs,
+            &mut pr_states,
+        )
+        .await?;
+    }
 
-    let (remote_refs, pr_states) =
-        fetch_remote_and_pr_states(git_repo, config, gh_client, &owner, &repo_name, &pr_numbers)
-            .await?;
+    // Phase 2c: No notes at all (fresh clone, lost notes ref) but the stack was
+    // exported before: rebuild metadata from the PR bodies
+    let slot_prefix = format!("{}--", slots::sanitize_branch_name(&stack.current_branch));
+    if !offline
+        && stack.entries.iter().all(|e| e.slot.is_none())
+        && remote_refs
+            .keys()
+            .any(|name| name.starts_with(&slot_prefix))
+    {
+        recover_from_pr_bodies(
+            git_repo,
+            gh_client,
+            &owner,
+            &repo_name,
+            &mut stack,
+            &remote_refs,
+            &mut pr_states,
+        )
+        .await?;
+        if stack.entries.iter().any(|e| e.slot.is_some()) {
+            warn!(
+                "no git-stk notes for {}: run 'git stk doctor' to rebuild and share them",
+                stack.current_branch
+            );
+        }
+    }
 
     // Phase 3: Hydrate entries with fetched data
     hydrate_entries(
@@ -45,12 +175,273 @@ pub async fn discover_stack(
         &pr_states,
     )?;
 
+    if let Some(previous) = previous {
+        for entry in &mut stack.entries {
+            if let Some(known) = previous
+                .entries
+                .iter()
+                .find(|e| e.pr_number.is_some() && e.pr_number == entry.pr_number)
+            {
+                copy_pr_state(known, entry);
+            }
+        }
+    }
+
     // Phase 4: Set up PR chain (base_ref for each entry)
     setup_pr_chain(&mut stack, config);
 
+    for entry in &stack.entries {
+        trace!(
+            index = entry.index,
+            sha = %entry.short_sha,
+            slot = ?entry.slot,
+            pr = ?entry.pr_number,
+            status = ?entry.status,
+            "hydrated entry"
+        );
+    }
+
+    stack.remote_refs = remote_refs;
+
+    // Phase 5: Open PRs left behind by entries dropped from the stack
+    stack.orphaned_prs = orphaned_slot_branches(git_repo, config, &stack, &slot_cache)?
+        .into_iter()
+        .filter_map(|(slot, head_ref, dropped_here)| {
+            let pr = open_prs_by_head.get(&head_ref)?;
+            Some(OrphanedPr {
+                slot,
+                head_ref,
+                pr_number: pr.number,
+                title: pr.title.clone(),
+                dropped_here,
+            })
+        })
+        .collect();
+
     Ok(stack)
 }
 
+/// Head refs of the slots this clone allocated for the current branch that no
+/// entry uses: the candidates of `orphaned_slot_branches`, before the remote
+/// branches are known
+fn unused_slot_heads(stack: &Stack, slot_cache: &slots::SlotCache) -> Vec<String> {
+    slot_cache
+        .used_slots(&stack.current_branch)
+        .iter()
+        .map(|slot| slots::generate_head_ref(&stack.current_branch, slot))
+        .filter(|head_ref| {
+            !stack
+                .entries
+                .iter()
+                .any(|e| e.head_ref.as_ref() == Some(head_ref))
+        })
+        .collect()
+}
+
+/// Slot branches of the current branch on the remote that no entry uses, as
+/// `(slot, head_ref, dropped_here)`. Only slots this clone allocated count,
+/// and only when the branch holds a known commit that left the branch and
+/// isn't owned by someone else: other slot branches may hold another clone's
+/// entries, or the slot of a context commit (`git-stk.onlyMine`).
+/// `dropped_here` tells that this clone exported that very commit to the slot
+/// (it's archived under `refs/git-stk/archive/`), so it's the one that dropped it.
+pub fn orphaned_slot_branches(
+    git_repo: &Repository,
+    config: &Config,
+    stack: &Stack,
+    slot_cache: &slots::SlotCache,
+) -> Result<Vec<(String, String, bool)>> {
+    let prefix = format!("{}--", slots::sanitize_branch_name(&stack.current_branch));
+    let tip = git_repo.head().ok().and_then(|head| head.target());
+    let user_email = crate::git::repo::user_email(git_repo);
+
+    let mut orphaned = Vec::new();
+    for (name, &oid) in &stack.remote_refs {
+        // Slots of a branch named e.g. "feat--x" share the prefix of "feat"
+        let Some(slot) = name
+            .strip_prefix(&prefix)
+            .filter(|s| !s.is_empty() && !s.contains("--"))
+        else {
+            continue;
+        };
+        if slot_cache.is_slot_available(&stack.current_branch, slot)
+            || stack
+                .entries
+                .iter()
+                .any(|e| e.head_ref.as_ref() == Some(name))
+        {
+            continue;
+        }
+        // Pushed from elsewhere since the last fetch, or still on the branch
+        let Ok(commit) = git_repo.find_commit(oid) else {
+            continue;
+        };
+        if tip.is_some_and(|tip| {
+            tip == oid || git_repo.graph_descendant_of(tip, oid).unwrap_or(false)
+        }) {
+            continue;
+        }
+        let owner = notes::read_note(git_repo, oid, &config.notes_ref)?
+            .and_then(|metadata| metadata.owner)
+            .or_else(|| commit.author().email().map(String::from));
+        if owner.is_some_and(|owner| {
+            !user_email
+                .as_deref()
+                .is_some_and(|me| me.eq_ignore_ascii_case(&owner))
+        }) {
+            continue;
+        }
+
+        let archive_prefix = slots::archive_ref(&stack.current_branch, slot, 0);
+        let archive_prefix = archive_prefix.trim_end_matches("v0");
+        let dropped_here = git_repo
+            .references_glob(&format!("{}*", archive_prefix))?
+            .flatten()
+            .any(|reference| reference.target() == Some(oid));
+        orphaned.push((slot.to_string(), name.clone(), dropped_here));
+    }
+    orphaned.sort();
+    Ok(orphaned)
+}
+
+/// Discover the stack of a branch that only exists on the remote (read-only).
+///
+/// The branch tip and the remote's notes are fetched into refs under
+/// `refs/git-stk/remote-view/` so local branches and notes are left untouched.
+pub async fn discover_remote_stack(
+    git_repo: &Repository,
+    config: &Config,
+    gh_client: &Client,
+    branch: &str,
+) -> Result<Stack> {
+    let (owner, repo_name) = get_repo_info(git_repo, config)?;
+
+    let tip_ref = format!("{}/heads/{}", REMOTE_VIEW_PREFIX, branch);
+    let notes_ref = format!("{}/notes", REMOTE_VIEW_PREFIX);
+    let mut refspecs = vec![format!("+refs/heads/{}:{}", branch, tip_ref)];
+    if let Some(base_branch) = &config.base_branch {
+        refspecs.push(format!(
+            "+refs/heads/{}:refs/remotes/{}/{}",
+            base_branch, config.remote, base_branch
+        ));
+    }
+
+    refs::fetch_refspecs(git_repo, &config.remote, &refspecs, &config.transport)
+        .with_context(|| format!("Failed to fetch branch '{}' from {}", branch, config.remote))?;
+
+    // A remote without git-stk notes just yields a stack without metadata
+    let _ = refs::fetch_refspecs(
+        git_repo,
+        &config.remote,
+        &[format!("+{}:{}", config.notes_ref, notes_ref)],
+        &config.transport,
+    );
+
+    debug!(branch, "fetched remote branch for read-only view");
+    let tip = git_repo.refname_to_id(&tip_ref)?;
+    let base = match &config.base_branch {
+        Some(base_branch) => {
+            git_repo.refname_to_id(&format!("refs/remotes/{}/{}", config.remote, base_branch))?
+        }
+        None => resolve_base_commit(git_repo, config)?.id(),
+    };
+    // Someone else's stack: their commits are the entries, whatever onlyMine and onlySince say
+    let mut stack = walk_range(
+        git_repo,
+        config,
+        tip,
+        base,
+        branch.to_string(),
+        &[notes_ref],
+        &EntryFilter::default(),
+    )?;
+
+    if stack.entries.is_empty() {
+        return Ok(stack);
+    }
+
+    let pr_numbers: Vec<u64> = stack.entries.iter().filter_map(|e| e.pr_number).collect();
+    let RemoteState {
+        remote_refs,
+        remote_refs_source,
+        pr_states,
+        offline,
+        ..
+    } = fetch_remote_and_pr_states(
+        git_repo,
+        config,
+        branch,
+        true,
+        gh_client,
+        &owner,
+        &repo_name,
+        &pr_numbers,
+        &[],
+    )
+    .await?;
+
+    hydrate_entries(
+        &mut stack,
+        git_repo,
+        &owner,
+        &repo_name,
+        &remote_refs,
+        &pr_states,
+    )?;
+    setup_pr_chain(&mut stack, config);
+    stack.remote_refs = remote_refs;
+    stack.remote_refs_source = remote_refs_source;
+    stack.offline = offline;
+
+    Ok(stack)
+}
+
+/// Refuse to go on with `command` when discovery couldn't reach GitHub or the
+/// remote: it acts on PR states and remote branches that are unknown
+pub fn ensure_online(stack: &Stack, config: &Config, command: &str) -> Result<()> {
+    if stack.offline {
+        return Err(anyhow!(
+            "Couldn't reach GitHub or {}, so the state of the stack's PRs is unknown. Check your connection and {} again.",
+            config.remote,
+            command
+        ));
+    }
+    Ok(())
+}
+
+/// Discover the stack from local commits and git notes only.
+///
+/// No remote or GitHub state is fetched, so this works offline. Use it for
+/// commands that only operate on local commits (e.g., `test`, `bisect`).
+pub fn discover_local_stack(git_repo: &Repository, config: &Config) -> Result<Stack> {
+    walk_commits(git_repo, config)
+}
+
+/// Discover the stack of a local branch other than HEAD's, from local commits
+/// and git notes only (e.g. the branch a detached HEAD was checked out from)
+pub fn discover_branch_stack(
+    git_repo: &Repository,
+    config: &Config,
+    branch: &str,
+) -> Result<Stack> {
+    let tip = git_repo
+        .find_branch(branch, git2::BranchType::Local)
+        .with_context(|| format!("Branch '{}' not found", branch))?
+        .get()
+        .peel_to_commit()?
+        .id();
+    let base = resolve_base_commit(git_repo, config)?.id();
+    walk_range(
+        git_repo,
+        config,
+        tip,
+        base,
+        branch.to_string(),
+        &local_notes_refs(git_repo, config)?,
+        &entry_filter(git_repo, config)?,
+    )
+}
+
 // =============================================================================
 // Private helper functions
 // =============================================================================
@@ -66,51 +457,182 @@ fn get_repo_info(git_repo: &Repository, config: &Config) -> Result<(String, Stri
 
 /// Walk commits from HEAD to base and load metadata from git notes
 fn walk_commits(repo: &Repository, config: &Config) -> Result<Stack> {
+    let _span = profile::span("stack: walk commits");
     let head = repo.head().context("Failed to get HEAD")?;
-    let current_branch = head
-        .shorthand()
-        .context("Failed to get current branch name")?
-        .to_string();
-
-    let base_ref_name = format!("refs/heads/{}", config.base);
-    let base_commit = repo
-        .find_reference(&base_ref_name)
-        .with_context(|| format!("Failed to find base branch: {}", config.base))?
+    let current_branch = match jj::bookmark_at_head(repo)? {
+        Some(bookmark) => bookmark,
+        None => head
+            .shorthand()
+            .context("Failed to get current branch name")?
+            .to_string(),
+    };
+
+    let base_commit = resolve_base_commit(repo, config)?;
+
+    let head_commit = head.peel_to_commit().context("Failed to resolve HEAD")?;
+
+    let notes_refs = local_notes_refs(repo, config)?;
+    walk_range(
+        repo,
+        config,
+        head_commit.id(),
+        base_commit.id(),
+        current_branch,
+        &notes_refs,
+        &entry_filter(repo, config)?,
+    )
+}
+
+/// Which commits of a shared branch are entries; the others are context commits
+#[derive(Debug, Default)]
+struct EntryFilter {
+    /// Author email of the entries (`git-stk.onlyMine`)
+    author: Option<String>,
+    /// Earliest commit time of the entries, in seconds (`git-stk.onlySince`)
+    since: Option<i64>,
+}
+
+impl EntryFilter {
+    fn is_entry(&self, commit: &git2::Commit) -> bool {
+        let author = commit.author();
+        self.author.as_deref().is_none_or(|email| {
+            author
+                .email()
+                .is_some_and(|e| e.eq_ignore_ascii_case(email))
+        }) && self
+            .since
+            .is_none_or(|since| commit.time().seconds() >= since)
+    }
+}
+
+/// The entry filter of `git-stk.onlyMine` and `git-stk.onlySince`
+fn entry_filter(repo: &Repository, config: &Config) -> Result<EntryFilter> {
+    let author = if config.only_mine {
+        Some(crate::git::repo::user_email(repo).context(
+            "git-stk.onlyMine is set but user.email isn't: set it to the email you commit with",
+        )?)
+    } else {
+        None
+    };
+    let since = match &config.only_since {
+        Some(date) => Some(
+            crate::git::repo::parse_date(repo, date)
+                .with_context(|| format!("Invalid git-stk.onlySince '{}'", date))?,
+        ),
+        None => None,
+    };
+    Ok(EntryFilter { author, since })
+}
+
+/// The notes refs stack metadata is read from
+fn local_notes_refs(repo: &Repository, config: &Config) -> Result<Vec<String>> {
+    let mut notes_refs = vec![config.notes_ref.clone()];
+    if config.notes_per_user {
+        notes_refs.extend(notes::user_notes_refs(repo)?);
+    }
+    Ok(notes_refs)
+}
+
+/// Resolve the base to a commit. A plain branch name falls back to the
+/// remote-tracking branch in clones that never checked it out locally; with
+/// `git-stk.createBaseBranch`, the missing local branch is created from it.
+pub fn resolve_base_commit<'r>(repo: &'r Repository, config: &Config) -> Result<git2::Commit<'r>> {
+    // Full refs, `<remote>/<branch>`, tags, and commit IDs resolve as revisions
+    if config.base_branch.as_deref() != Some(config.base.as_str()) {
+        return repo
+            .revparse_single(&config.base)
+            .and_then(|object| object.peel_to_commit())
+            .with_context(|| format!("Failed to resolve base '{}' to a commit", config.base));
+    }
+
+    if let Ok(branch) = repo.find_branch(&config.base, git2::BranchType::Local) {
+        return branch
+            .get()
+            .peel_to_commit()
+            .context("Failed to resolve base branch to commit");
+    }
+
+    let remote_base = format!("{}/{}", config.remote, config.base);
+    let commit = repo
+        .find_branch(&remote_base, git2::BranchType::Remote)
+        .with_context(|| {
+            format!(
+                "Failed to find base branch: {} (nor {}). Run 'git fetch {} {}' first.",
+                config.base, remote_base, config.remote, config.base
+            )
+        })?
+        .get()
         .peel_to_commit()
         .context("Failed to resolve base branch to commit")?;
+    debug!(base = %remote_base, "local base branch missing, using remote-tracking branch");
 
-    let head_commit = head.peel_to_commit().context("Failed to resolve HEAD")?;
+    if config.create_base_branch {
+        let mut branch = repo
+            .branch(&config.base, &commit, false)
+            .with_context(|| format!("Failed to create local branch '{}'", config.base))?;
+        branch
+            .set_upstream(Some(&remote_base))
+            .with_context(|| format!("Failed to set upstream of '{}'", config.base))?;
+        debug!(base = %config.base, "created local base branch");
+    }
 
+    Ok(commit)
+}
+
+/// Walk commits from `tip` down to `base` and load metadata from `notes_refs`.
+/// Commits `filter` rejects become context commits.
+fn walk_range(
+    repo: &Repository,
+    config: &Config,
+    tip: Oid,
+    base: Oid,
+    current_branch: String,
+    notes_refs: &[String],
+    filter: &EntryFilter,
+) -> Result<Stack> {
     // Empty stack if on base branch
-    if head_commit.id() == base_commit.id() {
+    if tip == base {
         return Ok(Stack::new(config.base.clone(), current_branch));
     }
 
+    // A truncated history would end the walk at the shallow boundary instead of the base
+    let deepened = history::ensure_history(repo, &config.remote, tip, base, &config.transport)?;
+    let repo = deepened.as_ref().unwrap_or(repo);
+    history::prefetch_blobs(repo, tip, base, &config.transport)?;
+
     // Walk commits
     let mut revwalk = repo.revwalk().context("Failed to create revwalk")?;
-    revwalk.push(head_commit.id())?;
-    revwalk.hide(base_commit.id())?;
+    revwalk.push(tip)?;
+    revwalk.hide(base)?;
     revwalk.set_sorting(git2::Sort::TOPOLOGICAL | git2::Sort::REVERSE)?;
 
     let mut stack = Stack::new(config.base.clone(), current_branch.clone());
 
-    for (index, oid_result) in revwalk.enumerate() {
+    for oid_result in revwalk {
         let oid = oid_result.context("Failed to walk commit")?;
         let commit = repo.find_commit(oid).context("Failed to find commit")?;
 
         if commit.parent_count() > 1 {
-            return Err(anyhow!(
-                "Merge commits are not supported. Found: {}",
-                oid
-            ));
+            return Err(anyhow!("Merge commits are not supported. Found: {}", oid));
+        }
+
+        if !filter.is_entry(&commit) {
+            stack.context.push(ContextCommit {
+                below: stack.len(),
+                oid,
+                short_sha: format!("{:.7}", oid),
+                subject: commit.summary().unwrap_or("<no subject>").to_string(),
+                author_name: commit.author().name().unwrap_or("").to_string(),
+            });
+            continue;
         }
 
-        let entry = create_entry_from_commit(index, &commit, config);
+        let entry = create_entry_from_commit(stack.len(), &commit, config);
         stack.add_entry(entry);
     }
 
     // Load metadata from git notes
-    load_metadata_from_notes(repo, &mut stack, config)?;
+    load_metadata_from_notes(repo, &mut stack, notes_refs, config.verify_notes)?;
 
     Ok(stack)
 }
@@ -127,7 +649,7 @@ fn create_entry_from_commit(index: usize, commit: &git2::Commit, config: &Config
         pr_state: None,
         status: UpdateStatus::CreatePr,
         base_ref: if index == 0 {
-            config.base.clone()
+            config.base_name().to_string()
         } else {
             "unknown".to_string()
         },
@@ -136,23 +658,50 @@ fn create_entry_from_commit(index: usize, commit: &git2::Commit, config: &Config
         predicted_slot: None,
         remote_branch_exists: false,
         merged_into_main: false,
+        has_conflicts: false,
+        behind_base: false,
+        unresolved_threads: 0,
+        review_decision: None,
+        ci_status: None,
+        pr_base_ref: None,
         repo_owner: None,
         repo_name: None,
+        forge: config.forge,
+        author_name: commit.author().name().unwrap_or("").to_string(),
+        commit_time: commit.time().seconds(),
+        annotation: None,
+        last_export: None,
+        // Until a note records otherwise, the slot belongs to the commit's author
+        owner: commit.author().email().map(String::from),
     }
 }
 
-/// Load PR numbers and slots from git notes into stack entries
+/// Load PR numbers and slots from git notes into stack entries.
+/// The first of `notes_refs` holding a note for a commit wins.
 fn load_metadata_from_notes(
     repo: &Repository,
     stack: &mut Stack,
-    config: &Config,
+    notes_refs: &[String],
+    verify: VerifyNotes,
 ) -> Result<()> {
     let current_branch = stack.current_branch.clone();
 
     for entry in &mut stack.entries {
-        if let Some(metadata) = notes::read_note(repo, entry.oid, &config.notes_ref)? {
+        let mut found = None;
+        for notes_ref in notes_refs {
+            found = notes::read_note(repo, entry.oid, notes_ref)?;
+            if found.is_some() {
+                break;
+            }
+        }
+
+        if let Some(metadata) = found.filter(|metadata| note_trusted(repo, entry, metadata, verify))
+        {
             entry.pr_number = metadata.pr;
             entry.slot = Some(metadata.slot.clone());
+            entry.annotation = metadata.annotation.clone();
+            entry.last_export = metadata.last_export.clone();
+            entry.owner = metadata.owner.clone().or(entry.owner.take());
             entry.head_ref = Some(slots::generate_head_ref(&current_branch, &metadata.slot));
         }
     }
@@ -160,47 +709,425 @@ fn load_metadata_from_notes(
     Ok(())
 }
 
-/// Fetch remote refs and PR states in parallel
+/// Whether a note may be used under `git-stk.verifyNotes`; untrusted notes are
+/// reported, and with `reject` ignored so the entry is recovered like a commit
+/// without a note
+fn note_trusted(
+    repo: &Repository,
+    entry: &Entry,
+    metadata: &CommitMetadata,
+    verify: VerifyNotes,
+) -> bool {
+    if verify == VerifyNotes::Off {
+        return true;
+    }
+    let problem = match note_signature::check(repo, entry.oid, metadata) {
+        note_signature::NoteTrust::Valid { signer } => {
+            trace!(oid = %entry.oid, %signer, "note signature verified");
+            return true;
+        }
+        note_signature::NoteTrust::Unsigned => "is not signed".to_string(),
+        note_signature::NoteTrust::Invalid(reason) => {
+            format!("has an untrusted signature: {}", reason)
+        }
+    };
+    let action = if verify == VerifyNotes::Reject {
+        "ignoring it"
+    } else {
+        "using it anyway"
+    };
+    warn!(
+        "note on {} ({}) {}; {}",
+        entry.short_sha, entry.subject, problem, action
+    );
+    verify == VerifyNotes::Warn
+}
+
+/// Restore the slot and PR of commits without notes by matching their
+/// `Change-Id` against the tips of this branch's remote slot branches.
+///
+/// Only the in-memory stack is updated; export persists the notes.
+#[allow(clippy::too_many_arguments)]
+async fn recover_from_change_ids(
+    git_repo: &Repository,
+    config: &Config,
+    gh_client: &Client,
+    owner: &str,
+    repo_name: &str,
+    stack: &mut Stack,
+    remote_refs: &HashMap<String, Oid>,
+    pr_states: &mut HashMap<u64, queries::PrInfo>,
+) -> Result<()> {
+    let commit_change_id = |oid: Oid| {
+        let commit = git_repo.find_commit(oid).ok()?;
+        jj::change_id(&commit).or_else(|| commit.message().and_then(change_id::change_id))
+    };
+
+    let missing: Vec<(usize, String)> = stack
+        .entries
+        .iter()
+        .enumerate()
+        .filter(|(_, entry)| entry.slot.is_none())
+        .filter_map(|(index, entry)| commit_change_id(entry.oid).map(|id| (index, id)))
+        .collect();
+    if missing.is_empty() {
+        return Ok(());
+    }
+
+    let prefix = format!("{}--", slots::sanitize_branch_name(&stack.current_branch));
+    let candidates: Vec<(&str, Oid)> = remote_refs
+        .iter()
+        .filter(|(name, _)| name.starts_with(&prefix))
+        .map(|(name, &oid)| (name.as_str(), oid))
+        .collect();
+
+    // Slot branches pushed from another clone may not be available locally yet
+    let unknown: Vec<String> = candidates
+        .iter()
+        .filter(|(_, oid)| git_repo.find_commit(*oid).is_err())
+        .map(|(name, _)| format!("+refs/heads/{}:{}/{}", name, CHANGE_ID_PREFIX, name))
+        .collect();
+    if !unknown.is_empty() {
+        refs::fetch_refspecs(git_repo, &config.remote, &unknown, &config.transport)?;
+    }
+
+    let slots_by_change_id: HashMap<String, &str> = candidates
+        .iter()
+        .filter_map(|&(name, oid)| commit_change_id(oid).map(|id| (id, &name[prefix.len()..])))
+        .collect();
+
+    for (index, id) in missing {
+        let Some(&slot) = slots_by_change_id.get(&id) else {
+            continue;
+        };
+
+        let head_ref = slots::generate_head_ref(&stack.current_branch, slot);
+        let pr = queries::find_pr_by_head(gh_client, owner, repo_name, &head_ref).await?;
+        debug!(change_id = %id, slot, pr = ?pr.as_ref().map(|p| p.number), "recovered metadata by Change-Id");
+
+        let entry = &mut stack.entries[index];
+        entry.slot = Some(slot.to_string());
+        entry.head_ref = Some(head_ref);
+        if let Some(pr) = pr {
+            entry.pr_number = Some(pr.number);
+            pr_states.insert(pr.number, pr);
+        }
+    }
+
+    Ok(())
+}
+
+/// Restore the slot and PR of commits from the metadata block embedded in open
+/// PR bodies. A PR matches a commit when its slot branch points at the commit
+/// (or an equivalent one), or else when stack size and position agree.
+///
+/// Only the in-memory stack is updated; export persists the notes.
+async fn recover_from_pr_bodies(
+    git_repo: &Repository,
+    gh_client: &Client,
+    owner: &str,
+    repo_name: &str,
+    stack: &mut Stack,
+    remote_refs: &HashMap<String, Oid>,
+    pr_states: &mut HashMap<u64, queries::PrInfo>,
+) -> Result<()> {
+    let current_branch = stack.current_branch.clone();
+    let mut candidates: Vec<(queries::PrInfo, PrStackMetadata)> =
+        queries::list_open_prs(gh_client, owner, repo_name)
+            .await?
+            .into_iter()
+            .filter_map(|pr| {
+                let metadata = callout::parse_metadata_block(&pr.body)?;
+                (metadata.branch == current_branch).then_some((pr, metadata))
+            })
+            .collect();
+    debug!(
+        candidates = candidates.len(),
+        "recovering metadata from PR bodies"
+    );
+
+    let total = stack.len();
+    for entry in &mut stack.entries {
+        let matches_commit = |metadata: &PrStackMetadata| {
+            let head_ref = slots::generate_head_ref(&current_branch, &metadata.slot);
+            remote_refs.get(&head_ref).is_some_and(|&tip| {
+                tip == entry.oid
+                    || patch_id::commits_equivalent(git_repo, tip, entry.oid).unwrap_or(false)
+            })
+        };
+        let position = candidates
+            .iter()
+            .position(|(_, metadata)| matches_commit(metadata))
+            .or_else(|| {
+                candidates
+                    .iter()
+                    .position(|(_, m)| m.total == total && m.position == entry.index)
+            });
+        let Some(position) = position else {
+            continue;
+        };
+
+        let (pr, metadata) = candidates.swap_remove(position);
+        debug!(sha = %entry.short_sha, slot = %metadata.slot, pr = pr.number, "recovered metadata from PR body");
+        entry.head_ref = Some(slots::generate_head_ref(&current_branch, &metadata.slot));
+        entry.slot = Some(metadata.slot);
+        entry.pr_number = Some(pr.number);
+        pr_states.insert(pr.number, pr);
+    }
+
+    Ok(())
+}
+
+/// Restore the slot and PR of commits without metadata from the open PRs on
+/// this branch's slot branches (`<branch>--<slot>`) in `stack.remote_refs`. A
+/// PR matches a commit when its slot branch holds the commit or an equivalent one. Unlike
+/// `recover_from_pr_bodies`, this needs no metadata block, e.g. for PRs
+/// exported before there was one.
+///
+/// Only the in-memory stack is updated. Returns how many entries were recovered.
+pub async fn recover_from_slot_heads(
+    git_repo: &Repository,
+    config: &Config,
+    gh_client: &Client,
+    stack: &mut Stack,
+) -> Result<usize> {
+    if stack.entries.iter().all(|e| e.slot.is_some()) {
+        return Ok(0);
+    }
+    let (owner, repo_name) = get_repo_info(git_repo, config)?;
+    let prefix = format!("{}--", slots::sanitize_branch_name(&stack.current_branch));
+    let mut heads: Vec<String> = stack
+        .remote_refs
+        .keys()
+        .filter(|head| {
+            head.starts_with(&prefix)
+                && !stack
+                    .entries
+                    .iter()
+                    .any(|e| e.head_ref.as_ref() == Some(*head))
+        })
+        .cloned()
+        .collect();
+    if heads.is_empty() {
+        return Ok(0);
+    }
+    heads.sort();
+
+    // The open PRs of the slot branches on the remote, looked up by head like discovery does
+    let taken: Vec<u64> = stack.entries.iter().filter_map(|e| e.pr_number).collect();
+    let mut open_prs = queries::get_stack_state(gh_client, &owner, &repo_name, &[], &heads)
+        .await?
+        .open_prs_by_head;
+    let mut candidates: Vec<(String, queries::OpenPr, Oid)> = heads
+        .into_iter()
+        .filter_map(|head| {
+            let pr = open_prs
+                .remove(&head)
+                .filter(|pr| !taken.contains(&pr.number))?;
+            let tip = stack.remote_refs[&head];
+            Some((head, pr, tip))
+        })
+        .collect();
+    debug!(
+        candidates = candidates.len(),
+        "recovering metadata from slot branches"
+    );
+
+    // Slot branches pushed from another clone may not be available locally yet
+    let unknown: Vec<String> = candidates
+        .iter()
+        .filter(|(_, _, oid)| git_repo.find_commit(*oid).is_err())
+        .map(|(head, _, _)| format!("+refs/heads/{}:{}/{}", head, CHANGE_ID_PREFIX, head))
+        .collect();
+    if !unknown.is_empty() {
+        refs::fetch_refspecs(git_repo, &config.remote, &unknown, &config.transport)?;
+    }
+
+    let mut recovered = 0;
+    for entry in stack.entries.iter_mut().filter(|e| e.slot.is_none()) {
+        let Some(position) = candidates.iter().position(|&(_, _, tip)| {
+            tip == entry.oid
+                || patch_id::commits_equivalent(git_repo, tip, entry.oid).unwrap_or(false)
+        }) else {
+            continue;
+        };
+
+        let (head, pr, _) = candidates.swap_remove(position);
+        let slot = head[prefix.len()..].to_string();
+        debug!(sha = %entry.short_sha, slot = %slot, pr = pr.number, "recovered metadata from slot branch");
+        entry.head_ref = Some(head);
+        entry.slot = Some(slot);
+        entry.pr_number = Some(pr.number);
+        entry.pr_state = Some(PrState::Open);
+        recovered += 1;
+    }
+
+    Ok(recovered)
+}
+
+/// Remote branches and PR states found by `fetch_remote_and_pr_states`
+struct RemoteState {
+    remote_refs: HashMap<String, Oid>,
+    remote_refs_source: RemoteRefsSource,
+    pr_states: HashMap<u64, queries::PrInfo>,
+    /// Open PRs of the head refs asked for
+    open_prs_by_head: HashMap<String, queries::OpenPr>,
+    /// The remote or GitHub couldn't be reached
+    offline: bool,
+}
+
+/// Fetch remote refs and PR states in parallel. PR states, and the open PRs
+/// of `head_refs`, come from a single GraphQL query.
+///
+/// Remote refs are listed on the remote unless fetching is turned off
+/// (`git-stk.fetch`, `--no-fetch`), in which case the remote-tracking branches
+/// are used. Without connectivity, discovery degrades instead of failing: an
+/// unreachable remote falls back to the remote-tracking branches, and an
+/// unreachable GitHub API to no PR states.
+#[allow(clippy::too_many_arguments)]
 async fn fetch_remote_and_pr_states(
     git_repo: &Repository,
     config: &Config,
-    gh_client: &Octocrab,
+    current_branch: &str,
+    own_slots_only: bool,
+    gh_client: &Client,
     owner: &str,
     repo_name: &str,
     pr_numbers: &[u64],
-) -> Result<(HashMap<String, Oid>, HashMap<u64, queries::PrInfo>)> {
+    head_refs: &[String],
+) -> Result<RemoteState> {
+    let fetch = config.fetch;
     let remote_name = config.remote.clone();
+    let transport = config.transport.clone();
     let git_repo_path = git_repo.path().to_path_buf();
 
+    // Single-commit fast path (and remote views): only this branch's slot refs
+    // can matter, so avoid listing every head on the remote
+    let patterns = if own_slots_only {
+        vec![format!(
+            "refs/heads/{}--*",
+            slots::sanitize_branch_name(current_branch)
+        )]
+    } else {
+        Vec::new()
+    };
+
     // Spawn remote refs fetch as blocking task
     let remote_refs_task = tokio::task::spawn_blocking(move || {
+        if !fetch {
+            return Ok(None);
+        }
         let repo = Repository::open(&git_repo_path)?;
-        refs::get_all_remote_refs(&repo, &remote_name)
+        refs::get_remote_refs_matching(&repo, &remote_name, &patterns, &transport).map(Some)
     });
 
     // Fetch PR states from GitHub
-    let owner_clone = owner.to_string();
-    let repo_name_clone = repo_name.to_string();
-    let pr_numbers_clone = pr_numbers.to_vec();
+    let stack_state_task =
+        queries::get_stack_state(gh_client, owner, repo_name, pr_numbers, head_refs);
 
-    let pr_states_task = async move {
-        if pr_numbers_clone.is_empty() {
-            Ok(HashMap::new())
-        } else {
-            queries::get_prs_batch(gh_client, &owner_clone, &repo_name_clone, &pr_numbers_clone)
-                .await
+    let (remote_refs_result, stack_state_result) = tokio::join!(remote_refs_task, stack_state_task);
+
+    let mut offline = false;
+    let remote_refs = match remote_refs_result.context("Remote refs task panicked")? {
+        Ok(remote_refs) => remote_refs,
+        Err(e) if refs::is_unreachable(&e) => {
+            debug!(error = %e, "remote unreachable, using remote-tracking branches");
+            offline = true;
+            None
         }
+        Err(e) => return Err(e),
+    };
+    let (remote_refs, remote_refs_source) = match remote_refs {
+        Some(remote_refs) => (remote_refs, RemoteRefsSource::Remote),
+        None => (
+            refs::get_tracking_refs(git_repo, &config.remote)?,
+            RemoteRefsSource::Tracking {
+                fetched_at: refs::last_fetch_time(git_repo),
+            },
+        ),
     };
 
-    let (remote_refs_result, pr_states_result) = tokio::join!(remote_refs_task, pr_states_task);
+    let stack_state = match stack_state_result {
+        Ok(stack_state) => stack_state,
+        Err(e) if errors::is_unreachable(&e) => {
+            debug!(error = %e, "GitHub unreachable, PR states unknown");
+            offline = true;
+            queries::StackState::default()
+        }
+        Err(e) => return Err(e),
+    };
 
-    let remote_refs = remote_refs_result
-        .context("Remote refs task panicked")?
-        .context("Failed to fetch remote refs")?;
+    Ok(RemoteState {
+        remote_refs,
+        remote_refs_source,
+        pr_states: stack_state.prs,
+        open_prs_by_head: stack_state.open_prs_by_head,
+        offline,
+    })
+}
 
-    let pr_states = pr_states_result.context("Failed to fetch PR states from GitHub")?;
+/// The remote state of a stack discovered earlier: its remote branches and the
+/// open PRs of its unused slots, plus the states of PRs it didn't know. Those
+/// it knew are copied over from its entries (`copy_pr_state`).
+async fn reuse_remote_state(
+    previous: &Stack,
+    gh_client: &Client,
+    owner: &str,
+    repo_name: &str,
+    pr_numbers: &[u64],
+) -> Result<RemoteState> {
+    let unknown: Vec<u64> = pr_numbers
+        .iter()
+        .copied()
+        .filter(|&number| {
+            !previous
+                .entries
+                .iter()
+                .any(|e| e.pr_number == Some(number) && e.pr_base_ref.is_some())
+        })
+        .collect();
+    let pr_states = if unknown.is_empty() || previous.offline {
+        HashMap::new()
+    } else {
+        queries::get_stack_state(gh_client, owner, repo_name, &unknown, &[])
+            .await?
+            .prs
+    };
 
-    Ok((remote_refs, pr_states))
+    Ok(RemoteState {
+        remote_refs: previous.remote_refs.clone(),
+        remote_refs_source: previous.remote_refs_source,
+        pr_states,
+        open_prs_by_head: previous
+            .orphaned_prs
+            .iter()
+            .map(|pr| {
+                (
+                    pr.head_ref.clone(),
+                    queries::OpenPr {
+                        number: pr.pr_number,
+                        title: pr.title.clone(),
+                    },
+                )
+            })
+            .collect(),
+        offline: previous.offline,
+    })
+}
+
+/// Carry the PR state `hydrate_pr_state` set on an entry over to its rewrite
+fn copy_pr_state(from: &Entry, to: &mut Entry) {
+    if to.pr_base_ref.is_some() || from.pr_base_ref.is_none() {
+        return;
+    }
+    to.pr_state = from.pr_state.clone();
+    to.has_conflicts = from.has_conflicts;
+    to.behind_base = from.behind_base;
+    to.unresolved_threads = from.unresolved_threads;
+    to.review_decision = from.review_decision.clone();
+    to.ci_status = from.ci_status;
+    to.pr_base_ref = from.pr_base_ref.clone();
+    to.merged_into_main |= from.pr_state == Some(PrState::Merged);
 }
 
 /// Hydrate stack entries with remote and PR state information
@@ -228,6 +1155,20 @@ fn hydrate_entries(
     Ok(())
 }
 
+/// Whether a remote branch exists for `slot` that doesn't point at `oid`.
+/// Such slots were allocated elsewhere (e.g., another clone with its own slot cache).
+pub fn is_slot_taken_remotely(
+    remote_refs: &HashMap<String, Oid>,
+    current_branch: &str,
+    slot: &str,
+    oid: Oid,
+) -> bool {
+    let head_ref = slots::generate_head_ref(current_branch, slot);
+    remote_refs
+        .get(&head_ref)
+        .is_some_and(|&remote_oid| remote_oid != oid)
+}
+
 /// Update entry with remote branch status
 fn hydrate_remote_status(
     entry: &mut Entry,
@@ -249,8 +1190,11 @@ fn hydrate_remote_status(
             entry.status = UpdateStatus::CreatePr;
         }
     } else {
-        // No metadata - predict slot
-        entry.predicted_slot = Some(slot_cache.allocate(current_branch));
+        // No metadata - predict slot, skipping slots already used on the remote
+        let (slot, _) = slot_cache.allocate_skipping(current_branch, |slot| {
+            is_slot_taken_remotely(remote_refs, current_branch, slot, entry.oid)
+        });
+        entry.predicted_slot = Some(slot);
         entry.status = UpdateStatus::CreatePr;
         entry.remote_branch_exists = false;
     }
@@ -258,13 +1202,19 @@ fn hydrate_remote_status(
 
 /// Update entry with PR state from GitHub
 fn hydrate_pr_state(entry: &mut Entry, pr_states: &HashMap<u64, queries::PrInfo>) {
-    if let Some(pr_number) = entry.pr_number {
-        if let Some(pr_info) = pr_states.get(&pr_number) {
-            entry.pr_state = Some(pr_info.state.clone());
+    if let Some(pr_number) = entry.pr_number
+        && let Some(pr_info) = pr_states.get(&pr_number)
+    {
+        entry.pr_state = Some(pr_info.state.clone());
+        entry.has_conflicts = pr_info.has_conflicts;
+        entry.behind_base = pr_info.behind_base;
+        entry.unresolved_threads = pr_info.unresolved_threads;
+        entry.review_decision = pr_info.review_decision.clone();
+        entry.ci_status = pr_info.ci_status;
+        entry.pr_base_ref = Some(pr_info.base_ref.clone());
 
-            if pr_info.state == PrState::Merged {
-                entry.merged_into_main = true;
-            }
+        if pr_info.state == PrState::Merged {
+            entry.merged_into_main = true;
         }
     }
 }
@@ -273,6 +1223,270 @@ fn hydrate_pr_state(entry: &mut Entry, pr_states: &HashMap<u64, queries::PrInfo>
 fn setup_pr_chain(stack: &mut Stack, config: &Config) {
     for i in 1..stack.entries.len() {
         let prev_head_ref = stack.entries[i - 1].head_ref.clone();
-        stack.entries[i].base_ref = prev_head_ref.unwrap_or_else(|| config.base.clone());
+        stack.entries[i].base_ref = prev_head_ref.unwrap_or_else(|| config.base_name().to_string());
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_orphaned_slot_branches() {
+        let dir = tempfile::tempdir().unwrap();
+        let repo = Repository::init(dir.path()).unwrap();
+        repo.config()
+            .unwrap()
+            .set_str("user.email", "me@example.com")
+            .unwrap();
+        let config = Config::load(&repo).unwrap();
+        let tree = repo
+            .find_tree(repo.treebuilder(None).unwrap().write().unwrap())
+            .unwrap();
+        let commit = |email: &str, message: &str, parents: &[&git2::Commit]| {
+            let signature = git2::Signature::now("Test", email).unwrap();
+            repo.find_commit(
+                repo.commit(None, &signature, &signature, message, &tree, parents)
+                    .unwrap(),
+            )
+            .unwrap()
+        };
+        let base = commit("them@example.com", "context", &[]);
+        let entry = commit("me@example.com", "entry", &[&base]);
+        let dropped = commit("me@example.com", "dropped", &[&base]);
+        let dropped_elsewhere = commit("me@example.com", "dropped elsewhere", &[&base]);
+        let theirs = commit("them@example.com", "theirs", &[&base]);
+        repo.reference("refs/heads/feat", entry.id(), true, "test")
+            .unwrap();
+        repo.set_head("refs/heads/feat").unwrap();
+        repo.reference(
+            &slots::archive_ref("feat", "01", 1),
+            dropped.id(),
+            true,
+            "test",
+        )
+        .unwrap();
+
+        let mut stack = Stack::new("main".to_string(), "feat".to_string());
+        let unknown = Oid::from_str("1111111111111111111111111111111111111111").unwrap();
+        for (name, oid) in [
+            ("feat--01", dropped.id()),
+            ("feat--02", entry.id()),
+            ("feat--03", dropped_elsewhere.id()),
+            ("feat--04", dropped.id()),
+            ("feat--05", theirs.id()),
+            ("feat--06", base.id()),
+            ("feat--07", unknown),
+            ("feat--x--01", dropped.id()),
+            ("main", dropped.id()),
+            ("feature--01", dropped.id()),
+        ] {
+            stack.remote_refs.insert(name.to_string(), oid);
+        }
+        let mut stack_entry = create_entry_from_commit(0, &entry, &config);
+        stack_entry.head_ref = Some("feat--02".to_string());
+        stack.add_entry(stack_entry);
+
+        // Slot 04 was never allocated here: it may be another clone's entry
+        let mut slot_cache = slots::SlotCache::default();
+        for slot in ["01", "02", "03", "05", "06", "07"] {
+            slot_cache.mark_slot_used("feat", slot);
+        }
+
+        // 05 holds someone else's commit, 06 a commit still on the branch, and
+        // 07 one pushed from elsewhere since the last fetch
+        assert_eq!(
+            orphaned_slot_branches(&repo, &config, &stack, &slot_cache).unwrap(),
+            vec![
+                ("01".to_string(), "feat--01".to_string(), true),
+                ("03".to_string(), "feat--03".to_string(), false),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_resolve_base_commit_falls_back_to_remote() {
+        let dir = tempfile::tempdir().unwrap();
+        let repo = Repository::init(dir.path()).unwrap();
+        let signature = git2::Signature::now("Test", "test@example.com").unwrap();
+        let tree = repo
+            .find_tree(repo.index().unwrap().write_tree().unwrap())
+            .unwrap();
+        let oid = repo
+            .commit(None, &signature, &signature, "base", &tree, &[])
+            .unwrap();
+        repo.remote("origin", "https://github.com/owner/repo.git")
+            .unwrap();
+        repo.reference("refs/remotes/origin/main", oid, false, "fetch")
+            .unwrap();
+
+        let mut config = Config::load(&repo).unwrap();
+        config.base = "main".to_string();
+        config.base_branch = Some("main".to_string());
+        config.remote = "origin".to_string();
+
+        assert_eq!(resolve_base_commit(&repo, &config).unwrap().id(), oid);
+        assert!(repo.find_branch("main", git2::BranchType::Local).is_err());
+
+        config.create_base_branch = true;
+        resolve_base_commit(&repo, &config).unwrap();
+        let branch = repo.find_branch("main", git2::BranchType::Local).unwrap();
+        assert_eq!(branch.get().target(), Some(oid));
+        assert_eq!(
+            branch.upstream().unwrap().name().unwrap(),
+            Some("origin/main")
+        );
+    }
+
+    #[test]
+    fn test_walk_range_only_author() {
+        let dir = tempfile::tempdir().unwrap();
+        let repo = Repository::init(dir.path()).unwrap();
+        let config = Config::load(&repo).unwrap();
+        let tree = repo
+            .find_tree(repo.treebuilder(None).unwrap().write().unwrap())
+            .unwrap();
+        let me = git2::Signature::now("Me", "me@example.com").unwrap();
+        let mut parent = repo
+            .find_commit(repo.commit(None, &me, &me, "base", &tree, &[]).unwrap())
+            .unwrap();
+        let base = parent.id();
+        for (subject, email) in [
+            ("mine 1", "me@example.com"),
+            ("theirs", "them@example.com"),
+            ("mine 2", "ME@example.com"),
+            ("theirs on top", "them@example.com"),
+        ] {
+            let signature = git2::Signature::now("Someone", email).unwrap();
+            let oid = repo
+                .commit(None, &signature, &signature, subject, &tree, &[&parent])
+                .unwrap();
+            parent = repo.find_commit(oid).unwrap();
+        }
+
+        let mine = EntryFilter {
+            author: Some("me@example.com".to_string()),
+            since: None,
+        };
+        let stack = walk_range(
+            &repo,
+            &config,
+            parent.id(),
+            base,
+            "shared".to_string(),
+            &[],
+            &mine,
+        )
+        .unwrap();
+        let subjects: Vec<_> = stack
+            .entries
+            .iter()
+            .map(|e| (e.index, e.subject.as_str()))
+            .collect();
+        assert_eq!(subjects, vec![(1, "mine 1"), (2, "mine 2")]);
+        let context: Vec<_> = stack
+            .context
+            .iter()
+            .map(|c| (c.below, c.subject.as_str()))
+            .collect();
+        assert_eq!(context, vec![(1, "theirs"), (2, "theirs on top")]);
+        assert_eq!(stack.top_oid(), Some(parent.id()));
+
+        let stack = walk_range(
+            &repo,
+            &config,
+            parent.id(),
+            base,
+            "shared".to_string(),
+            &[],
+            &EntryFilter::default(),
+        )
+        .unwrap();
+        assert_eq!(stack.len(), 4);
+        assert!(stack.context.is_empty());
+    }
+
+    #[test]
+    fn test_walk_range_since() {
+        let dir = tempfile::tempdir().unwrap();
+        let repo = Repository::init(dir.path()).unwrap();
+        let config = Config::load(&repo).unwrap();
+        let tree = repo
+            .find_tree(repo.treebuilder(None).unwrap().write().unwrap())
+            .unwrap();
+        let at = |seconds| {
+            git2::Signature::new("Me", "me@example.com", &git2::Time::new(seconds, 0)).unwrap()
+        };
+        let mut parent = repo
+            .find_commit(
+                repo.commit(None, &at(0), &at(0), "base", &tree, &[])
+                    .unwrap(),
+            )
+            .unwrap();
+        let base = parent.id();
+        for (subject, time) in [("old", 1_000), ("new", 3_000), ("newer", 4_000)] {
+            let oid = repo
+                .commit(None, &at(time), &at(time), subject, &tree, &[&parent])
+                .unwrap();
+            parent = repo.find_commit(oid).unwrap();
+        }
+
+        let since = EntryFilter {
+            author: None,
+            since: Some(2_000),
+        };
+        let stack = walk_range(
+            &repo,
+            &config,
+            parent.id(),
+            base,
+            "shared".to_string(),
+            &[],
+            &since,
+        )
+        .unwrap();
+        let subjects: Vec<_> = stack.entries.iter().map(|e| e.subject.as_str()).collect();
+        assert_eq!(subjects, vec!["new", "newer"]);
+        assert_eq!(
+            stack
+                .context
+                .iter()
+                .map(|c| (c.below, c.subject.as_str()))
+                .collect::<Vec<_>>(),
+            vec![(0, "old")]
+        );
+        assert_eq!(stack.context_in_pr(&stack.entries[0]).count(), 1);
+        assert_eq!(stack.context_in_pr(&stack.entries[1]).count(), 0);
+
+        assert_eq!(
+            crate::git::repo::parse_date(&repo, "2024-05-01 00:00:00 +0000").unwrap(),
+            1_714_521_600
+        );
+    }
+
+    #[test]
+    fn test_copy_pr_state() {
+        let mut known = Entry::for_test(1);
+        known.pr_number = Some(7);
+        known.pr_state = Some(PrState::Merged);
+        known.pr_base_ref = Some("main".to_string());
+        known.unresolved_threads = 2;
+
+        let mut rewritten = Entry::for_test(2);
+        rewritten.pr_number = Some(7);
+        copy_pr_state(&known, &mut rewritten);
+        assert_eq!(rewritten.pr_state, Some(PrState::Merged));
+        assert_eq!(rewritten.pr_base_ref.as_deref(), Some("main"));
+        assert_eq!(rewritten.unresolved_threads, 2);
+        assert!(rewritten.merged_into_main);
+
+        // State fetched for the rewrite itself wins; unhydrated entries have nothing to give
+        let mut fetched = Entry::for_test(3);
+        fetched.pr_base_ref = Some("other".to_string());
+        copy_pr_state(&known, &mut fetched);
+        assert_eq!(fetched.pr_base_ref.as_deref(), Some("other"));
+        let mut untouched = Entry::for_test(4);
+        copy_pr_state(&Entry::for_test(5), &mut untouched);
+        assert_eq!(untouched.pr_state, None);
     }
 }
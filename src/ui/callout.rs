@@ -1,8 +1,15 @@
-use crate::model::{Entry, PrState};
+use crate::model::{Entry, PrStackMetadata, PrState, UpdateStatus};
 
 const CALLOUT_BEGIN: &str = "<!-- git-stk:begin (do not edit) -->";
 const CALLOUT_END: &str = "<!-- git-stk:end -->";
 
+/// Delimiters of the hidden, machine-readable stack metadata in PR bodies
+const METADATA_BEGIN: &str = "<!-- git-stk:meta ";
+const METADATA_END: &str = " -->";
+
+/// Marker identifying the stack summary comment so it can be edited in place
+pub const SUMMARY_MARKER: &str = "<!-- git-stk:summary (do not edit) -->";
+
 /// Generate the stack callout markdown for a PR
 pub fn generate_callout(
     entries: &[Entry],
@@ -14,7 +21,11 @@ pub fn generate_callout(
 
     // Use collapsible details element, expanded by default
     lines.push("<details open>".to_string());
-    lines.push(format!("<summary>📚 Stack ({} of {})</summary>", current_index, entries.len()));
+    lines.push(format!(
+        "<summary>📚 Stack ({} of {})</summary>",
+        current_index,
+        entries.len()
+    ));
     lines.push(String::new()); // Empty line for markdown parsing
 
     // Add each PR in the stack
@@ -31,12 +42,15 @@ pub fn generate_callout(
 }
 
 /// Format a single stack item with inline PR reference
-fn format_stack_item(entry: &Entry, is_current: bool, position: usize, owner: &str, repo: &str) -> String {
-    let state_emoji = if let Some(state) = &entry.pr_state {
-        match state {
-            PrState::Draft => " 🟡",
-            _ => "",
-        }
+fn format_stack_item(
+    entry: &Entry,
+    is_current: bool,
+    position: usize,
+    owner: &str,
+    repo: &str,
+) -> String {
+    let state_emoji = if let Some(PrState::Draft) = &entry.pr_state {
+        " 🟡"
     } else {
         ""
     };
@@ -45,10 +59,21 @@ fn format_stack_item(entry: &Entry, is_current: bool, position: usize, owner: &s
         Some(pr_number) => {
             if is_current {
                 // Current PR - bold with indicator
-                format!("{}. **{}/{}#{}** ← current{}", position, owner, repo, pr_number, state_emoji)
+                format!(
+                    "{}. **{}/{}#{}** ← current{}{}",
+                    position,
+                    owner,
+                    repo,
+                    pr_number,
+                    format_revision(entry),
+                    state_emoji
+                )
             } else {
                 // Other PRs - clickable reference (GitHub auto-renders title)
-                format!("{}. {}/{}#{}{}", position, owner, repo, pr_number, state_emoji)
+                format!(
+                    "{}. {}/{}#{}{}",
+                    position, owner, repo, pr_number, state_emoji
+                )
             }
         }
         None => {
@@ -62,6 +87,80 @@ fn format_stack_item(entry: &Entry, is_current: bool, position: usize, owner: &s
     }
 }
 
+/// " (revision N)" once the entry's current commit has been exported
+fn format_revision(entry: &Entry) -> String {
+    match &entry.last_export {
+        Some(last) if last.revision > 0 && last.oid == entry.oid.to_string() => {
+            format!(" (revision {})", last.revision)
+        }
+        _ => String::new(),
+    }
+}
+
+/// Plain-text cover letter blurb for a mailed patch series: the stack callout
+/// without GitHub markup
+pub fn generate_cover_letter(entries: &[Entry]) -> String {
+    let mut lines = vec![format!(
+        "This series contains {} patch{}:",
+        entries.len(),
+        if entries.len() == 1 { "" } else { "es" }
+    )];
+    lines.push(String::new());
+
+    for (idx, entry) in entries.iter().enumerate() {
+        lines.push(format!("  {}. {}", idx + 1, entry.subject));
+        if let Some(annotation) = &entry.annotation {
+            lines.push(format!("     ({})", annotation));
+        }
+    }
+
+    lines.join("\n")
+}
+
+/// Generate the stack summary comment posted on the top PR
+pub fn generate_summary_comment(entries: &[Entry], owner: &str, repo: &str) -> String {
+    let mut lines = vec![
+        SUMMARY_MARKER.to_string(),
+        format!(
+            "### 📚 Stack summary ({} PR{})",
+            entries.len(),
+            if entries.len() == 1 { "" } else { "s" }
+        ),
+        String::new(),
+        "| # | PR | Commit | Status |".to_string(),
+        "|---|----|--------|--------|".to_string(),
+    ];
+
+    for (idx, entry) in entries.iter().enumerate() {
+        let pr = match entry.pr_number {
+            Some(pr_number) => format!("{}/{}#{}", owner, repo, pr_number),
+            None => "_(pending)_".to_string(),
+        };
+        lines.push(format!(
+            "| {} | {} | `{}` {} | {} |",
+            idx + 1,
+            pr,
+            entry.short_sha,
+            entry.subject.replace('|', "\\|"),
+            summary_status(entry)
+        ));
+    }
+
+    lines.join("\n")
+}
+
+fn summary_status(entry: &Entry) -> &'static str {
+    if entry.merged_into_main {
+        return "🟣 Merged";
+    }
+    match (&entry.pr_state, entry.status) {
+        (Some(PrState::Closed), _) => "⚫ Closed",
+        (_, UpdateStatus::NeedsUpdate) => "🟠 Export needed",
+        (_, UpdateStatus::CreatePr) => "⚪ Not exported",
+        (Some(PrState::Draft), UpdateStatus::UpToDate) => "🟡 Draft",
+        (_, UpdateStatus::UpToDate) => "🟢 Synced",
+    }
+}
 
 /// Inject or replace the stack callout in a PR body
 pub fn inject_callout(existing_body: &str, callout: &str) -> String {
@@ -94,8 +193,51 @@ pub fn inject_callout(existing_body: &str, callout: &str) -> String {
     }
 }
 
-/// Strip the callout from a PR body, keeping all other content
+/// Render the hidden metadata block, e.g. `<!-- git-stk:meta {"branch":...} -->`
+pub fn generate_metadata_block(metadata: &PrStackMetadata) -> String {
+    let json = serde_json::to_string(metadata).unwrap_or_default();
+    format!("{}{}{}", METADATA_BEGIN, json, METADATA_END)
+}
+
+/// Parse the hidden metadata block of a PR body, if present and well-formed
+pub fn parse_metadata_block(body: &str) -> Option<PrStackMetadata> {
+    let start = body.find(METADATA_BEGIN)? + METADATA_BEGIN.len();
+    let end = body[start..].find(METADATA_END)?;
+    serde_json::from_str(&body[start..start + end]).ok()
+}
+
+/// Append the metadata block to a PR body, replacing any existing one
+pub fn inject_metadata_block(body: &str, metadata: &PrStackMetadata) -> String {
+    let body = strip_metadata_block(body);
+    let block = generate_metadata_block(metadata);
+    if body.is_empty() {
+        block
+    } else {
+        format!("{}\n\n{}", body, block)
+    }
+}
+
+/// Remove the metadata block from a PR body
+fn strip_metadata_block(body: &str) -> String {
+    if let Some(start) = body.find(METADATA_BEGIN)
+        && let Some(end) = body[start..].find(METADATA_END)
+    {
+        let end_pos = start + end + METADATA_END.len();
+        let before = body[..start].trim_end();
+        let after = body[end_pos..].trim_start();
+        return match (before.is_empty(), after.is_empty()) {
+            (_, true) => before.to_string(),
+            (true, false) => after.to_string(),
+            (false, false) => format!("{}\n\n{}", before, after),
+        };
+    }
+    body.to_string()
+}
+
+/// Strip the callout and metadata block from a PR body, keeping all other content
 pub fn strip_callout(body: &str) -> String {
+    let body = strip_metadata_block(body);
+    let body = body.as_str();
     if let Some(start) = body.find(CALLOUT_BEGIN) {
         if let Some(end) = body[start..].find(CALLOUT_END) {
             let end_pos = start + end + CALLOUT_END.len();
@@ -184,6 +326,31 @@ mod tests {
         assert_eq!(result, "Just user content.");
     }
 
+    #[test]
+    fn test_metadata_block_roundtrip() {
+        let metadata = PrStackMetadata {
+            branch: "feature/login".to_string(),
+            slot: "02".to_string(),
+            position: 2,
+            total: 3,
+        };
+
+        let body = inject_metadata_block("My description.", &metadata);
+        assert!(body.starts_with("My description.\n\n<!-- git-stk:meta "));
+        assert_eq!(parse_metadata_block(&body), Some(metadata.clone()));
+
+        // Re-injecting replaces the block instead of duplicating it
+        let moved = PrStackMetadata {
+            position: 1,
+            ..metadata
+        };
+        let body = inject_metadata_block(&body, &moved);
+        assert_eq!(body.matches("git-stk:meta").count(), 1);
+        assert_eq!(parse_metadata_block(&body), Some(moved));
+
+        assert_eq!(strip_callout(&body), "My description.");
+    }
+
     #[test]
     fn test_strip_callout_only_callout() {
         let body = "<!-- git-stk:begin (do not edit) -->\nStack\n<!-- git-stk:end -->";
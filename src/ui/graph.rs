@@ -0,0 +1,225 @@
+use crate::model::{Entry, Stack};
+use console::style;
+
+/// A base-branch commit shown beside the stack
+#[derive(Debug, Clone)]
+pub struct BaseCommit {
+    pub short_sha: String,
+    pub subject: String,
+    /// Refs pointing at this commit (e.g. "main", "origin/main")
+    pub refs: Vec<String>,
+}
+
+/// The base branch side of the graph
+#[derive(Debug, Clone)]
+pub struct BaseLane {
+    /// Commits on the base since the merge-base, newest first
+    pub commits: Vec<BaseCommit>,
+    /// Further base commits left out of `commits`
+    pub hidden: usize,
+    /// Where the stack forks off the base
+    pub merge_base: BaseCommit,
+}
+
+/// Render the stack as a graph, newest commit first: each entry with its slot
+/// branch on the remote and the PR it's based on, then the base branch commits
+/// the stack isn't rebased on yet, joined at the merge-base.
+pub fn render_graph(stack: &Stack, lane: &BaseLane, remote: &str) {
+    for line in graph_lines(stack, lane, remote) {
+        println!("  {}", line);
+    }
+    println!();
+}
+
+fn graph_lines(stack: &Stack, lane: &BaseLane, remote: &str) -> Vec<String> {
+    let mut lines = Vec::new();
+
+    for (idx, entry) in stack.entries.iter().enumerate().rev() {
+        let below = idx.checked_sub(1).map(|i| &stack.entries[i]);
+        let slot = entry
+            .slot
+            .as_deref()
+            .map(|slot| format!("  [{}]", style(slot).yellow()))
+            .unwrap_or_default();
+        lines.push(format!(
+            "{}  {}  {}{}",
+            style("●").blue(),
+            style(&entry.short_sha).black().bright(),
+            style(&entry.subject).bold(),
+            slot
+        ));
+        lines.push(format!(
+            "{}    {}  {}",
+            style("│").dim(),
+            format_pr_edge(entry, below),
+            format_slot_branch(entry, remote)
+        ));
+    }
+
+    if !lane.commits.is_empty() {
+        for commit in &lane.commits {
+            lines.push(format!(
+                "{} {}  {}  {}{}",
+                style("│").dim(),
+                style("○").yellow(),
+                style(&commit.short_sha).black().bright(),
+                commit.subject,
+                format_refs(&commit.refs)
+            ));
+        }
+        if lane.hidden > 0 {
+            lines.push(format!(
+                "{} {}  {}",
+                style("│").dim(),
+                style("┆").yellow(),
+                style(format!("… {} more on {}", lane.hidden, stack.base_branch)).dim()
+            ));
+        }
+        lines.push(style("├─╯").dim().to_string());
+    } else if !stack.is_empty() {
+        lines.push(style("│").dim().to_string());
+    }
+
+    let mut merge_base_refs = vec!["merge-base".to_string()];
+    merge_base_refs.extend(lane.merge_base.refs.iter().cloned());
+    lines.push(format!(
+        "{}  {}  {}{}",
+        style("◆").magenta(),
+        style(&lane.merge_base.short_sha).black().bright(),
+        lane.merge_base.subject,
+        format_refs(&merge_base_refs)
+    ));
+
+    lines
+}
+
+/// The PR of an entry and the one it's based on, e.g. "#12 → #11" or "#10 → main"
+fn format_pr_edge(entry: &Entry, below: Option<&Entry>) -> String {
+    let pr = match entry.pr_number {
+        Some(pr) => style(format!("#{}", pr)).cyan().to_string(),
+        None => style("<no PR>").dim().to_string(),
+    };
+    let base = match below.and_then(|b| b.pr_number) {
+        Some(pr) => style(format!("#{}", pr)).cyan().to_string(),
+        None => style(&entry.base_ref).yellow().to_string(),
+    };
+    format!("{} {} {}", pr, style("→").dim(), base)
+}
+
+/// The entry's slot branch on the remote and whether it matches the commit
+fn format_slot_branch(entry: &Entry, remote: &str) -> String {
+    let Some(head_ref) = &entry.head_ref else {
+        return style("no slot yet").dim().to_string();
+    };
+    let name = format!("{}/{}", remote, head_ref);
+
+    if entry.merged_into_main {
+        return format!("{}  {}", style(&name).dim(), style("merged").magenta());
+    }
+    match entry.remote_oid {
+        Some(oid) if oid == entry.oid => {
+            format!(
+                "{} @ {}  {}",
+                style(&name).dim(),
+                format_oid(oid),
+                style("✓").green()
+            )
+        }
+        Some(oid) => format!(
+            "{} @ {}  {}",
+            style(&name).dim(),
+            style(format_oid(oid)).red(),
+            style("export needed").yellow()
+        ),
+        None if entry.remote_branch_exists => style(&name).dim().to_string(),
+        None => format!("{}  {}", style(&name).dim(), style("not pushed").dim()),
+    }
+}
+
+fn format_oid(oid: git2::Oid) -> String {
+    format!("{:.7}", oid)
+}
+
+fn format_refs(refs: &[String]) -> String {
+    if refs.is_empty() {
+        String::new()
+    } else {
+        format!("  {}", style(format!("({})", refs.join(", "))).yellow())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::model::UpdateStatus;
+    use git2::Oid;
+
+    fn entry(index: usize, oid: &str, slot: &str, pr: Option<u64>, base_ref: &str) -> Entry {
+        let oid = Oid::from_str(oid).unwrap();
+        Entry {
+            oid,
+            short_sha: format!("{:.7}", oid),
+            head_ref: Some(format!("feat--{}", slot)),
+            pr_number: pr,
+            status: UpdateStatus::UpToDate,
+            base_ref: base_ref.to_string(),
+            slot: Some(slot.to_string()),
+            ..Entry::for_test(index)
+        }
+    }
+
+    fn base_commit(sha: &str, refs: &[&str]) -> BaseCommit {
+        BaseCommit {
+            short_sha: sha.to_string(),
+            subject: format!("base {}", sha),
+            refs: refs.iter().map(|r| r.to_string()).collect(),
+        }
+    }
+
+    #[test]
+    fn test_graph_lines() {
+        console::set_colors_enabled(false);
+        let mut stack = Stack::new("main".to_string(), "feat".to_string());
+        let mut bottom = entry(
+            1,
+            "1111111111111111111111111111111111111111",
+            "01",
+            Some(10),
+            "main",
+        );
+        bottom.remote_oid = Some(bottom.oid);
+        bottom.remote_branch_exists = true;
+        let mut top = entry(
+            2,
+            "2222222222222222222222222222222222222222",
+            "02",
+            None,
+            "feat--01",
+        );
+        top.remote_oid = Some(Oid::from_str("3333333333333333333333333333333333333333").unwrap());
+        top.remote_branch_exists = true;
+        stack.add_entry(bottom);
+        stack.add_entry(top);
+
+        let lane = BaseLane {
+            commits: vec![base_commit("aaaaaaa", &["origin/main"])],
+            hidden: 2,
+            merge_base: base_commit("bbbbbbb", &["main"]),
+        };
+
+        let lines = graph_lines(&stack, &lane, "origin");
+        assert_eq!(
+            lines,
+            vec![
+                "●  2222222  commit 2  [02]",
+                "│    <no PR> → #10  origin/feat--02 @ 3333333  export needed",
+                "●  1111111  commit 1  [01]",
+                "│    #10 → main  origin/feat--01 @ 1111111  ✓",
+                "│ ○  aaaaaaa  base aaaaaaa  (origin/main)",
+                "│ ┆  … 2 more on main",
+                "├─╯",
+                "◆  bbbbbbb  base bbbbbbb  (merge-base, main)",
+            ]
+        );
+    }
+}
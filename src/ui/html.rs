@@ -0,0 +1,194 @@
+use crate::model::{CiStatus, Entry, PrState, Stack, UpdateStatus};
+use crate::ui::timeline::{author_initials, format_relative_age, now_seconds};
+
+const STYLE: &str = r#"
+body { font-family: -apple-system, BlinkMacSystemFont, "Segoe UI", Helvetica, Arial, sans-serif; background: #0d1117; color: #e6edf3; margin: 2rem auto; max-width: 60rem; padding: 0 1rem; }
+h1 { font-size: 1.3rem; font-weight: 600; }
+h1 .base { color: #d29922; }
+.meta { color: #7d8590; font-size: 0.85rem; margin-bottom: 1.5rem; }
+ol { list-style: none; padding: 0; margin: 0; border-left: 2px solid #30363d; margin-left: 0.6rem; }
+li { position: relative; padding: 0 0 1.2rem 1.5rem; }
+li::before { content: ""; position: absolute; left: -0.5rem; top: 0.35rem; width: 0.8rem; height: 0.8rem; border-radius: 50%; background: var(--dot); }
+li.uptodate { --dot: #3fb950; } li.needsupdate { --dot: #d29922; } li.create { --dot: #58a6ff; } li.merged { --dot: #a371f7; }
+.subject { font-weight: 600; }
+.sha, .slot { font-family: ui-monospace, SFMono-Regular, Menlo, monospace; color: #7d8590; font-size: 0.85rem; margin-right: 0.5rem; }
+.slot { color: #d29922; }
+.line { margin-top: 0.25rem; font-size: 0.9rem; }
+a { color: #58a6ff; text-decoration: none; } a:hover { text-decoration: underline; }
+.badge { display: inline-block; border-radius: 1rem; padding: 0 0.55rem; font-size: 0.75rem; font-weight: 600; margin-right: 0.35rem; border: 1px solid currentColor; }
+.open { color: #3fb950; } .draft { color: #7d8590; } .closed { color: #f85149; } .merged-pr { color: #a371f7; }
+.ci-success { color: #3fb950; } .ci-failure { color: #f85149; } .ci-pending { color: #d29922; }
+.conflict { color: #f85149; } .stale { color: #d29922; } .synced { color: #3fb950; }
+.note { color: #d29922; } .muted { color: #7d8590; }
+"#;
+
+/// Render the stack as a standalone HTML page (PR states, CI badges, links)
+pub fn render_html(stack: &Stack) -> String {
+    let mut html = String::new();
+    html.push_str("<!DOCTYPE html>\n<html lang=\"en\">\n<head>\n<meta charset=\"utf-8\">\n");
+    html.push_str(&format!(
+        "<title>{} — git-stk</title>\n<style>{}</style>\n</head>\n<body>\n",
+        escape_html(&stack.current_branch),
+        STYLE
+    ));
+    html.push_str(&format!(
+        "<h1>{} <span class=\"muted\">on</span> <span class=\"base\">{}</span></h1>\n",
+        escape_html(&stack.current_branch),
+        escape_html(&stack.base_branch)
+    ));
+    html.push_str(&format!(
+        "<div class=\"meta\">{} {}</div>\n",
+        stack.len(),
+        if stack.len() == 1 {
+            "commit"
+        } else {
+            "commits"
+        }
+    ));
+
+    if stack.is_empty() {
+        html.push_str(&format!(
+            "<p class=\"muted\">No commits in stack: {} is up to date with {}.</p>\n",
+            escape_html(&stack.current_branch),
+            escape_html(&stack.base_branch)
+        ));
+    } else {
+        // Top of the stack first, like a commit log
+        html.push_str("<ol>\n");
+        for entry in stack.entries.iter().rev() {
+            render_entry(&mut html, entry);
+        }
+        html.push_str("</ol>\n");
+    }
+
+    html.push_str("</body>\n</html>\n");
+    html
+}
+
+fn render_entry(html: &mut String, entry: &Entry) {
+    let class = if entry.merged_into_main {
+        "merged"
+    } else {
+        match entry.status {
+            UpdateStatus::UpToDate => "uptodate",
+            UpdateStatus::NeedsUpdate => "needsupdate",
+            UpdateStatus::CreatePr => "create",
+        }
+    };
+
+    html.push_str(&format!("<li class=\"{}\">\n", class));
+    html.push_str(&format!(
+        "<div><span class=\"sha\">{}. {}</span><span class=\"subject\">{}</span>",
+        entry.index,
+        escape_html(&entry.short_sha),
+        escape_html(&entry.subject)
+    ));
+    if let Some(slot) = entry.slot.as_ref().or(entry.predicted_slot.as_ref()) {
+        html.push_str(&format!(
+            " <span class=\"slot\">[{}]</span>",
+            escape_html(slot)
+        ));
+    }
+    html.push_str("</div>\n");
+
+    // PR state, CI, and link
+    html.push_str("<div class=\"line\">");
+    match (entry.pr_number, entry.pr_url()) {
+        (Some(pr_number), url) => {
+            if let Some(state) = &entry.pr_state {
+                let (label, class) = match state {
+                    PrState::Open => ("Open", "open"),
+                    PrState::Draft => ("Draft", "draft"),
+                    PrState::Closed => ("Closed", "closed"),
+                    PrState::Merged => ("Merged", "merged-pr"),
+                };
+                html.push_str(&format!("<span class=\"badge {}\">{}</span>", class, label));
+            }
+            if let Some(status) = entry.ci_status {
+                let (label, class) = match status {
+                    CiStatus::Success => ("✓ CI", "ci-success"),
+                    CiStatus::Failure => ("✗ CI", "ci-failure"),
+                    CiStatus::Pending => ("● CI", "ci-pending"),
+                };
+                html.push_str(&format!("<span class=\"badge {}\">{}</span>", class, label));
+            }
+            match url {
+                Some(url) => html.push_str(&format!(
+                    "<a href=\"{}\">#{}</a>",
+                    escape_html(&url),
+                    pr_number
+                )),
+                None => html.push_str(&format!("#{}", pr_number)),
+            }
+        }
+        (None, _) => html.push_str("<span class=\"muted\">&lt;PR to be created&gt;</span>"),
+    }
+    html.push_str("</div>\n");
+
+    // Sync status, conflicts, author and age
+    html.push_str("<div class=\"line muted\">");
+    if entry.merged_into_main {
+        html.push_str("<span class=\"merged-pr\">Merged</span> · ");
+    } else if entry.remote_branch_exists {
+        match entry.remote_oid {
+            Some(remote_oid) if remote_oid != entry.oid => {
+                html.push_str("<span class=\"stale\">Export needed</span> · ")
+            }
+            _ => html.push_str("<span class=\"synced\">Synced</span> · "),
+        }
+    }
+    if entry.has_conflicts && !entry.merged_into_main {
+        html.push_str("<span class=\"conflict\">⚠ Conflict</span> · ");
+    }
+    if entry.unresolved_threads > 0 && !entry.merged_into_main {
+        html.push_str(&format!(
+            "<span class=\"stale\">💬 {} unresolved</span> · ",
+            entry.unresolved_threads
+        ));
+    }
+    html.push_str(&format!(
+        "{} · {}",
+        escape_html(&author_initials(&entry.author_name)),
+        format_relative_age(now_seconds() - entry.commit_time)
+    ));
+    html.push_str("</div>\n");
+
+    if let Some(annotation) = &entry.annotation {
+        html.push_str(&format!(
+            "<div class=\"line note\">📌 {}</div>\n",
+            escape_html(annotation)
+        ));
+    }
+
+    html.push_str("</li>\n");
+}
+
+/// Escape text for use in HTML content and attribute values
+fn escape_html(text: &str) -> String {
+    let mut escaped = String::with_capacity(text.len());
+    for c in text.chars() {
+        match c {
+            '&' => escaped.push_str("&amp;"),
+            '<' => escaped.push_str("&lt;"),
+            '>' => escaped.push_str("&gt;"),
+            '"' => escaped.push_str("&quot;"),
+            '\'' => escaped.push_str("&#39;"),
+            c => escaped.push(c),
+        }
+    }
+    escaped
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_escape_html() {
+        assert_eq!(
+            escape_html(r#"<a href="x">Tom & Jerry's</a>"#),
+            "&lt;a href=&quot;x&quot;&gt;Tom &amp; Jerry&#39;s&lt;/a&gt;"
+        );
+        assert_eq!(escape_html("plain"), "plain");
+    }
+}
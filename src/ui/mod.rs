@@ -1,4 +1,7 @@
 pub mod callout;
+pub mod graph;
+pub mod html;
+pub mod summary;
 pub mod timeline;
 
-pub use timeline::render_timeline;
+pub use timeline::{render_overlaps, render_timeline};
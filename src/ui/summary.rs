@@ -0,0 +1,165 @@
+use crate::git::conventional::{self, Conventional};
+use crate::model::Entry;
+
+/// Sections of a conventional-commit summary, in display order
+const SECTIONS: &[(&[&str], &str)] = &[
+    (&["feat"], "Features"),
+    (&["fix"], "Bug Fixes"),
+    (&["perf"], "Performance"),
+    (&["refactor"], "Refactoring"),
+    (&["docs"], "Documentation"),
+    (&["test"], "Tests"),
+    (&["build", "ci"], "Build & CI"),
+    (&["revert"], "Reverts"),
+];
+
+/// Render a markdown summary of the stack: one bullet per commit with its body
+/// indented below, or grouped by conventional-commit type with `conventional`.
+/// `items` pairs each entry with its commit body (trailers already stripped).
+pub fn render_summary(
+    title: &str,
+    items: &[(&Entry, String)],
+    conventional: bool,
+    include_bodies: bool,
+) -> String {
+    let mut lines = vec![format!("## {}", title), String::new()];
+
+    if !conventional {
+        for (entry, body) in items {
+            push_item(
+                &mut lines,
+                &entry.subject,
+                entry.pr_number,
+                body,
+                include_bodies,
+            );
+        }
+        return finish(lines);
+    }
+
+    let parsed: Vec<_> = items
+        .iter()
+        .map(|(entry, body)| (*entry, body, conventional::parse(&entry.subject)))
+        .collect();
+
+    let breaking: Vec<_> = parsed
+        .iter()
+        .filter(|(_, body, conv)| {
+            conv.as_ref().is_some_and(|c| c.breaking) || has_breaking_trailer(body)
+        })
+        .collect();
+    if !breaking.is_empty() {
+        lines.push("### ⚠ Breaking Changes".to_string());
+        lines.push(String::new());
+        for (entry, _, conv) in &breaking {
+            let text = conv
+                .as_ref()
+                .map_or(entry.subject.clone(), format_conventional);
+            push_item(&mut lines, &text, entry.pr_number, "", false);
+        }
+        end_section(&mut lines);
+    }
+
+    for (kinds, heading) in SECTIONS {
+        let section: Vec<_> = parsed
+            .iter()
+            .filter_map(|(entry, body, conv)| {
+                conv.as_ref()
+                    .filter(|c| kinds.contains(&c.kind.as_str()))
+                    .map(|c| (entry, body, c))
+            })
+            .collect();
+        if section.is_empty() {
+            continue;
+        }
+        lines.push(format!("### {}", heading));
+        lines.push(String::new());
+        for (entry, body, conv) in section {
+            push_item(
+                &mut lines,
+                &format_conventional(conv),
+                entry.pr_number,
+                body,
+                include_bodies,
+            );
+        }
+        end_section(&mut lines);
+    }
+
+    // Non-conventional subjects and types without a section of their own
+    let other: Vec<_> = parsed
+        .iter()
+        .filter(|(_, _, conv)| {
+            conv.as_ref().is_none_or(|c| {
+                !SECTIONS
+                    .iter()
+                    .any(|(kinds, _)| kinds.contains(&c.kind.as_str()))
+            })
+        })
+        .collect();
+    if !other.is_empty() {
+        lines.push("### Other Changes".to_string());
+        lines.push(String::new());
+        for (entry, body, _) in other {
+            push_item(
+                &mut lines,
+                &entry.subject,
+                entry.pr_number,
+                body,
+                include_bodies,
+            );
+        }
+    }
+
+    finish(lines)
+}
+
+fn push_item(
+    lines: &mut Vec<String>,
+    text: &str,
+    pr_number: Option<u64>,
+    body: &str,
+    include_body: bool,
+) {
+    match pr_number {
+        Some(pr_number) => lines.push(format!("- {} (#{})", text, pr_number)),
+        None => lines.push(format!("- {}", text)),
+    }
+    if include_body && !body.is_empty() {
+        lines.push(String::new());
+        for line in body.lines() {
+            if line.is_empty() {
+                lines.push(String::new());
+            } else {
+                lines.push(format!("  {}", line));
+            }
+        }
+        lines.push(String::new());
+    }
+}
+
+/// Separate sections by one blank line (item bodies already end with one)
+fn end_section(lines: &mut Vec<String>) {
+    if lines.last().is_some_and(|l| !l.is_empty()) {
+        lines.push(String::new());
+    }
+}
+
+fn finish(mut lines: Vec<String>) -> String {
+    while lines.last().is_some_and(|l| l.is_empty()) {
+        lines.pop();
+    }
+    lines.join("\n") + "\n"
+}
+
+fn format_conventional(conv: &Conventional) -> String {
+    match conv.scope {
+        Some(scope) => format!("**{}:** {}", scope, conv.description),
+        None => conv.description.to_string(),
+    }
+}
+
+fn has_breaking_trailer(body: &str) -> bool {
+    body.lines()
+        .any(|l| l.starts_with("BREAKING CHANGE:") || l.starts_with("BREAKING-CHANGE:"))
+}
@@ -1,8 +1,12 @@
-use crate::model::{Entry, Stack, UpdateStatus};
+use crate::git::overlap::Overlap;
+use crate::model::{ContextCommit, Entry, RemoteRefsSource, Stack, UpdateStatus};
 use console::style;
+use std::time::{SystemTime, UNIX_EPOCH};
 
 /// Render a beautiful timeline view of the stack
-pub fn render_timeline(stack: &Stack) {
+///
+/// With `show_details`, each entry also shows its author initials and relative age.
+pub fn render_timeline(stack: &Stack, show_details: bool) {
     if stack.is_empty() {
         println!("{}", style("No commits in stack").dim());
         println!(
@@ -14,33 +18,106 @@ pub fn render_timeline(stack: &Stack) {
     }
 
     // Calculate maximum width for index alignment
-    let max_index_width = stack.entries.len().to_string().len();
+    let max_index = stack.entries.iter().map(|e| e.index).max().unwrap_or(0);
+    let max_index_width = max_index.to_string().len();
+
+    if stack.offline {
+        println!(
+            "  {} {}",
+            style("⚠").yellow(),
+            style("Offline: PR states are unknown").yellow()
+        );
+    }
+    if let RemoteRefsSource::Tracking { fetched_at } = stack.remote_refs_source {
+        println!(
+            "  {} {}",
+            style("ℹ").blue(),
+            style(format_remote_refs_source(
+                fetched_at,
+                now_seconds(),
+                stack.offline
+            ))
+            .dim()
+        );
+    }
+    if stack.offline || stack.remote_refs_source != RemoteRefsSource::Remote {
+        println!();
+    }
 
     // Base branch indicator
     let padding = " ".repeat(max_index_width + 1);
-    println!("  {} {} {}", padding, style("┌─").dim(), style(&stack.base_branch).yellow().dim());
+    println!(
+        "  {} {} {}",
+        padding,
+        style("┌─").dim(),
+        style(&stack.base_branch).yellow().dim()
+    );
     println!("  {} {}", padding, style("│").dim());
 
-    // Render each entry
+    // Render each entry, with the context commits (`git-stk.onlyMine`) below it
+    let top_index = stack.entries.last().map_or(0, |e| e.index);
+    let context_above_top: Vec<_> = stack
+        .context
+        .iter()
+        .filter(|c| c.below == top_index)
+        .collect();
     for (idx, entry) in stack.entries.iter().enumerate() {
-        let is_last = idx == stack.entries.len() - 1;
-        let index = idx + 1; // Start from 1
-        render_entry(entry, is_last, index, max_index_width);
+        for context in stack.context.iter().filter(|c| c.below + 1 == entry.index) {
+            render_context_commit(context, false, max_index_width);
+        }
+
+        let is_last = idx == stack.entries.len() - 1 && context_above_top.is_empty();
+        // Positions in the whole stack, so a filtered view keeps them
+        let context_in_pr = stack.context_in_pr(entry).count();
+        render_entry(
+            entry,
+            is_last,
+            entry.index,
+            max_index_width,
+            show_details,
+            stack.offline,
+            context_in_pr,
+        );
 
         if !is_last {
             let padding = " ".repeat(max_index_width + 1); // +1 for the dot
             println!("  {} {}", padding, style("│").dim());
         }
     }
+    for (idx, context) in context_above_top.iter().enumerate() {
+        render_context_commit(context, idx == context_above_top.len() - 1, max_index_width);
+    }
 
     println!();
 }
 
+/// A commit by someone else: one dim line without index, PR, or slot
+fn render_context_commit(context: &ContextCommit, is_last: bool, max_index_width: usize) {
+    let padding = " ".repeat(max_index_width + 1); // +1 for the dot
+    println!(
+        "  {} {}{}  {}",
+        padding,
+        style(if is_last { "└─" } else { "├─" }).dim(),
+        style("·").dim(),
+        style(format!(
+            "{}  {} ({}, context)",
+            context.short_sha, context.subject, context.author_name
+        ))
+        .dim()
+    );
+    if !is_last {
+        println!("  {} {}", padding, style("│").dim());
+    }
+}
+
 fn render_entry(
     entry: &Entry,
     is_last: bool,
     index: usize,
     max_index_width: usize,
+    show_details: bool,
+    offline: bool,
+    context_in_pr: usize,
 ) {
     let connector = if is_last { "└─" } else { "├─" };
     let indent = if is_last { " " } else { "│" };
@@ -75,53 +152,113 @@ fn render_entry(
         String::new()
     };
 
+    // Author initials and relative age (only with --details)
+    let details_display = if show_details {
+        format!(
+            "  {}",
+            style(format!(
+                "{} · {}",
+                author_initials(&entry.author_name),
+                format_relative_age(now_seconds() - entry.commit_time)
+            ))
+            .dim()
+        )
+    } else {
+        String::new()
+    };
+
     println!(
-        "  {} {}{}  {}  {}{}",
+        "  {} {}{}  {}  {}{}{}",
         style(&index_str).dim(),
         style(connector).dim(),
         bullet,
         style(&entry.short_sha).black().bright(),
         style(&subject).bold(),
-        slot_display
+        slot_display,
+        details_display
     );
 
     let padding = " ".repeat(max_index_width + 1); // +1 for the dot
 
     // Line 2: PR link (no slot)
-    let pr_line = format_pr_link(entry);
+    let pr_line = format_pr_link(entry, offline);
     println!(
-        "  {} {}  {}",
+        "  {} {}  {}{}",
         padding,
         style(indent).dim(),
-        pr_line
+        pr_line,
+        format_unresolved_threads(entry)
     );
 
+    // The PR's diff also shows the context commits below the entry
+    if context_in_pr > 0 {
+        println!(
+            "  {} {}  {}",
+            padding,
+            style(indent).dim(),
+            style(format!(
+                "⚠ PR diff includes {} commit{} by others",
+                context_in_pr,
+                if context_in_pr == 1 { "" } else { "s" }
+            ))
+            .yellow()
+        );
+    }
+
+    // Annotation line (only if the commit has one)
+    if let Some(ref annotation) = entry.annotation {
+        println!(
+            "  {} {}  {} {}",
+            padding,
+            style(indent).dim(),
+            style("📌").yellow(),
+            style(annotation).yellow()
+        );
+    }
+
     // Line 3: Status (only show if remote branch exists or merged)
     if entry.remote_branch_exists || entry.merged_into_main {
         let status_line = format_status_line(entry);
         println!(
-            "  {} {}  {}",
+            "  {} {}  {}{}{}",
             padding,
             style(indent).dim(),
-            status_line
+            status_line,
+            format_last_export(entry),
+            format_conflict_marker(entry)
         );
     }
 }
 
 /// Line 2: PR link (or <PR to be created>)
-fn format_pr_link(entry: &Entry) -> String {
+fn format_pr_link(entry: &Entry, offline: bool) -> String {
     if let Some(pr_number) = entry.pr_number {
-        if let (Some(owner), Some(repo)) = (&entry.repo_owner, &entry.repo_name) {
-            let pr_url = format!("https://github.com/{}/{}/pull/{}", owner, repo, pr_number);
+        let link = if let Some(pr_url) = entry.pr_url() {
             style(&pr_url).cyan().underlined().to_string()
         } else {
             format!("#{}", pr_number)
+        };
+        if offline {
+            format!("{}  {}", link, style("PR state unknown (offline)").dim())
+        } else {
+            link
         }
     } else {
         style("<PR to be created>").dim().to_string()
     }
 }
 
+/// Suffix for the PR link with the number of unresolved review threads
+fn format_unresolved_threads(entry: &Entry) -> String {
+    if entry.unresolved_threads == 0 || entry.merged_into_main {
+        return String::new();
+    }
+    format!(
+        "  {}",
+        style(format!("💬 {} unresolved", entry.unresolved_threads)).yellow()
+    )
+}
+
 /// Line 3: Status (Synced | Export needed | Merged)
 /// Only called when remote_branch_exists || merged_into_main
 fn format_status_line(entry: &Entry) -> String {
@@ -149,3 +286,205 @@ fn format_status_line(entry: &Entry) -> String {
     // Remote exists but no OID info - assume synced
     style("Synced").green().to_string()
 }
+
+/// Suffix for the status line with the age of the last export, and the commit
+/// it pushed when the entry has been rewritten since
+fn format_last_export(entry: &Entry) -> String {
+    let Some(last) = &entry.last_export else {
+        return String::new();
+    };
+    if entry.merged_into_main {
+        return String::new();
+    }
+    let age = format_relative_age(now_seconds() - last.at);
+    let ago = if age == "now" {
+        "just now".to_string()
+    } else {
+        format!("{} ago", age)
+    };
+    let text = if last.oid == entry.oid.to_string() {
+        format!("exported {}", ago)
+    } else {
+        format!("exported {} as {:.7}", ago, last.oid)
+    };
+    format!("  {}", style(format!("· {}", text)).dim())
+}
+
+/// Where the remote branches shown come from, when they weren't listed on the
+/// remote: the remote-tracking branches, as of the last fetch
+fn format_remote_refs_source(fetched_at: Option<i64>, now: i64, offline: bool) -> String {
+    let age = match fetched_at {
+        Some(at) => match format_relative_age(now - at).as_str() {
+            "now" => "just now".to_string(),
+            age => format!("{} ago", age),
+        },
+        None => "time unknown".to_string(),
+    };
+    let hint = if offline {
+        ""
+    } else {
+        ", run with --fetch to refresh"
+    };
+    format!("Remote branches as of the last fetch ({}){}", age, hint)
+}
+
+/// Render entries that change the same files. Entries touching lines of the
+/// previous entry are always listed; plain file overlaps only with `show_file_overlaps`.
+pub fn render_overlaps(stack: &Stack, overlaps: &[Overlap], show_file_overlaps: bool) {
+    let shown: Vec<_> = overlaps
+        .iter()
+        .filter(|o| o.same_lines || show_file_overlaps)
+        .collect();
+    if shown.is_empty() {
+        return;
+    }
+
+    println!(
+        "{} {}",
+        style("⚠").yellow(),
+        style("Overlapping changes").bold()
+    );
+    for overlap in &shown {
+        let later = &stack.entries[overlap.later];
+        let earlier = &stack.entries[overlap.earlier];
+        let what = if overlap.same_lines {
+            style(format!("touches lines from {}.", earlier.index)).yellow()
+        } else {
+            style(format!("also changed in {}.", earlier.index)).dim()
+        };
+        println!(
+            "  {}. {}  {}  {}",
+            later.index,
+            style(&later.short_sha).yellow(),
+            style(&overlap.path).cyan(),
+            what
+        );
+    }
+    if shown.iter().any(|o| o.same_lines) {
+        println!(
+            "  {} Reviewers see these lines twice; consider squashing or reordering the entries.",
+            style("💡").yellow()
+        );
+    }
+    println!();
+}
+
+/// Suffix for the status line when the PR conflicts with its base
+fn format_conflict_marker(entry: &Entry) -> String {
+    if entry.has_conflicts && !entry.merged_into_main {
+        format!("  {}", style("⚠ CONFLICT").red().bold())
+    } else {
+        String::new()
+    }
+}
+
+/// Initials of an author name (e.g., "Jane Doe" -> "JD")
+pub(crate) fn author_initials(name: &str) -> String {
+    let initials: String = name
+        .split_whitespace()
+        .filter_map(|part| part.chars().next())
+        .flat_map(|c| c.to_uppercase())
+        .collect();
+
+    if initials.is_empty() {
+        "?".to_string()
+    } else {
+        initials
+    }
+}
+
+/// Compact relative age (e.g., "now", "5m", "3h", "3d", "2w", "4mo", "1y")
+pub(crate) fn format_relative_age(seconds: i64) -> String {
+    const MINUTE: i64 = 60;
+    const HOUR: i64 = 60 * MINUTE;
+    const DAY: i64 = 24 * HOUR;
+    const WEEK: i64 = 7 * DAY;
+    const MONTH: i64 = 30 * DAY;
+    const YEAR: i64 = 365 * DAY;
+
+    match seconds.max(0) {
+        s if s < MINUTE => "now".to_string(),
+        s if s < HOUR => format!("{}m", s / MINUTE),
+        s if s < DAY => format!("{}h", s / HOUR),
+        s if s < WEEK => format!("{}d", s / DAY),
+        s if s < MONTH => format!("{}w", s / WEEK),
+        s if s < YEAR => format!("{}mo", s / MONTH),
+        s => format!("{}y", s / YEAR),
+    }
+}
+
+pub(crate) fn now_seconds() -> i64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_secs() as i64)
+        .unwrap_or(0)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_author_initials() {
+        assert_eq!(author_initials("Jane Doe"), "JD");
+        assert_eq!(author_initials("alice"), "A");
+        assert_eq!(author_initials("Jean-Luc  Picard"), "JP");
+        assert_eq!(author_initials(""), "?");
+    }
+
+    #[test]
+    fn test_format_last_export() {
+        console::set_colors_enabled(false);
+        let mut entry = Entry {
+            status: UpdateStatus::UpToDate,
+            remote_branch_exists: true,
+            ..Entry::for_test(1)
+        };
+        let oid = entry.oid;
+        assert_eq!(format_last_export(&entry), "");
+
+        entry.last_export = Some(crate::model::LastExport {
+            at: now_seconds() - 2 * 3600,
+            oid: oid.to_string(),
+            previous: None,
+            revision: 1,
+        });
+        assert_eq!(format_last_export(&entry), "  · exported 2h ago");
+
+        entry.last_export = Some(crate::model::LastExport {
+            at: now_seconds(),
+            oid: "2222222222222222222222222222222222222222".to_string(),
+            previous: None,
+            revision: 1,
+        });
+        assert_eq!(
+            format_last_export(&entry),
+            "  · exported just now as 2222222"
+        );
+    }
+
+    #[test]
+    fn test_format_remote_refs_source() {
+        let now = 1_700_000_000;
+        assert_eq!(
+            format_remote_refs_source(Some(now - 3 * 3600), now, false),
+            "Remote branches as of the last fetch (3h ago), run with --fetch to refresh"
+        );
+        assert_eq!(
+            format_remote_refs_source(None, now, true),
+            "Remote branches as of the last fetch (time unknown)"
+        );
+    }
+
+    #[test]
+    fn test_format_relative_age() {
+        assert_eq!(format_relative_age(-5), "now");
+        assert_eq!(format_relative_age(30), "now");
+        assert_eq!(format_relative_age(5 * 60), "5m");
+        assert_eq!(format_relative_age(3 * 3600), "3h");
+        assert_eq!(format_relative_age(3 * 86400), "3d");
+        assert_eq!(format_relative_age(15 * 86400), "2w");
+        assert_eq!(format_relative_age(120 * 86400), "4mo");
+        assert_eq!(format_relative_age(400 * 86400), "1y");
+    }
+}
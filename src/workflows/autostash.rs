@@ -0,0 +1,167 @@
+//! Uncommitted changes around commands that rebase, like `git rebase --autostash`:
+//! they're stashed before the rebase and restored once the command is done.
+
+use crate::git::repo;
+use crate::model::Config;
+use anyhow::{Context, Result, bail};
+use console::style;
+use git2::{Oid, Repository};
+use std::process::Command;
+
+const STASH_REF: &str = "refs/stash";
+
+/// Changes stashed by [`stash_if_dirty`]
+#[derive(Debug)]
+pub struct Autostash {
+    oid: Oid,
+}
+
+/// Stash uncommitted changes (untracked files included) before `operation`
+///
+/// Without `git-stk.autostash` (or `--autostash`), uncommitted changes are an
+/// error, as before. Returns `None` when the worktree is clean.
+pub fn stash_if_dirty(
+    git_repo: &Repository,
+    config: &Config,
+    operation: &str,
+) -> Result<Option<Autostash>> {
+    if !repo::has_uncommitted_changes(git_repo)? {
+        return Ok(None);
+    }
+    if !config.autostash {
+        bail!(
+            "You have uncommitted changes. Commit or stash them before {}, or pass --autostash.",
+            operation
+        );
+    }
+
+    let workdir = git_repo
+        .workdir()
+        .context("Repository has no working directory")?;
+    let output = Command::new("git")
+        .current_dir(workdir)
+        .args(["stash", "push", "--include-untracked", "--message"])
+        .arg(format!("git-stk autostash before {}", operation))
+        .output()
+        .context("Failed to run git stash")?;
+    if !output.status.success() {
+        bail!(
+            "Failed to stash uncommitted changes: {}",
+            String::from_utf8_lossy(&output.stderr).trim()
+        );
+    }
+
+    let oid = git_repo
+        .refname_to_id(STASH_REF)
+        .context("Failed to find the stash that was just created")?;
+    println!(
+        "📦 Stashed uncommitted changes ({})",
+        style(format!("{:.7}", oid)).yellow()
+    );
+
+    Ok(Some(Autostash { oid }))
+}
+
+/// Restore changes stashed by [`stash_if_dirty`] once the command is over
+///
+/// After a failure the branch may be mid-rebase, so the changes stay stashed
+/// with instructions. A conflicting restore leaves them in the stash too, as
+/// `git rebase --autostash` does; neither fails the command.
+pub fn restore(git_repo: &Repository, stash: Option<Autostash>, succeeded: bool) {
+    let Some(stash) = stash else {
+        return;
+    };
+    let short_oid = format!("{:.7}", stash.oid);
+
+    if !succeeded {
+        eprintln!(
+            "\n{} Your uncommitted changes are stashed ({}). Once the branch is in order, run: git stash pop",
+            style("💡").yellow(),
+            style(&short_oid).yellow()
+        );
+        return;
+    }
+
+    // Pop our entry if it's still on top; otherwise only apply it by OID
+    let on_top = git_repo.refname_to_id(STASH_REF).ok() == Some(stash.oid);
+    let mut command = Command::new("git");
+    command.current_dir(git_repo.workdir().unwrap_or_else(|| git_repo.path()));
+    if on_top {
+        command.args(["stash", "pop"]);
+    } else {
+        command.args(["stash", "apply", &stash.oid.to_string()]);
+    }
+
+    match command.output() {
+        Ok(output) if output.status.success() => {
+            println!(
+                "📦 Restored uncommitted changes ({})",
+                style(&short_oid).yellow()
+            );
+            if !on_top {
+                println!(
+                    "  {} The stash entry was kept; drop it with 'git stash drop' once you no longer need it.",
+                    style("ℹ").blue()
+                );
+            }
+        }
+        result => {
+            if let Ok(output) = result {
+                eprintln!("\n{}", String::from_utf8_lossy(&output.stderr).trim_end());
+            }
+            eprintln!(
+                "\n{} Restoring your uncommitted changes ({}) conflicted. They are safe in the stash:",
+                style("⚠").yellow(),
+                style(&short_oid).yellow()
+            );
+            eprintln!(
+                "  resolve the conflicts (or 'git reset --hard' and 'git stash pop' later), then 'git stash drop'."
+            );
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs;
+
+    #[test]
+    fn test_stash_and_restore() {
+        let dir = tempfile::tempdir().unwrap();
+        let repo = Repository::init(dir.path()).unwrap();
+        let mut git_config = repo.config().unwrap();
+        git_config.set_str("user.name", "Ada").unwrap();
+        git_config.set_str("user.email", "ada@example.com").unwrap();
+
+        fs::write(dir.path().join("file.txt"), "one\n").unwrap();
+        let mut index = repo.index().unwrap();
+        index.add_path(std::path::Path::new("file.txt")).unwrap();
+        index.write().unwrap();
+        let tree = repo.find_tree(index.write_tree().unwrap()).unwrap();
+        let signature = repo.signature().unwrap();
+        repo.commit(Some("HEAD"), &signature, &signature, "Initial", &tree, &[])
+            .unwrap();
+
+        let mut config = Config::load(&repo).unwrap();
+        assert!(stash_if_dirty(&repo, &config, "landing").unwrap().is_none());
+
+        fs::write(dir.path().join("file.txt"), "two\n").unwrap();
+        fs::write(dir.path().join("new.txt"), "new\n").unwrap();
+        let error = stash_if_dirty(&repo, &config, "landing").unwrap_err();
+        assert!(error.to_string().contains("--autostash"));
+
+        config.autostash = true;
+        let stash = stash_if_dirty(&repo, &config, "landing").unwrap();
+        assert!(stash.is_some());
+        assert!(!repo::has_uncommitted_changes(&repo).unwrap());
+
+        restore(&repo, stash, true);
+        assert_eq!(
+            fs::read_to_string(dir.path().join("file.txt")).unwrap(),
+            "two\n"
+        );
+        assert!(dir.path().join("new.txt").exists());
+        assert!(repo.refname_to_id(STASH_REF).is_err());
+    }
+}
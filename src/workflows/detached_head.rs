@@ -0,0 +1,71 @@
+use crate::git::{jj, repo};
+use crate::model::Config;
+use anyhow::{Result, bail};
+use console::style;
+use git2::Repository;
+
+/// Make sure HEAD is on a branch before running a command that pushes or rewrites
+///
+/// Head refs are derived from the branch name, so a detached HEAD (e.g. after
+/// `git checkout <sha>`) is attached to a new branch named by `git-stk.detachedBranch`,
+/// after confirmation unless `skip_confirm` is set. In jj mode, where HEAD is always
+/// detached, a bookmark is created at HEAD instead.
+pub fn ensure_on_branch(git_repo: &Repository, config: &Config, skip_confirm: bool) -> Result<()> {
+    if !repo::is_detached(git_repo)? {
+        return Ok(());
+    }
+
+    let head = git_repo.head()?.peel_to_commit()?.id();
+    let name = repo::detached_branch_name(&config.detached_branch, head);
+
+    println!(
+        "{} HEAD is detached at {}",
+        style("⚠️").yellow(),
+        style(format!("{:.7}", head)).yellow()
+    );
+
+    let kind = if config.jj { "bookmark" } else { "branch" };
+    let confirmed = if skip_confirm {
+        true
+    } else if console::user_attended() {
+        use dialoguer::Confirm;
+        Confirm::new()
+            .with_prompt(format!("Create {} {} at HEAD and continue?", kind, name))
+            .default(true)
+            .interact()?
+    } else {
+        false
+    };
+
+    if !confirmed {
+        if config.jj {
+            bail!(
+                "No bookmark points at @-. Create one (e.g. 'jj bookmark create {} -r @-') and try again.",
+                name
+            );
+        }
+        bail!(
+            "HEAD is detached. Check out a branch (e.g. 'git switch -c {}') and try again.",
+            name
+        );
+    }
+
+    if config.jj {
+        jj::create_bookmark_at_head(git_repo, &name)?;
+        println!(
+            "  {} Created bookmark {}\n",
+            style("✓").green(),
+            style(&name).cyan()
+        );
+        return Ok(());
+    }
+
+    repo::attach_head_to_new_branch(git_repo, &name)?;
+    println!(
+        "  {} Switched to new branch {}\n",
+        style("✓").green(),
+        style(&name).cyan()
+    );
+
+    Ok(())
+}
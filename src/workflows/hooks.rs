@@ -0,0 +1,168 @@
+//! User commands run on stack events (`git-stk.hook.<event>`). Each gets the
+//! event's JSON payload on stdin, like git hooks get their arguments.
+
+use crate::model::Config;
+use anyhow::{Context, Result, bail};
+use console::style;
+use git2::Repository;
+use std::io::{ErrorKind, Write};
+use std::process::{Command, Stdio};
+use tracing::debug;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum HookEvent {
+    /// Before an export pushes anything; payload is the export plan
+    PreExport,
+    /// After an export; payload lists the stack's PRs
+    PostExport,
+    /// After a PR is created; payload describes the PR
+    PrCreated,
+    /// After a landed PR is cleaned up and the stack re-exported
+    PostLand,
+}
+
+impl HookEvent {
+    /// Event name as used in `git-stk.hook.<name>`
+    pub fn name(self) -> &'static str {
+        match self {
+            Self::PreExport => "pre-export",
+            Self::PostExport => "post-export",
+            Self::PrCreated => "pr-created",
+            Self::PostLand => "post-land",
+        }
+    }
+
+    /// Whether a failing hook aborts the operation, as git's pre-* hooks do
+    fn blocking(self) -> bool {
+        self == Self::PreExport
+    }
+}
+
+/// Run the commands configured for `event`, in order, with `payload` on stdin
+///
+/// Commands run through `sh` from the repository root with `GIT_STK_EVENT` set.
+/// Their output goes to stderr so it never mixes with `--json` output. A
+/// failing blocking hook is an error; other failures only warn.
+pub fn run_hooks(
+    repo: &Repository,
+    config: &Config,
+    event: HookEvent,
+    payload: serde_json::Value,
+) -> Result<()> {
+    let Some(commands) = config.hooks.get(event.name()).filter(|c| !c.is_empty()) else {
+        return Ok(());
+    };
+    let workdir = repo
+        .workdir()
+        .context("Repository has no working directory")?;
+
+    let mut payload = payload;
+    if let Some(object) = payload.as_object_mut() {
+        object.insert("event".to_string(), event.name().into());
+    }
+    let input = serde_json::to_vec(&payload).context("Failed to serialize hook payload")?;
+
+    for command in commands {
+        debug!(event = event.name(), command, "running hook");
+        let result = run_hook(workdir, event, command, &input);
+
+        let failure = match result {
+            Ok(status) if status.success() => continue,
+            Ok(status) => match status.code() {
+                Some(code) => format!("exited with status {}", code),
+                None => "was killed by a signal".to_string(),
+            },
+            Err(e) => format!("could not run: {}", e),
+        };
+        if event.blocking() {
+            bail!("{} hook '{}' {}", event.name(), command, failure);
+        }
+        eprintln!(
+            "{} {} hook '{}' {}",
+            style("⚠").yellow(),
+            event.name(),
+            command,
+            failure
+        );
+    }
+
+    Ok(())
+}
+
+fn run_hook(
+    workdir: &std::path::Path,
+    event: HookEvent,
+    command: &str,
+    input: &[u8],
+) -> Result<std::process::ExitStatus> {
+    let mut child = Command::new("sh")
+        .arg("-c")
+        .arg(command)
+        .current_dir(workdir)
+        .env("GIT_STK_EVENT", event.name())
+        .stdin(Stdio::piped())
+        .stdout(Stdio::from(std::io::stderr()))
+        .spawn()
+        .context("Failed to start sh")?;
+
+    if let Some(mut stdin) = child.stdin.take() {
+        // Hooks that don't read their payload close stdin early
+        match stdin.write_all(input) {
+            Err(e) if e.kind() != ErrorKind::BrokenPipe => {
+                return Err(e).context("Failed to write hook payload");
+            }
+            _ => {}
+        }
+    }
+
+    child.wait().context("Failed to wait for hook")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn test_run_hooks() {
+        let dir = tempfile::tempdir().unwrap();
+        let repo = Repository::init(dir.path()).unwrap();
+        let mut git_config = repo.config().unwrap();
+        git_config
+            .set_str("git-stk.hook.post-export", "cat > payload.json")
+            .unwrap();
+        git_config
+            .set_multivar("git-stk.hook.post-export", "^$", "exit 3")
+            .unwrap();
+        git_config
+            .set_str("git-stk.hook.pre-export", "exit 1")
+            .unwrap();
+        let config = Config::load(&repo).unwrap();
+        assert_eq!(
+            config.hooks["post-export"],
+            ["cat > payload.json", "exit 3"]
+        );
+
+        // Post hooks only warn when they fail
+        run_hooks(
+            &repo,
+            &config,
+            HookEvent::PostExport,
+            json!({ "branch": "feat" }),
+        )
+        .unwrap();
+        let payload: serde_json::Value =
+            serde_json::from_slice(&std::fs::read(dir.path().join("payload.json")).unwrap())
+                .unwrap();
+        assert_eq!(payload, json!({ "branch": "feat", "event": "post-export" }));
+
+        let error = run_hooks(&repo, &config, HookEvent::PreExport, json!({})).unwrap_err();
+        assert_eq!(
+            error.to_string(),
+            "pre-export hook 'exit 1' exited with status 1"
+        );
+
+        // Events without hooks are a no-op
+        run_hooks(&repo, &config, HookEvent::PostLand, json!({})).unwrap();
+    }
+}
@@ -1,12 +1,17 @@
 use crate::commands::export;
 use crate::gh::{client, mutations, queries};
-use crate::git::notes;
+use crate::git::{backup, notes, patch_id, refs, repo};
 use crate::model::Config;
 use crate::stack::discover_stack;
 use crate::ui::callout;
+use crate::workflows::hooks::{self, HookEvent};
+use crate::workflows::pending_land;
+use crate::workflows::webhook::{self, Activity, PrSummary, WebhookEvent};
 use anyhow::{Context, Result};
 use console::style;
-use git2::Repository;
+use git2::{Oid, Repository};
+use serde_json::json;
+use std::path::Path;
 use std::process::Command;
 
 /// Run post-merge operations: pull main, rebase feature branch, re-export stack
@@ -19,20 +24,24 @@ pub async fn run_post_merge_operations(
         .workdir()
         .context("Repository has no working directory")?;
 
+    let base = config.pr_base()?;
+
     // Get current branch name
-    let head = git_repo.head()?;
-    let current_branch = head
-        .shorthand()
-        .context("Could not get current branch name")?
-        .to_string();
+    let current_branch =
+        repo::current_branch(git_repo).context("Could not get current branch name")?;
+
+    // The note is removed during cleanup; keep the PR number for the post-land hook
+    let landed_pr = notes::read_note(git_repo, landed_commit_oid, &config.notes_ref)
+        .ok()
+        .flatten()
+        .and_then(|m| m.pr);
 
-    println!("\n📥 Updating {} branch...", config.base);
+    println!("\n📥 Updating {} branch...", base);
 
     // Fetch the base branch and update local tracking branch
     // Using refspec syntax: <remote-ref>:<local-ref> to update local main
-    let refspec = format!("{}:{}", config.base, config.base);
-    let output = Command::new("git")
-        .current_dir(repo_path)
+    let refspec = format!("{}:{}", base, base);
+    let output = refs::git_command(repo_path, &config.transport)
         .arg("fetch")
         .arg(&config.remote)
         .arg(&refspec)
@@ -44,14 +53,14 @@ pub async fn run_post_merge_operations(
 
         // If fetch fails (e.g., local main has diverged), try force update
         if stderr.contains("non-fast-forward") || stderr.contains("rejected") {
-            eprintln!("{} Local {} has diverged from remote. Force updating...",
+            eprintln!(
+                "{} Local {} has diverged from remote. Force updating...",
                 style("⚠").yellow(),
-                config.base
+                base
             );
 
-            let force_refspec = format!("+{}:{}", config.base, config.base);
-            let force_output = Command::new("git")
-                .current_dir(repo_path)
+            let force_refspec = format!("+{}:{}", base, base);
+            let force_output = refs::git_command(repo_path, &config.transport)
                 .arg("fetch")
                 .arg(&config.remote)
                 .arg(&force_refspec)
@@ -60,117 +69,171 @@ pub async fn run_post_merge_operations(
 
             if !force_output.status.success() {
                 let force_stderr = String::from_utf8_lossy(&force_output.stderr);
-                eprintln!("{} Failed to update {}: {}",
+                eprintln!(
+                    "{} Failed to update {}: {}",
                     style("✗").red(),
-                    config.base,
+                    base,
                     force_stderr
                 );
                 eprintln!("\n{} Try running:", style("💡").yellow());
-                eprintln!("  git fetch {} +{}:{}", config.remote, config.base, config.base);
+                eprintln!("  git fetch {} +{}:{}", config.remote, base, base);
                 return Err(anyhow::anyhow!("Failed to update base branch"));
             }
         } else {
-            eprintln!("{} Failed to fetch {}: {}",
-                style("✗").red(),
-                config.base,
-                stderr
-            );
+            eprintln!("{} Failed to fetch {}: {}", style("✗").red(), base, stderr);
             eprintln!("\n{} Try running:", style("💡").yellow());
-            eprintln!("  git fetch {} {}:{}", config.remote, config.base, config.base);
+            eprintln!("  git fetch {} {}:{}", config.remote, base, base);
             return Err(anyhow::anyhow!("Failed to fetch base branch"));
         }
     }
 
-    println!("  {} Updated local {} to match remote", style("✓").green(), config.base);
+    println!(
+        "  {} Updated local {} to match remote",
+        style("✓").green(),
+        base
+    );
 
-    // Rebase current branch on top of the updated base
-    println!("\n🔄 Rebasing {} on {}...", current_branch, config.base);
-
-    let remote_base = format!("{}/{}", config.remote, config.base);
-    let output = Command::new("git")
-        .current_dir(repo_path)
-        .arg("rebase")
-        .arg(&remote_base)
-        .output()
-        .context("Failed to rebase")?;
+    // Find out how GitHub merged the PR before trusting the rebase to have dropped it
+    let landed_as = match landed_pr {
+        Some(pr_number) => {
+            let landed_as = landed_merge_commit(git_repo, config, pr_number)
+                .await
+                .map(|merge_oid| classify_landing(git_repo, landed_commit_oid, merge_oid))
+                .unwrap_or(LandedAs::Unknown);
+            report_landing(landed_as, pr_number, base);
+            landed_as
+        }
+        None => LandedAs::Unknown,
+    };
 
-    if !output.status.success() {
-        let stderr = String::from_utf8_lossy(&output.stderr);
-        let stdout = String::from_utf8_lossy(&output.stdout);
+    // Snapshot the stack so the rebase can be rolled back with `git stk restore`
+    let snapshot = backup::create_snapshot(
+        git_repo,
+        &config.notes_ref,
+        "rebase after landing",
+        config.keep_snapshots,
+    )?;
+    println!(
+        "\n💾 Saved snapshot {} ({})",
+        style(snapshot.id).cyan(),
+        style(format!("{:.7}", snapshot.head)).yellow()
+    );
 
-        eprintln!("{} Rebase failed!", style("✗").red());
-        eprintln!("\n{}", stderr);
-        eprintln!("{}", stdout);
-
-        eprintln!("\n{} The rebase encountered conflicts or errors.", style("💡").yellow());
-        eprintln!("  You have a few options:");
-        eprintln!("  1. Resolve conflicts and continue:");
-        eprintln!("     git rebase --continue");
-        eprintln!("     git stk export");
-        eprintln!("  2. Abort the rebase:");
-        eprintln!("     git rebase --abort");
-        eprintln!("  3. Skip the problematic commit (if it's already merged):");
-        eprintln!("     git rebase --skip");
+    // Rebase current branch on top of the updated base
+    println!("\n🔄 Rebasing {} on {}...", current_branch, base);
 
-        return Err(anyhow::anyhow!("Rebase failed - see guidance above"));
+    if config.jj {
+        jj_rebase(repo_path, config, snapshot.id)?;
+    } else {
+        git_rebase(repo_path, config, snapshot.id)?;
     }
 
     println!("  {} Rebased successfully", style("✓").green());
 
     // Check if the bottom commit changed after rebase (indicates successful landing)
-    let gh_client = client::create_client()?;
+    let gh_client = client::create_configured_client(config)?;
     let stack_after = discover_stack(git_repo, config, &gh_client).await?;
-    let bottom_changed = stack_after.is_empty() ||
-                         (stack_after.entries.first().map(|e| e.oid) != Some(landed_commit_oid));
+    let bottom_changed = stack_after.is_empty()
+        || (stack_after.entries.first().map(|e| e.oid) != Some(landed_commit_oid));
+    // A squash or rebase merge leaves a copy of the landed change on the base; if the
+    // rebase didn't recognize it, the change is still in the stack under a new SHA
+    let still_in_stack = stack_after
+        .entries
+        .iter()
+        .any(|e| patch_id::commits_equivalent(git_repo, e.oid, landed_commit_oid).unwrap_or(false));
 
-    if bottom_changed {
+    if !bottom_changed {
+        println!(
+            "\n{} Bottom commit unchanged - skipping cleanup (already ran?)",
+            style("ℹ").blue()
+        );
+    } else if still_in_stack {
+        eprintln!(
+            "\n{} The landed change is still in the stack after rebasing - keeping the note for {:.7}",
+            style("⚠").yellow(),
+            landed_commit_oid
+        );
+        eprintln!(
+            "  Drop it with 'git rebase -i {}/{}' if it's already on {}",
+            config.remote, base, base
+        );
+    } else if let LandedAs::Mismatch(merge_oid) = landed_as {
+        eprintln!(
+            "\n{} {:.7} on {} doesn't carry the change of {:.7} - keeping its note",
+            style("⚠").yellow(),
+            merge_oid,
+            base,
+            landed_commit_oid
+        );
+    } else {
         // The landed commit is no longer at the bottom - it was successfully merged and rebased away
         // Now we can clean up the metadata
 
         // Get metadata for the landed commit to find its PR number
-        let metadata = notes::read_note(git_repo, landed_commit_oid, &config.notes_ref).ok().flatten();
+        let metadata = notes::read_note(git_repo, landed_commit_oid, &config.notes_ref)
+            .ok()
+            .flatten();
 
         // Clean up PR description to remove stale stack callout
-        if let Some(meta) = metadata {
-            if let Some(pr_number) = meta.pr {
-                println!("\n🔄 Cleaning up PR #{} description...", pr_number);
-
-                // Helper to clean up PR description
-                let cleanup_result = async {
-                    let remote = git_repo.find_remote(&config.remote)
-                        .context("Failed to find remote")?;
-                    let remote_url = remote.url()
-                        .context("Remote URL is not valid UTF-8")?;
-                    let (owner, repo_name) = client::parse_repo_from_url(remote_url)?;
-                    let gh_client = client::create_client()?;
-
-                    // Get current PR body
-                    let pr_info = queries::get_pr(&gh_client, &owner, &repo_name, pr_number).await?;
-
-                    // Strip the callout
-                    let clean_body = callout::strip_callout(&pr_info.body);
-
-                    // Always update PR with cleaned body (even if empty, to handle callout-only descriptions)
-                    mutations::update_pull_request(
-                        &gh_client,
-                        &owner,
-                        &repo_name,
-                        pr_number,
-                        None,
-                        None,
-                        Some(&clean_body),
-                    ).await?;
-
-                    Ok::<_, anyhow::Error>(())
-                };
-
-                match cleanup_result.await {
-                    Ok(_) => println!("  {} Removed stack callout from PR description", style("✓").green()),
-                    Err(e) => eprintln!("  {} Warning: Failed to update PR description: {}", style("⚠").yellow(), e),
-                }
+        if let Some(meta) = metadata
+            && let Some(pr_number) = meta.pr
+        {
+            println!("\n🔄 Cleaning up PR #{} description...", pr_number);
+
+            // Helper to clean up PR description
+            let cleanup_result = async {
+                let remote = git_repo
+                    .find_remote(&config.remote)
+                    .context("Failed to find remote")?;
+                let remote_url = remote.url().context("Remote URL is not valid UTF-8")?;
+                let (owner, repo_name) = client::parse_repo_from_url(remote_url)?;
+                let gh_client = client::create_configured_client(config)?;
+
+                // Get current PR body
+                let pr_info = queries::get_pr(&gh_client, &owner, &repo_name, pr_number).await?;
+
+                // Strip the callout
+                let clean_body = callout::strip_callout(&pr_info.body);
+
+                // Always update PR with cleaned body (even if empty, to handle callout-only descriptions)
+                mutations::update_pull_request(
+                    &gh_client,
+                    &owner,
+                    &repo_name,
+                    pr_number,
+                    None,
+                    None,
+                    Some(&clean_body),
+                )
+                .await?;
+
+                Ok::<_, anyhow::Error>(())
+            };
+
+            match cleanup_result.await {
+                Ok(_) => println!(
+                    "  {} Removed stack callout from PR description",
+                    style("✓").green()
+                ),
+                Err(e) => eprintln!(
+                    "  {} Warning: Failed to update PR description: {}",
+                    style("⚠").yellow(),
+                    e
+                ),
             }
         }
 
+        if let Some(pr_number) = landed_pr {
+            notify_landed(
+                git_repo,
+                config,
+                &current_branch,
+                landed_commit_oid,
+                pr_number,
+            )
+            .await;
+        }
+
         // Clean up note for the landed commit (it's not in the stack anymore after being merged to main)
         println!("\n🧹 Cleaning up note for landed commit...");
         match notes::remove_note(git_repo, landed_commit_oid, &config.notes_ref) {
@@ -182,13 +245,14 @@ pub async fn run_post_merge_operations(
             Err(e) => {
                 // Don't fail if note doesn't exist (might have been cleaned already)
                 if !e.to_string().contains("not found") {
-                    eprintln!("  {} Warning: Failed to remove note for landed commit: {}",
-                        style("⚠").yellow(), e);
+                    eprintln!(
+                        "  {} Warning: Failed to remove note for landed commit: {}",
+                        style("⚠").yellow(),
+                        e
+                    );
                 }
             }
         }
-    } else {
-        println!("\n{} Bottom commit unchanged - skipping cleanup (already ran?)", style("ℹ").blue());
     }
 
     // Re-export the stack
@@ -202,13 +266,301 @@ pub async fn run_post_merge_operations(
         dry_run: false,
         json: false,
         verbose: false,
+        summary_comment: false,
+        assignees: Vec::new(),
+        milestone: None,
+        project: None,
+        title: None,
+        body: None,
+        no_verify: false,
+        profile: false,
+        push_notes: None,
+        atomic: false,
+        take_ownership: false,
+        close_dropped: false,
+        format: export::ExportFormat::Github,
+        output_dir: None,
+        send_email: false,
+        amend_signoff: false,
+        update_behind: false,
+        keep_branches: false,
+        no_verify_policy: false,
+        fetch: None,
+        commits: None,
     };
 
     if let Err(e) = export::export(export_options).await {
-        eprintln!("\n{} Failed to re-export stack after landing:", style("✗").red());
+        eprintln!(
+            "\n{} Failed to re-export stack after landing:",
+            style("✗").red()
+        );
         eprintln!("  {}", e);
         return Err(e);
     }
 
+    pending_land::clear(git_repo, &current_branch)?;
+
+    hooks::run_hooks(
+        git_repo,
+        config,
+        HookEvent::PostLand,
+        json!({
+            "branch": current_branch,
+            "base": base,
+            "commit": landed_commit_oid.to_string(),
+            "pr": landed_pr,
+        }),
+    )?;
+
+    Ok(())
+}
+
+/// How a landed PR's change reached the base branch
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum LandedAs {
+    /// The stack commit itself is on the base (merge commit or fast-forward)
+    OnBase(Oid),
+    /// The base has a squashed or rebased copy of the stack commit
+    Copy(Oid),
+    /// The PR's merge commit carries a different change
+    Mismatch(Oid),
+    /// GitHub reported no merge commit, or it isn't available locally
+    Unknown,
+}
+
+/// The SHA GitHub created on the base branch when merging `pr_number`
+async fn landed_merge_commit(
+    git_repo: &Repository,
+    config: &Config,
+    pr_number: u64,
+) -> Option<Oid> {
+    let remote = git_repo.find_remote(&config.remote).ok()?;
+    let (owner, repo_name) = client::parse_repo_from_url(remote.url()?).ok()?;
+    let gh_client = client::create_configured_client(config).ok()?;
+    let pr_info = queries::get_pr(&gh_client, &owner, &repo_name, pr_number)
+        .await
+        .ok()?;
+    pr_info
+        .merge_commit_sha
+        .and_then(|sha| repo::resolve_sha(git_repo, &sha))
+}
+
+/// Match the merge commit against the landed stack commit, by merge method:
+/// a merge commit has the stack commit as an ancestor, while squash and rebase
+/// merges create a new commit with the same change
+fn classify_landing(repo: &Repository, landed: Oid, merge_oid: Oid) -> LandedAs {
+    if repo.find_commit(merge_oid).is_err() {
+        return LandedAs::Unknown;
+    }
+    if merge_oid == landed || repo.graph_descendant_of(merge_oid, landed).unwrap_or(false) {
+        return LandedAs::OnBase(merge_oid);
+    }
+    match patch_id::commits_equivalent(repo, landed, merge_oid) {
+        Ok(true) => LandedAs::Copy(merge_oid),
+        Ok(false) => LandedAs::Mismatch(merge_oid),
+        Err(_) => LandedAs::Unknown,
+    }
+}
+
+fn report_landing(landed_as: LandedAs, pr_number: u64, base: &str) {
+    match landed_as {
+        LandedAs::OnBase(oid) => println!(
+            "  {} PR #{} is on {} as {:.7} (merge commit)",
+            style("✓").green(),
+            pr_number,
+            base,
+            oid
+        ),
+        LandedAs::Copy(oid) => println!(
+            "  {} PR #{} is on {} as {:.7} (squash or rebase merge)",
+            style("✓").green(),
+            pr_number,
+            base,
+            oid
+        ),
+        LandedAs::Mismatch(oid) => eprintln!(
+            "  {} PR #{} merged as {:.7}, which doesn't match the stack commit",
+            style("⚠").yellow(),
+            pr_number,
+            oid
+        ),
+        LandedAs::Unknown => println!(
+            "  {} Couldn't find the merge commit of PR #{}, relying on the rebase to drop it",
+            style("ℹ").blue(),
+            pr_number
+        ),
+    }
+}
+
+/// Tell the configured webhook that `pr_number` landed
+async fn notify_landed(
+    git_repo: &Repository,
+    config: &Config,
+    branch: &str,
+    landed_commit_oid: git2::Oid,
+    pr_number: u64,
+) {
+    if config.webhook_url.is_none() {
+        return;
+    }
+    let Some((owner, repo_name)) = git_repo
+        .find_remote(&config.remote)
+        .ok()
+        .and_then(|remote| client::parse_repo_from_url(remote.url()?).ok())
+    else {
+        return;
+    };
+    let title = git_repo
+        .find_commit(landed_commit_oid)
+        .ok()
+        .and_then(|c| c.summary().map(String::from))
+        .unwrap_or_default();
+
+    let pr = PrSummary {
+        number: pr_number,
+        title,
+        url: config.forge.pr_url(&owner, &repo_name, pr_number),
+    };
+    let activity = Activity {
+        repository: &format!("{}/{}", owner, repo_name),
+        branch,
+        author: &webhook::user_name(git_repo),
+    };
+    webhook::notify(config, WebhookEvent::PrLanded, activity, &[pr], false).await;
+}
+
+/// Rebase the current branch onto the updated remote base with git
+pub(crate) fn git_rebase(repo_path: &Path, config: &Config, snapshot_id: i64) -> Result<()> {
+    let remote_base = format!("{}/{}", config.remote, config.pr_base()?);
+    let mut rebase = Command::new("git");
+    rebase.current_dir(repo_path).arg("rebase");
+
+    // Rebasing rewrites commits; re-sign them so signed-commit-required repos keep working
+    if config.gpg_sign {
+        rebase.arg("--gpg-sign");
+    }
+    if config.signoff {
+        rebase.arg("--signoff");
+    }
+
+    let output = rebase
+        .arg(&remote_base)
+        .output()
+        .context("Failed to rebase")?;
+
+    if !output.status.success() {
+        let stderr = String::from_utf8_lossy(&output.stderr);
+        let stdout = String::from_utf8_lossy(&output.stdout);
+
+        eprintln!("{} Rebase failed!", style("✗").red());
+        eprintln!("\n{}", stderr);
+        eprintln!("{}", stdout);
+
+        eprintln!(
+            "\n{} The rebase encountered conflicts or errors.",
+            style("💡").yellow()
+        );
+        eprintln!("  You have a few options:");
+        eprintln!("  1. Resolve conflicts and continue:");
+        eprintln!("     git rebase --continue");
+        eprintln!("     git stk export");
+        eprintln!("  2. Abort the rebase:");
+        eprintln!("     git rebase --abort");
+        eprintln!("  3. Skip the problematic commit (if it's already merged):");
+        eprintln!("     git rebase --skip");
+        eprintln!("  4. Roll back to the pre-rebase snapshot:");
+        eprintln!("     git rebase --abort && git stk restore {}", snapshot_id);
+
+        return Err(anyhow::anyhow!("Rebase failed - see guidance above"));
+    }
+
+    Ok(())
+}
+
+/// Rebase the stack onto the updated remote base with jj, dropping the landed
+/// change once it becomes empty. jj moves the bookmark along.
+pub(crate) fn jj_rebase(repo_path: &Path, config: &Config, snapshot_id: i64) -> Result<()> {
+    let destination = format!("{}@{}", config.pr_base()?, config.remote);
+    let output = Command::new("jj")
+        .current_dir(repo_path)
+        .args(["rebase", "--skip-emptied", "-b", "@", "-d", &destination])
+        .output()
+        .context("Failed to execute jj rebase. Is jj installed?")?;
+
+    if !output.status.success() {
+        let stderr = String::from_utf8_lossy(&output.stderr);
+        eprintln!("{} Rebase failed!", style("✗").red());
+        eprintln!("\n{}", stderr);
+        eprintln!(
+            "\n{} Undo with 'jj undo' (or roll back with 'git stk restore {}'),",
+            style("💡").yellow(),
+            snapshot_id
+        );
+        eprintln!(
+            "  rebase manually with 'jj rebase -b @ -d {}', then run 'git stk export'.",
+            destination
+        );
+        return Err(anyhow::anyhow!("Rebase failed - see guidance above"));
+    }
+
     Ok(())
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn commit(repo: &Repository, parents: &[Oid], path: &str, content: &str) -> Oid {
+        let parent_tree = parents
+            .first()
+            .map(|p| repo.find_commit(*p).unwrap().tree().unwrap());
+        let mut builder = repo.treebuilder(parent_tree.as_ref()).unwrap();
+        builder
+            .insert(path, repo.blob(content.as_bytes()).unwrap(), 0o100644)
+            .unwrap();
+        let tree = repo.find_tree(builder.write().unwrap()).unwrap();
+        let signature = git2::Signature::now("Test", "test@example.com").unwrap();
+        let parents: Vec<git2::Commit> = parents
+            .iter()
+            .map(|p| repo.find_commit(*p).unwrap())
+            .collect();
+        let parent_refs: Vec<&git2::Commit> = parents.iter().collect();
+        repo.commit(None, &signature, &signature, path, &tree, &parent_refs)
+            .unwrap()
+    }
+
+    #[test]
+    fn test_classify_landing_by_merge_method() {
+        let dir = tempfile::tempdir().unwrap();
+        let repo = Repository::init(dir.path()).unwrap();
+
+        let base = commit(&repo, &[], "a.txt", "a\n");
+        let landed = commit(&repo, &[base], "b.txt", "b\n");
+        let moved_base = commit(&repo, &[base], "c.txt", "c\n");
+
+        let merge = commit(&repo, &[moved_base, landed], "b.txt", "b\n");
+        assert_eq!(
+            classify_landing(&repo, landed, merge),
+            LandedAs::OnBase(merge)
+        );
+        assert_eq!(
+            classify_landing(&repo, landed, landed),
+            LandedAs::OnBase(landed)
+        );
+
+        let squashed = commit(&repo, &[moved_base], "b.txt", "b\n");
+        assert_eq!(
+            classify_landing(&repo, landed, squashed),
+            LandedAs::Copy(squashed)
+        );
+
+        let other = commit(&repo, &[moved_base], "b.txt", "other\n");
+        assert_eq!(
+            classify_landing(&repo, landed, other),
+            LandedAs::Mismatch(other)
+        );
+
+        let missing = Oid::from_str("1234567890123456789012345678901234567890").unwrap();
+        assert_eq!(classify_landing(&repo, landed, missing), LandedAs::Unknown);
+    }
+}
@@ -1,3 +1,26 @@
+pub mod autostash;
+pub mod detached_head;
+pub mod hooks;
 pub mod landing;
+pub mod notes_sync;
+pub mod orphans;
+pub mod pending_land;
+pub mod policy;
+pub mod protected_branch;
+pub mod signoff;
+pub mod size_lint;
+pub mod subject_check;
+pub mod submodule_check;
+pub mod test_run;
+pub mod update_behind;
+pub mod webhook;
 
+pub use detached_head::ensure_on_branch;
+pub use hooks::{HookEvent, run_hooks};
 pub use landing::run_post_merge_operations;
+pub use policy::ensure_policy;
+pub use protected_branch::ensure_not_protected;
+pub use signoff::ensure_signed_off;
+pub use size_lint::check_entry_sizes;
+pub use subject_check::ensure_subjects_match;
+pub use submodule_check::check_submodule_pointers;
@@ -0,0 +1,57 @@
+use crate::gh::client::Client;
+use crate::gh::queries;
+use crate::git::notes;
+use crate::model::{Config, PushHooks, PushNotes};
+use anyhow::Result;
+use console::style;
+use git2::Repository;
+
+/// Push the notes ref to the remote, to `<notesRef>/<login>` with `git-stk.notesPerUser`
+pub async fn push_notes(
+    git_repo: &Repository,
+    config: &Config,
+    gh_client: &Client,
+    no_verify: bool,
+) -> Result<()> {
+    let login = if config.notes_per_user {
+        Some(queries::get_viewer(gh_client).await?)
+    } else {
+        None
+    };
+    let remote_ref = notes::remote_notes_ref(&config.notes_ref, login.as_deref());
+
+    notes::push_notes(
+        git_repo,
+        &config.remote,
+        &config.notes_ref,
+        &remote_ref,
+        no_verify,
+        &config.transport,
+    )
+}
+
+/// Push notes after a command changed them locally, if `git-stk.pushNotes` is `always`
+pub async fn push_notes_if_always(
+    git_repo: &Repository,
+    config: &Config,
+    gh_client: &Client,
+) -> Result<()> {
+    if config.push_notes != PushNotes::Always {
+        return Ok(());
+    }
+
+    println!("☁️  Pushing metadata...");
+    match push_notes(
+        git_repo,
+        config,
+        gh_client,
+        config.push_hooks != PushHooks::Each,
+    )
+    .await
+    {
+        Ok(()) => println!("   {} Done", style("✓").green()),
+        Err(e) => eprintln!("   ⚠ Failed: {}", e),
+    }
+
+    Ok(())
+}
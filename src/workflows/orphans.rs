@@ -0,0 +1,83 @@
+use crate::gh::client::Client;
+use crate::gh::mutations;
+use crate::git::{refs, slots};
+use crate::model::{Config, OrphanedPr, PushHooks};
+use anyhow::Result;
+use git2::Repository;
+
+/// Close orphaned PRs of `branch` with a comment saying why, then delete the
+/// slot branches of the closed ones and release their slots. A PR that can't
+/// be closed keeps its branch. Returns the closed PRs and a warning per failure.
+#[allow(clippy::too_many_arguments)]
+pub async fn close_orphaned_prs<'a>(
+    git_repo: &Repository,
+    config: &Config,
+    gh_client: &Client,
+    owner: &str,
+    repo_name: &str,
+    branch: &str,
+    prs: &'a [OrphanedPr],
+    quiet: bool,
+) -> Result<(Vec<&'a OrphanedPr>, Vec<String>)> {
+    let mut warnings = Vec::new();
+    let mut closed = Vec::new();
+    let comment = format!(
+        "This PR is being closed because its commit is no longer part of the `{}` stack.",
+        branch
+    );
+    for pr in prs {
+        if let Err(e) =
+            mutations::add_pr_comment(gh_client, owner, repo_name, pr.pr_number, &comment).await
+        {
+            warnings.push(format!("Failed to comment on PR #{}: {}", pr.pr_number, e));
+        }
+        match mutations::close_pull_request(gh_client, owner, repo_name, pr.pr_number).await {
+            Ok(()) => {
+                if !quiet {
+                    println!("   ✓ Closed #{}", pr.pr_number);
+                }
+                closed.push(pr);
+            }
+            Err(e) => warnings.push(format!(
+                "Failed to close PR #{}, keeping its branch: {}",
+                pr.pr_number, e
+            )),
+        }
+    }
+    if closed.is_empty() {
+        return Ok((closed, warnings));
+    }
+
+    let refspecs: Vec<String> = closed
+        .iter()
+        .map(|pr| format!(":refs/heads/{}", pr.head_ref))
+        .collect();
+    let mut capabilities = refs::RemoteCapabilities::load(git_repo, &config.remote);
+    let result = refs::push_refs(
+        git_repo,
+        &config.remote,
+        &refspecs,
+        &mut capabilities,
+        config.push_hooks != PushHooks::Each,
+        config.max_refs_per_push,
+        &config.transport,
+        &mut |_| {},
+    )?;
+    match result.error {
+        None if !quiet => println!(
+            "   ✓ Deleted {} slot branch{}",
+            closed.len(),
+            if closed.len() == 1 { "" } else { "es" }
+        ),
+        None => {}
+        Some(e) => warnings.push(format!("Failed to delete slot branches: {}", e.trim_end())),
+    }
+
+    let mut slot_cache = slots::SlotCache::load(git_repo)?;
+    for pr in &closed {
+        slot_cache.release_slot(branch, &pr.slot);
+    }
+    slot_cache.save(git_repo)?;
+
+    Ok((closed, warnings))
+}
@@ -0,0 +1,120 @@
+use anyhow::{Context, Result};
+use git2::Repository;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::fs;
+
+use crate::model::Config;
+
+/// A merge started with `land --skip-wait` that `landed` hasn't finished yet
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct PendingLand {
+    pub pr: u64,
+    /// The stack commit being landed
+    pub commit: String,
+    /// When the merge was initiated (seconds since Unix epoch)
+    pub started_at: i64,
+}
+
+/// Pending landings by branch
+#[derive(Debug, Clone, Serialize, Deserialize, Default)]
+pub struct PendingLands {
+    lands: HashMap<String, PendingLand>,
+}
+
+impl PendingLands {
+    /// Load the pending landings from disk
+    pub fn load(repo: &Repository) -> Result<Self> {
+        let path = Config::pending_lands_path(repo)?;
+
+        if !path.exists() {
+            return Ok(Self::default());
+        }
+
+        let contents = fs::read_to_string(&path)
+            .with_context(|| format!("Failed to read pending landings from {:?}", path))?;
+
+        serde_json::from_str(&contents).context("Failed to parse pending landings JSON")
+    }
+
+    /// Save the pending landings to disk
+    pub fn save(&self, repo: &Repository) -> Result<()> {
+        let path = Config::pending_lands_path(repo)?;
+
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent)
+                .with_context(|| format!("Failed to create directory {:?}", parent))?;
+        }
+
+        let json =
+            serde_json::to_string_pretty(self).context("Failed to serialize pending landings")?;
+
+        fs::write(&path, json)
+            .with_context(|| format!("Failed to write pending landings to {:?}", path))?;
+
+        Ok(())
+    }
+
+    pub fn get(&self, branch: &str) -> Option<&PendingLand> {
+        self.lands.get(branch)
+    }
+
+    pub fn insert(&mut self, branch: &str, land: PendingLand) {
+        self.lands.insert(branch.to_string(), land);
+    }
+
+    /// Forget the pending landing of `branch`; returns whether there was one
+    pub fn remove(&mut self, branch: &str) -> bool {
+        self.lands.remove(branch).is_some()
+    }
+}
+
+/// Record that `branch` is landing `pr` (commit `commit`)
+pub fn record(repo: &Repository, branch: &str, pr: u64, commit: git2::Oid) -> Result<()> {
+    let mut lands = PendingLands::load(repo)?;
+    lands.insert(
+        branch,
+        PendingLand {
+            pr,
+            commit: commit.to_string(),
+            started_at: std::time::SystemTime::now()
+                .duration_since(std::time::UNIX_EPOCH)
+                .map(|d| d.as_secs() as i64)
+                .unwrap_or_default(),
+        },
+    );
+    lands.save(repo)
+}
+
+/// Forget the pending landing of `branch`, if any
+pub fn clear(repo: &Repository, branch: &str) -> Result<()> {
+    let mut lands = PendingLands::load(repo)?;
+    if lands.remove(branch) {
+        lands.save(repo)?;
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_record_and_clear() {
+        let dir = tempfile::tempdir().unwrap();
+        let repo = Repository::init(dir.path()).unwrap();
+        let oid = git2::Oid::from_str("1234567890abcdef1234567890abcdef12345678").unwrap();
+
+        record(&repo, "feat", 12, oid).unwrap();
+        let lands = PendingLands::load(&repo).unwrap();
+        let land = lands.get("feat").unwrap();
+        assert_eq!(
+            (land.pr, land.commit.as_str()),
+            (12, "1234567890abcdef1234567890abcdef12345678")
+        );
+        assert!(lands.get("other").is_none());
+
+        clear(&repo, "feat").unwrap();
+        assert!(PendingLands::load(&repo).unwrap().get("feat").is_none());
+    }
+}
@@ -0,0 +1,176 @@
+use crate::gh::client::Client;
+use crate::gh::{mutations, queries};
+use crate::git::refs;
+use crate::model::{Config, Stack, StackPolicy};
+use anyhow::{Result, bail};
+use console::style;
+
+/// Refuse to export a stack that breaks the organization's `[policy]`
+/// (`git-stk.policyFile` or `.git-stk.toml`), listing every violation
+pub fn ensure_policy(config: &Config, stack: &Stack, draft: bool, push_only: bool) -> Result<()> {
+    let Some(policy) = &config.policy else {
+        return Ok(());
+    };
+
+    let depth = stack.entries.iter().filter(|e| !e.merged_into_main).count();
+    let new_prs = if push_only {
+        0
+    } else {
+        stack
+            .entries
+            .iter()
+            .filter(|e| e.pr_number.is_none() && !e.merged_into_main)
+            .count()
+    };
+    let problems = violations(policy, depth, config.base_branch.as_deref(), new_prs, draft);
+    if problems.is_empty() {
+        return Ok(());
+    }
+
+    eprintln!("{} The stack breaks the stack policy:", style("✗").red());
+    for problem in &problems {
+        eprintln!("  - {}", problem);
+    }
+    bail!(
+        "{} policy violation{}. Fix them, or skip the check with --no-verify-policy.",
+        problems.len(),
+        if problems.len() == 1 { "" } else { "s" }
+    )
+}
+
+/// Rules of `policy` the stack breaks, as human-readable descriptions.
+/// Required labels aren't checked: export adds them (`add_required_labels`).
+fn violations(
+    policy: &StackPolicy,
+    depth: usize,
+    base_branch: Option<&str>,
+    new_prs: usize,
+    draft: bool,
+) -> Vec<String> {
+    let mut problems = Vec::new();
+
+    if let Some(max) = policy.max_depth
+        && depth > max
+    {
+        problems.push(format!(
+            "{} entries, at most {} allowed: land the bottom PRs or split the stack",
+            depth, max
+        ));
+    }
+    if let Some(base) = base_branch
+        && let Some(pattern) = policy
+            .forbidden_bases
+            .iter()
+            .find(|p| refs::glob_matches(p, base))
+    {
+        problems.push(format!(
+            "Stacks may not target {} (forbidden by '{}'): change the base with 'git stk set base'",
+            base, pattern
+        ));
+    }
+    if policy.draft_first && !draft && new_prs > 0 {
+        problems.push(format!(
+            "New PRs must start as drafts: export with --draft ({} PR{} to create)",
+            new_prs,
+            if new_prs == 1 { "" } else { "s" }
+        ));
+    }
+
+    problems
+}
+
+/// Add the policy's required labels to existing PRs that lack them; returns
+/// a warning per PR they couldn't be checked or added on
+pub async fn add_required_labels(
+    config: &Config,
+    gh_client: &Client,
+    owner: &str,
+    repo_name: &str,
+    pr_numbers: &[u64],
+) -> Vec<String> {
+    let Some(policy) = config
+        .policy
+        .as_ref()
+        .filter(|p| !p.required_labels.is_empty())
+    else {
+        return Vec::new();
+    };
+    if pr_numbers.is_empty() {
+        return Vec::new();
+    }
+
+    let labels = match queries::get_pr_labels(gh_client, owner, repo_name, pr_numbers).await {
+        Ok(labels) => labels,
+        Err(e) => return vec![format!("Failed to check the required labels: {}", e)],
+    };
+    let mut warnings = Vec::new();
+    for pr in pr_numbers {
+        let missing = missing_labels(
+            policy,
+            labels.get(pr).map(Vec::as_slice).unwrap_or_default(),
+        );
+        if missing.is_empty() {
+            continue;
+        }
+        if let Err(e) = mutations::add_labels(gh_client, owner, repo_name, *pr, &missing).await {
+            warnings.push(format!(
+                "PR #{} lacks the required label{} {}: {}",
+                pr,
+                if missing.len() == 1 { "" } else { "s" },
+                missing.join(", "),
+                e
+            ));
+        }
+    }
+    warnings
+}
+
+/// Required labels of `policy` that `labels` doesn't have
+fn missing_labels(policy: &StackPolicy, labels: &[String]) -> Vec<String> {
+    policy
+        .required_labels
+        .iter()
+        .filter(|required| !labels.contains(required))
+        .cloned()
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_violations() {
+        let policy = StackPolicy {
+            max_depth: Some(3),
+            required_labels: vec!["stacked".to_string()],
+            draft_first: true,
+            forbidden_bases: vec!["release/*".to_string()],
+        };
+
+        assert!(violations(&policy, 3, Some("main"), 1, true).is_empty());
+        assert_eq!(
+            violations(&policy, 4, Some("release/1.2"), 2, false),
+            vec![
+                "4 entries, at most 3 allowed: land the bottom PRs or split the stack",
+                "Stacks may not target release/1.2 (forbidden by 'release/*'): change the base with 'git stk set base'",
+                "New PRs must start as drafts: export with --draft (2 PRs to create)",
+            ]
+        );
+        // Without PRs to create, draft-first has nothing to check
+        assert!(violations(&policy, 1, Some("main"), 0, false).is_empty());
+    }
+
+    #[test]
+    fn test_missing_labels() {
+        let policy = StackPolicy {
+            required_labels: vec!["stacked".to_string(), "needs-review".to_string()],
+            ..Default::default()
+        };
+        assert_eq!(
+            missing_labels(&policy, &["bug".to_string(), "stacked".to_string()]),
+            vec!["needs-review"]
+        );
+        assert!(missing_labels(&policy, &policy.required_labels).is_empty());
+    }
+}
@@ -0,0 +1,60 @@
+use crate::git::{refs, repo};
+use crate::model::Config;
+use anyhow::{Result, bail};
+use git2::Repository;
+
+/// Refuse to run from the base branch or a `git-stk.protectedBranch` branch, where
+/// an export would find no commits or, with a misconfigured base, push `main--01`-style
+/// branches for commits that are already shared
+pub fn ensure_not_protected(git_repo: &Repository, config: &Config) -> Result<()> {
+    let branch = repo::current_branch(git_repo)?;
+
+    if let Some(reason) = protected_reason(&branch, config) {
+        bail!(
+            "Refusing to export from '{}' ({}). Create a branch for your stack first, e.g. 'git switch -c my-feature'.",
+            branch,
+            reason
+        );
+    }
+
+    Ok(())
+}
+
+/// Why `branch` is protected, if it is
+fn protected_reason(branch: &str, config: &Config) -> Option<String> {
+    if config.base_branch.as_deref() == Some(branch) {
+        return Some("it is the base branch".to_string());
+    }
+
+    config
+        .protected_branches
+        .iter()
+        .find(|pattern| refs::glob_matches(pattern, branch))
+        .map(|pattern| format!("matches git-stk.protectedBranch '{}'", pattern))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::model::config::base_branch_name;
+
+    fn config(base: &str, protected: &[&str]) -> Config {
+        let repo_dir = tempfile::tempdir().unwrap();
+        let repo = Repository::init(repo_dir.path()).unwrap();
+        let mut config = Config::load(&repo).unwrap();
+        config.base = base.to_string();
+        config.base_branch = base_branch_name(&repo, base, "origin");
+        config.protected_branches = protected.iter().map(|p| p.to_string()).collect();
+        config
+    }
+
+    #[test]
+    fn test_protected_reason() {
+        let config = config("origin/main", &["release/*", "develop"]);
+        assert!(protected_reason("main", &config).is_some());
+        assert!(protected_reason("release/1.2", &config).is_some());
+        assert!(protected_reason("develop", &config).is_some());
+        assert!(protected_reason("feature", &config).is_none());
+        assert!(protected_reason("developer", &config).is_none());
+    }
+}
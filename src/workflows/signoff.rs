@@ -0,0 +1,139 @@
+use crate::git::{rewrite, trailers};
+use crate::model::{Config, Entry, Stack};
+use anyhow::{Context, Result, bail};
+use console::style;
+use git2::Repository;
+
+const SIGNED_OFF_BY: &str = "Signed-off-by";
+
+/// Refuse to export commits their author hasn't signed off (`git-stk.requireSignoff`),
+/// catching what a DCO check would reject before anything is pushed
+pub fn ensure_signed_off(git_repo: &Repository, stack: &Stack, config: &Config) -> Result<()> {
+    if !config.require_signoff {
+        return Ok(());
+    }
+
+    let missing = missing_signoffs(git_repo, stack)?;
+    if missing.is_empty() {
+        return Ok(());
+    }
+
+    eprintln!(
+        "{} Commits without a Signed-off-by from their author:",
+        style("✗").red()
+    );
+    for (entry, _) in &missing {
+        eprintln!("  {} {}", style(&entry.short_sha).yellow(), entry.subject);
+    }
+    bail!(
+        "{} commit{} not signed off. Add the trailers with 'git stk export --amend-signoff', or skip the check with --no-verify.",
+        missing.len(),
+        if missing.len() == 1 { " is" } else { "s are" }
+    )
+}
+
+/// Add your `Signed-off-by` to the stack commits that lack their author's,
+/// rewriting the branch like `git commit --amend --signoff` would. Commits
+/// authored by someone else are refused: only their author can sign them off.
+/// Returns how many commits were signed off.
+pub fn amend_signoffs(git_repo: &Repository, config: &Config, stack: &Stack) -> Result<usize> {
+    let missing = missing_signoffs(git_repo, stack)?;
+    if missing.is_empty() {
+        return Ok(0);
+    }
+    if config.jj {
+        bail!(
+            "--amend-signoff would rewrite commits behind jj's back; add the trailers with 'jj describe' instead"
+        );
+    }
+
+    let signature = git_repo
+        .signature()
+        .context("Set user.name and user.email to sign off commits")?;
+    let email = signature.email().unwrap_or_default();
+    let others: Vec<_> = missing
+        .iter()
+        .filter(|(_, author_email)| !author_email.eq_ignore_ascii_case(email))
+        .collect();
+    if !others.is_empty() {
+        eprintln!("{} Commits authored by someone else:", style("✗").red());
+        for (entry, author_email) in &others {
+            eprintln!(
+                "  {} {} ({})",
+                style(&entry.short_sha).yellow(),
+                entry.subject,
+                author_email
+            );
+        }
+        bail!("Only their authors can sign these commits off");
+    }
+
+    let trailer = format!(
+        "{}: {} <{}>",
+        SIGNED_OFF_BY,
+        signature.name().unwrap_or_default(),
+        email
+    );
+    rewrite::rewrite_messages(
+        git_repo,
+        config,
+        stack,
+        "git-stk: add Signed-off-by trailers",
+        |commit, message| {
+            let author_email = commit.author().email().unwrap_or_default().to_string();
+            Ok((!is_signed_off_by(message, &author_email))
+                .then(|| trailers::append_trailer(message, &trailer)))
+        },
+    )
+}
+
+/// Entries whose message has no `Signed-off-by` from the commit author, with the author's email
+fn missing_signoffs<'s>(
+    git_repo: &Repository,
+    stack: &'s Stack,
+) -> Result<Vec<(&'s Entry, String)>> {
+    let mut missing = Vec::new();
+    for entry in &stack.entries {
+        let commit = git_repo
+            .find_commit(entry.oid)
+            .context("Failed to find commit")?;
+        let author_email = commit.author().email().unwrap_or_default().to_string();
+        if !is_signed_off_by(commit.message().unwrap_or_default(), &author_email) {
+            missing.push((entry, author_email));
+        }
+    }
+    Ok(missing)
+}
+
+/// Whether `message` carries a `Signed-off-by` trailer for `email`
+fn is_signed_off_by(message: &str, email: &str) -> bool {
+    trailers::find_trailers(message, SIGNED_OFF_BY)
+        .iter()
+        .any(|value| {
+            value
+                .rsplit_once('<')
+                .and_then(|(_, rest)| rest.strip_suffix('>'))
+                .is_some_and(|address| address.eq_ignore_ascii_case(email))
+        })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_is_signed_off_by() {
+        let message = "feat: add api\n\nBody.\n\nSigned-off-by: Ada Lovelace <Ada@example.com>\n";
+        assert!(is_signed_off_by(message, "ada@example.com"));
+        assert!(!is_signed_off_by(message, "bob@example.com"));
+        // Only the trailer block counts
+        assert!(!is_signed_off_by(
+            "Signed-off-by: Ada <ada@example.com>",
+            "ada@example.com"
+        ));
+        assert!(!is_signed_off_by(
+            "fix\n\nSigned-off-by: ada@example.com",
+            "ada@example.com"
+        ));
+    }
+}
@@ -0,0 +1,120 @@
+use crate::git::diff_stats::{self, DiffStats};
+use crate::model::config::SHARED_CONFIG_FILE;
+use crate::model::{Config, LintMode, SizeLint, Stack};
+use anyhow::{Result, bail};
+use console::style;
+use git2::Repository;
+
+/// Check every entry against the size limits of `.git-stk.toml`, warning about
+/// or (with `mode = "error"`) refusing to export entries too large to review
+pub fn check_entry_sizes(
+    git_repo: &Repository,
+    stack: &Stack,
+    config: &Config,
+    quiet: bool,
+) -> Result<()> {
+    let Some(lint) = &config.size_lint else {
+        return Ok(());
+    };
+
+    let mut offenders = Vec::new();
+    for entry in &stack.entries {
+        let stats = diff_stats::commit_diff_stats(git_repo, entry.oid)?;
+        let problems = violations(&stats, lint);
+        if !problems.is_empty() {
+            offenders.push((entry, problems));
+        }
+    }
+    if offenders.is_empty() {
+        return Ok(());
+    }
+
+    let icon = match lint.mode {
+        LintMode::Warn => style("⚠").yellow(),
+        LintMode::Error => style("✗").red(),
+    };
+    if !quiet || lint.mode == LintMode::Error {
+        eprintln!(
+            "{} {} entr{} exceed{} the size limits in {}:",
+            icon,
+            offenders.len(),
+            if offenders.len() == 1 { "y" } else { "ies" },
+            if offenders.len() == 1 { "s" } else { "" },
+            SHARED_CONFIG_FILE
+        );
+        for (entry, problems) in &offenders {
+            eprintln!(
+                "  {} {} ({})",
+                style(&entry.short_sha).yellow(),
+                entry.subject,
+                problems.join(", ")
+            );
+        }
+    }
+
+    if lint.mode == LintMode::Error {
+        bail!("Split the entries above into smaller commits, or skip the check with --no-verify.");
+    }
+    if !quiet {
+        eprintln!("  Consider splitting them into smaller commits.\n");
+    }
+
+    Ok(())
+}
+
+/// Which limits `stats` breaks, as human-readable descriptions
+fn violations(stats: &DiffStats, lint: &SizeLint) -> Vec<String> {
+    let mut problems = Vec::new();
+    if let Some(max) = lint.max_lines
+        && stats.lines() > max
+    {
+        problems.push(format!("{} changed lines > {}", stats.lines(), max));
+    }
+    if let Some(max) = lint.max_files
+        && stats.files > max
+    {
+        problems.push(format!("{} files > {}", stats.files, max));
+    }
+    problems
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_violations() {
+        let lint = SizeLint {
+            max_lines: Some(400),
+            max_files: Some(10),
+            mode: LintMode::Warn,
+        };
+        let small = DiffStats {
+            files: 3,
+            insertions: 100,
+            deletions: 20,
+        };
+        let large = DiffStats {
+            files: 12,
+            insertions: 380,
+            deletions: 40,
+        };
+
+        assert!(violations(&small, &lint).is_empty());
+        assert_eq!(
+            violations(&large, &lint),
+            vec!["420 changed lines > 400", "12 files > 10"]
+        );
+        assert_eq!(
+            violations(
+                &large,
+                &SizeLint {
+                    max_files: None,
+                    ..lint
+                }
+            )
+            .len(),
+            1
+        );
+    }
+}
@@ -0,0 +1,63 @@
+use crate::model::{Config, Stack};
+use anyhow::{Context, Result, bail};
+use console::style;
+use regex::Regex;
+
+/// Refuse to export commits whose subject doesn't match `git-stk.subjectPattern`,
+/// catching what a CI title check would reject before anything is pushed
+pub fn ensure_subjects_match(stack: &Stack, config: &Config) -> Result<()> {
+    let Some(pattern) = &config.subject_pattern else {
+        return Ok(());
+    };
+    let regex = Regex::new(pattern)
+        .with_context(|| format!("Invalid git-stk.subjectPattern '{}'", pattern))?;
+
+    let subjects: Vec<&str> = stack.entries.iter().map(|e| e.subject.as_str()).collect();
+    let failing = failing_subjects(&regex, &subjects);
+    if failing.is_empty() {
+        return Ok(());
+    }
+
+    eprintln!(
+        "{} Commit subjects not matching {}:",
+        style("✗").red(),
+        style(pattern).cyan()
+    );
+    for index in &failing {
+        let entry = &stack.entries[*index];
+        eprintln!("  {} {}", style(&entry.short_sha).yellow(), entry.subject);
+    }
+    bail!(
+        "{} commit subject{} failed the check. Reword with 'git rebase -i {}', or skip the check with --no-verify.",
+        failing.len(),
+        if failing.len() == 1 { "" } else { "s" },
+        config.base_name()
+    )
+}
+
+/// Indices of the subjects that don't match
+fn failing_subjects(regex: &Regex, subjects: &[&str]) -> Vec<usize> {
+    subjects
+        .iter()
+        .enumerate()
+        .filter(|(_, subject)| !regex.is_match(subject))
+        .map(|(index, _)| index)
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_failing_subjects() {
+        let regex = Regex::new(r"^(feat|fix|chore)(\(.+\))?!?: ").unwrap();
+        let subjects = [
+            "feat(api): add endpoint",
+            "Fix stuff",
+            "chore!: drop node 16",
+            "wip",
+        ];
+        assert_eq!(failing_subjects(&regex, &subjects), vec![1, 3]);
+    }
+}
@@ -0,0 +1,235 @@
+use crate::model::{Entry, Stack};
+use anyhow::{Context, Result};
+use console::style;
+use git2::{Delta, DiffOptions, FileMode, Oid, Repository};
+use tracing::debug;
+
+/// Whether the submodule's remote has a commit a stack entry points at
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum PointerState {
+    /// Reachable from one of the submodule's remote-tracking branches
+    Published,
+    /// Only local, or missing entirely: CI can't check it out
+    Unpublished,
+    /// The submodule isn't checked out here, so there is nothing to check against
+    Unknown,
+}
+
+/// Warn about stack commits that point a submodule at a commit its remote
+/// doesn't have, since CI on those PR branches would fail to check it out
+///
+/// Like `git push --recurse-submodules=check`, this looks at the submodule's
+/// remote-tracking branches, so it doesn't fetch anything. Returns the
+/// warnings, printed unless `quiet` (e.g. for `--json`).
+pub fn check_submodule_pointers(
+    git_repo: &Repository,
+    stack: &Stack,
+    quiet: bool,
+) -> Result<Vec<String>> {
+    let mut unpublished: Vec<(&Entry, String, Oid)> = Vec::new();
+    let mut unknown: Vec<String> = Vec::new();
+
+    for entry in &stack.entries {
+        for (path, commit) in changed_gitlinks(git_repo, entry.oid)? {
+            match pointer_state(git_repo, &path, commit) {
+                PointerState::Published => {}
+                PointerState::Unpublished => unpublished.push((entry, path, commit)),
+                PointerState::Unknown if !unknown.contains(&path) => unknown.push(path),
+                PointerState::Unknown => {}
+            }
+        }
+    }
+
+    let mut warnings: Vec<String> = unpublished
+        .iter()
+        .map(|(entry, path, commit)| {
+            format!(
+                "{} points {} at {:.7}, which isn't on the submodule's remote",
+                entry.short_sha, path, commit
+            )
+        })
+        .collect();
+    if !unknown.is_empty() {
+        warnings.push(format!(
+            "Couldn't check submodule pointers for {} (not checked out)",
+            unknown.join(", ")
+        ));
+    }
+    if quiet {
+        return Ok(warnings);
+    }
+
+    if !unpublished.is_empty() {
+        eprintln!(
+            "{} {} submodule pointer{} not on the submodule's remote; CI will fail to check {}:",
+            style("⚠").yellow(),
+            unpublished.len(),
+            if unpublished.len() == 1 {
+                " is"
+            } else {
+                "s are"
+            },
+            if unpublished.len() == 1 {
+                "it out"
+            } else {
+                "them out"
+            }
+        );
+        for (entry, path, commit) in &unpublished {
+            eprintln!(
+                "  {} {} → {} {}",
+                style(&entry.short_sha).yellow(),
+                path,
+                style(format!("{:.7}", commit)).yellow(),
+                style(format!("({})", entry.subject)).dim()
+            );
+        }
+        eprintln!(
+            "  Push those commits from the submodule first (or fetch there if they're already pushed).\n"
+        );
+    }
+    if !unknown.is_empty() {
+        eprintln!(
+            "{}",
+            style(format!(
+                "Couldn't check submodule pointers for {} (not checked out).\n",
+                unknown.join(", ")
+            ))
+            .dim()
+        );
+    }
+
+    Ok(warnings)
+}
+
+/// Submodule paths whose pointer a commit changes, with their new commit
+fn changed_gitlinks(repo: &Repository, oid: Oid) -> Result<Vec<(String, Oid)>> {
+    let commit = repo.find_commit(oid).context("Failed to find commit")?;
+    let tree = commit.tree().context("Failed to get commit tree")?;
+    let parent_tree = match commit.parent_count() {
+        0 => None,
+        _ => Some(
+            commit
+                .parent(0)
+                .and_then(|p| p.tree())
+                .context("Failed to get parent tree")?,
+        ),
+    };
+
+    // Submodules are ignored by default; diffing trees still reports their pointer changes
+    let mut options = DiffOptions::new();
+    options.ignore_submodules(false);
+    let diff = repo
+        .diff_tree_to_tree(parent_tree.as_ref(), Some(&tree), Some(&mut options))
+        .context("Failed to diff commit")?;
+
+    Ok(diff
+        .deltas()
+        .filter(|d| {
+            matches!(
+                d.status(),
+                Delta::Added | Delta::Modified | Delta::Typechange
+            )
+        })
+        .filter(|d| d.new_file().mode() == FileMode::Commit)
+        .filter_map(|d| {
+            let path = d.new_file().path()?.to_string_lossy().into_owned();
+            Some((path, d.new_file().id()))
+        })
+        .collect())
+}
+
+fn pointer_state(repo: &Repository, path: &str, commit: Oid) -> PointerState {
+    let Ok(submodule_repo) = repo.find_submodule(path).and_then(|s| s.open()) else {
+        return PointerState::Unknown;
+    };
+    if submodule_repo.find_commit(commit).is_err() {
+        return PointerState::Unpublished;
+    }
+
+    let Ok(references) = submodule_repo.references_glob("refs/remotes/*") else {
+        return PointerState::Unknown;
+    };
+    let published = references.flatten().filter_map(|r| r.target()).any(|tip| {
+        tip == commit
+            || submodule_repo
+                .graph_descendant_of(tip, commit)
+                .unwrap_or(false)
+    });
+    debug!(path, %commit, published, "checked submodule pointer");
+
+    if published {
+        PointerState::Published
+    } else {
+        PointerState::Unpublished
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_changed_gitlinks() {
+        let dir = tempfile::tempdir().unwrap();
+        let repo = Repository::init(dir.path()).unwrap();
+        let signature = git2::Signature::now("Ada", "ada@example.com").unwrap();
+        let blob = repo.blob(b"hello\n").unwrap();
+        let (old_pointer, new_pointer) = (
+            Oid::from_bytes(&[1; 20]).unwrap(),
+            Oid::from_bytes(&[2; 20]).unwrap(),
+        );
+
+        let tree = |pointer: Oid| {
+            let mut builder = repo.treebuilder(None).unwrap();
+            builder
+                .insert("README", blob, FileMode::Blob.into())
+                .unwrap();
+            builder
+                .insert("vendor", pointer, FileMode::Commit.into())
+                .unwrap();
+            repo.find_tree(builder.write().unwrap()).unwrap()
+        };
+        let first = repo
+            .commit(
+                None,
+                &signature,
+                &signature,
+                "Add submodule",
+                &tree(old_pointer),
+                &[],
+            )
+            .unwrap();
+        let parent = repo.find_commit(first).unwrap();
+        let second = repo
+            .commit(
+                None,
+                &signature,
+                &signature,
+                "Bump submodule",
+                &tree(new_pointer),
+                &[&parent],
+            )
+            .unwrap();
+        let unchanged = repo
+            .commit(
+                None,
+                &signature,
+                &signature,
+                "No-op",
+                &tree(new_pointer),
+                &[&repo.find_commit(second).unwrap()],
+            )
+            .unwrap();
+
+        assert_eq!(
+            changed_gitlinks(&repo, first).unwrap(),
+            vec![("vendor".to_string(), old_pointer)]
+        );
+        assert_eq!(
+            changed_gitlinks(&repo, second).unwrap(),
+            vec![("vendor".to_string(), new_pointer)]
+        );
+        assert!(changed_gitlinks(&repo, unchanged).unwrap().is_empty());
+    }
+}
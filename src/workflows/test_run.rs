@@ -0,0 +1,215 @@
+use anyhow::{Context, Result, anyhow};
+use git2::{Oid, Repository};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::fs;
+use std::process::Command;
+
+use crate::git::worktree::TempWorktree;
+use crate::model::Config;
+
+/// Outcome of running a command at a commit
+#[derive(Debug, Clone)]
+pub struct TestOutcome {
+    /// Whether the command exited successfully
+    pub passed: bool,
+    /// Whether the result came from the cache
+    pub cached: bool,
+    /// Combined stdout/stderr (empty for cached results)
+    pub output: String,
+}
+
+/// Test results cache - maps command -> tree OID -> passed
+///
+/// Results are keyed by tree rather than commit so that rewording or rebasing
+/// a commit without changing its content reuses the previous result.
+#[derive(Debug, Clone, Serialize, Deserialize, Default)]
+pub struct TestCache {
+    results: HashMap<String, HashMap<String, bool>>,
+}
+
+impl TestCache {
+    /// Load the test cache from disk
+    pub fn load(repo: &Repository) -> Result<Self> {
+        let path = Config::test_cache_path(repo)?;
+
+        if !path.exists() {
+            return Ok(Self::default());
+        }
+
+        let contents = fs::read_to_string(&path)
+            .with_context(|| format!("Failed to read test cache from {:?}", path))?;
+
+        serde_json::from_str(&contents).context("Failed to parse test cache JSON")
+    }
+
+    /// Save the test cache to disk
+    pub fn save(&self, repo: &Repository) -> Result<()> {
+        let path = Config::test_cache_path(repo)?;
+
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent)
+                .with_context(|| format!("Failed to create directory {:?}", parent))?;
+        }
+
+        let json = serde_json::to_string_pretty(self).context("Failed to serialize test cache")?;
+
+        fs::write(&path, json)
+            .with_context(|| format!("Failed to write test cache to {:?}", path))?;
+
+        Ok(())
+    }
+
+    /// Get a cached result for a command at a tree
+    pub fn get(&self, command: &str, tree: Oid) -> Option<bool> {
+        self.results
+            .get(command)
+            .and_then(|trees| trees.get(&tree.to_string()))
+            .copied()
+    }
+
+    /// Record a result for a command at a tree
+    pub fn insert(&mut self, command: &str, tree: Oid, passed: bool) {
+        self.results
+            .entry(command.to_string())
+            .or_default()
+            .insert(tree.to_string(), passed);
+    }
+}
+
+/// The shell command for `git stk test <command>`: a single argument is a
+/// shell command as is (`"make && make check"`); several are a command line
+/// whose arguments are quoted (`grep "a b" f` greps for `a b`)
+pub fn shell_command(args: &[String]) -> Result<String> {
+    match args {
+        [command] => Ok(command.clone()),
+        _ => shlex::try_join(args.iter().map(String::as_str))
+            .map_err(|e| anyhow!("Can't pass the command to the shell: {}", e)),
+    }
+}
+
+/// Run a shell command at `oid` in the worktree, consulting and updating the cache
+pub fn run_command_at(
+    repo: &Repository,
+    worktree: &TempWorktree,
+    cache: &mut TestCache,
+    oid: Oid,
+    command: &str,
+    use_cache: bool,
+) -> Result<TestOutcome> {
+    let tree = repo
+        .find_commit(oid)
+        .context("Failed to find commit")?
+        .tree_id();
+
+    if use_cache && let Some(passed) = cache.get(command, tree) {
+        return Ok(TestOutcome {
+            passed,
+            cached: true,
+            output: String::new(),
+        });
+    }
+
+    worktree.checkout(oid)?;
+
+    let output = Command::new("sh")
+        .current_dir(worktree.path())
+        .arg("-c")
+        .arg(command)
+        .output()
+        .with_context(|| format!("Failed to execute '{}'", command))?;
+
+    let passed = output.status.success();
+    cache.insert(command, tree, passed);
+
+    let mut combined = String::from_utf8_lossy(&output.stdout).to_string();
+    combined.push_str(&String::from_utf8_lossy(&output.stderr));
+
+    Ok(TestOutcome {
+        passed,
+        cached: false,
+        output: combined,
+    })
+}
+
+/// Binary-search `len` entries for the first one where `passes` returns false.
+///
+/// Assumes the base (before entry 0) is good and the breakage persists once
+/// introduced. Returns None if the last entry passes.
+pub fn bisect_first_failure<F>(len: usize, mut passes: F) -> Result<Option<usize>>
+where
+    F: FnMut(usize) -> Result<bool>,
+{
+    if len == 0 || passes(len - 1)? {
+        return Ok(None);
+    }
+
+    // Invariant: entries before `good_end` pass, entry `bad` fails
+    let mut good_end = 0;
+    let mut bad = len - 1;
+    while good_end < bad {
+        let mid = good_end + (bad - good_end) / 2;
+        if passes(mid)? {
+            good_end = mid + 1;
+        } else {
+            bad = mid;
+        }
+    }
+
+    Ok(Some(bad))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_bisect_first_failure() {
+        for len in 1..10 {
+            for first_bad in 0..len {
+                let mut probes = 0;
+                let result = bisect_first_failure(len, |i| {
+                    probes += 1;
+                    Ok(i < first_bad)
+                })
+                .unwrap();
+                assert_eq!(result, Some(first_bad));
+                assert!(probes <= 1 + (len as f64).log2().ceil() as usize);
+            }
+            assert_eq!(bisect_first_failure(len, |_| Ok(true)).unwrap(), None);
+        }
+        assert_eq!(bisect_first_failure(0, |_| Ok(false)).unwrap(), None);
+    }
+
+    #[test]
+    fn test_shell_command() {
+        let args = |args: &[&str]| args.iter().map(|a| a.to_string()).collect::<Vec<_>>();
+        assert_eq!(
+            shell_command(&args(&["cargo", "test"])).unwrap(),
+            "cargo test"
+        );
+        assert_eq!(
+            shell_command(&args(&["grep", "a b", "f"])).unwrap(),
+            "grep 'a b' f"
+        );
+        assert_eq!(
+            shell_command(&args(&["make && make check"])).unwrap(),
+            "make && make check"
+        );
+        assert!(shell_command(&args(&["echo", "nul\0"])).is_err());
+    }
+
+    #[test]
+    fn test_cache_roundtrip() {
+        let mut cache = TestCache::default();
+        let tree = Oid::from_str("1234567890abcdef1234567890abcdef12345678").unwrap();
+
+        assert_eq!(cache.get("cargo test", tree), None);
+
+        cache.insert("cargo test", tree, true);
+        cache.insert("cargo clippy", tree, false);
+
+        assert_eq!(cache.get("cargo test", tree), Some(true));
+        assert_eq!(cache.get("cargo clippy", tree), Some(false));
+    }
+}
@@ -0,0 +1,182 @@
+//! `export --update-behind`: when the base branch moved on beneath the stack
+//! (GitHub's "This branch is out-of-date with the base branch"), rebase the
+//! stack onto it so the export pushes the restacked entries.
+
+use crate::git::{backup, refs, repo};
+use crate::model::{Config, Stack};
+use crate::workflows::{autostash, landing};
+use anyhow::{Context, Result, bail};
+use console::style;
+use git2::Repository;
+
+/// Restack onto the remote base if the stack or one of its PRs is behind it;
+/// returns whether commits were rewritten (the stack must then be rediscovered)
+///
+/// PRs higher up that GitHub reports as behind their slot-branch base only need
+/// the re-push that export does for every entry whose branch differs.
+pub fn restack_if_behind(
+    git_repo: &Repository,
+    config: &Config,
+    stack: &Stack,
+    quiet: bool,
+) -> Result<bool> {
+    let base = config.pr_base()?;
+    let remote_base = format!("{}/{}", config.remote, base);
+    refs::fetch_refspecs(
+        git_repo,
+        &config.remote,
+        &[format!("+refs/heads/{}:refs/remotes/{}", base, remote_base)],
+        &config.transport,
+    )?;
+    let base_tip = git_repo
+        .refname_to_id(&format!("refs/remotes/{}", remote_base))
+        .with_context(|| format!("Failed to resolve {}", remote_base))?;
+    fast_forward_local_base(git_repo, base, base_tip)?;
+
+    let Some(top) = stack.entries.last() else {
+        return Ok(false);
+    };
+    let stack_behind = git_repo.merge_base(top.oid, base_tip)? != base_tip;
+
+    let behind_prs: Vec<String> = stack
+        .entries
+        .iter()
+        .filter(|e| e.behind_base && !e.merged_into_main)
+        .filter_map(|e| e.pr_number.map(|pr| format!("#{}", pr)))
+        .collect();
+
+    if !stack_behind {
+        if !quiet {
+            if behind_prs.is_empty() {
+                println!(
+                    "{} Stack is up to date with {}",
+                    style("✓").green(),
+                    remote_base
+                );
+            } else {
+                println!(
+                    "🔄 {} behind {} base; re-pushing",
+                    behind_prs.join(", "),
+                    if behind_prs.len() == 1 {
+                        "its"
+                    } else {
+                        "their"
+                    }
+                );
+            }
+        }
+        return Ok(false);
+    }
+
+    if repo::has_uncommitted_changes(git_repo)? && !config.autostash {
+        bail!(
+            "You have uncommitted changes. Commit or stash them before --update-behind rebases the stack, \
+             or set 'git config git-stk.autostash true'."
+        );
+    }
+    let repo_path = git_repo
+        .workdir()
+        .context("Repository has no working directory")?;
+
+    if !quiet {
+        println!(
+            "🔄 {} moved on beneath the stack; rebasing {} entr{} onto {}...",
+            remote_base,
+            stack.len(),
+            if stack.len() == 1 { "y" } else { "ies" },
+            style(format!("{:.7}", base_tip)).yellow()
+        );
+    }
+    let snapshot = backup::create_snapshot(
+        git_repo,
+        &config.notes_ref,
+        "rebase onto updated base",
+        config.keep_snapshots,
+    )?;
+    let stash = autostash::stash_if_dirty(git_repo, config, "rebasing the stack")?;
+    let result = if config.jj {
+        landing::jj_rebase(repo_path, config, snapshot.id)
+    } else {
+        landing::git_rebase(repo_path, config, snapshot.id)
+    };
+    autostash::restore(git_repo, stash, result.is_ok());
+    result?;
+
+    if !quiet {
+        println!(
+            "   ✓ Rebased (undo with {})",
+            style(format!("git stk restore {}", snapshot.id)).cyan()
+        );
+    }
+    Ok(true)
+}
+
+/// Move the local base branch up to the fetched tip, as landing does, since
+/// discovery measures the stack from it; a base with local commits is left alone
+fn fast_forward_local_base(git_repo: &Repository, base: &str, tip: git2::Oid) -> Result<()> {
+    let name = format!("refs/heads/{}", base);
+    let Ok(current) = git_repo.refname_to_id(&name) else {
+        return Ok(());
+    };
+    if current != tip && git_repo.graph_descendant_of(tip, current)? {
+        git_repo
+            .reference(
+                &name,
+                tip,
+                true,
+                "git-stk: fast-forward base for --update-behind",
+            )
+            .with_context(|| format!("Failed to update {}", base))?;
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::git::testing::git;
+    use crate::stack::discover_local_stack;
+
+    #[test]
+    fn test_restack_if_behind_rebases_onto_remote_base() {
+        let dir = tempfile::tempdir().unwrap();
+        let origin = dir.path().join("origin");
+        std::fs::create_dir(&origin).unwrap();
+        git(&origin, &["init", "-q", "-b", "main"]);
+        git(&origin, &["commit", "-q", "--allow-empty", "-m", "base 0"]);
+
+        let url = format!("file://{}", origin.display());
+        git(dir.path(), &["clone", "-q", &url, "clone"]);
+        let clone_path = dir.path().join("clone");
+        git(&clone_path, &["config", "user.name", "Ada"]);
+        git(&clone_path, &["config", "user.email", "ada@example.com"]);
+        git(&clone_path, &["checkout", "-q", "-b", "feat"]);
+        for i in 0..2 {
+            git(
+                &clone_path,
+                &[
+                    "commit",
+                    "-q",
+                    "--allow-empty",
+                    "-m",
+                    &format!("stack {}", i),
+                ],
+            );
+        }
+        git(&origin, &["commit", "-q", "--allow-empty", "-m", "base 1"]);
+
+        let clone = Repository::open(&clone_path).unwrap();
+        let config = Config::load(&clone).unwrap();
+        let stack = discover_local_stack(&clone, &config).unwrap();
+        assert_eq!(stack.len(), 2);
+
+        assert!(restack_if_behind(&clone, &config, &stack, true).unwrap());
+        let base_tip = clone.refname_to_id("refs/remotes/origin/main").unwrap();
+        let head = clone.head().unwrap().target().unwrap();
+        assert_eq!(clone.merge_base(head, base_tip).unwrap(), base_tip);
+
+        let stack = discover_local_stack(&clone, &config).unwrap();
+        assert_eq!(stack.len(), 2);
+        assert!(!restack_if_behind(&clone, &config, &stack, true).unwrap());
+    }
+}
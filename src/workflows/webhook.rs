@@ -0,0 +1,226 @@
+//! Stack activity posted to `git-stk.webhookUrl`. The payload carries a `text`
+//! summary, so Slack incoming webhooks can take it as is, plus structured
+//! fields for other consumers.
+
+use crate::gh::proxy;
+use crate::git::issue_keys;
+use crate::model::Config;
+use anyhow::{Context, Result, bail};
+use console::style;
+use git2::Repository;
+use http::header::{CONTENT_TYPE, USER_AGENT};
+use http::{Request, Uri};
+use hyper_util::client::legacy::Client;
+use hyper_util::rt::TokioExecutor;
+use serde_json::json;
+use std::time::Duration;
+use tracing::debug;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum WebhookEvent {
+    /// An export created PRs
+    PrsCreated,
+    /// A PR was merged and the stack rebased on top of it
+    PrLanded,
+}
+
+impl WebhookEvent {
+    fn name(self) -> &'static str {
+        match self {
+            Self::PrsCreated => "prs-created",
+            Self::PrLanded => "pr-landed",
+        }
+    }
+}
+
+/// A PR mentioned in a notification
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PrSummary {
+    pub number: u64,
+    pub title: String,
+    pub url: String,
+}
+
+/// Where the activity happened
+#[derive(Debug, Clone, Copy)]
+pub struct Activity<'a> {
+    /// `owner/repo`
+    pub repository: &'a str,
+    pub branch: &'a str,
+    /// Who ran git-stk (`user.name`)
+    pub author: &'a str,
+}
+
+/// Name shown as the author of notifications: `user.name`, or "Someone"
+pub fn user_name(repo: &Repository) -> String {
+    repo.config()
+        .and_then(|c| c.get_string("user.name"))
+        .unwrap_or_else(|_| "Someone".to_string())
+}
+
+/// Post a notification if `git-stk.webhookUrl` is set. Failures only warn:
+/// the PRs exist either way.
+pub async fn notify(
+    config: &Config,
+    event: WebhookEvent,
+    activity: Activity<'_>,
+    prs: &[PrSummary],
+    quiet: bool,
+) {
+    let Some(url) = &config.webhook_url else {
+        return;
+    };
+    if prs.is_empty() {
+        return;
+    }
+
+    let body = payload(event, activity, prs).to_string();
+    if let Err(e) = post(config, url, body).await
+        && !quiet
+    {
+        eprintln!(
+            "   {} Webhook notification failed: {}",
+            style("⚠").yellow(),
+            e
+        );
+    }
+}
+
+/// Tell the issue tracker about created PRs: one POST to `issues.notify-url`
+/// per issue key in a PR's title. Failures only warn.
+pub async fn notify_issues(config: &Config, repository: &str, prs: &[PrSummary], quiet: bool) {
+    let Some(links) = &config.issue_links else {
+        return;
+    };
+    let Some(url) = &links.notify_url else {
+        return;
+    };
+
+    for pr in prs {
+        for key in issue_keys::find_issue_keys(&pr.title, &links.projects) {
+            let body = json!({
+                "issue": key,
+                "issue_url": issue_keys::issue_url(&links.url, &key),
+                "repository": repository,
+                "pr": pr.number,
+                "pr_url": pr.url,
+                "title": pr.title,
+            });
+            if let Err(e) = post(config, url, body.to_string()).await
+                && !quiet
+            {
+                eprintln!(
+                    "   {} Failed to notify {} about PR #{}: {}",
+                    style("⚠").yellow(),
+                    key,
+                    pr.number,
+                    e
+                );
+            }
+        }
+    }
+}
+
+/// The JSON body sent for `event`
+fn payload(event: WebhookEvent, activity: Activity, prs: &[PrSummary]) -> serde_json::Value {
+    let headline = match event {
+        WebhookEvent::PrsCreated => format!(
+            "{} opened {} PR{} on {} in {}",
+            activity.author,
+            prs.len(),
+            if prs.len() == 1 { "" } else { "s" },
+            activity.branch,
+            activity.repository
+        ),
+        WebhookEvent::PrLanded => format!(
+            "{} landed a PR from {} in {}",
+            activity.author, activity.branch, activity.repository
+        ),
+    };
+    // Slack link syntax; other consumers can use the structured fields
+    let lines: Vec<String> = prs
+        .iter()
+        .map(|pr| format!("• <{}|#{}> {}", pr.url, pr.number, pr.title))
+        .collect();
+
+    json!({
+        "text": format!("{}\n{}", headline, lines.join("\n")),
+        "event": event.name(),
+        "repository": activity.repository,
+        "branch": activity.branch,
+        "author": activity.author,
+        "prs": prs.iter().map(|pr| json!({
+            "number": pr.number,
+            "title": pr.title,
+            "url": pr.url,
+        })).collect::<Vec<_>>(),
+    })
+}
+
+async fn post(config: &Config, url: &str, body: String) -> Result<()> {
+    let uri: Uri = url
+        .parse()
+        .with_context(|| format!("Invalid git-stk.webhookUrl '{}'", url))?;
+    let timeout =
+        (config.http_timeout_seconds > 0).then(|| Duration::from_secs(config.http_timeout_seconds));
+    let client =
+        Client::builder(TokioExecutor::new()).build(proxy::https_connector(config, &uri, timeout)?);
+
+    let request = Request::post(uri)
+        .header(CONTENT_TYPE, "application/json")
+        .header(USER_AGENT, "git-stk")
+        .body(body)
+        .context("Failed to build webhook request")?;
+
+    debug!(url, "posting webhook");
+    let response = client
+        .request(request)
+        .await
+        .context("Failed to reach the webhook")?;
+    if !response.status().is_success() {
+        bail!("the webhook responded {}", response.status());
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_payload() {
+        let activity = Activity {
+            repository: "acme/api",
+            branch: "feat",
+            author: "Ada",
+        };
+        let prs = vec![
+            PrSummary {
+                number: 12,
+                title: "Add handlers".to_string(),
+                url: "https://github.com/acme/api/pull/12".to_string(),
+            },
+            PrSummary {
+                number: 13,
+                title: "Add tests".to_string(),
+                url: "https://github.com/acme/api/pull/13".to_string(),
+            },
+        ];
+
+        let value = payload(WebhookEvent::PrsCreated, activity, &prs);
+        assert_eq!(
+            value["text"],
+            "Ada opened 2 PRs on feat in acme/api\n\
+             • <https://github.com/acme/api/pull/12|#12> Add handlers\n\
+             • <https://github.com/acme/api/pull/13|#13> Add tests"
+        );
+        assert_eq!(value["event"], "prs-created");
+        assert_eq!(value["prs"][1]["number"], 13);
+
+        let value = payload(WebhookEvent::PrLanded, activity, &prs[..1]);
+        assert_eq!(
+            value["text"],
+            "Ada landed a PR from feat in acme/api\n• <https://github.com/acme/api/pull/12|#12> Add handlers"
+        );
+    }
+}
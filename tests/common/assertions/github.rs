@@ -1,18 +1,22 @@
 #![allow(dead_code)]
 
+use super::super::helpers::github_client;
 use anyhow::Result;
-use git_stk::gh::{client, queries};
+use git_stk::gh::queries;
 use git_stk::gh::queries::PrInfo;
+use std::path::{Path, PathBuf};
 
 /// Entry point for GitHub PR assertions
 pub struct GithubAssertion {
+    repo_path: PathBuf,
     owner: String,
     repo: String,
 }
 
 impl GithubAssertion {
-    pub fn new(owner: &str, repo: &str) -> Self {
+    pub fn new(repo_path: &Path, owner: &str, repo: &str) -> Self {
         Self {
+            repo_path: repo_path.to_path_buf(),
             owner: owner.to_string(),
             repo: repo.to_string(),
         }
@@ -21,6 +25,7 @@ impl GithubAssertion {
     /// Select PR by head branch name
     pub fn pr_with_head(self, head: &str) -> PrSelectorBuilder {
         PrSelectorBuilder {
+            repo_path: self.repo_path,
             owner: self.owner,
             repo: self.repo,
             selector: PrSelector::ByHead(head.to_string()),
@@ -30,6 +35,7 @@ impl GithubAssertion {
     /// Select PR by PR number
     pub fn pr_with_number(self, number: u64) -> PrSelectorBuilder {
         PrSelectorBuilder {
+            repo_path: self.repo_path,
             owner: self.owner,
             repo: self.repo,
             selector: PrSelector::ByNumber(number),
@@ -40,6 +46,7 @@ impl GithubAssertion {
     pub fn pr_with_slot(self, test_id: &str, slot: &str) -> PrSelectorBuilder {
         let head = format!("{}-feature--{}", test_id, slot);
         PrSelectorBuilder {
+            repo_path: self.repo_path,
             owner: self.owner,
             repo: self.repo,
             selector: PrSelector::ByHead(head),
@@ -54,6 +61,7 @@ enum PrSelector {
 
 /// Builder for selecting and fetching a PR
 pub struct PrSelectorBuilder {
+    repo_path: PathBuf,
     owner: String,
     repo: String,
     selector: PrSelector,
@@ -62,18 +70,13 @@ pub struct PrSelectorBuilder {
 impl PrSelectorBuilder {
     /// Fetch the PR from GitHub (async operation)
     pub async fn fetch(self) -> Result<PrAssertion> {
-        let client = client::create_client()?;
+        let client = github_client(&self.repo_path)?;
 
         let pr_info = match self.selector {
             PrSelector::ByHead(head) => {
                 queries::find_pr_by_head(&client, &self.owner, &self.repo, &head)
                     .await?
-                    .ok_or_else(|| {
-                        anyhow::anyhow!(
-                            "PR not found with head branch: {}",
-                            head
-                        )
-                    })?
+                    .ok_or_else(|| anyhow::anyhow!("PR not found with head branch: {}", head))?
             }
             PrSelector::ByNumber(number) => {
                 queries::get_pr(&client, &self.owner, &self.repo, number).await?
@@ -115,7 +118,9 @@ impl PrAssertion {
         assert!(
             self.pr.body.contains(text),
             "Expected PR #{} description to contain '{}', but it didn't. Description:\n{}",
-            self.pr.number, text, self.pr.body
+            self.pr.number,
+            text,
+            self.pr.body
         );
         self
     }
@@ -126,7 +131,9 @@ impl PrAssertion {
         assert!(
             re.is_match(&self.pr.body),
             "Expected PR #{} description to match pattern '{}', but it didn't. Description:\n{}",
-            self.pr.number, pattern, self.pr.body
+            self.pr.number,
+            pattern,
+            self.pr.body
         );
         self
     }
@@ -138,7 +145,8 @@ impl PrAssertion {
             self.pr.state,
             PrState::Draft,
             "Expected PR #{} to be a draft, but state is {:?}",
-            self.pr.number, self.pr.state
+            self.pr.number,
+            self.pr.state
         );
         self
     }
@@ -160,7 +168,8 @@ impl PrAssertion {
             self.pr.state,
             PrState::Open,
             "Expected PR #{} to be open, but state is {:?}",
-            self.pr.number, self.pr.state
+            self.pr.number,
+            self.pr.state
         );
         self
     }
@@ -172,7 +181,8 @@ impl PrAssertion {
             self.pr.state,
             PrState::Closed,
             "Expected PR #{} to be closed, but state is {:?}",
-            self.pr.number, self.pr.state
+            self.pr.number,
+            self.pr.state
         );
         self
     }
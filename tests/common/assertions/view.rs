@@ -46,21 +46,22 @@ impl ViewAssertion {
                 let mut lines_consumed = 1; // Start with the commit line
 
                 // Next line should have PR URL or "<PR to be created>"
-                if i + lines_consumed < lines.len() {
-                    if let Some(pr_number) = Self::try_parse_pr_url_line(lines[i + lines_consumed]) {
-                        parsed.pr_number = Some(pr_number);
-                        lines_consumed += 1;
-                    }
+                if i + lines_consumed < lines.len()
+                    && let Some(pr_number) = Self::try_parse_pr_url_line(lines[i + lines_consumed])
+                {
+                    parsed.pr_number = Some(pr_number);
+                    lines_consumed += 1;
                 }
 
                 // Next line might have status (Synced, Export needed, Merged)
                 // Only shown when remote exists or merged
-                if i + lines_consumed < lines.len() {
-                    if let Some((status, remote_sha)) = Self::try_parse_status_line(lines[i + lines_consumed]) {
-                        parsed.status = Some(status);
-                        parsed.remote_sha = remote_sha;
-                        let _ = lines_consumed; // Acknowledge we're not using this to skip lines
-                    }
+                if i + lines_consumed < lines.len()
+                    && let Some((status, remote_sha)) =
+                        Self::try_parse_status_line(lines[i + lines_consumed])
+                {
+                    parsed.status = Some(status);
+                    parsed.remote_sha = remote_sha;
+                    let _ = lines_consumed; // Acknowledge we're not using this to skip lines
                 }
 
                 commits.push(parsed);
@@ -75,7 +76,9 @@ impl ViewAssertion {
         // Match: "  1. ├─●  4980ef6  feat: add authentication  [01]"
         // Slot is optional and can be assigned [01] or predicted [?→01]
         // Slot names can contain alphanumerics, hyphens, and underscores
-        let re = Regex::new(r"^\s*(\d+)\.\s+[├└]─●\s+([0-9a-f]+)\s+(.+?)(?:\s+\[(\?→)?([\w-]+)\])?\s*$").ok()?;
+        let re =
+            Regex::new(r"^\s*(\d+)\.\s+[├└]─●\s+([0-9a-f]+)\s+(.+?)(?:\s+\[(\?→)?([\w-]+)\])?\s*$")
+                .ok()?;
         let caps = re.captures(line)?;
 
         let slot = caps.get(5).map(|m| m.as_str().to_string());
@@ -167,7 +170,7 @@ impl CommitAssertion {
             self.commit.slot.as_ref().unwrap_or(&"none".to_string())
         );
         assert_eq!(
-            self.commit.slot.as_ref().map(|s| s.as_str()),
+            self.commit.slot.as_deref(),
             Some(slot),
             "Expected commit {} to have slot [{}], but found [{}]",
             self.index,
@@ -187,7 +190,7 @@ impl CommitAssertion {
             self.commit.slot.as_ref().unwrap_or(&"none".to_string())
         );
         assert_eq!(
-            self.commit.slot.as_ref().map(|s| s.as_str()),
+            self.commit.slot.as_deref(),
             Some(slot),
             "Expected commit {} to have slot [?→{}], but found [?→{}]",
             self.index,
@@ -200,12 +203,9 @@ impl CommitAssertion {
     /// Assert commit has specific title
     pub fn has_title(&self, title: &str) -> &Self {
         assert_eq!(
-            self.commit.title,
-            title,
+            self.commit.title, title,
             "Expected commit {} to have title '{}', but found '{}'",
-            self.index,
-            title,
-            self.commit.title
+            self.index, title, self.commit.title
         );
         self
     }
@@ -306,7 +306,11 @@ impl CommitAssertion {
             self.commit.status
         );
         assert!(
-            self.commit.remote_sha.as_ref().map(|s| s.starts_with(remote_sha_prefix)).unwrap_or(false),
+            self.commit
+                .remote_sha
+                .as_ref()
+                .map(|s| s.starts_with(remote_sha_prefix))
+                .unwrap_or(false),
             "Expected commit {} to have remote SHA starting with '{}', but found {:?}",
             self.index,
             remote_sha_prefix,
@@ -1,5 +1,5 @@
 use super::assertions::{BranchAssertion, CommitAssertion, GithubAssertion, ViewAssertion};
-use super::helpers::{cleanup_test_branches, run_git_stk_command};
+use super::helpers::{cleanup_test_branches, github_client, run_git_stk_command};
 use super::repo::TempGitRepo;
 use anyhow::{Context, Result};
 use git2::Repository;
@@ -112,7 +112,7 @@ impl TestEnv {
 
     /// Assert on GitHub PR state
     pub fn assert_github(&self) -> GithubAssertion {
-        GithubAssertion::new(&self.owner, &self.repo_name)
+        GithubAssertion::new(self.path(), &self.owner, &self.repo_name)
     }
 
     /// Assert on git branch
@@ -180,13 +180,20 @@ impl TestEnv {
         let get_output = Command::new("gh")
             .args([
                 "api",
-                &format!("/repos/{}/{}/contents/{}", self.owner, self.repo_name, filename),
-                "-q", ".sha",
-                "-H", &format!("ref: {}", branch),
+                &format!(
+                    "/repos/{}/{}/contents/{}",
+                    self.owner, self.repo_name, filename
+                ),
+                "-q",
+                ".sha",
+                "-H",
+                &format!("ref: {}", branch),
             ])
             .output()?;
 
-        let file_sha = String::from_utf8_lossy(&get_output.stdout).trim().to_string();
+        let file_sha = String::from_utf8_lossy(&get_output.stdout)
+            .trim()
+            .to_string();
 
         // Update the file
         let encoded_content = STANDARD.encode(content);
@@ -194,12 +201,20 @@ impl TestEnv {
         let output = Command::new("gh")
             .args([
                 "api",
-                "--method", "PUT",
-                &format!("/repos/{}/{}/contents/{}", self.owner, self.repo_name, filename),
-                "-f", &format!("message=External modification"),
-                "-f", &format!("content={}", encoded_content),
-                "-f", &format!("branch={}", branch),
-                "-f", &format!("sha={}", file_sha),
+                "--method",
+                "PUT",
+                &format!(
+                    "/repos/{}/{}/contents/{}",
+                    self.owner, self.repo_name, filename
+                ),
+                "-f",
+                "message=External modification",
+                "-f",
+                &format!("content={}", encoded_content),
+                "-f",
+                &format!("branch={}", branch),
+                "-f",
+                &format!("sha={}", file_sha),
             ])
             .output()?;
 
@@ -216,9 +231,7 @@ impl TestEnv {
 
     /// Merge a PR on GitHub using the GitHub API (simulates external merge)
     pub async fn merge_pr_on_github(&self, slot: &str) -> Result<()> {
-        use git_stk::gh::client;
-
-        let client = client::create_client()?;
+        let client = github_client(self.path())?;
         let head_branch = format!("{}-feature--{}", self.test_id, slot);
 
         // Find the PR by head branch
@@ -246,7 +259,10 @@ impl TestEnv {
 
             Ok(())
         } else {
-            Err(anyhow::anyhow!("PR with head branch {} not found", head_branch))
+            Err(anyhow::anyhow!(
+                "PR with head branch {} not found",
+                head_branch
+            ))
         }
     }
 }
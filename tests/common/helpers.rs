@@ -26,6 +26,12 @@ pub fn run_git_stk_command(repo_path: &Path, args: &[&str]) -> Result<String> {
     Ok(String::from_utf8_lossy(&output.stdout).to_string())
 }
 
+/// GitHub client configured from the test repository's git-stk config, as the commands get it
+pub fn github_client(repo_path: &Path) -> Result<git_stk::gh::client::Client> {
+    let repo = git2::Repository::open(repo_path)?;
+    git_stk::gh::client::create_configured_client(&git_stk::model::Config::load(&repo)?)
+}
+
 /// Clean up all branches with the given test ID prefix
 pub fn cleanup_test_branches(repo_path: &Path, test_id: &str) -> Result<()> {
     // List all remote branches